@@ -27,20 +27,61 @@ pub struct BilibiliClient {
     wbi_keys: RefCell<Option<WbiKeys>>,
 }
 
-impl BilibiliClient {
-    /// Create a new client, loading session from disk.
-    pub fn new() -> Result<Self> {
-        let http = Client::builder()
+/// Builder for [`BilibiliClient`].
+///
+/// Without a [`proxy`](Self::proxy), the `HTTPS_PROXY` / `HTTP_PROXY` /
+/// `ALL_PROXY` environment variables are honored.
+#[derive(Default)]
+pub struct BilibiliClientBuilder {
+    session: Option<BiliSession>,
+    proxy: Option<String>,
+}
+
+impl BilibiliClientBuilder {
+    /// Use an explicit session instead of loading it from disk.
+    #[must_use]
+    pub fn session(mut self, session: BiliSession) -> Self {
+        self.session = Some(session);
+        self
+    }
+
+    /// Route all requests through a proxy (`http://`, `https://` or `socks5://` URL).
+    #[must_use]
+    pub fn proxy(mut self, url: impl Into<String>) -> Self {
+        self.proxy = Some(url.into());
+        self
+    }
+
+    /// Build the client, loading the session from disk if none was given.
+    pub fn build(self) -> Result<BilibiliClient> {
+        let mut http = Client::builder()
             .user_agent(USER_AGENT)
-            .timeout(std::time::Duration::from_secs(30))
-            .build()?;
-        let session = BiliSession::load()?;
-        Ok(Self {
-            http,
+            .timeout(std::time::Duration::from_secs(30));
+        if let Some(url) = &self.proxy {
+            http = http.proxy(reqwest::Proxy::all(url)?);
+        }
+        let session = match self.session {
+            Some(session) => session,
+            None => BiliSession::load()?,
+        };
+        Ok(BilibiliClient {
+            http: http.build()?,
             session,
             wbi_keys: RefCell::new(None),
         })
     }
+}
+
+impl BilibiliClient {
+    /// Create a new client, loading session from disk.
+    pub fn new() -> Result<Self> {
+        Self::builder().build()
+    }
+
+    /// Start building a client with non-default settings (e.g. a proxy).
+    pub fn builder() -> BilibiliClientBuilder {
+        BilibiliClientBuilder::default()
+    }
 
     pub fn session(&self) -> &BiliSession {
         &self.session
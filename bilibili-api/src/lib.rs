@@ -24,5 +24,5 @@ pub mod types;
 pub mod video;
 pub mod wbi;
 
-pub use client::BilibiliClient;
+pub use client::{BilibiliClient, BilibiliClientBuilder};
 pub use error::{BilibiliError, Result};
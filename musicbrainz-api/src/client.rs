@@ -0,0 +1,189 @@
+//! HTTP client for the MusicBrainz web service and the Cover Art Archive.
+
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use reqwest::StatusCode;
+use reqwest::blocking::Client;
+use serde::Deserialize;
+
+use crate::error::{MusicBrainzError, Result};
+use crate::types::{Recording, Release};
+
+const API_BASE: &str = "https://musicbrainz.org/ws/2";
+const COVER_BASE: &str = "https://coverartarchive.org";
+/// MusicBrainz asks clients to identify themselves with a contact URL.
+const USER_AGENT: &str = concat!(
+    "ncmdump-rs/",
+    env!("CARGO_PKG_VERSION"),
+    " ( https://github.com/acking-you/ncmdump-rs )"
+);
+/// Minimum spacing of MusicBrainz requests (the Cover Art Archive has no
+/// such limit).
+const REQUEST_INTERVAL: Duration = Duration::from_secs(1);
+/// Width of the cover thumbnails fetched, in pixels; originals can be huge
+/// scans.
+const COVER_SIZE: u32 = 1200;
+
+/// What to search recordings by.
+#[derive(Debug, Clone, Default)]
+pub struct RecordingQuery<'a> {
+    pub title: &'a str,
+    pub artist: Option<&'a str>,
+    /// Only recordings on a release of this title.
+    pub release: Option<&'a str>,
+}
+
+impl RecordingQuery<'_> {
+    /// The Lucene query for the search endpoint.
+    fn lucene(&self) -> String {
+        let mut query = format!("recording:{}", phrase(self.title));
+        if let Some(artist) = self.artist {
+            query.push_str(" AND artist:");
+            query.push_str(&phrase(artist));
+        }
+        if let Some(release) = self.release {
+            query.push_str(" AND release:");
+            query.push_str(&phrase(release));
+        }
+        query
+    }
+}
+
+/// `s` as a quoted Lucene phrase.
+fn phrase(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Blocking client for MusicBrainz and the Cover Art Archive. Safe to share
+/// between threads; requests are spaced out across all of them.
+pub struct MusicBrainzClient {
+    http: Client,
+    /// When the last MusicBrainz request was sent.
+    last_request: Mutex<Option<Instant>>,
+}
+
+/// Builder for [`MusicBrainzClient`].
+///
+/// Without a [`proxy`](Self::proxy), the `HTTPS_PROXY` / `HTTP_PROXY` /
+/// `ALL_PROXY` environment variables are honored.
+#[derive(Default)]
+pub struct MusicBrainzClientBuilder {
+    proxy: Option<String>,
+}
+
+impl MusicBrainzClientBuilder {
+    /// Route all requests through a proxy (`http://`, `https://` or `socks5://` URL).
+    #[must_use]
+    pub fn proxy(mut self, url: impl Into<String>) -> Self {
+        self.proxy = Some(url.into());
+        self
+    }
+
+    pub fn build(self) -> Result<MusicBrainzClient> {
+        let mut http = Client::builder()
+            .user_agent(USER_AGENT)
+            .timeout(Duration::from_secs(30));
+        if let Some(url) = &self.proxy {
+            http = http.proxy(reqwest::Proxy::all(url)?);
+        }
+        Ok(MusicBrainzClient {
+            http: http.build()?,
+            last_request: Mutex::new(None),
+        })
+    }
+}
+
+impl MusicBrainzClient {
+    pub fn new() -> Result<Self> {
+        Self::builder().build()
+    }
+
+    /// Start building a client with non-default settings (e.g. a proxy).
+    pub fn builder() -> MusicBrainzClientBuilder {
+        MusicBrainzClientBuilder::default()
+    }
+
+    /// Recordings matching `query`, best first (at most `limit`).
+    pub fn search_recordings(
+        &self,
+        query: &RecordingQuery<'_>,
+        limit: u32,
+    ) -> Result<Vec<Recording>> {
+        #[derive(Deserialize)]
+        struct Response {
+            #[serde(default)]
+            recordings: Vec<Recording>,
+        }
+
+        let url = format!(
+            "{API_BASE}/recording?query={}&limit={limit}&fmt=json",
+            urlencoding::encode(&query.lucene())
+        );
+        let response: Response = self.get_json(&url)?;
+        Ok(response.recordings)
+    }
+
+    /// Release `id` with its artist credit and release group.
+    pub fn release(&self, id: &str) -> Result<Release> {
+        self.get_json(&format!(
+            "{API_BASE}/release/{id}?inc=artist-credits+release-groups&fmt=json"
+        ))
+    }
+
+    /// The front cover of release `id`, or else of its release group; `None`
+    /// if the Cover Art Archive has neither.
+    pub fn front_cover(&self, id: &str, release_group: Option<&str>) -> Result<Option<Vec<u8>>> {
+        let urls = std::iter::once(format!("{COVER_BASE}/release/{id}/front-{COVER_SIZE}")).chain(
+            release_group.map(|rg| format!("{COVER_BASE}/release-group/{rg}/front-{COVER_SIZE}")),
+        );
+        for url in urls {
+            let response = self.http.get(&url).send()?;
+            if response.status() == StatusCode::NOT_FOUND {
+                continue;
+            }
+            return Ok(Some(response.error_for_status()?.bytes()?.to_vec()));
+        }
+        Ok(None)
+    }
+
+    fn get_json<T: serde::de::DeserializeOwned>(&self, url: &str) -> Result<T> {
+        self.throttle();
+        let response = self.http.get(url).send()?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Err(MusicBrainzError::NotFound);
+        }
+        let body = response.error_for_status()?.bytes()?;
+        Ok(serde_json::from_slice(&body)?)
+    }
+
+    /// Wait until [`REQUEST_INTERVAL`] has passed since the last request.
+    fn throttle(&self) {
+        let mut last = self
+            .last_request
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if let Some(wait) = last.and_then(|t| REQUEST_INTERVAL.checked_sub(t.elapsed())) {
+            std::thread::sleep(wait);
+        }
+        *last = Some(Instant::now());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lucene_query() {
+        let query = RecordingQuery {
+            title: r#"Say "Hi" \o/"#,
+            artist: Some("周杰伦"),
+            release: None,
+        };
+        assert_eq!(
+            query.lucene(),
+            r#"recording:"Say \"Hi\" \\o/" AND artist:"周杰伦""#
+        );
+    }
+}
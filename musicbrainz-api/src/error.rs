@@ -0,0 +1,22 @@
+//! Error types for the MusicBrainz client.
+
+use thiserror::Error;
+
+/// Errors that can occur when querying MusicBrainz or the Cover Art Archive.
+#[derive(Debug, Error)]
+pub enum MusicBrainzError {
+    /// HTTP transport error, or an unexpected HTTP status.
+    #[error("HTTP request failed: {0}")]
+    Http(#[from] reqwest::Error),
+
+    /// JSON parse error.
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+
+    /// The requested entity does not exist.
+    #[error("not found on MusicBrainz")]
+    NotFound,
+}
+
+/// Convenience alias used throughout this crate.
+pub type Result<T> = std::result::Result<T, MusicBrainzError>;
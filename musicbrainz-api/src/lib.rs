@@ -0,0 +1,15 @@
+//! MusicBrainz and Cover Art Archive client library.
+//!
+//! Looks up recordings and releases on [MusicBrainz](https://musicbrainz.org)
+//! and front covers on the [Cover Art Archive](https://coverartarchive.org),
+//! so tracks from Netease can carry the MBID tags that beets and Picard use.
+//!
+//! No account is needed. MusicBrainz allows one request per second per
+//! client; [`MusicBrainzClient`] waits between requests to stay within it.
+
+pub mod client;
+pub mod error;
+pub mod types;
+
+pub use client::{MusicBrainzClient, MusicBrainzClientBuilder, RecordingQuery};
+pub use error::{MusicBrainzError, Result};
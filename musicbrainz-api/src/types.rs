@@ -0,0 +1,129 @@
+//! Data types for MusicBrainz web service (`/ws/2`) JSON responses.
+//!
+//! Only the fields needed for tagging are kept. Field names follow Rust
+//! conventions; the API uses kebab-case (`artist-credit`).
+
+use serde::{Deserialize, Serialize};
+
+/// An artist, as referenced from a credit.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Artist {
+    /// Artist MBID.
+    pub id: String,
+    pub name: String,
+}
+
+/// One name in an artist credit, e.g. `周杰伦` followed by `" feat. "`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArtistCredit {
+    /// Name as credited (may differ from the artist's own name).
+    pub name: String,
+    /// Text joining this name to the next one.
+    #[serde(default)]
+    pub joinphrase: String,
+    pub artist: Artist,
+}
+
+/// The credit string of `credits`, as MusicBrainz displays it.
+pub fn credit_name(credits: &[ArtistCredit]) -> String {
+    credits
+        .iter()
+        .flat_map(|c| [c.name.as_str(), c.joinphrase.as_str()])
+        .collect()
+}
+
+/// The release group (album across editions) a release belongs to.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ReleaseGroup {
+    /// Release group MBID.
+    pub id: String,
+    /// `Album`, `Single`, `EP`, …
+    pub primary_type: Option<String>,
+}
+
+/// A track on a medium of a release.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReleaseTrack {
+    /// Release track MBID.
+    pub id: String,
+    /// Track number as printed (`"3"`, `"A1"`).
+    pub number: String,
+}
+
+/// A disc, vinyl side set, etc. of a release.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Medium {
+    pub position: Option<u32>,
+    /// In recording search results, only the track of that recording.
+    #[serde(default, rename = "track")]
+    pub tracks: Vec<ReleaseTrack>,
+}
+
+/// A release (one edition of an album).
+///
+/// Returned by [`MusicBrainzClient::release`](crate::MusicBrainzClient::release)
+/// and inside [`Recording`] search results, where the artist credit is
+/// often missing.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Release {
+    /// Release MBID.
+    pub id: String,
+    pub title: String,
+    /// `Official`, `Promotion`, `Bootleg`, …
+    pub status: Option<String>,
+    /// `YYYY`, `YYYY-MM` or `YYYY-MM-DD`.
+    pub date: Option<String>,
+    pub country: Option<String>,
+    #[serde(default)]
+    pub artist_credit: Vec<ArtistCredit>,
+    pub release_group: Option<ReleaseGroup>,
+    #[serde(default)]
+    pub media: Vec<Medium>,
+}
+
+/// A recording (one performance of a song), from a search.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Recording {
+    /// Recording MBID.
+    pub id: String,
+    pub title: String,
+    /// Duration in milliseconds.
+    pub length: Option<u64>,
+    /// Search relevance, 0–100.
+    #[serde(default)]
+    pub score: u8,
+    #[serde(default)]
+    pub artist_credit: Vec<ArtistCredit>,
+    #[serde(default)]
+    pub releases: Vec<Release>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_recording() {
+        let json = r#"{
+            "id": "r1", "score": 100, "title": "晴天", "length": 269000,
+            "artist-credit": [
+                { "name": "周杰伦", "joinphrase": " & ", "artist": { "id": "a1", "name": "周杰伦" } },
+                { "name": "X", "artist": { "id": "a2", "name": "X" } }
+            ],
+            "releases": [{
+                "id": "rel1", "title": "叶惠美", "status": "Official", "date": "2003-07-31",
+                "release-group": { "id": "rg1", "primary-type": "Album" },
+                "media": [{ "position": 1, "track": [{ "id": "t1", "number": "3", "title": "晴天" }] }]
+            }]
+        }"#;
+        let recording: Recording = serde_json::from_str(json).unwrap();
+        assert_eq!(credit_name(&recording.artist_credit), "周杰伦 & X");
+        let release = &recording.releases[0];
+        assert!(release.artist_credit.is_empty());
+        assert_eq!(release.release_group.as_ref().unwrap().id, "rg1");
+        assert_eq!(release.media[0].tracks[0].id, "t1");
+    }
+}
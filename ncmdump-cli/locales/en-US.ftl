@@ -0,0 +1,386 @@
+## Shared
+
+error = Error: { $error }
+item-error = error: { $item }: { $error }
+downloaded = Downloaded { $path } ({ $bytes } bytes)
+skipped-existing = Skipped { $path } (already exists)
+files-failed = { $failed } of { $total } files failed
+downloads-failed = { $failed } of { $total } downloads failed
+queue-resumed = Resuming { $count } track(s) left from an interrupted download
+queue-failed = failed to open the download queue { $path }
+not-logged-in = Not logged in.
+total = Total: { $total }
+output-dir-failed = failed to create output directory
+read-failed = failed to read { $path }
+write-failed = failed to write { $path }
+remove-failed = failed to remove { $path }
+stdin-failed = failed to read stdin
+ffmpeg-missing = ffmpeg not found in PATH. Please install ffmpeg first.
+log-open-failed = failed to open log file { $path }
+ref-invalid = not { $kind ->
+        [album] an album
+        [playlist] a playlist
+        [artist] an artist
+        [mv] an MV
+       *[song] a track
+    } ID or URL: { $input }
+label-artists = Artists:  { $artists }
+label-duration = Duration: { $duration }
+label-tracks = Tracks:   { $count }
+label-desc = Desc:     { $desc }
+label-avatar = Avatar: { $url }
+summary = { $action ->
+        [download] Downloaded
+       *[convert] Converted
+    } { $done }, skipped { $skipped }, failed { $failed }
+
+## hooks
+
+hooks-config-invalid = invalid hooks config { $path }: { $error }
+hooks-config-ignored = Hooks disabled: { $error }
+hook-failed = Hook { $hook } failed for { $path }: { $error }
+notify-config-ignored = Notifications disabled: { $error }
+notifier-failed = Notifier { $notifier } failed: { $error }
+notify-finished-title = ncmdump: { $operation } finished
+notify-failed-title = ncmdump: { $operation } failed
+notify-finished = Finished in { $secs }s.
+notify-daemon-job = daemon job { $name }
+
+## dump
+
+dump-no-match = warning: no files match { $pattern }
+dump-read-dir-failed = failed to read directory
+dump-no-files = no NCM files specified, use --help for usage
+dump-remove-failed = warning: failed to remove { $path }: { $error }
+glob-invalid = invalid glob pattern { $pattern }: { $error }
+dump-verify-failed = output failed verification, source kept: { $reason }
+dump-verify-size = output holds { $size } bytes of audio, expected { $expected }
+dump-verify-duration = output has no playable audio
+dump-plan-convert = { $input } -> { $output } ({ $format })
+dump-plan-overwrite = { $input } -> { $output } ({ $format }, overwrites existing file)
+dump-plan-skip = skip { $input }: { $error }
+dump-plan-summary = { $convert } would be converted ({ $overwrite } overwriting), { $skipped } skipped
+
+## cache
+
+cache-none = no .uc/.uc! cache files found in { $dir }
+cache-skip = [{ $index }/{ $total }] skip { $path } (already converted)
+cache-fail = [{ $index }/{ $total }] FAIL { $path }: { $error }
+cache-failed = { $failed } of { $total } cache files failed
+cache-no-id = no track ID in file name
+cache-untagged = warning: track { $id }: { $error }; keeping it untagged
+
+## stats
+
+stats-library = Library: { $dir }
+stats-files = files
+stats-total = total
+stats-unreadable = { $count } files could not be read (run with -v for details)
+stats-unconverted = Unconverted NCM files
+stats-no-cover = Missing cover
+stats-no-lyrics = Missing lyrics
+stats-fix = (fix with `{ $command }`)
+
+## rename
+
+rename-exists = skip: { $from } -> { $to } (target exists)
+rename-skip = skip: { $path }: { $error }
+rename-failed = failed to rename { $path }
+rename-summary = { $renamed } { $dry_run ->
+        [true] would be renamed
+       *[false] renamed
+    }, { $skipped } skipped
+
+## organize
+
+organize-summary = { $done } { $action ->
+        [copy] copied
+        [would-copy] would be copied
+        [would-move] would be moved
+       *[move] moved
+    }, { $skipped } skipped
+organize-sidecars = { $count } sidecar { $count ->
+        [one] file
+       *[other] files
+    } { $dry_run ->
+        [true] would be written
+       *[false] written
+    }
+
+## retag
+
+retag-id-single = --id can only be used with a single file
+retag-result = { $path }: { $dry_run ->
+        [true] matches
+       *[false] retagged as
+    } { $artists } - { $title } (id={ $id })
+retag-no-match = no search match for { $query } (use --id)
+
+## gain
+
+gain-result = { $path }: track { $track } dB (peak { $peak }){ $album ->
+        [none] {""}
+       *[other] , album { $album } dB
+    }
+
+## enrich
+
+enrich-matched = { $path }: { $dry_run ->
+        [true] matches
+       *[false] enriched from
+    } { $album_artist } - { $release } { $date } (mbid={ $id })
+enrich-no-match = { $path }: no MusicBrainz match
+enrich-summary = Matched { $matched } of { $total } files on MusicBrainz
+
+## import
+
+import-no-fpcalc = fpcalc (Chromaprint) not found; matching by tags and duration only
+import-matched = { $path }: { $artists } - { $title } (id={ $id }, { $method ->
+        [tag] 163 key
+        [fingerprint] fingerprint { $score }%
+       *[metadata] tags { $score }%
+    })
+import-no-match = { $path }: no match
+import-summary = Matched { $matched } of { $total } files
+
+## login / logout
+
+login-as = Logged in as: { $name } (id={ $id })
+login-invalid = Session exists but validation failed: { $error }
+login-music-u-required = MUSIC_U value required
+login-saved = Session saved.
+logout-done = Session cleared.
+login-encrypted-passphrase = Session file: encrypted with a passphrase
+login-encrypted-machine = Session file: encrypted with this machine's ID
+session-passphrase = Session passphrase:{" "}
+session-passphrase-new = New session passphrase:{" "}
+session-passphrase-confirm = Repeat passphrase:{" "}
+session-passphrase-empty = the passphrase must not be empty
+session-passphrase-mismatch = the passphrases do not match
+
+## search
+
+search-more = (more: --page { $page })
+fields-tracks-only = --fields only applies to track searches (-t track)
+search-playlist = [{ $id }] { $name } ({ $count } tracks)
+
+## info / lyric / comments
+
+info-track = Track:    { $name } (id={ $id })
+info-album = Album:    { $name } (id={ $id })
+quality-unavailable = unavailable
+quality-fallback = unavailable (falls back to { $level })
+lyric-unavailable = no lyrics available
+lyric-none = No lyrics available.
+lyric-saved = Saved lyrics to { $path }
+lyric-translation = --- Translation ---
+comments-hot = --- Hot ---
+comments-latest = --- Latest ---
+comments-row = [{ $likes } likes] { $user }: { $content }
+comments-more = (more: --page { $page })
+
+## similar
+
+similar-seed = Seed: { $artists } - { $title } (id={ $id })
+similar-songs = Similar songs:
+similar-artists = Similar artists (to { $name }):
+similar-artists-unavailable = warning: similar artists unavailable: { $error }
+
+## download
+
+batch-ok = [{ $index }/{ $total }] ok   { $input } -> { $path }
+batch-skip = [{ $index }/{ $total }] skip { $input } -> { $path }
+batch-fail = [{ $index }/{ $total }] FAIL { $input }: { $error }
+batch-done = Done: { $ok } succeeded, { $failed } failed
+batch-no-ids = no track IDs found in { $path }
+track-error = error: track { $id } ({ $name }): { $error }
+program-error = error: program { $id } ({ $name }): { $error }
+transcode-no-ffmpeg = --transcode needs ffmpeg, which was not found in PATH. Please install ffmpeg first.
+transcode-stream-failed = download interrupted while transcoding
+transcode-failed = ffmpeg failed: { $error }
+
+## play
+
+play-missing-id = missing track ID
+play-fm-empty = personal FM returned no tracks
+play-skip = warning: skipping { $id } ({ $name }): { $error }
+play-empty-player = empty player command
+play-no-player = no player found: install mpv or ffplay, or pass --player
+play-now = Now playing: { $artists } - { $title } (id={ $id })
+play-run-failed = failed to run { $program }
+play-exit = { $program } exited with { $status }
+
+## fm
+
+fm-liked = Liked { $id }
+fm-trashed = Moved { $id } to the FM trash
+
+## mv / album / artist / playlist / toplist
+
+mv-title = MV:          { $artists } - { $name } (id={ $id })
+mv-resolutions = Resolutions: { $list }
+album-name = Album:    { $name } (id={ $id })
+album-released = Released: { $date }
+album-label = Label:    { $company }
+artist-name = Artist: { $name } (id={ $id })
+artist-albums = Albums: { $count }
+artist-songs = Songs:  { $count }
+artist-desc = Desc:   { $desc }
+artist-top = Top songs:
+artist-recent = Recent albums:
+artist-album = [{ $id }] { $name } ({ $date }, { $count } tracks)
+playlist-name = Playlist: { $name } (id={ $id })
+playlist-creator = Creator:  { $name } (id={ $id })
+toplist-unknown = unknown chart: { $chart } (run `toplist` to list charts)
+toplist-chart = Chart: { $name } (id={ $id })
+
+## podcast
+
+podcast-radio = Radio:    { $name } (id={ $id })
+podcast-dj = DJ:       { $name } (id={ $id })
+podcast-category = Category: { $category }
+podcast-programs = Programs: { $count }
+podcast-subs = Subs:     { $count }
+
+## open
+
+open-invalid = not an ID or music.163.com URL: { $input }
+open-opening = Opening { $url }
+open-launch-failed = failed to launch browser (use --print to get the URL)
+open-launcher-exit = browser launcher exited with { $status }
+
+## doctor
+
+doctor-fix = fix: { $fix }
+doctor-failed = { $count } check(s) failed
+doctor-config-error = Config directory: { $error }
+doctor-config-error-fix = set HOME (or XDG_CONFIG_HOME) to a valid directory
+doctor-config-ok = Config directory: { $dir } (writable)
+doctor-config-readonly = Config directory: { $dir } is not writable: { $error }
+doctor-config-readonly-fix = fix the permissions of { $dir }
+doctor-proxy-none = Proxy: none (direct connection)
+doctor-proxy-ok = Proxy: { $url } (from { $source })
+doctor-proxy-invalid = Proxy: { $url } (from { $source }) is not a valid proxy URL
+doctor-proxy-fix = use http://, https://, socks5:// or socks5h://, e.g. `--proxy socks5://127.0.0.1:1080`
+doctor-network-fix = check your internet connection, or route through a proxy with `--proxy <URL>` / HTTPS_PROXY
+doctor-network-client = Network: cannot build HTTP client: { $error }
+doctor-network-ok = Network: music.163.com reachable ({ $ms } ms)
+doctor-network-fail = Network: music.163.com unreachable: { $error }
+doctor-login-fix = run `ncmdump-cli login <MUSIC_U>` with the cookie from a logged-in browser
+doctor-session-unreadable = Session: cannot read session file: { $error }
+doctor-session-unreadable-fix = run `ncmdump-cli logout`, then { $login }
+doctor-session-locked = Session: encrypted, no passphrase given
+doctor-session-locked-fix = run in a terminal to enter it, or set { $var }
+doctor-session-none = Session: not logged in (search works; VIP tracks, cloud and FM need login)
+doctor-session-error = Session: { $error }
+doctor-session-ok = Session: logged in as { $name } (id={ $id })
+doctor-session-expired = Session: cookie expired or invalid
+doctor-session-expired-fix = copy a fresh MUSIC_U cookie and { $login }
+doctor-session-unverified = Session: could not validate cookie: { $error }
+doctor-session-unverified-fix = fix the network check above, then run `ncmdump-cli login --check`
+doctor-output-ok = Output directory: { $dir } (writable)
+doctor-output-readonly = Output directory: { $dir } is not writable: { $error }
+doctor-output-fix = run from a writable directory or pass `-o <PATH>`
+doctor-ffmpeg-ok = ffmpeg: found (needed by bili-download and download --transcode)
+doctor-ffmpeg-missing = ffmpeg: not found in PATH (needed by bili-download and download --transcode)
+doctor-ffmpeg-fix = install ffmpeg, e.g. `brew install ffmpeg` or `apt install ffmpeg`
+
+## signin
+
+signin-earned = { $platform ->
+        [web] Web
+       *[mobile] Mobile
+    }: +{ $points } points
+signin-already = { $platform ->
+        [web] Web
+       *[mobile] Mobile
+    }: already signed in today
+signin-failed = { $failed } of { $total } sign-ins failed
+
+## daemon
+
+daemon-config-invalid = invalid daemon config { $path }: { $error }
+daemon-no-jobs = no jobs in { $path }
+daemon-started = Running { $count } job(s) from { $path }
+daemon-job-start = [{ $time }] { $name }: started
+daemon-job-ok = [{ $time }] { $name }: done in { $secs }s
+daemon-job-failed = [{ $time }] { $name }: failed: { $error }
+daemon-jobs-failed = { $failed } of { $total } jobs failed
+daemon-status-job = { $name } ({ $schedule })
+daemon-schedule-every = every { $interval }
+daemon-schedule-at = daily at { $time }
+daemon-schedule-cron = cron { $expr }
+daemon-last-never = Last run: never
+daemon-last-ok = Last run: { $time }, ok in { $secs }s
+daemon-last-failed = Last run: { $time }, failed after { $secs }s: { $error }
+daemon-next = Next run: { $time }
+
+## serve
+
+serve-bind-failed = cannot listen on { $addr }: { $error }
+serve-listening = Serving { $dir } at http://{ $addr }/ (M3U playlist; Ctrl-C to stop)
+
+## rpc
+
+rpc-listening = JSON-RPC on { $addr } (Ctrl-C to stop)
+rpc-running = another server is already listening on { $addr }
+rpc-bind-failed = cannot listen on { $addr }
+
+## me / record / cloud
+
+me-user = User:   { $name } (id={ $id })
+me-vip-active = VIP:    level { $level }, active until { $date }
+me-vip-package = VIP:    music package until { $date }
+me-vip-expired = VIP:    expired on { $date }
+me-vip-none = VIP:    none
+me-vip-unavailable = warning: VIP status unavailable: { $error }
+me-level = Level:  { $level } ({ $percent }% to next: { $plays }/{ $next_plays } songs played, { $days }/{ $next_days } days logged in)
+me-level-max = Level:  { $level } (max)
+me-likes = Liked:  { $count } songs
+me-created = Created playlists ({ $count }):
+me-subscribed = Subscribed playlists ({ $count }):
+record-row = [{ $id }] { $artists } - { $title } ({ $count } plays)
+record-empty = No listening history for this period.
+cloud-total = Total: { $count } ({ $used } / { $max } MiB)
+cloud-uploaded = { $path } -> cloud id { $id }
+cloud-deleted = Deleted { $count } track(s) from cloud drive.
+
+## backup
+
+backup-playlist = [{ $index }/{ $total }] { $name }
+backup-saved = Saved { $path }: { $playlists } playlists ({ $tracks } tracks), { $liked } liked songs, { $artists } artists, { $podcasts } podcasts, { $cloud } cloud tracks
+backup-incomplete = warning: the tracks of { $count } playlist(s) could not be fetched; only their names are saved
+backup-invalid = { $path } is not a backup snapshot
+backup-too-new = { $path } is a version { $version } snapshot; update ncmdump to read it
+backup-no-changes = No changes.
+backup-section = { $section }: +{ $added } -{ $removed }
+backup-playlists = Playlists
+backup-playlist-changed = Playlist { $name } [{ $id }]
+backup-playlist-renamed = Playlist { $name } [{ $id }] (was { $from })
+backup-liked = Liked songs
+backup-artists = Followed artists
+backup-podcasts = Subscribed podcasts
+backup-cloud = Cloud drive
+
+## Bilibili
+
+bili-login-as = Logged in as: { $name } (mid={ $mid })
+bili-vip-active = VIP: active
+bili-vip = VIP:    { $active ->
+        [true] active
+       *[false] none
+    }
+bili-session-invalid = Session exists but validation failed.
+bili-qr-failed = failed to generate QR code
+bili-scan = Scan with Bilibili mobile app:
+bili-waiting = Waiting for scan...
+bili-login-ok = Login successful! Session saved.
+bili-scanned = Scanned, waiting for confirm...
+bili-qr-expired = QR code expired. Please try again.
+bili-logout = Bilibili session cleared.
+bili-title = Title:    { $title }
+bili-author = Author:   { $name } (mid={ $mid })
+bili-cover = Cover:    { $url }
+bili-parts = Parts:
+bili-downloading = Downloading audio from { $bvid }...
+bili-user = User:   { $name } (mid={ $mid })
@@ -0,0 +1,383 @@
+## 通用
+
+error = 错误：{ $error }
+item-error = 错误：{ $item }：{ $error }
+downloaded = 已下载 { $path }（{ $bytes } 字节）
+skipped-existing = 已跳过 { $path }（文件已存在）
+files-failed = { $total } 个文件中有 { $failed } 个失败
+downloads-failed = { $total } 个下载中有 { $failed } 个失败
+queue-resumed = 继续上次中断的下载，剩余 { $count } 首
+queue-failed = 无法打开下载队列 { $path }
+not-logged-in = 未登录。
+total = 共 { $total } 条
+output-dir-failed = 创建输出目录失败
+read-failed = 读取 { $path } 失败
+write-failed = 写入 { $path } 失败
+remove-failed = 删除 { $path } 失败
+stdin-failed = 读取标准输入失败
+ffmpeg-missing = 未在 PATH 中找到 ffmpeg，请先安装 ffmpeg。
+log-open-failed = 无法打开日志文件 { $path }
+ref-invalid = 不是{ $kind ->
+        [album] 专辑
+        [playlist] 歌单
+        [artist] 歌手
+        [mv] MV
+       *[song] 歌曲
+    } ID 或链接：{ $input }
+label-artists = 歌手：{ $artists }
+label-duration = 时长：{ $duration }
+label-tracks = 曲目：{ $count }
+label-desc = 简介：{ $desc }
+label-avatar = 头像：{ $url }
+summary = { $action ->
+        [download] 已下载
+       *[convert] 已转换
+    } { $done } 个，跳过 { $skipped } 个，失败 { $failed } 个
+
+## hooks
+
+hooks-config-invalid = 钩子配置 { $path } 无效：{ $error }
+hooks-config-ignored = 已停用钩子：{ $error }
+hook-failed = 钩子 { $hook } 处理 { $path } 失败：{ $error }
+notify-config-ignored = 已停用通知：{ $error }
+notifier-failed = 通知 { $notifier } 发送失败：{ $error }
+notify-finished-title = ncmdump：{ $operation } 已完成
+notify-failed-title = ncmdump：{ $operation } 失败
+notify-finished = 用时 { $secs } 秒。
+notify-daemon-job = 守护任务 { $name }
+
+## dump
+
+dump-no-match = 警告：没有文件匹配 { $pattern }
+dump-read-dir-failed = 读取目录失败
+dump-no-files = 未指定 NCM 文件，使用 --help 查看用法
+dump-remove-failed = 警告：删除 { $path } 失败：{ $error }
+glob-invalid = 无效的通配符模式 { $pattern }：{ $error }
+dump-verify-failed = 输出文件校验失败，已保留源文件：{ $reason }
+dump-verify-size = 输出文件的音频为 { $size } 字节，预期 { $expected } 字节
+dump-verify-duration = 输出文件没有可播放的音频
+dump-plan-convert = { $input } -> { $output }（{ $format }）
+dump-plan-overwrite = { $input } -> { $output }（{ $format }，将覆盖已有文件）
+dump-plan-skip = 跳过 { $input }：{ $error }
+dump-plan-summary = 将转换 { $convert } 个（其中覆盖 { $overwrite } 个），跳过 { $skipped } 个
+
+## cache
+
+cache-none = { $dir } 中没有找到 .uc/.uc! 缓存文件
+cache-skip = [{ $index }/{ $total }] 跳过 { $path }（已转换）
+cache-fail = [{ $index }/{ $total }] 失败 { $path }：{ $error }
+cache-failed = { $total } 个缓存文件中有 { $failed } 个失败
+cache-no-id = 文件名中没有歌曲 ID
+cache-untagged = 警告：歌曲 { $id }：{ $error }；保留为无标签文件
+
+## stats
+
+stats-library = 音乐库：{ $dir }
+stats-files = 个文件
+stats-total = 合计
+stats-unreadable = { $count } 个文件无法读取（使用 -v 查看详情）
+stats-unconverted = 未转换的 NCM 文件
+stats-no-cover = 缺少封面
+stats-no-lyrics = 缺少歌词
+stats-fix = （可用 `{ $command }` 修复）
+
+## rename
+
+rename-exists = 跳过：{ $from } -> { $to }（目标已存在）
+rename-skip = 跳过：{ $path }：{ $error }
+rename-failed = 重命名 { $path } 失败
+rename-summary = { $dry_run ->
+        [true] 将重命名
+       *[false] 已重命名
+    } { $renamed } 个，跳过 { $skipped } 个
+
+## organize
+
+organize-summary = { $action ->
+        [copy] 已复制
+        [would-copy] 将复制
+        [would-move] 将移动
+       *[move] 已移动
+    } { $done } 个，跳过 { $skipped } 个
+organize-sidecars = { $dry_run ->
+        [true] 将写入
+       *[false] 已写入
+    } { $count } 个附属文件
+
+## retag
+
+retag-id-single = --id 只能用于单个文件
+retag-result = { $path }：{ $dry_run ->
+        [true] 匹配到
+       *[false] 已更新标签为
+    } { $artists } - { $title }（id={ $id }）
+retag-no-match = 搜索 { $query } 没有结果（请使用 --id）
+
+## gain
+
+gain-result = { $path }：音轨 { $track } dB（峰值 { $peak }）{ $album ->
+        [none] {""}
+       *[other] ，专辑 { $album } dB
+    }
+
+## enrich
+
+enrich-matched = { $path }：{ $dry_run ->
+        [true] 匹配到
+       *[false] 已补全自
+    } { $album_artist } - { $release } { $date }（mbid={ $id }）
+enrich-no-match = { $path }：MusicBrainz 上没有匹配
+enrich-summary = 在 MusicBrainz 上匹配了 { $matched } / { $total } 个文件
+
+## import
+
+import-no-fpcalc = 未找到 fpcalc（Chromaprint），仅按标签和时长匹配
+import-matched = { $path }：{ $artists } - { $title }（id={ $id }，{ $method ->
+        [tag] 163 key
+        [fingerprint] 指纹 { $score }%
+       *[metadata] 标签 { $score }%
+    }）
+import-no-match = { $path }：没有匹配
+import-summary = 匹配了 { $matched } / { $total } 个文件
+
+## login / logout
+
+login-as = 已登录：{ $name }（id={ $id }）
+login-invalid = 会话存在但验证失败：{ $error }
+login-music-u-required = 需要提供 MUSIC_U 的值
+login-saved = 会话已保存。
+logout-done = 会话已清除。
+login-encrypted-passphrase = 会话文件：已用口令加密
+login-encrypted-machine = 会话文件：已用本机 ID 加密
+session-passphrase = 会话口令：{" "}
+session-passphrase-new = 新的会话口令：{" "}
+session-passphrase-confirm = 再次输入口令：{" "}
+session-passphrase-empty = 口令不能为空
+session-passphrase-mismatch = 两次输入的口令不一致
+
+## search
+
+search-more = （更多：--page { $page }）
+fields-tracks-only = --fields 只能用于歌曲搜索（-t track）
+search-playlist = [{ $id }] { $name }（{ $count } 首）
+
+## info / lyric / comments
+
+info-track = 歌曲：{ $name }（id={ $id }）
+info-album = 专辑：{ $name }（id={ $id }）
+quality-unavailable = 不可用
+quality-fallback = 不可用（降级为 { $level }）
+lyric-unavailable = 没有歌词
+lyric-none = 没有歌词。
+lyric-saved = 歌词已保存到 { $path }
+lyric-translation = --- 翻译 ---
+comments-hot = --- 热门 ---
+comments-latest = --- 最新 ---
+comments-row = [{ $likes } 赞] { $user }：{ $content }
+comments-more = （更多：--page { $page }）
+
+## similar
+
+similar-seed = 种子歌曲：{ $artists } - { $title }（id={ $id }）
+similar-songs = 相似歌曲：
+similar-artists = 相似歌手（{ $name }）：
+similar-artists-unavailable = 警告：无法获取相似歌手：{ $error }
+
+## download
+
+batch-ok = [{ $index }/{ $total }] 完成 { $input } -> { $path }
+batch-skip = [{ $index }/{ $total }] 跳过 { $input } -> { $path }
+batch-fail = [{ $index }/{ $total }] 失败 { $input }：{ $error }
+batch-done = 完成：成功 { $ok } 个，失败 { $failed } 个
+batch-no-ids = { $path } 中没有找到歌曲 ID
+track-error = 错误：歌曲 { $id }（{ $name }）：{ $error }
+program-error = 错误：节目 { $id }（{ $name }）：{ $error }
+transcode-no-ffmpeg = --transcode 需要 ffmpeg，但未在 PATH 中找到，请先安装 ffmpeg。
+transcode-stream-failed = 边下载边转码时中断
+transcode-failed = ffmpeg 转码失败：{ $error }
+
+## play
+
+play-missing-id = 缺少歌曲 ID
+play-fm-empty = 私人 FM 没有返回歌曲
+play-skip = 警告：跳过 { $id }（{ $name }）：{ $error }
+play-empty-player = 播放器命令为空
+play-no-player = 未找到播放器：请安装 mpv 或 ffplay，或使用 --player 指定
+play-now = 正在播放：{ $artists } - { $title }（id={ $id }）
+play-run-failed = 无法运行 { $program }
+play-exit = { $program } 退出：{ $status }
+
+## fm
+
+fm-liked = 已喜欢 { $id }
+fm-trashed = 已将 { $id } 移入 FM 垃圾桶
+
+## mv / album / artist / playlist / toplist
+
+mv-title = MV：{ $artists } - { $name }（id={ $id }）
+mv-resolutions = 分辨率：{ $list }
+album-name = 专辑：{ $name }（id={ $id }）
+album-released = 发行：{ $date }
+album-label = 厂牌：{ $company }
+artist-name = 歌手：{ $name }（id={ $id }）
+artist-albums = 专辑数：{ $count }
+artist-songs = 歌曲数：{ $count }
+artist-desc = 简介：{ $desc }
+artist-top = 热门歌曲：
+artist-recent = 最新专辑：
+artist-album = [{ $id }] { $name }（{ $date }，{ $count } 首）
+playlist-name = 歌单：{ $name }（id={ $id }）
+playlist-creator = 创建者：{ $name }（id={ $id }）
+toplist-unknown = 未知榜单：{ $chart }（运行 `toplist` 查看榜单列表）
+toplist-chart = 榜单：{ $name }（id={ $id }）
+
+## podcast
+
+podcast-radio = 电台：{ $name }（id={ $id }）
+podcast-dj = 主播：{ $name }（id={ $id }）
+podcast-category = 分类：{ $category }
+podcast-programs = 节目数：{ $count }
+podcast-subs = 订阅数：{ $count }
+
+## open
+
+open-invalid = 不是 ID 或 music.163.com 链接：{ $input }
+open-opening = 正在打开 { $url }
+open-launch-failed = 无法启动浏览器（使用 --print 获取链接）
+open-launcher-exit = 浏览器启动器退出：{ $status }
+
+## doctor
+
+doctor-fix = 修复：{ $fix }
+doctor-failed = { $count } 项检查未通过
+doctor-config-error = 配置目录：{ $error }
+doctor-config-error-fix = 将 HOME（或 XDG_CONFIG_HOME）设置为有效目录
+doctor-config-ok = 配置目录：{ $dir }（可写）
+doctor-config-readonly = 配置目录：{ $dir } 不可写：{ $error }
+doctor-config-readonly-fix = 修正 { $dir } 的权限
+doctor-proxy-none = 代理：无（直连）
+doctor-proxy-ok = 代理：{ $url }（来自 { $source }）
+doctor-proxy-invalid = 代理：{ $url }（来自 { $source }）不是有效的代理 URL
+doctor-proxy-fix = 使用 http://、https://、socks5:// 或 socks5h://，例如 `--proxy socks5://127.0.0.1:1080`
+doctor-network-fix = 检查网络连接，或通过 `--proxy <URL>` / HTTPS_PROXY 使用代理
+doctor-network-client = 网络：无法创建 HTTP 客户端：{ $error }
+doctor-network-ok = 网络：music.163.com 可访问（{ $ms } ms）
+doctor-network-fail = 网络：无法访问 music.163.com：{ $error }
+doctor-login-fix = 从已登录的浏览器复制 Cookie，运行 `ncmdump-cli login <MUSIC_U>`
+doctor-session-unreadable = 会话：无法读取会话文件：{ $error }
+doctor-session-unreadable-fix = 运行 `ncmdump-cli logout`，然后{ $login }
+doctor-session-locked = 会话：已加密，未提供口令
+doctor-session-locked-fix = 在终端中运行以输入口令，或设置 { $var }
+doctor-session-none = 会话：未登录（搜索可用；VIP 歌曲、云盘和 FM 需要登录）
+doctor-session-error = 会话：{ $error }
+doctor-session-ok = 会话：已登录为 { $name }（id={ $id }）
+doctor-session-expired = 会话：Cookie 已过期或无效
+doctor-session-expired-fix = 获取新的 MUSIC_U Cookie，然后{ $login }
+doctor-session-unverified = 会话：无法验证 Cookie：{ $error }
+doctor-session-unverified-fix = 先解决上面的网络问题，再运行 `ncmdump-cli login --check`
+doctor-output-ok = 输出目录：{ $dir }（可写）
+doctor-output-readonly = 输出目录：{ $dir } 不可写：{ $error }
+doctor-output-fix = 在可写目录下运行，或使用 `-o <PATH>` 指定
+doctor-ffmpeg-ok = ffmpeg：已找到（bili-download 和 download --transcode 需要）
+doctor-ffmpeg-missing = ffmpeg：未在 PATH 中找到（bili-download 和 download --transcode 需要）
+doctor-ffmpeg-fix = 安装 ffmpeg，例如 `brew install ffmpeg` 或 `apt install ffmpeg`
+
+## signin
+
+signin-earned = { $platform ->
+        [web] 网页端
+       *[mobile] 移动端
+    }：签到成功，获得 { $points } 积分
+signin-already = { $platform ->
+        [web] 网页端
+       *[mobile] 移动端
+    }：今天已签到
+signin-failed = { $total } 个平台中有 { $failed } 个签到失败
+
+## daemon
+
+daemon-config-invalid = 守护进程配置 { $path } 无效：{ $error }
+daemon-no-jobs = { $path } 中没有任务
+daemon-started = 正在运行 { $path } 中的 { $count } 个任务
+daemon-job-start = [{ $time }] { $name }：开始
+daemon-job-ok = [{ $time }] { $name }：完成，耗时 { $secs } 秒
+daemon-job-failed = [{ $time }] { $name }：失败：{ $error }
+daemon-jobs-failed = { $total } 个任务中有 { $failed } 个失败
+daemon-status-job = { $name }（{ $schedule }）
+daemon-schedule-every = 每 { $interval }
+daemon-schedule-at = 每天 { $time }
+daemon-schedule-cron = cron { $expr }
+daemon-last-never = 上次运行：从未
+daemon-last-ok = 上次运行：{ $time }，成功，耗时 { $secs } 秒
+daemon-last-failed = 上次运行：{ $time }，{ $secs } 秒后失败：{ $error }
+daemon-next = 下次运行：{ $time }
+
+## serve
+
+serve-bind-failed = 无法监听 { $addr }：{ $error }
+serve-listening = 正在 http://{ $addr }/ 提供 { $dir }（M3U 播放列表；Ctrl-C 停止）
+
+## rpc
+
+rpc-listening = JSON-RPC 监听于 { $addr }（Ctrl-C 停止）
+rpc-running = 已有服务在 { $addr } 上监听
+rpc-bind-failed = 无法监听 { $addr }
+
+## me / record / cloud
+
+me-user = 用户：{ $name }（id={ $id }）
+me-vip-active = 会员：黑胶 VIP { $level } 级，有效期至 { $date }
+me-vip-package = 会员：音乐包，有效期至 { $date }
+me-vip-expired = 会员：已于 { $date } 过期
+me-vip-none = 会员：无
+me-vip-unavailable = 警告：无法获取会员状态：{ $error }
+me-level = 等级：{ $level } 级（距下一级 { $percent }%：听歌 { $plays }/{ $next_plays } 首，登录 { $days }/{ $next_days } 天）
+me-level-max = 等级：{ $level } 级（已满级）
+me-likes = 喜欢：{ $count } 首
+me-created = 创建的歌单（{ $count }）：
+me-subscribed = 收藏的歌单（{ $count }）：
+record-row = [{ $id }] { $artists } - { $title }（{ $count } 次）
+record-empty = 该时间段没有听歌记录。
+cloud-total = 共 { $count } 首（{ $used } / { $max } MiB）
+cloud-uploaded = { $path } -> 云盘 id { $id }
+cloud-deleted = 已从云盘删除 { $count } 首歌曲。
+
+## backup
+
+backup-playlist = [{ $index }/{ $total }] { $name }
+backup-saved = 已保存 { $path }：歌单 { $playlists } 个（{ $tracks } 首），喜欢的歌曲 { $liked } 首，关注歌手 { $artists } 位，订阅播客 { $podcasts } 个，云盘 { $cloud } 首
+backup-incomplete = 警告：{ $count } 个歌单无法获取曲目，只保存了名称
+backup-invalid = { $path } 不是备份快照
+backup-too-new = { $path } 是第 { $version } 版快照，请更新 ncmdump 后再读取
+backup-no-changes = 没有变化。
+backup-section = { $section }：+{ $added } -{ $removed }
+backup-playlists = 歌单
+backup-playlist-changed = 歌单 { $name } [{ $id }]
+backup-playlist-renamed = 歌单 { $name } [{ $id }]（原名 { $from }）
+backup-liked = 喜欢的歌曲
+backup-artists = 关注的歌手
+backup-podcasts = 订阅的播客
+backup-cloud = 云盘
+
+## Bilibili
+
+bili-login-as = 已登录：{ $name }（mid={ $mid }）
+bili-vip-active = 大会员：有效
+bili-vip = 大会员：{ $active ->
+        [true] 有效
+       *[false] 无
+    }
+bili-session-invalid = 会话存在但验证失败。
+bili-qr-failed = 生成二维码失败
+bili-scan = 请使用哔哩哔哩手机客户端扫码：
+bili-waiting = 等待扫码...
+bili-login-ok = 登录成功！会话已保存。
+bili-scanned = 已扫码，等待确认...
+bili-qr-expired = 二维码已过期，请重试。
+bili-logout = 哔哩哔哩会话已清除。
+bili-title = 标题：{ $title }
+bili-author = UP 主：{ $name }（mid={ $mid }）
+bili-cover = 封面：{ $url }
+bili-parts = 分 P：
+bili-downloading = 正在下载 { $bvid } 的音频...
+bili-user = 用户：{ $name }（mid={ $mid }）
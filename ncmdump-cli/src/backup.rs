@@ -0,0 +1,362 @@
+//! Account snapshots (`backup`).
+//!
+//! A snapshot is one JSON file holding what the account would lose to a
+//! takedown or a lost login: created and subscribed playlists with their
+//! tracks, liked songs, followed artists, subscribed podcasts and the cloud
+//! drive listing. Entries use the `netease-api` types as they serialize.
+//! `version` is bumped whenever a field changes meaning, and newer snapshots
+//! are refused rather than misread.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use netease_api::NeteaseClient;
+use netease_api::types::{Artist, CloudTrack, Playlist, Radio, Track, UserBrief};
+use serde::{Deserialize, Serialize};
+
+use crate::i18n::tr;
+
+/// Format version written by this build.
+pub const VERSION: u32 = 1;
+
+/// Entries requested per page of the paginated listings.
+const PAGE: u64 = 100;
+
+/// The account's data at one point in time.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub version: u32,
+    /// Unix seconds.
+    pub created: i64,
+    pub user: UserBrief,
+    /// Created playlists first (the first is 我喜欢的音乐), then subscribed
+    /// ones. `tracks` is missing for playlists that could not be fetched.
+    pub playlists: Vec<Playlist>,
+    /// Liked song IDs.
+    pub liked: Vec<u64>,
+    /// Followed artists.
+    pub artists: Vec<Artist>,
+    /// Subscribed podcasts.
+    pub podcasts: Vec<Radio>,
+    pub cloud: Vec<CloudTrack>,
+}
+
+impl Snapshot {
+    /// Fetch everything for the logged-in user. `on_playlist` is called
+    /// before each playlist's tracks are fetched, with its index and the
+    /// playlist count.
+    pub fn collect(
+        client: &NeteaseClient,
+        mut on_playlist: impl FnMut(usize, usize, &Playlist),
+    ) -> Result<Self> {
+        let profile = client.user_info()?;
+        let uid = profile.id;
+        let listed = paged(|limit, offset| client.user_playlists(uid, limit, offset))?;
+        let total = listed.len();
+        let mut playlists = Vec::with_capacity(total);
+        for (i, listing) in listed.into_iter().enumerate() {
+            on_playlist(i + 1, total, &listing);
+            match client.playlist_detail(listing.id) {
+                Ok(detail) => playlists.push(detail),
+                // Keep the listing: a playlist gone private or taken down is
+                // exactly what a later diff should still name.
+                Err(e) => {
+                    tracing::warn!(playlist = listing.id, error = %e, "cannot fetch playlist");
+                    playlists.push(listing);
+                }
+            }
+        }
+        Ok(Self {
+            version: VERSION,
+            created: chrono::Utc::now().timestamp(),
+            user: UserBrief {
+                id: uid,
+                name: profile.nickname,
+            },
+            playlists,
+            liked: client.liked_track_ids(uid)?,
+            artists: paged(|limit, offset| client.followed_artists(limit, offset))?,
+            podcasts: paged(|limit, offset| client.subscribed_radios(limit, offset))?,
+            cloud: paged(|limit, offset| client.cloud_list(limit, offset).map(|l| l.tracks))?,
+        })
+    }
+
+    /// Read a snapshot written by [`save`](Self::save).
+    pub fn load(path: &Path) -> Result<Self> {
+        #[derive(Deserialize)]
+        struct Header {
+            version: u32,
+        }
+
+        let text = std::fs::read_to_string(path)
+            .with_context(|| tr!("read-failed", path = path.display()))?;
+        let header: Header = serde_json::from_str(&text)
+            .with_context(|| tr!("backup-invalid", path = path.display()))?;
+        anyhow::ensure!(
+            header.version <= VERSION,
+            tr!(
+                "backup-too-new",
+                path = path.display(),
+                version = header.version
+            )
+        );
+        serde_json::from_str(&text).with_context(|| tr!("backup-invalid", path = path.display()))
+    }
+
+    /// Write the snapshot to `path` as pretty-printed JSON.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, json + "\n")
+            .with_context(|| tr!("write-failed", path = path.display()))
+    }
+
+    /// Number of tracks over all fetched playlists.
+    pub fn track_count(&self) -> usize {
+        self.playlists
+            .iter()
+            .filter_map(|p| p.tracks.as_ref())
+            .map(Vec::len)
+            .sum()
+    }
+
+    /// Playlists whose tracks could not be fetched.
+    pub fn incomplete(&self) -> usize {
+        self.playlists.iter().filter(|p| p.tracks.is_none()).count()
+    }
+
+    /// The track `id` as listed in any playlist.
+    pub fn track(&self, id: u64) -> Option<&Track> {
+        self.playlists
+            .iter()
+            .filter_map(|p| p.tracks.as_ref())
+            .flatten()
+            .find(|t| t.id == id)
+    }
+
+    /// What changed from `self` to the later snapshot `new`.
+    pub fn diff<'a>(&'a self, new: &'a Self) -> Diff<'a> {
+        let old_playlists: BTreeMap<u64, &Playlist> =
+            self.playlists.iter().map(|p| (p.id, p)).collect();
+        let playlists = new
+            .playlists
+            .iter()
+            .filter_map(|p| {
+                let before = old_playlists.get(&p.id)?;
+                // Tracks can only be compared when both snapshots have them.
+                let tracks = match (before.tracks.as_deref(), p.tracks.as_deref()) {
+                    (Some(old), Some(new)) => changes(old, new, |t| t.id),
+                    _ => Changes {
+                        added: Vec::new(),
+                        removed: Vec::new(),
+                    },
+                };
+                let renamed_from = (before.name != p.name).then_some(before.name.as_str());
+                (renamed_from.is_some() || !tracks.is_empty()).then_some(PlaylistChanges {
+                    playlist: p,
+                    renamed_from,
+                    tracks,
+                })
+            })
+            .collect();
+        Diff {
+            playlists: changes(&self.playlists, &new.playlists, |p| p.id),
+            changed: playlists,
+            liked: changes(&self.liked, &new.liked, |&id| id),
+            artists: changes(&self.artists, &new.artists, |a| a.id),
+            podcasts: changes(&self.podcasts, &new.podcasts, |r| r.id),
+            cloud: changes(&self.cloud, &new.cloud, |t| t.id),
+        }
+    }
+}
+
+/// Fetch every page of a listing `fetch(limit, offset)`.
+fn paged<T>(
+    mut fetch: impl FnMut(u64, u64) -> netease_api::Result<Vec<T>>,
+) -> netease_api::Result<Vec<T>> {
+    let mut all = Vec::new();
+    loop {
+        let page = fetch(PAGE, all.len() as u64)?;
+        let fetched = page.len() as u64;
+        all.extend(page);
+        if fetched < PAGE {
+            return Ok(all);
+        }
+    }
+}
+
+/// Entries only in the newer or only in the older list.
+#[derive(Debug)]
+pub struct Changes<'a, T> {
+    pub added: Vec<&'a T>,
+    pub removed: Vec<&'a T>,
+}
+
+impl<T> Changes<'_, T> {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Entries of `new` not in `old` and the reverse, compared by `key`, each in
+/// list order.
+fn changes<'a, T, K: Ord>(old: &'a [T], new: &'a [T], key: impl Fn(&T) -> K) -> Changes<'a, T> {
+    let only_in = |list: &'a [T], other: &[T]| {
+        let keys: std::collections::BTreeSet<K> = other.iter().map(&key).collect();
+        list.iter().filter(|e| !keys.contains(&key(e))).collect()
+    };
+    Changes {
+        added: only_in(new, old),
+        removed: only_in(old, new),
+    }
+}
+
+/// A playlist in both snapshots that was renamed or whose tracks changed.
+#[derive(Debug)]
+pub struct PlaylistChanges<'a> {
+    /// The playlist as it is in the newer snapshot.
+    pub playlist: &'a Playlist,
+    pub renamed_from: Option<&'a str>,
+    pub tracks: Changes<'a, Track>,
+}
+
+/// The difference between two snapshots.
+#[derive(Debug)]
+pub struct Diff<'a> {
+    /// Playlists created, subscribed, deleted or unsubscribed.
+    pub playlists: Changes<'a, Playlist>,
+    pub changed: Vec<PlaylistChanges<'a>>,
+    pub liked: Changes<'a, u64>,
+    pub artists: Changes<'a, Artist>,
+    pub podcasts: Changes<'a, Radio>,
+    pub cloud: Changes<'a, CloudTrack>,
+}
+
+impl Diff<'_> {
+    pub fn is_empty(&self) -> bool {
+        self.playlists.is_empty()
+            && self.changed.is_empty()
+            && self.liked.is_empty()
+            && self.artists.is_empty()
+            && self.podcasts.is_empty()
+            && self.cloud.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use netease_api::types::Album;
+
+    fn track(id: u64, name: &str) -> Track {
+        Track {
+            id,
+            name: name.into(),
+            artists: Vec::new(),
+            album: Album {
+                id: 0,
+                name: String::new(),
+                pic_url: None,
+            },
+            duration_ms: 0,
+        }
+    }
+
+    fn playlist(id: u64, name: &str, tracks: Option<Vec<Track>>) -> Playlist {
+        Playlist {
+            id,
+            name: name.into(),
+            description: None,
+            cover_url: None,
+            track_count: tracks.as_ref().map_or(0, |t| t.len() as u64),
+            creator: None,
+            tracks,
+        }
+    }
+
+    fn snapshot(playlists: Vec<Playlist>, liked: Vec<u64>) -> Snapshot {
+        Snapshot {
+            version: VERSION,
+            created: 0,
+            user: UserBrief {
+                id: 1,
+                name: "user".into(),
+            },
+            playlists,
+            liked,
+            artists: Vec::new(),
+            podcasts: Vec::new(),
+            cloud: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_diff() {
+        let old = snapshot(
+            vec![
+                playlist(
+                    1,
+                    "我喜欢的音乐",
+                    Some(vec![track(10, "晴天"), track(11, "稻香")]),
+                ),
+                playlist(2, "Old", Some(vec![])),
+                playlist(3, "Private", None),
+            ],
+            vec![10, 11],
+        );
+        let new = snapshot(
+            vec![
+                playlist(
+                    1,
+                    "我喜欢的音乐",
+                    Some(vec![track(12, "七里香"), track(10, "晴天")]),
+                ),
+                playlist(3, "Private now", Some(vec![track(13, "夜曲")])),
+                playlist(4, "New", Some(vec![])),
+            ],
+            vec![12, 10],
+        );
+        let diff = old.diff(&new);
+        let ids = |list: &[&Playlist]| list.iter().map(|p| p.id).collect::<Vec<_>>();
+        assert_eq!(ids(&diff.playlists.added), [4]);
+        assert_eq!(ids(&diff.playlists.removed), [2]);
+        assert_eq!(diff.liked.added, [&12]);
+        assert_eq!(diff.liked.removed, [&11]);
+
+        let [liked, private] = &diff.changed[..] else {
+            panic!("{diff:?}");
+        };
+        assert_eq!(liked.playlist.id, 1);
+        assert_eq!(liked.renamed_from, None);
+        assert_eq!(liked.tracks.added[0].name, "七里香");
+        assert_eq!(liked.tracks.removed[0].name, "稻香");
+        // Without its old tracks, only the rename of playlist 3 shows.
+        assert_eq!(private.renamed_from, Some("Private"));
+        assert!(private.tracks.is_empty());
+
+        assert!(new.diff(&new).is_empty());
+        assert_eq!(old.track(11).unwrap().name, "稻香");
+        assert_eq!(old.track_count(), 2);
+        assert_eq!(old.incomplete(), 1);
+    }
+
+    #[test]
+    fn test_load_checks_version() {
+        let dir = std::env::temp_dir().join(format!("ncmdump-backup-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("snapshot.json");
+
+        let mut saved = snapshot(
+            vec![playlist(1, "A", Some(vec![track(10, "晴天")]))],
+            vec![10],
+        );
+        saved.save(&path).unwrap();
+        let loaded = Snapshot::load(&path).unwrap();
+        assert_eq!(loaded.playlists[0].tracks.as_ref().unwrap()[0].name, "晴天");
+
+        saved.version = VERSION + 1;
+        saved.save(&path).unwrap();
+        assert!(Snapshot::load(&path).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,179 @@
+//! Configuration of `ncmdump-cli daemon`.
+//!
+//! Jobs are read from `daemon.toml` in the config directory:
+//!
+//! ```toml
+//! [[job]]
+//! name = "favorites"
+//! kind = "playlist"
+//! id = 19723756
+//! output = "/home/me/Music/Favorites"
+//! every = "1h"
+//!
+//! [[job]]
+//! name = "daily"
+//! kind = "daily"
+//! output = "/home/me/Music/Daily"
+//! at = "08:00"
+//!
+//! [[job]]
+//! name = "signin"
+//! kind = "signin"
+//! cron = "30 7 * * *"
+//! ```
+//!
+//! Every job has an `every` interval, a daily `at` time or a `cron`
+//! expression. Jobs run on a [`Scheduler`](crate::scheduler::Scheduler),
+//! which keeps the outcome of each job's last run in `daemon-state.json`
+//! next to the config.
+
+use std::path::PathBuf;
+
+use chrono::NaiveTime;
+use serde::Deserialize;
+
+use crate::scheduler::{Cron, Schedule, parse_interval};
+
+/// Contents of `daemon.toml`.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    #[serde(rename = "job", default)]
+    pub jobs: Vec<Job>,
+}
+
+impl Config {
+    /// Parse a config file and check every job's schedule and that names
+    /// are unique.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let config: Self = toml::from_str(text).map_err(|e| e.to_string())?;
+        let mut names = std::collections::HashSet::new();
+        for job in &config.jobs {
+            if !names.insert(job.name.as_str()) {
+                return Err(format!("duplicate job name {:?}", job.name));
+            }
+            job.schedule()
+                .map_err(|e| format!("job {:?}: {e}", job.name))?;
+        }
+        Ok(config)
+    }
+}
+
+/// One `[[job]]` entry.
+#[derive(Debug, Deserialize)]
+pub struct Job {
+    /// Unique name, used in logs and `daemon status`.
+    pub name: String,
+    /// Interval such as `30m`, `1h` or `1d12h`.
+    pub every: Option<String>,
+    /// Local time of day (`HH:MM`) to run once a day.
+    pub at: Option<String>,
+    /// Cron expression such as `0 8 * * 1-5`.
+    pub cron: Option<String>,
+    #[serde(flatten)]
+    pub task: Task,
+}
+
+impl Job {
+    pub fn schedule(&self) -> Result<Schedule, String> {
+        match (&self.every, &self.at, &self.cron) {
+            (Some(every), None, None) => parse_interval(every).map(Schedule::Every),
+            (None, Some(at), None) => NaiveTime::parse_from_str(at, "%H:%M")
+                .map(Schedule::Daily)
+                .map_err(|_| format!("invalid time {at:?} (expected HH:MM)")),
+            (None, None, Some(cron)) => Cron::parse(cron).map(Schedule::Cron),
+            _ => Err("set exactly one of `every`, `at` and `cron`".into()),
+        }
+    }
+}
+
+/// What a job does, selected by its `kind` key.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase", deny_unknown_fields)]
+pub enum Task {
+    /// Download the tracks of a playlist that are not in the output yet.
+    Playlist {
+        id: u64,
+        output: PathBuf,
+        quality: Option<String>,
+    },
+    /// Download an album.
+    Album {
+        id: u64,
+        output: PathBuf,
+        quality: Option<String>,
+    },
+    /// Download an official chart (by name or ID).
+    Toplist {
+        chart: String,
+        output: PathBuf,
+        limit: Option<usize>,
+        quality: Option<String>,
+    },
+    /// Download today's recommended songs (requires login).
+    Daily {
+        output: PathBuf,
+        quality: Option<String>,
+    },
+    /// Convert the NCM files in a watch folder.
+    Dump {
+        input: PathBuf,
+        output: Option<PathBuf>,
+        #[serde(default)]
+        recursive: bool,
+        #[serde(default)]
+        remove: bool,
+    },
+    /// Claim the daily sign-in points (requires login).
+    Signin,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_config() {
+        let config = Config::parse(
+            r#"
+            [[job]]
+            name = "favorites"
+            kind = "playlist"
+            id = 19723756
+            output = "music"
+            every = "1h"
+
+            [[job]]
+            name = "inbox"
+            kind = "dump"
+            input = "ncm"
+            remove = true
+            at = "03:00"
+
+            [[job]]
+            name = "signin"
+            kind = "signin"
+            cron = "30 7 * * *"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.jobs.len(), 3);
+        assert!(matches!(
+            config.jobs[0].task,
+            Task::Playlist { id: 19_723_756, .. }
+        ));
+        assert!(matches!(
+            config.jobs[1].task,
+            Task::Dump { remove: true, .. }
+        ));
+        assert!(matches!(config.jobs[2].task, Task::Signin));
+        assert!(matches!(config.jobs[2].schedule(), Ok(Schedule::Cron(_))));
+
+        let both = "[[job]]\nname = \"x\"\nkind = \"daily\"\noutput = \"d\"\nevery = \"1h\"\nat = \"08:00\"";
+        assert!(Config::parse(both).is_err());
+        let bad_cron = "[[job]]\nname = \"x\"\nkind = \"signin\"\ncron = \"61 * * * *\"";
+        assert!(Config::parse(bad_cron).is_err());
+        let unknown = "[[job]]\nname = \"x\"\nkind = \"sync\"\nevery = \"1h\"";
+        assert!(Config::parse(unknown).is_err());
+    }
+}
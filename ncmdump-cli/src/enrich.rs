@@ -0,0 +1,217 @@
+//! MusicBrainz enrichment (`enrich`).
+//!
+//! Looks up the recording a tagged file holds on MusicBrainz and adds what
+//! beets and Picard key their libraries on: the recording, release, release
+//! group and artist MBIDs, and the album artist as the release credits it.
+//! Title, artist and album stay as Netease has them. A recording counts only
+//! if its title and duration agree with the file; of its releases, the one
+//! whose title is closest to the file's album wins, official and earlier
+//! releases first.
+
+use std::path::Path;
+
+use anyhow::Result;
+use musicbrainz_api::types::{Recording, Release, credit_name};
+use musicbrainz_api::{MusicBrainzClient, RecordingQuery};
+use ncmdump::{AudioTags, MusicBrainzIds};
+
+use crate::identify::similar;
+
+/// Search results considered per file.
+const CANDIDATES: u32 = 10;
+
+/// Lowest MusicBrainz search score accepted.
+const MIN_SEARCH_SCORE: u8 = 80;
+
+/// Lowest title similarity accepted.
+const MIN_TITLE_SIMILARITY: f64 = 0.8;
+
+/// Largest duration difference accepted, in milliseconds.
+const DURATION_LIMIT_MS: u64 = 3_000;
+
+/// What was found for a file.
+#[derive(Debug)]
+pub struct Enrichment {
+    /// Tags to add; everything else is left as is.
+    pub tags: AudioTags,
+    /// The chosen release, with its full artist credit.
+    pub release: Release,
+}
+
+/// Looks files up on MusicBrainz.
+pub struct Enricher<'a> {
+    client: &'a MusicBrainzClient,
+}
+
+impl<'a> Enricher<'a> {
+    pub fn new(client: &'a MusicBrainzClient) -> Self {
+        Self { client }
+    }
+
+    /// MusicBrainz tags for the audio file at `path`, or `None` without a
+    /// title to search for or a fitting recording.
+    pub fn lookup(&self, path: &Path) -> Result<Option<Enrichment>> {
+        let tags = ncmdump::tag_read(path)?;
+        let Some(title) = tags.title.as_deref() else {
+            return Ok(None);
+        };
+        // Netease joins several artists with " / "; the first one is enough
+        // to narrow the search.
+        let artist = tags
+            .artist
+            .as_deref()
+            .and_then(|a| a.split(" / ").next())
+            .map(str::trim);
+        let query = RecordingQuery {
+            title,
+            artist,
+            release: None,
+        };
+        let recordings = self.client.search_recordings(&query, CANDIDATES)?;
+        tracing::debug!(file = %path.display(), candidates = recordings.len(), "searched MusicBrainz");
+
+        let Some((recording, release)) = pick(&tags, &recordings) else {
+            return Ok(None);
+        };
+        let release_track = release
+            .media
+            .iter()
+            .flat_map(|m| &m.tracks)
+            .next()
+            .map(|t| t.id.clone());
+        // Search results carry neither the release's credit nor, reliably,
+        // its release group.
+        let release = self.client.release(&release.id)?;
+        let tags = AudioTags {
+            album_artist: (!release.artist_credit.is_empty())
+                .then(|| credit_name(&release.artist_credit)),
+            musicbrainz: MusicBrainzIds {
+                recording: Some(recording.id.clone()),
+                release_track,
+                release: Some(release.id.clone()),
+                release_group: release.release_group.as_ref().map(|g| g.id.clone()),
+                artists: artist_ids(recording),
+                album_artists: release
+                    .artist_credit
+                    .iter()
+                    .map(|c| c.artist.id.clone())
+                    .collect(),
+            },
+            ..AudioTags::default()
+        };
+        Ok(Some(Enrichment { tags, release }))
+    }
+
+    /// The Cover Art Archive front cover of `release`, if it has one.
+    pub fn cover(&self, release: &Release) -> Result<Option<Vec<u8>>> {
+        let group = release.release_group.as_ref().map(|g| g.id.as_str());
+        Ok(self.client.front_cover(&release.id, group)?)
+    }
+}
+
+fn artist_ids(recording: &Recording) -> Vec<String> {
+    recording
+        .artist_credit
+        .iter()
+        .map(|c| c.artist.id.clone())
+        .collect()
+}
+
+/// The first recording in `recordings` that fits `tags`, with its best
+/// release for the file's album.
+fn pick<'r>(tags: &AudioTags, recordings: &'r [Recording]) -> Option<(&'r Recording, &'r Release)> {
+    let title = tags.title.as_deref()?;
+    recordings
+        .iter()
+        .filter(|r| r.score >= MIN_SEARCH_SCORE)
+        .filter(|r| similar(title, &r.title) >= MIN_TITLE_SIMILARITY)
+        .filter(|r| match r.length {
+            Some(length) if tags.duration_ms > 0 => {
+                length.abs_diff(tags.duration_ms) <= DURATION_LIMIT_MS
+            }
+            _ => true,
+        })
+        .find_map(|r| Some((r, best_release(tags.album.as_deref(), &r.releases)?)))
+}
+
+/// The release closest to `album` by title; ties go to official releases,
+/// then to the earliest.
+fn best_release<'r>(album: Option<&str>, releases: &'r [Release]) -> Option<&'r Release> {
+    releases.iter().min_by(|a, b| {
+        let closeness = |r: &Release| album.map_or(0.0, |album| similar(album, &r.title));
+        let official = |r: &Release| r.status.as_deref() != Some("Official");
+        // Undated releases sort last.
+        let date = |r: &Release| (r.date.is_none(), r.date.clone());
+        closeness(b)
+            .total_cmp(&closeness(a))
+            .then_with(|| official(a).cmp(&official(b)))
+            .then_with(|| date(a).cmp(&date(b)))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn release(id: &str, title: &str, status: Option<&str>, date: Option<&str>) -> Release {
+        Release {
+            id: id.into(),
+            title: title.into(),
+            status: status.map(String::from),
+            date: date.map(String::from),
+            country: None,
+            artist_credit: Vec::new(),
+            release_group: None,
+            media: Vec::new(),
+        }
+    }
+
+    fn recording(id: &str, title: &str, score: u8, length: Option<u64>) -> Recording {
+        Recording {
+            id: id.into(),
+            title: title.into(),
+            length,
+            score,
+            artist_credit: Vec::new(),
+            releases: vec![release("rel", "叶惠美", Some("Official"), None)],
+        }
+    }
+
+    #[test]
+    fn test_best_release() {
+        let releases = [
+            release("best-of", "Greatest Hits", Some("Official"), Some("2010")),
+            release("promo", "叶惠美", Some("Promotion"), Some("2003-07")),
+            release("reissue", "叶惠美", Some("Official"), Some("2008")),
+            release("first", "叶惠美", Some("Official"), Some("2003-07-31")),
+            release("undated", "叶惠美", Some("Official"), None),
+        ];
+        let best = |album| best_release(album, &releases).unwrap().id.as_str();
+        assert_eq!(best(Some("叶惠美")), "first");
+        // Without an album, only status and date count.
+        assert_eq!(best(None), "first");
+        assert!(best_release(Some("叶惠美"), &[]).is_none());
+    }
+
+    #[test]
+    fn test_pick_checks_title_duration_and_score() {
+        let tags = AudioTags {
+            title: Some("晴天".into()),
+            album: Some("叶惠美".into()),
+            duration_ms: 269_000,
+            ..AudioTags::default()
+        };
+        let recordings = [
+            recording("low-score", "晴天", 60, Some(269_000)),
+            recording("live", "晴天", 95, Some(290_000)),
+            recording("other", "稻香", 95, Some(269_000)),
+            recording("studio", "晴天", 90, Some(270_500)),
+        ];
+        let (found, release) = pick(&tags, &recordings).unwrap();
+        assert_eq!(found.id, "studio");
+        assert_eq!(release.id, "rel");
+
+        let untitled = AudioTags::default();
+        assert!(pick(&untitled, &recordings).is_none());
+    }
+}
@@ -0,0 +1,139 @@
+//! Chromaprint audio fingerprints, computed with the `fpcalc` tool.
+//!
+//! A fingerprint is a sequence of 32-bit sub-fingerprints, one every ~0.124
+//! seconds of audio. Two recordings are the same when, at some alignment,
+//! few of their bits differ; re-encoding (FLAC → MP3, a different bitrate)
+//! flips only a small fraction of them.
+
+use std::io;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use serde::Deserialize;
+
+/// Seconds of audio fingerprinted: enough to tell recordings apart, and
+/// short enough to compare against the start of a partially fetched file.
+pub const LENGTH_SECS: u32 = 30;
+
+/// Largest shift, in sub-fingerprints (~10 s), tried when aligning two
+/// fingerprints, e.g. for a different amount of leading silence.
+const MAX_OFFSET: usize = 80;
+
+/// Fewest overlapping sub-fingerprints (~5 s) for an alignment to count.
+const MIN_OVERLAP: usize = 40;
+
+/// Similarity from which two fingerprints are taken to be the same
+/// recording. Unrelated audio scores around 0.5.
+pub const MATCH_THRESHOLD: f64 = 0.8;
+
+/// The fingerprint of the first [`LENGTH_SECS`] of a file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fingerprint {
+    /// Duration of the whole file in seconds, as decoded.
+    pub duration: f64,
+    pub raw: Vec<u32>,
+}
+
+/// Whether `fpcalc` can be run.
+pub fn available() -> bool {
+    Command::new("fpcalc")
+        .arg("-version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|s| s.success())
+}
+
+/// Fingerprint the audio file at `path`.
+pub fn compute(path: &Path) -> io::Result<Fingerprint> {
+    #[derive(Deserialize)]
+    struct Output {
+        duration: f64,
+        // Signed in older fpcalc versions.
+        fingerprint: Vec<i64>,
+    }
+
+    let output = Command::new("fpcalc")
+        .args(["-raw", "-json", "-length", &LENGTH_SECS.to_string()])
+        .arg(ncmdump::path::extended(path).as_os_str())
+        .stdin(Stdio::null())
+        .output()?;
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(io::Error::other(format!("fpcalc failed: {}", error.trim())));
+    }
+    let parsed: Output = serde_json::from_slice(&output.stdout).map_err(io::Error::other)?;
+    Ok(Fingerprint {
+        duration: parsed.duration,
+        raw: parsed
+            .fingerprint
+            .into_iter()
+            .map(|v| u32::try_from(v.rem_euclid(1 << 32)).unwrap_or_default())
+            .collect(),
+    })
+}
+
+/// How alike two raw fingerprints are, from 0 to 1: the share of equal bits
+/// at their best alignment. 0 if they overlap too little to compare.
+#[allow(clippy::cast_precision_loss)]
+pub fn similarity(a: &[u32], b: &[u32]) -> f64 {
+    let mut best = 0.0;
+    for offset in 0..=MAX_OFFSET {
+        for (x, y) in [(a, b), (b, a)] {
+            let Some(x) = x.get(offset..) else {
+                continue;
+            };
+            let overlap = x.len().min(y.len());
+            if overlap < MIN_OVERLAP {
+                continue;
+            }
+            let errors: u32 = x.iter().zip(y).map(|(p, q)| (p ^ q).count_ones()).sum();
+            let score = 1.0 - f64::from(errors) / (32.0 * overlap as f64);
+            if score > best {
+                best = score;
+            }
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A deterministic pseudo-random fingerprint.
+    fn noise(len: usize, seed: u32) -> Vec<u32> {
+        let mut state = seed;
+        (0..len)
+            .map(|_| {
+                state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                state
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_identical_and_unrelated() {
+        let a = noise(240, 1);
+        assert!((similarity(&a, &a) - 1.0).abs() < f64::EPSILON);
+        let unrelated = similarity(&a, &noise(240, 2));
+        assert!(unrelated < MATCH_THRESHOLD, "{unrelated}");
+    }
+
+    #[test]
+    fn test_aligns_shifted_and_noisy() {
+        let a = noise(240, 3);
+        // `b` starts 20 sub-fingerprints later and has one flipped bit in
+        // every item (~3% of bits).
+        let b: Vec<u32> = a[20..].iter().map(|v| v ^ 1).collect();
+        let score = similarity(&a, &b);
+        assert!(score > 0.95, "{score}");
+        assert!((similarity(&b, &a) - score).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_too_short_to_compare() {
+        let a = noise(10, 4);
+        assert!(similarity(&a, &a).abs() < f64::EPSILON);
+    }
+}
@@ -0,0 +1,268 @@
+//! Download history, backed by the [`ncmdump_db`] library database.
+//!
+//! A [`History`] is the library as seen from one output directory: later runs
+//! skip tracks that were already saved there at the requested quality or
+//! better. Records keep each file's size and MD5, which lets a lookup find
+//! the file again after it was renamed behind our back.
+//!
+//! Older versions kept a [`LEGACY_FILE_NAME`] manifest per directory instead;
+//! it is imported into the library the first time the directory is used.
+
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+
+use ncmdump_db::{Library, Origin, Track};
+use serde::Deserialize;
+
+/// Name of the per-directory manifest written by older versions.
+pub const LEGACY_FILE_NAME: &str = ".ncmdump-history.json";
+
+static LIBRARY: OnceLock<Option<Arc<Library>>> = OnceLock::new();
+
+/// The library database, opened on first use; `None` (after a warning) if
+/// it cannot be opened.
+pub fn library() -> Option<Arc<Library>> {
+    LIBRARY
+        .get_or_init(|| {
+            Library::default_path()
+                .and_then(|path| Library::open(&path))
+                .map(Arc::new)
+                .inspect_err(|e| tracing::warn!(error = %e, "cannot open library database"))
+                .ok()
+        })
+        .clone()
+}
+
+/// A record of the audio file at `path` with the tags it carries; set the
+/// remaining fields with struct update syntax.
+pub fn scan(path: &Path, origin: Origin) -> io::Result<Track> {
+    let tags = ncmdump::tag_read(path)
+        .inspect_err(|e| tracing::debug!(file = %path.display(), error = %e, "no tags to record"))
+        .ok()
+        .unwrap_or_default();
+    Ok(Track {
+        title: tags.title,
+        artist: tags.artist,
+        album: tags.album,
+        ..Track::from_file(path, origin)?
+    })
+}
+
+/// The download history of one directory. Safe to share between threads.
+pub struct History {
+    library: Arc<Library>,
+    dir: PathBuf,
+}
+
+impl History {
+    /// The history of `dir` in `library`.
+    pub fn with_library(library: Arc<Library>, dir: &Path) -> Self {
+        let dir = std::path::absolute(dir).unwrap_or_else(|_| dir.to_path_buf());
+        let history = Self { library, dir };
+        history.import_legacy();
+        history
+    }
+
+    /// The file track `id` was saved as, if it was fetched at `bitrate` or
+    /// better and is still in the directory.
+    ///
+    /// A recorded file that has gone missing is searched for by size and
+    /// MD5 among the directory's files; if found, the record is updated.
+    pub fn find(&self, id: u64, bitrate: u64) -> Option<PathBuf> {
+        let tracks = self
+            .library
+            .by_netease_id(id)
+            .inspect_err(|e| tracing::warn!(error = %e, "cannot query library database"))
+            .ok()?;
+        tracks
+            .iter()
+            .filter(|t| t.bitrate.is_some_and(|b| b >= bitrate))
+            .filter(|t| t.path.parent() == Some(self.dir.as_path()))
+            .find_map(|t| {
+                if t.path.is_file() {
+                    return Some(t.path.clone());
+                }
+                let found = self.search(t)?;
+                tracing::debug!(id, from = %t.path.display(), to = %found.display(), "history entry moved");
+                if let Err(e) = self.library.rename(&t.path, &found) {
+                    tracing::warn!(error = %e, "failed to update download history");
+                }
+                Some(found)
+            })
+    }
+
+    /// Record that track `id` was downloaded to `path` (inside the
+    /// directory) at `bitrate`.
+    pub fn record(&self, id: u64, path: &Path, bitrate: u64) -> ncmdump_db::Result<()> {
+        self.library.record(&Track {
+            netease_id: Some(id),
+            bitrate: Some(bitrate),
+            ..scan(path, Origin::Download)?
+        })
+    }
+
+    /// A file in the directory with `track`'s size and MD5.
+    fn search(&self, track: &Track) -> Option<PathBuf> {
+        std::fs::read_dir(&self.dir)
+            .ok()?
+            .filter_map(Result::ok)
+            .map(|e| e.path())
+            .filter(|p| {
+                p.metadata()
+                    .is_ok_and(|m| m.is_file() && m.len() == track.size)
+            })
+            .find(|p| ncmdump_db::fingerprint(p).is_ok_and(|(_, md5)| md5 == track.md5))
+    }
+
+    /// Move the entries of the directory's legacy manifest, if any, into the
+    /// library and set the manifest aside as `*.imported`.
+    fn import_legacy(&self) {
+        #[derive(Deserialize)]
+        struct Entry {
+            file: String,
+            bitrate: u64,
+            size: u64,
+            md5: String,
+        }
+        #[derive(Deserialize)]
+        struct Manifest {
+            tracks: BTreeMap<u64, Entry>,
+        }
+
+        let path = self.dir.join(LEGACY_FILE_NAME);
+        let Ok(bytes) = std::fs::read(&path) else {
+            return;
+        };
+        let manifest: Manifest = match serde_json::from_slice(&bytes) {
+            Ok(m) => m,
+            Err(e) => {
+                tracing::warn!(dir = %self.dir.display(), error = %e, "ignoring corrupt download history");
+                return;
+            }
+        };
+        for (id, entry) in manifest.tracks {
+            let file = self.dir.join(&entry.file);
+            let track = scan(&file, Origin::Download).unwrap_or_else(|_| Track {
+                path: file.clone(),
+                origin: Origin::Download,
+                netease_id: None,
+                source: None,
+                bitrate: None,
+                size: entry.size,
+                md5: entry.md5,
+                title: None,
+                artist: None,
+                album: None,
+                recorded_at: 0,
+            });
+            let track = Track {
+                netease_id: Some(id),
+                bitrate: Some(entry.bitrate),
+                ..track
+            };
+            if let Err(e) = self.library.record(&track) {
+                tracing::warn!(dir = %self.dir.display(), error = %e, "failed to import download history");
+                return;
+            }
+        }
+        if let Err(e) = std::fs::rename(&path, path.with_extension("json.imported")) {
+            tracing::warn!(dir = %self.dir.display(), error = %e, "failed to set imported download history aside");
+        }
+        tracing::info!(dir = %self.dir.display(), "imported download history into library");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("ncmdump-history-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn library() -> Arc<Library> {
+        Arc::new(Library::open_in_memory().unwrap())
+    }
+
+    #[test]
+    fn test_record_and_find() {
+        let dir = temp_dir("find");
+        let song = dir.join("A - B.mp3");
+        std::fs::write(&song, b"audio").unwrap();
+        let library = library();
+        History::with_library(Arc::clone(&library), &dir)
+            .record(1, &song, 320_000)
+            .unwrap();
+
+        let history = History::with_library(library, &dir);
+        assert_eq!(history.find(1, 320_000), Some(song.clone()));
+        assert_eq!(history.find(1, 128_000), Some(song.clone()));
+        // A higher quality than recorded is fetched again.
+        assert_eq!(history.find(1, 999_000), None);
+        assert_eq!(history.find(2, 128_000), None);
+
+        std::fs::remove_file(&song).unwrap();
+        assert_eq!(history.find(1, 320_000), None);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_find_is_per_directory() {
+        let dir = temp_dir("per-dir");
+        let other = temp_dir("per-dir-other");
+        let song = dir.join("1.mp3");
+        std::fs::write(&song, b"audio").unwrap();
+        let library = library();
+        History::with_library(Arc::clone(&library), &dir)
+            .record(1, &song, 320_000)
+            .unwrap();
+
+        assert_eq!(
+            History::with_library(library, &other).find(1, 320_000),
+            None
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+        std::fs::remove_dir_all(&other).unwrap();
+    }
+
+    #[test]
+    fn test_find_follows_renamed_file() {
+        let dir = temp_dir("rename");
+        let song = dir.join("1.mp3");
+        std::fs::write(&song, b"audio").unwrap();
+        std::fs::write(dir.join("other.mp3"), b"other").unwrap();
+        let library = library();
+        let history = History::with_library(Arc::clone(&library), &dir);
+        history.record(1, &song, 320_000).unwrap();
+
+        let moved = dir.join("A - B.mp3");
+        std::fs::rename(&song, &moved).unwrap();
+        assert_eq!(history.find(1, 320_000), Some(moved.clone()));
+        // The record was updated, so the next lookup needs no search.
+        assert_eq!(library.by_netease_id(1).unwrap()[0].path, moved);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_imports_legacy_manifest() {
+        let dir = temp_dir("legacy");
+        std::fs::write(dir.join("A - B.mp3"), b"audio").unwrap();
+        std::fs::write(
+            dir.join(LEGACY_FILE_NAME),
+            r#"{"tracks":{"1":{"file":"A - B.mp3","bitrate":320000,"size":5,"md5":"x"}}}"#,
+        )
+        .unwrap();
+
+        let history = History::with_library(library(), &dir);
+        assert_eq!(history.find(1, 320_000), Some(dir.join("A - B.mp3")));
+        assert!(!dir.join(LEGACY_FILE_NAME).exists());
+        assert!(dir.join(".ncmdump-history.json.imported").exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
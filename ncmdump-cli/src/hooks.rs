@@ -0,0 +1,308 @@
+//! Post-processing hooks: commands run after each converted or downloaded
+//! file.
+//!
+//! Hooks are read from `hooks.toml` in the config directory, and can be
+//! added for one run with the global `--hook` flag:
+//!
+//! ```toml
+//! [[hook]]
+//! name = "nas"
+//! command = "rsync -a \"$NCMDUMP_PATH\" nas:/music/"
+//! on = ["download"]
+//!
+//! [[hook]]
+//! command = "notify-send ncmdump \"$(jq -r .title)\""
+//! ```
+//!
+//! A command runs through the shell (`sh -c`, `cmd /C` on Windows) with a
+//! [`Payload`] as one line of JSON on stdin, and `NCMDUMP_EVENT` and
+//! `NCMDUMP_PATH` in its environment. Hooks without `on` run for every
+//! event. A failing hook is reported but does not fail the file it ran for.
+//!
+//! The same file holds the `[[notify]]` entries of [`crate::notify`].
+
+// `serialize_with` functions take `&Option<&Path>`, in the derive as well.
+#![allow(clippy::ref_option_ref)]
+
+use std::io::Write as _;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use serde::{Deserialize, Serialize};
+
+/// What a file went through.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Event {
+    /// Converted from NCM or the client cache.
+    Convert,
+    /// Downloaded from Netease.
+    Download,
+}
+
+impl Event {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Convert => "convert",
+            Self::Download => "download",
+        }
+    }
+}
+
+/// Contents of `hooks.toml`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    #[serde(rename = "hook", default)]
+    pub hooks: Vec<Hook>,
+    #[serde(rename = "notify", default)]
+    pub notifiers: Vec<crate::notify::Notifier>,
+}
+
+impl Config {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let config: Self = toml::from_str(text).map_err(|e| e.to_string())?;
+        if let Some(hook) = config.hooks.iter().find(|h| h.command.trim().is_empty()) {
+            return Err(format!("hook {:?} has an empty command", hook.label()));
+        }
+        for notifier in &config.notifiers {
+            notifier.validate()?;
+        }
+        Ok(config)
+    }
+}
+
+/// One `[[hook]]` entry.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Hook {
+    /// Shown in messages; the command itself if absent.
+    pub name: Option<String>,
+    /// Shell command line.
+    pub command: String,
+    /// Events to run for; all if empty.
+    #[serde(default)]
+    pub on: Vec<Event>,
+}
+
+impl Hook {
+    /// A hook running `command` for every event (from `--hook`).
+    pub fn command(command: String) -> Self {
+        Self {
+            name: None,
+            command,
+            on: Vec::new(),
+        }
+    }
+
+    pub fn label(&self) -> &str {
+        self.name.as_deref().unwrap_or(&self.command)
+    }
+
+    fn runs_on(&self, event: Event) -> bool {
+        self.on.is_empty() || self.on.contains(&event)
+    }
+
+    /// Run the hook with `payload`; the error describes why it failed.
+    fn run(&self, payload: &Payload, json: &str) -> Result<(), String> {
+        let mut child = shell(&self.command)
+            .env("NCMDUMP_EVENT", payload.event.name())
+            .env("NCMDUMP_PATH", payload.path)
+            .stdin(Stdio::piped())
+            .spawn()
+            .map_err(|e| e.to_string())?;
+        if let Some(mut stdin) = child.stdin.take() {
+            // A hook that doesn't read its input closes the pipe early.
+            let _ = writeln!(stdin, "{json}");
+        }
+        let status = child.wait().map_err(|e| e.to_string())?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(status.to_string())
+        }
+    }
+}
+
+fn shell(command: &str) -> Command {
+    if cfg!(windows) {
+        let mut cmd = Command::new("cmd");
+        cmd.arg("/C").arg(command);
+        cmd
+    } else {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg(command);
+        cmd
+    }
+}
+
+/// What a hook is told about a file, as JSON on its stdin.
+#[derive(Debug, Serialize)]
+pub struct Payload<'a> {
+    pub event: Event,
+    /// The output file; see `NCMDUMP_PATH` for names that aren't Unicode.
+    #[serde(serialize_with = "lossy")]
+    pub path: &'a Path,
+    /// The NCM or cache file it was converted from.
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "lossy_option"
+    )]
+    pub source: Option<&'a Path>,
+    /// Netease track ID, when known.
+    pub track_id: Option<u64>,
+    pub bytes: u64,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub duration_ms: u64,
+}
+
+fn lossy<S: serde::Serializer>(path: &&Path, s: S) -> Result<S::Ok, S::Error> {
+    s.serialize_str(&path.to_string_lossy())
+}
+
+fn lossy_option<S: serde::Serializer>(path: &Option<&Path>, s: S) -> Result<S::Ok, S::Error> {
+    match path {
+        Some(path) => lossy(path, s),
+        None => s.serialize_none(),
+    }
+}
+
+impl<'a> Payload<'a> {
+    /// The payload for `path`, with its size and tags read from the file.
+    /// The track ID is taken from its `163 key` unless given.
+    pub fn new(event: Event, path: &'a Path, track_id: Option<u64>) -> Self {
+        let tags = ncmdump::tag_read(path).unwrap_or_default();
+        Self {
+            event,
+            path,
+            source: None,
+            track_id: track_id.or_else(|| crate::embedded_track_id(&tags)),
+            bytes: std::fs::metadata(path).map_or(0, |m| m.len()),
+            title: tags.title,
+            artist: tags.artist,
+            album: tags.album,
+            duration_ms: tags.duration_ms,
+        }
+    }
+}
+
+/// The configured hooks.
+#[derive(Debug, Default)]
+pub struct Hooks(Vec<Hook>);
+
+impl Hooks {
+    pub fn new(hooks: Vec<Hook>) -> Self {
+        Self(hooks)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Run the hooks for `payload.event` in order. Returns the label and
+    /// error of each hook that failed.
+    pub fn run(&self, payload: &Payload) -> Vec<(&str, String)> {
+        let hooks: Vec<&Hook> = self.0.iter().filter(|h| h.runs_on(payload.event)).collect();
+        if hooks.is_empty() {
+            return Vec::new();
+        }
+        let json = match serde_json::to_string(payload) {
+            Ok(json) => json,
+            Err(e) => return vec![("payload", e.to_string())],
+        };
+        hooks
+            .into_iter()
+            .filter_map(|hook| {
+                tracing::debug!(hook = hook.label(), path = %payload.path.display(), "running hook");
+                hook.run(payload, &json).err().map(|e| (hook.label(), e))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_config() {
+        let config = Config::parse(
+            r#"
+            [[hook]]
+            name = "nas"
+            command = "rsync -a \"$NCMDUMP_PATH\" nas:/music/"
+            on = ["download"]
+
+            [[hook]]
+            command = "true"
+
+            [[notify]]
+            desktop = true
+            on = ["failed"]
+            "#,
+        )
+        .unwrap();
+        let [nas, any] = &config.hooks[..] else {
+            panic!("{config:?}");
+        };
+        assert_eq!(nas.label(), "nas");
+        assert!(nas.runs_on(Event::Download) && !nas.runs_on(Event::Convert));
+        assert_eq!(any.label(), "true");
+        assert!(any.runs_on(Event::Convert));
+        assert_eq!(config.notifiers[0].label(), "desktop");
+
+        assert!(Config::parse("[[notify]]\nname = \"nowhere\"").is_err());
+
+        assert!(Config::parse("[[hook]]\ncommand = \" \"").is_err());
+        assert!(Config::parse("[[hook]]\ncommand = \"x\"\non = [\"upload\"]").is_err());
+    }
+
+    #[test]
+    fn test_payload_json() {
+        let payload = Payload {
+            event: Event::Convert,
+            path: Path::new("out/a.flac"),
+            source: None,
+            track_id: Some(1),
+            bytes: 10,
+            title: Some("晴天".into()),
+            artist: None,
+            album: None,
+            duration_ms: 1000,
+        };
+        assert_eq!(
+            serde_json::to_string(&payload).unwrap(),
+            r#"{"event":"convert","path":"out/a.flac","track_id":1,"bytes":10,"title":"晴天","artist":null,"album":null,"duration_ms":1000}"#
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_reports_failures() {
+        let hooks = Hooks::new(vec![
+            Hook::command("read line && test \"$NCMDUMP_EVENT\" = download".into()),
+            Hook {
+                name: Some("convert only".into()),
+                command: "false".into(),
+                on: vec![Event::Convert],
+            },
+            Hook::command("exit 3".into()),
+        ]);
+        let payload = Payload {
+            event: Event::Download,
+            path: Path::new("a.mp3"),
+            source: None,
+            track_id: None,
+            bytes: 0,
+            title: None,
+            artist: None,
+            album: None,
+            duration_ms: 0,
+        };
+        let failed = hooks.run(&payload);
+        assert_eq!(failed.len(), 1, "{failed:?}");
+        assert_eq!(failed[0].0, "exit 3");
+    }
+}
@@ -0,0 +1,154 @@
+//! Localized CLI messages.
+//!
+//! Messages are [Fluent](https://projectfluent.org) resources compiled into
+//! the binary from `locales/<lang>.ftl`; English is the fallback for any
+//! message a translation does not define. Look messages up with [`tr!`].
+//! Log output (`-v`, `--log-file`) and `--help` text stay in English.
+
+use std::sync::OnceLock;
+
+use clap::ValueEnum;
+use fluent::concurrent::FluentBundle;
+use fluent::{FluentArgs, FluentResource};
+use unic_langid::LanguageIdentifier;
+
+const EN_US: &str = include_str!("../locales/en-US.ftl");
+const ZH_CN: &str = include_str!("../locales/zh-CN.ftl");
+
+/// Language of CLI output.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Lang {
+    /// English
+    #[value(name = "en", alias = "en-US")]
+    En,
+    /// Simplified Chinese
+    #[value(name = "zh-CN", alias = "zh")]
+    ZhCn,
+}
+
+impl Lang {
+    /// Detect the language from `LC_ALL`, `LC_MESSAGES` and `LANG`, in the
+    /// order POSIX gives them precedence.
+    pub fn from_env() -> Self {
+        let locale = ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .find_map(|var| std::env::var(var).ok().filter(|v| !v.is_empty()))
+            .unwrap_or_default();
+        Self::from_locale(&locale)
+    }
+
+    /// Map a POSIX locale such as `zh_CN.UTF-8` to a supported language.
+    fn from_locale(locale: &str) -> Self {
+        if locale.to_ascii_lowercase().starts_with("zh") {
+            Self::ZhCn
+        } else {
+            Self::En
+        }
+    }
+
+    fn resource(self) -> (&'static str, &'static str) {
+        match self {
+            Self::En => ("en-US", EN_US),
+            Self::ZhCn => ("zh-CN", ZH_CN),
+        }
+    }
+}
+
+/// Bundles to search in order: the selected language, then English.
+static BUNDLES: OnceLock<Vec<FluentBundle<FluentResource>>> = OnceLock::new();
+
+/// Select the output language. Only the first call has an effect; messages
+/// looked up before it are English.
+pub fn init(lang: Lang) {
+    BUNDLES.get_or_init(|| {
+        let mut bundles = vec![bundle(lang)];
+        if lang != Lang::En {
+            bundles.push(bundle(Lang::En));
+        }
+        bundles
+    });
+}
+
+fn bundle(lang: Lang) -> FluentBundle<FluentResource> {
+    let (id, source) = lang.resource();
+    let langid: LanguageIdentifier = id.parse().expect("valid language identifier");
+    // Both files are checked by the tests below, so these can't fail at runtime.
+    let resource = FluentResource::try_new(source.to_owned()).expect("valid Fluent syntax");
+    let mut bundle = FluentBundle::new_concurrent(vec![langid]);
+    // Unicode isolation marks around arguments show up as junk in terminals.
+    bundle.set_use_isolating(false);
+    bundle
+        .add_resource(resource)
+        .expect("no duplicate message IDs");
+    bundle
+}
+
+/// Format message `id` with `args`; use [`tr!`] instead of calling this.
+///
+/// Falls back to English, then to the ID itself if no bundle defines it.
+pub fn message(id: &str, args: Option<&FluentArgs>) -> String {
+    let bundles = BUNDLES.get_or_init(|| vec![bundle(Lang::En)]);
+    for bundle in bundles {
+        if let Some(pattern) = bundle.get_message(id).and_then(|m| m.value()) {
+            let mut errors = Vec::new();
+            let text = bundle.format_pattern(pattern, args, &mut errors);
+            if !errors.is_empty() {
+                tracing::debug!(id, ?errors, "message formatting errors");
+            }
+            return text.into_owned();
+        }
+    }
+    tracing::debug!(id, "missing message");
+    id.to_owned()
+}
+
+/// Look up a localized message: `tr!("id")` or `tr!("id", name = value, ...)`.
+///
+/// Argument values are passed through `to_string()`, so anything `Display`
+/// works (use `.display()` for paths).
+macro_rules! tr {
+    ($id:literal) => {
+        $crate::i18n::message($id, None)
+    };
+    ($id:literal, $($name:ident = $value:expr),+ $(,)?) => {{
+        let mut args = fluent::FluentArgs::new();
+        $(args.set(stringify!($name), ($value).to_string());)+
+        $crate::i18n::message($id, Some(&args))
+    }};
+}
+pub(crate) use tr;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// IDs of the top-level messages in an FTL file.
+    fn message_ids(source: &str) -> Vec<&str> {
+        let mut ids: Vec<&str> = source
+            .lines()
+            .filter_map(|l| l.split_once(" = ").map(|(id, _)| id))
+            .filter(|id| id.starts_with(|c: char| c.is_ascii_lowercase()))
+            .collect();
+        ids.sort_unstable();
+        ids
+    }
+
+    #[test]
+    fn test_locales_parse_and_match() {
+        // Building the bundles panics on syntax errors or duplicate IDs.
+        let (en, zh) = (bundle(Lang::En), bundle(Lang::ZhCn));
+        let ids = message_ids(EN_US);
+        assert_eq!(ids, message_ids(ZH_CN));
+        for id in ids {
+            assert!(en.has_message(id) && zh.has_message(id), "{id}");
+        }
+    }
+
+    #[test]
+    fn test_from_locale() {
+        assert!(Lang::from_locale("zh_CN.UTF-8") == Lang::ZhCn);
+        assert!(Lang::from_locale("zh_TW") == Lang::ZhCn);
+        assert!(Lang::from_locale("en_US.UTF-8") == Lang::En);
+        assert!(Lang::from_locale("C") == Lang::En);
+    }
+}
@@ -0,0 +1,336 @@
+//! Matching local audio files to Netease tracks (`import match`).
+//!
+//! A file that carries the official client's `163 key` comment is taken at
+//! its word. Otherwise candidates come from a Netease search for its tags
+//! (or its name); those within a few seconds of the file's duration are
+//! ranked by how well title and artist agree. When `fpcalc` is available,
+//! the best candidates are then checked by fingerprinting the start of their
+//! standard-quality stream and comparing it with the file's — a candidate
+//! that merely shares the name of a live version or cover does not pass.
+
+use std::io::Write as _;
+use std::path::Path;
+
+use anyhow::Result;
+use ncmdump::AudioTags;
+use netease_api::NeteaseClient;
+use netease_api::types::{Quality, SearchType, Track};
+
+use crate::fingerprint::{self, Fingerprint};
+
+/// Search results considered per file.
+const CANDIDATES: u64 = 10;
+
+/// Best-ranked candidates whose audio is fingerprinted.
+const FINGERPRINTED: usize = 3;
+
+/// Duration differences up to this many milliseconds cost nothing…
+const DURATION_SLACK_MS: u64 = 2_000;
+
+/// …and candidates further off than this are dropped.
+const DURATION_LIMIT_MS: u64 = 10_000;
+
+/// Metadata score from which a candidate is accepted without a fingerprint.
+const METADATA_THRESHOLD: f64 = 0.75;
+
+/// How a file was matched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Method {
+    /// Its embedded `163 key`.
+    Tag,
+    /// Its audio fingerprint.
+    Fingerprint,
+    /// Title, artist and duration only.
+    Metadata,
+}
+
+/// The track a file was identified as.
+#[derive(Debug)]
+pub struct Match {
+    pub track: Track,
+    pub method: Method,
+    /// Confidence from 0 to 1.
+    pub score: f64,
+}
+
+/// Identifies files against Netease.
+pub struct Matcher<'a> {
+    client: &'a NeteaseClient,
+    /// Whether to confirm candidates with `fpcalc`.
+    fingerprints: bool,
+}
+
+impl<'a> Matcher<'a> {
+    pub fn new(client: &'a NeteaseClient, fingerprints: bool) -> Self {
+        Self {
+            client,
+            fingerprints,
+        }
+    }
+
+    /// The Netease track the audio file at `path` holds, if one matches
+    /// well enough.
+    pub fn identify(&self, path: &Path) -> Result<Option<Match>> {
+        let tags = ncmdump::tag_read(path)?;
+        if let Some(id) = crate::embedded_track_id(&tags) {
+            return Ok(Some(Match {
+                track: self.client.track_detail(id)?,
+                method: Method::Tag,
+                score: 1.0,
+            }));
+        }
+
+        let wanted = Wanted::new(&tags, path);
+        let result = self
+            .client
+            .search(&wanted.query(), SearchType::Track, CANDIDATES, 0)?;
+        let mut ranked: Vec<(f64, Track)> = result
+            .tracks
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|t| Some((wanted.score(&t)?, t)))
+            .collect();
+        ranked.sort_by(|a, b| b.0.total_cmp(&a.0));
+        tracing::debug!(file = %path.display(), candidates = ranked.len(), "ranked candidates");
+
+        let local = self
+            .fingerprints
+            .then(|| fingerprint::compute(path))
+            .and_then(|r| {
+                r.inspect_err(
+                    |e| tracing::warn!(file = %path.display(), error = %e, "cannot fingerprint"),
+                )
+                .ok()
+            });
+        if let Some(local) = local {
+            let mut compared = false;
+            for (_, track) in ranked.iter().take(FINGERPRINTED) {
+                let remote = match self.remote_fingerprint(track.id) {
+                    Ok(fp) => fp,
+                    Err(e) => {
+                        tracing::debug!(track = track.id, error = %e, "cannot fingerprint candidate");
+                        continue;
+                    }
+                };
+                compared = true;
+                let score = fingerprint::similarity(&local.raw, &remote.raw);
+                tracing::debug!(track = track.id, score, "fingerprint compared");
+                if score >= fingerprint::MATCH_THRESHOLD {
+                    return Ok(Some(Match {
+                        track: track.clone(),
+                        method: Method::Fingerprint,
+                        score,
+                    }));
+                }
+            }
+            // The audio of the likeliest candidates is something else.
+            if compared {
+                return Ok(None);
+            }
+        }
+        Ok(ranked
+            .into_iter()
+            .next()
+            .filter(|(score, _)| *score >= METADATA_THRESHOLD)
+            .map(|(score, track)| Match {
+                track,
+                method: Method::Metadata,
+                score,
+            }))
+    }
+
+    /// Fingerprint of the start of track `id`, fetched at standard quality
+    /// into a temporary file.
+    fn remote_fingerprint(&self, id: u64) -> Result<Fingerprint> {
+        let file = self.client.track_file(id, Quality::Standard)?;
+        // Enough audio for the fingerprint, plus room for tags and a cover.
+        let bytes = file.bitrate / 8 * u64::from(fingerprint::LENGTH_SECS + 10) + 256 * 1024;
+        let mut stream = self
+            .client
+            .open_stream(&file.url, Some(&format!("bytes=0-{}", bytes - 1)))?;
+        let path = std::env::temp_dir().join(format!(
+            "ncmdump-fingerprint-{}-{id}.{}",
+            std::process::id(),
+            file.extension()
+        ));
+        let result = std::fs::File::create(&path)
+            .and_then(|mut f| std::io::copy(&mut stream, &mut f).and_then(|_| f.flush()))
+            .and_then(|()| fingerprint::compute(&path));
+        let _ = std::fs::remove_file(&path);
+        Ok(result?)
+    }
+}
+
+/// The API bitrate of the quality tier the audio file at `path` corresponds
+/// to, so library lookups can compare it with requested qualities: lossless
+/// for FLAC, else the highest MP3 tier its average bitrate reaches (with 10%
+/// slack for VBR). `None` if that cannot be told.
+pub fn quality_bitrate(path: &Path) -> Option<u64> {
+    if path
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("flac"))
+    {
+        return Some(Quality::Lossless.bitrate());
+    }
+    let duration_ms = ncmdump::tag_read(path).ok()?.duration_ms;
+    let size = std::fs::metadata(path).ok()?.len();
+    mp3_tier(size, duration_ms)
+}
+
+fn mp3_tier(size: u64, duration_ms: u64) -> Option<u64> {
+    let bps = size.checked_mul(8_000)?.checked_div(duration_ms)?;
+    [Quality::Exhigh, Quality::Higher, Quality::Standard]
+        .into_iter()
+        .map(Quality::bitrate)
+        .find(|&b| bps >= b / 10 * 9)
+}
+
+/// What a file says about itself.
+struct Wanted {
+    title: String,
+    artist: Option<String>,
+    duration_ms: u64,
+}
+
+impl Wanted {
+    /// From `tags`, falling back to an `Artist - Title` or `NN Title` file
+    /// name.
+    fn new(tags: &AudioTags, path: &Path) -> Self {
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        // A leading track number needs a separator: `1999` is a title.
+        let rest = stem.trim_start_matches(|c: char| c.is_ascii_digit());
+        let stem = match rest.strip_prefix([' ', '.', '-', '_']) {
+            Some(rest) if rest.len() + 1 < stem.len() => {
+                rest.trim_start_matches([' ', '.', '-', '_'])
+            }
+            _ => &stem,
+        };
+        let (stem_artist, stem_title) = match stem.split_once(" - ") {
+            Some((artist, title)) => (Some(artist.trim()), title.trim()),
+            None => (None, stem.trim()),
+        };
+        Self {
+            title: tags.title.clone().unwrap_or_else(|| stem_title.to_owned()),
+            artist: tags
+                .artist
+                .clone()
+                .or_else(|| stem_artist.map(String::from)),
+            duration_ms: tags.duration_ms,
+        }
+    }
+
+    fn query(&self) -> String {
+        match &self.artist {
+            Some(artist) => format!("{artist} {}", self.title),
+            None => self.title.clone(),
+        }
+    }
+
+    /// How well `track` fits, from 0 to 1: a weighted mean of title, artist
+    /// and duration agreement over what the file tells. `None` if the
+    /// durations are too far apart.
+    #[allow(clippy::cast_precision_loss)]
+    fn score(&self, track: &Track) -> Option<f64> {
+        let mut parts = vec![(0.5, similar(&self.title, &track.name))];
+        if let Some(artist) = &self.artist {
+            let names: Vec<&str> = track.artists.iter().map(|a| a.name.as_str()).collect();
+            parts.push((0.3, similar(artist, &names.join(" "))));
+        }
+        if self.duration_ms > 0 && track.duration_ms > 0 {
+            let diff = self.duration_ms.abs_diff(track.duration_ms);
+            if diff > DURATION_LIMIT_MS {
+                return None;
+            }
+            let over = diff.saturating_sub(DURATION_SLACK_MS) as f64;
+            parts.push((
+                0.2,
+                1.0 - over / (DURATION_LIMIT_MS - DURATION_SLACK_MS) as f64,
+            ));
+        }
+        let weight: f64 = parts.iter().map(|(w, _)| w).sum();
+        Some(parts.iter().map(|(w, s)| w * s).sum::<f64>() / weight)
+    }
+}
+
+/// Similarity of two names from 0 to 1, ignoring case, spacing and
+/// punctuation.
+pub(crate) fn similar(a: &str, b: &str) -> f64 {
+    let normalize = |s: &str| -> String {
+        s.chars()
+            .filter(|c| c.is_alphanumeric())
+            .flat_map(char::to_lowercase)
+            .collect()
+    };
+    strsim::normalized_levenshtein(&normalize(a), &normalize(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use netease_api::types::{Album, Artist};
+
+    fn track(name: &str, artist: &str, duration_ms: u64) -> Track {
+        Track {
+            id: 1,
+            name: name.into(),
+            artists: vec![Artist {
+                id: 1,
+                name: artist.into(),
+            }],
+            album: Album {
+                id: 1,
+                name: String::new(),
+                pic_url: None,
+            },
+            duration_ms,
+        }
+    }
+
+    #[test]
+    fn test_wanted_from_file_name() {
+        let tags = AudioTags::default();
+        let wanted = Wanted::new(&tags, Path::new("/m/03 - 周杰伦 - 晴天.mp3"));
+        assert_eq!(wanted.title, "晴天");
+        assert_eq!(wanted.artist.as_deref(), Some("周杰伦"));
+        assert_eq!(wanted.query(), "周杰伦 晴天");
+
+        let wanted = Wanted::new(&tags, Path::new("01. Yellow.flac"));
+        assert_eq!(wanted.title, "Yellow");
+        assert_eq!(wanted.artist, None);
+        assert_eq!(Wanted::new(&tags, Path::new("1999.mp3")).title, "1999");
+    }
+
+    #[test]
+    fn test_score() {
+        let wanted = Wanted {
+            title: "晴天".into(),
+            artist: Some("周杰伦".into()),
+            duration_ms: 269_000,
+        };
+        let exact = wanted.score(&track("晴天", "周杰伦", 270_000)).unwrap();
+        assert!((exact - 1.0).abs() < 1e-9, "{exact}");
+        let live = wanted
+            .score(&track("晴天 (Live)", "周杰伦", 275_000))
+            .unwrap();
+        assert!(live < exact && live > 0.5, "{live}");
+        let other = wanted.score(&track("稻香", "周杰伦", 270_000)).unwrap();
+        assert!(other < METADATA_THRESHOLD, "{other}");
+        assert_eq!(wanted.score(&track("晴天", "周杰伦", 300_000)), None);
+    }
+
+    #[test]
+    fn test_mp3_tier() {
+        // Four minutes at 320, ~300 (VBR), 192 and 96 kbit/s.
+        assert_eq!(mp3_tier(9_600_000, 240_000), Some(320_000));
+        assert_eq!(mp3_tier(9_000_000, 240_000), Some(320_000));
+        assert_eq!(mp3_tier(5_760_000, 240_000), Some(192_000));
+        assert_eq!(mp3_tier(2_880_000, 240_000), None);
+        assert_eq!(mp3_tier(1, 0), None);
+    }
+
+    #[test]
+    fn test_similar_ignores_case_and_punctuation() {
+        assert!((similar("Don't Stop", "dont stop") - 1.0).abs() < f64::EPSILON);
+        assert!(similar("Yellow", "Fix You") < 0.5);
+    }
+}
@@ -1,27 +1,129 @@
+use std::io::IsTerminal as _;
 use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::OnceLock;
 
 use anyhow::{Context, Result};
 use clap::{Parser, Subcommand, ValueEnum};
 use walkdir::WalkDir;
 
+mod backup;
+mod daemon;
+mod enrich;
+mod fingerprint;
+mod history;
+mod hooks;
+mod i18n;
+mod identify;
+mod notify;
+mod queue;
+mod rpc;
+mod scheduler;
+mod serve;
+mod sidecar;
+mod table;
+use i18n::tr;
+
 #[derive(Parser)]
 #[command(
     name = "ncmdump",
     version,
     about = "NCM decryptor & Netease/Bilibili Music CLI"
 )]
+// Independent global switches, not a state machine in disguise.
+#[allow(clippy::struct_excessive_bools)]
 struct Cli {
+    /// Proxy URL for API requests, e.g. `http://127.0.0.1:7890` or `socks5://127.0.0.1:1080`
+    /// (defaults to the `HTTPS_PROXY`/`ALL_PROXY` environment variables)
+    #[arg(long, global = true, value_name = "URL")]
+    proxy: Option<String>,
+    /// Increase log verbosity (-v info, -vv debug, -vvv trace)
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    verbose: u8,
+    /// Also write logs to this file (appended, info level or higher detail with -v)
+    #[arg(long, global = true, value_name = "PATH")]
+    log_file: Option<PathBuf>,
+    /// Log format; `json` writes one JSON object per line and implies -v.
+    /// `RUST_LOG` (e.g. `netease_api=debug`) overrides the level
+    #[arg(long, global = true, value_name = "FORMAT", default_value = "pretty")]
+    log_format: LogFormat,
+    /// Progress reporting for `dump` and downloads; `json` writes one JSON event per line to stderr
+    #[arg(long, global = true, value_name = "FORMAT", default_value = "text")]
+    progress: ProgressFormat,
+    /// Suppress per-file output of `dump`, `cache` and downloads; print only a final summary
+    #[arg(long, global = true)]
+    quiet: bool,
+    /// Don't consult or update the library database of converted and downloaded tracks
+    #[arg(long, global = true)]
+    no_history: bool,
+    /// Also run this shell command after each converted or downloaded file, with JSON
+    /// about it on stdin (repeatable; adds to the hooks in `hooks.toml`)
+    #[arg(long, global = true, value_name = "COMMAND")]
+    hook: Vec<String>,
+    /// Don't run any post-processing hooks
+    #[arg(long, global = true, conflicts_with = "hook")]
+    no_hooks: bool,
+    /// Also notify `desktop` or POST to this webhook URL when a batch command or daemon
+    /// job ends (repeatable; adds to the notifiers in `hooks.toml`)
+    #[arg(long, global = true, value_name = "TARGET", value_parser = notify::parse_target)]
+    notify: Vec<String>,
+    /// Don't send any completion notifications
+    #[arg(long, global = true, conflicts_with = "notify")]
+    no_notify: bool,
+    /// Limit the total download bandwidth, e.g. `500K` or `2M` (bytes per second)
+    #[arg(long, global = true, value_name = "RATE", value_parser = queue::parse_rate)]
+    limit_rate: Option<u64>,
+    /// Retry a failed track this many times in playlist, album and chart downloads
+    #[arg(long, global = true, value_name = "N", default_value = "2")]
+    retries: u32,
+    /// Output language (defaults to `LC_ALL` / `LC_MESSAGES` / `LANG`)
+    #[arg(long, global = true, value_name = "LANG")]
+    lang: Option<i18n::Lang>,
     #[command(subcommand)]
     command: Command,
 }
 
+/// Proxy from the global `--proxy` flag, set once in `main`.
+static PROXY: OnceLock<Option<String>> = OnceLock::new();
+
+/// Log format from the global `--log-format` flag, set once in `main`.
+static LOG_FORMAT: OnceLock<LogFormat> = OnceLock::new();
+
+/// Progress format from the global `--progress` flag, set once in `main`.
+static PROGRESS: OnceLock<ProgressFormat> = OnceLock::new();
+
+/// Whether the global `--quiet` flag was given, set once in `main`.
+static QUIET: OnceLock<bool> = OnceLock::new();
+
+/// Commands from the global `--hook` flag, set once in `main`; `None` with
+/// `--no-hooks`.
+static HOOK_COMMANDS: OnceLock<Option<Vec<String>>> = OnceLock::new();
+
+/// Targets from the global `--notify` flag, set once in `main`; `None` with
+/// `--no-notify`.
+static NOTIFY_TARGETS: OnceLock<Option<Vec<String>>> = OnceLock::new();
+
+/// Whether the global `--no-history` flag was given, set once in `main`.
+static NO_HISTORY: OnceLock<bool> = OnceLock::new();
+
+/// Bandwidth limit from the global `--limit-rate` flag, set once in `main`.
+static RATE_LIMIT: OnceLock<Option<queue::RateLimit>> = OnceLock::new();
+
+/// Retries from the global `--retries` flag, set once in `main`.
+static RETRIES: OnceLock<u32> = OnceLock::new();
+
 #[derive(Subcommand)]
 enum Command {
     /// Decrypt NCM files to MP3/FLAC
     Dump {
-        /// NCM files to convert
+        /// NCM files (or `.uc`/`.uc!` client cache files, QQ Music QMC or Kuwo KWM files) to
+        /// convert (glob patterns such as `Music/**/*.{ncm,NCM}` are expanded; `-` reads the list
+        /// from stdin)
         files: Vec<PathBuf>,
-        /// Process all NCM files in directory
+        /// Read NCM file paths from a file, newline- or NUL-separated (`-` for stdin)
+        #[arg(long, value_name = "PATH")]
+        files_from: Option<PathBuf>,
+        /// Process all NCM, client cache, QMC and KWM files in directory
         #[arg(short, long, value_name = "PATH")]
         directory: Option<PathBuf>,
         /// Recursive directory traversal (with -d)
@@ -30,18 +132,169 @@ enum Command {
         /// Output directory
         #[arg(short, long, value_name = "PATH")]
         output: Option<PathBuf>,
-        /// Remove source file after successful conversion
+        /// Remove source file after successful conversion (only once the output is verified)
         #[arg(short = 'm', long = "remove")]
         remove: bool,
+        /// List what would be converted, and to where, without writing anything
+        #[arg(short = 'n', long)]
+        dry_run: bool,
+        /// Name outputs from the NCM's embedded metadata instead of the source file name;
+        /// extension is added. Placeholders: {artist} {title} {album}, plus {track} {year}
+        /// when the header carries them
+        #[arg(short = 't', long, value_name = "TEMPLATE")]
+        name_template: Option<String>,
+        /// When an output file already exists: skip the input, or number the new file
+        /// (default: overwrite files from earlier runs; number outputs of this run, and
+        /// any with --remove)
+        #[arg(long, value_name = "MODE")]
+        on_collision: Option<Collision>,
+        /// Analyze the converted files' loudness and write ReplayGain tags (see `gain`)
+        #[arg(long)]
+        replaygain: bool,
+        /// Also write each NCM's embedded metadata, with all its fields, as JSON next to the
+        /// output (`<name>.json`)
+        #[arg(long)]
+        metadata_json: bool,
+        /// Scale embedded covers down to at most this many pixels per side, re-encoding them
+        /// as JPEG (PNG covers are converted even when smaller)
+        #[arg(long, value_name = "PX")]
+        cover_max_size: Option<u32>,
+        /// JPEG quality of re-encoded covers (1-100, with --cover-max-size)
+        #[arg(long, value_name = "Q", requires = "cover_max_size", value_parser = clap::value_parser!(u8).range(1..=100))]
+        cover_quality: Option<u8>,
+        /// Keep the NCM's `163 key(Don't modify):...` string in the output's comment tag,
+        /// as the official client does, so `import match` can match it back to the track
+        #[arg(long)]
+        keep_163_key: bool,
+        /// Write ID3v2.3 (UTF-16) instead of ID3v2.4 tags to MP3 outputs, for older players
+        #[arg(long)]
+        id3v23: bool,
+        /// Text encoding of MP3 tags (default: UTF-8 for ID3v2.4, UTF-16 for ID3v2.3, which
+        /// has no UTF-8)
+        #[arg(long, value_name = "ENCODING")]
+        id3_encoding: Option<Id3Encoding>,
+        /// What to do with tags the decrypted audio already carries (default: overwrite)
+        #[arg(long, value_name = "MODE")]
+        tag_mode: Option<TagMode>,
+    },
+    /// Convert the Netease client's `.uc`/`.uc!` cache into named, tagged MP3/FLAC
+    Cache {
+        /// Cache directory (scanned recursively)
+        dir: PathBuf,
+        /// Output directory
+        #[arg(short, long, value_name = "PATH", default_value = ".")]
+        output: PathBuf,
+        /// Name template without extension; placeholders: {artist} {title} {album},
+        /// plus {track} {year} when the header carries them
+        #[arg(short, long, default_value = ncmdump::template::DEFAULT_TEMPLATE)]
+        template: String,
+        /// Don't embed album covers
+        #[arg(long)]
+        no_cover: bool,
+    },
+    /// Show library statistics for a directory
+    Stats {
+        /// Directory to scan (recursively)
+        #[arg(default_value = ".")]
+        dir: PathBuf,
+    },
+    /// Rename MP3/FLAC files from their embedded tags
+    Rename {
+        /// Audio files or directories
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+        /// Name template without extension; placeholders: {artist} {title} {album} {track} {year}
+        #[arg(short, long, default_value = ncmdump::template::DEFAULT_TEMPLATE)]
+        template: String,
+        /// Recurse into directories
+        #[arg(short, long)]
+        recursive: bool,
+        /// Only show what would be renamed
+        #[arg(short = 'n', long)]
+        dry_run: bool,
+    },
+    /// Move or copy MP3/FLAC files into an `Artist/Album/NN Title.ext` library layout
+    Organize {
+        /// Directory with audio files (searched recursively)
+        dir: PathBuf,
+        /// Library root (defaults to DIR itself)
+        #[arg(short, long, value_name = "PATH")]
+        output: Option<PathBuf>,
+        /// Path template without extension; `{track}` is left out for files without a track number
+        #[arg(short, long, default_value = ORGANIZE_TEMPLATE)]
+        template: String,
+        /// Copy files instead of moving them
+        #[arg(long)]
+        copy: bool,
+        /// What to do when the target path is already taken
+        #[arg(long, value_name = "MODE", default_value = "skip")]
+        on_collision: Collision,
+        /// Also write missing `album.nfo`, `artist.nfo` and `cover.jpg` files for Jellyfin/Navidrome
+        #[arg(long)]
+        sidecars: bool,
+        /// Only show what would be done
+        #[arg(short = 'n', long)]
+        dry_run: bool,
+    },
+    /// Rewrite tags, cover and lyrics of MP3/FLAC files from the API
+    Retag {
+        /// Audio files (matched via their embedded 163 key, or by searching artist + title)
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+        /// Use this track ID instead of matching (single file only)
+        #[arg(long)]
+        id: Option<u64>,
+        /// Keep the existing cover
+        #[arg(long)]
+        no_cover: bool,
+        /// Keep the existing lyrics
+        #[arg(long)]
+        no_lyrics: bool,
+        /// Only show which track each file matches
+        #[arg(short = 'n', long)]
+        dry_run: bool,
+    },
+    /// Analyze the loudness of MP3/FLAC files and write ReplayGain tags
+    Gain {
+        /// Audio files or directories (searched recursively)
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+        /// Only write track gain (by default, files with the same album tag in one
+        /// directory also get album gain)
+        #[arg(long)]
+        no_album: bool,
+        /// Only show the measured gains
+        #[arg(short = 'n', long)]
+        dry_run: bool,
+    },
+    /// Add MusicBrainz IDs and album artist credits to tagged MP3/FLAC files
+    Enrich {
+        /// Audio files or directories (searched recursively)
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+        /// Also replace the cover with the Cover Art Archive front cover
+        #[arg(long)]
+        cover: bool,
+        /// Only show which release each file matches
+        #[arg(short = 'n', long)]
+        dry_run: bool,
+    },
+    /// Identify existing audio files and record them in the library database
+    Import {
+        #[command(subcommand)]
+        action: ImportAction,
     },
     /// Set login cookie (`MUSIC_U`)
     Login {
-        /// `MUSIC_U` cookie value
-        #[arg(required_unless_present = "check")]
+        /// `MUSIC_U` cookie value (omit with --encrypt to re-encrypt the saved session)
+        #[arg(required_unless_present_any = ["check", "encrypt"])]
         music_u: Option<String>,
         /// Check current login status
-        #[arg(long)]
+        #[arg(long, conflicts_with = "encrypt")]
         check: bool,
+        /// Encrypt the session file with a passphrase or this machine's ID
+        #[arg(long, value_name = "KEY")]
+        encrypt: Option<SessionEncryption>,
     },
     /// Clear saved session
     Logout,
@@ -52,38 +305,358 @@ enum Command {
         /// Search type
         #[arg(short = 't', long, default_value = "track")]
         r#type: SearchKind,
-        /// Max results
+        /// Max results per page
         #[arg(short, long, default_value = "20")]
         limit: u64,
+        /// Page number
+        #[arg(short, long, default_value = "1", conflicts_with_all = ["offset", "all"])]
+        page: u64,
+        /// Skip this many results instead of paging
+        #[arg(long, conflicts_with = "all")]
+        offset: Option<u64>,
+        /// Fetch every page, up to 1000 results
+        #[arg(long)]
+        all: bool,
+        /// Print only these track columns, aligned (e.g. id,artist,title)
+        #[arg(long, value_delimiter = ',', value_name = "FIELDS")]
+        fields: Vec<table::Field>,
+    },
+    /// Show trending searches
+    Hot {
+        /// Show detailed trending topics (score and description)
+        #[arg(short, long)]
+        detail: bool,
+        /// Max results
+        #[arg(short, long)]
+        limit: Option<usize>,
+        /// Print the raw result as JSON
+        #[arg(long)]
+        json: bool,
     },
     /// Show track details
     Info {
-        /// Track ID
-        track_id: u64,
+        /// Track ID or song URL
+        track_id: String,
+    },
+    /// Show which audio quality tiers of a track your account can download
+    Quality {
+        /// Track ID or song URL
+        track_id: String,
+        /// Print the tiers as JSON
+        #[arg(long)]
+        json: bool,
     },
     /// Get track lyrics
     Lyric {
-        /// Track ID
-        track_id: u64,
+        /// Track ID or song URL
+        track_id: String,
+        /// Strip LRC timestamps and print plain text
+        #[arg(long)]
+        plain: bool,
+        /// Shift all timestamps by this many milliseconds (negative = earlier)
+        #[arg(long, value_name = "MS", allow_hyphen_values = true)]
+        offset: Option<i64>,
+        /// Save the (processed) original lyrics to a file instead of printing
+        #[arg(short, long, value_name = "PATH")]
+        output: Option<PathBuf>,
+    },
+    /// Show comments for a track
+    Comments {
+        /// Track ID or song URL
+        track_id: String,
+        /// Show hot (most-liked) comments instead of the latest
+        #[arg(long)]
+        hot: bool,
+        /// Max results per page
+        #[arg(short, long, default_value = "20")]
+        limit: u64,
+        /// Page number
+        #[arg(short, long, default_value = "1")]
+        page: u64,
+        /// Print the raw result as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Show songs and artists similar to a track
+    Similar {
+        /// Seed track ID or song URL
+        track_id: String,
+        /// Max similar songs
+        #[arg(short, long, default_value = "20")]
+        limit: u64,
+        /// Download the similar songs
+        #[arg(long)]
+        download: bool,
+        /// Audio quality (with --download)
+        #[arg(short, long, default_value = "exhigh")]
+        quality: QualityArg,
+        /// Output directory (with --download)
+        #[arg(short, long, value_name = "PATH")]
+        output: Option<PathBuf>,
+        /// Skip tracks whose output file already exists (optionally checking size or MD5)
+        #[arg(long, value_name = "CHECK", num_args = 0..=1, default_missing_value = "exists")]
+        skip_existing: Option<SkipCheck>,
     },
-    /// Download a track
+    /// Download one or more tracks
     Download {
-        /// Track ID
-        track_id: u64,
+        /// Track IDs or song URLs
+        #[arg(required_unless_present = "from_file")]
+        track_ids: Vec<String>,
+        /// Audio quality
+        #[arg(short, long, default_value = "exhigh")]
+        quality: QualityArg,
+        /// Output file path (output directory with several tracks or --from-file)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Read track IDs or song URLs from a file, one per line (`-` for stdin)
+        #[arg(long, value_name = "PATH", conflicts_with = "track_ids")]
+        from_file: Option<PathBuf>,
+        /// Number of concurrent downloads (with several tracks or --from-file)
+        #[arg(short, long, visible_alias = "concurrency", default_value = "4")]
+        jobs: usize,
+        /// Convert downloads to this codec with ffmpeg (tags are preserved)
+        #[arg(long, value_name = "CODEC")]
+        transcode: Option<Codec>,
+        /// Bitrate for --transcode in kbit/s (default: per codec)
+        #[arg(long, value_name = "KBPS", requires = "transcode")]
+        bitrate: Option<u32>,
+        /// Skip tracks whose output file already exists (optionally checking size or MD5)
+        #[arg(long, value_name = "CHECK", num_args = 0..=1, default_missing_value = "exists")]
+        skip_existing: Option<SkipCheck>,
+    },
+    /// List or play personal-FM tracks (requires login)
+    Fm {
+        /// Keep playing FM tracks through an external player
+        #[arg(long)]
+        play: bool,
+        /// Like these tracks first (IDs or song URLs)
+        #[arg(long, value_name = "ID")]
+        like: Vec<String>,
+        /// Move these tracks to the FM trash first, so they aren't recommended again
+        #[arg(long, value_name = "ID")]
+        trash: Vec<String>,
+        /// Audio quality (with --play)
+        #[arg(short, long, default_value = "exhigh")]
+        quality: QualityArg,
+        /// Player command (with --play); the stream URL is appended (default: mpv, then ffplay)
+        #[arg(long, value_name = "CMD", env = "NCMDUMP_PLAYER")]
+        player: Option<String>,
+    },
+    /// Play a track (or personal FM) through an external player
+    Play {
+        /// Track ID or song URL
+        #[arg(required_unless_present = "fm")]
+        track_id: Option<String>,
+        /// Keep playing personal-FM tracks (requires login)
+        #[arg(long, conflicts_with = "track_id")]
+        fm: bool,
         /// Audio quality
         #[arg(short, long, default_value = "exhigh")]
         quality: QualityArg,
+        /// Player command; the stream URL is appended (default: mpv, then ffplay)
+        #[arg(long, value_name = "CMD", env = "NCMDUMP_PLAYER")]
+        player: Option<String>,
+    },
+    /// Download a music video
+    Mv {
+        /// MV ID or URL
+        mv_id: String,
+        /// Video resolution
+        #[arg(short, long, default_value = "1080")]
+        resolution: MvResolutionArg,
         /// Output file path
         #[arg(short, long)]
         output: Option<PathBuf>,
     },
+    /// Show album details
+    Album {
+        /// Album ID or URL
+        album_id: String,
+        /// Download all tracks
+        #[arg(long)]
+        download: bool,
+        /// Print only these track columns, aligned (e.g. id,artist,title)
+        #[arg(
+            long,
+            value_delimiter = ',',
+            value_name = "FIELDS",
+            conflicts_with = "download"
+        )]
+        fields: Vec<table::Field>,
+        /// Audio quality (with --download)
+        #[arg(short, long, default_value = "exhigh")]
+        quality: QualityArg,
+        /// Output directory (with --download)
+        #[arg(short, long, value_name = "PATH")]
+        output: Option<PathBuf>,
+        /// Skip tracks whose output file already exists (optionally checking size or MD5)
+        #[arg(long, value_name = "CHECK", num_args = 0..=1, default_missing_value = "exists")]
+        skip_existing: Option<SkipCheck>,
+        /// Number of concurrent downloads (with --download)
+        #[arg(short, long, visible_alias = "concurrency", default_value = "4")]
+        jobs: usize,
+    },
+    /// Show artist overview (top songs and recent albums)
+    Artist {
+        /// Artist ID or URL
+        artist_id: String,
+        /// Number of recent albums to show
+        #[arg(short, long, default_value = "10")]
+        albums: u64,
+    },
     /// Show playlist details
     Playlist {
-        /// Playlist ID
-        playlist_id: u64,
+        /// Playlist ID or URL
+        playlist_id: String,
+        /// Download all tracks
+        #[arg(long)]
+        download: bool,
+        /// Print only these track columns, aligned (e.g. id,artist,title)
+        #[arg(
+            long,
+            value_delimiter = ',',
+            value_name = "FIELDS",
+            conflicts_with = "download"
+        )]
+        fields: Vec<table::Field>,
+        /// Audio quality (with --download)
+        #[arg(short, long, default_value = "exhigh")]
+        quality: QualityArg,
+        /// Output directory (with --download)
+        #[arg(short, long, value_name = "PATH")]
+        output: Option<PathBuf>,
+        /// Skip tracks whose output file already exists (optionally checking size or MD5)
+        #[arg(long, value_name = "CHECK", num_args = 0..=1, default_missing_value = "exists")]
+        skip_existing: Option<SkipCheck>,
+        /// Number of concurrent downloads (with --download)
+        #[arg(short, long, visible_alias = "concurrency", default_value = "4")]
+        jobs: usize,
+    },
+    /// Browse and archive DJ radio programs
+    Podcast {
+        #[command(subcommand)]
+        action: PodcastAction,
+    },
+    /// List official charts, or show/download a chart's entries
+    Toplist {
+        /// Chart name (e.g. 飙升榜) or ID
+        #[arg(short, long)]
+        chart: Option<String>,
+        /// Download the chart entries
+        #[arg(long, requires = "chart")]
+        download: bool,
+        /// Only show/download the top N entries
+        #[arg(short, long)]
+        limit: Option<usize>,
+        /// Audio quality (with --download)
+        #[arg(short, long, default_value = "exhigh")]
+        quality: QualityArg,
+        /// Output directory (with --download)
+        #[arg(short, long, value_name = "PATH")]
+        output: Option<PathBuf>,
+        /// Skip tracks whose output file already exists (optionally checking size or MD5)
+        #[arg(long, value_name = "CHECK", num_args = 0..=1, default_missing_value = "exists")]
+        skip_existing: Option<SkipCheck>,
+        /// Number of concurrent downloads (with --download)
+        #[arg(short, long, visible_alias = "concurrency", default_value = "4")]
+        jobs: usize,
+    },
+    /// Open a track/album/playlist/artist/MV page in the browser
+    Open {
+        /// ID, or a music.163.com URL (its type is detected from the URL)
+        target: String,
+        /// What kind of ID `target` is
+        #[arg(short = 't', long, default_value = "song")]
+        r#type: ResourceKind,
+        /// Print the canonical URL instead of opening it
+        #[arg(long)]
+        print: bool,
+    },
+    /// Check configuration, session, network and permissions
+    Doctor,
+    /// Do the daily sign-in and print the points earned (requires login)
+    Signin {
+        /// Only sign in on this platform (default: both)
+        #[arg(long)]
+        platform: Option<SigninPlatformArg>,
+    },
+    /// Show current user info and VIP status
+    Me {
+        /// List created and subscribed playlists
+        #[arg(long)]
+        playlists: bool,
+        /// Show the number of liked songs
+        #[arg(long)]
+        likes: bool,
+        /// Show the listening level and progress to the next
+        #[arg(long)]
+        level: bool,
+    },
+    /// Show your most played songs of the last week (requires login)
+    Record {
+        /// Show the all-time ranking instead of the last week
+        #[arg(long)]
+        all: bool,
+        /// Only show the top N songs
+        #[arg(short, long)]
+        limit: Option<usize>,
+        /// Print the records as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Manage the Netease cloud drive
+    Cloud {
+        #[command(subcommand)]
+        action: CloudAction,
+    },
+    /// Save playlists with their tracks, liked songs, followed artists and podcasts
+    /// and the cloud drive listing to a JSON snapshot (requires login)
+    #[command(args_conflicts_with_subcommands = true)]
+    Backup {
+        #[command(subcommand)]
+        action: Option<BackupAction>,
+        /// Snapshot file (default: `ncmdump-backup-<user id>-<time>.json`)
+        #[arg(short, long, value_name = "PATH")]
+        output: Option<PathBuf>,
+    },
+    /// Run scheduled download/convert jobs from the daemon config
+    Daemon {
+        /// Job config file (default: `<config dir>/ncmdump/daemon.toml`)
+        #[arg(long, global = true, value_name = "PATH")]
+        config: Option<PathBuf>,
+        #[command(subcommand)]
+        action: DaemonAction,
+    },
+    /// Serve a music directory over HTTP, decrypting NCM files on the fly
+    Serve {
+        /// Directory to serve
+        dir: PathBuf,
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1:8163")]
+        listen: String,
+        /// Default quality of proxied Netease tracks (`/track/<id>`)
+        #[arg(short, long, default_value = "exhigh")]
+        quality: QualityArg,
+        /// User name Subsonic clients must log in with (any login is accepted without it)
+        #[arg(long, value_name = "USER", requires = "subsonic_password")]
+        subsonic_user: Option<String>,
+        /// Password for --subsonic-user
+        #[arg(
+            long,
+            value_name = "PASSWORD",
+            env = "NCMDUMP_SUBSONIC_PASSWORD",
+            hide_env_values = true,
+            requires = "subsonic_user"
+        )]
+        subsonic_password: Option<String>,
+    },
+    /// Serve JSON-RPC on a local socket for GUI front-ends and editor plugins
+    Rpc {
+        /// Socket path, or loopback `HOST:PORT` [default: `rpc.sock` in the config directory;
+        /// 127.0.0.1:8164 on Windows]
+        #[arg(long, value_name = "ADDR", value_parser = rpc::parse_listen)]
+        listen: Option<rpc::Listen>,
     },
-    /// Show current user info
-    Me,
 
     // ── Bilibili commands ──
     /// Bilibili QR code login
@@ -131,6 +704,200 @@ enum Command {
     BiliMe,
 }
 
+#[derive(Subcommand)]
+enum ImportAction {
+    /// Match MP3/FLAC files to Netease tracks by audio fingerprint, duration and tags
+    Match {
+        /// Audio files or directories (searched recursively)
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+        /// Only show what each file matches, without recording it
+        #[arg(short = 'n', long)]
+        dry_run: bool,
+        /// Match by tags and duration only, without fingerprinting (`fpcalc`)
+        #[arg(long)]
+        no_fingerprint: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum CloudAction {
+    /// List tracks in the cloud drive
+    List {
+        /// Max results
+        #[arg(short, long, default_value = "30")]
+        limit: u64,
+        /// Pagination offset
+        #[arg(long, default_value = "0")]
+        offset: u64,
+    },
+    /// Upload audio files to the cloud drive
+    Upload {
+        /// Audio files to upload
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+    },
+    /// Delete tracks from the cloud drive
+    Delete {
+        /// Cloud song IDs
+        #[arg(required = true)]
+        ids: Vec<u64>,
+    },
+}
+
+#[derive(Subcommand)]
+enum BackupAction {
+    /// Show what changed between two snapshots
+    Diff {
+        /// The older snapshot
+        old: PathBuf,
+        /// The newer snapshot
+        new: PathBuf,
+    },
+}
+
+#[derive(Clone, Copy, Subcommand)]
+enum DaemonAction {
+    /// Run jobs when they are due, until interrupted
+    Run {
+        /// Run every job once now and exit
+        #[arg(long)]
+        once: bool,
+    },
+    /// Show each job's schedule, last run and next run
+    Status,
+}
+
+#[derive(Subcommand)]
+enum PodcastAction {
+    /// Show radio details
+    Info {
+        /// Radio ID
+        rid: u64,
+    },
+    /// List radio episodes (newest first)
+    Episodes {
+        /// Radio ID
+        rid: u64,
+        /// Max results per page
+        #[arg(short, long, default_value = "30")]
+        limit: u64,
+        /// Page number
+        #[arg(short, long, default_value = "1")]
+        page: u64,
+        /// Oldest first
+        #[arg(long)]
+        asc: bool,
+    },
+    /// Download radio episodes
+    Download {
+        /// Radio ID
+        rid: u64,
+        /// Only download the N most recent episodes
+        #[arg(long, value_name = "N")]
+        latest: Option<u64>,
+        /// Audio quality
+        #[arg(short, long, default_value = "exhigh")]
+        quality: QualityArg,
+        /// Output directory
+        #[arg(short, long, value_name = "PATH")]
+        output: Option<PathBuf>,
+        /// Skip episodes whose output file already exists (optionally checking size or MD5)
+        #[arg(long, value_name = "CHECK", num_args = 0..=1, default_missing_value = "exists")]
+        skip_existing: Option<SkipCheck>,
+    },
+}
+
+/// Key for `login --encrypt`.
+#[derive(Clone, Copy, ValueEnum)]
+enum SessionEncryption {
+    /// A passphrase, asked for when the session is loaded
+    Passphrase,
+    /// This machine's ID; the file can't be opened elsewhere
+    Machine,
+    /// Store the session unencrypted
+    None,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum LogFormat {
+    /// Human-readable lines
+    Pretty,
+    /// One JSON object per line, for log collectors
+    Json,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ProgressFormat {
+    /// Human-readable output only
+    Text,
+    /// Also emit JSON-lines events on stderr
+    Json,
+}
+
+/// Target codec for `download --transcode`.
+#[derive(Clone, Copy, ValueEnum)]
+enum Codec {
+    /// Opus 160 kbit/s (`.opus`)
+    Opus,
+    /// MP3 VBR V0 (`.mp3`)
+    Mp3,
+    /// AAC 256 kbit/s (`.m4a`)
+    Aac,
+}
+
+impl From<Codec> for ncmdump::transcode::Codec {
+    fn from(c: Codec) -> Self {
+        match c {
+            Codec::Opus => Self::Opus,
+            Codec::Mp3 => Self::Mp3,
+            Codec::Aac => Self::Aac,
+        }
+    }
+}
+
+/// How `--skip-existing` decides that an output file is already complete.
+#[derive(Clone, Copy, ValueEnum)]
+enum SkipCheck {
+    /// The file exists
+    Exists,
+    /// The file size matches the server's
+    Size,
+    /// The file MD5 matches the server's
+    Hash,
+}
+
+/// How `organize` and `dump` handle a target path that is already taken.
+#[derive(Clone, Copy, ValueEnum)]
+enum Collision {
+    /// Leave the file alone
+    Skip,
+    /// Append ` (2)`, ` (3)`, ... to the file name
+    Number,
+}
+
+/// What `dump` does with tags already in the decrypted audio.
+#[derive(Clone, Copy, ValueEnum)]
+enum TagMode {
+    /// Replace them with the NCM metadata
+    Overwrite,
+    /// Keep them, only adding missing fields and cover
+    FillMissing,
+    /// Leave files that already carry tags untouched
+    Skip,
+}
+
+/// Text encoding of the ID3 tags `dump` writes.
+#[derive(Clone, Copy, ValueEnum)]
+enum Id3Encoding {
+    /// UTF-8 (UTF-16 in ID3v2.3)
+    Utf8,
+    /// UTF-16 with a byte order mark
+    Utf16,
+    /// ISO-8859-1; other characters become `?`
+    Latin1,
+}
+
 #[derive(Clone, ValueEnum)]
 enum SearchKind {
     Track,
@@ -145,6 +912,73 @@ enum QualityArg {
     Higher,
     Exhigh,
     Lossless,
+    Hires,
+}
+
+#[derive(Clone, ValueEnum)]
+enum MvResolutionArg {
+    #[value(name = "240")]
+    R240,
+    #[value(name = "480")]
+    R480,
+    #[value(name = "720")]
+    R720,
+    #[value(name = "1080")]
+    R1080,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum SigninPlatformArg {
+    Mobile,
+    Web,
+}
+
+impl From<SigninPlatformArg> for netease_api::types::SigninPlatform {
+    fn from(p: SigninPlatformArg) -> Self {
+        match p {
+            SigninPlatformArg::Mobile => Self::Mobile,
+            SigninPlatformArg::Web => Self::Web,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ResourceKind {
+    Song,
+    Album,
+    Playlist,
+    Artist,
+    Mv,
+}
+
+impl ResourceKind {
+    /// Path segment used by music.163.com pages, e.g. `song` in `#/song?id=1`.
+    fn path(self) -> &'static str {
+        match self {
+            Self::Song => "song",
+            Self::Album => "album",
+            Self::Playlist => "playlist",
+            Self::Artist => "artist",
+            Self::Mv => "mv",
+        }
+    }
+
+    fn from_path(segment: &str) -> Option<Self> {
+        [
+            Self::Song,
+            Self::Album,
+            Self::Playlist,
+            Self::Artist,
+            Self::Mv,
+        ]
+        .into_iter()
+        .find(|k| k.path() == segment)
+    }
+
+    /// Canonical web page for `id`.
+    fn page_url(self, id: u64) -> String {
+        format!("https://music.163.com/#/{}?id={id}", self.path())
+    }
 }
 
 #[derive(Clone, ValueEnum)]
@@ -171,6 +1005,18 @@ impl From<QualityArg> for netease_api::types::Quality {
             QualityArg::Higher => Self::Higher,
             QualityArg::Exhigh => Self::Exhigh,
             QualityArg::Lossless => Self::Lossless,
+            QualityArg::Hires => Self::Hires,
+        }
+    }
+}
+
+impl From<MvResolutionArg> for netease_api::types::MvResolution {
+    fn from(r: MvResolutionArg) -> Self {
+        match r {
+            MvResolutionArg::R240 => Self::R240,
+            MvResolutionArg::R480 => Self::R480,
+            MvResolutionArg::R720 => Self::R720,
+            MvResolutionArg::R1080 => Self::R1080,
         }
     }
 }
@@ -184,261 +1030,4674 @@ impl From<BiliFormatArg> for bilibili_api::types::AudioFormat {
     }
 }
 
-fn main() -> Result<()> {
-    let cli = Cli::parse();
-    match cli.command {
+fn main() -> ExitCode {
+    let cli = match Cli::try_parse() {
+        Ok(cli) => cli,
+        Err(e) => {
+            let _ = e.print();
+            // `--help` and `--version` are reported as "errors" by clap too.
+            return if e.use_stderr() {
+                Exit::Input.into()
+            } else {
+                ExitCode::SUCCESS
+            };
+        }
+    };
+    i18n::init(cli.lang.unwrap_or_else(i18n::Lang::from_env));
+    PROXY.get_or_init(|| cli.proxy.clone());
+    LOG_FORMAT.get_or_init(|| cli.log_format);
+    PROGRESS.get_or_init(|| cli.progress);
+    QUIET.get_or_init(|| cli.quiet);
+    NO_HISTORY.get_or_init(|| cli.no_history);
+    RATE_LIMIT.get_or_init(|| cli.limit_rate.map(queue::RateLimit::new));
+    RETRIES.get_or_init(|| cli.retries);
+    HOOK_COMMANDS.get_or_init(|| (!cli.no_hooks).then(|| cli.hook.clone()));
+    NOTIFY_TARGETS.get_or_init(|| (!cli.no_notify).then(|| cli.notify.clone()));
+    let operation = notify_operation(&cli.command);
+    let start = std::time::Instant::now();
+    let result = init_logging(cli.verbose, cli.log_format, cli.log_file.as_deref())
+        .and_then(|()| run(cli.command));
+    if let Some(operation) = operation {
+        let error = result.as_ref().err().map(|e| format!("{e:#}"));
+        send_notification(operation.into(), error, start.elapsed());
+    }
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            tracing::error!(error = format!("{e:#}"), "command failed");
+            report(tr!("error", error = format!("{e:?}")));
+            Exit::classify(&e).into()
+        }
+    }
+}
+
+// A flat dispatch table: one arm per subcommand, nothing to factor out.
+#[allow(clippy::too_many_lines)]
+fn run(command: Command) -> Result<()> {
+    match command {
         Command::Dump {
             files,
+            files_from,
             directory,
             recursive,
             output,
             remove,
-        } => cmd_dump(
-            files,
-            directory.as_ref(),
+            dry_run,
+            name_template,
+            on_collision,
+            replaygain,
+            metadata_json,
+            cover_max_size,
+            cover_quality,
+            keep_163_key,
+            id3v23,
+            id3_encoding,
+            tag_mode,
+        } => {
+            let naming = Naming {
+                template: name_template.as_deref(),
+                collision: on_collision,
+                keep_existing: remove,
+                ..Naming::default()
+            };
+            let jobs = dump_jobs(
+                files,
+                files_from.as_deref(),
+                directory.as_ref(),
+                recursive,
+                output.as_ref(),
+            )?;
+            let opts = ncmdump::ConvertOptions {
+                tags: tag_options(
+                    cover_max_size,
+                    cover_quality,
+                    keep_163_key,
+                    id3v23,
+                    id3_encoding,
+                    tag_mode,
+                ),
+                metadata_json,
+            };
+            cmd_dump(&jobs, naming, remove, replaygain, &opts, dry_run)
+        }
+        Command::Cache {
+            dir,
+            output,
+            template,
+            no_cover,
+        } => cmd_cache(&dir, &output, &template, !no_cover),
+        Command::Stats { dir } => cmd_stats(&dir),
+        Command::Rename {
+            paths,
+            template,
             recursive,
-            output.as_ref(),
-            remove,
+            dry_run,
+        } => cmd_rename(&paths, &template, recursive, dry_run),
+        Command::Organize {
+            dir,
+            output,
+            template,
+            copy,
+            on_collision,
+            sidecars,
+            dry_run,
+        } => cmd_organize(
+            &dir,
+            output.as_deref(),
+            &template,
+            copy,
+            on_collision,
+            sidecars,
+            dry_run,
         ),
-        Command::Login { music_u, check } => cmd_login(music_u, check),
+        Command::Retag {
+            files,
+            id,
+            no_cover,
+            no_lyrics,
+            dry_run,
+        } => cmd_retag(&files, id, !no_cover, !no_lyrics, dry_run),
+        Command::Gain {
+            paths,
+            no_album,
+            dry_run,
+        } => {
+            let files: Vec<PathBuf> = paths
+                .iter()
+                .flat_map(|path| {
+                    WalkDir::new(path)
+                        .into_iter()
+                        .filter_map(std::result::Result::ok)
+                        .filter(|e| e.file_type().is_file() && has_audio_extension(e.path()))
+                        .map(walkdir::DirEntry::into_path)
+                })
+                .collect();
+            tag_replay_gain(&files, !no_album, dry_run)
+        }
+        Command::Enrich {
+            paths,
+            cover,
+            dry_run,
+        } => cmd_enrich(&paths, cover, dry_run),
+        Command::Import {
+            action:
+                ImportAction::Match {
+                    paths,
+                    dry_run,
+                    no_fingerprint,
+                },
+        } => cmd_import_match(&paths, dry_run, !no_fingerprint),
+        Command::Login {
+            music_u,
+            check,
+            encrypt,
+        } => cmd_login(music_u, check, encrypt),
         Command::Logout => cmd_logout(),
         Command::Search {
             keyword,
             r#type,
             limit,
-        } => cmd_search(&keyword, r#type, limit),
-        Command::Info { track_id } => cmd_info(track_id),
-        Command::Lyric { track_id } => cmd_lyric(track_id),
-        Command::Download {
+            page,
+            offset,
+            all,
+            fields,
+        } => {
+            let offset = offset.unwrap_or(page.saturating_sub(1) * limit);
+            cmd_search(&keyword, r#type, limit, offset, all, &fields)
+        }
+        Command::Hot {
+            detail,
+            limit,
+            json,
+        } => cmd_hot(detail, limit, json),
+        Command::Info { track_id } => cmd_info(parse_ref(&track_id, ResourceKind::Song)?),
+        Command::Quality { track_id, json } => {
+            cmd_quality(parse_ref(&track_id, ResourceKind::Song)?, json)
+        }
+        Command::Lyric {
             track_id,
-            quality,
+            plain,
+            offset,
             output,
-        } => cmd_download(track_id, quality, output),
-        Command::Playlist { playlist_id } => cmd_playlist(playlist_id),
-        Command::Me => cmd_me(),
-
-        // ── Bilibili ──
-        Command::BiliLogin { check } => cmd_bili_login(check),
-        Command::BiliLogout => cmd_bili_logout(),
-        Command::BiliSearch {
-            keyword,
+        } => {
+            let track_id = parse_ref(&track_id, ResourceKind::Song)?;
+            cmd_lyric(track_id, plain, offset, output.as_deref())
+        }
+        Command::Comments {
+            track_id,
+            hot,
             limit,
             page,
-        } => cmd_bili_search(&keyword, limit, page),
-        Command::BiliInfo { bvid } => cmd_bili_info(&bvid),
-        Command::BiliDownload {
-            bvid,
-            format,
+            json,
+        } => {
+            let track_id = parse_ref(&track_id, ResourceKind::Song)?;
+            cmd_comments(track_id, hot, limit, page, json)
+        }
+        Command::Similar {
+            track_id,
+            limit,
+            download,
+            quality,
             output,
-        } => cmd_bili_download(&bvid, format, output),
-        Command::BiliMe => cmd_bili_me(),
-    }
-}
-
-// ── dump ──
-
-fn cmd_dump(
-    mut files: Vec<PathBuf>,
+            skip_existing,
+        } => {
+            let track_id = parse_ref(&track_id, ResourceKind::Song)?;
+            cmd_similar(track_id, limit, download, quality, output, skip_existing)
+        }
+        Command::Download {
+            track_ids,
+            quality,
+            output,
+            from_file,
+            jobs,
+            skip_existing,
+            transcode,
+            bitrate,
+        } => {
+            let transcode = transcode.map(|codec| ncmdump::transcode::Target {
+                codec: codec.into(),
+                bitrate,
+            });
+            match (track_ids.as_slice(), from_file) {
+                (_, Some(list)) => {
+                    let entries = read_track_list(&list)?;
+                    cmd_download_batch(&entries, quality, output, jobs, skip_existing, transcode)
+                }
+                ([id], None) => {
+                    let id = parse_ref(id, ResourceKind::Song)?;
+                    cmd_download(id, quality, output, skip_existing, transcode)
+                }
+                (ids, None) => {
+                    cmd_download_batch(ids, quality, output, jobs, skip_existing, transcode)
+                }
+            }
+        }
+        Command::Fm {
+            play,
+            like,
+            trash,
+            quality,
+            player,
+        } => cmd_fm(play, &like, &trash, quality, player.as_deref()),
+        Command::Play {
+            track_id,
+            fm,
+            quality,
+            player,
+        } => {
+            let track_id = track_id
+                .map(|id| parse_ref(&id, ResourceKind::Song))
+                .transpose()?;
+            cmd_play(track_id, fm, quality, player.as_deref())
+        }
+        Command::Mv {
+            mv_id,
+            resolution,
+            output,
+        } => cmd_mv(parse_ref(&mv_id, ResourceKind::Mv)?, resolution, output),
+        Command::Album {
+            album_id,
+            download,
+            fields,
+            quality,
+            output,
+            skip_existing,
+            jobs,
+        } => {
+            let album_id = parse_ref(&album_id, ResourceKind::Album)?;
+            cmd_album(
+                album_id,
+                download,
+                &fields,
+                quality,
+                output,
+                skip_existing,
+                jobs,
+            )
+        }
+        Command::Artist { artist_id, albums } => {
+            cmd_artist(parse_ref(&artist_id, ResourceKind::Artist)?, albums)
+        }
+        Command::Playlist {
+            playlist_id,
+            download,
+            fields,
+            quality,
+            output,
+            skip_existing,
+            jobs,
+        } => {
+            let playlist_id = parse_ref(&playlist_id, ResourceKind::Playlist)?;
+            cmd_playlist(
+                playlist_id,
+                download,
+                &fields,
+                quality,
+                output,
+                skip_existing,
+                jobs,
+            )
+        }
+        Command::Toplist {
+            chart,
+            download,
+            limit,
+            quality,
+            output,
+            skip_existing,
+            jobs,
+        } => cmd_toplist(
+            chart.as_deref(),
+            download,
+            limit,
+            quality,
+            output,
+            skip_existing,
+            jobs,
+        ),
+        Command::Podcast { action } => cmd_podcast(action),
+        Command::Open {
+            target,
+            r#type,
+            print,
+        } => cmd_open(&target, r#type, print),
+        Command::Doctor => cmd_doctor(),
+        Command::Signin { platform } => cmd_signin(platform),
+        Command::Me {
+            playlists,
+            likes,
+            level,
+        } => cmd_me(playlists, likes, level),
+        Command::Record { all, limit, json } => cmd_record(all, limit, json),
+        Command::Cloud { action } => cmd_cloud(action),
+        Command::Backup { action, output } => match action {
+            None => cmd_backup(output),
+            Some(BackupAction::Diff { old, new }) => cmd_backup_diff(&old, &new),
+        },
+        Command::Daemon { config, action } => cmd_daemon(config, action),
+        Command::Serve {
+            dir,
+            listen,
+            quality,
+            subsonic_user,
+            subsonic_password,
+        } => {
+            let credentials = subsonic_user
+                .zip(subsonic_password)
+                .map(|(user, password)| serve::Credentials { user, password });
+            cmd_serve(&dir, &listen, quality.into(), credentials)
+        }
+        Command::Rpc { listen } => cmd_rpc(listen),
+
+        // ── Bilibili ──
+        Command::BiliLogin { check } => cmd_bili_login(check),
+        Command::BiliLogout => cmd_bili_logout(),
+        Command::BiliSearch {
+            keyword,
+            limit,
+            page,
+        } => cmd_bili_search(&keyword, limit, page),
+        Command::BiliInfo { bvid } => cmd_bili_info(&bvid),
+        Command::BiliDownload {
+            bvid,
+            format,
+            output,
+        } => cmd_bili_download(&bvid, format, output),
+        Command::BiliMe => cmd_bili_me(),
+    }
+}
+
+// ── exit codes ──
+
+/// Process exit status, so scripts can branch on what went wrong.
+///
+/// Keep in sync with the table in `docs/netease-api.md`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Exit {
+    /// Unclassified failure, or some items of a batch failed
+    Failure = 1,
+    /// Not logged in, or the session has expired
+    Auth = 2,
+    /// Connection, TLS, timeout or HTTP status error
+    Network = 3,
+    /// Invalid arguments, IDs, URLs, templates or input files
+    Input = 4,
+    /// The API rejected the request or returned an unexpected response
+    Api = 5,
+    /// Reading or writing local files failed
+    Io = 6,
+    /// ffmpeg or another external tool failed
+    Tool = 7,
+    /// The operation was cancelled, as with Ctrl-C
+    Cancelled = 130,
+}
+
+impl Exit {
+    /// Pick the status for `err` from the first typed error in its chain.
+    fn classify(err: &anyhow::Error) -> Self {
+        use bilibili_api::BilibiliError as Bili;
+        use ncmdump::NcmError as Ncm;
+        use netease_api::NeteaseError as Netease;
+
+        for cause in err.chain() {
+            if cause.is::<InputError>() || cause.is::<std::num::ParseIntError>() {
+                return Self::Input;
+            }
+            if let Some(e) = cause.downcast_ref::<Netease>() {
+                return match e {
+                    Netease::NotLoggedIn
+                    | Netease::SessionLocked
+                    | Netease::SessionDecrypt
+                    | Netease::Api { code: 301, .. } => Self::Auth,
+                    Netease::Http(_) => Self::Network,
+                    Netease::Api { .. } | Netease::Json(_) => Self::Api,
+                    Netease::Io(_) => Self::Io,
+                    Netease::Other(_) => Self::Failure,
+                };
+            }
+            if let Some(e) = cause.downcast_ref::<Bili>() {
+                return match e {
+                    Bili::NotLoggedIn | Bili::QrLogin(_) | Bili::Api { code: -101, .. } => {
+                        Self::Auth
+                    }
+                    Bili::Http(_) => Self::Network,
+                    Bili::Api { .. } | Bili::Json(_) => Self::Api,
+                    Bili::Io(_) => Self::Io,
+                    Bili::Ffmpeg(_) => Self::Tool,
+                    Bili::Other(_) => Self::Failure,
+                };
+            }
+            if let Some(e) = cause.downcast_ref::<Ncm>() {
+                return match e {
+                    Ncm::Io(_) => Self::Io,
+                    Ncm::Tag(_) => Self::Failure,
+                    Ncm::Transcode(_) => Self::Tool,
+                    Ncm::Cancelled => Self::Cancelled,
+                    _ => Self::Input,
+                };
+            }
+            if cause.is::<std::io::Error>() {
+                return Self::Io;
+            }
+        }
+        Self::Failure
+    }
+}
+
+impl From<Exit> for ExitCode {
+    fn from(exit: Exit) -> Self {
+        Self::from(exit as u8)
+    }
+}
+
+/// An error caused by invalid user input ([`Exit::Input`]).
+#[derive(Debug)]
+struct InputError(String);
+
+impl std::fmt::Display for InputError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for InputError {}
+
+// ── logging ──
+
+/// Install the tracing subscriber for the CLI and the library crates.
+///
+/// Without `-v` nothing is logged to stderr, so normal output is unchanged;
+/// `--log-format json` logs `info` and up by default, for daemon and server
+/// runs. `RUST_LOG` directives replace the level set by `-v`. The log file,
+/// when given, records at least `info` so failures in batch runs can be
+/// inspected afterwards.
+fn init_logging(verbose: u8, format: LogFormat, log_file: Option<&std::path::Path>) -> Result<()> {
+    use tracing_subscriber::filter::LevelFilter;
+    use tracing_subscriber::prelude::*;
+
+    let level = match verbose {
+        0 if format == LogFormat::Json => LevelFilter::INFO,
+        0 => LevelFilter::OFF,
+        1 => LevelFilter::INFO,
+        2 => LevelFilter::DEBUG,
+        _ => LevelFilter::TRACE,
+    };
+    let ansi = std::io::stderr().is_terminal();
+    let mut layers = vec![log_layer(format, std::io::stderr, ansi, level)];
+
+    if let Some(path) = log_file {
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| tr!("log-open-failed", path = path.display()))?;
+        layers.push(log_layer(
+            format,
+            std::sync::Mutex::new(file),
+            false,
+            level.max(LevelFilter::INFO),
+        ));
+    }
+
+    tracing_subscriber::registry().with(layers).init();
+    Ok(())
+}
+
+type LogLayer = Box<dyn tracing_subscriber::Layer<tracing_subscriber::Registry> + Send + Sync>;
+
+/// A layer writing events in `format` to `writer`, filtered by `RUST_LOG`
+/// or else `level`.
+fn log_layer<W>(
+    format: LogFormat,
+    writer: W,
+    ansi: bool,
+    level: tracing_subscriber::filter::LevelFilter,
+) -> LogLayer
+where
+    W: for<'w> tracing_subscriber::fmt::MakeWriter<'w> + Send + Sync + 'static,
+{
+    use tracing_subscriber::Layer as _;
+
+    let filter = tracing_subscriber::EnvFilter::builder()
+        .with_default_directive(level.into())
+        .from_env_lossy();
+    let layer = tracing_subscriber::fmt::layer().with_writer(writer);
+    match format {
+        LogFormat::Pretty => layer.with_ansi(ansi).with_filter(filter).boxed(),
+        LogFormat::Json => layer.json().flatten_event(true).with_filter(filter).boxed(),
+    }
+}
+
+/// Print a message for the user to stderr. With `--log-format json`, stderr
+/// is kept to JSON lines and the log event next to the message stands in for
+/// it.
+fn report(msg: impl std::fmt::Display) {
+    if LOG_FORMAT.get() != Some(&LogFormat::Json) {
+        eprintln!("{msg}");
+    }
+}
+
+// ── progress events ──
+
+/// Write a `--progress json` event to stderr as a single line; a no-op in
+/// text mode. `fields` must be a JSON object; its keys are merged with `event`.
+/// Paths go in with `to_string_lossy`: JSON strings can't hold file names
+/// that aren't valid Unicode.
+fn emit_progress(event: &str, fields: serde_json::Value) {
+    let mut obj = serde_json::Map::new();
+    obj.insert("event".into(), event.into());
+    if let serde_json::Value::Object(fields) = fields {
+        obj.extend(fields);
+    }
+    // Under `rpc`, events go to the client of the running call.
+    if rpc::forward(obj.clone()) || PROGRESS.get() != Some(&ProgressFormat::Json) {
+        return;
+    }
+    // `eprintln!` holds the stderr lock for the whole line, so events from
+    // parallel downloads never interleave.
+    eprintln!("{}", serde_json::Value::Object(obj));
+}
+
+/// Emit the terminal event for one download: `finished`, `skipped` or `failed`.
+fn emit_fetched(id: Option<u64>, input: &str, result: &Result<Fetched>) {
+    match result {
+        Ok(Fetched::Downloaded(dest, size)) => emit_progress(
+            "finished",
+            serde_json::json!({ "id": id, "input": input, "output": dest.to_string_lossy(), "bytes": size }),
+        ),
+        Ok(Fetched::Skipped(dest)) => emit_progress(
+            "skipped",
+            serde_json::json!({ "id": id, "input": input, "output": dest.to_string_lossy() }),
+        ),
+        Err(e) => emit_progress(
+            "failed",
+            serde_json::json!({ "id": id, "input": input, "error": format!("{e:#}") }),
+        ),
+    }
+}
+
+// ── hooks ──
+
+/// Hooks from `hooks.toml` and `--hook`, loaded on first use. An invalid
+/// config is reported and ignored rather than failing the file operation.
+fn hooks() -> &'static hooks::Hooks {
+    static HOOKS: OnceLock<hooks::Hooks> = OnceLock::new();
+    HOOKS.get_or_init(|| {
+        let Some(Some(commands)) = HOOK_COMMANDS.get() else {
+            return hooks::Hooks::default();
+        };
+        let mut list = match load_hooks_config() {
+            Ok(config) => config.hooks,
+            Err(e) => {
+                tracing::error!(error = format!("{e:#}"), "ignoring hooks config");
+                report(tr!("hooks-config-ignored", error = format!("{e:#}")));
+                Vec::new()
+            }
+        };
+        list.extend(commands.iter().cloned().map(hooks::Hook::command));
+        hooks::Hooks::new(list)
+    })
+}
+
+/// `hooks.toml` in the config directory; empty if there is none.
+fn load_hooks_config() -> Result<hooks::Config> {
+    let path = netease_api::auth::Session::path()?.with_file_name("hooks.toml");
+    let text = match std::fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(hooks::Config::default()),
+        Err(e) => return Err(e).with_context(|| tr!("read-failed", path = path.display())),
+    };
+    hooks::Config::parse(&text).map_err(|error| {
+        InputError(tr!(
+            "hooks-config-invalid",
+            path = path.display(),
+            error = error
+        ))
+        .into()
+    })
+}
+
+/// Run the hooks for `event` on the finished file `path`, reporting those
+/// that fail.
+fn run_hooks(
+    event: hooks::Event,
+    path: &std::path::Path,
+    source: Option<&std::path::Path>,
+    track_id: Option<u64>,
+) {
+    let hooks = hooks();
+    if hooks.is_empty() {
+        return;
+    }
+    // Converted NCM files don't always carry a `163 key`; their header does.
+    let track_id = track_id.or_else(|| {
+        let source = source.filter(|s| has_ncm_extension(s))?;
+        let metadata = ncmdump::NcmMetadata::read_from(source).ok()??;
+        Some(metadata.music_id).filter(|&id| id != 0)
+    });
+    let payload = hooks::Payload {
+        source,
+        ..hooks::Payload::new(event, path, track_id)
+    };
+    for (hook, error) in hooks.run(&payload) {
+        tracing::warn!(hook, file = %path.display(), error, "hook failed");
+        let msg = tr!(
+            "hook-failed",
+            hook = hook,
+            path = path.display(),
+            error = error
+        );
+        report(msg);
+    }
+}
+
+// ── notifications ──
+
+/// Notifiers from `hooks.toml` and `--notify`, loaded on first use. Like
+/// hooks, an invalid config is reported and ignored.
+fn notifiers() -> &'static notify::Notifiers {
+    static NOTIFIERS: OnceLock<notify::Notifiers> = OnceLock::new();
+    NOTIFIERS.get_or_init(|| {
+        let Some(Some(targets)) = NOTIFY_TARGETS.get() else {
+            return notify::Notifiers::default();
+        };
+        let mut list = match load_hooks_config() {
+            Ok(config) => config.notifiers,
+            Err(e) => {
+                tracing::error!(error = format!("{e:#}"), "ignoring notifiers config");
+                report(tr!("notify-config-ignored", error = format!("{e:#}")));
+                Vec::new()
+            }
+        };
+        // Targets were validated when the arguments were parsed.
+        list.extend(
+            targets
+                .iter()
+                .filter_map(|t| notify::Notifier::target(t).ok()),
+        );
+        notify::Notifiers::new(list)
+    })
+}
+
+/// The name batch commands are notified under; `None` for the rest. The
+/// daemon notifies per job instead.
+fn notify_operation(command: &Command) -> Option<&'static str> {
+    Some(match command {
+        Command::Dump { .. } => "dump",
+        Command::Cache { .. } => "cache",
+        Command::Rename { .. } => "rename",
+        Command::Organize { .. } => "organize",
+        Command::Retag { .. } => "retag",
+        Command::Gain { .. } => "gain",
+        Command::Enrich { .. } => "enrich",
+        Command::Import { .. } => "import",
+        Command::Download { .. } => "download",
+        Command::Similar { .. } => "similar",
+        Command::Album { .. } => "album",
+        Command::Playlist { .. } => "playlist",
+        Command::Podcast { .. } => "podcast",
+        Command::Toplist { .. } => "toplist",
+        Command::BiliDownload { .. } => "bili-download",
+        _ => return None,
+    })
+}
+
+/// Tell the notifiers that `operation` ended, with `error` if it failed,
+/// reporting those that fail.
+fn send_notification(operation: String, error: Option<String>, elapsed: std::time::Duration) {
+    let notifiers = notifiers();
+    if notifiers.is_empty() {
+        return;
+    }
+    let secs = elapsed.as_secs();
+    let notification = match error {
+        None => notify::Notification {
+            event: notify::Event::Finished,
+            title: tr!("notify-finished-title", operation = operation.as_str()),
+            message: tr!("notify-finished", secs = secs),
+            operation,
+            duration_secs: secs,
+        },
+        Some(error) => notify::Notification {
+            event: notify::Event::Failed,
+            title: tr!("notify-failed-title", operation = operation.as_str()),
+            message: error,
+            operation,
+            duration_secs: secs,
+        },
+    };
+    let proxy = PROXY.get().and_then(Option::as_deref);
+    for (notifier, error) in notifiers.send(&notification, proxy) {
+        tracing::warn!(notifier, error, "notifier failed");
+        report(tr!("notifier-failed", notifier = notifier, error = error));
+    }
+}
+
+// ── batch summary ──
+
+/// Whether per-file lines are left out: under `--quiet`, and in the `rpc`
+/// server, whose clients get progress events instead.
+fn quiet() -> bool {
+    QUIET.get() == Some(&true) || rpc::serving()
+}
+
+/// Outcome counts of a batch command, printed when it ends under `--quiet`
+/// in place of the per-file lines.
+#[derive(Default)]
+struct Summary {
+    done: usize,
+    skipped: usize,
+    /// Failed inputs and why they failed.
+    failed: Vec<(String, String)>,
+}
+
+impl Summary {
+    fn record(&mut self, input: impl std::fmt::Display, result: &Result<Fetched>) {
+        match result {
+            Ok(Fetched::Downloaded(..)) => self.done += 1,
+            Ok(Fetched::Skipped(_)) => self.skipped += 1,
+            Err(e) => self.failed.push((input.to_string(), format!("{e:#}"))),
+        }
+    }
+
+    /// Print the counts and failure reasons if `--quiet` is set, except in
+    /// the `rpc` server. `action` is `convert` or `download` and picks the
+    /// verb of the first count.
+    fn print(&self, action: &str) {
+        if !quiet() || rpc::serving() {
+            return;
+        }
+        let line = tr!(
+            "summary",
+            action = action,
+            done = self.done,
+            skipped = self.skipped,
+            failed = self.failed.len()
+        );
+        println!("{line}");
+        for (input, error) in &self.failed {
+            report(format_args!("  {input}: {error}"));
+        }
+    }
+}
+
+// ── clients ──
+
+fn netease_client_builder() -> netease_api::NeteaseClientBuilder {
+    let builder = netease_api::NeteaseClient::builder().passphrase(session_passphrase);
+    match PROXY.get().and_then(Option::as_deref) {
+        Some(url) => builder.proxy(url),
+        None => builder,
+    }
+}
+
+fn netease_client() -> Result<netease_api::NeteaseClient> {
+    Ok(netease_client_builder().build()?)
+}
+
+/// Ask for the passphrase of an encrypted session on the terminal, once per
+/// run.
+fn session_passphrase() -> Option<String> {
+    static PASSPHRASE: OnceLock<Option<String>> = OnceLock::new();
+    PASSPHRASE
+        .get_or_init(|| {
+            rpassword::prompt_password(tr!("session-passphrase"))
+                .ok()
+                .filter(|p| !p.is_empty())
+        })
+        .clone()
+}
+
+/// Load the saved session, asking for its passphrase if needed.
+fn load_session() -> Result<netease_api::auth::Session> {
+    Ok(netease_api::auth::Session::load_with(session_passphrase)?)
+}
+
+fn bili_client() -> Result<bilibili_api::BilibiliClient> {
+    let builder = bilibili_api::BilibiliClient::builder();
+    let builder = match PROXY.get().and_then(Option::as_deref) {
+        Some(url) => builder.proxy(url),
+        None => builder,
+    };
+    Ok(builder.build()?)
+}
+
+fn musicbrainz_client() -> Result<musicbrainz_api::MusicBrainzClient> {
+    let builder = musicbrainz_api::MusicBrainzClient::builder();
+    let builder = match PROXY.get().and_then(Option::as_deref) {
+        Some(url) => builder.proxy(url),
+        None => builder,
+    };
+    Ok(builder.build()?)
+}
+
+// ── dump ──
+
+fn cmd_dump(
+    jobs: &[(PathBuf, Option<PathBuf>)],
+    mut naming: Naming,
+    remove: bool,
+    replay_gain: bool,
+    opts: &ncmdump::ConvertOptions,
+    dry_run: bool,
+) -> Result<()> {
+    if jobs.is_empty() {
+        return Err(InputError(tr!("dump-no-files")).into());
+    }
+    if dry_run {
+        preview_jobs(jobs, &mut naming);
+        return Ok(());
+    }
+    convert_jobs(jobs, &mut naming, remove, replay_gain, opts)
+}
+
+/// Tag options for `dump`: covers are processed when `cover_max_size` is
+/// given.
+fn tag_options(
+    cover_max_size: Option<u32>,
+    cover_quality: Option<u8>,
+    keep_163_key: bool,
+    id3v23: bool,
+    encoding: Option<Id3Encoding>,
+    mode: Option<TagMode>,
+) -> ncmdump::TagOptions {
+    let defaults = ncmdump::cover::CoverOptions::default();
+    ncmdump::TagOptions {
+        cover: cover_max_size.map(|max_dimension| ncmdump::cover::CoverOptions {
+            max_dimension,
+            jpeg_quality: cover_quality.unwrap_or(defaults.jpeg_quality),
+        }),
+        keep_163_key,
+        id3_version: if id3v23 {
+            ncmdump::Id3Version::V23
+        } else {
+            ncmdump::Id3Version::V24
+        },
+        id3_encoding: match encoding {
+            None | Some(Id3Encoding::Utf8) => ncmdump::Id3Encoding::Utf8,
+            Some(Id3Encoding::Utf16) => ncmdump::Id3Encoding::Utf16,
+            Some(Id3Encoding::Latin1) => ncmdump::Id3Encoding::Latin1,
+        },
+        mode: match mode {
+            None | Some(TagMode::Overwrite) => ncmdump::TagMode::Overwrite,
+            Some(TagMode::FillMissing) => ncmdump::TagMode::FillMissing,
+            Some(TagMode::Skip) => ncmdump::TagMode::Skip,
+        },
+        ..ncmdump::TagOptions::default()
+    }
+}
+
+/// How `dump` names its outputs.
+#[derive(Default)]
+struct Naming<'a> {
+    /// Name template filled from the NCM's embedded metadata; the source
+    /// file's stem if `None`.
+    template: Option<&'a str>,
+    /// What to do when the output path is taken. `None` overwrites files
+    /// left by earlier runs, but numbers outputs that an earlier input of
+    /// this run already claimed.
+    collision: Option<Collision>,
+    /// Number outputs rather than overwrite existing files when `collision`
+    /// is `None`: set by `--remove`, under which an overwritten file could
+    /// not be converted again.
+    keep_existing: bool,
+    /// Outputs already assigned in this run.
+    claimed: std::collections::HashSet<PathBuf>,
+}
+
+/// Where [`Naming::plan`] puts one input.
+struct Planned {
+    /// The opened input, the container it was probed as and what its
+    /// header holds, for converting it without reading the header again.
+    input: std::fs::File,
+    container: std::sync::Arc<dyn ncmdump::EncryptedContainer>,
+    info: ncmdump::ContainerInfo,
+    output: PathBuf,
+    /// The output path is taken and `--on-collision skip` is set.
+    skip: bool,
+    /// Converting replaces an existing file (or an earlier input's output).
+    overwrites: bool,
+}
+
+impl Naming<'_> {
+    /// Read `file`'s header and pick its output path inside `out_dir` (or
+    /// next to `file`), claiming it for this run.
+    fn plan(
+        &mut self,
+        file: &std::path::Path,
+        out_dir: Option<&std::path::Path>,
+    ) -> ncmdump::Result<Planned> {
+        let mut input = std::fs::File::open(file)?;
+        let container = ncmdump::container::probe(file, &mut input)?;
+        let info = container.metadata(&mut input)?;
+        let format = info.format;
+        let mut target = ncmdump::output_path(file, format, out_dir);
+        if let Some(template) = self.template {
+            let name = match (&info.metadata, container.name()) {
+                (Some(meta), _) => Some(ncmdump::template::render(template, |f| {
+                    meta.template_field(f)
+                })?),
+                // An NCM file without metadata fails on the fields it lacks.
+                (None, "ncm") => Some(ncmdump::template::render(template, |_| None)?),
+                // Cache files carry no metadata; name them by track ID, as
+                // `cache` does for tracks it can't look up.
+                (None, "cache") => ncmdump::cache::track_id(file).map(|id| id.to_string()),
+                // Other formats have none either, nor an ID: keep the source
+                // name.
+                (None, _) => None,
+            };
+            if let Some(name) = name {
+                let dir = target.parent().unwrap_or(std::path::Path::new(""));
+                target = dir.join(format!("{name}.{}", format.extension()));
+            }
+        }
+        let claimed = self.claimed.contains(&target);
+        let taken = claimed || target.exists();
+        // Two inputs never share an output, or the first one's audio is lost.
+        let collision = match self.collision {
+            None if claimed || self.keep_existing => Some(Collision::Number),
+            collision => collision,
+        };
+        let output = match collision {
+            Some(collision) => free_target(file, target.clone(), &self.claimed, collision),
+            None => Some(target.clone()),
+        };
+        if let Some(out) = &output {
+            self.claimed.insert(out.clone());
+        }
+        Ok(Planned {
+            input,
+            container,
+            info,
+            skip: output.is_none(),
+            output: output.unwrap_or(target),
+            overwrites: taken && collision.is_none(),
+        })
+    }
+}
+
+/// Print what `convert_jobs` would do: each input's detected format and
+/// output path, whether that overwrites a file, and inputs that would be
+/// skipped or fail.
+fn preview_jobs(jobs: &[(PathBuf, Option<PathBuf>)], naming: &mut Naming) {
+    let (mut convert, mut overwrite, mut skipped) = (0, 0, 0);
+    for (file, out_dir) in jobs {
+        let planned = match naming.plan(file, out_dir.as_deref()) {
+            Ok(planned) => planned,
+            Err(e) => {
+                skipped += 1;
+                let error = e.to_string();
+                println!(
+                    "{}",
+                    tr!("dump-plan-skip", input = file.display(), error = error)
+                );
+                continue;
+            }
+        };
+        let (input, output) = (file.display(), planned.output.display());
+        let format = planned.info.format.extension();
+        let line = if planned.skip {
+            skipped += 1;
+            tr!("skipped-existing", path = output)
+        } else if planned.overwrites {
+            convert += 1;
+            overwrite += 1;
+            tr!(
+                "dump-plan-overwrite",
+                input = input,
+                output = output,
+                format = format
+            )
+        } else {
+            convert += 1;
+            tr!(
+                "dump-plan-convert",
+                input = input,
+                output = output,
+                format = format
+            )
+        };
+        println!("{line}");
+    }
+    println!(
+        "\n{}",
+        tr!(
+            "dump-plan-summary",
+            convert = convert,
+            overwrite = overwrite,
+            skipped = skipped
+        )
+    );
+}
+
+/// Convert each `(input, output directory)` pair, continuing past failures.
+fn convert_jobs(
+    jobs: &[(PathBuf, Option<PathBuf>)],
+    naming: &mut Naming,
+    remove: bool,
+    replay_gain: bool,
+    opts: &ncmdump::ConvertOptions,
+) -> Result<()> {
+    tracing::info!(count = jobs.len(), "converting NCM files");
+    let total = jobs.len();
+    let mut summary = Summary::default();
+    let mut converted = Vec::new();
+    for (i, (file, out_dir)) in jobs.iter().enumerate() {
+        tracing::debug!(input = %file.display(), "converting");
+        emit_progress(
+            "started",
+            serde_json::json!({ "input": file.to_string_lossy(), "index": i + 1, "total": total }),
+        );
+        let result = convert_job(naming, file, out_dir.as_deref(), remove, opts);
+        match result {
+            Ok(Planned {
+                output, skip: true, ..
+            }) => {
+                tracing::info!(input = %file.display(), output = %output.display(), "skipped existing");
+                emit_progress(
+                    "skipped",
+                    serde_json::json!({ "input": file.to_string_lossy(), "output": output.to_string_lossy() }),
+                );
+                summary.skipped += 1;
+                if !quiet() {
+                    println!("{}", tr!("skipped-existing", path = output.display()));
+                }
+            }
+            Ok(Planned {
+                input,
+                output: out,
+                info,
+                ..
+            }) => {
+                // Closed before `--remove`: Windows can't delete open files.
+                drop(input);
+                tracing::info!(input = %file.display(), output = %out.display(), "converted");
+                let bytes = std::fs::metadata(&out).map_or(0, |m| m.len());
+                emit_progress(
+                    "finished",
+                    serde_json::json!({ "input": file.to_string_lossy(), "output": out.to_string_lossy(), "bytes": bytes }),
+                );
+                summary.done += 1;
+                if !quiet() {
+                    println!("{} -> {}", file.display(), out.display());
+                }
+                record_conversion(file, &out, info.metadata.as_ref());
+                // With `--replaygain`, hooks see the file once it is tagged.
+                if !replay_gain {
+                    run_hooks(hooks::Event::Convert, &out, Some(file), None);
+                }
+                converted.push((file, out));
+                if remove {
+                    if let Err(e) = std::fs::remove_file(file) {
+                        tracing::warn!(input = %file.display(), error = %e, "failed to remove source");
+                        let msg = tr!("dump-remove-failed", path = file.display(), error = e);
+                        report(msg);
+                    }
+                }
+            }
+            Err(e) => {
+                tracing::error!(input = %file.display(), error = %e, "conversion failed");
+                emit_progress(
+                    "failed",
+                    serde_json::json!({ "input": file.to_string_lossy(), "error": e }),
+                );
+                if !quiet() {
+                    report(tr!("item-error", item = file.display(), error = e));
+                }
+                summary.failed.push((file.display().to_string(), e));
+            }
+        }
+    }
+    summary.print("convert");
+    if replay_gain && !converted.is_empty() {
+        let outputs: Vec<PathBuf> = converted.iter().map(|(_, out)| out.clone()).collect();
+        let gain = tag_replay_gain(&outputs, true, false);
+        for (file, out) in &converted {
+            run_hooks(hooks::Event::Convert, out, Some(file), None);
+        }
+        gain?;
+    }
+    if !summary.failed.is_empty() {
+        let failed = summary.failed.len();
+        anyhow::bail!(tr!("files-failed", failed = failed, total = total));
+    }
+    Ok(())
+}
+
+/// Plan and convert one input of `convert_jobs`, then, if `remove` is set,
+/// check the output before the source is deleted.
+fn convert_job(
+    naming: &mut Naming,
+    file: &std::path::Path,
+    out_dir: Option<&std::path::Path>,
+    remove: bool,
+    opts: &ncmdump::ConvertOptions,
+) -> std::result::Result<Planned, String> {
+    let mut planned = naming.plan(file, out_dir).map_err(|e| e.to_string())?;
+    if planned.skip {
+        return Ok(planned);
+    }
+    if let Some(dir) = planned.output.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let stream = ncmdump::convert_probed(
+        &mut planned.input,
+        planned.container.as_ref(),
+        &planned.info,
+        &planned.output,
+        opts,
+    )
+    .map_err(|e| e.to_string())?;
+    if remove {
+        verify_output(&planned.output, stream)
+            .map_err(|reason| tr!("dump-verify-failed", reason = reason))?;
+    }
+    Ok(planned)
+}
+
+/// Record in the library that `src` was converted to `out`.
+fn record_conversion(
+    src: &std::path::Path,
+    out: &std::path::Path,
+    meta: Option<&ncmdump::NcmMetadata>,
+) {
+    let Some(library) = open_library() else {
+        return;
+    };
+    let cache = ncmdump::cache::is_cache_file(src);
+    let origin = if cache {
+        ncmdump_db::Origin::Cache
+    } else {
+        ncmdump_db::Origin::Convert
+    };
+    let netease_id = match meta {
+        Some(m) => Some(m.music_id).filter(|&id| id != 0),
+        None if cache => ncmdump::cache::track_id(src),
+        None => None,
+    };
+    let result = history::scan(out, origin)
+        .map_err(ncmdump_db::DbError::from)
+        .and_then(|track| {
+            library.record(&ncmdump_db::Track {
+                netease_id,
+                bitrate: meta.map(|m| m.bitrate).filter(|&b| b != 0),
+                source: std::path::absolute(src).ok(),
+                ..track
+            })
+        });
+    if let Err(e) = result {
+        tracing::warn!(file = %out.display(), error = %e, "failed to update library");
+    }
+}
+
+/// Check that `out` is a complete conversion before `--remove` deletes its
+/// source: it must decode as audio with a duration, and hold exactly the
+/// `stream` bytes of audio that were decrypted, whatever tags were written
+/// around them. M4A and Ogg, whose tags live inside the stream (`stream` is
+/// `None`), are only checked for a duration. Returns the reason it is not.
+fn verify_output(out: &std::path::Path, stream: Option<u64>) -> std::result::Result<(), String> {
+    if let Some(stream) = stream {
+        let size = ncmdump::stream_len(out)
+            .map_err(|e| e.to_string())?
+            .unwrap_or_default();
+        if size != stream {
+            return Err(tr!("dump-verify-size", size = size, expected = stream));
+        }
+    }
+    let tags = ncmdump::tag_read(out).map_err(|e| e.to_string())?;
+    if tags.duration_ms == 0 {
+        return Err(tr!("dump-verify-duration"));
+    }
+    Ok(())
+}
+
+/// Collect the inputs of `dump`, each paired with the directory its output
+/// should be written to.
+fn dump_jobs(
+    files: Vec<PathBuf>,
+    files_from: Option<&std::path::Path>,
     directory: Option<&PathBuf>,
     recursive: bool,
     output: Option<&PathBuf>,
-    remove: bool,
-) -> Result<()> {
+) -> Result<Vec<(PathBuf, Option<PathBuf>)>> {
+    let mut jobs: Vec<(PathBuf, Option<PathBuf>)> = Vec::new();
+    // Listed paths are taken literally: they usually come from `find`/`fd`.
+    let stdin_list = files
+        .iter()
+        .any(|f| f.as_os_str() == "-")
+        .then(|| PathBuf::from("-"));
+    for list in stdin_list.as_deref().into_iter().chain(files_from) {
+        let listed = read_path_list(list)?;
+        jobs.extend(listed.into_iter().map(|f| (f, output.cloned())));
+    }
+    for file in files.into_iter().filter(|f| f.as_os_str() != "-") {
+        let pattern = file.to_string_lossy();
+        if file.exists() || !is_glob(&pattern) {
+            jobs.push((file, output.cloned()));
+            continue;
+        }
+        let matches = expand_glob(&pattern)?;
+        if matches.is_empty() {
+            tracing::warn!(pattern = %pattern, "pattern matched no files");
+            report(tr!("dump-no-match", pattern = pattern));
+        }
+        jobs.extend(matches.into_iter().map(|m| (m, output.cloned())));
+    }
+
     if let Some(dir) = directory {
         if recursive {
             for entry in WalkDir::new(dir)
                 .into_iter()
                 .filter_map(std::result::Result::ok)
             {
-                if entry.path().extension().is_some_and(|e| e == "ncm") {
-                    files.push(entry.into_path());
+                if is_dump_input(entry.path()) {
+                    // Mirror the source layout under the output directory so files
+                    // with the same stem in different folders don't collide.
+                    let out_dir = output.map(|o| {
+                        let rel = entry
+                            .path()
+                            .parent()
+                            .and_then(|p| p.strip_prefix(dir).ok())
+                            .unwrap_or(std::path::Path::new(""));
+                        o.join(rel)
+                    });
+                    jobs.push((entry.into_path(), out_dir));
                 }
             }
         } else {
-            for entry in std::fs::read_dir(dir).context("failed to read directory")? {
+            for entry in std::fs::read_dir(dir).with_context(|| tr!("dump-read-dir-failed"))? {
                 let path = entry?.path();
-                if path.extension().is_some_and(|e| e == "ncm") {
-                    files.push(path);
+                if is_dump_input(&path) {
+                    jobs.push((path, output.cloned()));
                 }
             }
         }
     }
 
-    if files.is_empty() {
-        eprintln!("No NCM files specified. Use --help for usage.");
-        std::process::exit(1);
+    Ok(jobs)
+}
+
+/// Read a newline- or NUL-separated list of paths from `source` (`-` for stdin).
+///
+/// NUL separation (`find -print0`, `fd -0`) is detected automatically; empty
+/// entries and trailing `\r` are ignored.
+fn read_path_list(source: &std::path::Path) -> Result<Vec<PathBuf>> {
+    let bytes = read_source(source)?;
+    let sep = if bytes.contains(&0) { b'\0' } else { b'\n' };
+    Ok(bytes
+        .split(|&b| b == sep)
+        .map(|entry| entry.strip_suffix(b"\r").unwrap_or(entry))
+        .filter(|entry| !entry.is_empty())
+        .map(path_from_bytes)
+        .collect())
+}
+
+/// Read all of `source`, or stdin if it is `-`.
+fn read_source(source: &std::path::Path) -> Result<Vec<u8>> {
+    use std::io::Read;
+
+    if source.as_os_str() != "-" {
+        return std::fs::read(source).with_context(|| tr!("read-failed", path = source.display()));
+    }
+    let mut bytes = Vec::new();
+    std::io::stdin()
+        .read_to_end(&mut bytes)
+        .with_context(|| tr!("stdin-failed"))?;
+    Ok(bytes)
+}
+
+#[cfg(unix)]
+fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    use std::os::unix::ffi::OsStrExt;
+    PathBuf::from(std::ffi::OsStr::from_bytes(bytes))
+}
+
+#[cfg(not(unix))]
+fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(bytes).into_owned())
+}
+
+fn has_ncm_extension(path: &std::path::Path) -> bool {
+    path.extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("ncm"))
+}
+
+/// Whether `dump` converts `path` when scanning a directory: NCM, client
+/// cache, QMC and KWM files.
+fn is_dump_input(path: &std::path::Path) -> bool {
+    has_ncm_extension(path)
+        || ncmdump::cache::is_cache_file(path)
+        || ncmdump::qmc::is_qmc_file(path)
+        || ncmdump::kwm::is_kwm_file(path)
+}
+
+fn is_glob(s: &str) -> bool {
+    s.contains(['*', '?', '[', '{'])
+}
+
+/// Expand a glob pattern (`*`, `**`, `?`, `[..]`, `{a,b}`) into matching files.
+///
+/// Matching is case-insensitive so `*.ncm` also picks up `SONG.NCM`. Only the
+/// part of the tree below the pattern's literal prefix is walked.
+fn expand_glob(pattern: &str) -> Result<Vec<PathBuf>> {
+    use std::path::{Component, Path};
+
+    let matcher = globset::GlobBuilder::new(pattern)
+        .literal_separator(true)
+        .case_insensitive(true)
+        .build()
+        .map_err(|e| InputError(tr!("glob-invalid", pattern = pattern, error = e)))?
+        .compile_matcher();
+
+    let components: Vec<Component> = Path::new(pattern).components().collect();
+    let literal = components
+        .iter()
+        .take_while(|c| !is_glob(&c.as_os_str().to_string_lossy()))
+        .count();
+    let base: PathBuf = components[..literal].iter().collect();
+    let rest = &components[literal..];
+
+    let mut walker = WalkDir::new(if literal == 0 { Path::new(".") } else { &base });
+    if !rest.iter().any(|c| c.as_os_str() == "**") {
+        walker = walker.max_depth(rest.len());
+    }
+
+    let mut found: Vec<PathBuf> = walker
+        .into_iter()
+        .filter_map(std::result::Result::ok)
+        .filter(|e| e.file_type().is_file())
+        .map(|e| {
+            // Relative patterns are walked from "." — drop that prefix again.
+            if literal == 0 {
+                e.path()
+                    .strip_prefix(".")
+                    .map_or_else(|_| e.path().to_path_buf(), Path::to_path_buf)
+            } else {
+                e.into_path()
+            }
+        })
+        .filter(|p| matcher.is_match(p))
+        .collect();
+    found.sort();
+    Ok(found)
+}
+
+// ── cache ──
+
+fn cmd_cache(
+    dir: &std::path::Path,
+    out_dir: &std::path::Path,
+    template: &str,
+    cover: bool,
+) -> Result<()> {
+    let files: Vec<PathBuf> = WalkDir::new(dir)
+        .into_iter()
+        .filter_map(std::result::Result::ok)
+        .filter(|e| e.file_type().is_file() && ncmdump::cache::is_cache_file(e.path()))
+        .map(walkdir::DirEntry::into_path)
+        .collect();
+    if files.is_empty() {
+        return Err(InputError(tr!("cache-none", dir = dir.display())).into());
+    }
+
+    let client = netease_client()?;
+    let total = files.len();
+    let mut summary = Summary::default();
+    for (i, file) in files.iter().enumerate() {
+        let (line, failed) = match convert_cache_file(&client, file, out_dir, template, cover) {
+            Ok(Some(out)) => {
+                summary.done += 1;
+                let line = format!(
+                    "[{}/{total}] {} -> {}",
+                    i + 1,
+                    file.display(),
+                    out.display()
+                );
+                (line, false)
+            }
+            Ok(None) => {
+                summary.skipped += 1;
+                let line = tr!(
+                    "cache-skip",
+                    index = i + 1,
+                    total = total,
+                    path = file.display()
+                );
+                (line, false)
+            }
+            Err(e) => {
+                tracing::error!(file = %file.display(), error = %e, "cache conversion failed");
+                let error = format!("{e:#}");
+                let line = tr!(
+                    "cache-fail",
+                    index = i + 1,
+                    total = total,
+                    path = file.display(),
+                    error = error
+                );
+                summary.failed.push((file.display().to_string(), error));
+                (line, true)
+            }
+        };
+        match (quiet(), failed) {
+            (true, _) => {}
+            (false, false) => println!("{line}"),
+            (false, true) => report(line),
+        }
+    }
+    summary.print("convert");
+    if !summary.failed.is_empty() {
+        let failed = summary.failed.len();
+        anyhow::bail!(tr!("cache-failed", failed = failed, total = total));
+    }
+    Ok(())
+}
+
+/// Decrypt one cache file and name/tag it from `track_detail`.
+///
+/// Returns `None` if the output already exists. Tracks the API can't resolve
+/// are still decrypted, named by ID and left untagged.
+fn convert_cache_file(
+    client: &netease_api::NeteaseClient,
+    file: &std::path::Path,
+    out_dir: &std::path::Path,
+    template: &str,
+    cover: bool,
+) -> Result<Option<PathBuf>> {
+    let id = ncmdump::cache::track_id(file).with_context(|| tr!("cache-no-id"))?;
+    let track = client
+        .track_detail(id)
+        .inspect_err(|e| {
+            tracing::warn!(track = id, error = %e, "cannot fetch track detail, leaving untagged");
+            report(tr!("cache-untagged", id = id, error = e));
+        })
+        .ok();
+    let stem = match &track {
+        Some(t) => render_track_name(template, t)?,
+        None => id.to_string(),
+    };
+    if ["mp3", "flac"]
+        .iter()
+        .any(|ext| out_dir.join(format!("{stem}.{ext}")).exists())
+    {
+        return Ok(None);
+    }
+    if let Some(parent) = out_dir.join(&stem).parent() {
+        std::fs::create_dir_all(parent).with_context(|| tr!("output-dir-failed"))?;
+    }
+    let out = ncmdump::cache::convert_cache(file, out_dir, &stem)?;
+
+    if let Some(t) = track {
+        let artists: Vec<&str> = t.artists.iter().map(|a| a.name.as_str()).collect();
+        let tags = ncmdump::AudioTags {
+            title: Some(t.name.clone()),
+            artist: Some(artists.join(" / ")),
+            album: Some(t.album.name.clone()),
+            ..ncmdump::AudioTags::default()
+        };
+        let image = match t.album.pic_url.as_deref() {
+            Some(url) if cover => client.fetch_bytes(url).ok(),
+            _ => None,
+        };
+        ncmdump::tag_update(&out, &tags, image.as_deref())?;
+    }
+    if let Some(library) = open_library() {
+        let result = history::scan(&out, ncmdump_db::Origin::Cache)
+            .map_err(ncmdump_db::DbError::from)
+            .and_then(|track| {
+                library.record(&ncmdump_db::Track {
+                    netease_id: Some(id),
+                    source: std::path::absolute(file).ok(),
+                    ..track
+                })
+            });
+        if let Err(e) = result {
+            tracing::warn!(file = %out.display(), error = %e, "failed to update library");
+        }
+    }
+    run_hooks(hooks::Event::Convert, &out, Some(file), Some(id));
+    Ok(Some(out))
+}
+
+// ── stats ──
+
+fn cmd_stats(dir: &std::path::Path) -> Result<()> {
+    use std::collections::BTreeMap;
+
+    #[derive(Default)]
+    struct FormatStats {
+        files: u64,
+        bytes: u64,
+        duration_ms: u64,
+    }
+
+    anyhow::ensure!(dir.is_dir(), "not a directory: {}", dir.display());
+
+    let mut formats: BTreeMap<String, FormatStats> = BTreeMap::new();
+    let mut unconverted = Vec::new();
+    let mut no_cover = Vec::new();
+    let mut no_lyrics = Vec::new();
+    let mut unreadable = 0;
+    let library = open_library();
+    for entry in WalkDir::new(dir)
+        .into_iter()
+        .filter_map(std::result::Result::ok)
+        .filter(|e| e.file_type().is_file())
+    {
+        let path = entry.path();
+        if has_ncm_extension(path) {
+            // Converted next to it, or anywhere the library knows of.
+            let converted = ["mp3", "flac", "m4a", "ogg", "MP3", "FLAC", "M4A", "OGG"]
+                .iter()
+                .any(|ext| path.with_extension(ext).exists())
+                || library.as_ref().is_some_and(|library| {
+                    library
+                        .by_source(path)
+                        .is_ok_and(|tracks| tracks.iter().any(|t| t.path.is_file()))
+                });
+            if !converted {
+                unconverted.push(path.to_path_buf());
+            }
+            continue;
+        }
+        if !has_audio_extension(path) {
+            continue;
+        }
+
+        let ext = path
+            .extension()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_lowercase();
+        let stats = formats.entry(ext).or_default();
+        stats.files += 1;
+        stats.bytes += entry.metadata().map_or(0, |m| m.len());
+        match ncmdump::tag_read(path) {
+            Ok(tags) => {
+                stats.duration_ms += tags.duration_ms;
+                if !tags.has_cover {
+                    no_cover.push(path.to_path_buf());
+                }
+                if tags.lyrics.is_none() {
+                    no_lyrics.push(path.to_path_buf());
+                }
+            }
+            Err(e) => {
+                tracing::warn!(file = %path.display(), error = %e, "cannot read tags");
+                unreadable += 1;
+            }
+        }
+    }
+
+    println!("{}\n", tr!("stats-library", dir = dir.display()));
+    let files_word = tr!("stats-files");
+    let mut total = FormatStats::default();
+    for (ext, s) in &formats {
+        println!(
+            "  {ext:<5} {:>6} {files_word} {:>10.1} MiB  {}",
+            s.files,
+            bytes_to_mib(s.bytes),
+            format_duration(s.duration_ms)
+        );
+        total.files += s.files;
+        total.bytes += s.bytes;
+        total.duration_ms += s.duration_ms;
+    }
+    println!(
+        "  {:<5} {:>6} {files_word} {:>10.1} MiB  {}",
+        tr!("stats-total"),
+        total.files,
+        bytes_to_mib(total.bytes),
+        format_duration(total.duration_ms)
+    );
+    if unreadable > 0 {
+        println!("\n{}", tr!("stats-unreadable", count = unreadable));
+    }
+
+    for (title, files, hint) in [
+        (tr!("stats-unconverted"), &unconverted, "ncmdump-cli dump"),
+        (tr!("stats-no-cover"), &no_cover, "ncmdump-cli retag"),
+        (tr!("stats-no-lyrics"), &no_lyrics, "ncmdump-cli retag"),
+    ] {
+        println!("\n{title}: {}", files.len());
+        for f in files {
+            println!("  {}", f.display());
+        }
+        if !files.is_empty() {
+            println!("  {}", tr!("stats-fix", command = hint));
+        }
+    }
+    Ok(())
+}
+
+/// Format milliseconds as `1h 02m 03s`.
+fn format_duration(ms: u64) -> String {
+    let secs = ms / 1000;
+    format!(
+        "{}h {:02}m {:02}s",
+        secs / 3600,
+        (secs / 60) % 60,
+        secs % 60
+    )
+}
+
+// ── rename ──
+
+fn cmd_rename(paths: &[PathBuf], template: &str, recursive: bool, dry_run: bool) -> Result<()> {
+    let mut files = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            let walker = WalkDir::new(path).max_depth(if recursive { usize::MAX } else { 1 });
+            files.extend(
+                walker
+                    .into_iter()
+                    .filter_map(std::result::Result::ok)
+                    .filter(|e| e.file_type().is_file() && has_audio_extension(e.path()))
+                    .map(walkdir::DirEntry::into_path),
+            );
+        } else {
+            files.push(path.clone());
+        }
+    }
+
+    // Targets claimed earlier in this run, so a dry run reports collisions too.
+    let mut claimed = std::collections::HashSet::new();
+    let (mut renamed, mut failed) = (0, 0);
+    for file in &files {
+        let target = ncmdump::tag_read(file)
+            .map_err(anyhow::Error::from)
+            .and_then(|tags| {
+                Ok(ncmdump::template::render(template, |f| {
+                    tags.template_field(f)
+                })?)
+            })
+            .map(|stem| {
+                let ext = file.extension().unwrap_or_default().to_string_lossy();
+                let parent = file.parent().unwrap_or(std::path::Path::new(""));
+                parent.join(format!("{stem}.{}", ext.to_lowercase()))
+            });
+        let target = match target {
+            Ok(t) if t == *file => continue,
+            Ok(t) if claimed.contains(&t) || t.exists() => {
+                tracing::warn!(from = %file.display(), to = %t.display(), "rename target exists");
+                let msg = tr!("rename-exists", from = file.display(), to = t.display());
+                report(msg);
+                failed += 1;
+                continue;
+            }
+            Ok(t) => t,
+            Err(e) => {
+                tracing::warn!(file = %file.display(), error = %e, "cannot render target name");
+                report(tr!("rename-skip", path = file.display(), error = e));
+                failed += 1;
+                continue;
+            }
+        };
+
+        println!("{} -> {}", file.display(), target.display());
+        if !dry_run {
+            if let Some(dir) = target.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            std::fs::rename(file, &target)
+                .with_context(|| tr!("rename-failed", path = file.display()))?;
+            tracing::info!(from = %file.display(), to = %target.display(), "renamed");
+            follow_rename(file, &target);
+        }
+        claimed.insert(target);
+        renamed += 1;
+    }
+
+    let summary = tr!(
+        "rename-summary",
+        renamed = renamed,
+        skipped = failed,
+        dry_run = dry_run
+    );
+    println!("\n{summary}");
+    Ok(())
+}
+
+/// Point the library's record of `from`, if it has one, at the renamed file.
+fn follow_rename(from: &std::path::Path, to: &std::path::Path) {
+    let Some(library) = open_library() else {
+        return;
+    };
+    if let Err(e) = library.rename(from, to) {
+        tracing::warn!(file = %from.display(), error = %e, "failed to update library");
+    }
+}
+
+fn has_audio_extension(path: &std::path::Path) -> bool {
+    path.extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("mp3") || e.eq_ignore_ascii_case("flac"))
+}
+
+// ── organize ──
+
+/// Default `organize` layout.
+const ORGANIZE_TEMPLATE: &str = "{artist}/{album}/{track} {title}";
+
+fn cmd_organize(
+    dir: &std::path::Path,
+    output: Option<&std::path::Path>,
+    template: &str,
+    copy: bool,
+    collision: Collision,
+    sidecars: bool,
+    dry_run: bool,
+) -> Result<()> {
+    let root = output.unwrap_or(dir);
+    // Collected up front: with the default root, files are moved inside `dir`.
+    let files: Vec<PathBuf> = WalkDir::new(dir)
+        .into_iter()
+        .filter_map(std::result::Result::ok)
+        .filter(|e| e.file_type().is_file() && has_audio_extension(e.path()))
+        .map(walkdir::DirEntry::into_path)
+        .collect();
+
+    let mut claimed = std::collections::HashSet::new();
+    let (mut done, mut skipped) = (0, 0);
+    for file in &files {
+        let target = match organize_target(file, root, template) {
+            Ok(t) => t,
+            Err(e) => {
+                tracing::warn!(file = %file.display(), error = %e, "cannot render target name");
+                report(tr!("rename-skip", path = file.display(), error = e));
+                skipped += 1;
+                continue;
+            }
+        };
+        let Some(target) = free_target(file, target.clone(), &claimed, collision) else {
+            tracing::warn!(from = %file.display(), to = %target.display(), "organize target exists");
+            let msg = tr!(
+                "rename-exists",
+                from = file.display(),
+                to = target.display()
+            );
+            report(msg);
+            skipped += 1;
+            continue;
+        };
+        if target == *file {
+            continue;
+        }
+
+        println!("{} -> {}", file.display(), target.display());
+        if !dry_run {
+            place_file(file, &target, copy)?;
+            // Keep `lyric -o` / `retag` sidecar lyrics next to their track.
+            let lrc = file.with_extension("lrc");
+            let lrc_target = target.with_extension("lrc");
+            if lrc.is_file() && !lrc_target.exists() {
+                place_file(&lrc, &lrc_target, copy)?;
+            }
+            tracing::info!(from = %file.display(), to = %target.display(), copy, "organized");
+        }
+        claimed.insert(target);
+        done += 1;
+    }
+
+    let action = match (dry_run, copy) {
+        (true, true) => "would-copy",
+        (true, false) => "would-move",
+        (false, true) => "copy",
+        (false, false) => "move",
+    };
+    let summary = tr!(
+        "organize-summary",
+        action = action,
+        done = done,
+        skipped = skipped
+    );
+    println!("\n{summary}");
+
+    if sidecars {
+        // A dry run has moved nothing, so only sidecars of files already in
+        // place can be listed.
+        let written = sidecar::write_library(root, dry_run)?;
+        for path in &written {
+            println!("+ {}", path.display());
+        }
+        let summary = tr!(
+            "organize-sidecars",
+            dry_run = dry_run,
+            count = written.len()
+        );
+        println!("{summary}");
+    }
+    Ok(())
+}
+
+/// Library path of `file` under `root` from its tags. Missing artist and album
+/// tags become `Unknown Artist` / `Unknown Album`.
+fn organize_target(
+    file: &std::path::Path,
+    root: &std::path::Path,
+    template: &str,
+) -> Result<PathBuf> {
+    let tags = ncmdump::tag_read(file)?;
+    let template = match tags.track {
+        Some(_) => template.to_owned(),
+        None => drop_placeholder(template, "track"),
+    };
+    let present = |v: &Option<String>| v.clone().filter(|s| !s.trim().is_empty());
+    let rel = ncmdump::template::render(&template, |f| match f {
+        "artist" => present(&tags.artist).or_else(|| Some("Unknown Artist".into())),
+        "album" => present(&tags.album).or_else(|| Some("Unknown Album".into())),
+        _ => tags.template_field(f),
+    })?;
+    let ext = file.extension().unwrap_or_default().to_string_lossy();
+    Ok(root.join(format!("{rel}.{}", ext.to_lowercase())))
+}
+
+/// Remove the `{name}` placeholder and the separator (` `, `-`, `.`, `_`)
+/// that follows it, e.g. `{track} {title}` → `{title}`.
+fn drop_placeholder(template: &str, name: &str) -> String {
+    let placeholder = format!("{{{name}}}");
+    match template.split_once(&placeholder) {
+        Some((head, tail)) => format!("{head}{}", tail.trim_start_matches([' ', '-', '.', '_'])),
+        None => template.to_owned(),
+    }
+}
+
+/// The path `file` should go to: `target`, or with [`Collision::Number`] the
+/// first free `<stem> (n).<ext>`. `None` if the file must be skipped. Returns
+/// `file` itself when it already sits at one of the candidates.
+fn free_target(
+    file: &std::path::Path,
+    target: PathBuf,
+    claimed: &std::collections::HashSet<PathBuf>,
+    collision: Collision,
+) -> Option<PathBuf> {
+    let taken = |p: &std::path::Path| p != file && (claimed.contains(p) || p.exists());
+    if !taken(&target) {
+        return Some(target);
+    }
+    if matches!(collision, Collision::Skip) {
+        return None;
+    }
+    // Built as `OsString`s, so names that aren't valid Unicode keep their bytes.
+    let stem = target.file_stem().unwrap_or_default();
+    (2..=u32::MAX)
+        .map(|n| {
+            let mut name = stem.to_owned();
+            name.push(format!(" ({n})"));
+            if let Some(ext) = target.extension() {
+                name.push(".");
+                name.push(ext);
+            }
+            target.with_file_name(name)
+        })
+        .find(|p| !taken(p))
+}
+
+/// Move (or copy) `from` to `to`, creating parent directories. Moves fall
+/// back to copy + delete across file systems.
+fn place_file(from: &std::path::Path, to: &std::path::Path, copy: bool) -> Result<()> {
+    if let Some(dir) = to.parent() {
+        std::fs::create_dir_all(dir).with_context(|| tr!("output-dir-failed"))?;
+    }
+    if !copy && std::fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    std::fs::copy(from, to).with_context(|| tr!("write-failed", path = to.display()))?;
+    if !copy {
+        std::fs::remove_file(from).with_context(|| tr!("remove-failed", path = from.display()))?;
+    }
+    Ok(())
+}
+
+// ── retag ──
+
+fn cmd_retag(
+    files: &[PathBuf],
+    id: Option<u64>,
+    cover: bool,
+    lyrics: bool,
+    dry_run: bool,
+) -> Result<()> {
+    if id.is_some() && files.len() > 1 {
+        return Err(InputError(tr!("retag-id-single")).into());
+    }
+    let client = netease_client()?;
+    let mut failed = 0;
+    for file in files {
+        match retag_file(&client, file, id, cover, lyrics, dry_run) {
+            Ok(track) => {
+                let artists: Vec<&str> = track.artists.iter().map(|a| a.name.as_str()).collect();
+                let msg = tr!(
+                    "retag-result",
+                    path = file.display(),
+                    dry_run = dry_run,
+                    artists = artists.join(", "),
+                    title = track.name,
+                    id = track.id
+                );
+                println!("{msg}");
+            }
+            Err(e) => {
+                tracing::error!(file = %file.display(), error = %e, "retag failed");
+                let error = format!("{e:#}");
+                report(tr!("item-error", item = file.display(), error = error));
+                failed += 1;
+            }
+        }
+    }
+    if failed > 0 {
+        anyhow::bail!(tr!("files-failed", failed = failed, total = files.len()));
+    }
+    Ok(())
+}
+
+/// Find the Netease track for `file` and rewrite its tags from the API.
+fn retag_file(
+    client: &netease_api::NeteaseClient,
+    file: &std::path::Path,
+    id: Option<u64>,
+    cover: bool,
+    lyrics: bool,
+    dry_run: bool,
+) -> Result<netease_api::types::Track> {
+    let existing = ncmdump::tag_read(file)?;
+    let id = match id.or_else(|| embedded_track_id(&existing)) {
+        Some(id) => id,
+        None => search_track_id(client, file, &existing)?,
+    };
+    let track = client.track_detail(id)?;
+    if dry_run {
+        return Ok(track);
+    }
+
+    let artists: Vec<&str> = track.artists.iter().map(|a| a.name.as_str()).collect();
+    let mut tags = ncmdump::AudioTags {
+        title: Some(track.name.clone()),
+        artist: Some(artists.join(" / ")),
+        album: Some(track.album.name.clone()),
+        ..ncmdump::AudioTags::default()
+    };
+    // Track number and year only come with the album; treat them as optional.
+    if let Ok(album) = client.album_detail(track.album.id) {
+        tags.track = album
+            .tracks
+            .iter()
+            .position(|t| t.id == track.id)
+            .and_then(|i| u32::try_from(i + 1).ok());
+        tags.year = i64::try_from(album.publish_time)
+            .ok()
+            .and_then(chrono::DateTime::from_timestamp_millis)
+            .and_then(|d| u16::try_from(chrono::Datelike::year(&d)).ok());
+    }
+    if lyrics {
+        tags.lyrics = client.track_lyric(id)?.lrc;
+    }
+    let image = match track.album.pic_url.as_deref() {
+        Some(url) if cover => Some(client.fetch_bytes(url)?),
+        _ => None,
+    };
+
+    ncmdump::tag_update(file, &tags, image.as_deref())?;
+    tracing::info!(file = %file.display(), track = track.id, "retagged");
+    Ok(track)
+}
+
+/// Track ID from the `163 key(Don't modify):...` comment written by the
+/// official client (and kept by most converters).
+fn embedded_track_id(tags: &ncmdump::AudioTags) -> Option<u64> {
+    let key = tags.comment.as_deref()?;
+    ncmdump::NcmMetadata::from_163_key(key)
+        .ok()
+        .map(|m| m.music_id)
+        .filter(|&id| id != 0)
+}
+
+/// Best search match for `artist title` (or the file stem when untagged).
+fn search_track_id(
+    client: &netease_api::NeteaseClient,
+    file: &std::path::Path,
+    tags: &ncmdump::AudioTags,
+) -> Result<u64> {
+    let query = match (&tags.artist, &tags.title) {
+        (Some(artist), Some(title)) => format!("{artist} {title}"),
+        (None, Some(title)) => title.clone(),
+        _ => file
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned(),
+    };
+    let result = client.search(&query, netease_api::types::SearchType::Track, 1, 0)?;
+    result
+        .tracks
+        .and_then(|t| t.into_iter().next())
+        .map(|t| t.id)
+        .with_context(|| tr!("retag-no-match", query = format!("{query:?}")))
+}
+
+// ── gain ──
+
+/// Measure the loudness of `files` (in parallel) and, unless `dry_run`,
+/// write their ReplayGain tags. With `album`, files sharing a directory and
+/// an album tag are treated as one album for album gain.
+fn tag_replay_gain(files: &[PathBuf], album: bool, dry_run: bool) -> Result<()> {
+    use std::collections::BTreeMap;
+
+    let measured = ncmdump::for_each_bounded(files, 0, |_, file| {
+        ncmdump::gain::analyze(file)
+            .and_then(|loudness| Ok((loudness, ncmdump::tag_read(file)?.album)))
+    });
+
+    let mut failed = 0;
+    let mut albums: BTreeMap<_, Vec<(&PathBuf, ncmdump::gain::Loudness)>> = BTreeMap::new();
+    for (file, result) in files.iter().zip(measured) {
+        match result {
+            Ok((loudness, album_tag)) => {
+                // Untagged files are albums of their own.
+                let key = match album_tag.filter(|_| album) {
+                    Some(name) => (file.parent().map(PathBuf::from), Some(name), None),
+                    None => (None, None, Some(file)),
+                };
+                albums.entry(key).or_default().push((file, loudness));
+            }
+            Err(e) => {
+                tracing::error!(file = %file.display(), error = %e, "loudness analysis failed");
+                report(tr!("item-error", item = file.display(), error = e));
+                failed += 1;
+            }
+        }
+    }
+
+    for ((_, name, _), tracks) in albums {
+        let loudness: Vec<_> = tracks.iter().map(|(_, l)| l.clone()).collect();
+        let gains = ncmdump::gain::replay_gain(&loudness);
+        for ((file, _), mut gain) in tracks.into_iter().zip(gains) {
+            if name.is_none() {
+                gain.album_gain = None;
+                gain.album_peak = None;
+            }
+            let db = |g: Option<f64>| g.map_or_else(|| "-".to_owned(), |g| format!("{g:+.2}"));
+            let msg = tr!(
+                "gain-result",
+                path = file.display(),
+                track = db(gain.track_gain),
+                peak = format!("{:.3}", gain.track_peak.unwrap_or_default()),
+                album = gain
+                    .album_gain
+                    .map_or_else(|| "none".to_owned(), |g| db(Some(g)))
+            );
+            if !rpc::serving() {
+                println!("{msg}");
+            }
+            if dry_run {
+                continue;
+            }
+            let tags = ncmdump::AudioTags {
+                replay_gain: gain,
+                ..ncmdump::AudioTags::default()
+            };
+            if let Err(e) = ncmdump::tag_update(file, &tags, None) {
+                tracing::error!(file = %file.display(), error = %e, "writing ReplayGain tags failed");
+                report(tr!("item-error", item = file.display(), error = e));
+                failed += 1;
+            }
+        }
+    }
+    if failed > 0 {
+        anyhow::bail!(tr!("files-failed", failed = failed, total = files.len()));
+    }
+    Ok(())
+}
+
+// ── enrich ──
+
+/// Look up the audio files under `paths` on MusicBrainz and, unless
+/// `dry_run`, add the IDs (and with `cover`, the front cover) found.
+fn cmd_enrich(paths: &[PathBuf], cover: bool, dry_run: bool) -> Result<()> {
+    let files: Vec<PathBuf> = paths
+        .iter()
+        .flat_map(|path| {
+            WalkDir::new(path)
+                .into_iter()
+                .filter_map(std::result::Result::ok)
+                .filter(|e| e.file_type().is_file() && has_audio_extension(e.path()))
+                .map(walkdir::DirEntry::into_path)
+        })
+        .collect();
+    let client = musicbrainz_client()?;
+    let enricher = enrich::Enricher::new(&client);
+
+    let (mut matched, mut failed) = (0, 0);
+    for file in &files {
+        let found = enricher.lookup(file).and_then(|found| {
+            let Some(found) = found else {
+                return Ok(None);
+            };
+            if !dry_run {
+                let image = if cover {
+                    enricher.cover(&found.release)?
+                } else {
+                    None
+                };
+                ncmdump::tag_update(file, &found.tags, image.as_deref())?;
+                tracing::info!(file = %file.display(), release = %found.release.id, "enriched");
+            }
+            Ok(Some(found))
+        });
+        match found {
+            Ok(Some(found)) => {
+                let msg = tr!(
+                    "enrich-matched",
+                    path = file.display(),
+                    dry_run = dry_run,
+                    album_artist = found.tags.album_artist.unwrap_or_default(),
+                    release = found.release.title,
+                    date = found.release.date.unwrap_or_default(),
+                    id = found.release.id
+                );
+                println!("{msg}");
+                matched += 1;
+            }
+            Ok(None) => println!("{}", tr!("enrich-no-match", path = file.display())),
+            Err(e) => {
+                tracing::error!(file = %file.display(), error = %e, "enrich failed");
+                let error = format!("{e:#}");
+                report(tr!("item-error", item = file.display(), error = error));
+                failed += 1;
+            }
+        }
+    }
+    println!(
+        "{}",
+        tr!("enrich-summary", matched = matched, total = files.len())
+    );
+    if failed > 0 {
+        anyhow::bail!(tr!("files-failed", failed = failed, total = files.len()));
+    }
+    Ok(())
+}
+
+// ── import ──
+
+/// Identify the audio files under `paths` and, unless `dry_run`, record
+/// them with their track IDs in the library.
+fn cmd_import_match(paths: &[PathBuf], dry_run: bool, fingerprints: bool) -> Result<()> {
+    let files: Vec<PathBuf> = paths
+        .iter()
+        .flat_map(|path| {
+            WalkDir::new(path)
+                .into_iter()
+                .filter_map(std::result::Result::ok)
+                .filter(|e| e.file_type().is_file() && has_audio_extension(e.path()))
+                .map(walkdir::DirEntry::into_path)
+        })
+        .collect();
+    let fingerprints = fingerprints && {
+        let available = fingerprint::available();
+        if !available {
+            tracing::warn!("fpcalc not found, matching by tags only");
+            report(tr!("import-no-fpcalc"));
+        }
+        available
+    };
+    let client = netease_client()?;
+    let library = if dry_run { None } else { open_library() };
+    let matcher = identify::Matcher::new(&client, fingerprints);
+
+    let (mut identified, mut failed) = (0, 0);
+    for file in &files {
+        let found = matcher.identify(file).and_then(|found| {
+            let Some(found) = found else {
+                return Ok(None);
+            };
+            if let Some(library) = &library {
+                library.record(&ncmdump_db::Track {
+                    netease_id: Some(found.track.id),
+                    bitrate: identify::quality_bitrate(file),
+                    ..history::scan(file, ncmdump_db::Origin::Import)?
+                })?;
+            }
+            Ok(Some(found))
+        });
+        match found {
+            Ok(Some(found)) => {
+                let artists: Vec<&str> = found
+                    .track
+                    .artists
+                    .iter()
+                    .map(|a| a.name.as_str())
+                    .collect();
+                let method = match found.method {
+                    identify::Method::Tag => "tag",
+                    identify::Method::Fingerprint => "fingerprint",
+                    identify::Method::Metadata => "metadata",
+                };
+                let msg = tr!(
+                    "import-matched",
+                    path = file.display(),
+                    artists = artists.join(", "),
+                    title = found.track.name,
+                    id = found.track.id,
+                    method = method,
+                    score = format!("{:.0}", found.score * 100.0)
+                );
+                println!("{msg}");
+                identified += 1;
+            }
+            Ok(None) => println!("{}", tr!("import-no-match", path = file.display())),
+            Err(e) => {
+                tracing::error!(file = %file.display(), error = %e, "import match failed");
+                let error = format!("{e:#}");
+                report(tr!("item-error", item = file.display(), error = error));
+                failed += 1;
+            }
+        }
+    }
+    println!(
+        "{}",
+        tr!("import-summary", matched = identified, total = files.len())
+    );
+    if failed > 0 {
+        anyhow::bail!(tr!("files-failed", failed = failed, total = files.len()));
+    }
+    Ok(())
+}
+
+// ── login / logout ──
+
+fn cmd_login(
+    music_u: Option<String>,
+    check: bool,
+    encrypt: Option<SessionEncryption>,
+) -> Result<()> {
+    use netease_api::auth::{KeySource, PASSPHRASE_ENV, Session, SessionKey};
+
+    if check {
+        let session = load_session()?;
+        match Session::encryption()? {
+            Some(KeySource::Passphrase) => println!("{}", tr!("login-encrypted-passphrase")),
+            Some(KeySource::Machine) => println!("{}", tr!("login-encrypted-machine")),
+            None => {}
+        }
+        if session.is_logged_in() {
+            let client = netease_client_builder().session(session).build()?;
+            match client.user_info() {
+                Ok(profile) => println!(
+                    "{}",
+                    tr!("login-as", name = profile.nickname, id = profile.id)
+                ),
+                Err(e) => println!("{}", tr!("login-invalid", error = e)),
+            }
+        } else {
+            println!("{}", tr!("not-logged-in"));
+        }
+        return Ok(());
+    }
+
+    let session = if let Some(music_u) = music_u {
+        Session {
+            music_u: Some(music_u),
+        }
+    } else {
+        let session = load_session()?;
+        anyhow::ensure!(session.is_logged_in(), tr!("not-logged-in"));
+        session
+    };
+    match encrypt {
+        None | Some(SessionEncryption::None) => session.save()?,
+        Some(SessionEncryption::Machine) => session.save_encrypted(&SessionKey::Machine)?,
+        Some(SessionEncryption::Passphrase) => {
+            let passphrase = match std::env::var(PASSPHRASE_ENV) {
+                Ok(p) if !p.is_empty() => p,
+                _ => {
+                    let p = rpassword::prompt_password(tr!("session-passphrase-new"))?;
+                    anyhow::ensure!(!p.is_empty(), InputError(tr!("session-passphrase-empty")));
+                    let again = rpassword::prompt_password(tr!("session-passphrase-confirm"))?;
+                    anyhow::ensure!(p == again, InputError(tr!("session-passphrase-mismatch")));
+                    p
+                }
+            };
+            session.save_encrypted(&SessionKey::Passphrase(passphrase))?;
+        }
+    }
+    println!("{}", tr!("login-saved"));
+    Ok(())
+}
+
+fn cmd_logout() -> Result<()> {
+    netease_api::auth::Session::clear()?;
+    println!("{}", tr!("logout-done"));
+    Ok(())
+}
+
+// ── search ──
+
+/// Most results `search --all` fetches.
+const SEARCH_ALL_MAX: usize = 1000;
+
+/// Page size `search --all` requests (the API maximum).
+const SEARCH_ALL_PAGE: u64 = 100;
+
+fn cmd_search(
+    keyword: &str,
+    kind: SearchKind,
+    limit: u64,
+    offset: u64,
+    all: bool,
+    fields: &[table::Field],
+) -> Result<()> {
+    if !fields.is_empty() && !matches!(kind, SearchKind::Track) {
+        return Err(InputError(tr!("fields-tracks-only")).into());
+    }
+    let client = netease_client()?;
+    let search_type = kind.into();
+
+    if !all {
+        let result = client.search(keyword, search_type, limit, offset)?;
+        if !fields.is_empty() {
+            print_track_table(result.tracks.as_deref().unwrap_or_default(), fields);
+            return Ok(());
+        }
+        println!("{}\n", tr!("total", total = result.total));
+        print_search_result(&result);
+        if limit > 0 && offset + (result.len() as u64) < result.total {
+            let page = offset / limit + 2;
+            println!("\n{}", tr!("search-more", page = page));
+        }
+        return Ok(());
+    }
+
+    // With --fields, rows are collected first so every page shares one
+    // set of column widths.
+    let mut table = Vec::new();
+    let mut shown = 0;
+    for page in client.search_pages(keyword, search_type, SEARCH_ALL_PAGE, 0) {
+        let mut page = page?;
+        if shown == 0 && fields.is_empty() {
+            println!("{}\n", tr!("total", total = page.total));
+        }
+        truncate_search_result(&mut page, SEARCH_ALL_MAX - shown);
+        shown += page.len();
+        if fields.is_empty() {
+            print_search_result(&page);
+        } else {
+            table.extend(page.tracks.unwrap_or_default());
+        }
+        if shown >= SEARCH_ALL_MAX {
+            break;
+        }
+    }
+    print_track_table(&table, fields);
+    Ok(())
+}
+
+/// Print `tracks` as an aligned table of `fields`, without any heading.
+fn print_track_table(tracks: &[netease_api::types::Track], fields: &[table::Field]) {
+    for row in table::render(tracks, fields) {
+        println!("{row}");
+    }
+}
+
+/// Drop the results of `result` past the first `n`.
+fn truncate_search_result(result: &mut netease_api::types::SearchResult, n: usize) {
+    if let Some(v) = &mut result.tracks {
+        v.truncate(n);
+    }
+    if let Some(v) = &mut result.albums {
+        v.truncate(n);
+    }
+    if let Some(v) = &mut result.artists {
+        v.truncate(n);
+    }
+    if let Some(v) = &mut result.playlists {
+        v.truncate(n);
+    }
+}
+
+fn print_search_result(result: &netease_api::types::SearchResult) {
+    if let Some(tracks) = &result.tracks {
+        for t in tracks {
+            let artists: Vec<&str> = t.artists.iter().map(|a| a.name.as_str()).collect();
+            println!(
+                "  [{}] {} - {} ({})",
+                t.id,
+                artists.join(", "),
+                t.name,
+                t.album.name,
+            );
+        }
+    }
+    if let Some(albums) = &result.albums {
+        for a in albums {
+            println!("  [{}] {}", a.id, a.name);
+        }
+    }
+    if let Some(artists) = &result.artists {
+        for a in artists {
+            println!("  [{}] {}", a.id, a.name);
+        }
+    }
+    if let Some(playlists) = &result.playlists {
+        for p in playlists {
+            let row = tr!(
+                "search-playlist",
+                id = p.id,
+                name = p.name,
+                count = p.track_count
+            );
+            println!("  {row}");
+        }
+    }
+}
+
+// ── hot ──
+
+fn cmd_hot(detail: bool, limit: Option<usize>, json: bool) -> Result<()> {
+    let client = netease_client()?;
+    let limit = limit.unwrap_or(usize::MAX);
+
+    if !detail {
+        let keywords: Vec<String> = client.hot_searches()?.into_iter().take(limit).collect();
+        if json {
+            println!("{}", serde_json::to_string_pretty(&keywords)?);
+        } else {
+            for (i, k) in keywords.iter().enumerate() {
+                println!("  {:>2}. {k}", i + 1);
+            }
+        }
+        return Ok(());
+    }
+
+    let topics: Vec<_> = client
+        .hot_search_detail()?
+        .into_iter()
+        .take(limit)
+        .collect();
+    if json {
+        println!("{}", serde_json::to_string_pretty(&topics)?);
+        return Ok(());
+    }
+    for (i, t) in topics.iter().enumerate() {
+        println!("  {:>2}. {} ({})", i + 1, t.keyword, t.score);
+        if let Some(content) = &t.content {
+            println!("      {content}");
+        }
+    }
+    Ok(())
+}
+
+// ── info / lyric / download ──
+
+fn cmd_info(track_id: u64) -> Result<()> {
+    let client = netease_client()?;
+    let t = client.track_detail(track_id)?;
+    let artists: Vec<&str> = t.artists.iter().map(|a| a.name.as_str()).collect();
+    let duration = format!(
+        "{}:{:02}",
+        t.duration_ms / 60000,
+        (t.duration_ms / 1000) % 60
+    );
+    println!("{}", tr!("info-track", name = t.name, id = t.id));
+    println!("{}", tr!("label-artists", artists = artists.join(", ")));
+    println!(
+        "{}",
+        tr!("info-album", name = t.album.name, id = t.album.id)
+    );
+    println!("{}", tr!("label-duration", duration = duration));
+    Ok(())
+}
+
+fn cmd_quality(track_id: u64, json: bool) -> Result<()> {
+    let client = netease_client()?;
+    let tiers = client.track_qualities(track_id)?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&tiers)?);
+        return Ok(());
+    }
+
+    let t = client.track_detail(track_id)?;
+    println!("{}\n", tr!("info-track", name = t.name, id = t.id));
+    for tier in &tiers {
+        let level = tier.quality.level();
+        let line = match &tier.served {
+            _ if tier.available => format!(
+                "{:<5} {:>5} kbps {:>8.1} MiB",
+                tier.format.as_deref().unwrap_or("-"),
+                tier.bitrate / 1000,
+                bytes_to_mib(tier.size)
+            ),
+            Some(served) => tr!("quality-fallback", level = served),
+            None => tr!("quality-unavailable"),
+        };
+        println!("  {level:<9} {line}");
+    }
+    Ok(())
+}
+
+fn cmd_lyric(
+    track_id: u64,
+    plain: bool,
+    offset: Option<i64>,
+    output: Option<&std::path::Path>,
+) -> Result<()> {
+    use netease_api::lrc;
+
+    let client = netease_client()?;
+    let mut lyric = client.track_lyric(track_id)?;
+    for text in [&mut lyric.lrc, &mut lyric.tlyric].into_iter().flatten() {
+        if let Some(ms) = offset {
+            *text = lrc::shift(text, ms);
+        }
+        if plain {
+            *text = lrc::strip_timestamps(text);
+        }
+    }
+
+    if let Some(path) = output {
+        let text = lyric.lrc.with_context(|| tr!("lyric-unavailable"))?;
+        std::fs::write(path, text + "\n")
+            .with_context(|| tr!("write-failed", path = path.display()))?;
+        println!("{}", tr!("lyric-saved", path = path.display()));
+        return Ok(());
+    }
+    if let Some(lrc) = &lyric.lrc {
+        println!("{lrc}");
+    }
+    if let Some(tlyric) = &lyric.tlyric {
+        println!("\n{}\n{tlyric}", tr!("lyric-translation"));
+    }
+    if lyric.lrc.is_none() && lyric.tlyric.is_none() {
+        println!("{}", tr!("lyric-none"));
+    }
+    Ok(())
+}
+
+fn cmd_comments(track_id: u64, hot: bool, limit: u64, page: u64, json: bool) -> Result<()> {
+    let client = netease_client()?;
+    let offset = page.saturating_sub(1) * limit;
+    let result = if hot {
+        client.track_hot_comments(track_id, limit, offset)?
+    } else {
+        client.track_comments(track_id, limit, offset)?
+    };
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&result)?);
+        return Ok(());
+    }
+
+    let print_comment = |c: &netease_api::types::Comment| {
+        let row = tr!(
+            "comments-row",
+            likes = c.liked_count,
+            user = c.user.name,
+            content = c.content
+        );
+        println!("  {row}");
+    };
+    println!("{}", tr!("total", total = result.total));
+    if !result.hot.is_empty() {
+        println!("\n{}", tr!("comments-hot"));
+        result.hot.iter().for_each(print_comment);
+    }
+    if !result.comments.is_empty() {
+        println!("\n{}", tr!("comments-latest"));
+        result.comments.iter().for_each(print_comment);
+    }
+    if result.more {
+        println!("\n{}", tr!("comments-more", page = page + 1));
+    }
+    Ok(())
+}
+
+fn cmd_similar(
+    track_id: u64,
+    limit: u64,
+    download: bool,
+    quality: QualityArg,
+    output: Option<PathBuf>,
+    skip: Option<SkipCheck>,
+) -> Result<()> {
+    let client = netease_client()?;
+    let seed = client.track_detail(track_id)?;
+    let seed_artists: Vec<&str> = seed.artists.iter().map(|a| a.name.as_str()).collect();
+    let msg = tr!(
+        "similar-seed",
+        artists = seed_artists.join(", "),
+        title = seed.name,
+        id = seed.id
+    );
+    println!("{msg}");
+
+    let tracks = client.similar_tracks(track_id, limit)?;
+    println!("\n{}", tr!("similar-songs"));
+    for t in &tracks {
+        let artists: Vec<&str> = t.artists.iter().map(|a| a.name.as_str()).collect();
+        println!("  [{}] {} - {}", t.id, artists.join(", "), t.name);
+    }
+
+    if let Some(artist) = seed.artists.first() {
+        // Similar artists require login; keep the song list useful without it.
+        match client.similar_artists(artist.id) {
+            Ok(artists) if !artists.is_empty() => {
+                println!("\n{}", tr!("similar-artists", name = artist.name));
+                for a in &artists {
+                    println!("  [{}] {}", a.id, a.name);
+                }
+            }
+            Ok(_) => {}
+            Err(e) => {
+                tracing::warn!(artist = artist.id, error = %e, "similar artists unavailable");
+                report(tr!("similar-artists-unavailable", error = e));
+            }
+        }
+    }
+
+    if download {
+        let out_dir = output.unwrap_or_else(|| PathBuf::from("."));
+        println!();
+        download_tracks(
+            &client,
+            &tracks,
+            quality.into(),
+            &out_dir,
+            skip,
+            DEFAULT_JOBS,
+        )?;
+    }
+    Ok(())
+}
+
+fn cmd_download(
+    track_id: u64,
+    quality: QualityArg,
+    output: Option<PathBuf>,
+    skip: Option<SkipCheck>,
+    transcode: Option<ncmdump::transcode::Target>,
+) -> Result<()> {
+    ensure_transcoder(transcode)?;
+    let client = netease_client()?;
+    let dir = output
+        .as_deref()
+        .and_then(std::path::Path::parent)
+        .filter(|d| !d.as_os_str().is_empty())
+        .unwrap_or(std::path::Path::new("."));
+    let history = open_history(dir);
+    let opts = FetchOptions {
+        transcode,
+        history: history.as_ref(),
+        ..FetchOptions::new(quality.into(), skip)
+    };
+
+    let input = track_id.to_string();
+    emit_progress(
+        "started",
+        serde_json::json!({ "id": track_id, "input": input, "index": 1, "total": 1 }),
+    );
+    let result = match opts.fetched_before(track_id) {
+        Some(fetched) => Ok(fetched),
+        None => client
+            .track_file(track_id, opts.quality)
+            .map_err(anyhow::Error::from)
+            .and_then(|file| {
+                let ext = transcode.map_or(file.extension(), |t| t.codec.extension());
+                let dest = output.unwrap_or_else(|| PathBuf::from(format!("{track_id}.{ext}")));
+                fetch_file(&client, &file, dest, opts)
+            }),
+    };
+    emit_fetched(Some(track_id), &input, &result);
+    if quiet() {
+        let mut summary = Summary::default();
+        summary.record(&input, &result);
+        summary.print("download");
+        return result.map(drop);
+    }
+    match result? {
+        Fetched::Downloaded(dest, size) => {
+            println!("{}", tr!("downloaded", path = dest.display(), bytes = size));
+        }
+        Fetched::Skipped(dest) => println!("{}", tr!("skipped-existing", path = dest.display())),
+    }
+    Ok(())
+}
+
+/// Read a `--from-file` list: one track ID or song URL per line, skipping
+/// blank lines and `#` comments.
+fn read_track_list(list: &std::path::Path) -> Result<Vec<String>> {
+    let text = String::from_utf8_lossy(&read_source(list)?).into_owned();
+    let entries: Vec<String> = text
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .map(String::from)
+        .collect();
+    if entries.is_empty() {
+        return Err(InputError(tr!("batch-no-ids", path = list.display())).into());
+    }
+    Ok(entries)
+}
+
+/// Download `entries` (track IDs or song URLs) into the output directory,
+/// `jobs` at a time.
+fn cmd_download_batch(
+    entries: &[String],
+    quality: QualityArg,
+    output: Option<PathBuf>,
+    jobs: usize,
+    skip: Option<SkipCheck>,
+    transcode: Option<ncmdump::transcode::Target>,
+) -> Result<()> {
+    ensure_transcoder(transcode)?;
+
+    let client = netease_client()?;
+    let out_dir = output.unwrap_or_else(|| PathBuf::from("."));
+    std::fs::create_dir_all(&out_dir).with_context(|| tr!("output-dir-failed"))?;
+    let history = open_history(&out_dir);
+    let opts = FetchOptions {
+        transcode,
+        history: history.as_ref(),
+        ..FetchOptions::new(quality.into(), skip)
+    };
+
+    let total = entries.len();
+    let results = ncmdump::for_each_bounded(entries, jobs.max(1), |i, entry| {
+        download_entry(&client, entry, i + 1, total, &out_dir, opts)
+    });
+    let mut summary = Summary::default();
+    for (entry, result) in entries.iter().zip(&results) {
+        summary.record(entry, result);
+    }
+    let failed = summary.failed.len();
+    if quiet() {
+        summary.print("download");
+    } else {
+        println!(
+            "\n{}",
+            tr!("batch-done", ok = total - failed, failed = failed)
+        );
+        for (entry, err) in &summary.failed {
+            report(format_args!("  {entry}: {err}"));
+        }
+    }
+    if failed > 0 {
+        anyhow::bail!(tr!("downloads-failed", failed = failed, total = total));
+    }
+    Ok(())
+}
+
+/// Download one `--from-file` entry and print its `[i/total]` status line.
+fn download_entry(
+    client: &netease_api::NeteaseClient,
+    entry: &str,
+    index: usize,
+    total: usize,
+    out_dir: &std::path::Path,
+    opts: FetchOptions,
+) -> Result<Fetched> {
+    let id = parse_ref(entry, ResourceKind::Song);
+    let id_field = id.as_ref().ok().copied();
+    emit_progress(
+        "started",
+        serde_json::json!({ "id": id_field, "input": entry, "index": index, "total": total }),
+    );
+    let result = id.and_then(|id| {
+        // Checked before `track_detail` to spare the request.
+        if let Some(fetched) = opts.fetched_before(id) {
+            return Ok(fetched);
+        }
+        let track = client.track_detail(id)?;
+        fetch_track(client, id, &track_stem(&track), out_dir, opts)
+    });
+    emit_fetched(id_field, entry, &result);
+    let line = match &result {
+        Ok(Fetched::Downloaded(dest, size)) => {
+            tracing::info!(entry, dest = %dest.display(), size, "downloaded");
+            tr!(
+                "batch-ok",
+                index = index,
+                total = total,
+                input = entry,
+                path = dest.display()
+            )
+        }
+        Ok(Fetched::Skipped(dest)) => {
+            tracing::info!(entry, dest = %dest.display(), "skipped existing");
+            tr!(
+                "batch-skip",
+                index = index,
+                total = total,
+                input = entry,
+                path = dest.display()
+            )
+        }
+        Err(e) => {
+            let error = format!("{e:#}");
+            tracing::error!(entry, error, "download failed");
+            tr!(
+                "batch-fail",
+                index = index,
+                total = total,
+                input = entry,
+                error = error
+            )
+        }
+    };
+    if !quiet() {
+        println!("{line}");
+    }
+    result
+}
+
+// ── play ──
+
+fn cmd_play(
+    track_id: Option<u64>,
+    fm: bool,
+    quality: QualityArg,
+    player: Option<&str>,
+) -> Result<()> {
+    let client = netease_client()?;
+    let q: netease_api::types::Quality = quality.into();
+    let player = player_command(player)?;
+
+    if !fm {
+        let id = track_id.with_context(|| tr!("play-missing-id"))?;
+        let track = client.track_detail(id)?;
+        let url = client.track_url(id, q)?;
+        return play_url(&player, &track, &url);
+    }
+
+    play_fm(&client, &player, q)
+}
+
+/// Play personal-FM batches until the player fails or is interrupted.
+fn play_fm(
+    client: &netease_api::NeteaseClient,
+    player: &[String],
+    q: netease_api::types::Quality,
+) -> Result<()> {
+    loop {
+        let batch = client.personal_fm()?;
+        if batch.is_empty() {
+            anyhow::bail!(tr!("play-fm-empty"));
+        }
+        for t in &batch {
+            // FM batches regularly contain tracks we can't stream; move on.
+            match client.track_url(t.id, q) {
+                Ok(url) => play_url(player, t, &url)?,
+                Err(e) => {
+                    tracing::warn!(track = t.id, error = %e, "skipping unplayable track");
+                    report(tr!("play-skip", id = t.id, name = t.name, error = e));
+                }
+            }
+        }
+    }
+}
+
+// ── fm ──
+
+fn cmd_fm(
+    play: bool,
+    like: &[String],
+    trash: &[String],
+    quality: QualityArg,
+    player: Option<&str>,
+) -> Result<()> {
+    let client = netease_client()?;
+    for input in like {
+        let id = parse_ref(input, ResourceKind::Song)?;
+        client.like_track(id, true)?;
+        println!("{}", tr!("fm-liked", id = id));
+    }
+    for input in trash {
+        let id = parse_ref(input, ResourceKind::Song)?;
+        client.fm_trash(id)?;
+        println!("{}", tr!("fm-trashed", id = id));
+    }
+
+    if play {
+        let player = player_command(player)?;
+        return play_fm(&client, &player, quality.into());
+    }
+    let batch = client.personal_fm()?;
+    if batch.is_empty() {
+        anyhow::bail!(tr!("play-fm-empty"));
+    }
+    for t in &batch {
+        let artists: Vec<&str> = t.artists.iter().map(|a| a.name.as_str()).collect();
+        println!(
+            "  [{}] {} - {} ({})",
+            t.id,
+            artists.join(", "),
+            t.name,
+            t.album.name
+        );
+    }
+    Ok(())
+}
+
+/// Resolve the player argv: `--player`/`NCMDUMP_PLAYER` split on whitespace,
+/// otherwise the first of mpv/ffplay found in `PATH`.
+fn player_command(custom: Option<&str>) -> Result<Vec<String>> {
+    if let Some(cmd) = custom {
+        let argv: Vec<String> = cmd.split_whitespace().map(String::from).collect();
+        anyhow::ensure!(!argv.is_empty(), tr!("play-empty-player"));
+        return Ok(argv);
+    }
+    let candidates: [(&str, &[&str]); 2] = [
+        ("mpv", &["--no-video", "--really-quiet"]),
+        ("ffplay", &["-nodisp", "-autoexit", "-loglevel", "quiet"]),
+    ];
+    candidates
+        .iter()
+        .find(|(program, _)| {
+            std::process::Command::new(program)
+                .arg("-version")
+                .stdout(std::process::Stdio::null())
+                .stderr(std::process::Stdio::null())
+                .status()
+                .is_ok()
+        })
+        .map(|(program, args)| {
+            std::iter::once(*program)
+                .chain(args.iter().copied())
+                .map(String::from)
+                .collect()
+        })
+        .with_context(|| tr!("play-no-player"))
+}
+
+/// Run the player on `url` and wait for it to finish.
+fn play_url(player: &[String], track: &netease_api::types::Track, url: &str) -> Result<()> {
+    let artists: Vec<&str> = track.artists.iter().map(|a| a.name.as_str()).collect();
+    let msg = tr!(
+        "play-now",
+        artists = artists.join(", "),
+        title = track.name,
+        id = track.id
+    );
+    println!("{msg}");
+    tracing::debug!(track = track.id, player = ?player, "starting player");
+    let status = std::process::Command::new(&player[0])
+        .args(&player[1..])
+        .arg(url)
+        .status()
+        .with_context(|| tr!("play-run-failed", program = player[0]))?;
+    anyhow::ensure!(
+        status.success(),
+        tr!("play-exit", program = player[0], status = status)
+    );
+    Ok(())
+}
+
+// ── mv ──
+
+fn cmd_mv(mv_id: u64, resolution: MvResolutionArg, output: Option<PathBuf>) -> Result<()> {
+    let client = netease_client()?;
+    let mv = client.mv_detail(mv_id)?;
+    let artists: Vec<&str> = mv.artists.iter().map(|a| a.name.as_str()).collect();
+    let title = tr!(
+        "mv-title",
+        artists = artists.join(", "),
+        name = mv.name,
+        id = mv.id
+    );
+    println!("{title}");
+    let available: Vec<String> = mv.resolutions.iter().map(|r| format!("{r}p")).collect();
+    println!("{}", tr!("mv-resolutions", list = available.join(", ")));
+
+    let dest = output.unwrap_or_else(|| PathBuf::from(format!("{mv_id}.mp4")));
+    let size = client.download_mv(mv_id, resolution.into(), &dest)?;
+    println!("{}", tr!("downloaded", path = dest.display(), bytes = size));
+    Ok(())
+}
+
+// ── album ──
+
+fn cmd_album(
+    album_id: u64,
+    download: bool,
+    fields: &[table::Field],
+    quality: QualityArg,
+    output: Option<PathBuf>,
+    skip: Option<SkipCheck>,
+    jobs: usize,
+) -> Result<()> {
+    let client = netease_client()?;
+    let a = client.album_detail(album_id)?;
+    if !fields.is_empty() {
+        print_track_table(&a.tracks, fields);
+        return Ok(());
+    }
+    let artists: Vec<&str> = a.artists.iter().map(|x| x.name.as_str()).collect();
+    println!("{}", tr!("album-name", name = a.name, id = a.id));
+    println!("{}", tr!("label-artists", artists = artists.join(", ")));
+    println!(
+        "{}",
+        tr!("album-released", date = format_date(a.publish_time))
+    );
+    if let Some(company) = &a.company {
+        println!("{}", tr!("album-label", company = company));
+    }
+    println!("{}", tr!("label-tracks", count = a.tracks.len()));
+    println!();
+    for (i, t) in a.tracks.iter().enumerate() {
+        let artists: Vec<&str> = t.artists.iter().map(|x| x.name.as_str()).collect();
+        println!(
+            "  {:>2}. [{}] {} - {} ({}:{:02})",
+            i + 1,
+            t.id,
+            artists.join(", "),
+            t.name,
+            t.duration_ms / 60000,
+            (t.duration_ms / 1000) % 60
+        );
+    }
+
+    if download {
+        let out_dir = output.unwrap_or_else(|| PathBuf::from("."));
+        println!();
+        download_tracks(&client, &a.tracks, quality.into(), &out_dir, skip, jobs)?;
+    }
+    Ok(())
+}
+
+// ── artist ──
+
+fn cmd_artist(artist_id: u64, album_limit: u64) -> Result<()> {
+    let client = netease_client()?;
+    let a = client.artist_detail(artist_id)?;
+    println!("{}", tr!("artist-name", name = a.name, id = a.id));
+    println!("{}", tr!("artist-albums", count = a.album_count));
+    println!("{}", tr!("artist-songs", count = a.track_count));
+    if let Some(desc) = &a.brief_desc {
+        println!("{}", tr!("artist-desc", desc = desc));
+    }
+
+    println!("\n{}", tr!("artist-top"));
+    for t in &a.top_tracks {
+        println!(
+            "  [{}] {} ({}) {}:{:02}",
+            t.id,
+            t.name,
+            t.album.name,
+            t.duration_ms / 60000,
+            (t.duration_ms / 1000) % 60
+        );
+    }
+
+    let albums = client.artist_albums(artist_id, album_limit, 0)?;
+    println!("\n{}", tr!("artist-recent"));
+    for al in &albums {
+        let row = tr!(
+            "artist-album",
+            id = al.id,
+            name = al.name,
+            date = format_date(al.publish_time),
+            count = al.track_count
+        );
+        println!("  {row}");
+    }
+    Ok(())
+}
+
+/// Format a Netease epoch-milliseconds timestamp as `YYYY-MM-DD`.
+fn format_date(ms: u64) -> String {
+    i64::try_from(ms)
+        .ok()
+        .and_then(chrono::DateTime::from_timestamp_millis)
+        .map_or_else(|| "-".to_owned(), |d| d.format("%Y-%m-%d").to_string())
+}
+
+// ── playlist ──
+
+fn cmd_playlist(
+    playlist_id: u64,
+    download: bool,
+    fields: &[table::Field],
+    quality: QualityArg,
+    output: Option<PathBuf>,
+    skip: Option<SkipCheck>,
+    jobs: usize,
+) -> Result<()> {
+    let client = netease_client()?;
+    let p = client.playlist_detail(playlist_id)?;
+    if !fields.is_empty() {
+        print_track_table(p.tracks.as_deref().unwrap_or_default(), fields);
+        return Ok(());
+    }
+    println!("{}", tr!("playlist-name", name = p.name, id = p.id));
+    println!("{}", tr!("label-tracks", count = p.track_count));
+    if let Some(desc) = &p.description {
+        println!("{}", tr!("label-desc", desc = desc));
+    }
+    if let Some(creator) = &p.creator {
+        let msg = tr!("playlist-creator", name = creator.name, id = creator.id);
+        println!("{msg}");
+    }
+    if let Some(tracks) = &p.tracks {
+        println!();
+        for t in tracks {
+            let artists: Vec<&str> = t.artists.iter().map(|a| a.name.as_str()).collect();
+            println!("  [{}] {} - {}", t.id, artists.join(", "), t.name);
+        }
+    }
+
+    if download {
+        let out_dir = output.unwrap_or_else(|| PathBuf::from("."));
+        println!();
+        download_tracks(
+            &client,
+            p.tracks.as_deref().unwrap_or_default(),
+            quality.into(),
+            &out_dir,
+            skip,
+            jobs,
+        )?;
+    }
+    Ok(())
+}
+
+// ── toplist ──
+
+fn cmd_toplist(
+    chart: Option<&str>,
+    download: bool,
+    limit: Option<usize>,
+    quality: QualityArg,
+    output: Option<PathBuf>,
+    skip: Option<SkipCheck>,
+    jobs: usize,
+) -> Result<()> {
+    let client = netease_client()?;
+    let charts = client.toplists()?;
+
+    let Some(chart) = chart else {
+        for c in &charts {
+            println!(
+                "  [{}] {} ({})",
+                c.id,
+                c.name,
+                c.update_frequency.as_deref().unwrap_or("-")
+            );
+        }
+        return Ok(());
+    };
+
+    let selected = charts
+        .iter()
+        .find(|c| c.name == chart || c.id.to_string() == chart)
+        .with_context(|| tr!("toplist-unknown", chart = chart))?;
+    let playlist = client.playlist_detail(selected.id)?;
+    let mut tracks = playlist.tracks.unwrap_or_default();
+    if let Some(n) = limit {
+        tracks.truncate(n);
+    }
+
+    let title = tr!("toplist-chart", name = selected.name, id = selected.id);
+    println!("{title}\n");
+    for (i, t) in tracks.iter().enumerate() {
+        let artists: Vec<&str> = t.artists.iter().map(|a| a.name.as_str()).collect();
+        println!(
+            "  {:>3}. [{}] {} - {}",
+            i + 1,
+            t.id,
+            artists.join(", "),
+            t.name
+        );
+    }
+
+    if download {
+        let out_dir = output.unwrap_or_else(|| PathBuf::from("."));
+        println!();
+        download_tracks(&client, &tracks, quality.into(), &out_dir, skip, jobs)?;
+    }
+    Ok(())
+}
+
+/// Concurrent downloads of commands without a `--jobs` flag.
+const DEFAULT_JOBS: usize = 4;
+
+/// Download tracks into `out_dir` as `<artists> - <title>.<ext>`, `jobs` at a
+/// time, reporting per-track failures without aborting the batch.
+///
+/// The tracks go through the download queue of `out_dir`, so tracks left
+/// over from an interrupted run are finished first and failed ones are
+/// retried up to `--retries` times.
+fn download_tracks(
+    client: &netease_api::NeteaseClient,
+    tracks: &[netease_api::types::Track],
+    quality: netease_api::types::Quality,
+    out_dir: &std::path::Path,
+    skip: Option<SkipCheck>,
+    jobs: usize,
+) -> Result<()> {
+    use std::sync::Mutex;
+
+    std::fs::create_dir_all(out_dir).with_context(|| tr!("output-dir-failed"))?;
+    let history = open_history(out_dir);
+    let opts = FetchOptions {
+        history: history.as_ref(),
+        ..FetchOptions::new(quality, skip)
+    };
+    let entries = tracks.iter().map(|t| (t.id, t.name.clone(), track_stem(t)));
+    let retries = RETRIES.get().copied().unwrap_or_default();
+    let queue = queue::Queue::open(out_dir, entries, retries).with_context(|| {
+        tr!(
+            "queue-failed",
+            path = out_dir.join(queue::FILE_NAME).display()
+        )
+    })?;
+    if queue.resumed() > 0 {
+        tracing::info!(count = queue.resumed(), dir = %out_dir.display(), "resuming download queue");
+        if !quiet() {
+            println!("{}", tr!("queue-resumed", count = queue.resumed()));
+        }
+    }
+
+    let total = queue.len();
+    let summary = Mutex::new(Summary::default());
+    // Failed entries go back into the queue, so workers pull from it rather
+    // than walking a fixed list.
+    ncmdump::for_each_bounded_from(
+        jobs.clamp(1, total.max(1)),
+        || queue.next(),
+        |(i, entry)| {
+            if entry.attempts > 0 {
+                std::thread::sleep(queue::RETRY_DELAY * entry.attempts);
+            }
+            let result = download_queued(client, &entry, i + 1, total, out_dir, opts);
+            let error = result.as_ref().err().map(|e| format!("{e:#}"));
+            if queue.finish(i, error) {
+                return;
+            }
+            summary
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner)
+                .record(format!("{} ({})", entry.id, entry.name), &result);
+        },
+    );
+
+    let summary = summary
+        .into_inner()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+    summary.print("download");
+    if !summary.failed.is_empty() {
+        let failed = summary.failed.len();
+        anyhow::bail!(tr!("downloads-failed", failed = failed, total = total));
+    }
+    Ok(())
+}
+
+/// Download one queue entry and print its outcome.
+fn download_queued(
+    client: &netease_api::NeteaseClient,
+    entry: &queue::Entry,
+    index: usize,
+    total: usize,
+    out_dir: &std::path::Path,
+    opts: FetchOptions,
+) -> Result<Fetched> {
+    let id = entry.id;
+    let input = id.to_string();
+    emit_progress(
+        "started",
+        serde_json::json!({ "id": id, "input": input, "index": index, "total": total }),
+    );
+    let result = fetch_track(client, id, &entry.stem, out_dir, opts);
+    emit_fetched(Some(id), &input, &result);
+    match &result {
+        Ok(Fetched::Downloaded(dest, size)) => {
+            tracing::info!(track = id, dest = %dest.display(), size, "downloaded");
+            if !quiet() {
+                println!("{}", tr!("downloaded", path = dest.display(), bytes = size));
+            }
+        }
+        Ok(Fetched::Skipped(dest)) => {
+            tracing::info!(track = id, dest = %dest.display(), "skipped existing");
+            if !quiet() {
+                println!("{}", tr!("skipped-existing", path = dest.display()));
+            }
+        }
+        Err(e) => {
+            let error = format!("{e:#}");
+            tracing::error!(
+                track = id,
+                attempt = entry.attempts + 1,
+                error,
+                "download failed"
+            );
+            if !quiet() {
+                report(tr!(
+                    "track-error",
+                    id = id,
+                    name = entry.name,
+                    error = error
+                ));
+            }
+        }
+    }
+    result
+}
+
+/// Outcome of [`fetch_track`].
+enum Fetched {
+    Downloaded(PathBuf, u64),
+    Skipped(PathBuf),
+}
+
+/// File name stem for a track, rendered with the default name template.
+fn track_stem(track: &netease_api::types::Track) -> String {
+    render_track_name(ncmdump::template::DEFAULT_TEMPLATE, track)
+        .unwrap_or_else(|_| track.id.to_string())
+}
+
+/// Expand a name template with a track's API metadata.
+fn render_track_name(template: &str, track: &netease_api::types::Track) -> ncmdump::Result<String> {
+    let artists: Vec<&str> = track.artists.iter().map(|a| a.name.as_str()).collect();
+    let artists = artists.join(", ");
+    ncmdump::template::render(template, |name| match name {
+        "artist" => Some(artists.clone()),
+        "title" => Some(track.name.clone()),
+        "album" => Some(track.album.name.clone()),
+        _ => None,
+    })
+}
+
+/// How [`fetch_track`] / [`fetch_file`] download a track.
+#[derive(Clone, Copy)]
+struct FetchOptions<'a> {
+    quality: netease_api::types::Quality,
+    skip: Option<SkipCheck>,
+    transcode: Option<ncmdump::transcode::Target>,
+    /// History of the output directory, consulted and updated per track.
+    history: Option<&'a history::History>,
+}
+
+impl FetchOptions<'_> {
+    fn new(quality: netease_api::types::Quality, skip: Option<SkipCheck>) -> Self {
+        Self {
+            quality,
+            skip,
+            transcode: None,
+            history: None,
+        }
+    }
+
+    /// The file the history says track `id` was already saved as.
+    fn fetched_before(&self, id: u64) -> Option<Fetched> {
+        let path = self.history?.find(id, self.quality.bitrate())?;
+        tracing::info!(track = id, path = %path.display(), "already in download history");
+        Some(Fetched::Skipped(path))
+    }
+}
+
+/// The download history of `dir`, unless `--no-history` is given.
+fn open_history(dir: &std::path::Path) -> Option<history::History> {
+    open_library().map(|library| history::History::with_library(library, dir))
+}
+
+/// The library database, unless `--no-history` is given or it cannot be
+/// opened.
+fn open_library() -> Option<std::sync::Arc<ncmdump_db::Library>> {
+    (NO_HISTORY.get() != Some(&true))
+        .then(history::library)
+        .flatten()
+}
+
+/// Download track `id` into `out_dir` as `<stem>.<ext>`, where `<ext>` is the
+/// server's format or the `--transcode` codec's. Tracks found in the download
+/// history are skipped without asking the server.
+fn fetch_track(
+    client: &netease_api::NeteaseClient,
+    id: u64,
+    stem: &str,
+    out_dir: &std::path::Path,
+    opts: FetchOptions,
+) -> Result<Fetched> {
+    if let Some(fetched) = opts.fetched_before(id) {
+        return Ok(fetched);
+    }
+    let file = client.track_file(id, opts.quality)?;
+    let ext = opts
+        .transcode
+        .map_or(file.extension(), |t| t.codec.extension());
+    let name = ncmdump::path::with_extension(std::ffi::OsStr::new(stem), ext);
+    fetch_file(client, &file, out_dir.join(name), opts)
+}
+
+/// Download `file` to `dest`, unless `opts.skip` finds an up-to-date copy,
+/// and record it in `opts.history`.
+///
+/// With `opts.transcode`, the download is piped through ffmpeg into
+/// `<dest>.part` as it arrives and renamed once complete, so the original is
+/// never written to disk; such a transfer starts over rather than resuming.
+/// Files already in the target codec are kept as downloaded unless a
+/// bitrate is given.
+fn fetch_file(
+    client: &netease_api::NeteaseClient,
+    file: &netease_api::types::TrackFile,
+    dest: PathBuf,
+    opts: FetchOptions,
+) -> Result<Fetched> {
+    let target = opts
+        .transcode
+        .filter(|t| t.bitrate.is_some() || t.codec.extension() != file.extension());
+    if let Some(check) = opts.skip {
+        // A transcoded file can't be compared with the server's size or hash.
+        let done = match target {
+            Some(_) => dest.exists(),
+            None => is_up_to_date(&dest, file, check),
+        };
+        if done {
+            return Ok(Fetched::Skipped(dest));
+        }
+    }
+
+    let mut part = dest.as_os_str().to_owned();
+    part.push(".part");
+    let size = if let Some(target) = target {
+        let stream = client.open_stream(&file.url, None)?;
+        let total = stream.content_length;
+        let mut reader = Transferred {
+            inner: stream,
+            transfer: Transfer::new(file, 0),
+            done: 0,
+            total,
+        };
+        transcode_into(&mut reader, std::path::Path::new(&part), target)?;
+        std::fs::rename(&part, &dest)
+            .with_context(|| tr!("write-failed", path = dest.display()))?;
+        std::fs::metadata(&dest).map_or(0, |m| m.len())
+    } else {
+        // Progress includes what a resumed download already had on disk.
+        let resumed = std::fs::metadata(&part).map_or(0, |m| m.len());
+        let mut transfer = Transfer::new(file, resumed);
+        client.download_resumable_with_progress(&file.url, &dest, |done, total| {
+            transfer.update(done, total);
+        })?
+    };
+    if let Some(history) = opts.history {
+        if let Err(e) = history.record(file.id, &dest, opts.quality.bitrate()) {
+            tracing::warn!(track = file.id, error = %e, "failed to update download history");
+        }
+    }
+    run_hooks(hooks::Event::Download, &dest, None, Some(file.id));
+    Ok(Fetched::Downloaded(dest, size))
+}
+
+/// Fail early when `--transcode` is given but ffmpeg is missing.
+fn ensure_transcoder(target: Option<ncmdump::transcode::Target>) -> Result<()> {
+    if target.is_some() && !ncmdump::transcode::available() {
+        anyhow::bail!(tr!("transcode-no-ffmpeg"));
+    }
+    Ok(())
+}
+
+/// Rate limiting and progress events for the transfer of one track.
+struct Transfer<'a> {
+    file: &'a netease_api::types::TrackFile,
+    /// Bytes already accounted to the rate limit.
+    counted: u64,
+    last_percent: Option<u64>,
+}
+
+impl<'a> Transfer<'a> {
+    /// A transfer continuing after `resumed` bytes.
+    fn new(file: &'a netease_api::types::TrackFile, resumed: u64) -> Self {
+        Self {
+            file,
+            counted: resumed,
+            last_percent: None,
+        }
+    }
+
+    /// Record that `done` of `total` bytes have arrived.
+    fn update(&mut self, done: u64, total: Option<u64>) {
+        if let Some(limit) = RATE_LIMIT.get().and_then(Option::as_ref) {
+            limit.consume(done.saturating_sub(self.counted));
+            self.counted = done;
+        }
+        let Some(total) = total.or(Some(self.file.size)).filter(|&t| t > 0) else {
+            return;
+        };
+        let percent = (done * 100 / total).min(100);
+        if self.last_percent != Some(percent) {
+            self.last_percent = Some(percent);
+            emit_progress(
+                "progress",
+                serde_json::json!({
+                    "id": self.file.id, "percent": percent, "bytes": done, "total_bytes": total,
+                }),
+            );
+        }
+    }
+}
+
+/// A download read through a [`Transfer`], so rate limit and progress cover
+/// the whole pipeline it feeds.
+struct Transferred<'a, R> {
+    inner: R,
+    transfer: Transfer<'a>,
+    done: u64,
+    total: Option<u64>,
+}
+
+impl<R: std::io::Read> std::io::Read for Transferred<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.done += n as u64;
+        self.transfer.update(self.done, self.total);
+        Ok(n)
+    }
+}
+
+/// Transcode `input` into `dest` with ffmpeg as it arrives, keeping tags
+/// (and the cover where the container supports it).
+fn transcode_into(
+    input: &mut impl std::io::Read,
+    dest: &std::path::Path,
+    target: ncmdump::transcode::Target,
+) -> Result<()> {
+    tracing::debug!(dest = %dest.display(), "transcoding download");
+    match ncmdump::transcode::transcode_into(input, dest, target) {
+        Ok(()) => Ok(()),
+        Err(ncmdump::NcmError::Transcode(error)) => {
+            anyhow::bail!(tr!("transcode-failed", error = error))
+        }
+        Err(e) => Err(e).with_context(|| tr!("transcode-stream-failed")),
+    }
+}
+
+/// Whether `path` already holds the server's file according to `check`.
+///
+/// Size and hash checks degrade to an existence check when the server does
+/// not report the corresponding value.
+fn is_up_to_date(
+    path: &std::path::Path,
+    file: &netease_api::types::TrackFile,
+    check: SkipCheck,
+) -> bool {
+    use md5::{Digest, Md5};
+
+    let Ok(meta) = std::fs::metadata(path) else {
+        return false;
+    };
+    match check {
+        SkipCheck::Exists => true,
+        SkipCheck::Size => file.size == 0 || meta.len() == file.size,
+        SkipCheck::Hash => file.md5.as_ref().is_none_or(|expected| {
+            std::fs::read(path).is_ok_and(|bytes| format!("{:x}", Md5::digest(&bytes)) == *expected)
+        }),
+    }
+}
+
+// ── podcast ──
+
+fn cmd_podcast(action: PodcastAction) -> Result<()> {
+    let client = netease_client()?;
+    match action {
+        PodcastAction::Info { rid } => {
+            let r = client.radio_detail(rid)?;
+            println!("{}", tr!("podcast-radio", name = r.name, id = r.id));
+            if let Some(dj) = &r.dj {
+                println!("{}", tr!("podcast-dj", name = dj.name, id = dj.id));
+            }
+            if let Some(category) = &r.category {
+                println!("{}", tr!("podcast-category", category = category));
+            }
+            println!("{}", tr!("podcast-programs", count = r.program_count));
+            println!("{}", tr!("podcast-subs", count = r.subscriber_count));
+            if let Some(desc) = &r.description {
+                println!("{}", tr!("label-desc", desc = desc));
+            }
+        }
+        PodcastAction::Episodes {
+            rid,
+            limit,
+            page,
+            asc,
+        } => {
+            let offset = page.saturating_sub(1) * limit;
+            let result = client.radio_programs(rid, limit, offset, asc)?;
+            println!("{}\n", tr!("total", total = result.count));
+            for p in &result.programs {
+                println!(
+                    "  #{} [{}] {} ({}:{:02}, track={})",
+                    p.serial_num,
+                    p.id,
+                    p.name,
+                    p.duration_ms / 60000,
+                    (p.duration_ms / 1000) % 60,
+                    p.track_id
+                );
+            }
+        }
+        PodcastAction::Download {
+            rid,
+            latest,
+            quality,
+            output,
+            skip_existing,
+        } => {
+            let out_dir = output.unwrap_or_else(|| PathBuf::from("."));
+            std::fs::create_dir_all(&out_dir).with_context(|| tr!("output-dir-failed"))?;
+
+            let mut programs = Vec::new();
+            let mut offset = 0;
+            loop {
+                let page = client.radio_programs(rid, 100, offset, false)?;
+                let fetched = page.programs.len() as u64;
+                offset += fetched;
+                programs.extend(page.programs);
+                let enough = latest.is_some_and(|n| programs.len() as u64 >= n);
+                if !page.more || enough || fetched == 0 {
+                    break;
+                }
+            }
+            if let Some(n) = latest {
+                programs.truncate(usize::try_from(n).unwrap_or(usize::MAX));
+            }
+
+            let history = open_history(&out_dir);
+            let opts = FetchOptions {
+                history: history.as_ref(),
+                ..FetchOptions::new(quality.into(), skip_existing)
+            };
+            download_programs(&client, &programs, &out_dir, opts)?;
+        }
+    }
+    Ok(())
+}
+
+/// Download podcast programs into `out_dir` as `<serial> - <name>.<ext>`.
+fn download_programs(
+    client: &netease_api::NeteaseClient,
+    programs: &[netease_api::types::Program],
+    out_dir: &std::path::Path,
+    opts: FetchOptions,
+) -> Result<()> {
+    let total = programs.len();
+    let mut summary = Summary::default();
+    for (i, p) in programs.iter().enumerate() {
+        let name = ncmdump::template::sanitize(&format!("{:03} - {}", p.serial_num, p.name));
+        let stem = ncmdump::path::component(&name);
+        let input = p.track_id.to_string();
+        emit_progress(
+            "started",
+            serde_json::json!({
+                "id": p.track_id, "input": input, "index": i + 1, "total": total,
+            }),
+        );
+        let result = fetch_track(client, p.track_id, &stem, out_dir, opts);
+        emit_fetched(Some(p.track_id), &input, &result);
+        match &result {
+            Ok(Fetched::Downloaded(dest, size)) => {
+                tracing::info!(program = p.id, dest = %dest.display(), size, "downloaded");
+                if !quiet() {
+                    println!("{}", tr!("downloaded", path = dest.display(), bytes = size));
+                }
+            }
+            Ok(Fetched::Skipped(dest)) => {
+                tracing::info!(program = p.id, dest = %dest.display(), "skipped existing");
+                if !quiet() {
+                    println!("{}", tr!("skipped-existing", path = dest.display()));
+                }
+            }
+            Err(e) => {
+                tracing::error!(program = p.id, error = format!("{e:#}"), "download failed");
+                if !quiet() {
+                    let error = format!("{e:#}");
+                    report(tr!(
+                        "program-error",
+                        id = p.id,
+                        name = p.name,
+                        error = error
+                    ));
+                }
+            }
+        }
+        summary.record(format!("{} ({})", p.id, p.name), &result);
+    }
+    summary.print("download");
+    if !summary.failed.is_empty() {
+        let failed = summary.failed.len();
+        anyhow::bail!(tr!("downloads-failed", failed = failed, total = total));
+    }
+    Ok(())
+}
+
+// ── open ──
+
+fn cmd_open(target: &str, kind: ResourceKind, print: bool) -> Result<()> {
+    let (kind, id) = match target.parse() {
+        Ok(id) => (kind, id),
+        Err(_) => {
+            link_target(target)?.ok_or_else(|| InputError(tr!("open-invalid", input = target)))?
+        }
+    };
+    let url = kind.page_url(id);
+    if print {
+        println!("{url}");
+        return Ok(());
+    }
+    println!("{}", tr!("open-opening", url = url));
+    open_in_browser(&url)
+}
+
+/// Parse an ID argument: a bare numeric ID, or a link to a `kind` resource
+/// (see [`link_target`]).
+fn parse_ref(input: &str, kind: ResourceKind) -> Result<u64> {
+    if let Ok(id) = input.trim().parse() {
+        return Ok(id);
+    }
+    match link_target(input)? {
+        Some((k, id)) if k == kind => Ok(id),
+        _ => Err(InputError(tr!("ref-invalid", kind = kind.path(), input = input)).into()),
+    }
+}
+
+/// The resource a music.163.com link points at. `input` may be share text
+/// with the link embedded; `163cn.tv` / `163.cn` short links are followed
+/// over the network.
+fn link_target(input: &str) -> Result<Option<(ResourceKind, u64)>> {
+    let url = input.find("http").map_or(input.trim(), |start| {
+        input[start..]
+            .split(|c: char| c.is_whitespace() || c == '(' || c == '（')
+            .next()
+            .unwrap_or_default()
+    });
+    let host = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let host = host.split('/').next().unwrap_or_default();
+    if SHORT_LINK_HOSTS.contains(&host) {
+        let target = netease_client()?.resolve_link(url)?;
+        return Ok(parse_resource_url(&target));
+    }
+    Ok(parse_resource_url(url))
+}
+
+/// Hosts of the app's share short links.
+const SHORT_LINK_HOSTS: [&str; 2] = ["163cn.tv", "163.cn"];
+
+/// Extract the resource kind and ID from a web/app URL such as
+/// `https://music.163.com/#/playlist?id=1`, `https://y.music.163.com/m/song?id=1`
+/// or `https://music.163.com/song/1/?userid=2`.
+fn parse_resource_url(url: &str) -> Option<(ResourceKind, u64)> {
+    let (path, query) = url.split_once('?').unwrap_or((url, ""));
+    let mut segments = path.rsplit(['/', '#']).filter(|s| !s.is_empty());
+    let last = segments.next()?;
+    if let Ok(id) = last.parse() {
+        return Some((ResourceKind::from_path(segments.next()?)?, id));
+    }
+    let kind = ResourceKind::from_path(last)?;
+    let id = query
+        .split(['&', '#'])
+        .find_map(|part| part.strip_prefix("id="))?
+        .parse()
+        .ok()?;
+    Some((kind, id))
+}
+
+/// Open `url` with the platform's default handler.
+fn open_in_browser(url: &str) -> Result<()> {
+    let mut cmd = if cfg!(target_os = "windows") {
+        let mut c = std::process::Command::new("cmd");
+        c.args(["/C", "start", ""]);
+        c
+    } else if cfg!(target_os = "macos") {
+        std::process::Command::new("open")
+    } else {
+        std::process::Command::new("xdg-open")
+    };
+    let status = cmd
+        .arg(url)
+        .status()
+        .with_context(|| tr!("open-launch-failed"))?;
+    anyhow::ensure!(status.success(), tr!("open-launcher-exit", status = status));
+    Ok(())
+}
+
+// ── doctor ──
+
+/// Outcome of one `doctor` check; problems carry a suggested fix.
+enum Check {
+    Ok(String),
+    Warn(String, String),
+    Fail(String, String),
+}
+
+impl Check {
+    fn print(&self) {
+        let (label, msg, fix) = match self {
+            Self::Ok(msg) => (" ok ", msg, None),
+            Self::Warn(msg, fix) => ("warn", msg, Some(fix)),
+            Self::Fail(msg, fix) => ("FAIL", msg, Some(fix)),
+        };
+        println!("[{label}] {msg}");
+        if let Some(fix) = fix {
+            println!("       {}", tr!("doctor-fix", fix = fix));
+        }
+    }
+}
+
+fn cmd_doctor() -> Result<()> {
+    let checks = [
+        doctor_config_dir(),
+        doctor_proxy(),
+        doctor_network(),
+        doctor_session(),
+        doctor_output_dir(),
+        doctor_tools(),
+    ];
+    for check in &checks {
+        check.print();
+    }
+    let failed = checks
+        .iter()
+        .filter(|c| matches!(c, Check::Fail(..)))
+        .count();
+    if failed > 0 {
+        anyhow::bail!(tr!("doctor-failed", count = failed));
+    }
+    Ok(())
+}
+
+fn doctor_config_dir() -> Check {
+    let dir = match netease_api::auth::Session::path() {
+        Ok(path) => path.parent().map(PathBuf::from).unwrap_or_default(),
+        Err(e) => {
+            return Check::Fail(
+                tr!("doctor-config-error", error = e),
+                tr!("doctor-config-error-fix"),
+            );
+        }
+    };
+    match std::fs::create_dir_all(&dir).and_then(|()| probe_writable(&dir)) {
+        Ok(()) => Check::Ok(tr!("doctor-config-ok", dir = dir.display())),
+        Err(e) => Check::Fail(
+            tr!("doctor-config-readonly", dir = dir.display(), error = e),
+            tr!("doctor-config-readonly-fix", dir = dir.display()),
+        ),
+    }
+}
+
+fn doctor_proxy() -> Check {
+    let from_flag = PROXY.get().and_then(Option::as_deref);
+    let from_env = ["HTTPS_PROXY", "https_proxy", "ALL_PROXY", "all_proxy"]
+        .iter()
+        .find_map(|var| std::env::var(var).ok().map(|v| (*var, v)));
+    let (source, url) = match (from_flag, from_env) {
+        (Some(url), _) => ("--proxy", url.to_owned()),
+        (None, Some((var, url))) => (var, url),
+        (None, None) => return Check::Ok(tr!("doctor-proxy-none")),
+    };
+    let scheme = url.split_once("://").map(|(s, _)| s.to_ascii_lowercase());
+    match scheme.as_deref() {
+        Some("http" | "https" | "socks5" | "socks5h") => {
+            Check::Ok(tr!("doctor-proxy-ok", url = url, source = source))
+        }
+        _ => Check::Fail(
+            tr!("doctor-proxy-invalid", url = url, source = source),
+            tr!("doctor-proxy-fix"),
+        ),
+    }
+}
+
+fn doctor_network() -> Check {
+    let fix = tr!("doctor-network-fix");
+    let client = match netease_client() {
+        Ok(c) => c,
+        Err(e) => {
+            return Check::Fail(tr!("doctor-network-client", error = format!("{e:#}")), fix);
+        }
+    };
+    let start = std::time::Instant::now();
+    match client.fetch_bytes("https://music.163.com/") {
+        Ok(_) => Check::Ok(tr!("doctor-network-ok", ms = start.elapsed().as_millis())),
+        Err(e) => Check::Fail(tr!("doctor-network-fail", error = e), fix),
     }
+}
 
-    let output_dir = output.map(PathBuf::as_path);
-    for file in &files {
-        match ncmdump::convert(file, output_dir) {
-            Ok(out) => {
-                println!("{} -> {}", file.display(), out.display());
-                if remove {
-                    if let Err(e) = std::fs::remove_file(file) {
-                        eprintln!("warning: failed to remove {}: {e}", file.display());
-                    }
-                }
-            }
-            Err(e) => eprintln!("error: {}: {e}", file.display()),
+fn doctor_session() -> Check {
+    let login = tr!("doctor-login-fix");
+    let session = match load_session() {
+        Ok(s) => s,
+        Err(e)
+            if matches!(
+                e.downcast_ref(),
+                Some(netease_api::NeteaseError::SessionLocked)
+            ) =>
+        {
+            return Check::Warn(
+                tr!("doctor-session-locked"),
+                tr!(
+                    "doctor-session-locked-fix",
+                    var = netease_api::auth::PASSPHRASE_ENV
+                ),
+            );
+        }
+        Err(e) => {
+            return Check::Fail(
+                tr!("doctor-session-unreadable", error = e),
+                tr!("doctor-session-unreadable-fix", login = login),
+            );
         }
+    };
+    if !session.is_logged_in() {
+        return Check::Warn(tr!("doctor-session-none"), login);
+    }
+    let client = match netease_client_builder().session(session).build() {
+        Ok(c) => c,
+        Err(e) => return Check::Fail(tr!("doctor-session-error", error = e), login),
+    };
+    match client.user_info() {
+        Ok(p) => Check::Ok(tr!("doctor-session-ok", name = p.nickname, id = p.id)),
+        Err(netease_api::error::NeteaseError::Api { code: 301, .. }) => Check::Fail(
+            tr!("doctor-session-expired"),
+            tr!("doctor-session-expired-fix", login = login),
+        ),
+        Err(e) => Check::Warn(
+            tr!("doctor-session-unverified", error = e),
+            tr!("doctor-session-unverified-fix"),
+        ),
     }
-    Ok(())
 }
 
-// ── login / logout ──
+fn doctor_output_dir() -> Check {
+    let dir = PathBuf::from(".");
+    let shown = std::env::current_dir().unwrap_or_else(|_| dir.clone());
+    match probe_writable(&dir) {
+        Ok(()) => Check::Ok(tr!("doctor-output-ok", dir = shown.display())),
+        Err(e) => Check::Fail(
+            tr!("doctor-output-readonly", dir = shown.display(), error = e),
+            tr!("doctor-output-fix"),
+        ),
+    }
+}
+
+fn doctor_tools() -> Check {
+    if bilibili_api::download::ffmpeg_available() {
+        Check::Ok(tr!("doctor-ffmpeg-ok"))
+    } else {
+        Check::Warn(tr!("doctor-ffmpeg-missing"), tr!("doctor-ffmpeg-fix"))
+    }
+}
 
-fn cmd_login(music_u: Option<String>, check: bool) -> Result<()> {
-    use netease_api::auth::Session;
+/// Create and remove a scratch file in `dir`.
+fn probe_writable(dir: &std::path::Path) -> std::io::Result<()> {
+    let probe = dir.join(format!(".ncmdump-doctor-{}", std::process::id()));
+    std::fs::write(&probe, b"")?;
+    std::fs::remove_file(&probe)
+}
 
-    if check {
-        let session = Session::load()?;
-        if session.is_logged_in() {
-            let client = netease_api::NeteaseClient::with_session(session)?;
-            match client.user_info() {
-                Ok(profile) => println!("Logged in as: {} (id={})", profile.nickname, profile.id),
-                Err(e) => println!("Session exists but validation failed: {e}"),
-            }
+// ── me ──
+
+fn cmd_me(playlists: bool, likes: bool, level: bool) -> Result<()> {
+    let client = netease_client()?;
+    let profile = client.user_info()?;
+    println!(
+        "{}",
+        tr!("me-user", name = profile.nickname, id = profile.id)
+    );
+    if let Some(url) = &profile.avatar_url {
+        println!("{}", tr!("label-avatar", url = url));
+    }
+    match client.vip_info() {
+        Ok(vip) => println!("{}", vip_status(&vip)),
+        Err(e) => {
+            tracing::warn!(error = %e, "VIP status unavailable");
+            report(tr!("me-vip-unavailable", error = e.to_string()));
+        }
+    }
+    if level {
+        let l = client.user_level()?;
+        let line = if l.next_play_count == 0 {
+            tr!("me-level-max", level = l.level)
         } else {
-            println!("Not logged in.");
+            tr!(
+                "me-level",
+                level = l.level,
+                percent = format!("{:.0}", l.progress * 100.0),
+                plays = l.play_count,
+                next_plays = l.next_play_count,
+                days = l.login_count,
+                next_days = l.next_login_count
+            )
+        };
+        println!("{line}");
+    }
+    if likes {
+        let count = client.liked_track_ids(profile.id)?.len();
+        println!("{}", tr!("me-likes", count = count));
+    }
+    if playlists {
+        print_user_playlists(&client, profile.id)?;
+    }
+    Ok(())
+}
+
+/// One line describing the most relevant VIP membership.
+fn vip_status(vip: &netease_api::types::VipInfo) -> String {
+    let now = u64::try_from(chrono::Utc::now().timestamp_millis()).unwrap_or(0);
+    match (vip.vip_expire_ms, vip.music_package_expire_ms) {
+        (Some(end), _) if end > now => {
+            tr!("me-vip-active", level = vip.level, date = format_date(end))
         }
-        return Ok(());
+        (_, Some(end)) if end > now => tr!("me-vip-package", date = format_date(end)),
+        (Some(end), _) | (None, Some(end)) => tr!("me-vip-expired", date = format_date(end)),
+        (None, None) => tr!("me-vip-none"),
     }
+}
 
-    let music_u = music_u.context("MUSIC_U value required")?;
-    let session = Session {
-        music_u: Some(music_u),
-    };
-    session.save()?;
-    println!("Session saved.");
+/// Print `uid`'s created playlists, then the ones they subscribed to.
+fn print_user_playlists(client: &netease_api::NeteaseClient, uid: u64) -> Result<()> {
+    const PAGE: u64 = 100;
+    let mut all = Vec::new();
+    loop {
+        let page = client.user_playlists(uid, PAGE, all.len() as u64)?;
+        let fetched = page.len() as u64;
+        all.extend(page);
+        if fetched < PAGE {
+            break;
+        }
+    }
+    let (created, subscribed): (Vec<_>, Vec<_>) = all
+        .into_iter()
+        .partition(|p| p.creator.as_ref().is_none_or(|c| c.id == uid));
+    let sections = [
+        (tr!("me-created", count = created.len()), created),
+        (tr!("me-subscribed", count = subscribed.len()), subscribed),
+    ];
+    for (heading, list) in sections {
+        if list.is_empty() {
+            continue;
+        }
+        println!("\n{heading}");
+        for p in &list {
+            let row = tr!(
+                "search-playlist",
+                id = p.id,
+                name = p.name,
+                count = p.track_count
+            );
+            println!("  {row}");
+        }
+    }
     Ok(())
 }
 
-fn cmd_logout() -> Result<()> {
-    netease_api::auth::Session::clear()?;
-    println!("Session cleared.");
+// ── record ──
+
+fn cmd_record(all: bool, limit: Option<usize>, json: bool) -> Result<()> {
+    use netease_api::types::RecordPeriod;
+
+    let client = netease_client()?;
+    let uid = client.user_info()?.id;
+    let period = if all {
+        RecordPeriod::AllTime
+    } else {
+        RecordPeriod::Weekly
+    };
+    let mut records = client.play_record(uid, period)?;
+    if let Some(n) = limit {
+        records.truncate(n);
+    }
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&records)?);
+        return Ok(());
+    }
+    if records.is_empty() {
+        println!("{}", tr!("record-empty"));
+        return Ok(());
+    }
+    for (i, r) in records.iter().enumerate() {
+        let t = &r.track;
+        let artists: Vec<&str> = t.artists.iter().map(|a| a.name.as_str()).collect();
+        let row = tr!(
+            "record-row",
+            id = t.id,
+            artists = artists.join(", "),
+            title = t.name,
+            count = r.play_count
+        );
+        println!("  {:>3}. {row}", i + 1);
+    }
     Ok(())
 }
 
-// ── search ──
+// ── signin ──
 
-fn cmd_search(keyword: &str, kind: SearchKind, limit: u64) -> Result<()> {
-    let client = netease_api::NeteaseClient::new()?;
-    let search_type = kind.into();
-    let result = client.search(keyword, search_type, limit, 0)?;
+fn cmd_signin(platform: Option<SigninPlatformArg>) -> Result<()> {
+    let client = netease_client()?;
+    if !client.session().is_logged_in() {
+        return Err(netease_api::NeteaseError::NotLoggedIn.into());
+    }
+    let platforms = match platform {
+        Some(p) => vec![p],
+        None => vec![SigninPlatformArg::Mobile, SigninPlatformArg::Web],
+    };
+    let total = platforms.len();
+    let mut failed = 0;
+    for p in platforms {
+        let name = match p {
+            SigninPlatformArg::Mobile => "mobile",
+            SigninPlatformArg::Web => "web",
+        };
+        match client.daily_signin(p.into()) {
+            Ok(Some(points)) => {
+                println!("{}", tr!("signin-earned", platform = name, points = points));
+            }
+            Ok(None) => println!("{}", tr!("signin-already", platform = name)),
+            Err(e) => {
+                tracing::error!(platform = name, error = %e, "sign-in failed");
+                report(tr!("item-error", item = name, error = e.to_string()));
+                failed += 1;
+            }
+        }
+    }
+    if failed > 0 {
+        anyhow::bail!(tr!("signin-failed", failed = failed, total = total));
+    }
+    Ok(())
+}
 
-    println!("Total: {}\n", result.total);
+// ── daemon ──
 
-    if let Some(tracks) = &result.tracks {
-        for t in tracks {
-            let artists: Vec<&str> = t.artists.iter().map(|a| a.name.as_str()).collect();
-            println!(
-                "  [{}] {} - {} ({})",
-                t.id,
-                artists.join(", "),
-                t.name,
-                t.album.name,
-            );
+fn cmd_daemon(config: Option<PathBuf>, action: DaemonAction) -> Result<()> {
+    let config_path = match config {
+        Some(path) => path,
+        None => netease_api::auth::Session::path()?.with_file_name("daemon.toml"),
+    };
+    let state_path = config_path.with_file_name("daemon-state.json");
+    let config = load_daemon_config(&config_path)?;
+    match action {
+        DaemonAction::Run { once } => daemon_run(&config, &config_path, &state_path, once),
+        DaemonAction::Status => {
+            let state = scheduler::State::load(&state_path)
+                .with_context(|| tr!("read-failed", path = state_path.display()))?;
+            daemon_status(&config, &state);
+            Ok(())
         }
     }
-    if let Some(albums) = &result.albums {
-        for a in albums {
-            println!("  [{}] {}", a.id, a.name);
-        }
+}
+
+fn load_daemon_config(path: &std::path::Path) -> Result<daemon::Config> {
+    let text =
+        std::fs::read_to_string(path).with_context(|| tr!("read-failed", path = path.display()))?;
+    let invalid = |error: String| {
+        InputError(tr!(
+            "daemon-config-invalid",
+            path = path.display(),
+            error = error
+        ))
+    };
+    let config = daemon::Config::parse(&text).map_err(invalid)?;
+    for job in &config.jobs {
+        task_quality(&job.task).map_err(|e| invalid(format!("job {:?}: {e}", job.name)))?;
     }
-    if let Some(artists) = &result.artists {
-        for a in artists {
-            println!("  [{}] {}", a.id, a.name);
-        }
+    Ok(config)
+}
+
+/// The `quality` of a download task (default `exhigh`).
+fn task_quality(task: &daemon::Task) -> std::result::Result<QualityArg, String> {
+    use daemon::Task;
+
+    let quality = match task {
+        Task::Playlist { quality, .. }
+        | Task::Album { quality, .. }
+        | Task::Toplist { quality, .. }
+        | Task::Daily { quality, .. } => quality.as_deref(),
+        Task::Dump { .. } | Task::Signin => None,
+    };
+    quality.map_or(Ok(QualityArg::Exhigh), |q| QualityArg::from_str(q, true))
+}
+
+fn daemon_run(
+    config: &daemon::Config,
+    config_path: &std::path::Path,
+    state_path: &std::path::Path,
+    once: bool,
+) -> Result<()> {
+    if config.jobs.is_empty() {
+        return Err(InputError(tr!("daemon-no-jobs", path = config_path.display())).into());
     }
-    if let Some(playlists) = &result.playlists {
-        for p in playlists {
-            println!("  [{}] {} ({} tracks)", p.id, p.name, p.track_count);
+    let mut scheduler = scheduler::Scheduler::new(state_path)
+        .with_context(|| tr!("read-failed", path = state_path.display()))?;
+    for job in &config.jobs {
+        // Schedules were validated when the config was loaded.
+        let schedule = job.schedule().map_err(InputError)?;
+        scheduler.register(&job.name, schedule, || run_task(&job.task));
+    }
+    let msg = tr!(
+        "daemon-started",
+        count = scheduler.len(),
+        path = config_path.display()
+    );
+    println!("{msg}");
+
+    let time = || chrono::Local::now().format("%Y-%m-%d %H:%M:%S");
+    let mut failed = 0;
+    loop {
+        failed += scheduler.run_due(once, |event| match event {
+            scheduler::Event::Started(name) => {
+                tracing::info!(job = name, "job started");
+                println!("{}", tr!("daemon-job-start", time = time(), name = name));
+            }
+            scheduler::Event::Finished(name, record) => {
+                send_notification(
+                    tr!("notify-daemon-job", name = name),
+                    record.error.clone(),
+                    std::time::Duration::from_secs(record.duration_secs),
+                );
+                match &record.error {
+                    None => {
+                        tracing::info!(
+                            job = name,
+                            duration_secs = record.duration_secs,
+                            "job finished"
+                        );
+                        let msg = tr!(
+                            "daemon-job-ok",
+                            time = time(),
+                            name = name,
+                            secs = record.duration_secs
+                        );
+                        println!("{msg}");
+                    }
+                    Some(error) => {
+                        tracing::error!(job = name, error, "job failed");
+                        let msg = tr!(
+                            "daemon-job-failed",
+                            time = time(),
+                            name = name,
+                            error = error
+                        );
+                        report(msg);
+                    }
+                }
+            }
+        });
+        if once {
+            break;
         }
+        std::thread::sleep(scheduler.wait());
+    }
+    if failed > 0 {
+        anyhow::bail!(tr!(
+            "daemon-jobs-failed",
+            failed = failed,
+            total = config.jobs.len()
+        ));
     }
     Ok(())
 }
 
-// ── info / lyric / download ──
+fn run_task(task: &daemon::Task) -> Result<()> {
+    use daemon::Task;
 
-fn cmd_info(track_id: u64) -> Result<()> {
-    let client = netease_api::NeteaseClient::new()?;
-    let t = client.track_detail(track_id)?;
-    let artists: Vec<&str> = t.artists.iter().map(|a| a.name.as_str()).collect();
-    println!("Track:    {} (id={})", t.name, t.id);
-    println!("Artists:  {}", artists.join(", "));
-    println!("Album:    {} (id={})", t.album.name, t.album.id);
-    println!(
-        "Duration: {}:{:02}",
-        t.duration_ms / 60000,
-        (t.duration_ms / 1000) % 60
-    );
-    Ok(())
+    if let Task::Dump {
+        input,
+        output,
+        recursive,
+        remove,
+    } = task
+    {
+        // Files converted on an earlier run are left alone, so the watch
+        // folder doesn't need `remove`.
+        let jobs: Vec<_> = dump_jobs(Vec::new(), None, Some(input), *recursive, output.as_ref())?
+            .into_iter()
+            .filter(|(file, out_dir)| !is_converted(file, out_dir.as_deref()))
+            .collect();
+        return if jobs.is_empty() {
+            Ok(())
+        } else {
+            convert_jobs(
+                &jobs,
+                &mut Naming::default(),
+                *remove,
+                false,
+                &ncmdump::ConvertOptions::default(),
+            )
+        };
+    }
+    if let Task::Signin = task {
+        return cmd_signin(None);
+    }
+
+    let quality = task_quality(task).map_err(InputError)?.into();
+    let client = netease_client()?;
+    let (tracks, output) = match task {
+        Task::Playlist { id, output, .. } => {
+            let tracks = client.playlist_detail(*id)?.tracks.unwrap_or_default();
+            (tracks, output)
+        }
+        Task::Album { id, output, .. } => (client.album_detail(*id)?.tracks, output),
+        Task::Toplist {
+            chart,
+            output,
+            limit,
+            ..
+        } => {
+            let charts = client.toplists()?;
+            let selected = charts
+                .iter()
+                .find(|c| c.name == *chart || c.id.to_string() == *chart)
+                .with_context(|| tr!("toplist-unknown", chart = chart))?;
+            let mut tracks = client
+                .playlist_detail(selected.id)?
+                .tracks
+                .unwrap_or_default();
+            tracks.truncate(limit.unwrap_or(usize::MAX));
+            (tracks, output)
+        }
+        Task::Daily { output, .. } => (client.daily_songs()?, output),
+        Task::Dump { .. } | Task::Signin => unreachable!("handled above"),
+    };
+    download_tracks(&client, &tracks, quality, output, None, DEFAULT_JOBS)
 }
 
-fn cmd_lyric(track_id: u64) -> Result<()> {
-    let client = netease_api::NeteaseClient::new()?;
-    let lyric = client.track_lyric(track_id)?;
-    if let Some(lrc) = &lyric.lrc {
-        println!("{lrc}");
+/// Whether `file` already has an output of the same name.
+fn is_converted(file: &std::path::Path, out_dir: Option<&std::path::Path>) -> bool {
+    let Some(stem) = file.file_stem() else {
+        return false;
+    };
+    let dir = out_dir
+        .or_else(|| file.parent())
+        .unwrap_or(std::path::Path::new(""));
+    ["mp3", "flac", "m4a", "ogg"]
+        .iter()
+        .any(|ext| dir.join(stem).with_extension(ext).exists())
+}
+
+fn daemon_status(config: &daemon::Config, state: &scheduler::State) {
+    let now = chrono::Local::now();
+    let format = |t: chrono::DateTime<chrono::Local>| t.format("%Y-%m-%d %H:%M:%S").to_string();
+    for job in &config.jobs {
+        let schedule = match (&job.every, &job.at, &job.cron) {
+            (Some(every), _, _) => tr!("daemon-schedule-every", interval = every),
+            (_, Some(at), _) => tr!("daemon-schedule-at", time = at),
+            (_, _, cron) => tr!(
+                "daemon-schedule-cron",
+                expr = cron.as_deref().unwrap_or_default()
+            ),
+        };
+        println!(
+            "{}",
+            tr!("daemon-status-job", name = job.name, schedule = schedule)
+        );
+        let record = state.jobs.get(&job.name);
+        let last = match record {
+            None => tr!("daemon-last-never"),
+            Some(r) => {
+                let time = r.started_local().map(format).unwrap_or_default();
+                match &r.error {
+                    None => tr!("daemon-last-ok", time = time, secs = r.duration_secs),
+                    Some(error) => tr!(
+                        "daemon-last-failed",
+                        time = time,
+                        secs = r.duration_secs,
+                        error = error
+                    ),
+                }
+            }
+        };
+        println!("  {last}");
+        // Schedules were validated when the config was loaded.
+        let next = job
+            .schedule()
+            .map_or(now, |s| state.next_run(&job.name, s, now));
+        let next = format(next);
+        println!("  {}", tr!("daemon-next", time = next));
     }
-    if let Some(tlyric) = &lyric.tlyric {
-        println!("\n--- Translation ---\n{tlyric}");
+}
+
+// ── serve ──
+
+fn cmd_serve(
+    dir: &std::path::Path,
+    listen: &str,
+    quality: netease_api::types::Quality,
+    credentials: Option<serve::Credentials>,
+) -> Result<()> {
+    anyhow::ensure!(dir.is_dir(), "not a directory: {}", dir.display());
+    let http = tiny_http::Server::http(listen)
+        .map_err(|e| anyhow::anyhow!(tr!("serve-bind-failed", addr = listen, error = e)))?;
+    let mut server = serve::Server::new(dir.to_path_buf(), netease_client()?, quality);
+    if let Some(library) = open_library() {
+        server = server.library(library);
     }
-    if lyric.lrc.is_none() && lyric.tlyric.is_none() {
-        println!("No lyrics available.");
+    if let Some(credentials) = credentials {
+        server = server.credentials(credentials);
     }
+    if ncmdump::transcode::available() {
+        server = server.transcoding();
+    } else {
+        tracing::info!("ffmpeg not found, transcoding disabled");
+    }
+    println!(
+        "{}",
+        tr!(
+            "serve-listening",
+            dir = dir.display(),
+            addr = http.server_addr()
+        )
+    );
+    tracing::info!(dir = %dir.display(), addr = %http.server_addr(), "serving");
+    server.run(&http);
     Ok(())
 }
 
-fn cmd_download(track_id: u64, quality: QualityArg, output: Option<PathBuf>) -> Result<()> {
-    let client = netease_api::NeteaseClient::new()?;
-    let q: netease_api::types::Quality = quality.into();
+// ── rpc ──
 
-    let dest = if let Some(p) = output {
-        p
-    } else {
-        let url = client.track_url(track_id, q)?;
-        let ext = if url.contains(".flac") { "flac" } else { "mp3" };
-        PathBuf::from(format!("{track_id}.{ext}"))
+fn cmd_rpc(listen: Option<rpc::Listen>) -> Result<()> {
+    let listen = match listen {
+        Some(listen) => listen,
+        None => rpc::default_listen()?,
     };
+    // Unlock an encrypted session now, while there is a terminal to ask on.
+    if netease_api::auth::Session::encryption()? == Some(netease_api::auth::KeySource::Passphrase) {
+        load_session()?;
+    }
+    println!("{}", tr!("rpc-listening", addr = listen.to_string()));
+    tracing::info!(addr = %listen, "serving JSON-RPC");
+    rpc::run(&listen)
+}
 
-    let size = client.download_track(track_id, q, &dest)?;
-    println!("Downloaded {} ({} bytes)", dest.display(), size);
+// ── cloud ──
+
+fn cmd_cloud(action: CloudAction) -> Result<()> {
+    let client = netease_client()?;
+    match action {
+        CloudAction::List { limit, offset } => {
+            let list = client.cloud_list(limit, offset)?;
+            let total = tr!(
+                "cloud-total",
+                count = list.count,
+                used = list.size / (1024 * 1024),
+                max = list.max_size / (1024 * 1024)
+            );
+            println!("{total}\n");
+            for t in &list.tracks {
+                println!(
+                    "  [{}] {} - {} ({}, {:.1} MiB)",
+                    t.id,
+                    t.artist,
+                    t.name,
+                    t.file_name,
+                    bytes_to_mib(t.file_size)
+                );
+            }
+        }
+        CloudAction::Upload { files } => {
+            for file in &files {
+                match client.cloud_upload(file) {
+                    Ok(id) => {
+                        tracing::info!(file = %file.display(), cloud_id = id, "uploaded");
+                        println!("{}", tr!("cloud-uploaded", path = file.display(), id = id));
+                    }
+                    Err(e) => {
+                        tracing::error!(file = %file.display(), error = %e, "upload failed");
+                        report(tr!("item-error", item = file.display(), error = e));
+                    }
+                }
+            }
+        }
+        CloudAction::Delete { ids } => {
+            client.cloud_delete(&ids)?;
+            println!("{}", tr!("cloud-deleted", count = ids.len()));
+        }
+    }
     Ok(())
 }
 
-// ── playlist ──
+#[allow(clippy::cast_precision_loss)]
+fn bytes_to_mib(bytes: u64) -> f64 {
+    bytes as f64 / (1024.0 * 1024.0)
+}
 
-fn cmd_playlist(playlist_id: u64) -> Result<()> {
-    let client = netease_api::NeteaseClient::new()?;
-    let p = client.playlist_detail(playlist_id)?;
-    println!("Playlist: {} (id={})", p.name, p.id);
-    println!("Tracks:   {}", p.track_count);
-    if let Some(desc) = &p.description {
-        println!("Desc:     {desc}");
-    }
-    if let Some(creator) = &p.creator {
-        println!("Creator:  {} (id={})", creator.name, creator.id);
+// ── backup ──
+
+fn cmd_backup(output: Option<PathBuf>) -> Result<()> {
+    let client = netease_client()?;
+    if !client.session().is_logged_in() {
+        return Err(netease_api::NeteaseError::NotLoggedIn.into());
     }
-    if let Some(tracks) = &p.tracks {
-        println!();
-        for t in tracks {
-            let artists: Vec<&str> = t.artists.iter().map(|a| a.name.as_str()).collect();
-            println!("  [{}] {} - {}", t.id, artists.join(", "), t.name);
+    let snapshot = backup::Snapshot::collect(&client, |index, total, playlist| {
+        if !quiet() {
+            let line = tr!(
+                "backup-playlist",
+                index = index,
+                total = total,
+                name = playlist.name
+            );
+            println!("{line}");
         }
+    })?;
+    let path = output.unwrap_or_else(|| {
+        let time = chrono::Local::now().format("%Y%m%d-%H%M%S");
+        PathBuf::from(format!("ncmdump-backup-{}-{time}.json", snapshot.user.id))
+    });
+    snapshot.save(&path)?;
+    tracing::info!(path = %path.display(), playlists = snapshot.playlists.len(), "saved backup");
+    let msg = tr!(
+        "backup-saved",
+        path = path.display(),
+        playlists = snapshot.playlists.len(),
+        tracks = snapshot.track_count(),
+        liked = snapshot.liked.len(),
+        artists = snapshot.artists.len(),
+        podcasts = snapshot.podcasts.len(),
+        cloud = snapshot.cloud.len()
+    );
+    println!("{msg}");
+    let incomplete = snapshot.incomplete();
+    if incomplete > 0 {
+        report(tr!("backup-incomplete", count = incomplete));
     }
     Ok(())
 }
 
-// ── me ──
+fn cmd_backup_diff(old: &std::path::Path, new: &std::path::Path) -> Result<()> {
+    let before = backup::Snapshot::load(old)?;
+    let after = backup::Snapshot::load(new)?;
+    let diff = before.diff(&after);
+    if diff.is_empty() {
+        println!("{}", tr!("backup-no-changes"));
+        return Ok(());
+    }
 
-fn cmd_me() -> Result<()> {
-    let client = netease_api::NeteaseClient::new()?;
-    let profile = client.user_info()?;
-    println!("User:   {} (id={})", profile.nickname, profile.id);
-    if let Some(url) = &profile.avatar_url {
-        println!("Avatar: {url}");
+    let heading = |section: String, added: usize, removed: usize| {
+        let line = tr!(
+            "backup-section",
+            section = section,
+            added = added,
+            removed = removed
+        );
+        println!("{line}");
+    };
+    let track_row = |t: &netease_api::types::Track| {
+        let artists: Vec<&str> = t.artists.iter().map(|a| a.name.as_str()).collect();
+        format!("[{}] {} - {}", t.id, artists.join(", "), t.name)
+    };
+    let rows = |added: Vec<String>, removed: Vec<String>| {
+        for row in added {
+            println!("  + {row}");
+        }
+        for row in removed {
+            println!("  - {row}");
+        }
+    };
+
+    let d = &diff.playlists;
+    if !d.is_empty() {
+        heading(tr!("backup-playlists"), d.added.len(), d.removed.len());
+        let row = |p: &&netease_api::types::Playlist| format!("[{}] {}", p.id, p.name);
+        rows(
+            d.added.iter().map(row).collect(),
+            d.removed.iter().map(row).collect(),
+        );
+    }
+    for change in &diff.changed {
+        let p = change.playlist;
+        let section = if let Some(from) = change.renamed_from {
+            tr!(
+                "backup-playlist-renamed",
+                name = p.name,
+                id = p.id,
+                from = from
+            )
+        } else {
+            tr!("backup-playlist-changed", name = p.name, id = p.id)
+        };
+        let t = &change.tracks;
+        heading(section, t.added.len(), t.removed.len());
+        rows(
+            t.added.iter().map(|t| track_row(t)).collect(),
+            t.removed.iter().map(|t| track_row(t)).collect(),
+        );
+    }
+    let d = &diff.liked;
+    if !d.is_empty() {
+        heading(tr!("backup-liked"), d.added.len(), d.removed.len());
+        // Liked songs are stored as IDs; their names come from the playlists.
+        let row = |snapshot: &backup::Snapshot, id: u64| {
+            snapshot
+                .track(id)
+                .map_or_else(|| format!("[{id}]"), track_row)
+        };
+        rows(
+            d.added.iter().map(|&&id| row(&after, id)).collect(),
+            d.removed.iter().map(|&&id| row(&before, id)).collect(),
+        );
+    }
+    let d = &diff.artists;
+    if !d.is_empty() {
+        heading(tr!("backup-artists"), d.added.len(), d.removed.len());
+        let row = |a: &&netease_api::types::Artist| format!("[{}] {}", a.id, a.name);
+        rows(
+            d.added.iter().map(row).collect(),
+            d.removed.iter().map(row).collect(),
+        );
+    }
+    let d = &diff.podcasts;
+    if !d.is_empty() {
+        heading(tr!("backup-podcasts"), d.added.len(), d.removed.len());
+        let row = |r: &&netease_api::types::Radio| format!("[{}] {}", r.id, r.name);
+        rows(
+            d.added.iter().map(row).collect(),
+            d.removed.iter().map(row).collect(),
+        );
+    }
+    let d = &diff.cloud;
+    if !d.is_empty() {
+        heading(tr!("backup-cloud"), d.added.len(), d.removed.len());
+        let row = |t: &&netease_api::types::CloudTrack| {
+            format!("[{}] {} - {} ({})", t.id, t.artist, t.name, t.file_name)
+        };
+        rows(
+            d.added.iter().map(row).collect(),
+            d.removed.iter().map(row).collect(),
+        );
     }
     Ok(())
 }
@@ -451,50 +5710,50 @@ fn cmd_bili_login(check: bool) -> Result<()> {
     if check {
         let session = BiliSession::load()?;
         if session.is_logged_in() {
-            let client = bilibili_api::BilibiliClient::new()?;
+            let client = bili_client()?;
             match client.user_info() {
                 Ok(info) if info.is_login => {
-                    println!("Logged in as: {} (mid={})", info.name, info.mid);
+                    println!("{}", tr!("bili-login-as", name = info.name, mid = info.mid));
                     if info.vip_status > 0 {
-                        println!("VIP: active");
+                        println!("{}", tr!("bili-vip-active"));
                     }
                 }
-                _ => println!("Session exists but validation failed."),
+                _ => println!("{}", tr!("bili-session-invalid")),
             }
         } else {
-            println!("Not logged in.");
+            println!("{}", tr!("not-logged-in"));
         }
         return Ok(());
     }
 
     // QR code login flow.
-    let client = bilibili_api::BilibiliClient::new()?;
+    let client = bili_client()?;
     let qr = client.qr_generate()?;
 
     // Render QR code in terminal.
-    let code = qrcode::QrCode::new(qr.url.as_bytes()).context("failed to generate QR code")?;
+    let code = qrcode::QrCode::new(qr.url.as_bytes()).with_context(|| tr!("bili-qr-failed"))?;
     let qr_string = code
         .render::<qrcode::render::unicode::Dense1x2>()
         .dark_color(qrcode::render::unicode::Dense1x2::Light)
         .light_color(qrcode::render::unicode::Dense1x2::Dark)
         .build();
-    println!("Scan with Bilibili mobile app:\n");
+    println!("{}\n", tr!("bili-scan"));
     println!("{qr_string}");
-    println!("Waiting for scan...");
+    println!("{}", tr!("bili-waiting"));
 
     loop {
         std::thread::sleep(std::time::Duration::from_secs(2));
         match client.qr_poll(&qr.qrcode_key)? {
             bilibili_api::auth::QrPollStatus::Success(session) => {
                 session.save()?;
-                println!("Login successful! Session saved.");
+                println!("{}", tr!("bili-login-ok"));
                 return Ok(());
             }
             bilibili_api::auth::QrPollStatus::Scanned => {
-                println!("Scanned, waiting for confirm...");
+                println!("{}", tr!("bili-scanned"));
             }
             bilibili_api::auth::QrPollStatus::Expired => {
-                println!("QR code expired. Please try again.");
+                println!("{}", tr!("bili-qr-expired"));
                 return Ok(());
             }
             bilibili_api::auth::QrPollStatus::Waiting => {}
@@ -504,38 +5763,42 @@ fn cmd_bili_login(check: bool) -> Result<()> {
 
 fn cmd_bili_logout() -> Result<()> {
     bilibili_api::auth::BiliSession::clear()?;
-    println!("Bilibili session cleared.");
+    println!("{}", tr!("bili-logout"));
     Ok(())
 }
 
 fn cmd_bili_search(keyword: &str, limit: u64, page: u64) -> Result<()> {
-    let client = bilibili_api::BilibiliClient::new()?;
+    let client = bili_client()?;
     let result = client.search_video(keyword, page, limit)?;
 
-    println!("Total: {}\n", result.num_results);
+    println!("{}\n", tr!("total", total = result.num_results));
     for v in &result.results {
         // Strip HTML highlight tags from title.
         let title = v
             .title
             .replace("<em class=\"keyword\">", "")
             .replace("</em>", "");
-        println!("  [{}] {} - {} ({})", v.bvid, v.author, title, v.duration,);
+        println!("  [{}] {} - {} ({})", v.bvid, v.author, title, v.duration);
     }
     Ok(())
 }
 
 fn cmd_bili_info(bvid: &str) -> Result<()> {
-    let client = bilibili_api::BilibiliClient::new()?;
+    let client = bili_client()?;
     let v = client.video_detail(bvid)?;
-    println!("Title:    {}", v.title);
+    println!("{}", tr!("bili-title", title = v.title));
     println!("BV ID:    {}", v.bvid);
     println!("AV ID:    {}", v.aid);
     println!("CID:      {}", v.cid);
-    println!("Author:   {} (mid={})", v.owner.name, v.owner.mid);
-    println!("Duration: {}:{:02}", v.duration / 60, v.duration % 60);
-    println!("Cover:    {}", v.pic);
+    println!(
+        "{}",
+        tr!("bili-author", name = v.owner.name, mid = v.owner.mid)
+    );
+    let duration = format!("{}:{:02}", v.duration / 60, v.duration % 60);
+    println!("{}", tr!("label-duration", duration = duration));
+    println!("{}", tr!("bili-cover", url = v.pic));
     if v.pages.len() > 1 {
-        println!("\nParts:");
+        println!("\n{}", tr!("bili-parts"));
         for p in &v.pages {
             println!("  P{}: {} (cid={}, {}s)", p.page, p.part, p.cid, p.duration);
         }
@@ -545,36 +5808,225 @@ fn cmd_bili_info(bvid: &str) -> Result<()> {
 
 fn cmd_bili_download(bvid: &str, format: BiliFormatArg, output: Option<PathBuf>) -> Result<()> {
     if !bilibili_api::download::ffmpeg_available() {
-        anyhow::bail!("ffmpeg not found in PATH. Please install ffmpeg first.");
+        anyhow::bail!(tr!("ffmpeg-missing"));
     }
 
-    let client = bilibili_api::BilibiliClient::new()?;
+    let client = bili_client()?;
     let fmt: bilibili_api::types::AudioFormat = format.into();
 
     let dest = output.unwrap_or_else(|| PathBuf::from(format!("{bvid}.{}", fmt.extension())));
 
-    println!("Downloading audio from {bvid}...");
+    println!("{}", tr!("bili-downloading", bvid = bvid));
     let size = client.download_audio(bvid, &dest, fmt)?;
-    println!("Downloaded {} ({} bytes)", dest.display(), size);
+    println!("{}", tr!("downloaded", path = dest.display(), bytes = size));
     Ok(())
 }
 
 fn cmd_bili_me() -> Result<()> {
-    let client = bilibili_api::BilibiliClient::new()?;
+    let client = bili_client()?;
     let info = client.user_info()?;
     if info.is_login {
-        println!("User:   {} (mid={})", info.name, info.mid);
-        println!("Avatar: {}", info.face);
-        println!(
-            "VIP:    {}",
-            if info.vip_status > 0 {
-                "active"
-            } else {
-                "none"
-            }
-        );
+        println!("{}", tr!("bili-user", name = info.name, mid = info.mid));
+        println!("{}", tr!("label-avatar", url = info.face));
+        println!("{}", tr!("bili-vip", active = info.vip_status > 0));
     } else {
-        println!("Not logged in.");
+        println!("{}", tr!("not-logged-in"));
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan_never_shares_outputs() {
+        let dir = std::env::temp_dir().join(format!("ncmdump-cli-plan-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let sample = concat!(env!("CARGO_MANIFEST_DIR"), "/../testdata/test.ncm");
+        let (a, b) = (dir.join("a.ncm"), dir.join("b.ncm"));
+        std::fs::copy(sample, &a).unwrap();
+        std::fs::copy(sample, &b).unwrap();
+
+        // Both render the same name: the second input is numbered.
+        let mut naming = Naming {
+            template: Some("{artist} - {title}"),
+            ..Naming::default()
+        };
+        let first = naming.plan(&a, Some(&dir)).unwrap();
+        let second = naming.plan(&b, Some(&dir)).unwrap();
+        assert_ne!(first.output, second.output);
+        assert!(!second.overwrites);
+
+        // A file from an earlier run is overwritten, unless under `--remove`.
+        std::fs::write(dir.join("a.flac"), b"earlier").unwrap();
+        let planned = Naming::default().plan(&a, Some(&dir)).unwrap();
+        let kept = Naming {
+            keep_existing: true,
+            ..Naming::default()
+        }
+        .plan(&a, Some(&dir))
+        .unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(planned.overwrites);
+        assert_eq!(planned.output, dir.join("a.flac"));
+        assert!(!kept.overwrites);
+        assert_ne!(kept.output, dir.join("a.flac"));
+    }
+
+    fn resource(url: &str) -> Option<(&'static str, u64)> {
+        parse_resource_url(url).map(|(kind, id)| (kind.path(), id))
+    }
+
+    #[test]
+    fn test_parse_resource_url() {
+        let accepted = [
+            ("https://music.163.com/#/song?id=186016", ("song", 186_016)),
+            (
+                "https://music.163.com/song?id=186016&userid=1",
+                ("song", 186_016),
+            ),
+            (
+                "https://music.163.com/song/186016/?userid=2",
+                ("song", 186_016),
+            ),
+            (
+                "https://y.music.163.com/m/song?id=186016&uct2=x",
+                ("song", 186_016),
+            ),
+            ("http://music.163.com/#/album?id=18905", ("album", 18905)),
+            ("https://y.music.163.com/m/album?id=18905", ("album", 18905)),
+            (
+                "https://music.163.com/#/playlist?id=24381616",
+                ("playlist", 24_381_616),
+            ),
+            (
+                "https://music.163.com/playlist?userid=9&id=24381616",
+                ("playlist", 24_381_616),
+            ),
+            (
+                "https://music.163.com/#/my/m/music/playlist?id=1",
+                ("playlist", 1),
+            ),
+            ("https://music.163.com/#/artist?id=6452", ("artist", 6452)),
+            ("music.163.com/#/song?id=5", ("song", 5)),
+        ];
+        for (url, want) in accepted {
+            assert_eq!(resource(url), Some(want), "{url}");
+        }
+        let rejected = [
+            "",
+            "186016",
+            "https://music.163.com/#/song",
+            "https://music.163.com/#/song?id=abc",
+            "https://music.163.com/#/song?userid=1",
+            "https://music.163.com/#/discover",
+            "https://music.163.com/#/user/home?id=1",
+        ];
+        for url in rejected {
+            assert_eq!(resource(url), None, "{url}");
+        }
+    }
+
+    #[test]
+    fn test_parse_ref() {
+        assert_eq!(parse_ref("186016", ResourceKind::Song).unwrap(), 186_016);
+        assert_eq!(parse_ref(" 42\n", ResourceKind::Album).unwrap(), 42);
+        let share = "分享周杰伦的单曲《晴天》: https://y.music.163.com/m/song?id=186016&userid=1 (来自@网易云音乐)";
+        assert_eq!(parse_ref(share, ResourceKind::Song).unwrap(), 186_016);
+        let playlist = "https://music.163.com/#/playlist?id=24381616";
+        assert_eq!(
+            parse_ref(playlist, ResourceKind::Playlist).unwrap(),
+            24_381_616
+        );
+
+        // A link to another kind of resource, or no ID at all.
+        assert!(parse_ref(playlist, ResourceKind::Song).is_err());
+        assert!(parse_ref("-1", ResourceKind::Song).is_err());
+        assert!(parse_ref("晴天", ResourceKind::Song).is_err());
+        assert!(parse_ref("https://music.163.com/#/song", ResourceKind::Song).is_err());
+    }
+
+    /// One Ogg page holding `packet`, with the checksum Ogg readers verify.
+    fn ogg_page(packet: &[u8], kind: u8, granule: u64, sequence: u32) -> Vec<u8> {
+        let mut page = b"OggS\0".to_vec();
+        page.push(kind);
+        page.extend_from_slice(&granule.to_le_bytes());
+        page.extend_from_slice(&1u32.to_le_bytes());
+        page.extend_from_slice(&sequence.to_le_bytes());
+        page.extend_from_slice(&[0; 4]);
+        let mut lacing = vec![255; packet.len() / 255];
+        lacing.push(u8::try_from(packet.len() % 255).unwrap());
+        page.push(u8::try_from(lacing.len()).unwrap());
+        page.extend_from_slice(&lacing);
+        page.extend_from_slice(packet);
+        let crc = page.iter().fold(0u32, |crc, &b| {
+            (0..8).fold(crc ^ (u32::from(b) << 24), |crc, _| {
+                if crc & 0x8000_0000 == 0 {
+                    crc << 1
+                } else {
+                    crc << 1 ^ 0x04C1_1DB7
+                }
+            })
+        });
+        page[22..26].copy_from_slice(&crc.to_le_bytes());
+        page
+    }
+
+    /// A second of silent stereo Opus in Ogg, untagged.
+    fn opus() -> Vec<u8> {
+        let mut head = b"OpusHead\x01\x02".to_vec();
+        head.extend_from_slice(&312u16.to_le_bytes());
+        head.extend_from_slice(&48_000u32.to_le_bytes());
+        head.extend_from_slice(&[0, 0, 0]);
+        let mut tags = b"OpusTags".to_vec();
+        tags.extend_from_slice(&4u32.to_le_bytes());
+        tags.extend_from_slice(b"test");
+        tags.extend_from_slice(&0u32.to_le_bytes());
+        // Fifty 20 ms frames of silence.
+        let audio = [0xF8, 0xFF, 0xFE].repeat(50);
+        [
+            ogg_page(&head, 0x02, 0, 0),
+            ogg_page(&tags, 0, 0, 1),
+            ogg_page(&audio, 0x04, 48_312, 2),
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn test_verify_tagged_ogg() {
+        let dir = std::env::temp_dir().join(format!("ncmdump-cli-ogg-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let json = r#"{"musicId":1,"musicName":"T","album":"A","artist":[["B",1]],"bitrate":128000,"duration":1000,"format":"ogg"}"#;
+        let meta = ncmdump::NcmMetadata::from_decrypted(json.as_bytes()).unwrap();
+        let input = dir.join("song.ncm");
+        let mut ncm = Vec::new();
+        ncmdump::encode(&mut opus().as_slice(), Some(&meta), None, &mut ncm).unwrap();
+        std::fs::write(&input, ncm).unwrap();
+
+        let opts = ncmdump::ConvertOptions::default();
+        let planned = convert_job(&mut Naming::default(), &input, Some(&dir), true, &opts);
+        let tags = planned
+            .as_ref()
+            .map_err(String::clone)
+            .and_then(|planned| ncmdump::tag_read(&planned.output).map_err(|e| e.to_string()));
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let planned = planned.unwrap();
+        assert_eq!(planned.info.format, ncmdump::AudioFormat::Ogg);
+        assert_eq!(tags.unwrap().title.as_deref(), Some("T"));
+    }
+
+    #[test]
+    fn test_exit_classify() {
+        use ncmdump::NcmError;
+        let exit = |e: NcmError| Exit::classify(&anyhow::Error::from(e).context("dump"));
+        assert_eq!(exit(NcmError::InvalidMagic), Exit::Input);
+        assert_eq!(exit(NcmError::Transcode("boom".into())), Exit::Tool);
+        assert_eq!(exit(NcmError::Cancelled), Exit::Cancelled);
+        assert_eq!(ExitCode::from(Exit::Cancelled), ExitCode::from(130));
+        let io = std::io::Error::other("disk");
+        assert_eq!(exit(NcmError::Io(io)), Exit::Io);
+    }
+}
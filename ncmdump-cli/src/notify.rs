@@ -0,0 +1,292 @@
+//! Notifications when a long-running operation ends.
+//!
+//! Where hooks run per file, notifiers are told once a batch command
+//! (`dump`, `playlist`, `album`, …) or a daemon job has finished or failed,
+//! so unattended runs don't fail silently. They are configured in
+//! `hooks.toml` next to the hooks, and can be added for one run with the
+//! global `--notify` flag (`desktop` or a webhook URL):
+//!
+//! ```toml
+//! [[notify]]
+//! desktop = true
+//! on = ["failed"]
+//!
+//! [[notify]]
+//! name = "telegram"
+//! webhook = "https://api.telegram.org/bot<TOKEN>/sendMessage"
+//! body = '{"chat_id": "<CHAT>", "text": "{title}\n{message}"}'
+//! ```
+//!
+//! A desktop notification goes through `notify-send` (Linux and BSD),
+//! `osascript` (macOS) or PowerShell (Windows). A webhook receives a POST
+//! with the [`Notification`] as JSON, or `body` with `{event}`,
+//! `{operation}`, `{title}` and `{message}` filled in as JSON string
+//! contents. Notifiers without `on` are told about every outcome. A failing
+//! notifier is reported but does not change the outcome it reported on.
+
+use std::process::Command;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+/// How an operation ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Event {
+    Finished,
+    /// Ended with an error, including when only some items failed.
+    Failed,
+}
+
+impl Event {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Finished => "finished",
+            Self::Failed => "failed",
+        }
+    }
+}
+
+/// What a notifier is told, as JSON in a webhook's body.
+#[derive(Debug, Serialize)]
+pub struct Notification {
+    pub event: Event,
+    /// The command or daemon job, e.g. `playlist` or `daemon job likes`.
+    pub operation: String,
+    pub title: String,
+    /// The error for `failed`, a summary otherwise.
+    pub message: String,
+    pub duration_secs: u64,
+}
+
+/// One `[[notify]]` entry: a desktop notification or a webhook.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Notifier {
+    /// Shown in messages; the target itself if absent.
+    pub name: Option<String>,
+    #[serde(default)]
+    pub desktop: bool,
+    /// URL to POST to.
+    pub webhook: Option<String>,
+    /// Webhook body template; the notification as JSON if absent.
+    pub body: Option<String>,
+    /// Outcomes to notify about; all if empty.
+    #[serde(default)]
+    pub on: Vec<Event>,
+}
+
+impl Notifier {
+    /// A notifier for every outcome from a `--notify` target: `desktop` or
+    /// a webhook URL.
+    pub fn target(target: &str) -> Result<Self, String> {
+        let mut notifier = Self {
+            name: None,
+            desktop: false,
+            webhook: None,
+            body: None,
+            on: Vec::new(),
+        };
+        if target == "desktop" {
+            notifier.desktop = true;
+        } else if target.starts_with("http://") || target.starts_with("https://") {
+            notifier.webhook = Some(target.to_owned());
+        } else {
+            return Err(format!(
+                "invalid notify target {target:?} (use `desktop` or an http(s) URL)"
+            ));
+        }
+        Ok(notifier)
+    }
+
+    pub fn label(&self) -> &str {
+        match (&self.name, &self.webhook) {
+            (Some(name), _) => name,
+            (None, Some(url)) => url,
+            (None, None) => "desktop",
+        }
+    }
+
+    /// Check that exactly one target is set.
+    pub fn validate(&self) -> Result<(), String> {
+        match (self.desktop, &self.webhook) {
+            (true, None) => Ok(()),
+            (false, Some(url)) if url.starts_with("http://") || url.starts_with("https://") => {
+                Ok(())
+            }
+            (false, Some(url)) => Err(format!("notifier webhook {url:?} is not an http(s) URL")),
+            (true, Some(_)) => Err(format!(
+                "notifier {:?} sets both `desktop` and `webhook`",
+                self.label()
+            )),
+            (false, None) => Err("notifier needs `desktop = true` or a `webhook`".into()),
+        }
+    }
+
+    fn runs_on(&self, event: Event) -> bool {
+        self.on.is_empty() || self.on.contains(&event)
+    }
+
+    /// Deliver `notification`; the error describes why it failed.
+    fn send(&self, notification: &Notification, proxy: Option<&str>) -> Result<(), String> {
+        match &self.webhook {
+            Some(url) => {
+                let body = match &self.body {
+                    Some(template) => render(template, notification),
+                    None => serde_json::to_string(notification).map_err(|e| e.to_string())?,
+                };
+                post(url, body, proxy)
+            }
+            None => desktop(&notification.title, &notification.message),
+        }
+    }
+}
+
+/// Check a `--notify` target, for the argument parser.
+pub fn parse_target(target: &str) -> Result<String, String> {
+    Notifier::target(target).map(|_| target.to_owned())
+}
+
+/// `template` with the notification's fields escaped for a JSON string.
+fn render(template: &str, notification: &Notification) -> String {
+    let escape = |s: &str| {
+        let quoted = serde_json::to_string(s).unwrap_or_default();
+        quoted[1..quoted.len() - 1].to_owned()
+    };
+    template
+        .replace("{event}", notification.event.name())
+        .replace("{operation}", &escape(&notification.operation))
+        .replace("{title}", &escape(&notification.title))
+        .replace("{message}", &escape(&notification.message))
+}
+
+fn post(url: &str, body: String, proxy: Option<&str>) -> Result<(), String> {
+    let mut client = reqwest::blocking::Client::builder().timeout(Duration::from_secs(10));
+    if let Some(proxy) = proxy {
+        client = client.proxy(reqwest::Proxy::all(proxy).map_err(|e| e.to_string())?);
+    }
+    let response = client
+        .build()
+        .map_err(|e| e.to_string())?
+        .post(url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .body(body)
+        .send()
+        .map_err(|e| e.to_string())?;
+    if response.status().is_success() {
+        Ok(())
+    } else {
+        Err(format!("HTTP {}", response.status()))
+    }
+}
+
+fn desktop(title: &str, message: &str) -> Result<(), String> {
+    let mut cmd;
+    if cfg!(target_os = "macos") {
+        // AppleScript string literals only escape `\` and `"`.
+        let quote = |s: &str| format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""));
+        cmd = Command::new("osascript");
+        cmd.arg("-e").arg(format!(
+            "display notification {} with title {}",
+            quote(message),
+            quote(title)
+        ));
+    } else if cfg!(windows) {
+        cmd = Command::new("powershell");
+        cmd.args(["-NoProfile", "-Command", WINDOWS_BALLOON])
+            .env("NCMDUMP_TITLE", title)
+            .env("NCMDUMP_MESSAGE", message);
+        // The balloon stays up while PowerShell sleeps; don't wait for it.
+        return cmd.spawn().map(drop).map_err(|e| e.to_string());
+    } else {
+        cmd = Command::new("notify-send");
+        cmd.args(["--app-name=ncmdump", title, message]);
+    }
+    let status = cmd.status().map_err(|e| e.to_string())?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(status.to_string())
+    }
+}
+
+const WINDOWS_BALLOON: &str = "Add-Type -AssemblyName System.Windows.Forms; \
+    $n = New-Object System.Windows.Forms.NotifyIcon; \
+    $n.Icon = [System.Drawing.SystemIcons]::Information; $n.Visible = $true; \
+    $n.ShowBalloonTip(10000, $env:NCMDUMP_TITLE, $env:NCMDUMP_MESSAGE, 'None'); \
+    Start-Sleep -Seconds 10; $n.Dispose()";
+
+/// The configured notifiers.
+#[derive(Debug, Default)]
+pub struct Notifiers(Vec<Notifier>);
+
+impl Notifiers {
+    pub fn new(notifiers: Vec<Notifier>) -> Self {
+        Self(notifiers)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Send `notification` to the notifiers for its event, through `proxy`
+    /// for webhooks. Returns the label and error of each that failed.
+    pub fn send(&self, notification: &Notification, proxy: Option<&str>) -> Vec<(&str, String)> {
+        self.0
+            .iter()
+            .filter(|n| n.runs_on(notification.event))
+            .filter_map(|notifier| {
+                tracing::debug!(
+                    notifier = notifier.label(),
+                    event = notification.event.name(),
+                    "notifying"
+                );
+                notifier
+                    .send(notification, proxy)
+                    .err()
+                    .map(|e| (notifier.label(), e))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_targets() {
+        assert!(Notifier::target("desktop").unwrap().desktop);
+        let hook = Notifier::target("https://gotify.example/message?token=x").unwrap();
+        assert_eq!(hook.label(), "https://gotify.example/message?token=x");
+        assert!(hook.validate().is_ok());
+        assert!(Notifier::target("telegram").is_err());
+
+        let both = Notifier {
+            webhook: Some("https://example.com".into()),
+            ..Notifier::target("desktop").unwrap()
+        };
+        assert!(both.validate().is_err());
+    }
+
+    #[test]
+    fn test_render_body() {
+        let notification = Notification {
+            event: Event::Failed,
+            operation: "playlist".into(),
+            title: "ncmdump: playlist failed".into(),
+            message: "2 of 10 \"downloads\" failed\nretry later".into(),
+            duration_secs: 5,
+        };
+        let body = render(
+            r#"{"chat_id": 1, "text": "{title}: {message}", "e": "{event}"}"#,
+            &notification,
+        );
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(
+            json["text"],
+            "ncmdump: playlist failed: 2 of 10 \"downloads\" failed\nretry later"
+        );
+        assert_eq!(json["e"], "failed");
+    }
+}
@@ -0,0 +1,341 @@
+//! Persistent download queue for bulk downloads.
+//!
+//! Playlist, album, chart and daemon downloads go through a queue stored as
+//! `.ncmdump-queue.json` in the output directory. Each entry is `pending`,
+//! `active` or `failed` and counts its attempts; finished tracks are dropped
+//! and the file is removed once nothing is left. When a run is killed, the
+//! next run into the same directory finds the file, puts the entries that
+//! were `active` back to `pending` and finishes the remaining ones before
+//! any new tracks. Entries that used up their retries stay `failed` and are
+//! tried again on the next run.
+//!
+//! Transfers themselves resume through `<file>.part` (see
+//! `NeteaseClient::download_resumable`) and share one [`RateLimit`].
+
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+/// Name of the queue file in the output directory.
+pub const FILE_NAME: &str = ".ncmdump-queue.json";
+
+/// Format version written by this build.
+const VERSION: u32 = 1;
+
+/// Delay before the next attempt, times the attempts so far.
+pub const RETRY_DELAY: Duration = Duration::from_secs(2);
+
+/// Where an entry is in its download.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Pending,
+    /// Being downloaded; only seen in the file after a crash.
+    Active,
+    /// Out of retries for this run.
+    Failed,
+    /// Finished this run; not written to the file.
+    #[serde(skip)]
+    Done,
+}
+
+/// One queued track.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Entry {
+    pub id: u64,
+    /// Track name, for messages.
+    pub name: String,
+    /// Output file name without extension.
+    pub stem: String,
+    pub status: Status,
+    /// Failed attempts so far.
+    #[serde(default)]
+    pub attempts: u32,
+    /// Error of the last failed attempt.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct File {
+    version: u32,
+    entries: Vec<Entry>,
+}
+
+/// The download queue of one output directory, shared by the download
+/// threads.
+#[derive(Debug)]
+pub struct Queue {
+    path: PathBuf,
+    retries: u32,
+    /// Entries carried over from an interrupted run.
+    resumed: usize,
+    entries: Mutex<Vec<Entry>>,
+}
+
+impl Queue {
+    /// Open the queue in `dir` and append `tracks` (ID, name and file stem)
+    /// that are not queued yet. A failed download is retried `retries`
+    /// times in this run.
+    pub fn open(
+        dir: &Path,
+        tracks: impl IntoIterator<Item = (u64, String, String)>,
+        retries: u32,
+    ) -> std::io::Result<Self> {
+        let path = dir.join(FILE_NAME);
+        let mut entries = match std::fs::read(&path) {
+            Ok(bytes) => {
+                let file: File = serde_json::from_slice(&bytes).map_err(std::io::Error::other)?;
+                if file.version > VERSION {
+                    return Err(std::io::Error::other(format!(
+                        "queue format {} is newer than this build supports",
+                        file.version
+                    )));
+                }
+                file.entries
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e),
+        };
+        // Retries are counted per run.
+        for entry in &mut entries {
+            entry.status = Status::Pending;
+            entry.attempts = 0;
+        }
+        let resumed = entries.len();
+        for (id, name, stem) in tracks {
+            if !entries.iter().any(|e| e.id == id) {
+                entries.push(Entry {
+                    id,
+                    name,
+                    stem,
+                    status: Status::Pending,
+                    attempts: 0,
+                    error: None,
+                });
+            }
+        }
+        let queue = Self {
+            path,
+            retries,
+            resumed,
+            entries: Mutex::new(entries),
+        };
+        queue.save(&queue.lock())?;
+        Ok(queue)
+    }
+
+    /// Number of entries, finished ones included.
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Entries carried over from an interrupted run.
+    pub fn resumed(&self) -> usize {
+        self.resumed
+    }
+
+    /// Mark the next pending entry active and return it with its position.
+    /// Entries with fewer attempts go first, so retries wait for the rest.
+    pub fn next(&self) -> Option<(usize, Entry)> {
+        let mut entries = self.lock();
+        let (index, entry) = entries
+            .iter_mut()
+            .enumerate()
+            .filter(|(_, e)| e.status == Status::Pending)
+            .min_by_key(|(i, e)| (e.attempts, *i))?;
+        entry.status = Status::Active;
+        let taken = (index, entry.clone());
+        self.save_logged(&entries);
+        Some(taken)
+    }
+
+    /// Record the outcome of the entry at `index`. Returns whether it will
+    /// be retried.
+    pub fn finish(&self, index: usize, error: Option<String>) -> bool {
+        let mut entries = self.lock();
+        let entry = &mut entries[index];
+        let retry = match error {
+            None => {
+                entry.status = Status::Done;
+                false
+            }
+            Some(error) => {
+                entry.attempts += 1;
+                entry.error = Some(error);
+                let retry = entry.attempts <= self.retries;
+                entry.status = if retry {
+                    Status::Pending
+                } else {
+                    Status::Failed
+                };
+                retry
+            }
+        };
+        self.save_logged(&entries);
+        retry
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<Entry>> {
+        self.entries
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    /// Write the unfinished entries, or remove the file if there are none.
+    fn save(&self, entries: &[Entry]) -> std::io::Result<()> {
+        let file = File {
+            version: VERSION,
+            entries: entries
+                .iter()
+                .filter(|e| e.status != Status::Done)
+                .cloned()
+                .collect(),
+        };
+        if file.entries.is_empty() {
+            return match std::fs::remove_file(&self.path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+                _ => Ok(()),
+            };
+        }
+        let json = serde_json::to_vec_pretty(&file).map_err(std::io::Error::other)?;
+        let tmp = self.path.with_extension("json.tmp");
+        std::fs::write(&tmp, json)?;
+        std::fs::rename(&tmp, &self.path)
+    }
+
+    fn save_logged(&self, entries: &[Entry]) {
+        if let Err(e) = self.save(entries) {
+            tracing::warn!(path = %self.path.display(), error = %e, "failed to save download queue");
+        }
+    }
+}
+
+/// A bandwidth limit shared by concurrent downloads.
+#[derive(Debug)]
+pub struct RateLimit {
+    bytes_per_sec: u64,
+    /// Start of the current window and the bytes transferred in it.
+    window: Mutex<(Instant, u64)>,
+}
+
+impl RateLimit {
+    pub fn new(bytes_per_sec: u64) -> Self {
+        Self {
+            bytes_per_sec,
+            window: Mutex::new((Instant::now(), 0)),
+        }
+    }
+
+    /// Account for `bytes` just transferred, sleeping as long as the
+    /// transfers are ahead of the limit.
+    pub fn consume(&self, bytes: u64) {
+        let wait = {
+            let mut window = self
+                .window
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner);
+            let elapsed = window.0.elapsed();
+            // Start over after a pause, so idle time doesn't allow a burst.
+            if elapsed > Duration::from_secs(1) + self.due(window.1) {
+                *window = (Instant::now(), 0);
+            }
+            window.1 += bytes;
+            self.due(window.1).saturating_sub(window.0.elapsed())
+        };
+        if !wait.is_zero() {
+            std::thread::sleep(wait);
+        }
+    }
+
+    /// How long `bytes` take at the limit.
+    fn due(&self, bytes: u64) -> Duration {
+        Duration::from_millis(bytes.saturating_mul(1000) / self.bytes_per_sec)
+    }
+}
+
+/// Parse a rate such as `500K` or `2M` (bytes per second; `K`, `M` and `G`
+/// are powers of 1024).
+pub fn parse_rate(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let (digits, unit) = s.split_at(s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len()));
+    let n: u64 = digits
+        .parse()
+        .map_err(|_| format!("invalid rate {s:?} (e.g. 500K, 2M)"))?;
+    let scale = match unit.to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KB" => 1 << 10,
+        "M" | "MB" => 1 << 20,
+        "G" | "GB" => 1 << 30,
+        _ => return Err(format!("invalid rate unit {unit:?} (use K, M or G)")),
+    };
+    match n.checked_mul(scale) {
+        Some(0) => Err("rate must be positive".into()),
+        Some(rate) => Ok(rate),
+        None => Err(format!("rate {s:?} is too large")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tracks(ids: &[u64]) -> Vec<(u64, String, String)> {
+        ids.iter()
+            .map(|&id| (id, format!("track {id}"), format!("{id}")))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_rate() {
+        assert_eq!(parse_rate("1000"), Ok(1000));
+        assert_eq!(parse_rate("500k"), Ok(500 * 1024));
+        assert_eq!(parse_rate("2M"), Ok(2 << 20));
+        assert_eq!(parse_rate("1GB"), Ok(1 << 30));
+        assert!(parse_rate("0").is_err());
+        assert!(parse_rate("M").is_err());
+        assert!(parse_rate("5x").is_err());
+        assert!(parse_rate("1.5M").is_err());
+    }
+
+    #[test]
+    fn test_queue_resumes() {
+        let dir = std::env::temp_dir().join(format!("ncmdump-queue-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let queue = Queue::open(&dir, tracks(&[1, 2, 3]), 1).unwrap();
+        assert_eq!((queue.len(), queue.resumed()), (3, 0));
+        let (i, first) = queue.next().unwrap();
+        assert_eq!(first.id, 1);
+        assert!(!queue.finish(i, None));
+        let (i, second) = queue.next().unwrap();
+        assert_eq!(second.id, 2);
+        // A failed entry is retried after the others.
+        assert!(queue.finish(i, Some("timeout".into())));
+        assert_eq!(queue.next().unwrap().1.id, 3);
+        // Interrupted here: 2 is pending again, 3 active.
+        drop(queue);
+
+        let queue = Queue::open(&dir, tracks(&[3, 4]), 0).unwrap();
+        assert_eq!((queue.len(), queue.resumed()), (3, 2));
+        let order: Vec<u64> = std::iter::from_fn(|| {
+            let (i, entry) = queue.next()?;
+            let error = (entry.id == 4).then(|| "gone".to_owned());
+            assert!(!queue.finish(i, error));
+            Some(entry.id)
+        })
+        .collect();
+        assert_eq!(order, [2, 3, 4]);
+        drop(queue);
+
+        // Only the failed entry is left, and it gets another chance.
+        let queue = Queue::open(&dir, Vec::new(), 0).unwrap();
+        let (i, entry) = queue.next().unwrap();
+        assert_eq!((entry.id, entry.error.as_deref()), (4, Some("gone")));
+        queue.finish(i, None);
+        assert!(!dir.join(FILE_NAME).exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,577 @@
+//! JSON-RPC server of `ncmdump-cli rpc`, for GUI front-ends and editor
+//! plugins.
+//!
+//! Clients connect to a local socket — `rpc.sock` in the config directory
+//! by default, or loopback TCP (the default on Windows) — and exchange
+//! JSON-RPC 2.0 messages, one per line:
+//!
+//! ```text
+//! → {"jsonrpc":"2.0","id":1,"method":"sync","params":{"playlist":"24381616","output":"music"}}
+//! ← {"jsonrpc":"2.0","method":"progress","params":{"request":1,"event":"progress","id":186016,"percent":40,…}}
+//! ← {"jsonrpc":"2.0","id":1,"result":{"playlist":{"id":24381616,"name":"…"},"tracks":50}}
+//! ```
+//!
+//! | Method         | Params                                                              |
+//! |----------------|---------------------------------------------------------------------|
+//! | `version`      | —                                                                   |
+//! | `login.status` | —                                                                   |
+//! | `inspect`      | `path`                                                              |
+//! | `convert`      | `files`, `directory`, `recursive`, `output`, `template`, `on_collision`, `replaygain`, `metadata_json`, `cover_max_size`, `cover_quality`, `keep_163_key`, `id3v23`, `id3_encoding`, `tag_mode` |
+//! | `search`       | `keyword`, `type`, `limit`, `offset`                                |
+//! | `download`     | `tracks` (IDs or song URLs), `quality`, `output`, `jobs`, `skip_existing` |
+//! | `sync`         | `playlist` (ID or URL), `quality`, `output`, `jobs`, `skip_existing` |
+//!
+//! While a call runs, the events `--progress json` would print are sent to
+//! its connection as `progress` notifications carrying the request ID;
+//! the per-file lines and summaries the commands print are left out.
+//! Calls from all connections run one at a time, in arrival order. A failed
+//! operation is answered with error code `-32000` and the exit status the
+//! command would have had as `data.exit`.
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+
+use anyhow::Context as _;
+use clap::ValueEnum;
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde_json::{Value, json};
+
+use crate::i18n::tr;
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// The operation itself failed.
+const OPERATION_FAILED: i64 = -32000;
+
+/// Where the server listens.
+#[derive(Debug, Clone)]
+pub enum Listen {
+    #[cfg(unix)]
+    Unix(PathBuf),
+    Tcp(SocketAddr),
+}
+
+impl std::fmt::Display for Listen {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            #[cfg(unix)]
+            Self::Unix(path) => write!(f, "{}", path.display()),
+            Self::Tcp(addr) => write!(f, "{addr}"),
+        }
+    }
+}
+
+/// Parse `--listen`: a loopback `HOST:PORT`, or a socket path on Unix.
+pub fn parse_listen(s: &str) -> Result<Listen, String> {
+    if let Ok(addr) = s.parse::<SocketAddr>() {
+        // Anyone who can connect acts with the saved login.
+        if !addr.ip().is_loopback() {
+            return Err(format!("{addr} is not a loopback address"));
+        }
+        return Ok(Listen::Tcp(addr));
+    }
+    #[cfg(unix)]
+    {
+        Ok(Listen::Unix(PathBuf::from(s)))
+    }
+    #[cfg(not(unix))]
+    {
+        Err(format!("invalid address {s:?} (expected 127.0.0.1:PORT)"))
+    }
+}
+
+/// `rpc.sock` next to the session file, or loopback TCP on Windows.
+#[cfg_attr(unix, allow(clippy::unnecessary_wraps))]
+pub fn default_listen() -> anyhow::Result<Listen> {
+    #[cfg(unix)]
+    {
+        let path = netease_api::auth::Session::path()?.with_file_name("rpc.sock");
+        Ok(Listen::Unix(path))
+    }
+    #[cfg(not(unix))]
+    {
+        Ok(Listen::Tcp(SocketAddr::from(([127, 0, 0, 1], 8164))))
+    }
+}
+
+/// A connection's write half, shared with the progress sink.
+type Writer = Arc<Mutex<Box<dyn Write + Send>>>;
+
+/// The connection and request ID of the running call.
+struct Sink {
+    request: Value,
+    out: Writer,
+}
+
+static SINK: Mutex<Option<Sink>> = Mutex::new(None);
+
+/// Held while a call runs, so calls don't interleave their events.
+static RUNNING: Mutex<()> = Mutex::new(());
+
+/// Set once the server starts; see [`serving`].
+static SERVING: AtomicBool = AtomicBool::new(false);
+
+/// Whether this process is the RPC server. Its calls answer through results
+/// and progress events, so the commands they run print nothing to stdout.
+pub fn serving() -> bool {
+    SERVING.load(Ordering::Relaxed)
+}
+
+/// Send a progress event to the client of the running call. Returns `false`
+/// outside the RPC server, where events go to stderr instead.
+pub fn forward(mut event: serde_json::Map<String, Value>) -> bool {
+    let sink = SINK.lock().unwrap_or_else(PoisonError::into_inner);
+    let Some(sink) = sink.as_ref() else {
+        return false;
+    };
+    event.insert("request".into(), sink.request.clone());
+    let message = json!({ "jsonrpc": "2.0", "method": "progress", "params": event });
+    send(&sink.out, &message);
+    true
+}
+
+fn send(out: &Writer, message: &Value) {
+    let mut out = out.lock().unwrap_or_else(PoisonError::into_inner);
+    // A client that went away only loses its messages.
+    let _ = writeln!(out, "{message}").and_then(|()| out.flush());
+}
+
+/// Accept connections on `listen` until the process is stopped.
+pub fn run(listen: &Listen) -> anyhow::Result<()> {
+    SERVING.store(true, Ordering::Relaxed);
+    match listen {
+        #[cfg(unix)]
+        Listen::Unix(path) => {
+            use std::os::unix::fs::PermissionsExt;
+            use std::os::unix::net::{UnixListener, UnixStream};
+
+            if path.exists() {
+                // A socket nobody answers on is left over from a killed server.
+                anyhow::ensure!(
+                    UnixStream::connect(path).is_err(),
+                    tr!("rpc-running", addr = path.display())
+                );
+                std::fs::remove_file(path)
+                    .with_context(|| tr!("write-failed", path = path.display()))?;
+            }
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)
+                    .with_context(|| tr!("write-failed", path = parent.display()))?;
+            }
+            let listener = UnixListener::bind(path)
+                .with_context(|| tr!("rpc-bind-failed", addr = path.display()))?;
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))
+                .with_context(|| tr!("write-failed", path = path.display()))?;
+            for stream in listener.incoming() {
+                match stream.and_then(|s| Ok((s.try_clone()?, s))) {
+                    Ok((read, write)) => spawn_connection(read, write),
+                    Err(e) => tracing::warn!(error = %e, "cannot accept RPC connection"),
+                }
+            }
+        }
+        Listen::Tcp(addr) => {
+            let listener = std::net::TcpListener::bind(addr)
+                .with_context(|| tr!("rpc-bind-failed", addr = addr))?;
+            for stream in listener.incoming() {
+                match stream.and_then(|s| Ok((s.try_clone()?, s))) {
+                    Ok((read, write)) => spawn_connection(read, write),
+                    Err(e) => tracing::warn!(error = %e, "cannot accept RPC connection"),
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn spawn_connection(read: impl std::io::Read + Send + 'static, write: impl Write + Send + 'static) {
+    std::thread::spawn(move || {
+        tracing::debug!("RPC client connected");
+        let out: Writer = Arc::new(Mutex::new(Box::new(write)));
+        for line in BufReader::new(read).lines() {
+            let Ok(line) = line else { break };
+            if line.trim().is_empty() {
+                continue;
+            }
+            if let Some(response) = handle(&line, &out) {
+                send(&out, &response);
+            }
+        }
+        tracing::debug!("RPC client disconnected");
+    });
+}
+
+/// Answer one message; `None` for notifications.
+fn handle(line: &str, out: &Writer) -> Option<Value> {
+    let message: Value = match serde_json::from_str(line) {
+        Ok(message) => message,
+        Err(e) => return Some(error(&Value::Null, PARSE_ERROR, &e.to_string(), None)),
+    };
+    let id = message.get("id").cloned();
+    let (Some(method), Some("2.0")) = (
+        message.get("method").and_then(Value::as_str),
+        message.get("jsonrpc").and_then(Value::as_str),
+    ) else {
+        let id = id.unwrap_or(Value::Null);
+        return Some(error(
+            &id,
+            INVALID_REQUEST,
+            "not a JSON-RPC 2.0 request",
+            None,
+        ));
+    };
+    let params = message.get("params").cloned().unwrap_or(Value::Null);
+
+    let _running = RUNNING.lock().unwrap_or_else(PoisonError::into_inner);
+    *SINK.lock().unwrap_or_else(PoisonError::into_inner) = id.clone().map(|request| Sink {
+        request,
+        out: Arc::clone(out),
+    });
+    tracing::info!(method, "RPC call");
+    let result = call(method, params);
+    *SINK.lock().unwrap_or_else(PoisonError::into_inner) = None;
+
+    let id = id?;
+    Some(match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(Failure::Method) => error(
+            &id,
+            METHOD_NOT_FOUND,
+            &format!("unknown method {method:?}"),
+            None,
+        ),
+        Err(Failure::Params(message)) => error(&id, INVALID_PARAMS, &message, None),
+        Err(Failure::Operation(e)) => {
+            let exit = crate::Exit::classify(&e) as u8;
+            error(
+                &id,
+                OPERATION_FAILED,
+                &format!("{e:#}"),
+                Some(json!({ "exit": exit })),
+            )
+        }
+    })
+}
+
+fn error(id: &Value, code: i64, message: &str, data: Option<Value>) -> Value {
+    let mut error = json!({ "code": code, "message": message });
+    if let Some(data) = data {
+        error["data"] = data;
+    }
+    json!({ "jsonrpc": "2.0", "id": id, "error": error })
+}
+
+/// Why a call failed.
+enum Failure {
+    Method,
+    Params(String),
+    Operation(anyhow::Error),
+}
+
+impl From<anyhow::Error> for Failure {
+    fn from(e: anyhow::Error) -> Self {
+        Self::Operation(e)
+    }
+}
+
+impl From<netease_api::NeteaseError> for Failure {
+    fn from(e: netease_api::NeteaseError) -> Self {
+        Self::Operation(e.into())
+    }
+}
+
+fn params<T: DeserializeOwned>(params: Value) -> Result<T, Failure> {
+    // Methods without required params may be called without any.
+    let params = if params.is_null() { json!({}) } else { params };
+    serde_json::from_value(params).map_err(|e| Failure::Params(e.to_string()))
+}
+
+/// A `ValueEnum` param given by its command-line name.
+fn choice<T: ValueEnum>(value: Option<&str>, default: &str) -> Result<T, Failure> {
+    let value = value.unwrap_or(default);
+    T::from_str(value, true).map_err(|_| Failure::Params(format!("invalid value {value:?}")))
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PathParams {
+    path: PathBuf,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+#[allow(clippy::struct_excessive_bools)]
+struct ConvertParams {
+    #[serde(default)]
+    files: Vec<PathBuf>,
+    directory: Option<PathBuf>,
+    #[serde(default)]
+    recursive: bool,
+    output: Option<PathBuf>,
+    template: Option<String>,
+    on_collision: Option<String>,
+    #[serde(default)]
+    replaygain: bool,
+    #[serde(default)]
+    metadata_json: bool,
+    cover_max_size: Option<u32>,
+    cover_quality: Option<u8>,
+    #[serde(default)]
+    keep_163_key: bool,
+    #[serde(default)]
+    id3v23: bool,
+    id3_encoding: Option<String>,
+    tag_mode: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SearchParams {
+    keyword: String,
+    r#type: Option<String>,
+    limit: Option<u64>,
+    #[serde(default)]
+    offset: u64,
+}
+
+/// Options shared by `download` and `sync`.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DownloadParams {
+    #[serde(default)]
+    tracks: Vec<Value>,
+    playlist: Option<Value>,
+    quality: Option<String>,
+    output: Option<PathBuf>,
+    jobs: Option<usize>,
+    skip_existing: Option<String>,
+}
+
+impl DownloadParams {
+    fn quality(&self) -> Result<crate::QualityArg, Failure> {
+        choice(self.quality.as_deref(), "exhigh")
+    }
+
+    fn skip(&self) -> Result<Option<crate::SkipCheck>, Failure> {
+        self.skip_existing
+            .as_deref()
+            .map(|s| choice(Some(s), "exists"))
+            .transpose()
+    }
+}
+
+/// A track or playlist reference: a number, or a string ID or URL.
+fn reference(value: &Value) -> Result<String, Failure> {
+    match value {
+        Value::Number(n) => Ok(n.to_string()),
+        Value::String(s) => Ok(s.clone()),
+        _ => Err(Failure::Params(format!("invalid ID {value}"))),
+    }
+}
+
+fn call(method: &str, raw: Value) -> Result<Value, Failure> {
+    match method {
+        "version" => Ok(json!({
+            "version": env!("CARGO_PKG_VERSION"),
+            "methods": ["version", "login.status", "inspect", "convert", "search", "download", "sync"],
+        })),
+        "login.status" => login_status(),
+        "inspect" => {
+            let p: PathParams = params(raw)?;
+            inspect(&p.path)
+        }
+        "convert" => convert(params(raw)?),
+        "search" => {
+            let p: SearchParams = params(raw)?;
+            let kind: crate::SearchKind = choice(p.r#type.as_deref(), "track")?;
+            let client = crate::netease_client()?;
+            let result = client.search(&p.keyword, kind.into(), p.limit.unwrap_or(30), p.offset)?;
+            serde_json::to_value(result).map_err(|e| Failure::Operation(e.into()))
+        }
+        "download" => {
+            let p: DownloadParams = params(raw)?;
+            if p.tracks.is_empty() || p.playlist.is_some() {
+                return Err(Failure::Params(
+                    "`download` takes a non-empty `tracks`".into(),
+                ));
+            }
+            let tracks = p
+                .tracks
+                .iter()
+                .map(reference)
+                .collect::<Result<Vec<_>, _>>()?;
+            crate::cmd_download_batch(
+                &tracks,
+                p.quality()?,
+                p.output.clone(),
+                p.jobs.unwrap_or(crate::DEFAULT_JOBS),
+                p.skip()?,
+                None,
+            )?;
+            Ok(json!({ "tracks": tracks.len() }))
+        }
+        "sync" => {
+            let p: DownloadParams = params(raw)?;
+            let (Some(playlist), true) = (&p.playlist, p.tracks.is_empty()) else {
+                return Err(Failure::Params("`sync` takes a `playlist`".into()));
+            };
+            let id = crate::parse_ref(&reference(playlist)?, crate::ResourceKind::Playlist)?;
+            let client = crate::netease_client()?;
+            let playlist = client.playlist_detail(id)?;
+            let tracks = playlist.tracks.as_deref().unwrap_or_default();
+            crate::download_tracks(
+                &client,
+                tracks,
+                p.quality()?.into(),
+                p.output.as_deref().unwrap_or(std::path::Path::new(".")),
+                p.skip()?,
+                p.jobs.unwrap_or(crate::DEFAULT_JOBS),
+            )?;
+            Ok(json!({
+                "playlist": { "id": playlist.id, "name": playlist.name },
+                "tracks": tracks.len(),
+            }))
+        }
+        _ => Err(Failure::Method),
+    }
+}
+
+fn login_status() -> Result<Value, Failure> {
+    use netease_api::auth::{KeySource, Session};
+
+    let encryption = Session::encryption()?.map(|key| match key {
+        KeySource::Passphrase => "passphrase",
+        KeySource::Machine => "machine",
+    });
+    let session = crate::load_session()?;
+    let user = if session.is_logged_in() {
+        let client = crate::netease_client_builder().session(session).build()?;
+        match client.user_info() {
+            Ok(profile) => Some(json!({ "id": profile.id, "name": profile.nickname })),
+            Err(e) => {
+                return Ok(json!({
+                    "logged_in": true, "valid": false, "error": e.to_string(), "encryption": encryption,
+                }));
+            }
+        }
+    } else {
+        None
+    };
+    Ok(json!({
+        "logged_in": user.is_some(),
+        "valid": user.is_some(),
+        "user": user,
+        "encryption": encryption,
+    }))
+}
+
+fn convert(p: ConvertParams) -> Result<Value, Failure> {
+    let naming = crate::Naming {
+        template: p.template.as_deref(),
+        collision: p
+            .on_collision
+            .as_deref()
+            .map(|c| choice(Some(c), "rename"))
+            .transpose()?,
+        ..crate::Naming::default()
+    };
+    let jobs = crate::dump_jobs(
+        p.files,
+        None,
+        p.directory.as_ref(),
+        p.recursive,
+        p.output.as_ref(),
+    )?;
+    let opts = ncmdump::ConvertOptions {
+        tags: crate::tag_options(
+            p.cover_max_size,
+            p.cover_quality,
+            p.keep_163_key,
+            p.id3v23,
+            p.id3_encoding
+                .as_deref()
+                .map(|e| choice(Some(e), "utf8"))
+                .transpose()?,
+            p.tag_mode
+                .as_deref()
+                .map(|m| choice(Some(m), "overwrite"))
+                .transpose()?,
+        ),
+        metadata_json: p.metadata_json,
+    };
+    crate::cmd_dump(&jobs, naming, false, p.replaygain, &opts, false)?;
+    Ok(json!({ "files": jobs.len() }))
+}
+
+fn inspect(path: &std::path::Path) -> Result<Value, Failure> {
+    let ncm = ncmdump::inspect(path).with_context(|| tr!("read-failed", path = path.display()))?;
+    let meta = ncm.metadata.as_ref();
+    Ok(json!({
+        "format": ncm.format.extension(),
+        "title": meta.map(|m| &m.music_name),
+        "artist": meta.map(ncmdump::NcmMetadata::artist_names),
+        "album": meta.map(|m| &m.album),
+        "duration_ms": meta.map_or(0, |m| m.duration),
+        "bitrate": meta.map_or(0, |m| m.bitrate),
+        "netease_id": meta.map(|m| m.music_id).filter(|&id| id != 0),
+        "cover_bytes": ncm.cover_image.as_ref().map_or(0, Vec::len),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A writer collecting what is sent, for checking responses.
+    fn writer() -> Writer {
+        Arc::new(Mutex::new(Box::new(Vec::new())))
+    }
+
+    #[test]
+    fn test_protocol_errors() {
+        let out = writer();
+        let response = handle("{", &out).unwrap();
+        assert_eq!(response["error"]["code"], PARSE_ERROR);
+        let response = handle(r#"{"id":1,"method":"version"}"#, &out).unwrap();
+        assert_eq!(response["error"]["code"], INVALID_REQUEST);
+        let response = handle(r#"{"jsonrpc":"2.0","id":2,"method":"nope"}"#, &out).unwrap();
+        assert_eq!(
+            (response["id"].clone(), response["error"]["code"].clone()),
+            (json!(2), json!(METHOD_NOT_FOUND))
+        );
+        let response = handle(
+            r#"{"jsonrpc":"2.0","id":3,"method":"inspect","params":{}}"#,
+            &out,
+        )
+        .unwrap();
+        assert_eq!(response["error"]["code"], INVALID_PARAMS);
+        // Notifications get no response.
+        assert!(handle(r#"{"jsonrpc":"2.0","method":"version"}"#, &out).is_none());
+    }
+
+    #[test]
+    fn test_inspect() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/../testdata/test.ncm");
+        let line =
+            json!({ "jsonrpc": "2.0", "id": "a", "method": "inspect", "params": { "path": path } });
+        let response = handle(&line.to_string(), &writer()).unwrap();
+        assert_eq!(response["id"], "a");
+        assert!(response["result"]["format"].is_string(), "{response}");
+
+        let line = json!({ "jsonrpc": "2.0", "id": 1, "method": "inspect", "params": { "path": "missing.ncm" } });
+        let response = handle(&line.to_string(), &writer()).unwrap();
+        assert_eq!(response["error"]["code"], OPERATION_FAILED);
+        assert_eq!(response["error"]["data"]["exit"], 6);
+    }
+
+    #[test]
+    fn test_parse_listen() {
+        assert!(matches!(parse_listen("127.0.0.1:8164"), Ok(Listen::Tcp(_))));
+        assert!(parse_listen("0.0.0.0:8164").is_err());
+    }
+}
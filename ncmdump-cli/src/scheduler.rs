@@ -0,0 +1,516 @@
+//! Job scheduling with persistent run records.
+//!
+//! A [`Scheduler`] runs named jobs on a [`Schedule`] — an interval, a daily
+//! time or a cron expression — and keeps the outcome of each job's last run
+//! in a JSON [`State`] file, so a restarted process picks up where it left
+//! off: interval jobs wait out the rest of their interval and a run missed
+//! while it was down is made up at once. Jobs are plain closures, so any
+//! command can register its own (`daemon` registers its `daemon.toml` jobs).
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use chrono::{DateTime, Datelike, Local, NaiveDate, NaiveTime, TimeZone, Timelike};
+use serde::{Deserialize, Serialize};
+
+/// Longest sleep between checks, so clock changes and suspends are noticed.
+const MAX_WAIT: Duration = Duration::from_secs(60);
+
+/// How far ahead a cron expression is searched for its next match: long
+/// enough for `29 feb`.
+const SEARCH_DAYS: i64 = 366 * 8;
+
+/// When a job runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Schedule {
+    /// Repeatedly, this long after the previous run started.
+    Every(Duration),
+    /// Once a day at this local time.
+    Daily(NaiveTime),
+    /// At the local times a cron expression matches.
+    Cron(Cron),
+}
+
+impl Schedule {
+    /// When the job is due, given when it last started. Jobs that never ran
+    /// are due immediately (`Every`) or at the next matching time (`Daily`,
+    /// `Cron`); a run missed while nothing was running is due immediately.
+    pub fn next_run(self, last: Option<DateTime<Local>>, now: DateTime<Local>) -> DateTime<Local> {
+        match (self, last) {
+            (Self::Every(_), None) => now,
+            (Self::Every(interval), Some(last)) => {
+                last + chrono::Duration::from_std(interval).unwrap_or(chrono::Duration::MAX)
+            }
+            (Self::Daily(time), last) => {
+                let after = last.unwrap_or(now);
+                let mut date = after.date_naive();
+                loop {
+                    if let Some(at) = Local.from_local_datetime(&date.and_time(time)).earliest() {
+                        if at > after || (last.is_none() && at == after) {
+                            return at;
+                        }
+                    }
+                    date = date.succ_opt().unwrap_or(date);
+                }
+            }
+            (Self::Cron(cron), last) => {
+                // Never ran: the current minute counts if it matches.
+                let after = last.unwrap_or(now - chrono::Duration::minutes(1));
+                // `Cron::parse` rejects expressions that never match.
+                cron.next_after(after)
+                    .unwrap_or(after + chrono::Duration::days(SEARCH_DAYS))
+            }
+        }
+    }
+}
+
+/// Parse an interval made of `<n><unit>` parts, with units `s`, `m`, `h` and
+/// `d` (e.g. `90s`, `1h30m`).
+pub fn parse_interval(s: &str) -> Result<Duration, String> {
+    let invalid = || format!("invalid interval {s:?} (expected e.g. 30m, 1h, 1d)");
+    let mut total = 0u64;
+    let mut rest = s.trim();
+    if rest.is_empty() {
+        return Err(invalid());
+    }
+    while !rest.is_empty() {
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .ok_or_else(invalid)?;
+        let n: u64 = rest[..digits].parse().map_err(|_| invalid())?;
+        let unit = match rest[digits..].chars().next() {
+            Some('s') => 1,
+            Some('m') => 60,
+            Some('h') => 3600,
+            Some('d') => 86_400,
+            _ => return Err(invalid()),
+        };
+        total = total.saturating_add(n.saturating_mul(unit));
+        rest = &rest[digits + 1..];
+    }
+    if total == 0 {
+        return Err(invalid());
+    }
+    Ok(Duration::from_secs(total))
+}
+
+/// A five-field cron expression: minute, hour, day of month, month and day
+/// of week, each `*`, a value, a range `a-b` or a list of these, optionally
+/// with a step (`*/15`, `9-17/2`). Months and weekdays also take English
+/// three-letter names; Sunday is `0` or `7`. As in Vixie cron, when both day
+/// fields are restricted a day matching either one counts. `@hourly`,
+/// `@daily`, `@weekly`, `@monthly` and `@yearly` are accepted too.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cron {
+    minutes: u64,
+    hours: u32,
+    days: u32,
+    months: u16,
+    weekdays: u8,
+    any_day: bool,
+    any_weekday: bool,
+}
+
+const MONTHS: &[&str] = &[
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+const WEEKDAYS: &[&str] = &["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+impl Cron {
+    pub fn parse(expr: &str) -> Result<Self, String> {
+        let expr = match expr.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            expr => expr,
+        };
+        let fields: Vec<&str> = expr.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err(format!(
+                "invalid cron expression {expr:?} (expected 5 fields, e.g. \"0 8 * * 1-5\")"
+            ));
+        };
+        let weekdays = field(weekday, 0, 7, WEEKDAYS)?;
+        let cron = Self {
+            minutes: field(minute, 0, 59, &[])?,
+            hours: u32::try_from(field(hour, 0, 23, &[])?).unwrap_or(0),
+            days: u32::try_from(field(day, 1, 31, &[])?).unwrap_or(0),
+            months: u16::try_from(field(month, 1, 12, MONTHS)?).unwrap_or(0),
+            // Fold 7 onto Sunday.
+            weekdays: u8::try_from((weekdays | weekdays >> 7) & 0x7F).unwrap_or(0),
+            any_day: day == "*",
+            any_weekday: weekday == "*",
+        };
+        if cron.next_after(Local::now()).is_none() {
+            return Err(format!("cron expression {expr:?} never matches"));
+        }
+        Ok(cron)
+    }
+
+    fn matches_date(&self, date: NaiveDate) -> bool {
+        let day = self.days & 1 << date.day() != 0;
+        let weekday = self.weekdays & 1 << date.weekday().num_days_from_sunday() != 0;
+        let day_ok = match (self.any_day, self.any_weekday) {
+            (false, false) => day || weekday,
+            _ => day && weekday,
+        };
+        day_ok && self.months & 1 << date.month() != 0
+    }
+
+    /// The first matching minute strictly after `after`, within
+    /// [`SEARCH_DAYS`].
+    fn next_after(&self, after: DateTime<Local>) -> Option<DateTime<Local>> {
+        let start =
+            after.naive_local().with_second(0)?.with_nanosecond(0)? + chrono::Duration::minutes(1);
+        let mut date = start.date();
+        let mut from = start.time();
+        for _ in 0..SEARCH_DAYS {
+            if self.matches_date(date) {
+                for hour in from.hour()..24 {
+                    if self.hours & 1 << hour == 0 {
+                        continue;
+                    }
+                    let first = if hour == from.hour() {
+                        from.minute()
+                    } else {
+                        0
+                    };
+                    for minute in first..60 {
+                        if self.minutes & 1 << minute == 0 {
+                            continue;
+                        }
+                        let time = NaiveTime::from_hms_opt(hour, minute, 0)?;
+                        // Skipped by a DST change: try the next match.
+                        if let Some(at) = Local.from_local_datetime(&date.and_time(time)).earliest()
+                        {
+                            if at > after {
+                                return Some(at);
+                            }
+                        }
+                    }
+                }
+            }
+            date = date.succ_opt()?;
+            from = NaiveTime::MIN;
+        }
+        None
+    }
+}
+
+/// The bit set of values `field` selects out of `min..=max`. `names` are
+/// accepted in place of numbers, the first one standing for `min`.
+fn field(field: &str, min: u32, max: u32, names: &[&str]) -> Result<u64, String> {
+    let invalid = || format!("invalid cron field {field:?} (values {min}-{max})");
+    let value = |s: &str| -> Result<u32, String> {
+        let lower = s.to_ascii_lowercase();
+        let n = match names.iter().position(|n| *n == lower) {
+            Some(i) => min + u32::try_from(i).map_err(|_| invalid())?,
+            None => s.parse().map_err(|_| invalid())?,
+        };
+        if (min..=max).contains(&n) {
+            Ok(n)
+        } else {
+            Err(invalid())
+        }
+    };
+    let mut bits = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().map_err(|_| invalid())?),
+            None => (part, 1),
+        };
+        if step == 0 {
+            return Err(invalid());
+        }
+        let (lo, hi) = match range {
+            "*" => (min, max),
+            range => match range.split_once('-') {
+                Some((lo, hi)) => (value(lo)?, value(hi)?),
+                // `5/15` means from 5 to the end.
+                None if step > 1 => (value(range)?, max),
+                None => {
+                    let n = value(range)?;
+                    (n, n)
+                }
+            },
+        };
+        if lo > hi {
+            return Err(invalid());
+        }
+        for n in (lo..=hi).step_by(step as usize) {
+            bits |= 1 << n;
+        }
+    }
+    Ok(bits)
+}
+
+/// Outcome of a job's most recent run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunRecord {
+    /// Start time, Unix seconds.
+    pub started: i64,
+    /// Wall-clock duration in seconds.
+    pub duration_secs: u64,
+    /// Error message if the run failed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl RunRecord {
+    pub fn started_local(&self) -> Option<DateTime<Local>> {
+        Local.timestamp_opt(self.started, 0).single()
+    }
+}
+
+/// Last run of each job, by name.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct State {
+    pub jobs: BTreeMap<String, RunRecord>,
+}
+
+impl State {
+    /// Load the state file; a missing one is an empty state.
+    pub fn load(path: &Path) -> std::io::Result<Self> {
+        match std::fs::read(path) {
+            Ok(bytes) => serde_json::from_slice(&bytes).map_err(std::io::Error::other),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let json = serde_json::to_vec_pretty(self).map_err(std::io::Error::other)?;
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, json)?;
+        std::fs::rename(&tmp, path)
+    }
+
+    /// When job `name` on `schedule` is next due.
+    pub fn next_run(
+        &self,
+        name: &str,
+        schedule: Schedule,
+        now: DateTime<Local>,
+    ) -> DateTime<Local> {
+        let last = self.jobs.get(name).and_then(RunRecord::started_local);
+        schedule.next_run(last, now)
+    }
+}
+
+/// What [`Scheduler::run_due`] reports about the jobs it runs.
+#[derive(Debug)]
+pub enum Event<'e> {
+    Started(&'e str),
+    Finished(&'e str, &'e RunRecord),
+}
+
+type Task<'a> = Box<dyn FnMut() -> anyhow::Result<()> + 'a>;
+
+/// Registered jobs and their run records.
+pub struct Scheduler<'a> {
+    jobs: Vec<(String, Schedule, Task<'a>)>,
+    state: State,
+    state_path: PathBuf,
+}
+
+impl<'a> Scheduler<'a> {
+    /// A scheduler keeping its run records in `state_path`.
+    pub fn new(state_path: &Path) -> std::io::Result<Self> {
+        Ok(Self {
+            jobs: Vec::new(),
+            state: State::load(state_path)?,
+            state_path: state_path.to_owned(),
+        })
+    }
+
+    /// Add job `name`, running `task` on `schedule`. Names identify run
+    /// records across restarts, so they should be stable.
+    pub fn register(
+        &mut self,
+        name: impl Into<String>,
+        schedule: Schedule,
+        task: impl FnMut() -> anyhow::Result<()> + 'a,
+    ) {
+        self.jobs.push((name.into(), schedule, Box::new(task)));
+    }
+
+    pub fn len(&self) -> usize {
+        self.jobs.len()
+    }
+
+    /// Run the jobs that are due, or all of them with `force`, in order of
+    /// registration, recording each outcome. Returns how many failed.
+    pub fn run_due(&mut self, force: bool, mut observe: impl FnMut(Event)) -> usize {
+        let mut failed = 0;
+        let now = Local::now();
+        for (name, schedule, task) in &mut self.jobs {
+            if !force && self.state.next_run(name, *schedule, now) > now {
+                continue;
+            }
+            observe(Event::Started(name));
+            let started = Local::now();
+            let result = task();
+            let record = RunRecord {
+                started: started.timestamp(),
+                duration_secs: u64::try_from((Local::now() - started).num_seconds()).unwrap_or(0),
+                error: result.err().map(|e| format!("{e:#}")),
+            };
+            failed += usize::from(record.error.is_some());
+            observe(Event::Finished(name, &record));
+            self.state.jobs.insert(name.clone(), record);
+            if let Err(e) = self.state.save(&self.state_path) {
+                tracing::warn!(path = %self.state_path.display(), error = %e, "failed to save scheduler state");
+            }
+        }
+        failed
+    }
+
+    /// How long to sleep before the next job may be due.
+    pub fn wait(&self) -> Duration {
+        let now = Local::now();
+        let next = self
+            .jobs
+            .iter()
+            .map(|(name, schedule, _)| self.state.next_run(name, *schedule, now))
+            .min();
+        next.map_or(MAX_WAIT, |t| {
+            (t - now).to_std().unwrap_or_default().min(MAX_WAIT)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn local(s: &str) -> DateTime<Local> {
+        let naive = chrono::NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap();
+        Local.from_local_datetime(&naive).earliest().unwrap()
+    }
+
+    #[test]
+    fn test_parse_interval() {
+        assert_eq!(parse_interval("90s"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_interval("1h30m"), Ok(Duration::from_secs(5400)));
+        assert_eq!(parse_interval("1d"), Ok(Duration::from_secs(86_400)));
+        assert!(parse_interval("").is_err());
+        assert!(parse_interval("10").is_err());
+        assert!(parse_interval("5x").is_err());
+        assert!(parse_interval("0m").is_err());
+    }
+
+    #[test]
+    fn test_next_run() {
+        let hourly = Schedule::Every(Duration::from_secs(3600));
+        let now = local("2026-10-16 10:30");
+        assert_eq!(hourly.next_run(None, now), now);
+        assert_eq!(
+            hourly.next_run(Some(local("2026-10-16 10:00")), now),
+            local("2026-10-16 11:00")
+        );
+
+        let daily = Schedule::Daily(NaiveTime::from_hms_opt(8, 0, 0).unwrap());
+        assert_eq!(daily.next_run(None, now), local("2026-10-17 08:00"));
+        assert_eq!(
+            daily.next_run(None, local("2026-10-16 07:00")),
+            local("2026-10-16 08:00")
+        );
+        // Yesterday's run done: due today at 8:00, which has already passed.
+        assert_eq!(
+            daily.next_run(Some(local("2026-10-15 08:00")), now),
+            local("2026-10-16 08:00")
+        );
+        assert_eq!(
+            daily.next_run(Some(local("2026-10-16 08:00")), now),
+            local("2026-10-17 08:00")
+        );
+    }
+
+    #[test]
+    fn test_cron() {
+        // 2026-10-16 is a Friday.
+        let now = local("2026-10-16 10:30");
+        let next = |expr: &str, last: Option<&str>| {
+            let cron = Schedule::Cron(Cron::parse(expr).unwrap());
+            cron.next_run(last.map(local), now)
+        };
+        assert_eq!(next("*/15 * * * *", None), local("2026-10-16 10:30"));
+        assert_eq!(
+            next("*/15 * * * *", Some("2026-10-16 10:30")),
+            local("2026-10-16 10:45")
+        );
+        assert_eq!(next("0 8 * * mon-fri", None), local("2026-10-19 08:00"));
+        assert_eq!(next("0 9-17/4 * * *", None), local("2026-10-16 13:00"));
+        assert_eq!(next("@monthly", None), local("2026-11-01 00:00"));
+        // Sunday as 7, and either day field matching.
+        assert_eq!(next("0 0 * * 7", None), local("2026-10-18 00:00"));
+        assert_eq!(next("0 0 20 * sun", None), local("2026-10-18 00:00"));
+        // A run missed since the last one is due at once.
+        assert_eq!(
+            next("0 8 * * *", Some("2026-10-15 08:00")),
+            local("2026-10-16 08:00")
+        );
+        assert!(Cron::parse("0 0 29 feb *").is_ok());
+
+        for bad in [
+            "",
+            "* * * *",
+            "60 * * * *",
+            "* * 0 * *",
+            "*/0 * * * *",
+            "5-1 * * * *",
+            "0 0 * foo *",
+            "0 0 30 feb *",
+        ] {
+            assert!(Cron::parse(bad).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn test_scheduler_records_runs() {
+        let path =
+            std::env::temp_dir().join(format!("ncmdump-scheduler-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let hourly = Schedule::Every(Duration::from_secs(3600));
+        let mut runs = 0;
+        {
+            let mut scheduler = Scheduler::new(&path).unwrap();
+            scheduler.register("ok", hourly, || {
+                runs += 1;
+                Ok(())
+            });
+            scheduler.register("broken", hourly, || anyhow::bail!("boom"));
+            let mut events = Vec::new();
+            let failed = scheduler.run_due(false, |e| {
+                events.push(match e {
+                    Event::Started(name) => format!("start {name}"),
+                    Event::Finished(name, r) => format!("{name}: {:?}", r.error),
+                });
+            });
+            assert_eq!(failed, 1);
+            assert_eq!(
+                events,
+                [
+                    "start ok",
+                    "ok: None",
+                    "start broken",
+                    "broken: Some(\"boom\")"
+                ]
+            );
+            // Both ran just now: nothing is due for an hour.
+            assert_eq!(scheduler.run_due(false, |_| {}), 0);
+            assert!(scheduler.wait() > Duration::ZERO);
+        }
+        assert_eq!(runs, 1);
+
+        let state = State::load(&path).unwrap();
+        assert_eq!(state.jobs["broken"].error.as_deref(), Some("boom"));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,663 @@
+//! HTTP server of `ncmdump-cli serve`.
+//!
+//! Lets network players use a music directory without converting it first:
+//!
+//! | Route               | Response                                                   |
+//! |---------------------|------------------------------------------------------------|
+//! | `GET /`             | M3U playlist of every track, to open the server URL itself |
+//! | `GET /library.json` | The same tracks with their tags, as JSON                   |
+//! | `GET /files/<path>` | A file of the directory; `.ncm` files decrypted on the fly |
+//! | `GET /track/<id>`   | A Netease track, proxied from its CDN URL                  |
+//! | `GET /rest/<call>`  | Subsonic API, see [`subsonic`]                             |
+//!
+//! Files and tracks honour a single-range `Range` header, so players can seek.
+//! NCM files are decrypted with [`ncmdump::NcmFile::decrypt_at`], starting
+//! right at the requested offset.
+//!
+//! With `?format=<codec>` (and optionally `&bitrate=<kbps>`), files and tracks
+//! are transcoded on the fly by [`ncmdump::transcode`] instead. Transcoded
+//! responses have no length and ignore `Range`.
+
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use ncmdump::transcode::{Codec, Target};
+use ncmdump_db::Library;
+use netease_api::NeteaseClient;
+use netease_api::types::Quality;
+use percent_encoding::{AsciiSet, CONTROLS, percent_decode_str, utf8_percent_encode};
+use serde::Serialize;
+use tiny_http::{Header, Request, Response, ResponseBox, StatusCode};
+use walkdir::WalkDir;
+
+mod subsonic;
+
+/// Characters escaped in a path segment of a file URL.
+const SEGMENT: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'`')
+    .add(b'{')
+    .add(b'}');
+
+/// One track of the served directory.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Entry {
+    /// Path relative to the directory, `/`-separated.
+    pub path: String,
+    /// `"mp3"` or `"flac"` (what an NCM file decrypts to).
+    pub format: &'static str,
+    /// Whether the file is an NCM file.
+    pub ncm: bool,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub duration_ms: u64,
+    /// Netease track ID, from the NCM header.
+    pub netease_id: Option<u64>,
+    pub size: u64,
+}
+
+impl Entry {
+    /// URL path under which the server streams the file.
+    pub fn url(&self) -> String {
+        let segments: Vec<String> = self
+            .path
+            .split('/')
+            .map(|s| utf8_percent_encode(s, SEGMENT).to_string())
+            .collect();
+        format!("/files/{}", segments.join("/"))
+    }
+}
+
+/// Every NCM, MP3 and FLAC file under `root`, sorted by path. Files whose
+/// header or tags cannot be read are listed without tags.
+pub fn scan(root: &Path) -> Vec<Entry> {
+    let mut entries: Vec<Entry> = WalkDir::new(root)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| {
+            let rel = e.path().strip_prefix(root).ok()?;
+            let path = rel
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            let ext = e.path().extension()?.to_string_lossy().to_lowercase();
+            let size = e.metadata().map_or(0, |m| m.len());
+            let entry = Entry {
+                path,
+                format: "mp3",
+                ncm: false,
+                title: None,
+                artist: None,
+                album: None,
+                duration_ms: 0,
+                netease_id: None,
+                size,
+            };
+            match ext.as_str() {
+                "ncm" => Some(ncm_entry(e.path(), entry)),
+                "mp3" | "flac" => Some(audio_entry(
+                    e.path(),
+                    Entry {
+                        format: if ext == "mp3" { "mp3" } else { "flac" },
+                        ..entry
+                    },
+                )),
+                _ => None,
+            }
+        })
+        .collect();
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+    entries
+}
+
+fn ncm_entry(path: &Path, entry: Entry) -> Entry {
+    let Ok(ncm) = ncmdump::inspect(path) else {
+        return Entry { ncm: true, ..entry };
+    };
+    let meta = ncm.metadata.as_ref();
+    Entry {
+        format: ncm.format.extension(),
+        ncm: true,
+        title: meta.map(|m| m.music_name.clone()),
+        artist: meta.map(ncmdump::NcmMetadata::artist_names),
+        album: meta.map(|m| m.album.clone()),
+        duration_ms: meta.map_or(0, |m| m.duration),
+        netease_id: meta.map(|m| m.music_id).filter(|&id| id != 0),
+        ..entry
+    }
+}
+
+fn audio_entry(path: &Path, entry: Entry) -> Entry {
+    let Ok(tags) = ncmdump::tag_read(path) else {
+        return entry;
+    };
+    Entry {
+        title: tags.title,
+        artist: tags.artist,
+        album: tags.album,
+        duration_ms: tags.duration_ms,
+        ..entry
+    }
+}
+
+/// An M3U playlist of `entries`, with URLs on `base` (e.g.
+/// `http://host:port`).
+pub fn playlist(entries: &[Entry], base: &str) -> String {
+    let mut m3u = String::from("#EXTM3U\n");
+    for e in entries {
+        let name = match (&e.artist, &e.title) {
+            (Some(artist), Some(title)) => format!("{artist} - {title}"),
+            (None, Some(title)) => title.clone(),
+            _ => e.path.clone(),
+        };
+        let secs = e.duration_ms / 1000;
+        let _ = write!(m3u, "#EXTINF:{secs},{name}\n{base}{}\n", e.url());
+    }
+    m3u
+}
+
+/// Which part of a body of `len` bytes a `Range` header asks for.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Range {
+    /// No usable range: send everything.
+    Full,
+    /// Bytes `start..=end`.
+    Partial(u64, u64),
+    /// The range lies outside the body.
+    Unsatisfiable,
+}
+
+impl Range {
+    /// Resolve a `Range` header against a body of `len` bytes. Headers that
+    /// are malformed or ask for several ranges are ignored.
+    pub fn parse(header: Option<&str>, len: u64) -> Self {
+        let Some(spec) = header.and_then(|h| h.trim().strip_prefix("bytes=")) else {
+            return Self::Full;
+        };
+        let Some((start, end)) = spec.split_once('-').filter(|_| !spec.contains(',')) else {
+            return Self::Full;
+        };
+        let (start, end) = (start.trim(), end.trim());
+        if start.is_empty() {
+            // `bytes=-N`: the last N bytes.
+            return match end.parse::<u64>() {
+                Ok(0) => Self::Unsatisfiable,
+                Ok(_) if len == 0 => Self::Unsatisfiable,
+                Ok(n) => Self::Partial(len.saturating_sub(n), len - 1),
+                Err(_) => Self::Full,
+            };
+        }
+        let Ok(start) = start.parse::<u64>() else {
+            return Self::Full;
+        };
+        let end = if end.is_empty() {
+            len.saturating_sub(1)
+        } else {
+            match end.parse::<u64>() {
+                Ok(end) => end.min(len.saturating_sub(1)),
+                Err(_) => return Self::Full,
+            }
+        };
+        if start >= len || end < start {
+            Self::Unsatisfiable
+        } else {
+            Self::Partial(start, end)
+        }
+    }
+}
+
+/// A local file's bytes from some offset on, decrypted if it is an NCM.
+struct FileBody {
+    file: io::Take<File>,
+    ncm: Option<ncmdump::NcmFile>,
+    /// Offset into the audio of the next byte read.
+    pos: u64,
+}
+
+impl Read for FileBody {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.file.read(buf)?;
+        if let Some(ncm) = &self.ncm {
+            ncm.decrypt_at(self.pos, &mut buf[..n]);
+        }
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+/// A failed request: status and a plain-text reason.
+#[derive(Debug)]
+struct HttpError(u16, String);
+
+impl HttpError {
+    fn not_found() -> Self {
+        Self(404, "not found".into())
+    }
+}
+
+impl From<io::Error> for HttpError {
+    fn from(e: io::Error) -> Self {
+        match e.kind() {
+            io::ErrorKind::NotFound => Self::not_found(),
+            _ => Self(500, e.to_string()),
+        }
+    }
+}
+
+/// How long a scan of the directory is reused before it is taken again.
+const RESCAN_AFTER: Duration = Duration::from_secs(60);
+
+/// The account Subsonic clients must log in with.
+pub struct Credentials {
+    pub user: String,
+    pub password: String,
+}
+
+/// Serves one directory and proxies Netease tracks.
+pub struct Server {
+    root: PathBuf,
+    client: NeteaseClient,
+    quality: Quality,
+    /// Library database; tracks with a local copy are served from disk.
+    library: Option<Arc<Library>>,
+    /// Required Subsonic login; any login is accepted without it.
+    credentials: Option<Credentials>,
+    /// Whether ffmpeg is there to transcode with.
+    transcoding: bool,
+    /// The last scan of `root` and when it was taken.
+    entries: Mutex<Option<(Instant, Arc<Vec<Entry>>)>>,
+}
+
+impl Server {
+    pub fn new(root: PathBuf, client: NeteaseClient, quality: Quality) -> Self {
+        Self {
+            root,
+            client,
+            quality,
+            library: None,
+            credentials: None,
+            transcoding: false,
+            entries: Mutex::new(None),
+        }
+    }
+
+    /// Serve Netease tracks from their local copies in `library` when they
+    /// are at least of the requested quality.
+    #[must_use]
+    pub fn library(mut self, library: Arc<Library>) -> Self {
+        self.library = Some(library);
+        self
+    }
+
+    /// Require Subsonic clients to log in as `credentials`.
+    #[must_use]
+    pub fn credentials(mut self, credentials: Credentials) -> Self {
+        self.credentials = Some(credentials);
+        self
+    }
+
+    /// Transcode on request; ffmpeg must be available.
+    #[must_use]
+    pub fn transcoding(mut self) -> Self {
+        self.transcoding = true;
+        self
+    }
+
+    /// Answer requests on `http`, each on its own thread, until the
+    /// listener fails.
+    pub fn run(self, http: &tiny_http::Server) {
+        let this = Arc::new(self);
+        for request in http.incoming_requests() {
+            let this = Arc::clone(&this);
+            std::thread::spawn(move || this.handle(request));
+        }
+    }
+
+    fn handle(&self, request: Request) {
+        let url = request.url().to_owned();
+        let (path, query) = url.split_once('?').unwrap_or((&url, ""));
+        let range = header(&request, "Range");
+        tracing::debug!(method = %request.method(), url, range, "request");
+        let response = match request.method() {
+            tiny_http::Method::Get | tiny_http::Method::Head => {
+                self.route(&request, path, query, range)
+            }
+            _ => Err(HttpError(405, "method not allowed".into())),
+        };
+        let response = response.unwrap_or_else(|HttpError(status, reason)| {
+            if status >= 500 {
+                tracing::warn!(url, status, reason, "request failed");
+            }
+            Response::from_string(reason)
+                .with_status_code(status)
+                .boxed()
+        });
+        if let Err(e) = request.respond(response) {
+            // Players routinely drop connections while seeking.
+            tracing::debug!(url, error = %e, "response aborted");
+        }
+    }
+
+    fn route(
+        &self,
+        request: &Request,
+        path: &str,
+        query: &str,
+        range: Option<&str>,
+    ) -> Result<ResponseBox, HttpError> {
+        if path == "/" {
+            let host = header(request, "Host").unwrap_or("localhost");
+            let m3u = playlist(&self.entries(), &format!("http://{host}"));
+            return Ok(text(m3u, "audio/x-mpegurl; charset=utf-8"));
+        }
+        if path == "/library.json" {
+            let json = serde_json::to_string(&*self.entries())
+                .map_err(|e| HttpError(500, e.to_string()))?;
+            return Ok(text(json, "application/json"));
+        }
+        if let Some(rel) = path.strip_prefix("/files/") {
+            let target = self.target(query)?;
+            return self.file(&percent_decode_str(rel).decode_utf8_lossy(), range, target);
+        }
+        if let Some(id) = path.strip_prefix("/track/") {
+            let id = id.parse().map_err(|_| HttpError::not_found())?;
+            let quality = match query_param(query, "quality") {
+                Some(q) => serde_json::from_value(serde_json::Value::String(q.to_owned()))
+                    .map_err(|_| HttpError(400, format!("unknown quality: {q}")))?,
+                None => self.quality,
+            };
+            let target = self.target(query)?;
+            return self.track(id, quality, range, target);
+        }
+        if let Some(method) = path.strip_prefix("/rest/") {
+            return Ok(subsonic::handle(self, method, query, range));
+        }
+        Err(HttpError::not_found())
+    }
+
+    /// The transcoding asked for by the `format` and `bitrate` parameters.
+    fn target(&self, query: &str) -> Result<Option<Target>, HttpError> {
+        let Some(format) = query_param(query, "format").filter(|&f| f != "raw") else {
+            return Ok(None);
+        };
+        let codec = Codec::from_name(format)
+            .ok_or_else(|| HttpError(400, format!("unknown format: {format}")))?;
+        let bitrate = match query_param(query, "bitrate") {
+            Some(b) => Some(
+                b.parse()
+                    .map_err(|_| HttpError(400, format!("invalid bitrate: {b}")))?,
+            ),
+            None => None,
+        };
+        if !self.transcoding {
+            return Err(HttpError(
+                501,
+                "transcoding unavailable: ffmpeg not found".into(),
+            ));
+        }
+        Ok(Some(Target { codec, bitrate }))
+    }
+
+    /// The tracks under the root, rescanned at most every [`RESCAN_AFTER`].
+    fn entries(&self) -> Arc<Vec<Entry>> {
+        let mut cached = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        match &*cached {
+            Some((at, entries)) if at.elapsed() < RESCAN_AFTER => Arc::clone(entries),
+            _ => {
+                let entries = Arc::new(scan(&self.root));
+                *cached = Some((Instant::now(), Arc::clone(&entries)));
+                entries
+            }
+        }
+    }
+
+    /// The file at `rel` (a `/`-separated path) under the root.
+    fn file(
+        &self,
+        rel: &str,
+        range: Option<&str>,
+        target: Option<Target>,
+    ) -> Result<ResponseBox, HttpError> {
+        let rel = Path::new(rel);
+        // Only plain names: no `..`, root or drive prefixes.
+        if !rel.components().all(|c| matches!(c, Component::Normal(_))) {
+            return Err(HttpError::not_found());
+        }
+        open_file(&self.root.join(rel), range, target)
+    }
+
+    /// Netease track `id` at `quality`: its local copy if the library has
+    /// one that good, else proxied from the CDN.
+    fn track(
+        &self,
+        id: u64,
+        quality: Quality,
+        range: Option<&str>,
+        target: Option<Target>,
+    ) -> Result<ResponseBox, HttpError> {
+        let local = self.library.as_ref().and_then(|library| {
+            let tracks = library.by_netease_id(id).ok()?;
+            tracks
+                .into_iter()
+                .filter(|t| t.bitrate.is_some_and(|b| b >= quality.bitrate()))
+                .map(|t| t.path)
+                .find(|p| p.is_file())
+        });
+        if let Some(path) = local {
+            tracing::debug!(track = id, path = %path.display(), "serving local copy");
+            return open_file(&path, range, target);
+        }
+        let url = self
+            .client
+            .track_url(id, quality)
+            .map_err(|e| HttpError(502, e.to_string()))?;
+        let stream = self
+            .client
+            .open_stream(&url, range.filter(|_| target.is_none()))
+            .map_err(|e| HttpError(502, e.to_string()))?;
+        if let Some(target) = target {
+            return transcoded(stream, target);
+        }
+        let content_type = stream
+            .content_type
+            .clone()
+            .unwrap_or_else(|| "application/octet-stream".into());
+        let mut headers = vec![
+            header_of("Content-Type", &content_type),
+            header_of("Accept-Ranges", "bytes"),
+        ];
+        headers.extend(
+            stream
+                .content_range
+                .as_deref()
+                .map(|r| header_of("Content-Range", r)),
+        );
+        let len = stream.content_length.and_then(|l| usize::try_from(l).ok());
+        Ok(Response::new(
+            StatusCode(stream.status),
+            headers,
+            Box::new(stream) as Box<dyn Read + Send>,
+            len,
+            None,
+        )
+        .with_chunked_threshold(usize::MAX))
+    }
+}
+
+/// The audio file at `path`, decrypted if it is an NCM, honouring `range`
+/// unless it is transcoded to `target`.
+fn open_file(
+    path: &Path,
+    range: Option<&str>,
+    target: Option<Target>,
+) -> Result<ResponseBox, HttpError> {
+    let is_ncm = path
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("ncm"));
+    let mut file = File::open(path)?;
+    if !file.metadata()?.is_file() {
+        return Err(HttpError::not_found());
+    }
+    let (ncm, content_type) = if is_ncm {
+        let ncm = ncmdump::NcmFile::parse(&mut file).map_err(|e| HttpError(500, e.to_string()))?;
+        let content_type = mime(ncm.format.extension());
+        (Some(ncm), content_type)
+    } else {
+        let ext = path.extension().unwrap_or_default().to_string_lossy();
+        (None, mime(&ext.to_lowercase()))
+    };
+    let audio_offset = ncm.as_ref().map_or(0, |n| n.audio_offset);
+    let len = file.metadata()?.len().saturating_sub(audio_offset);
+    if let Some(target) = target {
+        file.seek(SeekFrom::Start(audio_offset))?;
+        let body = FileBody {
+            file: file.take(len),
+            ncm,
+            pos: 0,
+        };
+        return transcoded(body, target);
+    }
+
+    let (status, start, count, content_range) = match Range::parse(range, len) {
+        Range::Full => (200, 0, len, None),
+        Range::Partial(start, end) => (
+            206,
+            start,
+            end - start + 1,
+            Some(format!("bytes {start}-{end}/{len}")),
+        ),
+        Range::Unsatisfiable => {
+            return Ok(Response::empty(416)
+                .with_header(header_of("Content-Range", &format!("bytes */{len}")))
+                .boxed());
+        }
+    };
+    file.seek(SeekFrom::Start(audio_offset + start))?;
+    let body = FileBody {
+        file: file.take(count),
+        ncm,
+        pos: start,
+    };
+    let mut headers = vec![
+        header_of("Content-Type", content_type),
+        header_of("Accept-Ranges", "bytes"),
+    ];
+    headers.extend(content_range.map(|r| header_of("Content-Range", &r)));
+    Ok(Response::new(
+        StatusCode(status),
+        headers,
+        Box::new(body) as Box<dyn Read + Send>,
+        usize::try_from(count).ok(),
+        None,
+    )
+    .with_chunked_threshold(usize::MAX))
+}
+
+/// `body` transcoded to `target` as it is read, sent chunked.
+fn transcoded<R: Read + Send + 'static>(body: R, target: Target) -> Result<ResponseBox, HttpError> {
+    let stream = ncmdump::transcode::transcode_stream(body, target)
+        .map_err(|e| HttpError(500, e.to_string()))?;
+    Ok(Response::new(
+        StatusCode(200),
+        vec![header_of("Content-Type", target.codec.stream_mime())],
+        Box::new(stream) as Box<dyn Read + Send>,
+        None,
+        None,
+    ))
+}
+
+/// The raw value of query parameter `name`.
+fn query_param<'a>(query: &'a str, name: &str) -> Option<&'a str> {
+    query
+        .split('&')
+        .find_map(|p| p.strip_prefix(name).and_then(|rest| rest.strip_prefix('=')))
+}
+
+fn header<'a>(request: &'a Request, name: &str) -> Option<&'a str> {
+    request
+        .headers()
+        .iter()
+        .find(|h| h.field.as_str().as_str().eq_ignore_ascii_case(name))
+        .map(|h| h.value.as_str())
+}
+
+fn header_of(name: &str, value: &str) -> Header {
+    // Names are constants and values ASCII, so this cannot fail.
+    Header::from_bytes(name, value).unwrap_or_else(|()| unreachable!("invalid header {name}"))
+}
+
+fn text(body: String, content_type: &str) -> ResponseBox {
+    Response::from_string(body)
+        .with_header(header_of("Content-Type", content_type))
+        .boxed()
+}
+
+fn mime(ext: &str) -> &'static str {
+    match ext {
+        "mp3" => "audio/mpeg",
+        "flac" => "audio/flac",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_range_parse() {
+        assert_eq!(Range::parse(None, 100), Range::Full);
+        assert_eq!(Range::parse(Some("bytes=0-"), 100), Range::Partial(0, 99));
+        assert_eq!(
+            Range::parse(Some("bytes=10-19"), 100),
+            Range::Partial(10, 19)
+        );
+        assert_eq!(
+            Range::parse(Some("bytes=90-200"), 100),
+            Range::Partial(90, 99)
+        );
+        assert_eq!(Range::parse(Some("bytes=-10"), 100), Range::Partial(90, 99));
+        assert_eq!(Range::parse(Some("bytes=-500"), 100), Range::Partial(0, 99));
+        assert_eq!(Range::parse(Some("bytes=100-"), 100), Range::Unsatisfiable);
+        assert_eq!(Range::parse(Some("bytes=20-10"), 100), Range::Unsatisfiable);
+        assert_eq!(Range::parse(Some("bytes=-0"), 100), Range::Unsatisfiable);
+        // Multiple or malformed ranges fall back to the whole body.
+        assert_eq!(Range::parse(Some("bytes=0-1,5-6"), 100), Range::Full);
+        assert_eq!(Range::parse(Some("items=0-1"), 100), Range::Full);
+        assert_eq!(Range::parse(Some("bytes=a-"), 100), Range::Full);
+    }
+
+    #[test]
+    fn test_playlist() {
+        let entry = Entry {
+            path: "华语/晴天 #1.ncm".into(),
+            format: "flac",
+            ncm: true,
+            title: Some("晴天".into()),
+            artist: Some("周杰伦".into()),
+            album: None,
+            duration_ms: 269_000,
+            netease_id: Some(186_016),
+            size: 0,
+        };
+        assert_eq!(
+            playlist(&[entry], "http://h:1"),
+            "#EXTM3U\n#EXTINF:269,周杰伦 - 晴天\n\
+             http://h:1/files/%E5%8D%8E%E8%AF%AD/%E6%99%B4%E5%A4%A9%20%231.ncm\n"
+        );
+    }
+}
@@ -0,0 +1,978 @@
+//! Subsonic API (with the `OpenSubsonic` flag) under `/rest/`, for mobile
+//! clients such as `DSub` or Symfonium.
+//!
+//! The served directory is the only music folder. It can be browsed by
+//! folder (`getIndexes`, `getMusicDirectory`) or by tags (`getArtists`,
+//! `getAlbum`, …), where tracks are grouped by their artist and album tags.
+//! The logged-in user's Netease playlists appear as playlists, and their
+//! tracks stream through [`Server::track`].
+//!
+//! IDs carry their kind as a prefix:
+//!
+//! | ID            | Refers to                                      |
+//! |---------------|------------------------------------------------|
+//! | `d:<path>`    | Directory under the root (`d:` is the root)    |
+//! | `f:<path>`    | File under the root                            |
+//! | `ar:<hash>`   | Local artist                                   |
+//! | `al:<hash>`   | Local album                                    |
+//! | `n:<id>`      | Netease track                                  |
+//! | `na:<id>`     | Netease album                                  |
+//! | `p:<id>`      | Netease playlist                               |
+//!
+//! Responses are XML unless the client asks for `f=json`. When the server
+//! can transcode, `stream` honours `format` and `maxBitRate`, converting
+//! sources above the limit to MP3 unless a format is named. Ratings,
+//! scrobbling and play queues are not supported; `scrobble` is accepted and
+//! ignored.
+
+use std::collections::BTreeMap;
+
+use md5::{Digest, Md5};
+use ncmdump::transcode::{Codec, Target};
+use percent_encoding::percent_decode_str;
+use serde_json::{Map, Value, json};
+use tiny_http::ResponseBox;
+
+use super::{Entry, HttpError, Server, header_of, mime, text};
+
+/// Subsonic API version implemented.
+const API_VERSION: &str = "1.16.1";
+
+/// Artist and album name of tracks without those tags.
+const UNKNOWN_ARTIST: &str = "Unknown Artist";
+const UNKNOWN_ALBUM: &str = "Unknown Album";
+
+/// A failed call: Subsonic error code and message.
+struct ApiError(u16, String);
+
+impl ApiError {
+    fn missing(name: &str) -> Self {
+        Self(10, format!("required parameter is missing: {name}"))
+    }
+
+    fn not_found() -> Self {
+        Self(70, "the requested data was not found".into())
+    }
+
+    fn netease(e: &netease_api::NeteaseError) -> Self {
+        Self(0, e.to_string())
+    }
+}
+
+/// What a call answers with: a payload to wrap in `subsonic-response`, or
+/// raw bytes (`stream`, `getCoverArt`).
+enum Reply {
+    Data(Map<String, Value>),
+    Raw(ResponseBox),
+}
+
+fn data(key: &str, value: Value) -> Reply {
+    let mut map = Map::new();
+    map.insert(key.to_owned(), value);
+    Reply::Data(map)
+}
+
+/// Query parameters; keys may repeat.
+struct Params(Vec<(String, String)>);
+
+impl Params {
+    fn parse(query: &str) -> Self {
+        let decode = |s: &str| {
+            percent_decode_str(&s.replace('+', " "))
+                .decode_utf8_lossy()
+                .into_owned()
+        };
+        Self(
+            query
+                .split('&')
+                .filter(|p| !p.is_empty())
+                .map(|p| {
+                    let (k, v) = p.split_once('=').unwrap_or((p, ""));
+                    (decode(k), decode(v))
+                })
+                .collect(),
+        )
+    }
+
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    fn require(&self, key: &str) -> Result<&str, ApiError> {
+        self.get(key).ok_or_else(|| ApiError::missing(key))
+    }
+
+    fn number(&self, key: &str, default: usize) -> usize {
+        self.get(key)
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(default)
+    }
+}
+
+/// Answer Subsonic call `method` (e.g. `getAlbum.view`).
+pub(super) fn handle(
+    server: &Server,
+    method: &str,
+    query: &str,
+    range: Option<&str>,
+) -> ResponseBox {
+    let params = Params::parse(query);
+    let json = params.get("f") == Some("json");
+    let method = method.strip_suffix(".view").unwrap_or(method);
+    let reply = authenticate(server, &params).and_then(|()| call(server, method, &params, range));
+    match reply {
+        Ok(Reply::Raw(response)) => response,
+        Ok(Reply::Data(payload)) => envelope("ok", payload, json),
+        Err(ApiError(code, message)) => {
+            tracing::debug!(method, code, message, "Subsonic call failed");
+            let mut payload = Map::new();
+            payload.insert("error".into(), json!({ "code": code, "message": message }));
+            envelope("failed", payload, json)
+        }
+    }
+}
+
+/// Check the `u` and `p` (plain or `enc:` hex) or `t` and `s` (salted MD5)
+/// parameters against the server's credentials, if it has any.
+fn authenticate(server: &Server, params: &Params) -> Result<(), ApiError> {
+    let Some(credentials) = &server.credentials else {
+        return Ok(());
+    };
+    let user = params.require("u")?;
+    let valid = if let Some(token) = params.get("t") {
+        let salt = params.require("s")?;
+        let expected = Md5::digest(format!("{}{salt}", credentials.password));
+        token.eq_ignore_ascii_case(&format!("{expected:x}"))
+    } else {
+        let password = params.require("p")?;
+        match password.strip_prefix("enc:") {
+            Some(hex) => decode_hex(hex).is_some_and(|p| p == credentials.password.as_bytes()),
+            None => password == credentials.password,
+        }
+    };
+    if user == credentials.user && valid {
+        Ok(())
+    } else {
+        Err(ApiError(40, "wrong username or password".into()))
+    }
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+fn call(
+    server: &Server,
+    method: &str,
+    params: &Params,
+    range: Option<&str>,
+) -> Result<Reply, ApiError> {
+    match method {
+        "ping" | "scrobble" => Ok(Reply::Data(Map::new())),
+        "getLicense" => Ok(data("license", json!({ "valid": true }))),
+        "getOpenSubsonicExtensions" => Ok(data("openSubsonicExtensions", json!([]))),
+        "getUser" => Ok(data(
+            "user",
+            user(params.get("username").or(params.get("u"))),
+        )),
+        "getMusicFolders" => Ok(data(
+            "musicFolders",
+            json!({ "musicFolder": [{ "id": 1, "name": folder_name(server) }] }),
+        )),
+        "getIndexes" => Ok(get_indexes(server)),
+        "getMusicDirectory" => get_music_directory(server, params.require("id")?),
+        "getArtists" => Ok(get_artists(server)),
+        "getArtist" => get_artist(server, params.require("id")?),
+        "getAlbum" => get_album(server, params.require("id")?),
+        "getAlbumList2" => get_album_list(server, params),
+        "getSong" => get_song(server, params.require("id")?),
+        "search3" => search(server, params),
+        "getPlaylists" => get_playlists(server),
+        "getPlaylist" => get_playlist(server, params.require("id")?),
+        "stream" => stream(server, params.require("id")?, range, params),
+        "download" => stream(server, params.require("id")?, range, &Params(Vec::new())),
+        "getCoverArt" => cover_art(server, params.require("id")?),
+        _ => Err(ApiError(0, format!("unsupported method: {method}"))),
+    }
+}
+
+// ── responses ──
+
+fn envelope(status: &str, payload: Map<String, Value>, json: bool) -> ResponseBox {
+    let mut body = Map::new();
+    body.insert("status".into(), status.into());
+    body.insert("version".into(), API_VERSION.into());
+    body.insert("type".into(), "ncmdump".into());
+    body.insert("serverVersion".into(), env!("CARGO_PKG_VERSION").into());
+    body.insert("openSubsonic".into(), true.into());
+    body.extend(payload);
+    if json {
+        let json = json!({ "subsonic-response": body }).to_string();
+        text(json, "application/json")
+    } else {
+        let mut xml = String::from(r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+        body.insert("xmlns".into(), "http://subsonic.org/restapi".into());
+        write_xml(&mut xml, "subsonic-response", &Value::Object(body));
+        text(xml, "text/xml; charset=utf-8")
+    }
+}
+
+/// Write `value` as Subsonic XML: scalar fields become attributes, objects
+/// child elements, and arrays repeated child elements.
+fn write_xml(out: &mut String, name: &str, value: &Value) {
+    match value {
+        Value::Null => {}
+        Value::Array(items) => {
+            for item in items {
+                write_xml(out, name, item);
+            }
+        }
+        Value::Object(map) => {
+            out.push('<');
+            out.push_str(name);
+            let mut children = Vec::new();
+            for (key, value) in map {
+                match value {
+                    Value::Null => {}
+                    Value::Object(_) | Value::Array(_) => children.push((key, value)),
+                    _ => {
+                        out.push(' ');
+                        out.push_str(key);
+                        out.push_str("=\"");
+                        out.push_str(&escape_xml(&scalar(value)));
+                        out.push('"');
+                    }
+                }
+            }
+            if children.is_empty() {
+                out.push_str("/>");
+                return;
+            }
+            out.push('>');
+            for (key, value) in children {
+                write_xml(out, key, value);
+            }
+            out.push_str("</");
+            out.push_str(name);
+            out.push('>');
+        }
+        _ => {
+            out.push('<');
+            out.push_str(name);
+            out.push('>');
+            out.push_str(&escape_xml(&scalar(value)));
+            out.push_str("</");
+            out.push_str(name);
+            out.push('>');
+        }
+    }
+}
+
+fn scalar(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn escape_xml(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+// ── local library ──
+
+fn folder_name(server: &Server) -> String {
+    server.root.file_name().map_or_else(
+        || server.root.display().to_string(),
+        |n| n.to_string_lossy().into_owned(),
+    )
+}
+
+fn artist_name(e: &Entry) -> &str {
+    e.artist
+        .as_deref()
+        .filter(|a| !a.is_empty())
+        .unwrap_or(UNKNOWN_ARTIST)
+}
+
+fn album_name(e: &Entry) -> &str {
+    e.album
+        .as_deref()
+        .filter(|a| !a.is_empty())
+        .unwrap_or(UNKNOWN_ALBUM)
+}
+
+/// A stable ID from names, e.g. an artist and album.
+fn hash_id(prefix: &str, parts: &[&str]) -> String {
+    let digest = Md5::digest(parts.join("\0"));
+    format!("{prefix}{digest:x}")[..prefix.len() + 16].to_owned()
+}
+
+fn artist_id(e: &Entry) -> String {
+    hash_id("ar:", &[artist_name(e)])
+}
+
+fn album_id(e: &Entry) -> String {
+    hash_id("al:", &[artist_name(e), album_name(e)])
+}
+
+/// Directory part of a `/`-separated path (`""` at the root).
+fn parent_dir(path: &str) -> &str {
+    path.rsplit_once('/').map_or("", |(dir, _)| dir)
+}
+
+fn file_name(path: &str) -> &str {
+    path.rsplit_once('/').map_or(path, |(_, name)| name)
+}
+
+fn song(e: &Entry) -> Value {
+    let title = e.title.clone().unwrap_or_else(|| {
+        let name = file_name(&e.path);
+        name.rsplit_once('.')
+            .map_or(name, |(stem, _)| stem)
+            .to_owned()
+    });
+    let id = format!("f:{}", e.path);
+    json!({
+        "id": id,
+        "parent": format!("d:{}", parent_dir(&e.path)),
+        "isDir": false,
+        "title": title,
+        "album": album_name(e),
+        "artist": artist_name(e),
+        "albumId": album_id(e),
+        "artistId": artist_id(e),
+        "coverArt": id,
+        "duration": e.duration_ms / 1000,
+        "size": e.size,
+        "suffix": e.format,
+        "contentType": mime(e.format),
+        "path": e.path,
+        "type": "music",
+        "mediaType": "song",
+    })
+}
+
+/// Local albums: `(artist, album)` → tracks, in path order.
+fn albums(entries: &[Entry]) -> BTreeMap<(&str, &str), Vec<&Entry>> {
+    let mut albums: BTreeMap<_, Vec<_>> = BTreeMap::new();
+    for e in entries {
+        albums
+            .entry((artist_name(e), album_name(e)))
+            .or_default()
+            .push(e);
+    }
+    albums
+}
+
+fn album(tracks: &[&Entry]) -> Value {
+    let first = tracks[0];
+    json!({
+        "id": album_id(first),
+        "name": album_name(first),
+        "title": album_name(first),
+        "artist": artist_name(first),
+        "artistId": artist_id(first),
+        "coverArt": format!("f:{}", first.path),
+        "songCount": tracks.len(),
+        "duration": tracks.iter().map(|e| e.duration_ms).sum::<u64>() / 1000,
+        "isDir": true,
+    })
+}
+
+/// `(name, artist)` pairs grouped under the upper-cased first letter of the
+/// name, `#` for names not starting with a Latin letter.
+fn index(items: Vec<(String, Value)>) -> Vec<Value> {
+    let mut groups: BTreeMap<String, Vec<Value>> = BTreeMap::new();
+    for (name, item) in items {
+        let letter = name
+            .chars()
+            .next()
+            .filter(char::is_ascii_alphabetic)
+            .map_or_else(|| "#".to_owned(), |c| c.to_ascii_uppercase().to_string());
+        groups.entry(letter).or_default().push(item);
+    }
+    groups
+        .into_iter()
+        .map(|(name, items)| json!({ "name": name, "artist": items }))
+        .collect()
+}
+
+fn get_indexes(server: &Server) -> Reply {
+    let entries = server.entries();
+    let (dirs, files) = directory(&entries, "");
+    let dirs = dirs
+        .into_iter()
+        .map(|d| (d.to_owned(), json!({ "id": format!("d:{d}"), "name": d })))
+        .collect();
+    data(
+        "indexes",
+        json!({
+            "lastModified": 0,
+            "ignoredArticles": "",
+            "index": index(dirs),
+            "child": files.into_iter().map(song).collect::<Vec<_>>(),
+        }),
+    )
+}
+
+/// Subdirectory names and files directly in directory `dir`.
+fn directory<'a>(entries: &'a [Entry], dir: &str) -> (Vec<&'a str>, Vec<&'a Entry>) {
+    let mut dirs = Vec::new();
+    let mut files = Vec::new();
+    for e in entries {
+        let rest = if dir.is_empty() {
+            e.path.as_str()
+        } else {
+            match e.path.strip_prefix(dir).and_then(|r| r.strip_prefix('/')) {
+                Some(rest) => rest,
+                None => continue,
+            }
+        };
+        match rest.split_once('/') {
+            Some((sub, _)) => {
+                if dirs.last() != Some(&sub) {
+                    dirs.push(sub);
+                }
+            }
+            None => files.push(e),
+        }
+    }
+    dirs.dedup();
+    (dirs, files)
+}
+
+fn get_music_directory(server: &Server, id: &str) -> Result<Reply, ApiError> {
+    let dir = id.strip_prefix("d:").ok_or_else(ApiError::not_found)?;
+    let entries = server.entries();
+    let (dirs, files) = directory(&entries, dir);
+    if !dir.is_empty() && dirs.is_empty() && files.is_empty() {
+        return Err(ApiError::not_found());
+    }
+    let path = |sub: &str| {
+        if dir.is_empty() {
+            sub.to_owned()
+        } else {
+            format!("{dir}/{sub}")
+        }
+    };
+    let mut children: Vec<Value> = dirs
+        .into_iter()
+        .map(|sub| {
+            json!({
+                "id": format!("d:{}", path(sub)),
+                "parent": id,
+                "isDir": true,
+                "title": sub,
+            })
+        })
+        .collect();
+    children.extend(files.into_iter().map(song));
+    let mut directory = json!({
+        "id": id,
+        "name": if dir.is_empty() { folder_name(server) } else { file_name(dir).to_owned() },
+        "child": children,
+    });
+    if !dir.is_empty() {
+        directory["parent"] = format!("d:{}", parent_dir(dir)).into();
+    }
+    Ok(data("directory", directory))
+}
+
+fn get_artists(server: &Server) -> Reply {
+    let entries = server.entries();
+    let mut artists: BTreeMap<&str, usize> = BTreeMap::new();
+    for (artist, _) in albums(&entries).keys() {
+        *artists.entry(artist).or_default() += 1;
+    }
+    let artists = artists
+        .into_iter()
+        .map(|(name, count)| {
+            let artist = json!({
+                "id": hash_id("ar:", &[name]),
+                "name": name,
+                "albumCount": count,
+            });
+            (name.to_owned(), artist)
+        })
+        .collect();
+    data(
+        "artists",
+        json!({ "ignoredArticles": "", "index": index(artists) }),
+    )
+}
+
+fn get_artist(server: &Server, id: &str) -> Result<Reply, ApiError> {
+    let entries = server.entries();
+    let albums: Vec<Value> = albums(&entries)
+        .into_iter()
+        .filter(|((artist, _), _)| hash_id("ar:", &[artist]) == id)
+        .map(|(_, tracks)| album(&tracks))
+        .collect();
+    let Some(name) = albums.first().map(|a| a["artist"].clone()) else {
+        return Err(ApiError::not_found());
+    };
+    Ok(data(
+        "artist",
+        json!({
+            "id": id,
+            "name": name,
+            "albumCount": albums.len(),
+            "album": albums,
+        }),
+    ))
+}
+
+fn get_album(server: &Server, id: &str) -> Result<Reply, ApiError> {
+    if let Some(album_id) = id.strip_prefix("na:") {
+        return netease_album(server, album_id);
+    }
+    let entries = server.entries();
+    let tracks = albums(&entries)
+        .into_values()
+        .find(|tracks| album_id(tracks[0]) == id)
+        .ok_or_else(ApiError::not_found)?;
+    let mut value = album(&tracks);
+    value["song"] = tracks.into_iter().map(song).collect();
+    Ok(data("album", value))
+}
+
+/// `getAlbumList2`. Every list type is served in alphabetical order of
+/// album (or, for `alphabeticalByArtist`, artist) name; there is no play
+/// history to sort by.
+fn get_album_list(server: &Server, params: &Params) -> Result<Reply, ApiError> {
+    params.require("type")?;
+    let size = params.number("size", 10).min(500);
+    let offset = params.number("offset", 0);
+    let entries = server.entries();
+    let mut albums: Vec<Vec<&Entry>> = albums(&entries).into_values().collect();
+    if params.get("type") != Some("alphabeticalByArtist") {
+        albums.sort_by_key(|tracks| album_name(tracks[0]).to_lowercase());
+    }
+    let albums: Vec<Value> = albums
+        .iter()
+        .skip(offset)
+        .take(size)
+        .map(|tracks| album(tracks))
+        .collect();
+    Ok(data("albumList2", json!({ "album": albums })))
+}
+
+fn get_song(server: &Server, id: &str) -> Result<Reply, ApiError> {
+    if let Some(track_id) = id.strip_prefix("n:") {
+        let id = track_id.parse().map_err(|_| ApiError::not_found())?;
+        let track = server
+            .client
+            .track_detail(id)
+            .map_err(|e| ApiError::netease(&e))?;
+        return Ok(data("song", netease_song(&track)));
+    }
+    let path = id.strip_prefix("f:").ok_or_else(ApiError::not_found)?;
+    let entries = server.entries();
+    let entry = entries
+        .iter()
+        .find(|e| e.path == path)
+        .ok_or_else(ApiError::not_found)?;
+    Ok(data("song", song(entry)))
+}
+
+/// `search3`: local artists, albums and tracks whose names contain the
+/// query, then (for a non-empty query) Netease tracks. An empty query lists
+/// the whole local library, which clients use to sync.
+fn search(server: &Server, params: &Params) -> Result<Reply, ApiError> {
+    let query = params.require("query")?.trim_matches('"').to_lowercase();
+    let matches = |s: &str| query.is_empty() || s.to_lowercase().contains(&query);
+    let page = |kind: &str, items: Vec<Value>| -> Vec<Value> {
+        let count = params.number(&format!("{kind}Count"), 20);
+        let offset = params.number(&format!("{kind}Offset"), 0);
+        items.into_iter().skip(offset).take(count).collect()
+    };
+
+    let entries = server.entries();
+    let albums = albums(&entries);
+    let mut artists: Vec<(&str, usize)> = Vec::new();
+    for (artist, _) in albums.keys() {
+        match artists.last_mut() {
+            Some((name, count)) if name == artist => *count += 1,
+            _ => artists.push((artist, 1)),
+        }
+    }
+    let artists = artists
+        .into_iter()
+        .filter(|(name, _)| matches(name))
+        .map(|(name, count)| json!({ "id": hash_id("ar:", &[name]), "name": name, "albumCount": count }))
+        .collect();
+    let albums = albums
+        .iter()
+        .filter(|((_, name), _)| matches(name))
+        .map(|(_, tracks)| album(tracks))
+        .collect();
+    let mut songs: Vec<Value> = entries
+        .iter()
+        .filter(|e| {
+            matches(e.title.as_deref().unwrap_or(&e.path))
+                || matches(artist_name(e))
+                || matches(album_name(e))
+        })
+        .map(song)
+        .collect();
+    let local_songs = songs.len();
+    let mut songs_page = page("song", std::mem::take(&mut songs));
+    let song_count = params.number("songCount", 20);
+    let song_offset = params.number("songOffset", 0);
+    if !query.is_empty() && songs_page.len() < song_count {
+        // Fill the page with Netease results, continuing where local ones end.
+        let offset = song_offset.saturating_sub(local_songs);
+        let limit = song_count - songs_page.len();
+        match server.client.search(
+            &query,
+            netease_api::types::SearchType::Track,
+            limit as u64,
+            offset as u64,
+        ) {
+            Ok(result) => songs_page.extend(result.tracks.iter().flatten().map(netease_song)),
+            Err(e) => tracing::debug!(error = %e, "Netease search failed"),
+        }
+    }
+    Ok(data(
+        "searchResult3",
+        json!({
+            "artist": page("artist", artists),
+            "album": page("album", albums),
+            "song": songs_page,
+        }),
+    ))
+}
+
+// ── Netease ──
+
+fn netease_song(t: &netease_api::types::Track) -> Value {
+    let artists: Vec<&str> = t.artists.iter().map(|a| a.name.as_str()).collect();
+    let id = format!("n:{}", t.id);
+    json!({
+        "id": id,
+        "isDir": false,
+        "title": t.name,
+        "album": t.album.name,
+        "artist": artists.join(" / "),
+        "albumId": format!("na:{}", t.album.id),
+        "coverArt": id,
+        "duration": t.duration_ms / 1000,
+        "suffix": "mp3",
+        "contentType": "audio/mpeg",
+        "type": "music",
+        "mediaType": "song",
+    })
+}
+
+fn netease_album(server: &Server, id: &str) -> Result<Reply, ApiError> {
+    let id = id.parse().map_err(|_| ApiError::not_found())?;
+    let detail = server
+        .client
+        .album_detail(id)
+        .map_err(|e| ApiError::netease(&e))?;
+    let songs: Vec<Value> = detail.tracks.iter().map(netease_song).collect();
+    Ok(data(
+        "album",
+        json!({
+            "id": format!("na:{id}"),
+            "name": detail.name,
+            "artist": detail.artists.first().map(|a| a.name.clone()),
+            "coverArt": detail.tracks.first().map(|t| format!("n:{}", t.id)),
+            "songCount": songs.len(),
+            "duration": detail.tracks.iter().map(|t| t.duration_ms).sum::<u64>() / 1000,
+            "song": songs,
+        }),
+    ))
+}
+
+fn playlist(p: &netease_api::types::Playlist) -> Value {
+    json!({
+        "id": format!("p:{}", p.id),
+        "name": p.name,
+        "comment": p.description,
+        "owner": p.creator.as_ref().map(|c| c.name.clone()),
+        "public": true,
+        "songCount": p.track_count,
+        "duration": 0,
+        "coverArt": format!("p:{}", p.id),
+    })
+}
+
+/// The logged-in user's Netease playlists; none without a login.
+fn get_playlists(server: &Server) -> Result<Reply, ApiError> {
+    if !server.client.session().is_logged_in() {
+        return Ok(data("playlists", json!({ "playlist": [] })));
+    }
+    let user = server
+        .client
+        .user_info()
+        .map_err(|e| ApiError::netease(&e))?;
+    let playlists = server
+        .client
+        .user_playlists(user.id, 1000, 0)
+        .map_err(|e| ApiError::netease(&e))?;
+    let playlists: Vec<Value> = playlists.iter().map(playlist).collect();
+    Ok(data("playlists", json!({ "playlist": playlists })))
+}
+
+fn get_playlist(server: &Server, id: &str) -> Result<Reply, ApiError> {
+    let id = id
+        .strip_prefix("p:")
+        .and_then(|id| id.parse().ok())
+        .ok_or_else(ApiError::not_found)?;
+    let detail = server
+        .client
+        .playlist_detail(id)
+        .map_err(|e| ApiError::netease(&e))?;
+    let tracks = detail.tracks.as_deref().unwrap_or_default();
+    let mut value = playlist(&detail);
+    value["duration"] = (tracks.iter().map(|t| t.duration_ms).sum::<u64>() / 1000).into();
+    value["entry"] = tracks.iter().map(netease_song).collect();
+    Ok(data("playlist", value))
+}
+
+// ── media ──
+
+fn stream(
+    server: &Server,
+    id: &str,
+    range: Option<&str>,
+    params: &Params,
+) -> Result<Reply, ApiError> {
+    let response = if let Some(path) = id.strip_prefix("f:") {
+        let entries = server.entries();
+        let kbps = entries
+            .iter()
+            .find(|e| e.path == path)
+            .filter(|e| e.duration_ms > 0)
+            .map(|e| e.size * 8 / e.duration_ms);
+        let target = stream_target(params, kbps).filter(|_| server.transcoding);
+        server.file(path, range, target)
+    } else if let Some(track_id) = id.strip_prefix("n:") {
+        let track_id = track_id.parse().map_err(|_| ApiError::not_found())?;
+        let kbps = server.quality.bitrate() / 1000;
+        let target = stream_target(params, Some(kbps)).filter(|_| server.transcoding);
+        server.track(track_id, server.quality, range, target)
+    } else {
+        return Err(ApiError::not_found());
+    };
+    Ok(Reply::Raw(response.unwrap_or_else(http_error)))
+}
+
+/// The transcoding a `stream` call asks for, given the source's bitrate in
+/// kbit/s: the codec named by `format` (`raw` for none), else MP3 if the
+/// source exceeds `maxBitRate`.
+fn stream_target(params: &Params, source_kbps: Option<u64>) -> Option<Target> {
+    if params.get("format") == Some("raw") {
+        return None;
+    }
+    let max_bitrate = params
+        .get("maxBitRate")
+        .and_then(|v| v.parse::<u32>().ok())
+        .filter(|&b| b > 0);
+    if let Some(codec) = params.get("format").and_then(Codec::from_name) {
+        return Some(Target {
+            codec,
+            bitrate: max_bitrate,
+        });
+    }
+    let max_bitrate = max_bitrate?;
+    (source_kbps? > u64::from(max_bitrate)).then_some(Target {
+        codec: Codec::Mp3,
+        bitrate: Some(max_bitrate),
+    })
+}
+
+fn cover_art(server: &Server, id: &str) -> Result<Reply, ApiError> {
+    let image = if let Some(path) = id.strip_prefix("f:") {
+        local_cover(server, path)
+    } else if let Some(track_id) = id.strip_prefix("n:") {
+        let track_id = track_id.parse().map_err(|_| ApiError::not_found())?;
+        let track = server
+            .client
+            .track_detail(track_id)
+            .map_err(|e| ApiError::netease(&e))?;
+        remote_cover(server, track.album.pic_url.as_deref())
+    } else if let Some(playlist_id) = id.strip_prefix("p:") {
+        let playlist_id = playlist_id.parse().map_err(|_| ApiError::not_found())?;
+        let detail = server
+            .client
+            .playlist_detail(playlist_id)
+            .map_err(|e| ApiError::netease(&e))?;
+        remote_cover(server, detail.cover_url.as_deref())
+    } else {
+        None
+    };
+    let image = image.ok_or_else(ApiError::not_found)?;
+    let content_type = ncmdump::ImageKind::detect(&image).mime_type();
+    Ok(Reply::Raw(
+        tiny_http::Response::from_data(image)
+            .with_header(header_of("Content-Type", content_type))
+            .boxed(),
+    ))
+}
+
+/// Cover of the file at `path` under the root: the NCM header's, or the
+/// embedded picture of an MP3/FLAC.
+fn local_cover(server: &Server, path: &str) -> Option<Vec<u8>> {
+    let rel = std::path::Path::new(path);
+    if !rel
+        .components()
+        .all(|c| matches!(c, std::path::Component::Normal(_)))
+    {
+        return None;
+    }
+    let file = server.root.join(rel);
+    if file
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("ncm"))
+    {
+        ncmdump::extract_cover(&file).ok()?.map(|(image, _)| image)
+    } else {
+        ncmdump::tag_read_cover(&file).ok().flatten()
+    }
+}
+
+fn remote_cover(server: &Server, url: Option<&str>) -> Option<Vec<u8>> {
+    server.client.fetch_bytes(url?).ok()
+}
+
+fn http_error(HttpError(status, reason): HttpError) -> ResponseBox {
+    tiny_http::Response::from_string(reason)
+        .with_status_code(status)
+        .boxed()
+}
+
+fn user(name: Option<&str>) -> Value {
+    json!({
+        "username": name.unwrap_or("ncmdump"),
+        "scrobblingEnabled": false,
+        "adminRole": false,
+        "settingsRole": false,
+        "downloadRole": true,
+        "uploadRole": false,
+        "playlistRole": false,
+        "coverArtRole": true,
+        "commentRole": false,
+        "podcastRole": false,
+        "streamRole": true,
+        "jukeboxRole": false,
+        "shareRole": false,
+        "videoConversionRole": false,
+        "folder": [1],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(path: &str, artist: Option<&str>, album: Option<&str>) -> Entry {
+        Entry {
+            path: path.into(),
+            format: "mp3",
+            ncm: false,
+            title: None,
+            artist: artist.map(String::from),
+            album: album.map(String::from),
+            duration_ms: 0,
+            netease_id: None,
+            size: 0,
+        }
+    }
+
+    #[test]
+    fn test_params() {
+        let params = Params::parse("u=me&p=enc%3A7069&query=a+b&f=json");
+        assert_eq!(params.get("p"), Some("enc:7069"));
+        assert_eq!(params.get("query"), Some("a b"));
+        assert_eq!(params.get("x"), None);
+        assert_eq!(decode_hex("7069"), Some(b"pi".to_vec()));
+        assert_eq!(decode_hex("706"), None);
+    }
+
+    #[test]
+    fn test_stream_target() {
+        let target = |query: &str, kbps| stream_target(&Params::parse(query), kbps);
+        assert_eq!(target("", Some(900)), None);
+        assert_eq!(target("format=raw&maxBitRate=128", Some(900)), None);
+        assert_eq!(
+            target("format=opus", Some(900)),
+            Some(Target::new(Codec::Opus))
+        );
+        let mp3_128 = Some(Target {
+            codec: Codec::Mp3,
+            bitrate: Some(128),
+        });
+        assert_eq!(target("maxBitRate=128", Some(900)), mp3_128);
+        assert_eq!(target("format=mp3&maxBitRate=128", Some(100)), mp3_128);
+        // Sources within the limit, or of unknown bitrate, are sent as is.
+        assert_eq!(target("maxBitRate=320", Some(320)), None);
+        assert_eq!(target("maxBitRate=128", None), None);
+        assert_eq!(target("maxBitRate=0", Some(900)), None);
+    }
+
+    #[test]
+    fn test_write_xml() {
+        let mut xml = String::new();
+        let value = json!({
+            "id": "f:a&b",
+            "isDir": false,
+            "child": [{ "id": 1 }, { "id": 2 }],
+            "parent": null,
+        });
+        write_xml(&mut xml, "directory", &value);
+        assert_eq!(
+            xml,
+            r#"<directory id="f:a&amp;b" isDir="false"><child id="1"/><child id="2"/></directory>"#
+        );
+    }
+
+    #[test]
+    fn test_directory() {
+        let entries = [
+            entry("a.mp3", None, None),
+            entry("x/b.mp3", None, None),
+            entry("x/y/c.mp3", None, None),
+            entry("z/d.mp3", None, None),
+        ];
+        let (dirs, files) = directory(&entries, "");
+        assert_eq!(dirs, ["x", "z"]);
+        assert_eq!(files.len(), 1);
+        let (dirs, files) = directory(&entries, "x");
+        assert_eq!(dirs, ["y"]);
+        assert_eq!(files[0].path, "x/b.mp3");
+        assert_eq!(parent_dir("x/y/c.mp3"), "x/y");
+        assert_eq!(parent_dir("a.mp3"), "");
+    }
+
+    #[test]
+    fn test_albums_group_by_tags() {
+        let entries = [
+            entry("1.mp3", Some("A"), Some("X")),
+            entry("2.mp3", Some("A"), Some("X")),
+            entry("3.mp3", Some("B"), Some("X")),
+            entry("4.mp3", None, None),
+        ];
+        let albums = albums(&entries);
+        assert_eq!(albums.len(), 3);
+        assert_eq!(albums[&("A", "X")].len(), 2);
+        assert_eq!(albums[&(UNKNOWN_ARTIST, UNKNOWN_ALBUM)].len(), 1);
+        assert_ne!(album_id(&entries[0]), album_id(&entries[2]));
+        assert_eq!(album_id(&entries[0]).len(), "al:".len() + 16);
+    }
+}
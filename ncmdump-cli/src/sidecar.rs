@@ -0,0 +1,303 @@
+//! Media server sidecar files (`organize --sidecars`).
+//!
+//! Jellyfin and Navidrome scan an `Artist/Album/NN Title.ext` library
+//! best when each album folder carries `album.nfo` (Kodi's XML format) and
+//! a `cover.jpg`, and each artist folder an `artist.nfo`. These are derived
+//! from the files' tags — album artist and MusicBrainz IDs included, when
+//! `enrich` has added them. Existing sidecars are left alone, so files the
+//! server or the user edited win.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use ncmdump::AudioTags;
+use walkdir::WalkDir;
+
+use crate::i18n::tr;
+
+/// Album artist of albums whose tracks credit different artists and no
+/// album artist.
+const VARIOUS_ARTISTS: &str = "Various Artists";
+
+/// Image names media servers take as an album's cover.
+const COVER_STEMS: &[&str] = &["cover", "folder", "front"];
+
+/// An album folder, summarised from its tracks' tags.
+#[derive(Debug, PartialEq)]
+struct Album {
+    title: String,
+    artist: String,
+    year: Option<u16>,
+    release: Option<String>,
+    release_group: Option<String>,
+    artist_ids: Vec<String>,
+    /// Track number, title and duration in milliseconds, in album order.
+    tracks: Vec<(Option<u32>, String, u64)>,
+}
+
+impl Album {
+    /// The album of `tracks`, or `None` when none of them names one.
+    fn from_tracks(tracks: &[AudioTags]) -> Option<Self> {
+        let first = |f: fn(&AudioTags) -> Option<&String>| {
+            tracks
+                .iter()
+                .filter_map(f)
+                .find(|s| !s.trim().is_empty())
+                .cloned()
+        };
+        let title = first(|t| t.album.as_ref())?;
+        let artist = first(|t| t.album_artist.as_ref()).unwrap_or_else(|| {
+            let mut artists = tracks.iter().filter_map(|t| t.artist.as_deref());
+            match artists.next() {
+                Some(a) if artists.all(|b| b == a) => a.to_owned(),
+                _ => VARIOUS_ARTISTS.to_owned(),
+            }
+        });
+        let ids = tracks.iter().map(|t| &t.musicbrainz);
+        let mut entries: Vec<_> = tracks
+            .iter()
+            .map(|t| (t.track, t.title.clone().unwrap_or_default(), t.duration_ms))
+            .collect();
+        // Numbered tracks first.
+        entries.sort_by(|a, b| (a.0.is_none(), a.0, &a.1).cmp(&(b.0.is_none(), b.0, &b.1)));
+        Some(Self {
+            title,
+            artist,
+            year: tracks.iter().filter_map(|t| t.year).min(),
+            release: ids.clone().find_map(|m| m.release.clone()),
+            release_group: ids.clone().find_map(|m| m.release_group.clone()),
+            artist_ids: ids
+                .map(|m| &m.album_artists)
+                .find(|a| !a.is_empty())
+                .cloned()
+                .unwrap_or_default(),
+            tracks: entries,
+        })
+    }
+
+    /// The `album.nfo` document.
+    fn nfo(&self) -> String {
+        let mut xml = String::from(XML_HEADER);
+        xml.push_str("<album>\n");
+        element(&mut xml, 1, "title", &self.title);
+        element(&mut xml, 1, "artist", &self.artist);
+        element(&mut xml, 1, "albumartist", &self.artist);
+        if let Some(year) = self.year {
+            element(&mut xml, 1, "year", &year.to_string());
+        }
+        if let Some(id) = &self.release {
+            element(&mut xml, 1, "musicbrainzalbumid", id);
+        }
+        if let Some(id) = &self.release_group {
+            element(&mut xml, 1, "musicbrainzreleasegroupid", id);
+        }
+        for id in &self.artist_ids {
+            element(&mut xml, 1, "musicbrainzalbumartistid", id);
+        }
+        for (position, title, duration_ms) in &self.tracks {
+            xml.push_str("  <track>\n");
+            if let Some(position) = position {
+                element(&mut xml, 2, "position", &position.to_string());
+            }
+            element(&mut xml, 2, "title", title);
+            if *duration_ms > 0 {
+                let secs = duration_ms / 1000;
+                element(
+                    &mut xml,
+                    2,
+                    "duration",
+                    &format!("{}:{:02}", secs / 60, secs % 60),
+                );
+            }
+            xml.push_str("  </track>\n");
+        }
+        xml.push_str("</album>\n");
+        xml
+    }
+}
+
+/// The `artist.nfo` document for `name`.
+fn artist_nfo(name: &str, ids: &[String]) -> String {
+    let mut xml = String::from(XML_HEADER);
+    xml.push_str("<artist>\n");
+    element(&mut xml, 1, "name", name);
+    // A credit of several artists has no single ID.
+    if let [id] = ids {
+        element(&mut xml, 1, "musicbrainzartistid", id);
+    }
+    xml.push_str("</artist>\n");
+    xml
+}
+
+const XML_HEADER: &str = "<?xml version=\"1.0\" encoding=\"utf-8\" standalone=\"yes\"?>\n";
+
+fn element(xml: &mut String, depth: usize, name: &str, text: &str) {
+    let _ = writeln!(
+        xml,
+        "{:indent$}<{name}>{}</{name}>",
+        "",
+        escape(text),
+        indent = depth * 2
+    );
+}
+
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            // Not allowed in XML 1.0 at all.
+            c if c.is_control() && !matches!(c, '\n' | '\t' | '\r') => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Write the missing sidecars of the library under `root` (only list them
+/// with `dry_run`). Returns the files written, or that would be.
+pub fn write_library(root: &Path, dry_run: bool) -> Result<Vec<PathBuf>> {
+    let mut albums: BTreeMap<PathBuf, Vec<(PathBuf, AudioTags)>> = BTreeMap::new();
+    for entry in WalkDir::new(root)
+        .into_iter()
+        .filter_map(std::result::Result::ok)
+    {
+        let path = entry.path();
+        if !entry.file_type().is_file() || !crate::has_audio_extension(path) {
+            continue;
+        }
+        let Some(dir) = path.parent() else { continue };
+        match ncmdump::tag_read(path) {
+            Ok(tags) => albums
+                .entry(dir.to_owned())
+                .or_default()
+                .push((path.to_owned(), tags)),
+            Err(e) => tracing::warn!(file = %path.display(), error = %e, "cannot read tags"),
+        }
+    }
+
+    let mut written = Vec::new();
+    let mut write = |path: PathBuf, content: &[u8]| -> Result<()> {
+        if !dry_run {
+            std::fs::write(&path, content)
+                .with_context(|| tr!("write-failed", path = path.display()))?;
+            tracing::info!(path = %path.display(), "wrote sidecar");
+        }
+        written.push(path);
+        Ok(())
+    };
+    let mut artists: BTreeMap<PathBuf, (String, Vec<String>)> = BTreeMap::new();
+    for (dir, files) in &albums {
+        let tags: Vec<AudioTags> = files.iter().map(|(_, t)| t.clone()).collect();
+        let Some(album) = Album::from_tracks(&tags) else {
+            continue;
+        };
+        let nfo = dir.join("album.nfo");
+        if !nfo.exists() {
+            write(nfo, album.nfo().as_bytes())?;
+        }
+        if !has_cover_image(dir) {
+            let cover = files
+                .iter()
+                .find_map(|(path, _)| ncmdump::tag_read_cover(path).ok().flatten());
+            if let Some(cover) = cover {
+                let ext = ncmdump::ImageKind::detect(&cover).extension();
+                write(dir.join(format!("cover.{ext}")), &cover)?;
+            }
+        }
+        // Only in an `Artist/Album` layout: the root itself is no artist.
+        if let Some(artist_dir) = dir.parent().filter(|p| p.starts_with(root) && *p != root) {
+            artists
+                .entry(artist_dir.to_owned())
+                .or_insert_with(|| (album.artist.clone(), album.artist_ids.clone()));
+        }
+    }
+    for (dir, (name, ids)) in artists {
+        let nfo = dir.join("artist.nfo");
+        if !nfo.exists() {
+            write(nfo, artist_nfo(&name, &ids).as_bytes())?;
+        }
+    }
+    Ok(written)
+}
+
+/// Whether `dir` already holds an image a media server takes as the cover.
+fn has_cover_image(dir: &Path) -> bool {
+    COVER_STEMS.iter().any(|stem| {
+        ["jpg", "jpeg", "png", "webp"]
+            .iter()
+            .any(|ext| dir.join(format!("{stem}.{ext}")).is_file())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(n: Option<u32>, title: &str, artist: &str) -> AudioTags {
+        AudioTags {
+            title: Some(title.into()),
+            artist: Some(artist.into()),
+            album: Some("叶惠美".into()),
+            track: n,
+            year: Some(2003),
+            duration_ms: 269_500,
+            ..AudioTags::default()
+        }
+    }
+
+    #[test]
+    fn test_album_from_tracks() {
+        let album = Album::from_tracks(&[
+            track(None, "Bonus", "周杰伦"),
+            track(Some(3), "晴天", "周杰伦"),
+            track(Some(1), "以父之名", "周杰伦"),
+        ])
+        .unwrap();
+        assert_eq!(album.artist, "周杰伦");
+        let titles: Vec<&str> = album.tracks.iter().map(|t| t.1.as_str()).collect();
+        assert_eq!(titles, ["以父之名", "晴天", "Bonus"]);
+
+        let mixed = Album::from_tracks(&[track(Some(1), "A", "X"), track(Some(2), "B", "Y")]);
+        assert_eq!(mixed.unwrap().artist, VARIOUS_ARTISTS);
+        assert_eq!(Album::from_tracks(&[AudioTags::default()]), None);
+    }
+
+    #[test]
+    fn test_album_nfo() {
+        let mut tags = track(Some(3), "晴天", "周杰伦");
+        tags.album = Some("Rock & <Roll>".into());
+        tags.musicbrainz.release = Some("rel".into());
+        let nfo = Album::from_tracks(&[tags]).unwrap().nfo();
+        assert!(nfo.starts_with("<?xml"), "{nfo}");
+        assert!(
+            nfo.contains("  <title>Rock &amp; &lt;Roll&gt;</title>\n"),
+            "{nfo}"
+        );
+        assert!(
+            nfo.contains("  <musicbrainzalbumid>rel</musicbrainzalbumid>\n"),
+            "{nfo}"
+        );
+        assert!(
+            nfo.contains(
+                "  <track>\n    <position>3</position>\n    <title>晴天</title>\n    \
+                 <duration>4:29</duration>\n  </track>\n"
+            ),
+            "{nfo}"
+        );
+    }
+
+    #[test]
+    fn test_artist_nfo() {
+        let nfo = artist_nfo("周杰伦", &["a1".into()]);
+        assert!(nfo.contains("<name>周杰伦</name>"), "{nfo}");
+        assert!(nfo.contains("<musicbrainzartistid>a1</musicbrainzartistid>"));
+        assert!(!artist_nfo("A & B", &["a".into(), "b".into()]).contains("musicbrainz"));
+    }
+}
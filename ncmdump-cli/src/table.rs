@@ -0,0 +1,159 @@
+//! Track tables for `--fields`.
+//!
+//! Renders a list of tracks as one row per track with only the selected
+//! columns, padded so the columns line up. CJK and other wide characters
+//! count as two terminal cells. The last column is never padded, so rows have
+//! no trailing spaces.
+
+use clap::ValueEnum;
+use netease_api::types::Track;
+
+/// Gap between two columns.
+const GAP: &str = "  ";
+
+/// A column of a track table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Field {
+    /// Track ID
+    Id,
+    /// Artists, comma-separated
+    Artist,
+    /// Track title
+    Title,
+    /// Album name
+    Album,
+    /// Duration as `m:ss`
+    Duration,
+}
+
+impl Field {
+    fn value(self, t: &Track) -> String {
+        match self {
+            Self::Id => t.id.to_string(),
+            Self::Artist => {
+                let artists: Vec<&str> = t.artists.iter().map(|a| a.name.as_str()).collect();
+                artists.join(", ")
+            }
+            Self::Title => t.name.clone(),
+            Self::Album => t.album.name.clone(),
+            Self::Duration => format!(
+                "{}:{:02}",
+                t.duration_ms / 60000,
+                (t.duration_ms / 1000) % 60
+            ),
+        }
+    }
+
+    /// Numbers read better right-aligned.
+    fn right_aligned(self) -> bool {
+        matches!(self, Self::Id | Self::Duration)
+    }
+}
+
+/// Render `tracks` as aligned rows of `fields`, one line per track.
+pub fn render(tracks: &[Track], fields: &[Field]) -> Vec<String> {
+    let cells: Vec<Vec<String>> = tracks
+        .iter()
+        .map(|t| fields.iter().map(|f| f.value(t)).collect())
+        .collect();
+    let widths: Vec<usize> = (0..fields.len())
+        .map(|i| cells.iter().map(|row| width(&row[i])).max().unwrap_or(0))
+        .collect();
+
+    cells
+        .iter()
+        .map(|row| {
+            let mut line = String::new();
+            for (i, cell) in row.iter().enumerate() {
+                let pad = " ".repeat(widths[i] - width(cell));
+                if i > 0 {
+                    line.push_str(GAP);
+                }
+                if fields[i].right_aligned() {
+                    line.push_str(&pad);
+                    line.push_str(cell);
+                } else {
+                    line.push_str(cell);
+                    if i + 1 < row.len() {
+                        line.push_str(&pad);
+                    }
+                }
+            }
+            line
+        })
+        .collect()
+}
+
+/// Terminal cells `s` takes up.
+fn width(s: &str) -> usize {
+    s.chars().map(|c| if is_wide(c) { 2 } else { 1 }).sum()
+}
+
+/// Whether `c` is an East Asian wide or fullwidth character.
+fn is_wide(c: char) -> bool {
+    matches!(
+        u32::from(c),
+        0x1100..=0x115F
+            | 0x2E80..=0x303E
+            | 0x3041..=0x33FF
+            | 0x3400..=0x4DBF
+            | 0x4E00..=0x9FFF
+            | 0xA000..=0xA4CF
+            | 0xAC00..=0xD7A3
+            | 0xF900..=0xFAFF
+            | 0xFE30..=0xFE4F
+            | 0xFF00..=0xFF60
+            | 0xFFE0..=0xFFE6
+            | 0x1F300..=0x1F64F
+            | 0x1F900..=0x1F9FF
+            | 0x20000..=0x3FFFD
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use netease_api::types::{Album, Artist};
+
+    fn track(id: u64, artist: &str, name: &str, duration_ms: u64) -> Track {
+        Track {
+            id,
+            name: name.to_owned(),
+            artists: vec![Artist {
+                id: 0,
+                name: artist.to_owned(),
+            }],
+            album: Album {
+                id: 0,
+                name: "Album".to_owned(),
+                pic_url: None,
+            },
+            duration_ms,
+        }
+    }
+
+    #[test]
+    fn test_width() {
+        assert_eq!(width("abc"), 3);
+        assert_eq!(width("贝贝"), 4);
+        assert_eq!(width("Ｂ"), 2);
+        assert_eq!(width("李荣浩 x"), 8);
+    }
+
+    #[test]
+    fn test_render_aligns_columns() {
+        let tracks = [
+            track(7, "李荣浩", "贝贝", 245_000),
+            track(186_016, "Jay", "晴天", 3_600_000),
+        ];
+        let rows = render(&tracks, &[Field::Id, Field::Artist, Field::Duration]);
+        assert_eq!(rows, ["     7  李荣浩   4:05", "186016  Jay     60:00"]);
+    }
+
+    #[test]
+    fn test_render_last_column_unpadded() {
+        let tracks = [track(1, "A", "Long title", 0), track(2, "B", "T", 0)];
+        let rows = render(&tracks, &[Field::Title]);
+        assert_eq!(rows, ["Long title", "T"]);
+    }
+}
@@ -0,0 +1,540 @@
+//! Local library database.
+//!
+//! Every audio file ncmdump produces — decrypted from an NCM or cache file,
+//! or downloaded — is recorded in one `SQLite` file, keyed by its absolute
+//! path, together with its Netease track ID, quality, size, MD5 and tags.
+//! The CLI uses it to skip tracks that are already on disk, to follow files
+//! that were renamed, and to tell which NCM files have been converted.
+//!
+//! Paths are stored as UTF-8 text. The few that are not valid Unicode are
+//! stored as blobs of their raw bytes (UTF-16LE units on Windows) instead,
+//! so every file name round-trips unchanged.
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use md5::{Digest, Md5};
+use rusqlite::types::Value;
+use rusqlite::{Connection, OptionalExtension, Row, params};
+
+/// File name of the database inside `<data dir>/ncmdump`.
+pub const FILE_NAME: &str = "library.db";
+
+/// Schema version, kept in `SQLite`'s `user_version`.
+const SCHEMA_VERSION: i32 = 1;
+
+const SCHEMA: &str = "
+CREATE TABLE tracks (
+    path        TEXT PRIMARY KEY,
+    dir         TEXT NOT NULL,
+    origin      TEXT NOT NULL,
+    netease_id  INTEGER,
+    source      TEXT,
+    bitrate     INTEGER,
+    size        INTEGER NOT NULL,
+    md5         TEXT NOT NULL,
+    title       TEXT,
+    artist      TEXT,
+    album       TEXT,
+    recorded_at INTEGER NOT NULL
+);
+CREATE INDEX tracks_dir ON tracks (dir);
+CREATE INDEX tracks_netease_id ON tracks (netease_id);
+CREATE INDEX tracks_source ON tracks (source);
+";
+
+const COLUMNS: &str =
+    "path, origin, netease_id, source, bitrate, size, md5, title, artist, album, recorded_at";
+
+#[derive(Debug, thiserror::Error)]
+pub enum DbError {
+    #[error("database error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+    #[error("cannot determine data directory")]
+    NoDataDir,
+    #[error("database schema version {0} is newer than this version of ncmdump")]
+    TooNew(i32),
+}
+
+pub type Result<T> = std::result::Result<T, DbError>;
+
+/// How a file got into the library.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Origin {
+    /// Decrypted from an `.ncm` file.
+    Convert,
+    /// Decrypted from a client cache (`.uc`) file.
+    Cache,
+    /// Downloaded from Netease.
+    Download,
+    /// An existing file identified by `import match`.
+    Import,
+}
+
+impl Origin {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Convert => "convert",
+            Self::Cache => "cache",
+            Self::Download => "download",
+            Self::Import => "import",
+        }
+    }
+
+    fn parse(s: &str) -> Self {
+        match s {
+            "convert" => Self::Convert,
+            "cache" => Self::Cache,
+            "import" => Self::Import,
+            _ => Self::Download,
+        }
+    }
+}
+
+/// One audio file in the library.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Track {
+    /// Absolute path of the audio file.
+    pub path: PathBuf,
+    pub origin: Origin,
+    /// Netease track ID, if known.
+    pub netease_id: Option<u64>,
+    /// The `.ncm` or cache file it was decrypted from.
+    pub source: Option<PathBuf>,
+    /// Quality as API bitrate: the one requested for downloads, the NCM
+    /// header's for conversions.
+    pub bitrate: Option<u64>,
+    /// File size in bytes.
+    pub size: u64,
+    /// Lowercase hex MD5 of the file.
+    pub md5: String,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    /// Unix time (seconds) it was recorded.
+    pub recorded_at: u64,
+}
+
+impl Track {
+    /// A record of the file at `path` as it is now on disk; set the other
+    /// fields with struct update syntax.
+    pub fn from_file(path: &Path, origin: Origin) -> io::Result<Self> {
+        let (size, md5) = fingerprint(path)?;
+        Ok(Self {
+            path: std::path::absolute(path)?,
+            origin,
+            netease_id: None,
+            source: None,
+            bitrate: None,
+            size,
+            md5,
+            title: None,
+            artist: None,
+            album: None,
+            recorded_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs()),
+        })
+    }
+
+    fn from_row(row: &Row<'_>) -> rusqlite::Result<Self> {
+        Ok(Self {
+            path: path_from(row.get(0)?)?,
+            origin: Origin::parse(&row.get::<_, String>(1)?),
+            netease_id: row.get::<_, Option<i64>>(2)?.map(to_u64),
+            source: match row.get(3)? {
+                Value::Null => None,
+                value => Some(path_from(value)?),
+            },
+            bitrate: row.get::<_, Option<i64>>(4)?.map(to_u64),
+            size: to_u64(row.get(5)?),
+            md5: row.get(6)?,
+            title: row.get(7)?,
+            artist: row.get(8)?,
+            album: row.get(9)?,
+            recorded_at: to_u64(row.get(10)?),
+        })
+    }
+}
+
+/// Number and total size of the files of one [`Origin`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OriginStats {
+    pub origin: Origin,
+    pub files: u64,
+    pub bytes: u64,
+}
+
+/// A library database. Safe to share between threads.
+pub struct Library {
+    conn: Mutex<Connection>,
+}
+
+impl Library {
+    /// Location of the database (`<data dir>/ncmdump/library.db`).
+    pub fn default_path() -> Result<PathBuf> {
+        let data = dirs::data_dir().ok_or(DbError::NoDataDir)?;
+        Ok(data.join("ncmdump").join(FILE_NAME))
+    }
+
+    /// Open (or create) the database at `path`.
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let conn = Connection::open(path)?;
+        // Parallel CLI runs share the file; wait for each other's writes.
+        conn.busy_timeout(Duration::from_secs(5))?;
+        Self::init(conn)
+    }
+
+    /// A private, empty database that lives as long as the value.
+    pub fn open_in_memory() -> Result<Self> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(conn: Connection) -> Result<Self> {
+        let version: i32 = conn.query_row("PRAGMA user_version", [], |r| r.get(0))?;
+        if version > SCHEMA_VERSION {
+            return Err(DbError::TooNew(version));
+        }
+        if version < SCHEMA_VERSION {
+            conn.execute_batch(&format!(
+                "BEGIN; {SCHEMA} PRAGMA user_version = {SCHEMA_VERSION}; COMMIT;"
+            ))?;
+        }
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    fn conn(&self) -> MutexGuard<'_, Connection> {
+        self.conn.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Add `track`, replacing any record of the same path.
+    pub fn record(&self, track: &Track) -> Result<()> {
+        let path = std::path::absolute(&track.path)?;
+        self.conn().execute(
+            &format!(
+                "INSERT OR REPLACE INTO tracks (dir, {COLUMNS})
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)"
+            ),
+            params![
+                dir_key(&path),
+                key(&path),
+                track.origin.as_str(),
+                track.netease_id.map(to_i64),
+                track.source.as_deref().map(key),
+                track.bitrate.map(to_i64),
+                to_i64(track.size),
+                track.md5,
+                track.title,
+                track.artist,
+                track.album,
+                to_i64(track.recorded_at),
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// The record of the file at `path`.
+    pub fn get(&self, path: &Path) -> Result<Option<Track>> {
+        let path = key(&std::path::absolute(path)?);
+        let conn = self.conn();
+        let track = conn
+            .query_row(
+                &format!("SELECT {COLUMNS} FROM tracks WHERE path = ?1"),
+                [path],
+                Track::from_row,
+            )
+            .optional()?;
+        Ok(track)
+    }
+
+    /// Files of Netease track `id`, newest first.
+    pub fn by_netease_id(&self, id: u64) -> Result<Vec<Track>> {
+        self.query("netease_id = ?1", to_i64(id))
+    }
+
+    /// Files decrypted from `source`, newest first.
+    pub fn by_source(&self, source: &Path) -> Result<Vec<Track>> {
+        self.query("source = ?1", key(&std::path::absolute(source)?))
+    }
+
+    /// Files directly inside `dir`, newest first.
+    pub fn in_dir(&self, dir: &Path) -> Result<Vec<Track>> {
+        self.query("dir = ?1", key(&std::path::absolute(dir)?))
+    }
+
+    fn query(&self, filter: &str, value: impl rusqlite::ToSql) -> Result<Vec<Track>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {COLUMNS} FROM tracks WHERE {filter} ORDER BY recorded_at DESC, path"
+        ))?;
+        let tracks = stmt
+            .query_map([value], Track::from_row)?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(tracks)
+    }
+
+    /// Follow a file moved from `from` to `to`. Returns whether `from` was
+    /// recorded.
+    pub fn rename(&self, from: &Path, to: &Path) -> Result<bool> {
+        let to = std::path::absolute(to)?;
+        let changed = self.conn().execute(
+            "UPDATE OR REPLACE tracks SET path = ?1, dir = ?2 WHERE path = ?3",
+            params![key(&to), dir_key(&to), key(&std::path::absolute(from)?)],
+        )?;
+        Ok(changed > 0)
+    }
+
+    /// Forget the file at `path`. Returns whether it was recorded.
+    pub fn remove(&self, path: &Path) -> Result<bool> {
+        let changed = self.conn().execute(
+            "DELETE FROM tracks WHERE path = ?1",
+            [key(&std::path::absolute(path)?)],
+        )?;
+        Ok(changed > 0)
+    }
+
+    /// Files and bytes per origin, for the origins that have any.
+    pub fn stats(&self) -> Result<Vec<OriginStats>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare(
+            "SELECT origin, COUNT(*), COALESCE(SUM(size), 0) FROM tracks GROUP BY origin",
+        )?;
+        let mut stats = stmt
+            .query_map([], |row| {
+                Ok(OriginStats {
+                    origin: Origin::parse(&row.get::<_, String>(0)?),
+                    files: to_u64(row.get(1)?),
+                    bytes: to_u64(row.get(2)?),
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        stats.sort_by_key(|s| s.origin);
+        Ok(stats)
+    }
+}
+
+/// Size and lowercase hex MD5 of the file at `path`.
+pub fn fingerprint(path: &Path) -> io::Result<(u64, String)> {
+    let mut hasher = Md5::new();
+    let size = io::copy(&mut std::fs::File::open(path)?, &mut hasher)?;
+    Ok((size, format!("{:x}", hasher.finalize())))
+}
+
+fn key(path: &Path) -> Value {
+    match path.to_str() {
+        Some(s) => Value::Text(s.to_owned()),
+        None => Value::Blob(os_bytes(path.as_os_str())),
+    }
+}
+
+/// The path stored as `value` by [`key`].
+fn path_from(value: Value) -> rusqlite::Result<PathBuf> {
+    match value {
+        Value::Text(s) => Ok(PathBuf::from(s)),
+        Value::Blob(bytes) => Ok(PathBuf::from(os_string(bytes))),
+        other => Err(rusqlite::Error::InvalidColumnType(
+            0,
+            "path".into(),
+            other.data_type(),
+        )),
+    }
+}
+
+#[cfg(unix)]
+fn os_bytes(s: &std::ffi::OsStr) -> Vec<u8> {
+    std::os::unix::ffi::OsStrExt::as_bytes(s).to_vec()
+}
+
+#[cfg(unix)]
+fn os_string(bytes: Vec<u8>) -> std::ffi::OsString {
+    std::os::unix::ffi::OsStringExt::from_vec(bytes)
+}
+
+#[cfg(windows)]
+fn os_bytes(s: &std::ffi::OsStr) -> Vec<u8> {
+    std::os::windows::ffi::OsStrExt::encode_wide(s)
+        .flat_map(u16::to_le_bytes)
+        .collect()
+}
+
+#[cfg(windows)]
+fn os_string(bytes: Vec<u8>) -> std::ffi::OsString {
+    let wide: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|c| u16::from_le_bytes([c[0], c[1]]))
+        .collect();
+    std::os::windows::ffi::OsStringExt::from_wide(&wide)
+}
+
+#[cfg(not(any(unix, windows)))]
+fn os_bytes(s: &std::ffi::OsStr) -> Vec<u8> {
+    s.to_string_lossy().into_owned().into_bytes()
+}
+
+#[cfg(not(any(unix, windows)))]
+fn os_string(bytes: Vec<u8>) -> std::ffi::OsString {
+    String::from_utf8_lossy(&bytes).into_owned().into()
+}
+
+fn dir_key(path: &Path) -> Value {
+    key(path.parent().unwrap_or(Path::new("")))
+}
+
+// SQLite integers are signed; IDs, sizes and times stay far below 2^63.
+fn to_i64(v: u64) -> i64 {
+    i64::try_from(v).unwrap_or(i64::MAX)
+}
+
+fn to_u64(v: i64) -> u64 {
+    u64::try_from(v).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(path: &str, id: u64, recorded_at: u64) -> Track {
+        Track {
+            path: PathBuf::from(path),
+            origin: Origin::Download,
+            netease_id: Some(id),
+            source: None,
+            bitrate: Some(320_000),
+            size: 5,
+            md5: "d41d8cd98f00b204e9800998ecf8427e".into(),
+            title: Some("贝贝".into()),
+            artist: Some("李荣浩".into()),
+            album: None,
+            recorded_at,
+        }
+    }
+
+    #[test]
+    fn test_record_and_query() {
+        let lib = Library::open_in_memory().unwrap();
+        lib.record(&track("/music/a.mp3", 1, 10)).unwrap();
+        lib.record(&track("/music/b.mp3", 1, 20)).unwrap();
+        lib.record(&track("/other/c.mp3", 2, 30)).unwrap();
+
+        assert_eq!(
+            lib.get(Path::new("/music/a.mp3")).unwrap(),
+            Some(track("/music/a.mp3", 1, 10))
+        );
+        assert_eq!(lib.get(Path::new("/music/x.mp3")).unwrap(), None);
+        let paths =
+            |tracks: Vec<Track>| -> Vec<PathBuf> { tracks.into_iter().map(|t| t.path).collect() };
+        assert_eq!(
+            paths(lib.by_netease_id(1).unwrap()),
+            [Path::new("/music/b.mp3"), Path::new("/music/a.mp3")]
+        );
+        assert_eq!(
+            paths(lib.in_dir(Path::new("/other")).unwrap()),
+            [Path::new("/other/c.mp3")]
+        );
+
+        // Recording the same path again replaces the entry.
+        lib.record(&track("/music/a.mp3", 3, 40)).unwrap();
+        assert_eq!(lib.by_netease_id(1).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_rename_and_remove() {
+        let lib = Library::open_in_memory().unwrap();
+        lib.record(&track("/music/a.mp3", 1, 10)).unwrap();
+
+        assert!(
+            lib.rename(Path::new("/music/a.mp3"), Path::new("/new/b.mp3"))
+                .unwrap()
+        );
+        assert!(
+            !lib.rename(Path::new("/music/a.mp3"), Path::new("/x.mp3"))
+                .unwrap()
+        );
+        assert_eq!(lib.get(Path::new("/music/a.mp3")).unwrap(), None);
+        assert_eq!(lib.in_dir(Path::new("/new")).unwrap().len(), 1);
+
+        assert!(lib.remove(Path::new("/new/b.mp3")).unwrap());
+        assert!(lib.by_netease_id(1).unwrap().is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_non_unicode_paths() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let lib = Library::open_in_memory().unwrap();
+        let dir = Path::new(std::ffi::OsStr::from_bytes(b"/music/\xb8\xe8"));
+        let mut song = track("", 1, 10);
+        song.path = dir.join("晴天.flac");
+        song.source = Some(dir.join(std::ffi::OsStr::from_bytes(b"\xff.ncm")));
+        lib.record(&song).unwrap();
+        // A lossy key would make this a different file.
+        lib.record(&track("/music/\u{fffd}\u{fffd}/晴天.flac", 2, 10))
+            .unwrap();
+
+        let found = lib.get(&song.path).unwrap().unwrap();
+        assert_eq!(found.path, song.path);
+        assert_eq!(found.source, song.source);
+        assert_eq!(lib.in_dir(dir).unwrap().len(), 1);
+        assert_eq!(
+            lib.by_source(song.source.as_ref().unwrap()).unwrap()[0].netease_id,
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn test_stats() {
+        let lib = Library::open_in_memory().unwrap();
+        lib.record(&track("/a.mp3", 1, 0)).unwrap();
+        lib.record(&Track {
+            origin: Origin::Convert,
+            size: 7,
+            ..track("/b.flac", 2, 0)
+        })
+        .unwrap();
+        lib.record(&track("/c.mp3", 3, 0)).unwrap();
+        assert_eq!(
+            lib.stats().unwrap(),
+            [
+                OriginStats {
+                    origin: Origin::Convert,
+                    files: 1,
+                    bytes: 7
+                },
+                OriginStats {
+                    origin: Origin::Download,
+                    files: 2,
+                    bytes: 10
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reopen_keeps_records() {
+        let path = std::env::temp_dir().join(format!("ncmdump-db-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        Library::open(&path)
+            .unwrap()
+            .record(&track("/a.mp3", 1, 0))
+            .unwrap();
+        assert_eq!(
+            Library::open(&path)
+                .unwrap()
+                .by_netease_id(1)
+                .unwrap()
+                .len(),
+            1
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,24 @@
+//! Regenerates `include/ncmdump.h` from the exported functions with cbindgen.
+//!
+//! The header is checked in so C consumers need no Rust toolchain; it is only
+//! rewritten when its contents change, so builds do not dirty the tree.
+
+use std::path::PathBuf;
+
+fn main() {
+    let crate_dir = PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap());
+    let header = crate_dir.join("include").join("ncmdump.h");
+    println!("cargo:rerun-if-changed=src");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+
+    let config = cbindgen::Config::from_file(crate_dir.join("cbindgen.toml"))
+        .expect("invalid cbindgen.toml");
+    match cbindgen::generate_with_config(&crate_dir, config) {
+        Ok(bindings) => {
+            bindings.write_to_file(&header);
+        }
+        // A half-edited source file should surface as a compile error from
+        // rustc, not a panic here.
+        Err(e) => println!("cargo:warning=ncmdump.h not regenerated: {e}"),
+    }
+}
@@ -0,0 +1,444 @@
+#ifndef NCMDUMP_H
+#define NCMDUMP_H
+
+/* Generated by cbindgen from ncmdump-ffi/src; do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * Version of the exported C ABI. Bumped whenever an entry point is added or
+ * changed, so hosts that load the library at runtime can require a minimum.
+ */
+#define NCM_ABI_VERSION 4
+
+/**
+ * Status returned by `Dump`.
+ */
+typedef enum {
+  /**
+   * Success.
+   */
+  NCM_STATUS_OK = 0,
+  /**
+   * Any failure: bad handle or path, unreadable input, unwritable output.
+   */
+  NCM_STATUS_ERROR = 1,
+  /**
+   * Stopped by `CancelDump`; the partial output was removed.
+   */
+  NCM_STATUS_CANCELLED = 2,
+} NcmStatus;
+
+/**
+ * Severity of a log message, most severe first.
+ */
+typedef enum {
+  /**
+   * Logging disabled; only valid as `SetLogCallback`'s `level`.
+   */
+  NCM_LOG_LEVEL_OFF = 0,
+  NCM_LOG_LEVEL_ERROR = 1,
+  NCM_LOG_LEVEL_WARN = 2,
+  NCM_LOG_LEVEL_INFO = 3,
+  NCM_LOG_LEVEL_DEBUG = 4,
+  NCM_LOG_LEVEL_TRACE = 5,
+} NcmLogLevel;
+
+#if defined(NCMDUMP_NETEASE)
+/**
+ * What `NeteaseSearch` looks for.
+ */
+typedef enum {
+#if defined(NCMDUMP_NETEASE)
+  NETEASE_SEARCH_TYPE_TRACK = 0,
+#endif
+#if defined(NCMDUMP_NETEASE)
+  NETEASE_SEARCH_TYPE_ALBUM = 1,
+#endif
+#if defined(NCMDUMP_NETEASE)
+  NETEASE_SEARCH_TYPE_ARTIST = 2,
+#endif
+#if defined(NCMDUMP_NETEASE)
+  NETEASE_SEARCH_TYPE_PLAYLIST = 3,
+#endif
+} NeteaseSearchType;
+#endif
+
+#if defined(NCMDUMP_NETEASE)
+/**
+ * Quality tier for `NeteaseTrackUrl` and `NeteaseDownloadTrack`. The server
+ * falls back to the best tier the account can get.
+ */
+typedef enum {
+#if defined(NCMDUMP_NETEASE)
+  /**
+   * 128 kbps MP3.
+   */
+  NETEASE_QUALITY_STANDARD = 0,
+#endif
+#if defined(NCMDUMP_NETEASE)
+  /**
+   * 192 kbps MP3.
+   */
+  NETEASE_QUALITY_HIGHER = 1,
+#endif
+#if defined(NCMDUMP_NETEASE)
+  /**
+   * 320 kbps MP3.
+   */
+  NETEASE_QUALITY_EXHIGH = 2,
+#endif
+#if defined(NCMDUMP_NETEASE)
+  /**
+   * FLAC. Requires VIP.
+   */
+  NETEASE_QUALITY_LOSSLESS = 3,
+#endif
+#if defined(NCMDUMP_NETEASE)
+  /**
+   * 24-bit FLAC. Requires VIP.
+   */
+  NETEASE_QUALITY_HIRES = 4,
+#endif
+#if defined(NCMDUMP_NETEASE)
+  /**
+   * Immersive surround sound. Requires SVIP.
+   */
+  NETEASE_QUALITY_SKY = 5,
+#endif
+} NeteaseQuality;
+#endif
+
+#if defined(NCMDUMP_NETEASE)
+/**
+ * An API client with its session and connection pool. One client may be
+ * used from several threads at once.
+ */
+typedef struct NeteaseClient NeteaseClient;
+#endif
+
+/**
+ * Shared between the thread running `Dump` and one calling `CancelDump`, so
+ * everything `Dump` changes is behind a lock or atomic.
+ */
+typedef struct NeteaseCrypt NeteaseCrypt;
+
+/**
+ * Receives the decrypted audio from `DumpToCallback`, one chunk (at most
+ * 32 KiB) per call. `data` is only valid during the call.
+ */
+typedef void (*NcmWriteCallback)(const uint8_t *data, size_t len, void *user);
+
+/**
+ * Called by `DumpMany` after each file with the number of files finished so
+ * far and the total.
+ */
+typedef void (*NcmProgressCallback)(size_t done, size_t total, void *user);
+
+/**
+ * Called by `DumpMany` once per file with its index in `paths`, the input
+ * path, the outcome and the output path (null unless `status` is
+ * `NCM_STATUS_OK`). The strings are only valid during the call.
+ */
+typedef void (*NcmResultCallback)(size_t index,
+                                  const char *input,
+                                  NcmStatus status,
+                                  const char *output,
+                                  void *user);
+
+/**
+ * Receives one log message: its level, the module it came from (e.g.
+ * `ncmdump_ffi`) and the text with any fields appended as `key=value`. The
+ * strings are only valid during the call.
+ */
+typedef void (*NcmLogCallback)(NcmLogLevel level,
+                               const char *target,
+                               const char *message,
+                               void *user);
+
+#if defined(NCMDUMP_NETEASE)
+/**
+ * Called by `NeteaseDownloadTrack` after every chunk with the bytes written
+ * so far and the total size (`0` if the server did not send one).
+ */
+typedef void (*NeteaseProgressCallback)(uint64_t written, uint64_t total, void *user);
+#endif
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Open the NCM file at `path`. Returns null if `path` is null, or the file
+ * can't be read as NCM.
+ *
+ * # Safety
+ * `path` must be a valid null-terminated C string, or null.
+ */
+NeteaseCrypt *CreateNeteaseCrypt(const char *path);
+
+/**
+ * Create a handle from NCM data already in memory, e.g. bytes read from an
+ * Android content provider. The data is copied, so the caller may free it
+ * as soon as this returns.
+ *
+ * `Dump` on such a handle needs an explicit `output_path` directory and
+ * names the file `<artist> - <title>` from the NCM metadata.
+ *
+ * # Safety
+ * `data` must point to `len` readable bytes.
+ */
+NeteaseCrypt *CreateNeteaseCryptFromBuffer(const uint8_t *data, size_t len);
+
+/**
+ * # Safety
+ * `handle` must be a valid pointer from `CreateNeteaseCrypt`.
+ * `output_path` must be a valid null-terminated C string.
+ */
+NcmStatus Dump(NeteaseCrypt *handle, const char *output_path);
+
+#if defined(_WIN32)
+/**
+ * `CreateNeteaseCrypt` for a UTF-16 path, so Windows hosts can open any
+ * file name regardless of the ANSI code page.
+ *
+ * # Safety
+ * `path` must be a valid null-terminated wide string, or null.
+ */
+NeteaseCrypt *CreateNeteaseCryptW(const wchar_t *path);
+#endif
+
+#if defined(_WIN32)
+/**
+ * `Dump` with a UTF-16 output directory (or null).
+ *
+ * # Safety
+ * `handle` must be a valid pointer from `CreateNeteaseCrypt`.
+ * `output_path` must be a valid null-terminated wide string, or null.
+ */
+NcmStatus DumpW(NeteaseCrypt *handle, const wchar_t *output_path);
+#endif
+
+/**
+ * Like `Dump`, but hands the decrypted audio to `write` in order instead of
+ * writing a file, e.g. to feed a player or encrypted storage. No tags are
+ * added, and `FixMetadata` does not apply. `CancelDump` stops it as well.
+ *
+ * # Safety
+ * `handle` must be a valid pointer from `CreateNeteaseCrypt`. `write` must
+ * be safe to call with `user`.
+ */
+NcmStatus DumpToCallback(NeteaseCrypt *handle, NcmWriteCallback write, void *user);
+
+/**
+ * Ask a `Dump` or `DumpToCallback` running on `handle` in another thread to
+ * stop. It returns `NCM_STATUS_CANCELLED` within one 32 KiB chunk, and
+ * `Dump` removes the partial file. A cancel only applies to the dump in
+ * progress: if neither is running, it has no effect.
+ *
+ * # Safety
+ * `handle` must be a valid pointer from `CreateNeteaseCrypt` that is not
+ * destroyed before this returns.
+ */
+void CancelDump(NeteaseCrypt *handle);
+
+/**
+ * # Safety
+ * `handle` must be a valid pointer from `CreateNeteaseCrypt`.
+ */
+void FixMetadata(NeteaseCrypt *handle);
+
+/**
+ * The file written by the last successful `Dump` on `handle`, or null if
+ * it has not succeeded yet. The string belongs to the handle and stays valid
+ * until the next `Dump` on it or `DestroyNeteaseCrypt`.
+ *
+ * # Safety
+ * `handle` must be a valid pointer from `CreateNeteaseCrypt`.
+ */
+const char *GetDumpPath(const NeteaseCrypt *handle);
+
+/**
+ * The audio format inside the NCM file, and so of the file `Dump` writes:
+ * `"mp3"`, `"flac"`, `"m4a"` or `"ogg"`. The string is static; do not free it.
+ *
+ * # Safety
+ * `handle` must be a valid pointer from `CreateNeteaseCrypt`.
+ */
+const char *GetFormat(const NeteaseCrypt *handle);
+
+/**
+ * # Safety
+ * `handle` must be a valid pointer from `CreateNeteaseCrypt`, or null.
+ */
+void DestroyNeteaseCrypt(NeteaseCrypt *handle);
+
+/**
+ * Convert `count` NCM files on an internal thread pool, one thread per CPU,
+ * writing each into `out_dir` (or next to its input when null) with tags
+ * and cover. Blocks until every file is done.
+ *
+ * The callbacks may be null. They run on the worker threads, but never two
+ * at once, so the host needs no locking of its own.
+ *
+ * Returns `NCM_STATUS_OK` if every file converted, `NCM_STATUS_ERROR`
+ * otherwise; use `result` to tell which failed.
+ *
+ * # Safety
+ * `paths` must point to `count` valid null-terminated C strings, and
+ * `out_dir` must be one or null. The callbacks must be safe to call from any
+ * thread with `user`.
+ */
+NcmStatus DumpMany(const char *const *paths,
+                   size_t count,
+                   const char *out_dir,
+                   NcmProgressCallback progress,
+                   NcmResultCallback result,
+                   void *user);
+
+/**
+ * The `NCM_ABI_VERSION` this library was built with. Compare it against the
+ * one in the header the host was compiled with.
+ */
+uint32_t NcmAbiVersion(void);
+
+/**
+ * Whether this build supports `feature`: `"buffer_api"`, `"cancel"`,
+ * `"batch"`, `"progress"`, `"stream"`, `"log"`, `"getters"`, `"wide_path"` on
+ * Windows, or `"netease"` when built with the Netease API client. Unknown names yield `false`.
+ *
+ * # Safety
+ * `feature` must be a valid null-terminated C string, or null.
+ */
+bool NcmHasFeature(const char *feature);
+
+/**
+ * Send log messages at `level` or more severe to `callback`, replacing any
+ * previous registration. A null `callback` or `NCM_LOG_LEVEL_OFF` turns
+ * logging off.
+ *
+ * The callback may run on any thread, including several at once (e.g.
+ * during `DumpMany`), and may itself call `SetLogCallback`.
+ *
+ * # Safety
+ * `callback` must be safe to call from any thread with `user` until it is
+ * replaced by another `SetLogCallback` call and calls already under way on
+ * other threads have returned.
+ */
+void SetLogCallback(NcmLogLevel level, NcmLogCallback callback, void *user);
+
+#if defined(NCMDUMP_NETEASE)
+/**
+ * Create an API client. With a null `music_u` it uses the session saved by
+ * `ncmdump-cli login`, if any. `proxy` is an optional `http://`, `https://`
+ * or `socks5://` URL. Returns null on failure.
+ *
+ * # Safety
+ * `music_u` and `proxy` must each be a valid null-terminated C string or
+ * null.
+ */
+NeteaseClient *CreateNeteaseClient(const char *music_u, const char *proxy);
+#endif
+
+#if defined(NCMDUMP_NETEASE)
+/**
+ * # Safety
+ * `client` must be a valid pointer from `CreateNeteaseClient`, or null.
+ */
+void DestroyNeteaseClient(NeteaseClient *client);
+#endif
+
+#if defined(NCMDUMP_NETEASE)
+/**
+ * Whether the client has a `MUSIC_U` cookie. This does not check that the
+ * cookie is still valid; `NeteaseUserInfo` does.
+ *
+ * # Safety
+ * `client` must be a valid pointer from `CreateNeteaseClient`.
+ */
+bool NeteaseIsLoggedIn(const NeteaseClient *client);
+#endif
+
+#if defined(NCMDUMP_NETEASE)
+/**
+ * Profile of the logged-in user as JSON, or null if not logged in or the
+ * cookie has expired.
+ *
+ * # Safety
+ * `client` must be a valid pointer from `CreateNeteaseClient`.
+ */
+char *NeteaseUserInfo(const NeteaseClient *client);
+#endif
+
+#if defined(NCMDUMP_NETEASE)
+/**
+ * Search, returning the results as JSON.
+ *
+ * # Safety
+ * `client` must be a valid pointer from `CreateNeteaseClient`. `keyword`
+ * must be a valid null-terminated C string.
+ */
+char *NeteaseSearch(const NeteaseClient *client,
+                    const char *keyword,
+                    NeteaseSearchType kind,
+                    uint32_t limit,
+                    uint32_t offset);
+#endif
+
+#if defined(NCMDUMP_NETEASE)
+/**
+ * Temporary download URL of track `id`, or null if it is unavailable (e.g.
+ * VIP-only without VIP).
+ *
+ * # Safety
+ * `client` must be a valid pointer from `CreateNeteaseClient`.
+ */
+char *NeteaseTrackUrl(const NeteaseClient *client, uint64_t id, NeteaseQuality quality);
+#endif
+
+#if defined(NCMDUMP_NETEASE)
+/**
+ * Download track `id` to the file `dest`, calling `progress` (which may be
+ * null) as data arrives. Blocks until done.
+ *
+ * # Safety
+ * `client` must be a valid pointer from `CreateNeteaseClient`. `dest` must
+ * be a valid null-terminated C string. `progress` must be safe to call with
+ * `user`.
+ */
+NcmStatus NeteaseDownloadTrack(const NeteaseClient *client,
+                               uint64_t id,
+                               NeteaseQuality quality,
+                               const char *dest,
+                               NeteaseProgressCallback progress,
+                               void *user);
+#endif
+
+#if defined(NCMDUMP_NETEASE)
+/**
+ * Why the last `Netease*` call on this thread failed, or null if it
+ * succeeded. Valid until the next such call on this thread; do not free it.
+ */
+const char *NeteaseLastError(void);
+#endif
+
+#if defined(NCMDUMP_NETEASE)
+/**
+ * Free a string returned by a `Netease*` function.
+ *
+ * # Safety
+ * `s` must be a string returned by a `Netease*` function and not yet freed,
+ * or null.
+ */
+void NeteaseFreeString(char *s);
+#endif
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* NCMDUMP_H */
@@ -1,13 +1,86 @@
 #![allow(unsafe_code, private_interfaces, non_snake_case)]
 
-use std::ffi::{CStr, c_char, c_int};
+use std::ffi::{CStr, CString, c_char, c_void};
+use std::io::{Cursor, Read, Seek, Write};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, PoisonError};
 
 use ncmdump::{NcmFile, NcmMetadata};
 
-struct NeteaseCrypt {
+mod log;
+#[cfg(feature = "netease")]
+mod netease;
+
+/// Status returned by `Dump`.
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum NcmStatus {
+    /// Success.
+    Ok = 0,
+    /// Any failure: bad handle or path, unreadable input, unwritable output.
+    Error = 1,
+    /// Stopped by `CancelDump`; the partial output was removed.
+    Cancelled = 2,
+}
+
+/// Called by `DumpMany` after each file with the number of files finished so
+/// far and the total.
+pub type NcmProgressCallback = Option<extern "C" fn(done: usize, total: usize, user: *mut c_void)>;
+
+/// Called by `DumpMany` once per file with its index in `paths`, the input
+/// path, the outcome and the output path (null unless `status` is
+/// `NCM_STATUS_OK`). The strings are only valid during the call.
+pub type NcmResultCallback = Option<
+    extern "C" fn(
+        index: usize,
+        input: *const c_char,
+        status: NcmStatus,
+        output: *const c_char,
+        user: *mut c_void,
+    ),
+>;
+
+/// Receives the decrypted audio from `DumpToCallback`, one chunk (at most
+/// 32 KiB) per call. `data` is only valid during the call.
+pub type NcmWriteCallback = Option<extern "C" fn(data: *const u8, len: usize, user: *mut c_void)>;
+
+/// File name stem for buffer handles whose metadata yields no name.
+const BUFFER_STEM: &str = "ncmdump";
+
+/// Where a handle reads the NCM data from.
+enum Source {
+    Path(PathBuf),
+    /// A copy of the bytes passed to `CreateNeteaseCryptFromBuffer`.
+    Buffer(Vec<u8>),
+}
+
+impl Source {
+    /// Open the NCM data for reading from the start.
+    fn open(&self) -> std::io::Result<Box<dyn ReadSeek + '_>> {
+        Ok(match self {
+            Self::Path(path) => Box::new(std::fs::File::open(path)?),
+            Self::Buffer(bytes) => Box::new(Cursor::new(bytes.as_slice())),
+        })
+    }
+}
+
+trait ReadSeek: Read + Seek {}
+impl<T: Read + Seek> ReadSeek for T {}
+
+/// The file written by the last successful `Dump`.
+struct Output {
     path: PathBuf,
-    dump_path: Option<PathBuf>,
+    /// `path` as handed out by `GetDumpPath`.
+    c_path: CString,
+}
+
+/// Shared between the thread running `Dump` and one calling `CancelDump`, so
+/// everything `Dump` changes is behind a lock or atomic.
+struct NeteaseCrypt {
+    source: Source,
+    output: Mutex<Option<Output>>,
+    cancel: AtomicBool,
     metadata: Option<NcmMetadata>,
     cover: Option<Vec<u8>>,
     key_box: [u8; 256],
@@ -15,75 +88,338 @@ struct NeteaseCrypt {
     format: ncmdump::AudioFormat,
 }
 
+/// Open the NCM file at `path`. Returns null if `path` is null, or the file
+/// can't be read as NCM.
+///
 /// # Safety
-/// `path` must be a valid null-terminated C string.
+/// `path` must be a valid null-terminated C string, or null.
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn CreateNeteaseCrypt(path: *const c_char) -> *mut NeteaseCrypt {
     std::panic::catch_unwind(|| {
-        let c_str = unsafe { CStr::from_ptr(path) };
-        let Ok(path_str) = c_str.to_str() else {
+        if path.is_null() {
             return std::ptr::null_mut();
-        };
-        let p = Path::new(path_str);
-        let Ok(mut file) = std::fs::File::open(p) else {
+        }
+        let Some(path) = c_path(unsafe { CStr::from_ptr(path) }) else {
+            tracing::warn!("path is not valid UTF-8");
             return std::ptr::null_mut();
         };
-        let Ok(ncm) = NcmFile::parse(&mut file) else {
+        open_path(path)
+    })
+    .unwrap_or(std::ptr::null_mut())
+}
+
+fn open_path(path: PathBuf) -> *mut NeteaseCrypt {
+    let Ok(mut file) = std::fs::File::open(&path).inspect_err(
+        |e| tracing::warn!(path = %path.display(), error = %e, "cannot open NCM file"),
+    ) else {
+        return std::ptr::null_mut();
+    };
+    let Ok(ncm) = NcmFile::parse(&mut file).inspect_err(
+        |e| tracing::warn!(path = %path.display(), error = %e, "not a valid NCM file"),
+    ) else {
+        return std::ptr::null_mut();
+    };
+    into_handle(Source::Path(path), ncm)
+}
+
+/// Create a handle from NCM data already in memory, e.g. bytes read from an
+/// Android content provider. The data is copied, so the caller may free it
+/// as soon as this returns.
+///
+/// `Dump` on such a handle needs an explicit `output_path` directory and
+/// names the file `<artist> - <title>` from the NCM metadata.
+///
+/// # Safety
+/// `data` must point to `len` readable bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn CreateNeteaseCryptFromBuffer(
+    data: *const u8,
+    len: usize,
+) -> *mut NeteaseCrypt {
+    std::panic::catch_unwind(|| {
+        if data.is_null() {
+            tracing::warn!("null buffer");
+            return std::ptr::null_mut();
+        }
+        let bytes = unsafe { std::slice::from_raw_parts(data, len) }.to_vec();
+        let Ok(ncm) = NcmFile::parse(&mut Cursor::new(&bytes))
+            .inspect_err(|e| tracing::warn!(len, error = %e, "buffer is not a valid NCM file"))
+        else {
             return std::ptr::null_mut();
         };
-        let handle = Box::new(NeteaseCrypt {
-            path: p.to_path_buf(),
-            dump_path: None,
-            metadata: ncm.metadata,
-            cover: ncm.cover_image,
-            key_box: ncm.key_box,
-            audio_offset: ncm.audio_offset,
-            format: ncm.format,
-        });
-        Box::into_raw(handle)
+        into_handle(Source::Buffer(bytes), ncm)
     })
     .unwrap_or(std::ptr::null_mut())
 }
 
+fn into_handle(source: Source, ncm: NcmFile) -> *mut NeteaseCrypt {
+    Box::into_raw(Box::new(NeteaseCrypt {
+        source,
+        output: Mutex::new(None),
+        cancel: AtomicBool::new(false),
+        metadata: ncm.metadata,
+        cover: ncm.cover_image,
+        key_box: ncm.key_box,
+        audio_offset: ncm.audio_offset,
+        format: ncm.format,
+    }))
+}
+
 /// # Safety
 /// `handle` must be a valid pointer from `CreateNeteaseCrypt`.
 /// `output_path` must be a valid null-terminated C string.
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn Dump(handle: *mut NeteaseCrypt, output_path: *const c_char) -> c_int {
+pub unsafe extern "C" fn Dump(handle: *mut NeteaseCrypt, output_path: *const c_char) -> NcmStatus {
     std::panic::catch_unwind(|| {
         if handle.is_null() {
-            return 1;
+            return NcmStatus::Error;
         }
-        let nc = unsafe { &mut *handle };
         let out_dir = if output_path.is_null() {
-            nc.path.parent().unwrap_or(Path::new(".")).to_path_buf()
+            None
         } else {
-            let c_str = unsafe { CStr::from_ptr(output_path) };
-            let Ok(s) = c_str.to_str() else { return 1 };
-            PathBuf::from(s)
+            let Some(path) = c_path(unsafe { CStr::from_ptr(output_path) }) else {
+                tracing::warn!("output path is not valid UTF-8");
+                return NcmStatus::Error;
+            };
+            Some(path)
         };
+        dump(unsafe { &*handle }, out_dir)
+    })
+    .unwrap_or(NcmStatus::Error)
+}
 
-        let stem = nc.path.file_stem().unwrap_or_default();
-        let ext = nc.format.extension();
-        let dump_path = out_dir.join(format!("{}.{ext}", stem.to_string_lossy()));
+/// Decrypt the audio of `nc` into `out_dir`, or next to the input when
+/// `None`.
+fn dump(nc: &NeteaseCrypt, out_dir: Option<PathBuf>) -> NcmStatus {
+    // Drops a `CancelDump` that came while no dump was running.
+    nc.cancel.store(false, Ordering::Relaxed);
+    *nc.output.lock().unwrap_or_else(PoisonError::into_inner) = None;
+    let out_dir = match (out_dir, &nc.source) {
+        (Some(dir), _) => dir,
+        (None, Source::Path(path)) => path.parent().unwrap_or(Path::new(".")).to_path_buf(),
+        (None, Source::Buffer(_)) => {
+            tracing::warn!("a handle created from a buffer needs an output directory");
+            return NcmStatus::Error;
+        }
+    };
 
-        let Ok(mut infile) = std::fs::File::open(&nc.path) else {
-            return 1;
-        };
+    let ext = nc.format.extension();
+    let name = match &nc.source {
+        Source::Path(path) => {
+            ncmdump::path::with_extension(path.file_stem().unwrap_or_default(), ext)
+        }
+        Source::Buffer(_) => {
+            let stem = nc
+                .metadata
+                .as_ref()
+                .and_then(|m| {
+                    ncmdump::template::render(ncmdump::template::DEFAULT_TEMPLATE, |f| {
+                        m.template_field(f)
+                    })
+                    .ok()
+                })
+                .unwrap_or_else(|| BUFFER_STEM.into());
+            ncmdump::path::with_extension(std::ffi::OsStr::new(&stem), ext)
+        }
+    };
+    let dump_path = out_dir.join(name);
 
-        let ncm = NcmFile::from_parts(nc.key_box, nc.audio_offset);
+    let Ok(mut infile) = nc
+        .source
+        .open()
+        .inspect_err(|e| tracing::warn!(error = %e, "cannot reopen NCM file"))
+    else {
+        return NcmStatus::Error;
+    };
+
+    let ncm = NcmFile::from_parts(nc.key_box, nc.audio_offset);
+
+    let Ok(outfile) = std::fs::File::create(&dump_path).inspect_err(|e| {
+        tracing::warn!(path = %dump_path.display(), error = %e, "cannot create output file");
+    }) else {
+        return NcmStatus::Error;
+    };
+    let mut writer = std::io::BufWriter::new(outfile);
+    match ncm
+        .dump_audio_cancellable(&mut infile, &mut writer, &nc.cancel)
+        .and_then(|()| Ok(writer.flush()?))
+    {
+        Ok(()) => {}
+        Err(ncmdump::NcmError::Cancelled) => {
+            drop(writer);
+            let _ = std::fs::remove_file(&dump_path);
+            tracing::info!(path = %dump_path.display(), "dump cancelled");
+            return NcmStatus::Cancelled;
+        }
+        Err(e) => {
+            tracing::warn!(path = %dump_path.display(), error = %e, "dump failed");
+            return NcmStatus::Error;
+        }
+    }
+    tracing::debug!(path = %dump_path.display(), "dumped");
+    let c_path = path_c_string(&dump_path);
+    *nc.output.lock().unwrap_or_else(PoisonError::into_inner) = Some(Output {
+        path: dump_path,
+        c_path,
+    });
+    NcmStatus::Ok
+}
+
+/// Path from a C string: its bytes as they are on Unix, where file names
+/// need not be UTF-8, and UTF-8 elsewhere (see the `W` functions on Windows).
+#[cfg_attr(unix, allow(clippy::unnecessary_wraps))]
+fn c_path(s: &CStr) -> Option<PathBuf> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        Some(PathBuf::from(std::ffi::OsStr::from_bytes(s.to_bytes())))
+    }
+    #[cfg(not(unix))]
+    {
+        s.to_str().ok().map(PathBuf::from)
+    }
+}
+
+/// `path` as a C string, byte for byte on Unix.
+fn path_c_string(path: &Path) -> CString {
+    #[cfg(unix)]
+    let bytes = std::os::unix::ffi::OsStrExt::as_bytes(path.as_os_str()).to_vec();
+    #[cfg(not(unix))]
+    let bytes = path.to_string_lossy().into_owned().into_bytes();
+    CString::new(bytes).unwrap_or_default()
+}
+
+/// A UTF-16 code unit, as `wchar_t` is on Windows.
+#[cfg(windows)]
+#[allow(non_camel_case_types)]
+pub type wchar_t = u16;
 
-        let Ok(outfile) = std::fs::File::create(&dump_path) else {
-            return 1;
+/// Path from a null-terminated UTF-16 string; unpaired surrogates survive.
+#[cfg(windows)]
+unsafe fn wide_path(s: *const wchar_t) -> PathBuf {
+    use std::os::windows::ffi::OsStringExt;
+
+    let len = (0..).take_while(|&i| unsafe { *s.add(i) } != 0).count();
+    let wide = unsafe { std::slice::from_raw_parts(s, len) };
+    PathBuf::from(std::ffi::OsString::from_wide(wide))
+}
+
+/// `CreateNeteaseCrypt` for a UTF-16 path, so Windows hosts can open any
+/// file name regardless of the ANSI code page.
+///
+/// # Safety
+/// `path` must be a valid null-terminated wide string, or null.
+#[cfg(windows)]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn CreateNeteaseCryptW(path: *const wchar_t) -> *mut NeteaseCrypt {
+    std::panic::catch_unwind(|| {
+        if path.is_null() {
+            return std::ptr::null_mut();
+        }
+        open_path(unsafe { wide_path(path) })
+    })
+    .unwrap_or(std::ptr::null_mut())
+}
+
+/// `Dump` with a UTF-16 output directory (or null).
+///
+/// # Safety
+/// `handle` must be a valid pointer from `CreateNeteaseCrypt`.
+/// `output_path` must be a valid null-terminated wide string, or null.
+#[cfg(windows)]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn DumpW(
+    handle: *mut NeteaseCrypt,
+    output_path: *const wchar_t,
+) -> NcmStatus {
+    std::panic::catch_unwind(|| {
+        if handle.is_null() {
+            return NcmStatus::Error;
+        }
+        let out_dir = (!output_path.is_null()).then(|| unsafe { wide_path(output_path) });
+        dump(unsafe { &*handle }, out_dir)
+    })
+    .unwrap_or(NcmStatus::Error)
+}
+
+/// Like `Dump`, but hands the decrypted audio to `write` in order instead of
+/// writing a file, e.g. to feed a player or encrypted storage. No tags are
+/// added, and `FixMetadata` does not apply. `CancelDump` stops it as well.
+///
+/// # Safety
+/// `handle` must be a valid pointer from `CreateNeteaseCrypt`. `write` must
+/// be safe to call with `user`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn DumpToCallback(
+    handle: *mut NeteaseCrypt,
+    write: NcmWriteCallback,
+    user: *mut c_void,
+) -> NcmStatus {
+    std::panic::catch_unwind(|| {
+        let Some(write) = write else {
+            return NcmStatus::Error;
+        };
+        if handle.is_null() {
+            return NcmStatus::Error;
+        }
+        let nc = unsafe { &*handle };
+        // Drops a `CancelDump` that came while no dump was running.
+        nc.cancel.store(false, Ordering::Relaxed);
+        let Ok(mut infile) = nc
+            .source
+            .open()
+            .inspect_err(|e| tracing::warn!(error = %e, "cannot reopen NCM file"))
+        else {
+            return NcmStatus::Error;
         };
-        let mut writer = std::io::BufWriter::new(outfile);
-        if ncm.dump_audio(&mut infile, &mut writer).is_err() {
-            return 1;
+        let ncm = NcmFile::from_parts(nc.key_box, nc.audio_offset);
+        let mut writer = CallbackWriter { write, user };
+        match ncm.dump_audio_cancellable(&mut infile, &mut writer, &nc.cancel) {
+            Ok(()) => NcmStatus::Ok,
+            Err(ncmdump::NcmError::Cancelled) => {
+                tracing::info!("dump cancelled");
+                NcmStatus::Cancelled
+            }
+            Err(e) => {
+                tracing::warn!(error = %e, "dump failed");
+                NcmStatus::Error
+            }
         }
-        nc.dump_path = Some(dump_path);
-        0
     })
-    .unwrap_or(1)
+    .unwrap_or(NcmStatus::Error)
+}
+
+/// Adapts an `NcmWriteCallback` to [`Write`].
+struct CallbackWriter {
+    write: extern "C" fn(*const u8, usize, *mut c_void),
+    user: *mut c_void,
+}
+
+impl Write for CallbackWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        (self.write)(buf.as_ptr(), buf.len(), self.user);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Ask a `Dump` or `DumpToCallback` running on `handle` in another thread to
+/// stop. It returns `NCM_STATUS_CANCELLED` within one 32 KiB chunk, and
+/// `Dump` removes the partial file. A cancel only applies to the dump in
+/// progress: if neither is running, it has no effect.
+///
+/// # Safety
+/// `handle` must be a valid pointer from `CreateNeteaseCrypt` that is not
+/// destroyed before this returns.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn CancelDump(handle: *mut NeteaseCrypt) {
+    if !handle.is_null() {
+        let nc = unsafe { &*handle };
+        nc.cancel.store(true, Ordering::Relaxed);
+    }
 }
 
 /// # Safety
@@ -95,16 +431,56 @@ pub unsafe extern "C" fn FixMetadata(handle: *mut NeteaseCrypt) {
             return;
         }
         let nc = unsafe { &*handle };
-        let Some(dump_path) = &nc.dump_path else {
+        let output = nc.output.lock().unwrap_or_else(PoisonError::into_inner);
+        let Some(dump_path) = output.as_ref().map(|o| &o.path) else {
             return;
         };
         let Some(meta) = &nc.metadata else {
             return;
         };
-        let _ = ncmdump::tag_write(dump_path, meta, nc.cover.as_deref());
+        if let Err(e) = ncmdump::tag_write(dump_path, meta, nc.cover.as_deref()) {
+            tracing::warn!(path = %dump_path.display(), error = %e, "cannot write tags");
+        }
     });
 }
 
+/// The file written by the last successful `Dump` on `handle`, or null if
+/// it has not succeeded yet. The string belongs to the handle and stays valid
+/// until the next `Dump` on it or `DestroyNeteaseCrypt`.
+///
+/// # Safety
+/// `handle` must be a valid pointer from `CreateNeteaseCrypt`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn GetDumpPath(handle: *const NeteaseCrypt) -> *const c_char {
+    if handle.is_null() {
+        return std::ptr::null();
+    }
+    let nc = unsafe { &*handle };
+    let output = nc.output.lock().unwrap_or_else(PoisonError::into_inner);
+    output
+        .as_ref()
+        .map_or(std::ptr::null(), |o| o.c_path.as_ptr())
+}
+
+/// The audio format inside the NCM file, and so of the file `Dump` writes:
+/// `"mp3"`, `"flac"`, `"m4a"` or `"ogg"`. The string is static; do not free it.
+///
+/// # Safety
+/// `handle` must be a valid pointer from `CreateNeteaseCrypt`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn GetFormat(handle: *const NeteaseCrypt) -> *const c_char {
+    if handle.is_null() {
+        return std::ptr::null();
+    }
+    let nc = unsafe { &*handle };
+    match nc.format {
+        ncmdump::AudioFormat::Mp3 => c"mp3".as_ptr(),
+        ncmdump::AudioFormat::Flac => c"flac".as_ptr(),
+        ncmdump::AudioFormat::M4a => c"m4a".as_ptr(),
+        ncmdump::AudioFormat::Ogg => c"ogg".as_ptr(),
+    }
+}
+
 /// # Safety
 /// `handle` must be a valid pointer from `CreateNeteaseCrypt`, or null.
 #[unsafe(no_mangle)]
@@ -115,3 +491,151 @@ pub unsafe extern "C" fn DestroyNeteaseCrypt(handle: *mut NeteaseCrypt) {
         });
     }
 }
+
+/// The host's `user` pointer, handed to callbacks on the worker threads.
+struct UserData(*mut c_void);
+
+// SAFETY: the pointer is only passed back to the host, which promised in
+// `DumpMany`'s contract that its callbacks may run on any thread.
+unsafe impl Send for UserData {}
+unsafe impl Sync for UserData {}
+
+impl UserData {
+    // A method rather than `.0`, so closures capture the whole `Sync`
+    // wrapper instead of the bare pointer.
+    fn get(&self) -> *mut c_void {
+        self.0
+    }
+}
+
+/// Convert `count` NCM files on an internal thread pool, one thread per CPU,
+/// writing each into `out_dir` (or next to its input when null) with tags
+/// and cover. Blocks until every file is done.
+///
+/// The callbacks may be null. They run on the worker threads, but never two
+/// at once, so the host needs no locking of its own.
+///
+/// Returns `NCM_STATUS_OK` if every file converted, `NCM_STATUS_ERROR`
+/// otherwise; use `result` to tell which failed.
+///
+/// # Safety
+/// `paths` must point to `count` valid null-terminated C strings, and
+/// `out_dir` must be one or null. The callbacks must be safe to call from any
+/// thread with `user`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn DumpMany(
+    paths: *const *const c_char,
+    count: usize,
+    out_dir: *const c_char,
+    progress: NcmProgressCallback,
+    result: NcmResultCallback,
+    user: *mut c_void,
+) -> NcmStatus {
+    std::panic::catch_unwind(|| {
+        if paths.is_null() && count > 0 {
+            return NcmStatus::Error;
+        }
+        let out_dir = if out_dir.is_null() {
+            None
+        } else {
+            match c_path(unsafe { CStr::from_ptr(out_dir) }) {
+                Some(path) => Some(path),
+                None => return NcmStatus::Error,
+            }
+        };
+        let inputs: Vec<&CStr> = (0..count)
+            .map(|i| unsafe { CStr::from_ptr(*paths.add(i)) })
+            .collect();
+        // A path that can't be represented fails to open like a missing file.
+        let paths: Vec<PathBuf> = inputs
+            .iter()
+            .map(|input| {
+                c_path(input).unwrap_or_else(|| {
+                    tracing::warn!("path is not valid UTF-8");
+                    PathBuf::new()
+                })
+            })
+            .collect();
+
+        let user = UserData(user);
+        let mut done = 0;
+        let mut failed = false;
+        let opts = ncmdump::BatchOptions {
+            output_dir: out_dir,
+            ..ncmdump::BatchOptions::default()
+        };
+        ncmdump::convert_many_with(&paths, &opts, |i, converted| {
+            let output = converted.as_ref().ok().map(|p| path_c_string(p));
+            let status = if output.is_some() {
+                NcmStatus::Ok
+            } else {
+                failed = true;
+                NcmStatus::Error
+            };
+            done += 1;
+            if let Some(cb) = result {
+                let out_ptr = output.as_ref().map_or(std::ptr::null(), |c| c.as_ptr());
+                cb(i, inputs[i].as_ptr(), status, out_ptr, user.get());
+            }
+            if let Some(cb) = progress {
+                cb(done, count, user.get());
+            }
+        });
+
+        if failed {
+            NcmStatus::Error
+        } else {
+            NcmStatus::Ok
+        }
+    })
+    .unwrap_or(NcmStatus::Error)
+}
+
+/// Version of the exported C ABI. Bumped whenever an entry point is added or
+/// changed, so hosts that load the library at runtime can require a minimum.
+pub const NCM_ABI_VERSION: u32 = 4;
+
+/// Optional capabilities reported by `NcmHasFeature`.
+const FEATURES: &[&str] = &[
+    // `CreateNeteaseCryptFromBuffer`
+    "buffer_api",
+    // `CancelDump`
+    "cancel",
+    // `DumpMany`
+    "batch",
+    // `DumpMany`'s progress callback
+    "progress",
+    // `DumpToCallback`
+    "stream",
+    // `SetLogCallback`
+    "log",
+    // `GetDumpPath` and `GetFormat`
+    "getters",
+    // `CreateNeteaseCryptW` and `DumpW`
+    #[cfg(windows)]
+    "wide_path",
+    #[cfg(feature = "netease")]
+    "netease",
+];
+
+/// The `NCM_ABI_VERSION` this library was built with. Compare it against the
+/// one in the header the host was compiled with.
+#[unsafe(no_mangle)]
+pub extern "C" fn NcmAbiVersion() -> u32 {
+    NCM_ABI_VERSION
+}
+
+/// Whether this build supports `feature`: `"buffer_api"`, `"cancel"`,
+/// `"batch"`, `"progress"`, `"stream"`, `"log"`, `"getters"`, `"wide_path"` on
+/// Windows, or `"netease"` when built with the Netease API client. Unknown names yield `false`.
+///
+/// # Safety
+/// `feature` must be a valid null-terminated C string, or null.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn NcmHasFeature(feature: *const c_char) -> bool {
+    if feature.is_null() {
+        return false;
+    }
+    let feature = unsafe { CStr::from_ptr(feature) };
+    feature.to_str().is_ok_and(|name| FEATURES.contains(&name))
+}
@@ -0,0 +1,150 @@
+//! Forwards `tracing` events to a callback registered by the host.
+//!
+//! A library inside a GUI process has no visible stderr, so the host hands us
+//! a function and a minimum level instead. A subscriber that reads the
+//! current registration is installed as the global default on first use; if
+//! the process already has one (a Rust host with its own subscriber), events
+//! go there instead.
+
+use std::ffi::{CString, c_char, c_void};
+use std::fmt::Write as _;
+use std::sync::{Once, PoisonError, RwLock};
+
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::prelude::*;
+
+/// Severity of a log message, most severe first.
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum NcmLogLevel {
+    /// Logging disabled; only valid as `SetLogCallback`'s `level`.
+    Off = 0,
+    Error = 1,
+    Warn = 2,
+    Info = 3,
+    Debug = 4,
+    Trace = 5,
+}
+
+impl From<&Level> for NcmLogLevel {
+    fn from(level: &Level) -> Self {
+        match *level {
+            Level::ERROR => Self::Error,
+            Level::WARN => Self::Warn,
+            Level::INFO => Self::Info,
+            Level::DEBUG => Self::Debug,
+            Level::TRACE => Self::Trace,
+        }
+    }
+}
+
+/// Receives one log message: its level, the module it came from (e.g.
+/// `ncmdump_ffi`) and the text with any fields appended as `key=value`. The
+/// strings are only valid during the call.
+pub type NcmLogCallback = Option<
+    extern "C" fn(
+        level: NcmLogLevel,
+        target: *const c_char,
+        message: *const c_char,
+        user: *mut c_void,
+    ),
+>;
+
+#[derive(Clone, Copy)]
+struct Sink {
+    level: NcmLogLevel,
+    callback: extern "C" fn(NcmLogLevel, *const c_char, *const c_char, *mut c_void),
+    user: *mut c_void,
+}
+
+// SAFETY: the pointer is only passed back to the host, which promised in
+// `SetLogCallback`'s contract that the callback may run on any thread.
+unsafe impl Send for Sink {}
+unsafe impl Sync for Sink {}
+
+static SINK: RwLock<Option<Sink>> = RwLock::new(None);
+static INSTALL: Once = Once::new();
+
+struct CallbackLayer;
+
+impl<S: Subscriber> Layer<S> for CallbackLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        // Copied out so the callback runs without the lock held: it may
+        // call `SetLogCallback` itself.
+        let Some(sink) = *SINK.read().unwrap_or_else(PoisonError::into_inner) else {
+            return;
+        };
+        let level = NcmLogLevel::from(event.metadata().level());
+        if level > sink.level {
+            return;
+        }
+        let mut message = Message::default();
+        event.record(&mut message);
+        let (Ok(target), Ok(text)) = (
+            CString::new(event.metadata().target()),
+            CString::new(message.0.replace('\0', " ")),
+        ) else {
+            return;
+        };
+        (sink.callback)(level, target.as_ptr(), text.as_ptr(), sink.user);
+    }
+}
+
+/// The `message` of an event followed by its other fields.
+#[derive(Default)]
+struct Message(String);
+
+impl Visit for Message {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            let fields = std::mem::take(&mut self.0);
+            let _ = write!(self.0, "{value:?}{fields}");
+        } else {
+            let _ = write!(self.0, " {}={value:?}", field.name());
+        }
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.0.insert_str(0, value);
+        } else {
+            let _ = write!(self.0, " {}={value}", field.name());
+        }
+    }
+}
+
+/// Send log messages at `level` or more severe to `callback`, replacing any
+/// previous registration. A null `callback` or `NCM_LOG_LEVEL_OFF` turns
+/// logging off.
+///
+/// The callback may run on any thread, including several at once (e.g.
+/// during `DumpMany`), and may itself call `SetLogCallback`.
+///
+/// # Safety
+/// `callback` must be safe to call from any thread with `user` until it is
+/// replaced by another `SetLogCallback` call and calls already under way on
+/// other threads have returned.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn SetLogCallback(
+    level: NcmLogLevel,
+    callback: NcmLogCallback,
+    user: *mut c_void,
+) {
+    let _ = std::panic::catch_unwind(|| {
+        INSTALL.call_once(|| {
+            let _ = tracing::subscriber::set_global_default(
+                tracing_subscriber::registry().with(CallbackLayer),
+            );
+        });
+        let sink = callback
+            .filter(|_| level != NcmLogLevel::Off)
+            .map(|callback| Sink {
+                level,
+                callback,
+                user,
+            });
+        *SINK.write().unwrap_or_else(PoisonError::into_inner) = sink;
+    });
+}
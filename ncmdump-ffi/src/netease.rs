@@ -0,0 +1,301 @@
+//! C ABI for the Netease Cloud Music API client (`netease` feature).
+//!
+//! Lists and objects come back as JSON strings shaped like the `serde` form
+//! of the `netease_api` types; free them with `NeteaseFreeString`. Failing
+//! calls return null or `NCM_STATUS_ERROR`, and `NeteaseLastError` says why.
+
+use std::cell::RefCell;
+use std::ffi::{CStr, CString, c_char, c_void};
+use std::path::Path;
+
+use netease_api::auth::Session;
+use netease_api::types::{Quality, SearchType};
+
+use crate::NcmStatus;
+
+/// What `NeteaseSearch` looks for.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub enum NeteaseSearchType {
+    Track = 0,
+    Album = 1,
+    Artist = 2,
+    Playlist = 3,
+}
+
+/// Quality tier for `NeteaseTrackUrl` and `NeteaseDownloadTrack`. The server
+/// falls back to the best tier the account can get.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub enum NeteaseQuality {
+    /// 128 kbps MP3.
+    Standard = 0,
+    /// 192 kbps MP3.
+    Higher = 1,
+    /// 320 kbps MP3.
+    Exhigh = 2,
+    /// FLAC. Requires VIP.
+    Lossless = 3,
+    /// 24-bit FLAC. Requires VIP.
+    Hires = 4,
+    /// Immersive surround sound. Requires SVIP.
+    Sky = 5,
+}
+
+/// Called by `NeteaseDownloadTrack` after every chunk with the bytes written
+/// so far and the total size (`0` if the server did not send one).
+pub type NeteaseProgressCallback =
+    Option<extern "C" fn(written: u64, total: u64, user: *mut c_void)>;
+
+/// An API client with its session and connection pool. One client may be
+/// used from several threads at once.
+struct NeteaseClient(netease_api::NeteaseClient);
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_error(message: &str) {
+    tracing::warn!(error = message, "Netease API call failed");
+    let message = CString::new(message.replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|e| *e.borrow_mut() = Some(message));
+}
+
+fn clear_error() {
+    LAST_ERROR.with(|e| *e.borrow_mut() = None);
+}
+
+/// Run `f` with the client behind `client`, recording any error (or panic)
+/// for `NeteaseLastError`.
+fn with_client<T>(
+    client: *const NeteaseClient,
+    f: impl FnOnce(&netease_api::NeteaseClient) -> Result<T, String>,
+) -> Option<T> {
+    clear_error();
+    if client.is_null() {
+        set_error("null client");
+        return None;
+    }
+    let client = unsafe { &(*client).0 };
+    match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| f(client))) {
+        Ok(Ok(value)) => Some(value),
+        Ok(Err(message)) => {
+            set_error(&message);
+            None
+        }
+        Err(_) => {
+            set_error("internal panic");
+            None
+        }
+    }
+}
+
+/// An optional C string argument; null means absent.
+fn opt_str(s: *const c_char) -> Result<Option<String>, String> {
+    if s.is_null() {
+        return Ok(None);
+    }
+    let s = unsafe { CStr::from_ptr(s) };
+    s.to_str()
+        .map(|s| Some(s.to_owned()))
+        .map_err(|_| "string is not valid UTF-8".to_owned())
+}
+
+fn req_str(s: *const c_char, name: &str) -> Result<String, String> {
+    opt_str(s)?.ok_or_else(|| format!("{name} is null"))
+}
+
+fn to_json<T: serde::Serialize>(value: &T) -> Result<*mut c_char, String> {
+    let json = serde_json::to_string(value).map_err(|e| e.to_string())?;
+    into_c_string(json)
+}
+
+fn into_c_string(s: String) -> Result<*mut c_char, String> {
+    CString::new(s)
+        .map(CString::into_raw)
+        .map_err(|e| e.to_string())
+}
+
+impl From<NeteaseQuality> for Quality {
+    fn from(q: NeteaseQuality) -> Self {
+        match q {
+            NeteaseQuality::Standard => Self::Standard,
+            NeteaseQuality::Higher => Self::Higher,
+            NeteaseQuality::Exhigh => Self::Exhigh,
+            NeteaseQuality::Lossless => Self::Lossless,
+            NeteaseQuality::Hires => Self::Hires,
+            NeteaseQuality::Sky => Self::Sky,
+        }
+    }
+}
+
+impl From<NeteaseSearchType> for SearchType {
+    fn from(t: NeteaseSearchType) -> Self {
+        match t {
+            NeteaseSearchType::Track => Self::Track,
+            NeteaseSearchType::Album => Self::Album,
+            NeteaseSearchType::Artist => Self::Artist,
+            NeteaseSearchType::Playlist => Self::Playlist,
+        }
+    }
+}
+
+/// Create an API client. With a null `music_u` it uses the session saved by
+/// `ncmdump-cli login`, if any. `proxy` is an optional `http://`, `https://`
+/// or `socks5://` URL. Returns null on failure.
+///
+/// # Safety
+/// `music_u` and `proxy` must each be a valid null-terminated C string or
+/// null.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn CreateNeteaseClient(
+    music_u: *const c_char,
+    proxy: *const c_char,
+) -> *mut NeteaseClient {
+    clear_error();
+    let result = std::panic::catch_unwind(|| {
+        let mut builder = netease_api::NeteaseClient::builder();
+        if let Some(music_u) = opt_str(music_u)? {
+            builder = builder.session(Session {
+                music_u: Some(music_u),
+            });
+        }
+        if let Some(proxy) = opt_str(proxy)? {
+            builder = builder.proxy(proxy);
+        }
+        builder.build().map_err(|e| e.to_string())
+    });
+    match result {
+        Ok(Ok(client)) => Box::into_raw(Box::new(NeteaseClient(client))),
+        Ok(Err(message)) => {
+            set_error(&message);
+            std::ptr::null_mut()
+        }
+        Err(_) => {
+            set_error("internal panic");
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// # Safety
+/// `client` must be a valid pointer from `CreateNeteaseClient`, or null.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn DestroyNeteaseClient(client: *mut NeteaseClient) {
+    if !client.is_null() {
+        let _ = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            drop(unsafe { Box::from_raw(client) });
+        }));
+    }
+}
+
+/// Whether the client has a `MUSIC_U` cookie. This does not check that the
+/// cookie is still valid; `NeteaseUserInfo` does.
+///
+/// # Safety
+/// `client` must be a valid pointer from `CreateNeteaseClient`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn NeteaseIsLoggedIn(client: *const NeteaseClient) -> bool {
+    with_client(client, |c| Ok(c.session().is_logged_in())).unwrap_or(false)
+}
+
+/// Profile of the logged-in user as JSON, or null if not logged in or the
+/// cookie has expired.
+///
+/// # Safety
+/// `client` must be a valid pointer from `CreateNeteaseClient`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn NeteaseUserInfo(client: *const NeteaseClient) -> *mut c_char {
+    with_client(client, |c| {
+        to_json(&c.user_info().map_err(|e| e.to_string())?)
+    })
+    .unwrap_or(std::ptr::null_mut())
+}
+
+/// Search, returning the results as JSON.
+///
+/// # Safety
+/// `client` must be a valid pointer from `CreateNeteaseClient`. `keyword`
+/// must be a valid null-terminated C string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn NeteaseSearch(
+    client: *const NeteaseClient,
+    keyword: *const c_char,
+    kind: NeteaseSearchType,
+    limit: u32,
+    offset: u32,
+) -> *mut c_char {
+    with_client(client, |c| {
+        let keyword = req_str(keyword, "keyword")?;
+        let result = c
+            .search(&keyword, kind.into(), limit.into(), offset.into())
+            .map_err(|e| e.to_string())?;
+        to_json(&result)
+    })
+    .unwrap_or(std::ptr::null_mut())
+}
+
+/// Temporary download URL of track `id`, or null if it is unavailable (e.g.
+/// VIP-only without VIP).
+///
+/// # Safety
+/// `client` must be a valid pointer from `CreateNeteaseClient`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn NeteaseTrackUrl(
+    client: *const NeteaseClient,
+    id: u64,
+    quality: NeteaseQuality,
+) -> *mut c_char {
+    with_client(client, |c| {
+        into_c_string(c.track_url(id, quality.into()).map_err(|e| e.to_string())?)
+    })
+    .unwrap_or(std::ptr::null_mut())
+}
+
+/// Download track `id` to the file `dest`, calling `progress` (which may be
+/// null) as data arrives. Blocks until done.
+///
+/// # Safety
+/// `client` must be a valid pointer from `CreateNeteaseClient`. `dest` must
+/// be a valid null-terminated C string. `progress` must be safe to call with
+/// `user`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn NeteaseDownloadTrack(
+    client: *const NeteaseClient,
+    id: u64,
+    quality: NeteaseQuality,
+    dest: *const c_char,
+    progress: NeteaseProgressCallback,
+    user: *mut c_void,
+) -> NcmStatus {
+    with_client(client, |c| {
+        let dest = req_str(dest, "dest")?;
+        let url = c.track_url(id, quality.into()).map_err(|e| e.to_string())?;
+        c.download_with_progress(&url, Path::new(&dest), |written, total| {
+            if let Some(progress) = progress {
+                progress(written, total.unwrap_or(0), user);
+            }
+        })
+        .map_err(|e| e.to_string())
+    })
+    .map_or(NcmStatus::Error, |_| NcmStatus::Ok)
+}
+
+/// Why the last `Netease*` call on this thread failed, or null if it
+/// succeeded. Valid until the next such call on this thread; do not free it.
+#[unsafe(no_mangle)]
+pub extern "C" fn NeteaseLastError() -> *const c_char {
+    LAST_ERROR.with(|e| e.borrow().as_ref().map_or(std::ptr::null(), |s| s.as_ptr()))
+}
+
+/// Free a string returned by a `Netease*` function.
+///
+/// # Safety
+/// `s` must be a string returned by a `Netease*` function and not yet freed,
+/// or null.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn NeteaseFreeString(s: *mut c_char) {
+    if !s.is_null() {
+        drop(unsafe { CString::from_raw(s) });
+    }
+}
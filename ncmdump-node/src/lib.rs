@@ -0,0 +1,257 @@
+//! Node.js bindings (napi-rs), e.g. for Electron front-ends.
+//!
+//! Every function returns a `Promise`: the work runs on a blocking thread
+//! pool, so conversions and downloads never block the event loop. API results
+//! are plain objects shaped like the `serde` form of the Rust types.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use napi::bindgen_prelude::{Buffer, spawn_blocking};
+use napi::{Error, Result, Status};
+use napi_derive::napi;
+use netease_api::auth::Session;
+use netease_api::types::{Quality, SearchType};
+use serde_json::Value;
+
+fn ncm_err(e: &ncmdump::NcmError) -> Error {
+    Error::new(Status::GenericFailure, e.to_string())
+}
+
+fn api_err(e: &netease_api::NeteaseError) -> Error {
+    Error::new(Status::GenericFailure, e.to_string())
+}
+
+fn invalid(message: String) -> Error {
+    Error::new(Status::InvalidArg, message)
+}
+
+/// IDs arrive as JS numbers; every Netease ID fits in 2^53.
+fn to_id(id: i64) -> Result<u64> {
+    u64::try_from(id).map_err(|_| invalid(format!("invalid ID: {id}")))
+}
+
+fn parse_quality(quality: Option<&str>) -> Result<Quality> {
+    let quality = quality.unwrap_or("exhigh");
+    serde_json::from_value(Value::String(quality.to_owned()))
+        .map_err(|_| invalid(format!("unknown quality: {quality}")))
+}
+
+fn parse_search_type(kind: Option<&str>) -> Result<SearchType> {
+    Ok(match kind.unwrap_or("track") {
+        "track" => SearchType::Track,
+        "album" => SearchType::Album,
+        "artist" => SearchType::Artist,
+        "playlist" => SearchType::Playlist,
+        other => return Err(invalid(format!("unknown search type: {other}"))),
+    })
+}
+
+/// Run blocking `f` on tokio's blocking pool, off the JS thread.
+async fn blocking<T: Send + 'static>(f: impl FnOnce() -> Result<T> + Send + 'static) -> Result<T> {
+    spawn_blocking(f)
+        .await
+        .map_err(|e| Error::from_reason(e.to_string()))?
+}
+
+/// Header of an NCM file, as resolved by `inspect()`.
+#[napi(object)]
+pub struct NcmInfo {
+    /// `"mp3"`, `"flac"`, `"m4a"` or `"ogg"`.
+    pub format: String,
+    pub music_id: Option<i64>,
+    pub title: Option<String>,
+    pub artists: Vec<String>,
+    pub album: Option<String>,
+    pub duration_ms: Option<i64>,
+    pub bitrate: Option<i64>,
+    /// Embedded cover image.
+    pub cover: Option<Buffer>,
+}
+
+/// Convert an NCM file to MP3/FLAC with tags and cover, in `outputDir` or
+/// next to the input. Resolves with the output path.
+#[napi]
+pub async fn convert(input: String, output_dir: Option<String>) -> Result<String> {
+    blocking(move || {
+        let out = ncmdump::convert(Path::new(&input), output_dir.as_deref().map(Path::new))
+            .map_err(|e| ncm_err(&e))?;
+        Ok(out.to_string_lossy().into_owned())
+    })
+    .await
+}
+
+/// Read an NCM file's header without decrypting the audio.
+#[napi]
+pub async fn inspect(path: String) -> Result<NcmInfo> {
+    blocking(move || {
+        let ncm = ncmdump::inspect(Path::new(&path)).map_err(|e| ncm_err(&e))?;
+        let meta = ncm.metadata.as_ref();
+        let to_i64 = |v: u64| i64::try_from(v).ok();
+        Ok(NcmInfo {
+            format: ncm.format.extension().to_owned(),
+            music_id: meta.and_then(|m| to_i64(m.music_id)),
+            title: meta.map(|m| m.music_name.clone()),
+            artists: meta.map_or_else(Vec::new, |m| {
+                m.artist
+                    .iter()
+                    .filter_map(|a| a.first().and_then(Value::as_str).map(String::from))
+                    .collect()
+            }),
+            album: meta.map(|m| m.album.clone()),
+            duration_ms: meta.and_then(|m| to_i64(m.duration)),
+            bitrate: meta.and_then(|m| to_i64(m.bitrate)),
+            cover: ncm.cover_image.map(Buffer::from),
+        })
+    })
+    .await
+}
+
+/// Options for `new NeteaseClient()`.
+#[napi(object)]
+pub struct ClientOptions {
+    /// `MUSIC_U` cookie; without it the session saved by `ncmdump-cli login`
+    /// is used.
+    pub music_u: Option<String>,
+    /// `http://`, `https://` or `socks5://` proxy URL.
+    pub proxy: Option<String>,
+}
+
+/// Netease Cloud Music API client.
+#[napi(js_name = "NeteaseClient")]
+pub struct JsNeteaseClient {
+    inner: Arc<netease_api::NeteaseClient>,
+}
+
+impl JsNeteaseClient {
+    /// Run `f` on the blocking pool and resolve with its result as a plain
+    /// object.
+    async fn json<T: serde::Serialize>(
+        &self,
+        f: impl FnOnce(&netease_api::NeteaseClient) -> netease_api::Result<T> + Send + 'static,
+    ) -> Result<Value> {
+        let client = Arc::clone(&self.inner);
+        blocking(move || {
+            let value = f(&client).map_err(|e| api_err(&e))?;
+            serde_json::to_value(value).map_err(|e| Error::from_reason(e.to_string()))
+        })
+        .await
+    }
+}
+
+#[napi]
+impl JsNeteaseClient {
+    #[napi(constructor)]
+    pub fn new(options: Option<ClientOptions>) -> Result<Self> {
+        let mut builder = netease_api::NeteaseClient::builder();
+        if let Some(options) = options {
+            if let Some(music_u) = options.music_u {
+                builder = builder.session(Session {
+                    music_u: Some(music_u),
+                });
+            }
+            if let Some(proxy) = options.proxy {
+                builder = builder.proxy(proxy);
+            }
+        }
+        let client = builder.build().map_err(|e| api_err(&e))?;
+        Ok(Self {
+            inner: Arc::new(client),
+        })
+    }
+
+    /// Whether a `MUSIC_U` cookie is configured.
+    #[napi(getter)]
+    pub fn logged_in(&self) -> bool {
+        self.inner.session().is_logged_in()
+    }
+
+    /// Search; `type` is `"track"` (default), `"album"`, `"artist"` or
+    /// `"playlist"`.
+    #[napi]
+    pub async fn search(
+        &self,
+        keyword: String,
+        r#type: Option<String>,
+        limit: Option<u32>,
+        offset: Option<u32>,
+    ) -> Result<Value> {
+        let kind = parse_search_type(r#type.as_deref())?;
+        let limit = limit.map_or(20, u64::from);
+        let offset = offset.map_or(0, u64::from);
+        self.json(move |c| c.search(&keyword, kind, limit, offset))
+            .await
+    }
+
+    /// Track metadata.
+    #[napi]
+    pub async fn track_detail(&self, id: i64) -> Result<Value> {
+        let id = to_id(id)?;
+        self.json(move |c| c.track_detail(id)).await
+    }
+
+    /// Temporary download URL at `quality` (`"standard"` … `"hires"`,
+    /// default `"exhigh"`).
+    #[napi]
+    pub async fn track_url(&self, id: i64, quality: Option<String>) -> Result<Value> {
+        let id = to_id(id)?;
+        let quality = parse_quality(quality.as_deref())?;
+        self.json(move |c| c.track_url(id, quality)).await
+    }
+
+    /// Which quality tiers of a track the account can get.
+    #[napi]
+    pub async fn track_qualities(&self, id: i64) -> Result<Value> {
+        let id = to_id(id)?;
+        self.json(move |c| c.track_qualities(id)).await
+    }
+
+    /// Original and translated LRC lyrics.
+    #[napi]
+    pub async fn track_lyric(&self, id: i64) -> Result<Value> {
+        let id = to_id(id)?;
+        self.json(move |c| c.track_lyric(id)).await
+    }
+
+    /// Album with its tracks.
+    #[napi]
+    pub async fn album_detail(&self, id: i64) -> Result<Value> {
+        let id = to_id(id)?;
+        self.json(move |c| c.album_detail(id)).await
+    }
+
+    /// Playlist with its tracks.
+    #[napi]
+    pub async fn playlist_detail(&self, id: i64) -> Result<Value> {
+        let id = to_id(id)?;
+        self.json(move |c| c.playlist_detail(id)).await
+    }
+
+    /// Profile of the logged-in user.
+    #[napi]
+    pub async fn user_info(&self) -> Result<Value> {
+        self.json(netease_api::NeteaseClient::user_info).await
+    }
+
+    /// Download a track to `dest`; resolves with the number of bytes written.
+    #[napi]
+    pub async fn download_track(
+        &self,
+        id: i64,
+        dest: String,
+        quality: Option<String>,
+    ) -> Result<f64> {
+        let id = to_id(id)?;
+        let quality = parse_quality(quality.as_deref())?;
+        let client = Arc::clone(&self.inner);
+        blocking(move || {
+            let bytes = client
+                .download_track(id, quality, &PathBuf::from(dest))
+                .map_err(|e| api_err(&e))?;
+            // Sizes stay far below 2^53.
+            #[allow(clippy::cast_precision_loss)]
+            Ok(bytes as f64)
+        })
+        .await
+    }
+}
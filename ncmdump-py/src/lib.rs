@@ -0,0 +1,334 @@
+//! Python bindings (`import ncmdump`), built into a wheel with maturin.
+//!
+//! Exposes [`ncmdump::convert`], NCM header inspection and the common
+//! [`netease_api::NeteaseClient`] endpoints. Every call that touches the disk
+//! or the network releases the GIL, so Python threads can convert or download
+//! in parallel. API results are returned as plain `dict`s / `list`s, shaped
+//! like the `serde` form of the Rust types.
+
+// pyo3 hands arguments over by value, and `map_err` does the same.
+#![allow(clippy::needless_pass_by_value)]
+
+use std::path::PathBuf;
+
+use netease_api::auth::Session;
+use netease_api::types::{Quality, SearchType};
+use pyo3::exceptions::{PyException, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyDict, PyList};
+use serde::Serialize;
+use serde_json::Value;
+
+pyo3::create_exception!(
+    ncmdump,
+    NcmError,
+    PyException,
+    "Reading or converting an NCM file failed."
+);
+pyo3::create_exception!(
+    ncmdump,
+    NeteaseError,
+    PyException,
+    "A Netease API call failed."
+);
+
+fn ncm_err(e: ncmdump::NcmError) -> PyErr {
+    NcmError::new_err(e.to_string())
+}
+
+fn api_err(e: netease_api::NeteaseError) -> PyErr {
+    NeteaseError::new_err(e.to_string())
+}
+
+/// Convert a serializable value to the equivalent Python object.
+fn to_py<T: Serialize>(py: Python<'_>, value: &T) -> PyResult<Py<PyAny>> {
+    let value = serde_json::to_value(value).map_err(|e| PyValueError::new_err(e.to_string()))?;
+    json_to_py(py, &value)
+}
+
+fn json_to_py(py: Python<'_>, value: &Value) -> PyResult<Py<PyAny>> {
+    Ok(match value {
+        Value::Null => py.None(),
+        Value::Bool(b) => b.into_pyobject(py)?.to_owned().into_any().unbind(),
+        Value::Number(n) => match (n.as_u64(), n.as_i64()) {
+            (Some(u), _) => u.into_pyobject(py)?.into_any().unbind(),
+            (None, Some(i)) => i.into_pyobject(py)?.into_any().unbind(),
+            _ => n
+                .as_f64()
+                .unwrap_or(0.0)
+                .into_pyobject(py)?
+                .into_any()
+                .unbind(),
+        },
+        Value::String(s) => s.into_pyobject(py)?.into_any().unbind(),
+        Value::Array(items) => {
+            let list = PyList::empty(py);
+            for item in items {
+                list.append(json_to_py(py, item)?)?;
+            }
+            list.into_any().unbind()
+        }
+        Value::Object(map) => {
+            let dict = PyDict::new(py);
+            for (k, v) in map {
+                dict.set_item(k, json_to_py(py, v)?)?;
+            }
+            dict.into_any().unbind()
+        }
+    })
+}
+
+/// Convert an NCM file to MP3/FLAC with tags and cover, in `output_dir` or
+/// next to the input. Returns the output path.
+#[pyfunction]
+#[pyo3(signature = (input, output_dir=None))]
+fn convert(py: Python<'_>, input: PathBuf, output_dir: Option<PathBuf>) -> PyResult<PathBuf> {
+    py.detach(|| ncmdump::convert(&input, output_dir.as_deref()))
+        .map_err(ncm_err)
+}
+
+/// Read an NCM file's header without decrypting the audio.
+#[pyfunction]
+fn inspect(py: Python<'_>, path: PathBuf) -> PyResult<NcmFile> {
+    let ncm = py.detach(|| ncmdump::inspect(&path)).map_err(ncm_err)?;
+    Ok(NcmFile {
+        path,
+        format: ncm.format.extension(),
+        metadata: ncm.metadata,
+        cover: ncm.cover_image,
+    })
+}
+
+/// The header of an NCM file, as returned by `inspect()`.
+#[pyclass(frozen, module = "ncmdump")]
+struct NcmFile {
+    path: PathBuf,
+    format: &'static str,
+    metadata: Option<ncmdump::NcmMetadata>,
+    cover: Option<Vec<u8>>,
+}
+
+#[pymethods]
+impl NcmFile {
+    /// Path the file was read from.
+    #[getter]
+    fn path(&self) -> PathBuf {
+        self.path.clone()
+    }
+
+    /// Audio format inside: `"mp3"`, `"flac"`, `"m4a"` or `"ogg"`.
+    #[getter]
+    fn format(&self) -> &'static str {
+        self.format
+    }
+
+    /// Netease track ID, or `None` without metadata.
+    #[getter]
+    fn music_id(&self) -> Option<u64> {
+        self.metadata.as_ref().map(|m| m.music_id)
+    }
+
+    /// Track title.
+    #[getter]
+    fn title(&self) -> Option<String> {
+        self.metadata.as_ref().map(|m| m.music_name.clone())
+    }
+
+    /// Artist names.
+    #[getter]
+    fn artists(&self) -> Vec<String> {
+        self.metadata.as_ref().map_or_else(Vec::new, |m| {
+            m.artist
+                .iter()
+                .filter_map(|a| a.first().and_then(Value::as_str).map(String::from))
+                .collect()
+        })
+    }
+
+    /// Album name.
+    #[getter]
+    fn album(&self) -> Option<String> {
+        self.metadata.as_ref().map(|m| m.album.clone())
+    }
+
+    /// Duration in milliseconds.
+    #[getter]
+    fn duration_ms(&self) -> Option<u64> {
+        self.metadata.as_ref().map(|m| m.duration)
+    }
+
+    /// Bitrate in bits per second.
+    #[getter]
+    fn bitrate(&self) -> Option<u64> {
+        self.metadata.as_ref().map(|m| m.bitrate)
+    }
+
+    /// Embedded cover image bytes.
+    #[getter]
+    fn cover<'py>(&self, py: Python<'py>) -> Option<Bound<'py, PyBytes>> {
+        self.cover.as_deref().map(|c| PyBytes::new(py, c))
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "NcmFile(path='{}', format='{}', title='{}')",
+            self.path.display(),
+            self.format,
+            self.title().unwrap_or_default()
+        )
+    }
+}
+
+fn parse_quality(quality: &str) -> PyResult<Quality> {
+    serde_json::from_value(Value::String(quality.to_owned()))
+        .map_err(|_| PyValueError::new_err(format!("unknown quality: {quality}")))
+}
+
+fn parse_search_type(kind: &str) -> PyResult<SearchType> {
+    Ok(match kind {
+        "track" => SearchType::Track,
+        "album" => SearchType::Album,
+        "artist" => SearchType::Artist,
+        "playlist" => SearchType::Playlist,
+        _ => {
+            return Err(PyValueError::new_err(format!(
+                "unknown search type: {kind}"
+            )));
+        }
+    })
+}
+
+/// Netease Cloud Music API client.
+///
+/// `NeteaseClient()` loads the session saved by `ncmdump-cli login`;
+/// pass `music_u` to use a cookie directly, and `proxy` to route requests
+/// through an `http://`, `https://` or `socks5://` proxy.
+#[pyclass(frozen, name = "NeteaseClient", module = "ncmdump")]
+struct PyNeteaseClient(netease_api::NeteaseClient);
+
+#[pymethods]
+impl PyNeteaseClient {
+    #[new]
+    #[pyo3(signature = (music_u=None, proxy=None))]
+    fn new(music_u: Option<String>, proxy: Option<String>) -> PyResult<Self> {
+        let mut builder = netease_api::NeteaseClient::builder();
+        if let Some(music_u) = music_u {
+            builder = builder.session(Session {
+                music_u: Some(music_u),
+            });
+        }
+        if let Some(proxy) = proxy {
+            builder = builder.proxy(proxy);
+        }
+        builder.build().map(Self).map_err(api_err)
+    }
+
+    /// Whether a `MUSIC_U` cookie is configured.
+    #[getter]
+    fn logged_in(&self) -> bool {
+        self.0.session().is_logged_in()
+    }
+
+    /// Search; `type` is `"track"`, `"album"`, `"artist"` or `"playlist"`.
+    #[pyo3(signature = (keyword, r#type="track", limit=20, offset=0))]
+    fn search(
+        &self,
+        py: Python<'_>,
+        keyword: &str,
+        r#type: &str,
+        limit: u64,
+        offset: u64,
+    ) -> PyResult<Py<PyAny>> {
+        let kind = parse_search_type(r#type)?;
+        let result = py
+            .detach(|| self.0.search(keyword, kind, limit, offset))
+            .map_err(api_err)?;
+        to_py(py, &result)
+    }
+
+    /// Track metadata.
+    fn track_detail(&self, py: Python<'_>, id: u64) -> PyResult<Py<PyAny>> {
+        let track = py.detach(|| self.0.track_detail(id)).map_err(api_err)?;
+        to_py(py, &track)
+    }
+
+    /// Temporary download URL at `quality` (`"standard"` … `"hires"`).
+    #[pyo3(signature = (id, quality="exhigh"))]
+    fn track_url(&self, py: Python<'_>, id: u64, quality: &str) -> PyResult<String> {
+        let quality = parse_quality(quality)?;
+        py.detach(|| self.0.track_url(id, quality)).map_err(api_err)
+    }
+
+    /// Which quality tiers of a track the account can get.
+    fn track_qualities(&self, py: Python<'_>, id: u64) -> PyResult<Py<PyAny>> {
+        let tiers = py.detach(|| self.0.track_qualities(id)).map_err(api_err)?;
+        to_py(py, &tiers)
+    }
+
+    /// Original and translated LRC lyrics.
+    fn track_lyric(&self, py: Python<'_>, id: u64) -> PyResult<Py<PyAny>> {
+        let lyric = py.detach(|| self.0.track_lyric(id)).map_err(api_err)?;
+        to_py(py, &lyric)
+    }
+
+    /// Download a track to `dest`; returns the number of bytes written.
+    #[pyo3(signature = (id, dest, quality="exhigh"))]
+    fn download_track(
+        &self,
+        py: Python<'_>,
+        id: u64,
+        dest: PathBuf,
+        quality: &str,
+    ) -> PyResult<u64> {
+        let quality = parse_quality(quality)?;
+        py.detach(|| self.0.download_track(id, quality, &dest))
+            .map_err(api_err)
+    }
+
+    /// Album with its tracks.
+    fn album_detail(&self, py: Python<'_>, id: u64) -> PyResult<Py<PyAny>> {
+        let album = py.detach(|| self.0.album_detail(id)).map_err(api_err)?;
+        to_py(py, &album)
+    }
+
+    /// Artist overview with top tracks.
+    fn artist_detail(&self, py: Python<'_>, id: u64) -> PyResult<Py<PyAny>> {
+        let artist = py.detach(|| self.0.artist_detail(id)).map_err(api_err)?;
+        to_py(py, &artist)
+    }
+
+    /// Playlist with its tracks.
+    fn playlist_detail(&self, py: Python<'_>, id: u64) -> PyResult<Py<PyAny>> {
+        let playlist = py.detach(|| self.0.playlist_detail(id)).map_err(api_err)?;
+        to_py(py, &playlist)
+    }
+
+    /// Profile of the logged-in user.
+    fn user_info(&self, py: Python<'_>) -> PyResult<Py<PyAny>> {
+        let profile = py.detach(|| self.0.user_info()).map_err(api_err)?;
+        to_py(py, &profile)
+    }
+
+    /// Today's recommended songs (requires login).
+    fn daily_songs(&self, py: Python<'_>) -> PyResult<Py<PyAny>> {
+        let tracks = py.detach(|| self.0.daily_songs()).map_err(api_err)?;
+        to_py(py, &tracks)
+    }
+
+    /// The next batch of personal-FM tracks (requires login).
+    fn personal_fm(&self, py: Python<'_>) -> PyResult<Py<PyAny>> {
+        let tracks = py.detach(|| self.0.personal_fm()).map_err(api_err)?;
+        to_py(py, &tracks)
+    }
+}
+
+#[pymodule(name = "ncmdump")]
+fn ncmdump_py(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(convert, m)?)?;
+    m.add_function(wrap_pyfunction!(inspect, m)?)?;
+    m.add_class::<NcmFile>()?;
+    m.add_class::<PyNeteaseClient>()?;
+    m.add("NcmError", m.py().get_type::<NcmError>())?;
+    m.add("NeteaseError", m.py().get_type::<NeteaseError>())?;
+    Ok(())
+}
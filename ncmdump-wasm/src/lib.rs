@@ -0,0 +1,83 @@
+//! Browser bindings (wasm-bindgen) for a fully client-side converter.
+//!
+//! Everything happens in memory: the page hands over the bytes of an `.ncm`
+//! file and gets back tagged MP3/FLAC bytes, so no file ever leaves the
+//! user's machine.
+
+use js_sys::{Array, Object, Reflect, Uint8Array};
+use ncmdump::{NcmError, NcmMetadata};
+use wasm_bindgen::prelude::*;
+
+/// Output name used when the file carries no metadata.
+const FALLBACK_STEM: &str = "ncmdump";
+
+fn ncm_err(e: &NcmError) -> JsValue {
+    JsError::new(&e.to_string()).into()
+}
+
+fn set(target: &Object, key: &str, value: &JsValue) -> Result<(), JsValue> {
+    Reflect::set(target, &JsValue::from_str(key), value).map(drop)
+}
+
+fn u64_value(v: u64) -> JsValue {
+    // Track IDs, durations and bitrates stay far below 2^53.
+    #[allow(clippy::cast_precision_loss)]
+    JsValue::from_f64(v as f64)
+}
+
+fn metadata_object(meta: &NcmMetadata) -> Result<Object, JsValue> {
+    let artists: Array = meta
+        .artist
+        .iter()
+        .filter_map(|a| a.first().and_then(|v| v.as_str()))
+        .map(JsValue::from_str)
+        .collect();
+    let obj = Object::new();
+    set(&obj, "musicId", &u64_value(meta.music_id))?;
+    set(&obj, "title", &JsValue::from_str(&meta.music_name))?;
+    set(&obj, "artists", &artists)?;
+    set(&obj, "album", &JsValue::from_str(&meta.album))?;
+    set(&obj, "durationMs", &u64_value(meta.duration))?;
+    set(&obj, "bitrate", &u64_value(meta.bitrate))?;
+    Ok(obj)
+}
+
+/// Decrypt the bytes of an NCM file.
+///
+/// Returns `{ audio, format, fileName, metadata, cover }`: `audio` is a
+/// `Uint8Array` of the audio with title, artists, album and cover already
+/// tagged, `format` is `"mp3"`, `"flac"`, `"m4a"` or `"ogg"`, `fileName` is a
+/// suggested `"Artist - Title.ext"`, and `metadata` / `cover` are `null` when
+/// the file has none. Throws an `Error` if `data` is not a valid NCM file.
+#[wasm_bindgen]
+pub fn decrypt(data: &[u8]) -> Result<Object, JsValue> {
+    let (audio, format, metadata, cover) = ncmdump::convert_bytes(data).map_err(|e| ncm_err(&e))?;
+
+    let ext = format.extension();
+    let stem = metadata
+        .as_ref()
+        .and_then(|m| {
+            ncmdump::template::render(ncmdump::template::DEFAULT_TEMPLATE, |f| m.template_field(f))
+                .ok()
+        })
+        .unwrap_or_else(|| FALLBACK_STEM.to_owned());
+
+    let result = Object::new();
+    set(&result, "audio", &Uint8Array::from(audio.as_slice()))?;
+    set(&result, "format", &JsValue::from_str(ext))?;
+    set(
+        &result,
+        "fileName",
+        &JsValue::from_str(&format!("{stem}.{ext}")),
+    )?;
+    let metadata = match &metadata {
+        Some(meta) => metadata_object(meta)?.into(),
+        None => JsValue::NULL,
+    };
+    set(&result, "metadata", &metadata)?;
+    let cover = cover
+        .as_deref()
+        .map_or(JsValue::NULL, |c| Uint8Array::from(c).into());
+    set(&result, "cover", &cover)?;
+    Ok(result)
+}
@@ -0,0 +1,152 @@
+//! Converting many NCM files at once, and the pool of worker threads it
+//! runs on.
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, PoisonError};
+
+use crate::ConvertOptions;
+use crate::error::Result;
+
+/// Options for [`convert_many`].
+#[derive(Debug, Clone, Default)]
+pub struct BatchOptions {
+    /// Where to write the outputs; next to each input if `None`.
+    pub output_dir: Option<PathBuf>,
+    /// Worker threads; one per CPU if 0.
+    pub threads: usize,
+    /// How to convert each file.
+    pub convert: ConvertOptions,
+}
+
+/// [`convert`](crate::convert) each of `inputs` in parallel, continuing past
+/// failures. Returns one result per input, in the order of `inputs`.
+pub fn convert_many(inputs: &[PathBuf], opts: &BatchOptions) -> Vec<Result<PathBuf>> {
+    convert_many_with(inputs, opts, |_, _| {})
+}
+
+/// Like [`convert_many`], calling `on_done` with each input's index and
+/// result as soon as it is finished.
+///
+/// `on_done` runs on the worker threads, in the order the files finish, but
+/// never twice at once.
+pub fn convert_many_with<F>(
+    inputs: &[PathBuf],
+    opts: &BatchOptions,
+    on_done: F,
+) -> Vec<Result<PathBuf>>
+where
+    F: FnMut(usize, &Result<PathBuf>) + Send,
+{
+    let on_done = Mutex::new(on_done);
+    for_each_bounded(inputs, opts.threads, |i, input| {
+        let result =
+            crate::convert_inner(input, opts.output_dir.as_deref(), &opts.convert, |_, _| {});
+        if let Err(e) = &result {
+            tracing::warn!(input = %input.display(), error = %e, "conversion failed");
+        }
+        (on_done.lock().unwrap_or_else(PoisonError::into_inner))(i, &result);
+        result
+    })
+}
+
+/// Call `f` with the index of each of `items` and the item, on up to
+/// `threads` worker threads (one per CPU if 0). Returns what `f` returned,
+/// in the order of `items`.
+pub fn for_each_bounded<T, R, F>(items: &[T], threads: usize, f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(usize, &T) -> R + Sync,
+{
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(Vec::with_capacity(items.len()));
+    for_each_bounded_from(
+        resolve(threads).clamp(1, items.len().max(1)),
+        || {
+            let i = next.fetch_add(1, Ordering::Relaxed);
+            items.get(i).map(|item| (i, item))
+        },
+        |(i, item)| {
+            let result = f(i, item);
+            results
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .push((i, result));
+        },
+    );
+
+    let mut results = results.into_inner().unwrap_or_else(PoisonError::into_inner);
+    results.sort_unstable_by_key(|(i, _)| *i);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+/// Call `work` with each item `next` hands out until it returns `None`, on
+/// `threads` worker threads (one per CPU if 0). Unlike [`for_each_bounded`],
+/// `next` may hand out items that `work` queued again.
+pub fn for_each_bounded_from<T, N, W>(threads: usize, next: N, work: W)
+where
+    N: Fn() -> Option<T> + Sync,
+    W: Fn(T) + Sync,
+{
+    std::thread::scope(|scope| {
+        for _ in 0..resolve(threads) {
+            scope.spawn(|| {
+                while let Some(item) = next() {
+                    work(item);
+                }
+            });
+        }
+    });
+}
+
+/// `threads`, or one per CPU if 0.
+fn resolve(threads: usize) -> usize {
+    match threads {
+        0 => std::thread::available_parallelism().map_or(1, usize::from),
+        n => n,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NcmError;
+
+    #[test]
+    fn test_convert_many_keeps_order() {
+        let input = PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/../testdata/test.ncm"));
+        let dir = std::env::temp_dir().join(format!("ncmdump-batch-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let missing = dir.join("missing.ncm");
+        let opts = BatchOptions {
+            output_dir: Some(dir.clone()),
+            threads: 2,
+            ..BatchOptions::default()
+        };
+
+        let mut seen = Vec::new();
+        let results = convert_many_with(&[missing, input.clone()], &opts, |i, result| {
+            seen.push((i, result.is_ok()));
+        });
+        assert_eq!(results.len(), 2);
+        assert!(matches!(results[0], Err(NcmError::Io(_))));
+        let output = results[1].as_ref().unwrap();
+        assert!(output.starts_with(&dir));
+        assert_eq!(output.file_stem(), input.file_stem());
+        seen.sort_unstable();
+        assert_eq!(seen, [(0, false), (1, true)]);
+
+        assert!(convert_many(&[], &opts).is_empty());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_for_each_bounded_keeps_order() {
+        let items: Vec<u64> = (0..100).collect();
+        let squares = for_each_bounded(&items, 3, |i, &n| (i, n * n));
+        assert_eq!(squares.len(), 100);
+        assert!(squares.iter().all(|&(i, sq)| sq == (i as u64).pow(2)));
+        assert!(for_each_bounded(&[] as &[u64], 0, |_, &n| n).is_empty());
+    }
+}
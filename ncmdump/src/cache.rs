@@ -0,0 +1,169 @@
+//! Netease client cache files (`.uc` on desktop, `.uc!` on mobile).
+//!
+//! Cached tracks are the plain audio stream with every byte XOR-ed with
+//! `0xA3`. The file name starts with the track ID, e.g.
+//! `1974443815-320-0123456789abcdef0123456789abcdef.uc` (`<id>-<kbps>-<md5>`).
+
+use std::fs::File;
+use std::io::{BufWriter, Read, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use crate::container::{ContainerInfo, EncryptedContainer, ReadSeek};
+use crate::decoder::AudioFormat;
+use crate::error::{NcmError, Result};
+
+/// XOR key applied to every byte of a cache file.
+const CACHE_KEY: u8 = 0xA3;
+
+/// Whether `path` looks like a client cache file (`.uc` / `.uc!`).
+pub fn is_cache_file(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("uc") || e.eq_ignore_ascii_case("uc!"))
+}
+
+/// Track ID encoded in a cache file name (the digits before the first `-`).
+pub fn track_id(path: &Path) -> Option<u64> {
+    let name = path.file_name()?.to_str()?;
+    let end = name
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(name.len());
+    name[..end].parse().ok()
+}
+
+/// Decrypt a cache stream into `w`, returning the detected audio format.
+///
+/// # Errors
+///
+/// Returns [`NcmError::UnsupportedFormat`] if the decrypted data is none of
+/// the formats [`AudioFormat::detect`] recognises (MP3, FLAC, M4A and Ogg),
+/// e.g. a truncated or raw AAC cache entry.
+pub fn decrypt<R: Read, W: Write>(r: &mut R, w: &mut W) -> Result<AudioFormat> {
+    let mut buf = vec![0u8; 0x8000];
+    let mut format = None;
+    loop {
+        let n = r.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        for byte in &mut buf[..n] {
+            *byte ^= CACHE_KEY;
+        }
+        if format.is_none() {
+            format = Some(AudioFormat::detect(&buf[..n]).ok_or(NcmError::UnsupportedFormat)?);
+        }
+        w.write_all(&buf[..n])?;
+    }
+    format.ok_or(NcmError::UnsupportedFormat)
+}
+
+/// Client cache files, as an [`EncryptedContainer`]. Their track ID is
+/// only in the file name (see [`track_id`]), so they carry no metadata.
+pub struct Cache;
+
+impl EncryptedContainer for Cache {
+    fn name(&self) -> &'static str {
+        "cache"
+    }
+
+    fn probe(&self, path: &Path, _header: &[u8]) -> bool {
+        is_cache_file(path)
+    }
+
+    fn metadata(&self, r: &mut dyn ReadSeek) -> Result<ContainerInfo> {
+        let audio_len = r.seek(SeekFrom::End(0))?;
+        let mut header = Vec::with_capacity(4);
+        r.seek(SeekFrom::Start(0))?;
+        (&mut *r).take(4).read_to_end(&mut header)?;
+        for byte in &mut header {
+            *byte ^= CACHE_KEY;
+        }
+        Ok(ContainerInfo {
+            format: AudioFormat::detect(&header).ok_or(NcmError::UnsupportedFormat)?,
+            metadata: None,
+            cover: None,
+            audio_len,
+        })
+    }
+
+    fn dump_audio(&self, r: &mut dyn ReadSeek, w: &mut dyn Write) -> Result<()> {
+        r.seek(SeekFrom::Start(0))?;
+        let (mut r, mut w) = (r, w);
+        decrypt(&mut r, &mut w).map(drop)
+    }
+}
+
+/// Decrypt a cache file into `output_dir` as `<stem>.<ext>`.
+///
+/// Returns the path to the output file. Nothing is left behind if the
+/// format cannot be detected.
+pub fn convert_cache(input: &Path, output_dir: &Path, stem: &str) -> Result<PathBuf> {
+    let mut file = File::open(input)?;
+    let mut data = Vec::new();
+    let format = decrypt(&mut file, &mut data)?;
+
+    let output_path = output_dir.join(crate::path::with_extension(
+        std::ffi::OsStr::new(stem),
+        format.extension(),
+    ));
+    write_output(&data, &output_path)?;
+    tracing::debug!(input = %input.display(), output = %output_path.display(), "decrypted cache file");
+    Ok(output_path)
+}
+
+fn write_output(data: &[u8], output: &Path) -> Result<()> {
+    let mut writer = BufWriter::new(File::create(output)?);
+    writer.write_all(data)?;
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_track_id() {
+        let path = Path::new("/cache/1974443815-320-0123456789abcdef.uc");
+        assert_eq!(track_id(path), Some(1_974_443_815));
+        assert_eq!(track_id(Path::new("abc.uc!")), None);
+        assert!(is_cache_file(Path::new("1-128-x.uc!")));
+        assert!(!is_cache_file(Path::new("1-128-x.idx!")));
+    }
+
+    #[test]
+    fn test_decrypt_flac() {
+        let plain = b"fLaC\0\0\0\x22rest".to_vec();
+        let encrypted: Vec<u8> = plain.iter().map(|b| b ^ CACHE_KEY).collect();
+        let mut out = Vec::new();
+        let format = decrypt(&mut encrypted.as_slice(), &mut out).unwrap();
+        assert_eq!(format, AudioFormat::Flac);
+        assert_eq!(out, plain);
+    }
+
+    #[test]
+    fn test_convert_to() {
+        let dir = std::env::temp_dir().join(format!("ncmdump-cache-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let input = dir.join("1974443815-320-0123456789abcdef.uc");
+        let plain = b"ID3\x04\0\0\0\0\0\0frames".to_vec();
+        std::fs::write(
+            &input,
+            plain.iter().map(|b| b ^ CACHE_KEY).collect::<Vec<_>>(),
+        )
+        .unwrap();
+
+        let info = crate::container::inspect(&input).unwrap();
+        assert_eq!(info.format, AudioFormat::Mp3);
+        assert!(info.metadata.is_none());
+        let output = dir.join("out.mp3");
+        crate::convert_to(&input, &output).unwrap();
+        assert_eq!(std::fs::read(&output).unwrap(), plain);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_decrypt_unknown() {
+        let encrypted: Vec<u8> = b"ftypM4A ".iter().map(|b| b ^ CACHE_KEY).collect();
+        assert!(decrypt(&mut encrypted.as_slice(), &mut Vec::new()).is_err());
+    }
+}
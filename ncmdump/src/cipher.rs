@@ -1,9 +1,10 @@
 use aes::Aes128;
-use ecb::cipher::{BlockDecryptMut, KeyInit, block_padding::Pkcs7};
+use ecb::cipher::{BlockDecryptMut, BlockEncryptMut, KeyInit, block_padding::Pkcs7};
 
 use crate::error::{NcmError, Result};
 
 type Aes128EcbDec = ecb::Decryptor<Aes128>;
+type Aes128EcbEnc = ecb::Encryptor<Aes128>;
 
 /// AES-128-ECB decrypt with PKCS#7 unpadding.
 pub fn aes128_ecb_decrypt(key: &[u8; 16], data: &[u8]) -> Result<Vec<u8>> {
@@ -14,6 +15,19 @@ pub fn aes128_ecb_decrypt(key: &[u8; 16], data: &[u8]) -> Result<Vec<u8>> {
         .map_err(|e| NcmError::Decrypt(e.to_string()))
 }
 
+/// AES-128-ECB encrypt with PKCS#7 padding.
+pub fn aes128_ecb_encrypt(key: &[u8; 16], data: &[u8]) -> Vec<u8> {
+    // Room for up to a whole block of padding.
+    let mut buf = data.to_vec();
+    buf.resize(data.len() + 16, 0);
+    let len = Aes128EcbEnc::new(key.into())
+        .encrypt_padded_mut::<Pkcs7>(&mut buf, data.len())
+        .expect("buffer has room for the padding")
+        .len();
+    buf.truncate(len);
+    buf
+}
+
 /// Standard RC4 Key Scheduling Algorithm. Returns the permuted S-box.
 #[allow(clippy::cast_possible_truncation)]
 pub fn rc4_ksa(key: &[u8]) -> [u8; 256] {
@@ -49,6 +63,12 @@ pub fn rc4_stream_byte(key_box: &[u8; 256], offset: usize) -> u8 {
     key_box[(jv + key_box[(jv + j) & 0xff] as usize) & 0xff]
 }
 
+/// The whole keystream: it only depends on `offset & 0xff`, so byte `i` of
+/// the result is the stream byte at every offset `i + 256 * k`.
+pub fn rc4_keystream(key_box: &[u8; 256]) -> [u8; 256] {
+    std::array::from_fn(|i| rc4_stream_byte(key_box, i))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -70,6 +90,15 @@ mod tests {
         assert_eq!(b1, b2);
     }
 
+    #[test]
+    fn test_rc4_keystream_repeats() {
+        let sbox = rc4_ksa(b"testkey");
+        let stream = rc4_keystream(&sbox);
+        for offset in [0, 1, 255, 256, 257, 1000] {
+            assert_eq!(stream[offset & 0xff], rc4_stream_byte(&sbox, offset));
+        }
+    }
+
     #[test]
     fn test_aes128_ecb_roundtrip() {
         let key: [u8; 16] = *b"0123456789abcdef";
@@ -89,4 +118,40 @@ mod tests {
         let decrypted = aes128_ecb_decrypt(&key, &encrypted).unwrap();
         assert_eq!(&decrypted, plaintext);
     }
+
+    #[test]
+    fn test_aes128_ecb_encrypt() {
+        let key: [u8; 16] = *b"0123456789abcdef";
+        // From `openssl enc -aes-128-ecb`.
+        let cases: [(&[u8], &[u8]); 3] = [
+            (
+                b"",
+                &[
+                    0x37, 0x72, 0x22, 0xE0, 0x61, 0xA9, 0x24, 0xC5, 0x91, 0xCD, 0x9C, 0x27, 0xEA,
+                    0x16, 0x3E, 0xD4,
+                ],
+            ),
+            (
+                b"hello world!!!!!",
+                &[
+                    0xDE, 0xCB, 0x65, 0xF7, 0x52, 0x5B, 0xC6, 0x76, 0x0A, 0x3F, 0x6F, 0xD5, 0x97,
+                    0xAD, 0x4B, 0x34, 0x37, 0x72, 0x22, 0xE0, 0x61, 0xA9, 0x24, 0xC5, 0x91, 0xCD,
+                    0x9C, 0x27, 0xEA, 0x16, 0x3E, 0xD4,
+                ],
+            ),
+            (
+                b"not a block multiple",
+                &[
+                    0xCB, 0x74, 0xA3, 0x5D, 0x9E, 0xC9, 0xA9, 0xF3, 0x01, 0x4C, 0x56, 0xBD, 0xE3,
+                    0xA5, 0x39, 0x8E, 0x49, 0xBE, 0x19, 0xB2, 0x76, 0x8B, 0x62, 0x28, 0x2E, 0x66,
+                    0x5C, 0x00, 0xCC, 0x08, 0x9A, 0x7C,
+                ],
+            ),
+        ];
+        for (plaintext, expected) in cases {
+            let encrypted = aes128_ecb_encrypt(&key, plaintext);
+            assert_eq!(encrypted, expected);
+            assert_eq!(aes128_ecb_decrypt(&key, &encrypted).unwrap(), plaintext);
+        }
+    }
 }
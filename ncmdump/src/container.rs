@@ -0,0 +1,182 @@
+//! Encrypted audio containers, and the registry [`convert`](crate::convert)
+//! picks a decoder from.
+//!
+//! NCM, QMC, KWM and client cache files are built in. Decoders for other
+//! formats can be added with [`register`]; they are probed before the
+//! built-in ones, so they can also take a built-in format over.
+
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::sync::{Arc, LazyLock, PoisonError, RwLock};
+
+use crate::cache::Cache;
+use crate::decoder::{AudioFormat, Ncm};
+use crate::error::{NcmError, Result};
+use crate::kwm::Kwm;
+use crate::metadata::NcmMetadata;
+use crate::qmc::Qmc;
+
+/// Bytes from the start of a file passed to [`EncryptedContainer::probe`]
+/// (fewer if the file is shorter).
+pub const PROBE_LEN: usize = 16;
+
+/// A readable and seekable input, as containers are handed it.
+pub trait ReadSeek: Read + Seek {}
+
+impl<T: Read + Seek + ?Sized> ReadSeek for T {}
+
+/// What a container holds besides its encrypted audio.
+#[derive(Debug, Clone)]
+pub struct ContainerInfo {
+    pub format: AudioFormat,
+    /// Metadata to tag the output with.
+    pub metadata: Option<NcmMetadata>,
+    pub cover: Option<Vec<u8>>,
+    /// Length of the decrypted audio in bytes.
+    pub audio_len: u64,
+}
+
+/// An encrypted audio format.
+///
+/// Both reading methods get the whole file in `r`, positioned anywhere.
+pub trait EncryptedContainer: Send + Sync {
+    /// Short name for logs, such as `"ncm"`.
+    fn name(&self) -> &'static str;
+
+    /// Whether the file at `path`, which starts with `header` (see
+    /// [`PROBE_LEN`]), is in this format.
+    fn probe(&self, path: &Path, header: &[u8]) -> bool;
+
+    /// Read the audio format, and metadata and cover if the format has any.
+    fn metadata(&self, r: &mut dyn ReadSeek) -> Result<ContainerInfo>;
+
+    /// Decrypt the audio into `w`.
+    fn dump_audio(&self, r: &mut dyn ReadSeek, w: &mut dyn Write) -> Result<()>;
+}
+
+/// Containers in the order they are probed: latest registered first, then
+/// the built-in ones.
+static REGISTRY: LazyLock<RwLock<Vec<Arc<dyn EncryptedContainer>>>> =
+    LazyLock::new(|| RwLock::new(builtin()));
+
+fn builtin() -> Vec<Arc<dyn EncryptedContainer>> {
+    vec![Arc::new(Ncm), Arc::new(Qmc), Arc::new(Kwm), Arc::new(Cache)]
+}
+
+/// Add a decoder for [`convert`](crate::convert) and [`probe`] to try before
+/// those already known.
+pub fn register<C: EncryptedContainer + 'static>(container: C) {
+    let mut registry = REGISTRY.write().unwrap_or_else(PoisonError::into_inner);
+    registry.insert(0, Arc::new(container));
+}
+
+/// The first container that accepts the file at `path` starting with
+/// `header`.
+pub fn find(path: &Path, header: &[u8]) -> Option<Arc<dyn EncryptedContainer>> {
+    let registry = REGISTRY.read().unwrap_or_else(PoisonError::into_inner);
+    find_in(&registry, path, header)
+}
+
+/// [`find`] over `containers` rather than the registry.
+fn find_in(
+    containers: &[Arc<dyn EncryptedContainer>],
+    path: &Path,
+    header: &[u8],
+) -> Option<Arc<dyn EncryptedContainer>> {
+    containers.iter().find(|c| c.probe(path, header)).cloned()
+}
+
+/// The first [`PROBE_LEN`] bytes of `r`, leaving it at the start.
+fn read_header<R: Read + Seek>(r: &mut R) -> Result<Vec<u8>> {
+    r.seek(SeekFrom::Start(0))?;
+    let mut header = Vec::with_capacity(PROBE_LEN);
+    r.take(PROBE_LEN as u64).read_to_end(&mut header)?;
+    r.seek(SeekFrom::Start(0))?;
+    Ok(header)
+}
+
+/// Pick the container for the file at `path`, read from `r`. Leaves `r` at
+/// the start.
+///
+/// # Errors
+///
+/// Returns [`NcmError::InvalidMagic`] if no container accepts it.
+pub fn probe<R: Read + Seek>(path: &Path, r: &mut R) -> Result<Arc<dyn EncryptedContainer>> {
+    let header = read_header(r)?;
+    let container = find(path, &header).ok_or(NcmError::InvalidMagic)?;
+    tracing::trace!(path = %path.display(), container = container.name(), "probed");
+    Ok(container)
+}
+
+/// Read the format, metadata and cover of the file at `input`, in any
+/// registered format.
+pub fn inspect(input: &Path) -> Result<ContainerInfo> {
+    let mut file = std::fs::File::open(input)?;
+    probe(input, &mut file)?.metadata(&mut file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Plain audio behind a `PLAIN` tag.
+    struct Plain;
+
+    impl EncryptedContainer for Plain {
+        fn name(&self) -> &'static str {
+            "plain"
+        }
+
+        fn probe(&self, _path: &Path, header: &[u8]) -> bool {
+            header.starts_with(b"PLAIN")
+        }
+
+        fn metadata(&self, r: &mut dyn ReadSeek) -> Result<ContainerInfo> {
+            Ok(ContainerInfo {
+                format: AudioFormat::Flac,
+                metadata: None,
+                cover: None,
+                audio_len: r.seek(SeekFrom::End(0))? - 5,
+            })
+        }
+
+        fn dump_audio(&self, r: &mut dyn ReadSeek, w: &mut dyn Write) -> Result<()> {
+            r.seek(SeekFrom::Start(5))?;
+            std::io::copy(r, w)?;
+            Ok(())
+        }
+    }
+
+    /// Name of the first of `containers` to accept `data`, read as `path`.
+    fn probe_in(
+        containers: &[Arc<dyn EncryptedContainer>],
+        path: &str,
+        data: &[u8],
+    ) -> Option<&'static str> {
+        let header = read_header(&mut std::io::Cursor::new(data)).unwrap();
+        find_in(containers, Path::new(path), &header).map(|c| c.name())
+    }
+
+    #[test]
+    fn test_registered_container() {
+        let data = b"PLAINfLaC audio";
+        assert_eq!(probe_in(&builtin(), "song.plain", data), None);
+
+        // Registered as `register` would: in front of the built-in ones.
+        let mut containers: Vec<Arc<dyn EncryptedContainer>> = vec![Arc::new(Plain)];
+        containers.extend(builtin());
+        assert_eq!(probe_in(&containers, "song.plain", data), Some("plain"));
+        let mut r = std::io::Cursor::new(data.to_vec());
+        let info = Plain.metadata(&mut r).unwrap();
+        assert_eq!(info.format, AudioFormat::Flac);
+        assert_eq!(info.audio_len, 10);
+        let mut out = Vec::new();
+        Plain.dump_audio(&mut r, &mut out).unwrap();
+        assert_eq!(out, b"fLaC audio");
+
+        // The built-in ones are still found behind it.
+        let ncm =
+            std::fs::read(concat!(env!("CARGO_MANIFEST_DIR"), "/../testdata/test.ncm")).unwrap();
+        assert_eq!(probe_in(&containers, "test.ncm", &ncm), Some("ncm"));
+    }
+}
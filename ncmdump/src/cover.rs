@@ -0,0 +1,116 @@
+//! Shrinking cover art before it is embedded (feature `cover`).
+//!
+//! Some NCM headers carry multi-megabyte PNG covers, which end up in every
+//! output file. [`process`] scales a cover down to fit a maximum dimension
+//! and re-encodes it as JPEG; covers that are already small JPEGs are kept
+//! byte for byte.
+
+use std::io::Cursor;
+
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::{GenericImageView, ImageFormat};
+
+use crate::error::{NcmError, Result};
+
+/// How [`process`] treats a cover.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CoverOptions {
+    /// Longest side in pixels; larger covers are scaled down to it.
+    pub max_dimension: u32,
+    /// JPEG quality (1-100) for re-encoded covers.
+    pub jpeg_quality: u8,
+}
+
+impl Default for CoverOptions {
+    fn default() -> Self {
+        Self {
+            max_dimension: 1200,
+            jpeg_quality: 85,
+        }
+    }
+}
+
+/// Scale `data` down to fit `opts.max_dimension` and re-encode it as JPEG.
+///
+/// A JPEG that already fits is returned unchanged, so it is not
+/// re-compressed; a PNG is always converted.
+///
+/// # Errors
+///
+/// Returns [`NcmError::Image`] if `data` is not a JPEG or PNG image.
+pub fn process(data: &[u8], opts: &CoverOptions) -> Result<Vec<u8>> {
+    let format = image::guess_format(data).map_err(image_err)?;
+    let img = image::load_from_memory_with_format(data, format).map_err(image_err)?;
+    let (width, height) = img.dimensions();
+    let max = opts.max_dimension.max(1);
+    let fits = width <= max && height <= max;
+    if fits && format == ImageFormat::Jpeg {
+        return Ok(data.to_vec());
+    }
+
+    let img = if fits {
+        img
+    } else {
+        // Keeps the aspect ratio, the longer side becoming `max`.
+        img.resize(max, max, FilterType::Lanczos3)
+    };
+    let mut out = Vec::new();
+    JpegEncoder::new_with_quality(&mut Cursor::new(&mut out), opts.jpeg_quality.clamp(1, 100))
+        .encode_image(&img.to_rgb8())
+        .map_err(image_err)?;
+    tracing::debug!(
+        from = data.len(),
+        to = out.len(),
+        width = img.width(),
+        height = img.height(),
+        "re-encoded cover"
+    );
+    Ok(out)
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn image_err(e: image::ImageError) -> NcmError {
+    NcmError::Image(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageEncoder, Rgba, RgbaImage};
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let img = RgbaImage::from_fn(width, height, |x, y| {
+            Rgba([
+                u8::try_from(x % 256).unwrap(),
+                u8::try_from(y % 256).unwrap(),
+                128,
+                255,
+            ])
+        });
+        let mut out = Vec::new();
+        image::codecs::png::PngEncoder::new(&mut out)
+            .write_image(img.as_raw(), width, height, image::ExtendedColorType::Rgba8)
+            .unwrap();
+        out
+    }
+
+    #[test]
+    fn test_downscale_png() {
+        let opts = CoverOptions {
+            max_dimension: 100,
+            jpeg_quality: 80,
+        };
+        let out = process(&png(400, 200), &opts).unwrap();
+        assert_eq!(image::guess_format(&out).unwrap(), ImageFormat::Jpeg);
+        let img = image::load_from_memory(&out).unwrap();
+        assert_eq!(img.dimensions(), (100, 50));
+
+        // Small enough now, and JPEG: left alone.
+        assert_eq!(process(&out, &opts).unwrap(), out);
+        assert!(matches!(
+            process(b"not an image", &opts),
+            Err(NcmError::Image(_))
+        ));
+    }
+}
@@ -1,29 +1,27 @@
-use std::io::{Read, Seek, SeekFrom, Write};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
 
-use base64::Engine;
-use base64::engine::general_purpose::STANDARD as BASE64;
-
-use crate::cipher::{aes128_ecb_decrypt, rc4_ksa, rc4_stream_byte};
+use crate::cipher::{aes128_ecb_decrypt, rc4_keystream, rc4_ksa};
+use crate::container::{ContainerInfo, EncryptedContainer, ReadSeek};
 use crate::error::{NcmError, Result};
 use crate::metadata::NcmMetadata;
 
 /// NCM file magic: "CTENFDAM"
-const NCM_MAGIC: [u8; 8] = [0x43, 0x54, 0x45, 0x4E, 0x46, 0x44, 0x41, 0x4D];
+pub const NCM_MAGIC: [u8; 8] = [0x43, 0x54, 0x45, 0x4E, 0x46, 0x44, 0x41, 0x4D];
 
 /// AES key for decrypting the RC4 key data.
-const CORE_KEY: [u8; 16] = [
+pub const CORE_KEY: [u8; 16] = [
     0x68, 0x7A, 0x48, 0x52, 0x41, 0x6D, 0x73, 0x6F, 0x35, 0x6B, 0x49, 0x6E, 0x62, 0x61, 0x78, 0x57,
 ];
 
-/// AES key for decrypting the metadata.
-const MODIFY_KEY: [u8; 16] = [
-    0x23, 0x31, 0x34, 0x6C, 0x6A, 0x6B, 0x5F, 0x21, 0x5C, 0x5D, 0x26, 0x30, 0x55, 0x3C, 0x27, 0x28,
-];
-
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum AudioFormat {
     Mp3,
     Flac,
+    /// AAC in an MP4 container.
+    M4a,
+    Ogg,
 }
 
 impl AudioFormat {
@@ -31,6 +29,65 @@ impl AudioFormat {
         match self {
             Self::Mp3 => "mp3",
             Self::Flac => "flac",
+            Self::M4a => "m4a",
+            Self::Ogg => "ogg",
+        }
+    }
+
+    /// Detect MP3 (ID3 tag or frame sync), FLAC, M4A (`ftyp` box) or Ogg
+    /// from the first bytes of an audio stream; M4A needs 8 of them.
+    ///
+    /// Raw AAC (ADTS) shares the MPEG frame sync but has layer bits `00`,
+    /// and is not recognized.
+    pub fn detect(header: &[u8]) -> Option<Self> {
+        if header.starts_with(b"fLaC") {
+            Some(Self::Flac)
+        } else if header.starts_with(b"OggS") {
+            Some(Self::Ogg)
+        } else if header.get(4..8) == Some(b"ftyp") {
+            Some(Self::M4a)
+        } else if header.starts_with(b"ID3")
+            || matches!(header, [0xFF, b, ..] if b & 0xE0 == 0xE0 && b & 0x06 != 0)
+        {
+            Some(Self::Mp3)
+        } else {
+            None
+        }
+    }
+}
+
+/// PNG magic bytes for MIME detection.
+const PNG_MAGIC: [u8; 8] = [0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A];
+
+/// Format of an embedded cover image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageKind {
+    Jpeg,
+    Png,
+}
+
+impl ImageKind {
+    /// Detect PNG from the magic bytes; anything else is taken for JPEG,
+    /// the only other format the client embeds.
+    pub fn detect(data: &[u8]) -> Self {
+        if data.starts_with(&PNG_MAGIC) {
+            Self::Png
+        } else {
+            Self::Jpeg
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Jpeg => "jpg",
+            Self::Png => "png",
+        }
+    }
+
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            Self::Jpeg => "image/jpeg",
+            Self::Png => "image/png",
         }
     }
 }
@@ -42,11 +99,39 @@ pub struct NcmFile {
     pub format: AudioFormat,
     pub key_box: [u8; 256],
     pub audio_offset: u64,
+    /// One period of the keystream, derived from `key_box`.
+    keystream: [u8; 256],
+    /// Start of the audio (up to 8 bytes), read and decrypted while parsing
+    /// the header.
+    head: Vec<u8>,
 }
 
 impl NcmFile {
     /// Parse an NCM file from a reader. After this, call `dump_audio` to extract.
     pub fn parse<R: Read + Seek>(r: &mut R) -> Result<Self> {
+        let start = r.stream_position()?;
+        let mut ncm = Self::parse_header(r)?;
+        ncm.audio_offset += start;
+        Ok(ncm)
+    }
+
+    /// Like [`parse`](Self::parse), for readers that can't seek, such as
+    /// stdin or a socket: `audio_offset` is relative to where `r` started.
+    ///
+    /// The first bytes of the audio are read as well, to detect the format;
+    /// pass the same reader to
+    /// [`dump_audio_streaming`](Self::dump_audio_streaming) for the rest.
+    pub fn parse_streaming<R: Read>(r: &mut R) -> Result<Self> {
+        Self::parse_header(r)
+    }
+
+    /// Parse the header without seeking. Also reads the first 8 bytes of
+    /// audio (fewer if it is shorter) to detect the format, and keeps them
+    /// decrypted in `head`;
+    /// `audio_offset` is relative to where `r` started.
+    fn parse_header<R: Read>(r: &mut R) -> Result<Self> {
+        let r = &mut Counted { inner: r, read: 0 };
+
         // 1. Verify magic
         let mut magic = [0u8; 8];
         r.read_exact(&mut magic)?;
@@ -55,7 +140,7 @@ impl NcmFile {
         }
 
         // 2. Skip 2-byte gap
-        r.seek(SeekFrom::Current(2))?;
+        skip(r, 2)?;
 
         // 3. Read & decrypt RC4 key
         let key_len = read_u32_le(r)? as usize;
@@ -68,27 +153,13 @@ impl NcmFile {
         // Strip "neteasecloudmusic" prefix (17 bytes)
         let rc4_key = &key_decrypted[17..];
         let key_box = rc4_ksa(rc4_key);
+        let keystream = rc4_keystream(&key_box);
 
         // 4. Read & decrypt metadata
-        let meta_len = read_u32_le(r)? as usize;
-        let metadata = if meta_len > 0 {
-            let mut meta_data = vec![0u8; meta_len];
-            r.read_exact(&mut meta_data)?;
-            for b in &mut meta_data {
-                *b ^= 0x63;
-            }
-            // Strip "163 key(Don't modify):" prefix (22 bytes)
-            let b64_data = &meta_data[22..];
-            let decoded = BASE64.decode(b64_data)?;
-            let decrypted = aes128_ecb_decrypt(&MODIFY_KEY, &decoded)?;
-            // Strip "music:" prefix (6 bytes)
-            Some(NcmMetadata::from_decrypted(&decrypted[6..])?)
-        } else {
-            None
-        };
+        let metadata = read_metadata_block(r)?;
 
         // 5. Skip CRC + image version (5 bytes)
-        r.seek(SeekFrom::Current(5))?;
+        skip(r, 5)?;
 
         // 6. Read cover image
         let cover_frame_len = read_u32_le(r)?;
@@ -97,42 +168,119 @@ impl NcmFile {
             let mut img = vec![0u8; image_size as usize];
             r.read_exact(&mut img)?;
             // Skip padding
-            let padding = i64::from(cover_frame_len) - i64::from(image_size);
-            if padding > 0 {
-                r.seek(SeekFrom::Current(padding))?;
-            }
+            skip(r, u64::from(cover_frame_len.saturating_sub(image_size)))?;
             Some(img)
         } else {
-            if cover_frame_len > 0 {
-                r.seek(SeekFrom::Current(i64::from(cover_frame_len)))?;
-            }
+            skip(r, u64::from(cover_frame_len))?;
             None
         };
 
         // 7. Record audio offset
-        let audio_offset = r.stream_position()?;
+        let audio_offset = r.read;
 
-        // 8. Detect format from first 3 decrypted bytes
-        let mut header = [0u8; 3];
-        r.read_exact(&mut header)?;
-        for (i, b) in header.iter_mut().enumerate() {
-            *b ^= rc4_stream_byte(&key_box, i);
-        }
-        let format = if header == [0x49, 0x44, 0x33] {
-            AudioFormat::Mp3
-        } else {
-            AudioFormat::Flac
-        };
+        // 8. Detect format from the first decrypted bytes, however few
+        let mut head = Vec::with_capacity(8);
+        (&mut *r).take(8).read_to_end(&mut head)?;
+        decrypt_at(&keystream, 0, &mut head);
+        let format = AudioFormat::detect(&head).ok_or(NcmError::UnsupportedFormat)?;
 
-        Ok(Self {
+        tracing::trace!(
+            ?format,
+            audio_offset,
+            metadata = metadata.is_some(),
+            cover_bytes = cover_image.as_ref().map_or(0, Vec::len),
+            "parsed NCM header"
+        );
+        let ncm = Self {
             metadata,
             cover_image,
             format,
             key_box,
             audio_offset,
+            keystream,
+            head,
+        };
+        Ok(ncm)
+    }
+
+    /// Parse an NCM file that is already in memory, borrowing its cover,
+    /// metadata block and audio from `data` rather than copying them.
+    pub fn parse_slice(data: &[u8]) -> Result<NcmSlice<'_>> {
+        let mut r = data;
+        if take(&mut r, 8)? != NCM_MAGIC {
+            return Err(NcmError::InvalidMagic);
+        }
+        take(&mut r, 2)?;
+
+        let key_len = take_u32_le(&mut r)?;
+        let key_data: Vec<u8> = take(&mut r, key_len)?.iter().map(|b| b ^ 0x64).collect();
+        let key_decrypted = aes128_ecb_decrypt(&CORE_KEY, &key_data)?;
+        let key_box = rc4_ksa(&key_decrypted[17..]);
+        let keystream = rc4_keystream(&key_box);
+
+        let meta_len = take_u32_le(&mut r)?;
+        let metadata_block = Some(take(&mut r, meta_len)?).filter(|m| !m.is_empty());
+
+        take(&mut r, 5)?;
+        let cover_frame_len = take_u32_le(&mut r)?;
+        let image_size = take_u32_le(&mut r)?;
+        let cover_image = Some(take(&mut r, image_size)?).filter(|c| !c.is_empty());
+        take(&mut r, cover_frame_len.saturating_sub(image_size))?;
+
+        let audio = r;
+        let mut header = take(&mut r, audio.len().min(8))?.to_vec();
+        decrypt_at(&keystream, 0, &mut header);
+        Ok(NcmSlice {
+            format: AudioFormat::detect(&header).ok_or(NcmError::UnsupportedFormat)?,
+            key_box,
+            metadata_block,
+            cover_image,
+            audio,
+            audio_offset: (data.len() - audio.len()) as u64,
+            keystream,
         })
     }
 
+    /// Read only the cover image of an NCM stream, skipping the key and
+    /// metadata without decrypting them. `r` is left just past the image.
+    pub fn extract_cover<R: Read>(r: &mut R) -> Result<Option<(Vec<u8>, ImageKind)>> {
+        let mut magic = [0u8; 8];
+        r.read_exact(&mut magic)?;
+        if magic != NCM_MAGIC {
+            return Err(NcmError::InvalidMagic);
+        }
+        skip(r, 2)?;
+        // Key, then metadata, each behind its length.
+        for _ in 0..2 {
+            let len = read_u32_le(r)?;
+            skip(r, u64::from(len))?;
+        }
+        // CRC, image version and cover frame length.
+        skip(r, 9)?;
+        let image_size = read_u32_le(r)?;
+        if image_size == 0 {
+            return Ok(None);
+        }
+        let mut img = vec![0u8; image_size as usize];
+        r.read_exact(&mut img)?;
+        let kind = ImageKind::detect(&img);
+        Ok(Some((img, kind)))
+    }
+
+    /// Read only the metadata of an NCM stream, skipping the key and
+    /// stopping before the cover. `r` is left just past the metadata.
+    pub fn read_metadata<R: Read>(r: &mut R) -> Result<Option<NcmMetadata>> {
+        let mut magic = [0u8; 8];
+        r.read_exact(&mut magic)?;
+        if magic != NCM_MAGIC {
+            return Err(NcmError::InvalidMagic);
+        }
+        skip(r, 2)?;
+        let key_len = read_u32_le(r)?;
+        skip(r, u64::from(key_len))?;
+        read_metadata_block(r)
+    }
+
     /// Construct from pre-parsed parts (for FFI use).
     pub fn from_parts(key_box: [u8; 256], audio_offset: u64) -> Self {
         Self {
@@ -141,34 +289,825 @@ impl NcmFile {
             format: AudioFormat::Mp3, // will be overwritten by actual data
             key_box,
             audio_offset,
+            keystream: rc4_keystream(&key_box),
+            head: Vec::new(),
         }
     }
 
+    /// Decrypt `buf` in place, given that it holds the audio stream's bytes
+    /// starting `offset` bytes past [`audio_offset`](Self::audio_offset).
+    ///
+    /// The keystream depends only on the position, so any range of the
+    /// audio can be decrypted on its own (e.g. to answer HTTP range
+    /// requests without reading the file from the start).
+    pub fn decrypt_at(&self, offset: u64, buf: &mut [u8]) {
+        decrypt_at(&self.keystream, offset, buf);
+    }
+
     /// Decrypt and write the audio stream.
     pub fn dump_audio<R: Read + Seek, W: Write>(&self, r: &mut R, w: &mut W) -> Result<()> {
+        self.dump_audio_cancellable(r, w, &AtomicBool::new(false))
+    }
+
+    /// Like [`dump_audio`](Self::dump_audio), but checks `cancel` before
+    /// each chunk and stops with [`NcmError::Cancelled`] once it is set.
+    /// What was written to `w` so far is left as is.
+    pub fn dump_audio_cancellable<R: Read + Seek, W: Write>(
+        &self,
+        r: &mut R,
+        w: &mut W,
+        cancel: &AtomicBool,
+    ) -> Result<()> {
+        self.dump_audio_inner(r, w, cancel, &mut |_, _| {})
+    }
+
+    /// Like [`dump_audio`](Self::dump_audio), calling `progress` after each
+    /// chunk with the audio bytes written so far and the total: the length
+    /// of `r` past [`audio_offset`](Self::audio_offset).
+    pub fn dump_audio_with_progress<R, W, F>(
+        &self,
+        r: &mut R,
+        w: &mut W,
+        mut progress: F,
+    ) -> Result<()>
+    where
+        R: Read + Seek,
+        W: Write,
+        F: FnMut(u64, u64),
+    {
+        self.dump_audio_inner(r, w, &AtomicBool::new(false), &mut progress)
+    }
+
+    fn dump_audio_inner<R: Read + Seek, W: Write>(
+        &self,
+        r: &mut R,
+        w: &mut W,
+        cancel: &AtomicBool,
+        progress: &mut dyn FnMut(u64, u64),
+    ) -> Result<()> {
+        let total = r.seek(SeekFrom::End(0))?.saturating_sub(self.audio_offset);
         r.seek(SeekFrom::Start(self.audio_offset))?;
 
         let mut buf = vec![0u8; 0x8000];
-        let mut offset = 0usize;
+        let mut offset = 0u64;
 
         loop {
+            if cancel.load(Ordering::Relaxed) {
+                return Err(NcmError::Cancelled);
+            }
             let n = r.read(&mut buf)?;
             if n == 0 {
                 break;
             }
-            for (i, byte) in buf[..n].iter_mut().enumerate() {
-                *byte ^= rc4_stream_byte(&self.key_box, offset + i);
+            self.decrypt_at(offset, &mut buf[..n]);
+            w.write_all(&buf[..n])?;
+            offset += n as u64;
+            progress(offset, total);
+        }
+
+        Ok(())
+    }
+}
+
+/// An NCM file parsed in place by [`NcmFile::parse_slice`]: its sections
+/// point into the parsed buffer.
+pub struct NcmSlice<'a> {
+    pub format: AudioFormat,
+    pub key_box: [u8; 256],
+    /// The metadata block as stored, still encrypted; see
+    /// [`metadata`](Self::metadata).
+    pub metadata_block: Option<&'a [u8]>,
+    pub cover_image: Option<&'a [u8]>,
+    /// The encrypted audio, to the end of the buffer.
+    pub audio: &'a [u8],
+    /// Where `audio` starts in the buffer.
+    pub audio_offset: u64,
+    keystream: [u8; 256],
+}
+
+impl NcmSlice<'_> {
+    /// Decrypt the metadata block.
+    pub fn metadata(&self) -> Result<Option<NcmMetadata>> {
+        self.metadata_block
+            .map(|block| {
+                let key: Vec<u8> = block.iter().map(|b| b ^ 0x63).collect();
+                NcmMetadata::from_163_key(&String::from_utf8_lossy(&key))
+            })
+            .transpose()
+    }
+
+    /// Like [`NcmFile::decrypt_at`].
+    pub fn decrypt_at(&self, offset: u64, buf: &mut [u8]) {
+        decrypt_at(&self.keystream, offset, buf);
+    }
+
+    /// Decrypt and write the audio.
+    pub fn dump_audio<W: Write>(&self, w: &mut W) -> Result<()> {
+        let mut buf = vec![0u8; 0x8000];
+        let mut offset = 0u64;
+        for chunk in self.audio.chunks(buf.len()) {
+            let buf = &mut buf[..chunk.len()];
+            buf.copy_from_slice(chunk);
+            self.decrypt_at(offset, buf);
+            w.write_all(buf)?;
+            offset += chunk.len() as u64;
+        }
+        Ok(())
+    }
+}
+
+impl NcmFile {
+    /// Decrypt and write the rest of the audio from `r`, which
+    /// [`parse_streaming`](Self::parse_streaming) has read the header from.
+    pub fn dump_audio_streaming<R: Read, W: Write>(&self, r: &mut R, w: &mut W) -> Result<()> {
+        w.write_all(&self.head)?;
+        let mut buf = vec![0u8; 0x8000];
+        let mut offset = self.head.len() as u64;
+        loop {
+            let n = r.read(&mut buf)?;
+            if n == 0 {
+                break;
             }
+            self.decrypt_at(offset, &mut buf[..n]);
             w.write_all(&buf[..n])?;
-            offset += n;
+            offset += n as u64;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "mmap")]
+impl NcmFile {
+    /// Like [`dump_audio`](Self::dump_audio), but memory-maps the NCM file
+    /// at `path` and decrypts straight from the mapping, leaving the reads
+    /// to the OS's paging.
+    ///
+    /// The file must not be changed while this runs.
+    pub fn dump_audio_mmap<W: Write>(&self, path: &Path, w: &mut W) -> Result<()> {
+        let file = std::fs::File::open(path)?;
+        // SAFETY: the mapping is only read, and callers promise not to
+        // change the file meanwhile.
+        #[allow(unsafe_code)]
+        let map = unsafe { memmap2::Mmap::map(&file)? };
+        #[cfg(unix)]
+        map.advise(memmap2::Advice::Sequential).ok();
+
+        let audio = usize::try_from(self.audio_offset)
+            .ok()
+            .and_then(|start| map.get(start..))
+            .ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof))?;
+        let mut buf = vec![0u8; 0x8000];
+        let mut offset = 0u64;
+        for chunk in audio.chunks(buf.len()) {
+            let buf = &mut buf[..chunk.len()];
+            buf.copy_from_slice(chunk);
+            self.decrypt_at(offset, buf);
+            w.write_all(buf)?;
+            offset += chunk.len() as u64;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl NcmFile {
+    /// Like [`parse`](Self::parse), for tokio readers.
+    pub async fn parse_async<R>(r: &mut R) -> Result<Self>
+    where
+        R: tokio::io::AsyncRead + tokio::io::AsyncSeek + Unpin,
+    {
+        use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+        let start = r.stream_position().await?;
+        // Read the header's sections by their lengths, then parse the
+        // buffer as usual.
+        let mut header = Vec::new();
+        read_more(r, &mut header, 8 + 2 + 4).await?;
+        if header[..8] != NCM_MAGIC {
+            return Err(NcmError::InvalidMagic);
         }
+        let key_len = u32_at(&header, header.len() - 4);
+        read_more(r, &mut header, key_len + 4).await?;
+        let meta_len = u32_at(&header, header.len() - 4);
+        read_more(r, &mut header, meta_len + 5 + 8).await?;
+        let cover_frame_len = u32_at(&header, header.len() - 8);
+        let image_size = u32_at(&header, header.len() - 4);
+        // The cover and its padding, then the bytes telling the format.
+        read_more(r, &mut header, cover_frame_len.max(image_size)).await?;
+        (&mut *r).take(8).read_to_end(&mut header).await?;
 
+        let mut ncm = Self::parse_header(&mut header.as_slice())?;
+        ncm.audio_offset += start;
+        Ok(ncm)
+    }
+
+    /// Like [`dump_audio`](Self::dump_audio), for tokio readers and
+    /// writers. `w` is flushed at the end.
+    pub async fn dump_audio_async<R, W>(&self, r: &mut R, w: &mut W) -> Result<()>
+    where
+        R: tokio::io::AsyncRead + tokio::io::AsyncSeek + Unpin,
+        W: tokio::io::AsyncWrite + Unpin,
+    {
+        use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+
+        r.seek(SeekFrom::Start(self.audio_offset)).await?;
+
+        let mut buf = vec![0u8; 0x8000];
+        let mut offset = 0u64;
+
+        loop {
+            let n = r.read(&mut buf).await?;
+            if n == 0 {
+                break;
+            }
+            self.decrypt_at(offset, &mut buf[..n]);
+            w.write_all(&buf[..n]).await?;
+            offset += n as u64;
+        }
+
+        // Tokio writers don't flush on drop.
+        w.flush().await?;
         Ok(())
     }
 }
 
+/// Append the next `n` bytes of `r` to `buf`.
+#[cfg(feature = "tokio")]
+async fn read_more<R: tokio::io::AsyncRead + Unpin>(
+    r: &mut R,
+    buf: &mut Vec<u8>,
+    n: usize,
+) -> Result<()> {
+    use tokio::io::AsyncReadExt;
+
+    let start = buf.len();
+    buf.resize(start + n, 0);
+    r.read_exact(&mut buf[start..]).await?;
+    Ok(())
+}
+
+#[cfg(feature = "tokio")]
+fn u32_at(buf: &[u8], pos: usize) -> usize {
+    let bytes = buf[pos..pos + 4].try_into().expect("4 bytes");
+    u32::from_le_bytes(bytes) as usize
+}
+
+/// Decrypts an NCM stream as it is read, for piping the audio into a
+/// player or transcoder without writing it out first.
+///
+/// The header is parsed when the reader is created, so `inner` only has to
+/// implement [`Read`]: a pipe, socket or HTTP body works as well as a file.
+/// Reads then return the audio, exactly as [`NcmFile::dump_audio`] writes
+/// it.
+pub struct NcmReader<R> {
+    inner: R,
+    ncm: NcmFile,
+    /// Audio bytes returned so far.
+    offset: u64,
+}
+
+impl<R: Read> NcmReader<R> {
+    /// Parse the header of the NCM stream `inner`, leaving it at the audio.
+    pub fn new(mut inner: R) -> Result<Self> {
+        let ncm = NcmFile::parse_header(&mut inner)?;
+        Ok(Self {
+            inner,
+            ncm,
+            offset: 0,
+        })
+    }
+}
+
+impl<R> NcmReader<R> {
+    pub fn format(&self) -> AudioFormat {
+        self.ncm.format
+    }
+
+    pub fn metadata(&self) -> Option<&NcmMetadata> {
+        self.ncm.metadata.as_ref()
+    }
+
+    pub fn cover(&self) -> Option<&[u8]> {
+        self.ncm.cover_image.as_deref()
+    }
+
+    /// The underlying reader, positioned somewhere in the audio.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read> Read for NcmReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        #[allow(clippy::cast_possible_truncation)] // below `head.len()`
+        let pos = self.offset as usize;
+        let head = &self.ncm.head;
+        let n = if pos < head.len() {
+            let n = buf.len().min(head.len() - pos);
+            buf[..n].copy_from_slice(&head[pos..pos + n]);
+            n
+        } else {
+            let n = self.inner.read(buf)?;
+            self.ncm.decrypt_at(self.offset, &mut buf[..n]);
+            n
+        };
+        self.offset += n as u64;
+        Ok(n)
+    }
+}
+
+/// NCM files, as an [`EncryptedContainer`].
+pub struct Ncm;
+
+impl EncryptedContainer for Ncm {
+    fn name(&self) -> &'static str {
+        "ncm"
+    }
+
+    fn probe(&self, _path: &Path, header: &[u8]) -> bool {
+        header.starts_with(&NCM_MAGIC)
+    }
+
+    fn metadata(&self, r: &mut dyn ReadSeek) -> Result<ContainerInfo> {
+        r.seek(SeekFrom::Start(0))?;
+        let ncm = NcmFile::parse(&mut &mut *r)?;
+        Ok(ContainerInfo {
+            format: ncm.format,
+            audio_len: r.seek(SeekFrom::End(0))?.saturating_sub(ncm.audio_offset),
+            metadata: ncm.metadata,
+            cover: ncm.cover_image,
+        })
+    }
+
+    fn dump_audio(&self, r: &mut dyn ReadSeek, w: &mut dyn Write) -> Result<()> {
+        r.seek(SeekFrom::Start(0))?;
+        let (mut r, mut w) = (r, w);
+        NcmFile::parse(&mut r)?.dump_audio(&mut r, &mut w)
+    }
+}
+
+/// Decrypt `buf`, the audio from `offset` on, with one period of the
+/// keystream.
+fn decrypt_at(keystream: &[u8; 256], offset: u64, buf: &mut [u8]) {
+    // The keystream repeats every 256 bytes: line the buffer up with
+    // it, then XOR whole periods, which the compiler vectorizes.
+    let start = (offset % 256) as usize;
+    let (head, rest) = buf.split_at_mut(buf.len().min(256 - start));
+    xor(head, &keystream[start..]);
+    for chunk in rest.chunks_mut(256) {
+        xor(chunk, keystream);
+    }
+}
+
+/// XOR `key` into `buf`, up to the shorter of the two, with the widest
+/// vector instructions the CPU has.
+#[inline]
+fn xor(buf: &mut [u8], key: &[u8]) {
+    let len = buf.len().min(key.len());
+    let (buf, key) = (&mut buf[..len], &key[..len]);
+    let done = simd::xor(buf, key);
+    xor_scalar(&mut buf[done..], &key[done..]);
+}
+
+fn xor_scalar(buf: &mut [u8], key: &[u8]) {
+    for (b, k) in buf.iter_mut().zip(key) {
+        *b ^= k;
+    }
+}
+
+/// Vectorized XOR of equally long `buf` and `key`. Each returns how many
+/// leading bytes it handled; the rest is shorter than one vector.
+#[allow(unsafe_code)]
+mod simd {
+    #[cfg(target_arch = "x86_64")]
+    pub fn xor(buf: &mut [u8], key: &[u8]) -> usize {
+        if std::arch::is_x86_feature_detected!("avx2") {
+            // SAFETY: the CPU supports AVX2, checked above.
+            unsafe { xor_avx2(buf, key) }
+        } else {
+            // SAFETY: SSE2 is part of x86_64.
+            unsafe { xor_sse2(buf, key) }
+        }
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    #[target_feature(enable = "avx2")]
+    #[allow(clippy::cast_ptr_alignment)] // unaligned loads and stores
+    unsafe fn xor_avx2(buf: &mut [u8], key: &[u8]) -> usize {
+        use std::arch::x86_64::{
+            __m256i, _mm256_loadu_si256, _mm256_storeu_si256, _mm256_xor_si256,
+        };
+
+        let mut done = 0;
+        for (b, k) in buf.chunks_exact_mut(32).zip(key.chunks_exact(32)) {
+            let b = b.as_mut_ptr().cast::<__m256i>();
+            // SAFETY: both chunks are 32 bytes long.
+            unsafe {
+                let x =
+                    _mm256_xor_si256(_mm256_loadu_si256(b), _mm256_loadu_si256(k.as_ptr().cast()));
+                _mm256_storeu_si256(b, x);
+            }
+            done += 32;
+        }
+        done
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    #[target_feature(enable = "sse2")]
+    #[allow(clippy::cast_ptr_alignment)] // unaligned loads and stores
+    unsafe fn xor_sse2(buf: &mut [u8], key: &[u8]) -> usize {
+        use std::arch::x86_64::{__m128i, _mm_loadu_si128, _mm_storeu_si128, _mm_xor_si128};
+
+        let mut done = 0;
+        for (b, k) in buf.chunks_exact_mut(16).zip(key.chunks_exact(16)) {
+            let b = b.as_mut_ptr().cast::<__m128i>();
+            // SAFETY: both chunks are 16 bytes long.
+            unsafe {
+                let x = _mm_xor_si128(_mm_loadu_si128(b), _mm_loadu_si128(k.as_ptr().cast()));
+                _mm_storeu_si128(b, x);
+            }
+            done += 16;
+        }
+        done
+    }
+
+    #[cfg(target_arch = "aarch64")]
+    pub fn xor(buf: &mut [u8], key: &[u8]) -> usize {
+        if std::arch::is_aarch64_feature_detected!("neon") {
+            // SAFETY: the CPU supports NEON, checked above.
+            unsafe { xor_neon(buf, key) }
+        } else {
+            0
+        }
+    }
+
+    #[cfg(target_arch = "aarch64")]
+    #[target_feature(enable = "neon")]
+    unsafe fn xor_neon(buf: &mut [u8], key: &[u8]) -> usize {
+        use std::arch::aarch64::{veorq_u8, vld1q_u8, vst1q_u8};
+
+        let mut done = 0;
+        for (b, k) in buf.chunks_exact_mut(16).zip(key.chunks_exact(16)) {
+            // SAFETY: both chunks are 16 bytes long.
+            unsafe {
+                let x = veorq_u8(vld1q_u8(b.as_ptr()), vld1q_u8(k.as_ptr()));
+                vst1q_u8(b.as_mut_ptr(), x);
+            }
+            done += 16;
+        }
+        done
+    }
+
+    /// No vector path: everything is left to the scalar loop.
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+    pub fn xor(_buf: &mut [u8], _key: &[u8]) -> usize {
+        0
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::super::xor_scalar;
+
+        #[test]
+        fn test_vector_paths_match_scalar() {
+            let key: Vec<u8> = (0..=255u8).map(|b| b.wrapping_mul(31)).collect();
+            let data: Vec<u8> = (0..=255u8).rev().collect();
+            for len in [0, 1, 15, 16, 17, 31, 32, 33, 100, 256] {
+                let mut expected = data[..len].to_vec();
+                xor_scalar(&mut expected, &key[..len]);
+
+                let mut buf = data[..len].to_vec();
+                let done = super::xor(&mut buf, &key[..len]);
+                xor_scalar(&mut buf[done..], &key[done..len]);
+                assert_eq!(buf, expected, "len {len}");
+
+                #[cfg(target_arch = "x86_64")]
+                {
+                    let mut buf = data[..len].to_vec();
+                    // SAFETY: SSE2 is part of x86_64.
+                    let done = unsafe { super::xor_sse2(&mut buf, &key[..len]) };
+                    xor_scalar(&mut buf[done..], &key[done..len]);
+                    assert_eq!(buf, expected, "sse2, len {len}");
+                }
+            }
+        }
+    }
+}
+
+/// Counts the bytes read through it, for offsets in streams that can't
+/// seek.
+struct Counted<'a, R> {
+    inner: &'a mut R,
+    read: u64,
+}
+
+impl<R: Read> Read for Counted<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.read += n as u64;
+        Ok(n)
+    }
+}
+
+/// Read the length-prefixed metadata block and decrypt it.
+fn read_metadata_block<R: Read>(r: &mut R) -> Result<Option<NcmMetadata>> {
+    let meta_len = read_u32_le(r)? as usize;
+    if meta_len == 0 {
+        return Ok(None);
+    }
+    let mut meta_data = vec![0u8; meta_len];
+    r.read_exact(&mut meta_data)?;
+    for b in &mut meta_data {
+        *b ^= 0x63;
+    }
+    // "163 key(Don't modify):" + base64 of the AES-encrypted JSON
+    Ok(Some(NcmMetadata::from_163_key(&String::from_utf8_lossy(
+        &meta_data,
+    ))?))
+}
+
+/// Split the first `n` bytes off `r`.
+fn take<'a, N: TryInto<usize>>(r: &mut &'a [u8], n: N) -> Result<&'a [u8]> {
+    let (head, rest) = n
+        .try_into()
+        .ok()
+        .and_then(|n| r.split_at_checked(n))
+        .ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof))?;
+    *r = rest;
+    Ok(head)
+}
+
+fn take_u32_le(r: &mut &[u8]) -> Result<u32> {
+    let bytes = take(r, 4)?.try_into().expect("4 bytes");
+    Ok(u32::from_le_bytes(bytes))
+}
+
+/// Read past `n` bytes.
+fn skip<R: Read>(r: &mut R, n: u64) -> Result<()> {
+    let skipped = io::copy(&mut r.take(n), &mut io::sink())?;
+    if skipped < n {
+        return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+    }
+    Ok(())
+}
+
 fn read_u32_le<R: Read>(r: &mut R) -> Result<u32> {
     let mut buf = [0u8; 4];
     r.read_exact(&mut buf)?;
     Ok(u32::from_le_bytes(buf))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_NCM: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../testdata/test.ncm");
+
+    #[test]
+    fn test_extract_cover() {
+        let bytes = std::fs::read(TEST_NCM).unwrap();
+        let ncm = NcmFile::parse(&mut io::Cursor::new(&bytes)).unwrap();
+        let (cover, kind) = NcmFile::extract_cover(&mut bytes.as_slice())
+            .unwrap()
+            .unwrap();
+        assert_eq!(Some(&cover), ncm.cover_image.as_ref());
+        assert_eq!(kind, ImageKind::detect(&cover));
+
+        let mut bare = Vec::new();
+        crate::encode(&mut &b"fLaC"[..], None, None, &mut bare).unwrap();
+        assert!(
+            NcmFile::extract_cover(&mut bare.as_slice())
+                .unwrap()
+                .is_none()
+        );
+        assert!(matches!(
+            NcmFile::extract_cover(&mut &b"fLaC0000"[..]),
+            Err(NcmError::InvalidMagic)
+        ));
+    }
+
+    #[test]
+    fn test_read_metadata() {
+        let bytes = std::fs::read(TEST_NCM).unwrap();
+        let ncm = NcmFile::parse(&mut io::Cursor::new(&bytes)).unwrap();
+        let meta = NcmFile::read_metadata(&mut bytes.as_slice())
+            .unwrap()
+            .unwrap();
+        let expected = ncm.metadata.unwrap();
+        assert_eq!(meta.music_id, expected.music_id);
+        assert_eq!(meta.music_name, expected.music_name);
+
+        let mut bare = Vec::new();
+        crate::encode(&mut &b"fLaC"[..], None, None, &mut bare).unwrap();
+        assert!(
+            NcmFile::read_metadata(&mut bare.as_slice())
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_decrypt_at_matches_stream_bytes() {
+        let key_box = rc4_ksa(b"testkey");
+        let ncm = NcmFile::from_parts(key_box, 0);
+        let data: Vec<u8> = (0..=255u8)
+            .cycle()
+            .take(1000)
+            .map(|b| b.wrapping_mul(7))
+            .collect();
+        for (offset, len) in [(0, 1000), (3, 600), (255, 2), (256, 300), (700, 0)] {
+            let mut buf = data[..len].to_vec();
+            ncm.decrypt_at(offset, &mut buf);
+            for (i, (b, d)) in buf.iter().zip(&data).enumerate() {
+                let pos = usize::try_from(offset).unwrap() + i;
+                assert_eq!(*b, d ^ crate::cipher::rc4_stream_byte(&key_box, pos));
+            }
+        }
+    }
+
+    #[test]
+    fn test_reader_matches_dump() {
+        let bytes = std::fs::read(TEST_NCM).unwrap();
+        let mut cursor = io::Cursor::new(&bytes);
+        let ncm = NcmFile::parse(&mut cursor).unwrap();
+        let mut dumped = Vec::new();
+        ncm.dump_audio(&mut cursor, &mut dumped).unwrap();
+
+        // A plain `Read`, handing out a few bytes at a time.
+        let mut reader = NcmReader::new(io::BufReader::with_capacity(7, &bytes[..])).unwrap();
+        assert_eq!(reader.format(), ncm.format);
+        assert_eq!(reader.cover(), ncm.cover_image.as_deref());
+        let mut streamed = Vec::new();
+        let mut buf = [0u8; 2];
+        loop {
+            let n = reader.read(&mut buf).unwrap();
+            if n == 0 {
+                break;
+            }
+            streamed.extend_from_slice(&buf[..n]);
+        }
+        assert_eq!(streamed, dumped);
+    }
+
+    #[test]
+    fn test_detect_format() {
+        let parse = |audio: &[u8]| {
+            let mut ncm = Vec::new();
+            crate::encode(&mut &audio[..], None, None, &mut ncm).unwrap();
+            NcmFile::parse(&mut io::Cursor::new(ncm)).map(|ncm| ncm.format)
+        };
+        assert_eq!(parse(b"ID3\x04\0\0\0\0").unwrap(), AudioFormat::Mp3);
+        // MPEG-1 Layer III frame header, no ID3 tag.
+        assert_eq!(
+            parse(b"\xFF\xFB\x90\x64\0\0\0\0").unwrap(),
+            AudioFormat::Mp3
+        );
+        assert_eq!(parse(b"fLaC\0\0\0\x22").unwrap(), AudioFormat::Flac);
+        assert_eq!(parse(b"\0\0\0\x20ftypM4A ").unwrap(), AudioFormat::M4a);
+        assert_eq!(parse(b"OggS\0\x02\0\0").unwrap(), AudioFormat::Ogg);
+        assert!(matches!(
+            parse(b"RIFF\0\0\0\0WAVE"),
+            Err(NcmError::UnsupportedFormat)
+        ));
+        // AAC ADTS: frame sync, but layer bits 00.
+        assert!(matches!(
+            parse(b"\xFF\xF1\x50\x80\0\x1F\xFC\0"),
+            Err(NcmError::UnsupportedFormat)
+        ));
+        // Payloads shorter than 8 bytes are detected on what there is.
+        assert_eq!(parse(b"ID3").unwrap(), AudioFormat::Mp3);
+        assert_eq!(parse(b"fLaC").unwrap(), AudioFormat::Flac);
+        let mut ncm = Vec::new();
+        crate::encode(&mut &b"fLaC"[..], None, None, &mut ncm).unwrap();
+        assert_eq!(
+            NcmFile::parse_slice(&ncm).unwrap().format,
+            AudioFormat::Flac
+        );
+    }
+
+    #[test]
+    fn test_slice_matches_parse() {
+        let bytes = std::fs::read(TEST_NCM).unwrap();
+        let mut cursor = io::Cursor::new(&bytes);
+        let ncm = NcmFile::parse(&mut cursor).unwrap();
+        let mut dumped = Vec::new();
+        ncm.dump_audio(&mut cursor, &mut dumped).unwrap();
+
+        let slice = NcmFile::parse_slice(&bytes).unwrap();
+        assert_eq!(slice.format, ncm.format);
+        assert_eq!(slice.audio_offset, ncm.audio_offset);
+        assert_eq!(slice.key_box, ncm.key_box);
+        assert_eq!(slice.cover_image, ncm.cover_image.as_deref());
+        assert_eq!(
+            slice.metadata().unwrap().map(|m| m.music_id),
+            ncm.metadata.map(|m| m.music_id)
+        );
+        let mut sliced = Vec::new();
+        slice.dump_audio(&mut sliced).unwrap();
+        assert_eq!(sliced, dumped);
+
+        assert!(matches!(
+            NcmFile::parse_slice(&bytes[..100]),
+            Err(NcmError::Io(_))
+        ));
+    }
+
+    #[test]
+    fn test_streaming_matches_dump() {
+        let bytes = std::fs::read(TEST_NCM).unwrap();
+        let mut cursor = io::Cursor::new(&bytes);
+        let ncm = NcmFile::parse(&mut cursor).unwrap();
+        let mut dumped = Vec::new();
+        ncm.dump_audio(&mut cursor, &mut dumped).unwrap();
+
+        let mut r = io::BufReader::with_capacity(7, &bytes[..]);
+        let streaming = NcmFile::parse_streaming(&mut r).unwrap();
+        assert_eq!(streaming.format, ncm.format);
+        assert_eq!(streaming.audio_offset, ncm.audio_offset);
+        let mut streamed = Vec::new();
+        streaming
+            .dump_audio_streaming(&mut r, &mut streamed)
+            .unwrap();
+        assert_eq!(streamed, dumped);
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn test_mmap_matches_dump() {
+        let mut file = std::fs::File::open(TEST_NCM).unwrap();
+        let ncm = NcmFile::parse(&mut file).unwrap();
+        let mut dumped = Vec::new();
+        ncm.dump_audio(&mut file, &mut dumped).unwrap();
+
+        let mut mapped = Vec::new();
+        ncm.dump_audio_mmap(std::path::Path::new(TEST_NCM), &mut mapped)
+            .unwrap();
+        assert_eq!(mapped, dumped);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn test_async_matches_sync() {
+        let bytes = std::fs::read(TEST_NCM).unwrap();
+        let mut cursor = io::Cursor::new(&bytes);
+        let ncm = NcmFile::parse(&mut cursor).unwrap();
+        let mut dumped = Vec::new();
+        ncm.dump_audio(&mut cursor, &mut dumped).unwrap();
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            // Starting past other data, as in a tokio file opened at an offset.
+            let mut padded = vec![0u8; 10];
+            padded.extend_from_slice(&bytes);
+            let mut cursor = io::Cursor::new(padded);
+            tokio::io::AsyncSeekExt::seek(&mut cursor, SeekFrom::Start(10))
+                .await
+                .unwrap();
+            let parsed = NcmFile::parse_async(&mut cursor).await.unwrap();
+            assert_eq!(parsed.audio_offset, ncm.audio_offset + 10);
+            assert_eq!(parsed.format, ncm.format);
+            assert_eq!(parsed.cover_image, ncm.cover_image);
+            let mut streamed = Vec::new();
+            parsed
+                .dump_audio_async(&mut cursor, &mut streamed)
+                .await
+                .unwrap();
+            assert_eq!(streamed, dumped);
+
+            let mut garbage = io::Cursor::new(b"not an ncm file".to_vec());
+            assert!(matches!(
+                NcmFile::parse_async(&mut garbage).await,
+                Err(NcmError::InvalidMagic)
+            ));
+        });
+    }
+
+    #[test]
+    fn test_dump_progress() {
+        let bytes = std::fs::read(TEST_NCM).unwrap();
+        let mut cursor = io::Cursor::new(&bytes);
+        let ncm = NcmFile::parse(&mut cursor).unwrap();
+        let total = bytes.len() as u64 - ncm.audio_offset;
+
+        let mut calls = Vec::new();
+        let mut out = Vec::new();
+        ncm.dump_audio_with_progress(&mut cursor, &mut out, |done, of| calls.push((done, of)))
+            .unwrap();
+        assert!(calls.windows(2).all(|w| w[0].0 < w[1].0));
+        assert!(calls.iter().all(|&(_, of)| of == total));
+        assert_eq!(calls.last(), Some(&(total, total)));
+        assert_eq!(out.len() as u64, total);
+    }
+
+    #[test]
+    fn test_reader_truncated() {
+        let bytes = std::fs::read(TEST_NCM).unwrap();
+        assert!(NcmReader::new(&bytes[..64]).is_err());
+        assert!(matches!(
+            NcmReader::new(&b"not an ncm file"[..]),
+            Err(NcmError::InvalidMagic)
+        ));
+    }
+}
@@ -0,0 +1,132 @@
+use std::io::{self, Read, Write};
+
+use crate::cipher::{aes128_ecb_encrypt, rc4_ksa};
+use crate::decoder::{CORE_KEY, NCM_MAGIC, NcmFile};
+use crate::error::Result;
+use crate::metadata::NcmMetadata;
+
+/// RC4 key of the files written by [`encode`]. Any key works; a fixed one
+/// keeps the output reproducible.
+const RC4_KEY: &[u8] = b"117004921811758E7fT49x7dof9OKCgg9cdvhEuezy3iZCL1nFvBFd1T4uSktAJKmwZXsijPbijliionVUXXg9plTbXEclAE9Lb";
+
+/// Package `audio` (an MP3 or FLAC stream), with optional metadata and
+/// cover, into an NCM container written to `w`.
+///
+/// The result decodes with [`NcmFile::parse`] back to the same audio,
+/// metadata and cover.
+pub fn encode<R: Read, W: Write>(
+    audio: &mut R,
+    metadata: Option<&NcmMetadata>,
+    cover: Option<&[u8]>,
+    w: &mut W,
+) -> Result<()> {
+    w.write_all(&NCM_MAGIC)?;
+    w.write_all(&[0; 2])?;
+
+    // RC4 key, behind the same prefix and masks the decoder strips.
+    let mut key = b"neteasecloudmusic".to_vec();
+    key.extend_from_slice(RC4_KEY);
+    let mut key_block = aes128_ecb_encrypt(&CORE_KEY, &key);
+    for b in &mut key_block {
+        *b ^= 0x64;
+    }
+    write_block(w, &key_block)?;
+
+    let mut meta_block = match metadata {
+        Some(meta) => meta.to_163_key()?.into_bytes(),
+        None => Vec::new(),
+    };
+    for b in &mut meta_block {
+        *b ^= 0x63;
+    }
+    write_block(w, &meta_block)?;
+
+    // CRC and image version, which readers ignore.
+    w.write_all(&[0; 5])?;
+
+    // The cover's frame, without padding, then the image.
+    let cover = cover.unwrap_or_default();
+    let cover_len = block_len(cover)?;
+    w.write_all(&cover_len.to_le_bytes())?;
+    write_block(w, cover)?;
+
+    let ncm = NcmFile::from_parts(rc4_ksa(RC4_KEY), 0);
+    let mut buf = vec![0u8; 0x8000];
+    let mut offset = 0u64;
+    loop {
+        let n = audio.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        // The keystream is XORed in, so decrypting also encrypts.
+        ncm.decrypt_at(offset, &mut buf[..n]);
+        w.write_all(&buf[..n])?;
+        offset += n as u64;
+    }
+    Ok(())
+}
+
+/// Write `data` after its length as a little-endian `u32`.
+fn write_block<W: Write>(w: &mut W, data: &[u8]) -> Result<()> {
+    w.write_all(&block_len(data)?.to_le_bytes())?;
+    w.write_all(data)?;
+    Ok(())
+}
+
+fn block_len(data: &[u8]) -> Result<u32> {
+    u32::try_from(data.len())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "NCM section over 4 GiB").into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AudioFormat;
+
+    const TEST_NCM: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../testdata/test.ncm");
+
+    #[test]
+    fn test_encode_roundtrip() {
+        let bytes = std::fs::read(TEST_NCM).unwrap();
+        let mut cursor = io::Cursor::new(&bytes);
+        let ncm = NcmFile::parse(&mut cursor).unwrap();
+        let mut audio = Vec::new();
+        ncm.dump_audio(&mut cursor, &mut audio).unwrap();
+
+        let mut encoded = Vec::new();
+        encode(
+            &mut audio.as_slice(),
+            ncm.metadata.as_ref(),
+            ncm.cover_image.as_deref(),
+            &mut encoded,
+        )
+        .unwrap();
+
+        let mut cursor = io::Cursor::new(&encoded);
+        let parsed = NcmFile::parse(&mut cursor).unwrap();
+        assert_eq!(parsed.format, ncm.format);
+        assert_eq!(parsed.cover_image, ncm.cover_image);
+        let mut decoded = Vec::new();
+        parsed.dump_audio(&mut cursor, &mut decoded).unwrap();
+        assert_eq!(decoded, audio);
+        let (meta, parsed_meta) = (ncm.metadata.unwrap(), parsed.metadata.unwrap());
+        assert_eq!(parsed_meta.music_id, meta.music_id);
+        assert_eq!(parsed_meta.music_name, meta.music_name);
+    }
+
+    #[test]
+    fn test_encode_bare() {
+        let audio = b"fLaC and then some frames";
+        let mut encoded = Vec::new();
+        encode(&mut &audio[..], None, None, &mut encoded).unwrap();
+
+        let mut cursor = io::Cursor::new(&encoded);
+        let parsed = NcmFile::parse(&mut cursor).unwrap();
+        assert_eq!(parsed.format, AudioFormat::Flac);
+        assert!(parsed.metadata.is_none());
+        assert!(parsed.cover_image.is_none());
+        let mut decoded = Vec::new();
+        parsed.dump_audio(&mut cursor, &mut decoded).unwrap();
+        assert_eq!(decoded, audio);
+    }
+}
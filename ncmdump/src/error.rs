@@ -17,6 +17,16 @@ pub enum NcmError {
     UnsupportedFormat,
     #[error("tagging error: {0}")]
     Tag(String),
+    #[error("invalid name template: {0}")]
+    Template(String),
+    #[error("cannot decode audio: {0}")]
+    Decode(String),
+    #[error("transcoding failed: {0}")]
+    Transcode(String),
+    #[error("cannot process cover image: {0}")]
+    Image(String),
+    #[error("cancelled")]
+    Cancelled,
 }
 
 pub type Result<T> = std::result::Result<T, NcmError>;
@@ -0,0 +1,191 @@
+//! Kuwo Music's encrypted `.kwm` files.
+//!
+//! A 1 KiB header starting with `yeelion-kuwo` precedes the audio. The
+//! audio is XOR-ed with a repeating 32-byte mask: the decimal digits of a
+//! 64-bit key stored in the header (at `0x18`, little-endian), repeated or
+//! cut to 32 bytes, XOR-ed with a fixed string.
+//!
+//! Files whose header starts with `yeelion-kuwo` and four zero bytes
+//! instead of `yeelion-kuwo-tme` derive their key with DES, which is not
+//! implemented: they are recognised but rejected.
+
+use std::io::{Read, SeekFrom, Write};
+use std::path::Path;
+
+use crate::container::{ContainerInfo, EncryptedContainer, ReadSeek};
+use crate::decoder::AudioFormat;
+use crate::error::{NcmError, Result};
+
+/// Header magic of the files decrypted here.
+const MAGIC: &[u8; 16] = b"yeelion-kuwo-tme";
+
+/// Header magic of the DES-keyed variant, which is not supported.
+const DES_MAGIC: &[u8; 16] = b"yeelion-kuwo\0\0\0\0";
+
+const HEADER_LEN: usize = 0x400;
+
+/// Combined with the header key into the mask.
+const FIXED_KEY: &[u8; 32] = b"MoOtOiTvINGwd2E6n0E1i7L5t2IoOoNk";
+
+/// Whether `path` has the `.kwm` extension.
+pub fn is_kwm_file(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("kwm"))
+}
+
+/// Decrypt a KWM stream into `w`, returning the detected audio format.
+///
+/// # Errors
+///
+/// Returns [`NcmError::InvalidMagic`] if `r` is not a KWM file,
+/// [`NcmError::Decrypt`] for the DES-keyed variant, and
+/// [`NcmError::UnsupportedFormat`] if [`AudioFormat::detect`] doesn't
+/// recognise the audio.
+pub fn decrypt<R: Read, W: Write>(r: &mut R, w: &mut W) -> Result<AudioFormat> {
+    let mut header = vec![0u8; HEADER_LEN];
+    r.read_exact(&mut header)?;
+    let mask = read_mask(&header)?;
+
+    let mut buf = vec![0u8; 0x8000];
+    let mut offset = 0;
+    let mut format = None;
+    loop {
+        let n = r.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        apply(&mask, offset, &mut buf[..n]);
+        if format.is_none() {
+            format = Some(AudioFormat::detect(&buf[..n]).ok_or(NcmError::UnsupportedFormat)?);
+        }
+        w.write_all(&buf[..n])?;
+        offset += n;
+    }
+    format.ok_or(NcmError::UnsupportedFormat)
+}
+
+/// KWM files, as an [`EncryptedContainer`]. The audio keeps its own tags.
+pub struct Kwm;
+
+impl EncryptedContainer for Kwm {
+    fn name(&self) -> &'static str {
+        "kwm"
+    }
+
+    fn probe(&self, path: &Path, header: &[u8]) -> bool {
+        [MAGIC, DES_MAGIC].iter().any(|m| header.starts_with(*m)) || is_kwm_file(path)
+    }
+
+    fn metadata(&self, r: &mut dyn ReadSeek) -> Result<ContainerInfo> {
+        let len = r.seek(SeekFrom::End(0))?;
+        let mut header = Vec::new();
+        r.seek(SeekFrom::Start(0))?;
+        (&mut *r)
+            .take(HEADER_LEN as u64 + 4)
+            .read_to_end(&mut header)?;
+        let mask = read_mask(&header)?;
+        let mut start = header.split_off(HEADER_LEN);
+        apply(&mask, 0, &mut start);
+        Ok(ContainerInfo {
+            format: AudioFormat::detect(&start).ok_or(NcmError::UnsupportedFormat)?,
+            metadata: None,
+            cover: None,
+            audio_len: len - HEADER_LEN as u64,
+        })
+    }
+
+    fn dump_audio(&self, r: &mut dyn ReadSeek, w: &mut dyn Write) -> Result<()> {
+        r.seek(SeekFrom::Start(0))?;
+        let (mut r, mut w) = (r, w);
+        decrypt(&mut r, &mut w).map(drop)
+    }
+}
+
+/// Check the magic of `header` and build the mask from its key.
+fn read_mask(header: &[u8]) -> Result<[u8; 32]> {
+    if header.starts_with(DES_MAGIC) {
+        return Err(NcmError::Decrypt(
+            "KWM files with a DES-keyed header are not supported".into(),
+        ));
+    }
+    if header.len() < HEADER_LEN || !header.starts_with(MAGIC) {
+        return Err(NcmError::InvalidMagic);
+    }
+    let key = u64::from_le_bytes(header[0x18..0x20].try_into().expect("8 bytes"));
+    let digits = key.to_string().into_bytes();
+    Ok(std::array::from_fn(|i| {
+        FIXED_KEY[i] ^ digits[i % digits.len()]
+    }))
+}
+
+/// XOR the mask into `buf`, which starts `offset` bytes into the audio.
+fn apply(mask: &[u8; 32], offset: usize, buf: &mut [u8]) {
+    for (i, b) in buf.iter_mut().enumerate() {
+        *b ^= mask[(offset + i) % 32];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kwm(key: u64, plain: &[u8]) -> Vec<u8> {
+        let mut header = vec![0u8; HEADER_LEN];
+        header[..16].copy_from_slice(MAGIC);
+        header[0x18..0x20].copy_from_slice(&key.to_le_bytes());
+        let mask = read_mask(&header).unwrap();
+        let mut audio = plain.to_vec();
+        apply(&mask, 0, &mut audio);
+        header.extend(audio);
+        header
+    }
+
+    #[test]
+    fn test_mask() {
+        let mut header = vec![0u8; HEADER_LEN];
+        header[..16].copy_from_slice(MAGIC);
+        header[0x18..0x20].copy_from_slice(&123u64.to_le_bytes());
+        let mask = read_mask(&header).unwrap();
+        assert_eq!(mask[0], b'M' ^ b'1');
+        assert_eq!(mask[4], b'O' ^ b'2');
+        assert_eq!(mask[31], b'k' ^ b'2');
+    }
+
+    #[test]
+    fn test_des_variant_rejected() {
+        let mut data = vec![0u8; HEADER_LEN + 16];
+        data[..16].copy_from_slice(DES_MAGIC);
+        assert!(Kwm.probe(Path::new("song.bin"), &data));
+        assert!(matches!(
+            decrypt(&mut data.as_slice(), &mut Vec::new()),
+            Err(NcmError::Decrypt(_))
+        ));
+        let mut cursor = std::io::Cursor::new(data);
+        assert!(matches!(
+            Kwm.metadata(&mut cursor),
+            Err(NcmError::Decrypt(_))
+        ));
+    }
+
+    #[test]
+    fn test_decrypt() {
+        let plain: Vec<u8> = b"ID3\x04\0\0\0\0\0\0"
+            .iter()
+            .copied()
+            .chain((0..=255u8).cycle().take(100_000))
+            .collect();
+        let data = kwm(u64::MAX, &plain);
+        let mut out = Vec::new();
+        assert_eq!(
+            decrypt(&mut data.as_slice(), &mut out).unwrap(),
+            AudioFormat::Mp3
+        );
+        assert_eq!(out, plain);
+
+        assert!(matches!(
+            decrypt(&mut &[0u8; HEADER_LEN][..], &mut Vec::new()),
+            Err(NcmError::InvalidMagic)
+        ));
+        assert!(is_kwm_file(Path::new("a.KWM")));
+    }
+}
@@ -1,39 +1,346 @@
+mod batch;
+pub mod cache;
 mod cipher;
+pub mod container;
+#[cfg(feature = "cover")]
+pub mod cover;
 mod decoder;
+mod encoder;
 pub mod error;
+#[cfg(feature = "gain")]
+pub mod gain;
+pub mod kwm;
 mod metadata;
+pub mod path;
+pub mod qmc;
 mod tag;
+pub mod template;
+#[cfg(feature = "transcode")]
+pub mod transcode;
 
-pub use decoder::{AudioFormat, NcmFile};
+pub use batch::{
+    BatchOptions, convert_many, convert_many_with, for_each_bounded, for_each_bounded_from,
+};
+pub use container::{ContainerInfo, EncryptedContainer};
+pub use decoder::{AudioFormat, ImageKind, NcmFile, NcmReader, NcmSlice};
+pub use encoder::encode;
 pub use error::{NcmError, Result};
 pub use metadata::NcmMetadata;
-pub use tag::write_tags as tag_write;
+pub use tag::{
+    AudioTags, Id3Encoding, Id3Version, MusicBrainzIds, ReplayGain, TagMode, TagOptions,
+    read_cover as tag_read_cover, read_tags as tag_read, stream_len, update_tags as tag_update,
+    write_tags as tag_write, write_tags_buffer as tag_write_buffer,
+    write_tags_buffer_with as tag_write_buffer_with, write_tags_with as tag_write_with,
+};
 
 use std::fs::File;
-use std::io::BufWriter;
+use std::io::{BufReader, BufWriter, Write};
 use std::path::{Path, PathBuf};
 
+/// Read an NCM file's header (format, metadata and cover) without
+/// decrypting the audio.
+pub fn inspect(input: &Path) -> Result<NcmFile> {
+    NcmFile::parse(&mut File::open(input)?)
+}
+
+/// Read an NCM file's cover image and its format, without deriving the key
+/// or decrypting the metadata and audio.
+pub fn extract_cover(input: &Path) -> Result<Option<(Vec<u8>, ImageKind)>> {
+    NcmFile::extract_cover(&mut BufReader::new(File::open(input)?))
+}
+
+/// The file [`convert`] writes for `input`: its stem with the extension of
+/// `format`, in `output_dir` or else next to `input`.
+pub fn output_path(input: &Path, format: AudioFormat, output_dir: Option<&Path>) -> PathBuf {
+    let stem = input.file_stem().unwrap_or_default();
+    let out_dir = output_dir.unwrap_or_else(|| input.parent().unwrap_or(Path::new(".")));
+    out_dir.join(path::with_extension(stem, format.extension()))
+}
+
+/// How [`convert_with`] and its siblings convert a file.
+#[derive(Debug, Clone, Default)]
+pub struct ConvertOptions {
+    /// How the output is tagged, including whether the `163 key` is kept
+    /// in its comment ([`TagOptions::keep_163_key`]).
+    pub tags: TagOptions,
+    /// Also write the embedded metadata, exactly as decrypted, next to the
+    /// output as JSON (`<name>.json`). Files without metadata get none.
+    pub metadata_json: bool,
+}
+
 /// Convert an NCM file to a standard audio file (MP3/FLAC).
 ///
+/// Any other format in the [`container`] registry (QMC, KWM and client
+/// cache files, and whatever was [`register`](container::register)ed) is
+/// converted as well.
+///
 /// Returns the path to the output file.
 pub fn convert(input: &Path, output_dir: Option<&Path>) -> Result<PathBuf> {
+    convert_with_progress(input, output_dir, |_, _| {})
+}
+
+/// Like [`convert`], as `opts` asks.
+pub fn convert_with(
+    input: &Path,
+    output_dir: Option<&Path>,
+    opts: &ConvertOptions,
+) -> Result<PathBuf> {
+    convert_inner(input, output_dir, opts, |_, _| {})
+}
+
+/// Like [`convert`], calling `progress` with the audio bytes decrypted so far
+/// and the total, as [`NcmFile::dump_audio_with_progress`] does.
+pub fn convert_with_progress<F: FnMut(u64, u64)>(
+    input: &Path,
+    output_dir: Option<&Path>,
+    progress: F,
+) -> Result<PathBuf> {
+    convert_inner(input, output_dir, &ConvertOptions::default(), progress)
+}
+
+/// Convert an NCM file, or any other registered format, to `output`, whose
+/// extension should match the format reported by [`container::inspect`].
+pub fn convert_to(input: &Path, output: &Path) -> Result<()> {
+    convert_to_with(input, output, &ConvertOptions::default())
+}
+
+/// Like [`convert_to`], as `opts` asks.
+pub fn convert_to_with(input: &Path, output: &Path, opts: &ConvertOptions) -> Result<()> {
     let mut file = File::open(input)?;
-    let ncm = NcmFile::parse(&mut file)?;
+    let container = container::probe(input, &mut file)?;
+    let info = container.metadata(&mut file)?;
+    convert_probed(&mut file, container.as_ref(), &info, output, opts).map(drop)
+}
 
-    let stem = input.file_stem().unwrap_or_default();
-    let ext = ncm.format.extension();
-    let out_dir = output_dir.unwrap_or_else(|| input.parent().unwrap_or(Path::new(".")));
-    let output_path = out_dir.join(format!("{}.{ext}", stem.to_string_lossy()));
+/// Like [`convert_to_with`], for a `file` that [`container::probe`] already
+/// matched to `container` and whose header gave `info`, so the source is
+/// read only once.
+///
+/// Returns the [`stream_len`] of the decrypted audio before tagging, which
+/// tagging must not change: a check of the output that doesn't depend on
+/// how large the new tags are. `None` for formats that keep their tags in
+/// the stream.
+pub fn convert_probed(
+    file: &mut File,
+    container: &dyn EncryptedContainer,
+    info: &ContainerInfo,
+    output: &Path,
+    opts: &ConvertOptions,
+) -> Result<Option<u64>> {
+    extract(file, container, info, output, opts, |_, _| {})
+}
+
+fn convert_inner<F: FnMut(u64, u64)>(
+    input: &Path,
+    output_dir: Option<&Path>,
+    opts: &ConvertOptions,
+    progress: F,
+) -> Result<PathBuf> {
+    let mut file = File::open(input)?;
+    let container = container::probe(input, &mut file)?;
+    let info = container.metadata(&mut file)?;
+    let output_path = output_path(input, info.format, output_dir);
+    extract(
+        &mut file,
+        container.as_ref(),
+        &info,
+        &output_path,
+        opts,
+        progress,
+    )?;
+    Ok(output_path)
+}
 
-    {
-        let out_file = File::create(&output_path)?;
-        let mut writer = BufWriter::new(out_file);
-        ncm.dump_audio(&mut file, &mut writer)?;
+/// Convert an NCM file held in memory, without touching the file system.
+///
+/// Returns the audio, tagged like the output of [`convert`], with its
+/// format, metadata and cover.
+#[allow(clippy::type_complexity)]
+pub fn convert_bytes(
+    data: &[u8],
+) -> Result<(Vec<u8>, AudioFormat, Option<NcmMetadata>, Option<Vec<u8>>)> {
+    convert_bytes_with(data, &ConvertOptions::default())
+}
+
+/// Like [`convert_bytes`], tagging the audio as `opts.tags` asks. As
+/// nothing is written to disk, `opts.metadata_json` has no effect; the
+/// metadata is returned instead.
+#[allow(clippy::type_complexity)]
+pub fn convert_bytes_with(
+    data: &[u8],
+    opts: &ConvertOptions,
+) -> Result<(Vec<u8>, AudioFormat, Option<NcmMetadata>, Option<Vec<u8>>)> {
+    let ncm = NcmFile::parse_slice(data)?;
+    let mut audio = ncm.audio.to_vec();
+    ncm.decrypt_at(0, &mut audio);
+    let metadata = ncm.metadata()?;
+    if let Some(meta) = &metadata {
+        tag::write_tags_buffer_with(&mut audio, meta, ncm.cover_image, &opts.tags)?;
     }
+    Ok((
+        audio,
+        ncm.format,
+        metadata,
+        ncm.cover_image.map(<[u8]>::to_vec),
+    ))
+}
 
-    if let Some(meta) = &ncm.metadata {
-        tag::write_tags(&output_path, meta, ncm.cover_image.as_deref())?;
+/// Decrypt the audio of `file` into `output` and tag it with what
+/// `container` found in it. Returns the [`stream_len`] of the audio before
+/// it was tagged.
+fn extract<F: FnMut(u64, u64)>(
+    file: &mut File,
+    container: &dyn EncryptedContainer,
+    info: &ContainerInfo,
+    output: &Path,
+    opts: &ConvertOptions,
+    progress: F,
+) -> Result<Option<u64>> {
+    let written = {
+        let out_file = File::create(output)?;
+        let mut writer = ProgressWriter {
+            inner: BufWriter::new(out_file),
+            written: 0,
+            total: info.audio_len,
+            progress,
+        };
+        container.dump_audio(file, &mut writer)?;
+        // Dropping a `BufWriter` ignores a failed final write, which would
+        // leave a silently truncated file.
+        writer.flush()?;
+        writer.written
+    };
+    // The source was cut short (or grew) since its header was read.
+    if written != info.audio_len {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::UnexpectedEof,
+            format!("decrypted {written} of {} audio bytes", info.audio_len),
+        )
+        .into());
     }
+    let stream = tag::stream_len(output)?;
+    tracing::debug!(output = %output.display(), container = container.name(), format = ?info.format, "decrypted audio");
 
-    Ok(output_path)
+    if let Some(meta) = &info.metadata {
+        tag::write_tags_with(output, meta, info.cover.as_deref(), &opts.tags)?;
+        tracing::debug!(output = %output.display(), track = meta.music_id, "wrote tags");
+        if opts.metadata_json {
+            std::fs::write(output.with_extension("json"), meta.to_json()?)?;
+        }
+    }
+    Ok(stream)
+}
+
+/// Reports the bytes written through it to a progress callback.
+struct ProgressWriter<W, F> {
+    inner: W,
+    written: u64,
+    total: u64,
+    progress: F,
+}
+
+impl<W: Write, F: FnMut(u64, u64)> Write for ProgressWriter<W, F> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.written += n as u64;
+        (self.progress)(self.written, self.total);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_convert_bytes_matches_convert() {
+        let input = Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/../testdata/test.ncm"));
+        let dir = std::env::temp_dir().join(format!("ncmdump-bytes-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let output = convert(input, Some(&dir)).unwrap();
+
+        let (audio, format, metadata, cover) =
+            convert_bytes(&std::fs::read(input).unwrap()).unwrap();
+        let ncm = inspect(input).unwrap();
+        assert_eq!(format, ncm.format);
+        assert_eq!(
+            metadata.map(|m| m.music_id),
+            ncm.metadata.map(|m| m.music_id)
+        );
+        assert_eq!(cover, ncm.cover_image);
+        assert_eq!(audio, std::fs::read(&output).unwrap());
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(matches!(convert_bytes(b"CTENFDA"), Err(NcmError::Io(_))));
+    }
+
+    #[test]
+    fn test_convert_writes_metadata_json() {
+        let input = Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/../testdata/test.ncm"));
+        let dir = std::env::temp_dir().join(format!("ncmdump-json-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let opts = ConvertOptions {
+            metadata_json: true,
+            ..ConvertOptions::default()
+        };
+        let output = convert_with(input, Some(&dir), &opts).unwrap();
+        let json = std::fs::read_to_string(output.with_extension("json")).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let meta = NcmMetadata::read_from(input).unwrap().unwrap();
+        assert_eq!(Some(json), meta.raw_json);
+    }
+
+    #[test]
+    fn test_convert_bytes_keeps_163_key() {
+        let input = Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/../testdata/test.ncm"));
+        let opts = ConvertOptions {
+            tags: TagOptions {
+                keep_163_key: true,
+                ..TagOptions::default()
+            },
+            ..ConvertOptions::default()
+        };
+        let (audio, format, metadata, _) =
+            convert_bytes_with(&std::fs::read(input).unwrap(), &opts).unwrap();
+        let output = std::env::temp_dir().join(format!(
+            "ncmdump-key-{}.{}",
+            std::process::id(),
+            format.extension()
+        ));
+        std::fs::write(&output, audio).unwrap();
+        let tags = tag_read(&output).unwrap();
+        std::fs::remove_file(&output).unwrap();
+
+        let key = metadata.unwrap().raw_key.unwrap();
+        assert!(key.starts_with("163 key(Don't modify):"));
+        assert_eq!(tags.comment, Some(key));
+    }
+
+    #[test]
+    fn test_convert_probed_stream_len() {
+        let input = Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/../testdata/test.ncm"));
+        let output =
+            std::env::temp_dir().join(format!("ncmdump-probed-{}.flac", std::process::id()));
+        let mut file = File::open(input).unwrap();
+        let container = container::probe(input, &mut file).unwrap();
+        let info = container.metadata(&mut file).unwrap();
+        let stream = convert_probed(
+            &mut file,
+            container.as_ref(),
+            &info,
+            &output,
+            &ConvertOptions::default(),
+        )
+        .unwrap();
+        let tagged = stream_len(&output).unwrap();
+        std::fs::remove_file(&output).unwrap();
+
+        assert!(stream.is_some_and(|len| len > 0 && len <= info.audio_len));
+        assert_eq!(tagged, stream);
+    }
 }
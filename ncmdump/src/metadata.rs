@@ -1,9 +1,29 @@
-use serde::Deserialize;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
 
-use crate::error::Result;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use serde::{Deserialize, Deserializer, Serialize};
 
-#[derive(Debug, Clone, Deserialize)]
+use crate::cipher::{aes128_ecb_decrypt, aes128_ecb_encrypt};
+use crate::decoder::NcmFile;
+use crate::error::{NcmError, Result};
+
+/// AES key for decrypting the metadata.
+const MODIFY_KEY: [u8; 16] = [
+    0x23, 0x31, 0x34, 0x6C, 0x6A, 0x6B, 0x5F, 0x21, 0x5C, 0x5D, 0x26, 0x30, 0x55, 0x3C, 0x27, 0x28,
+];
+
+/// Prefix of the metadata block, also used as the comment tag written by the
+/// official client.
+const KEY_PREFIX: &str = "163 key(Don't modify):";
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct NcmMetadata {
+    /// Netease track ID (`0` if absent).
+    #[serde(rename = "musicId", default, deserialize_with = "lenient_u64")]
+    pub music_id: u64,
     #[serde(rename = "musicName")]
     pub music_name: String,
     pub album: String,
@@ -11,6 +31,17 @@ pub struct NcmMetadata {
     pub bitrate: u64,
     pub duration: u64,
     pub format: String,
+    /// Fields not modelled above (album ID, cover URL, aliases and so on),
+    /// kept so they survive [`to_json`](Self::to_json) and
+    /// [`to_163_key`](Self::to_163_key).
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+    /// The `163 key(Don't modify):...` string this was parsed from, if any.
+    #[serde(skip)]
+    pub raw_key: Option<String>,
+    /// The decrypted JSON this was parsed from, if any.
+    #[serde(skip)]
+    pub raw_json: Option<String>,
 }
 
 impl NcmMetadata {
@@ -22,7 +53,122 @@ impl NcmMetadata {
         } else {
             data
         };
-        Ok(serde_json::from_slice(json_bytes)?)
+        let mut meta: Self = serde_json::from_slice(json_bytes)?;
+        meta.raw_json = Some(String::from_utf8_lossy(json_bytes).into_owned());
+        Ok(meta)
+    }
+
+    /// Parse a `163 key(Don't modify):...` string, as stored in the NCM header
+    /// and in the comment tag of files produced by the official client.
+    ///
+    /// The prefix is optional; the rest is base64 of the AES-encrypted JSON.
+    pub fn from_163_key(key: &str) -> Result<Self> {
+        let b64 = key.trim();
+        let b64 = b64.strip_prefix(KEY_PREFIX).unwrap_or(b64);
+        let decoded = BASE64.decode(b64)?;
+        let decrypted = aes128_ecb_decrypt(&MODIFY_KEY, &decoded)?;
+        if decrypted.starts_with(b"dj:") {
+            return Err(NcmError::Decrypt(
+                "radio program keys are not supported".into(),
+            ));
+        }
+        let mut meta = Self::from_decrypted(&decrypted)?;
+        meta.raw_key = Some(format!("{KEY_PREFIX}{b64}"));
+        Ok(meta)
+    }
+
+    /// The `163 key(Don't modify):...` string for this metadata, the inverse
+    /// of [`from_163_key`](Self::from_163_key).
+    pub fn to_163_key(&self) -> Result<String> {
+        let mut json = b"music:".to_vec();
+        serde_json::to_writer(&mut json, self)?;
+        let encrypted = aes128_ecb_encrypt(&MODIFY_KEY, &json);
+        Ok(format!("{KEY_PREFIX}{}", BASE64.encode(encrypted)))
+    }
+
+    /// Read the metadata of the NCM file at `path`, without deriving the
+    /// key or reading the cover and audio. `None` if the file has none.
+    pub fn read_from(path: &Path) -> Result<Option<Self>> {
+        NcmFile::read_metadata(&mut BufReader::new(File::open(path)?))
+    }
+
+    /// The metadata as JSON: the decrypted JSON exactly as the header
+    /// carried it, or the fields above for metadata built otherwise.
+    pub fn to_json(&self) -> Result<String> {
+        match &self.raw_json {
+            Some(json) => Ok(json.clone()),
+            None => Ok(serde_json::to_string(self)?),
+        }
+    }
+
+    /// Value for a [`template`](crate::template) placeholder, if present.
+    /// Only some headers carry a track number or year.
+    pub fn template_field(&self, name: &str) -> Option<String> {
+        match name {
+            "artist" => Some(self.artist_names()),
+            "title" => Some(self.music_name.clone()),
+            "album" => Some(self.album.clone()),
+            "track" => self.track_number().map(|n| format!("{n:02}")),
+            "year" => self.year().map(|y| y.to_string()),
+            _ => None,
+        }
+    }
+
+    /// Track number on the album (`trackNumber`, older clients use `no`).
+    pub fn track_number(&self) -> Option<u32> {
+        self.extra_number(&["trackNumber", "no"])
+    }
+
+    /// Disc number (`cd`), stored as `"1"`, `"01"` or `"1/2"`.
+    pub fn disc(&self) -> Option<u32> {
+        self.extra_number(&["cd", "disc"])
+    }
+
+    /// Release year, from `publishTime` (milliseconds since the Unix epoch).
+    pub fn year(&self) -> Option<u16> {
+        let ms = self.extra.get("publishTime")?.as_i64()?;
+        if ms == 0 {
+            return None;
+        }
+        u16::try_from(civil_year(ms.div_euclid(86_400_000))).ok()
+    }
+
+    /// Genre, if the header names one.
+    pub fn genre(&self) -> Option<&str> {
+        self.extra
+            .get("genre")
+            .and_then(|v| v.as_str())
+            .filter(|s| !s.is_empty())
+    }
+
+    /// Album artist (`albumArtist`), either a name or `[name, id]` pairs
+    /// like [`artist`](Self::artist).
+    pub fn album_artist(&self) -> Option<String> {
+        let names = match self.extra.get("albumArtist")? {
+            serde_json::Value::String(s) => s.clone(),
+            serde_json::Value::Array(artists) => artists
+                .iter()
+                .filter_map(|a| a.get(0).and_then(|v| v.as_str()))
+                .collect::<Vec<_>>()
+                .join(" / "),
+            _ => return None,
+        };
+        Some(names).filter(|s| !s.is_empty())
+    }
+
+    /// The first of `keys` holding a positive number, given as a number or
+    /// as a string starting with digits.
+    fn extra_number(&self, keys: &[&str]) -> Option<u32> {
+        keys.iter()
+            .filter_map(|key| match self.extra.get(*key)? {
+                serde_json::Value::Number(n) => n.as_u64().and_then(|n| u32::try_from(n).ok()),
+                serde_json::Value::String(s) => {
+                    let digits = s.trim().split(|c: char| !c.is_ascii_digit()).next()?;
+                    digits.parse().ok()
+                }
+                _ => None,
+            })
+            .find(|&n| n > 0)
     }
 
     /// Join artist names with " / ".
@@ -35,6 +181,28 @@ impl NcmMetadata {
     }
 }
 
+/// Accept `musicId` as either a number or a numeric string.
+fn lenient_u64<'de, D: Deserializer<'de>>(d: D) -> std::result::Result<u64, D::Error> {
+    Ok(match serde_json::Value::deserialize(d)? {
+        serde_json::Value::Number(n) => n.as_u64().unwrap_or(0),
+        serde_json::Value::String(s) => s.parse().unwrap_or(0),
+        _ => 0,
+    })
+}
+
+/// Gregorian year of the day `days` after 1970-01-01.
+fn civil_year(days: i64) -> i64 {
+    // Howard Hinnant's days_from_civil, inverted; eras are 400 years.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let year = yoe + era * 400;
+    if mp >= 10 { year + 1 } else { year }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -45,6 +213,8 @@ mod tests {
         let meta = NcmMetadata::from_decrypted(json).unwrap();
         assert_eq!(meta.music_name, "Test");
         assert_eq!(meta.artist_names(), "Artist1 / Artist2");
+        assert_eq!(meta.template_field("title").as_deref(), Some("Test"));
+        assert_eq!(meta.template_field("track"), None);
     }
 
     #[test]
@@ -53,5 +223,63 @@ mod tests {
         data.extend_from_slice(br#"{"musicName":"X","album":"A","artist":[],"bitrate":128000,"duration":1000,"format":"flac"}"#);
         let meta = NcmMetadata::from_decrypted(&data).unwrap();
         assert_eq!(meta.music_name, "X");
+        assert_eq!(meta.music_id, 0);
+    }
+
+    #[test]
+    fn test_163_key_roundtrip() {
+        let json = br#"{"musicId":42,"musicName":"Test","album":"Album","artist":[["Artist1",7]],"bitrate":320000,"duration":240000,"format":"mp3"}"#;
+        let meta = NcmMetadata::from_decrypted(json).unwrap();
+        let key = meta.to_163_key().unwrap();
+        assert!(key.starts_with(KEY_PREFIX));
+        let parsed = NcmMetadata::from_163_key(&key).unwrap();
+        assert_eq!(parsed.raw_key.as_deref(), Some(key.as_str()));
+        assert_eq!(parsed.music_id, 42);
+        assert_eq!(parsed.music_name, "Test");
+        assert_eq!(parsed.artist, meta.artist);
+    }
+
+    #[test]
+    fn test_json_keeps_unknown_fields() {
+        let json = br#"{"musicId":7,"musicName":"X","album":"A","albumId":99,"albumPic":"http://p/1.jpg","artist":[],"bitrate":128000,"duration":1000,"format":"mp3","alias":["Y"]}"#;
+        let meta = NcmMetadata::from_decrypted(json).unwrap();
+        assert_eq!(meta.extra["albumId"], 99);
+        let value: serde_json::Value = serde_json::from_str(&meta.to_json().unwrap()).unwrap();
+        assert_eq!(
+            value,
+            serde_json::from_slice::<serde_json::Value>(json).unwrap()
+        );
+
+        // Exactly as decrypted: key order, a string ID and 1.0 survive.
+        let json = br#"{"musicName":"X","musicId":"7","album":"A","artist":[],"bitrate":128000,"duration":1000,"format":"mp3","gain":1.0}"#;
+        let meta = NcmMetadata::from_decrypted(json).unwrap();
+        assert_eq!(meta.to_json().unwrap().as_bytes(), json);
+    }
+
+    #[test]
+    fn test_album_fields() {
+        let json = br#"{"musicName":"X","album":"A","artist":[["B",1]],"bitrate":128000,"duration":1000,"format":"mp3","trackNumber":"3","cd":"01/2","publishTime":1577836800000,"genre":"Pop","albumArtist":[["B",1],["C",2]]}"#;
+        let meta = NcmMetadata::from_decrypted(json).unwrap();
+        assert_eq!(meta.track_number(), Some(3));
+        assert_eq!(meta.disc(), Some(1));
+        assert_eq!(meta.year(), Some(2020));
+        assert_eq!(meta.genre(), Some("Pop"));
+        assert_eq!(meta.album_artist().as_deref(), Some("B / C"));
+        assert_eq!(meta.template_field("track").as_deref(), Some("03"));
+
+        // One millisecond before 2020 starts, and before the epoch.
+        assert_eq!(
+            civil_year(1_577_836_799_999_i64.div_euclid(86_400_000)),
+            2019
+        );
+        assert_eq!(civil_year(-1), 1969);
+        assert_eq!(civil_year(11_016), 2000);
+    }
+
+    #[test]
+    fn test_music_id_as_string() {
+        let json = br#"{"musicId":"1974443815","musicName":"X","album":"A","artist":[],"bitrate":128000,"duration":1000,"format":"mp3"}"#;
+        let meta = NcmMetadata::from_decrypted(json).unwrap();
+        assert_eq!(meta.music_id, 1_974_443_815);
     }
 }
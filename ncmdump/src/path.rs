@@ -0,0 +1,140 @@
+//! Output paths that every platform can write.
+//!
+//! File systems cap a single name at 255 bytes (ext4, APFS) or 255 UTF-16
+//! units (NTFS), which a long CJK title reaches at 85 characters. Windows
+//! also strips trailing dots and spaces from names, reserves device names
+//! such as `CON`, and limits ordinary paths to 260 characters. Names built
+//! here are shortened and adjusted to stay within those limits, and file
+//! names that are not valid Unicode are carried as [`OsStr`] so they survive
+//! unchanged.
+
+use std::borrow::Cow;
+use std::ffi::{OsStr, OsString};
+use std::path::Path;
+
+/// Longest file stem or directory name produced, in UTF-8 bytes. Leaves room
+/// for an extension, a ` (2)` collision suffix and `.part`.
+pub const MAX_STEM_BYTES: usize = 240;
+
+/// Names Windows reserves for devices, with or without an extension.
+const RESERVED: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Cut `name` to at most [`MAX_STEM_BYTES`] bytes, on a character boundary.
+pub fn shorten(name: &str) -> &str {
+    if name.len() <= MAX_STEM_BYTES {
+        return name;
+    }
+    let end = (0..=MAX_STEM_BYTES)
+        .rev()
+        .find(|&i| name.is_char_boundary(i))
+        .unwrap_or(0);
+    &name[..end]
+}
+
+/// Make one rendered path component writable everywhere: shortened, without
+/// trailing dots and spaces, and not a reserved device name. `.` and `..`
+/// become `_`, like empty names, so a component never steps out of its
+/// directory.
+pub fn component(name: &str) -> String {
+    let name = shorten(name).trim_end_matches(['.', ' ']);
+    if name.is_empty() {
+        return "_".to_owned();
+    }
+    let base = name.split('.').next().unwrap_or(name);
+    if RESERVED.iter().any(|r| r.eq_ignore_ascii_case(base)) {
+        format!("_{name}")
+    } else {
+        name.to_owned()
+    }
+}
+
+/// `<stem>.<ext>`, with a Unicode `stem` shortened to [`MAX_STEM_BYTES`].
+/// Other stems are kept byte for byte.
+pub fn with_extension(stem: &OsStr, ext: &str) -> OsString {
+    let mut name = match stem.to_str() {
+        Some(s) => OsString::from(shorten(s)),
+        None => stem.to_owned(),
+    };
+    if !ext.is_empty() {
+        name.push(".");
+        name.push(ext);
+    }
+    name
+}
+
+/// `path` in a form external programs (ffmpeg, fpcalc) can open even when
+/// it is longer than Windows' 260 characters: absolute and `\\?\`-prefixed
+/// on Windows when needed, unchanged elsewhere. The standard library does
+/// this on its own for its file functions.
+pub fn extended(path: &Path) -> Cow<'_, Path> {
+    #[cfg(windows)]
+    {
+        const MAX_PATH: usize = 260;
+        let Ok(absolute) = std::path::absolute(path) else {
+            return Cow::Borrowed(path);
+        };
+        let len = std::os::windows::ffi::OsStrExt::encode_wide(absolute.as_os_str()).count();
+        if len < MAX_PATH || absolute.as_os_str().to_string_lossy().starts_with(r"\\?\") {
+            return Cow::Borrowed(path);
+        }
+        let mut verbatim = OsString::from(r"\\?\");
+        match absolute.to_str().and_then(|s| s.strip_prefix(r"\\")) {
+            // `\\server\share` becomes `\\?\UNC\server\share`.
+            Some(unc) => verbatim.push(format!(r"UNC\{unc}")),
+            None => verbatim.push(absolute.as_os_str()),
+        }
+        Cow::Owned(verbatim.into())
+    }
+    #[cfg(not(windows))]
+    {
+        Cow::Borrowed(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shorten() {
+        let title = "晴".repeat(100);
+        let short = shorten(&title);
+        assert_eq!(short.len(), 240);
+        assert!(title.starts_with(short));
+        assert_eq!(shorten("a"), "a");
+        // After a one-byte prefix, the cut falls inside a character.
+        let title = format!("a{}", "晴".repeat(100));
+        assert_eq!(shorten(&title).len(), 238);
+    }
+
+    #[test]
+    fn test_component() {
+        assert_eq!(component("Vol. 2."), "Vol. 2");
+        assert_eq!(component("..."), "_");
+        assert_eq!(component(".."), "_");
+        assert_eq!(component("."), "_");
+        assert_eq!(component("con"), "_con");
+        assert_eq!(component("NUL.txt"), "_NUL.txt");
+        assert_eq!(component("Console"), "Console");
+    }
+
+    #[test]
+    fn test_with_extension() {
+        let name = with_extension(OsStr::new(&"歌".repeat(90)), "flac");
+        assert_eq!(name.len(), 240 + ".flac".len());
+        assert_eq!(with_extension(OsStr::new("a"), ""), "a");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_with_extension_keeps_non_unicode() {
+        use std::os::unix::ffi::{OsStrExt, OsStringExt};
+
+        let stem = OsStr::from_bytes(b"\xff\xfe song");
+        let name = with_extension(stem, "mp3").into_vec();
+        assert_eq!(name, b"\xff\xfe song.mp3");
+    }
+}
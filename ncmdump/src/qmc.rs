@@ -0,0 +1,571 @@
+//! QQ Music's encrypted formats: `.qmc0` and `.qmc3` (MP3), `.qmcflac` and
+//! `.mflac` (FLAC).
+//!
+//! The audio is XOR-ed with a keystream. Older files share one static
+//! stream. Newer ones (`.mflac`, some `.qmcflac`) append their own key after
+//! the audio, as base64 of the key's first 8 bytes followed by the rest
+//! TEA-encrypted with a key derived from those 8. Keys up to 300 bytes drive
+//! a "map" stream, longer ones a segmented RC4 variant.
+//!
+//! The key is followed either by its length (`u32`, little-endian) or, as
+//! the Android client writes it, `<key>,<song id>,<flag>` is followed by its
+//! length (`u32`, big-endian) and `QTag`.
+
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+
+use crate::container::{ContainerInfo, EncryptedContainer, ReadSeek};
+use crate::decoder::AudioFormat;
+use crate::error::{NcmError, Result};
+
+/// File extensions of the supported formats.
+const EXTENSIONS: &[&str] = &["qmc0", "qmc3", "qmcflac", "mflac"];
+
+/// Rows of the static keystream, walked back and forth by [`StaticMask`].
+const SEED_MAP: [[u8; 7]; 8] = [
+    [0x4a, 0xd6, 0xca, 0x90, 0x67, 0xf7, 0x52],
+    [0x5e, 0x95, 0x23, 0x9f, 0x13, 0x11, 0x7e],
+    [0x47, 0x74, 0x3d, 0x90, 0xaa, 0x3f, 0x51],
+    [0xc6, 0x09, 0xd5, 0x9f, 0xfa, 0x66, 0xf9],
+    [0xf3, 0xd6, 0xa1, 0x90, 0xa0, 0xf7, 0xf0],
+    [0x1d, 0x95, 0xde, 0x9f, 0x84, 0x11, 0xf4],
+    [0x0e, 0x74, 0xbb, 0x90, 0xbc, 0x3f, 0x92],
+    [0x00, 0x09, 0x5b, 0x9f, 0x62, 0x66, 0xa1],
+];
+
+/// Half of the TEA key for an embedded key: `|tan(106 + i / 10)| * 100`.
+const SIMPLE_KEY: [u8; 8] = [0x69, 0x56, 0x46, 0x38, 0x2b, 0x20, 0x15, 0x0b];
+
+const TEA_DELTA: u32 = 0x9e37_79b9;
+
+/// Longest key that uses the map stream rather than RC4.
+const MAP_KEY_MAX: usize = 300;
+
+/// RC4 keys restart their stream every segment; the first 128 bytes are
+/// handled apart.
+const RC4_SEGMENT: usize = 5120;
+const RC4_FIRST_SEGMENT: usize = 128;
+
+/// Whether `path` has a QMC extension.
+pub fn is_qmc_file(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|e| EXTENSIONS.iter().any(|ext| e.eq_ignore_ascii_case(ext)))
+}
+
+/// Decrypt a QMC stream into `w`, returning the detected audio format.
+///
+/// # Errors
+///
+/// Returns [`NcmError::Decrypt`] if the embedded key is unusable, and
+/// [`NcmError::UnsupportedFormat`] if the audio is none of the formats
+/// [`AudioFormat::detect`] recognises: MP3, FLAC, M4A and Ogg.
+pub fn decrypt<R: Read + Seek, W: Write>(r: &mut R, w: &mut W) -> Result<AudioFormat> {
+    let (audio_len, key) = read_key(r)?;
+    let mut cipher = Cipher::new(key);
+    r.seek(SeekFrom::Start(0))?;
+    let mut r = r.take(audio_len);
+
+    let mut buf = vec![0u8; 0x8000];
+    let mut offset = 0;
+    let mut format = None;
+    loop {
+        let n = r.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        cipher.apply(offset, &mut buf[..n]);
+        if format.is_none() {
+            format = Some(AudioFormat::detect(&buf[..n]).ok_or(NcmError::UnsupportedFormat)?);
+        }
+        w.write_all(&buf[..n])?;
+        offset += n;
+    }
+    format.ok_or(NcmError::UnsupportedFormat)
+}
+
+/// QMC files, as an [`EncryptedContainer`]. They are recognized by their
+/// extension, having no magic; there is no metadata to tag them with.
+pub struct Qmc;
+
+impl EncryptedContainer for Qmc {
+    fn name(&self) -> &'static str {
+        "qmc"
+    }
+
+    fn probe(&self, path: &Path, _header: &[u8]) -> bool {
+        is_qmc_file(path)
+    }
+
+    fn metadata(&self, r: &mut dyn ReadSeek) -> Result<ContainerInfo> {
+        let (audio_len, key) = read_key(&mut &mut *r)?;
+        let mut header = Vec::with_capacity(4);
+        r.seek(SeekFrom::Start(0))?;
+        (&mut *r).take(audio_len.min(4)).read_to_end(&mut header)?;
+        Cipher::new(key).apply(0, &mut header);
+        Ok(ContainerInfo {
+            format: AudioFormat::detect(&header).ok_or(NcmError::UnsupportedFormat)?,
+            metadata: None,
+            cover: None,
+            audio_len,
+        })
+    }
+
+    fn dump_audio(&self, r: &mut dyn ReadSeek, w: &mut dyn Write) -> Result<()> {
+        let (mut r, mut w) = (r, w);
+        decrypt(&mut r, &mut w).map(drop)
+    }
+}
+
+/// Find the embedded key at the end of `r`. Returns the length of the audio
+/// before it, and the decrypted key, or `None` for the static stream.
+fn read_key<R: Read + Seek>(r: &mut R) -> Result<(u64, Option<Vec<u8>>)> {
+    let len = r.seek(SeekFrom::End(0))?;
+    if len < 8 {
+        return Ok((len, None));
+    }
+    let tail = read_u32_at(r, len - 4)?;
+    match &tail.to_le_bytes() {
+        b"QTag" => {
+            let meta_len = u64::from(u32::from_be_bytes(read_u32_at(r, len - 8)?.to_le_bytes()));
+            let start = (len - 8).checked_sub(meta_len).ok_or_else(truncated)?;
+            let meta = read_at(r, start, meta_len)?;
+            let key = meta.split(|&b| b == b',').next().unwrap_or_default();
+            Ok((start, Some(derive_key(key)?)))
+        }
+        b"STag" => Err(NcmError::Decrypt("QMC file has no embedded key".into())),
+        // Otherwise the length of a key, or audio if it is implausible.
+        _ if tail == 0 || tail > 0xFFFF => Ok((len, None)),
+        _ => {
+            let start = (len - 4)
+                .checked_sub(u64::from(tail))
+                .ok_or_else(truncated)?;
+            let raw = read_at(r, start, u64::from(tail))?;
+            let end = raw.iter().rposition(|&b| b != 0).map_or(0, |i| i + 1);
+            Ok((start, Some(derive_key(&raw[..end])?)))
+        }
+    }
+}
+
+fn truncated() -> NcmError {
+    io::Error::from(io::ErrorKind::UnexpectedEof).into()
+}
+
+fn read_at<R: Read + Seek>(r: &mut R, pos: u64, len: u64) -> Result<Vec<u8>> {
+    r.seek(SeekFrom::Start(pos))?;
+    let mut buf = Vec::new();
+    r.take(len).read_to_end(&mut buf)?;
+    if (buf.len() as u64) < len {
+        return Err(truncated());
+    }
+    Ok(buf)
+}
+
+fn read_u32_at<R: Read + Seek>(r: &mut R, pos: u64) -> Result<u32> {
+    let bytes = read_at(r, pos, 4)?;
+    Ok(u32::from_le_bytes(bytes.try_into().expect("4 bytes")))
+}
+
+/// Decode an embedded key: base64 of 8 plain bytes, then the rest
+/// encrypted with a TEA key interleaving [`SIMPLE_KEY`] and those 8 bytes.
+fn derive_key(raw: &[u8]) -> Result<Vec<u8>> {
+    let decoded = BASE64.decode(raw)?;
+    if decoded.starts_with(b"QQMusic EncV2,Key:") {
+        return Err(NcmError::Decrypt("QMC EncV2 keys are not supported".into()));
+    }
+    if decoded.len() < 16 {
+        return Err(NcmError::Decrypt("QMC key too short".into()));
+    }
+    let mut tea_key = [0u8; 16];
+    for i in 0..8 {
+        tea_key[2 * i] = SIMPLE_KEY[i];
+        tea_key[2 * i + 1] = decoded[i];
+    }
+    let mut key = decoded[..8].to_vec();
+    key.extend(tc_tea_decrypt(&decoded[8..], &tea_key)?);
+    Ok(key)
+}
+
+/// Tencent's TEA-CBC: 16-round TEA, each block chained to the previous
+/// plain and cipher blocks. The plaintext is a length byte, up to 7 bytes
+/// of padding and 2 of salt before the data, and 7 zeros after.
+fn tc_tea_decrypt(data: &[u8], key: &[u8; 16]) -> Result<Vec<u8>> {
+    if data.len() % 8 != 0 || data.len() < 16 {
+        return Err(NcmError::Decrypt("bad QMC key length".into()));
+    }
+    let key = tea_key_words(key);
+    let mut plain = Vec::with_capacity(data.len());
+    let mut prev = [0u8; 8];
+    let mut block = [0u8; 8];
+    for chunk in data.chunks_exact(8) {
+        for (b, c) in block.iter_mut().zip(chunk) {
+            *b ^= c;
+        }
+        block = tea_decrypt_block(block, &key);
+        plain.extend(block.iter().zip(prev).map(|(b, p)| b ^ p));
+        prev.copy_from_slice(chunk);
+    }
+
+    let start = 1 + usize::from(plain[0] & 7) + 2;
+    let end = plain.len() - 7;
+    if start > end || plain[end..].iter().any(|&b| b != 0) {
+        return Err(NcmError::Decrypt("QMC key check failed".into()));
+    }
+    Ok(plain[start..end].to_vec())
+}
+
+fn tea_key_words(key: &[u8; 16]) -> [u32; 4] {
+    std::array::from_fn(|i| u32::from_be_bytes(key[i * 4..i * 4 + 4].try_into().expect("4 bytes")))
+}
+
+fn tea_decrypt_block(block: [u8; 8], k: &[u32; 4]) -> [u8; 8] {
+    let mut v0 = u32::from_be_bytes(block[..4].try_into().expect("4 bytes"));
+    let mut v1 = u32::from_be_bytes(block[4..].try_into().expect("4 bytes"));
+    let mut sum = TEA_DELTA.wrapping_mul(16);
+    for _ in 0..16 {
+        v1 = v1.wrapping_sub(
+            (v0 << 4).wrapping_add(k[2]) ^ v0.wrapping_add(sum) ^ (v0 >> 5).wrapping_add(k[3]),
+        );
+        v0 = v0.wrapping_sub(
+            (v1 << 4).wrapping_add(k[0]) ^ v1.wrapping_add(sum) ^ (v1 >> 5).wrapping_add(k[1]),
+        );
+        sum = sum.wrapping_sub(TEA_DELTA);
+    }
+    let mut out = [0u8; 8];
+    out[..4].copy_from_slice(&v0.to_be_bytes());
+    out[4..].copy_from_slice(&v1.to_be_bytes());
+    out
+}
+
+/// The keystream of one file.
+enum Cipher {
+    Static(StaticMask),
+    Map(Vec<u8>),
+    Rc4(Rc4),
+}
+
+impl Cipher {
+    fn new(key: Option<Vec<u8>>) -> Self {
+        match key {
+            Some(key) if key.len() > MAP_KEY_MAX => Self::Rc4(Rc4::new(key)),
+            Some(key) if !key.is_empty() => Self::Map(key),
+            _ => Self::Static(StaticMask::default()),
+        }
+    }
+
+    /// Decrypt `buf`, which starts `offset` bytes into the audio. Calls
+    /// must cover the audio in order.
+    fn apply(&mut self, offset: usize, buf: &mut [u8]) {
+        match self {
+            Self::Static(mask) => {
+                for b in buf {
+                    *b ^= mask.next_mask();
+                }
+            }
+            Self::Map(key) => {
+                for (i, b) in buf.iter_mut().enumerate() {
+                    *b ^= map_mask(key, offset + i);
+                }
+            }
+            Self::Rc4(rc4) => rc4.apply(offset, buf),
+        }
+    }
+}
+
+/// The static keystream: [`SEED_MAP`] read along a zigzag, one row forward
+/// and the next backward, with `0xC3`/`0xD8` at the turns.
+struct StaticMask {
+    /// Column in `SEED_MAP`, plus one; 0 and 8 are the turns.
+    col: usize,
+    row: usize,
+    forward: bool,
+    /// Index of the next mask generated.
+    next: usize,
+}
+
+impl Default for StaticMask {
+    fn default() -> Self {
+        Self {
+            col: 0,
+            row: 8,
+            forward: true,
+            next: 0,
+        }
+    }
+}
+
+impl StaticMask {
+    fn next_mask(&mut self) -> u8 {
+        loop {
+            let index = self.next;
+            self.next += 1;
+            let mask = match self.col {
+                0 => {
+                    self.forward = true;
+                    self.row = (8 - self.row) % 8;
+                    0xC3
+                }
+                8 => {
+                    self.forward = false;
+                    self.row = 7 - self.row;
+                    0xD8
+                }
+                col => SEED_MAP[self.row][col - 1],
+            };
+            if self.forward {
+                self.col += 1;
+            } else {
+                self.col -= 1;
+            }
+            // One mask is dropped at each 32 KiB boundary.
+            if index == 0x8000 || (index > 0x8000 && (index + 1) % 0x8000 == 0) {
+                continue;
+            }
+            return mask;
+        }
+    }
+}
+
+/// Mask at `offset` for a key of at most [`MAP_KEY_MAX`] bytes.
+fn map_mask(key: &[u8], offset: usize) -> u8 {
+    let offset = if offset > 0x7FFF {
+        offset % 0x7FFF
+    } else {
+        offset
+    };
+    let idx = (offset * offset + 71214) % key.len();
+    // Not a true rotation: both shifts go the same distance.
+    let shift = (idx + 4) % 8;
+    (key[idx] << shift) | (key[idx] >> shift)
+}
+
+/// RC4 variant for long keys. Each 5120-byte segment restarts the stream
+/// from the scheduled box, skipping ahead by an amount derived from the key.
+struct Rc4 {
+    key: Vec<u8>,
+    sbox: Vec<u8>,
+    /// Product of the key's bytes, up to the first that overflows.
+    hash: u32,
+}
+
+impl Rc4 {
+    #[allow(clippy::cast_possible_truncation)] // indices below `key.len()`
+    fn new(key: Vec<u8>) -> Self {
+        let n = key.len();
+        let mut sbox: Vec<u8> = (0..n).map(|i| i as u8).collect();
+        let mut j = 0;
+        for i in 0..n {
+            j = (j + usize::from(sbox[i]) + usize::from(key[i])) % n;
+            sbox.swap(i, j);
+        }
+
+        let mut hash = 1u32;
+        for &b in &key {
+            if b == 0 {
+                continue;
+            }
+            let next = hash.wrapping_mul(u32::from(b));
+            if next == 0 || next <= hash {
+                break;
+            }
+            hash = next;
+        }
+        Self { key, sbox, hash }
+    }
+
+    /// Index into the key for segment `id` (or byte `id` of the first
+    /// segment).
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_precision_loss,
+        clippy::cast_sign_loss
+    )]
+    fn segment_key(&self, id: usize) -> usize {
+        let seed = usize::from(self.key[id % self.key.len()]);
+        let idx = (f64::from(self.hash) / ((id + 1) * seed) as f64 * 100.0) as u64;
+        (idx % self.key.len() as u64) as usize
+    }
+
+    fn apply(&self, mut offset: usize, mut buf: &mut [u8]) {
+        if offset < RC4_FIRST_SEGMENT {
+            let n = buf.len().min(RC4_FIRST_SEGMENT - offset);
+            let (first, rest) = buf.split_at_mut(n);
+            for (i, b) in first.iter_mut().enumerate() {
+                *b ^= self.key[self.segment_key(offset + i)];
+            }
+            offset += n;
+            buf = rest;
+        }
+        while !buf.is_empty() {
+            let n = buf.len().min(RC4_SEGMENT - offset % RC4_SEGMENT);
+            let (segment, rest) = buf.split_at_mut(n);
+            self.apply_segment(offset, segment);
+            offset += n;
+            buf = rest;
+        }
+    }
+
+    /// Decrypt `buf`, which lies within one segment.
+    fn apply_segment(&self, offset: usize, buf: &mut [u8]) {
+        let n = self.key.len();
+        let mut sbox = self.sbox.clone();
+        let (mut j, mut k) = (0, 0);
+        let skip = offset % RC4_SEGMENT + self.segment_key(offset / RC4_SEGMENT);
+        for i in 0..skip + buf.len() {
+            j = (j + 1) % n;
+            k = (usize::from(sbox[j]) + k) % n;
+            sbox.swap(j, k);
+            if let Some(b) = i.checked_sub(skip).map(|i| &mut buf[i]) {
+                *b ^= sbox[(usize::from(sbox[j]) + usize::from(sbox[k])) % n];
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tea_encrypt_block(block: [u8; 8], k: &[u32; 4]) -> [u8; 8] {
+        let mut v0 = u32::from_be_bytes(block[..4].try_into().unwrap());
+        let mut v1 = u32::from_be_bytes(block[4..].try_into().unwrap());
+        let mut sum = 0u32;
+        for _ in 0..16 {
+            sum = sum.wrapping_add(TEA_DELTA);
+            v0 = v0.wrapping_add(
+                (v1 << 4).wrapping_add(k[0]) ^ v1.wrapping_add(sum) ^ (v1 >> 5).wrapping_add(k[1]),
+            );
+            v1 = v1.wrapping_add(
+                (v0 << 4).wrapping_add(k[2]) ^ v0.wrapping_add(sum) ^ (v0 >> 5).wrapping_add(k[3]),
+            );
+        }
+        let mut out = [0u8; 8];
+        out[..4].copy_from_slice(&v0.to_be_bytes());
+        out[4..].copy_from_slice(&v1.to_be_bytes());
+        out
+    }
+
+    /// Inverse of [`tc_tea_decrypt`], with fixed padding and salt.
+    fn tc_tea_encrypt(data: &[u8], key: &[u8; 16]) -> Vec<u8> {
+        let pad = (8 - (1 + 2 + data.len() + 7) % 8) % 8;
+        let mut plain = vec![0xA8 | u8::try_from(pad).unwrap()];
+        plain.extend(std::iter::repeat_n(0x55, pad + 2));
+        plain.extend_from_slice(data);
+        plain.extend([0; 7]);
+
+        let key = tea_key_words(key);
+        let (mut prev_cipher, mut prev_block) = ([0u8; 8], [0u8; 8]);
+        let mut out = Vec::new();
+        for chunk in plain.chunks_exact(8) {
+            let mut block = [0u8; 8];
+            for ((b, p), c) in block.iter_mut().zip(chunk).zip(prev_cipher) {
+                *b = p ^ c;
+            }
+            let mut cipher = tea_encrypt_block(block, &key);
+            for (c, b) in cipher.iter_mut().zip(prev_block) {
+                *c ^= b;
+            }
+            out.extend_from_slice(&cipher);
+            (prev_cipher, prev_block) = (cipher, block);
+        }
+        out
+    }
+
+    /// The trailer for `key`, as `derive_key` expects it.
+    fn embed_key(key: &[u8]) -> Vec<u8> {
+        let mut tea_key = [0u8; 16];
+        for i in 0..8 {
+            tea_key[2 * i] = SIMPLE_KEY[i];
+            tea_key[2 * i + 1] = key[i];
+        }
+        let mut raw = key[..8].to_vec();
+        raw.extend(tc_tea_encrypt(&key[8..], &tea_key));
+        BASE64.encode(raw).into_bytes()
+    }
+
+    fn encrypt(plain: &[u8], key: Option<Vec<u8>>) -> Vec<u8> {
+        let mut data = plain.to_vec();
+        let mut cipher = Cipher::new(key);
+        // In uneven pieces, to cross segment boundaries mid-call.
+        let mut offset = 0;
+        for chunk in data.chunks_mut(777) {
+            cipher.apply(offset, chunk);
+            offset += chunk.len();
+        }
+        data
+    }
+
+    fn flac(len: usize) -> Vec<u8> {
+        let mut plain = b"fLaC".to_vec();
+        plain.extend((0..len).map(|i| u8::try_from(i * 13 % 251).unwrap()));
+        plain
+    }
+
+    #[test]
+    fn test_static_mask() {
+        let mut mask = StaticMask::default();
+        let start: Vec<u8> = (0..10).map(|_| mask.next_mask()).collect();
+        assert_eq!(
+            start,
+            [0xC3, 0x4a, 0xd6, 0xca, 0x90, 0x67, 0xf7, 0x52, 0xD8, 0xa1]
+        );
+
+        let plain = flac(0x10000);
+        let data = encrypt(&plain, None);
+        let mut out = Vec::new();
+        let format = decrypt(&mut io::Cursor::new(&data), &mut out).unwrap();
+        assert_eq!(format, AudioFormat::Flac);
+        assert_eq!(out, plain);
+    }
+
+    #[test]
+    fn test_embedded_keys() {
+        for key_len in [128, 512] {
+            let key: Vec<u8> = (0..key_len)
+                .map(|i: usize| u8::try_from((i * 7 + 1) % 256).unwrap())
+                .collect();
+            let plain = flac(20_000);
+
+            // Key length trailer.
+            let mut data = encrypt(&plain, Some(key.clone()));
+            let raw = embed_key(&key);
+            data.extend_from_slice(&raw);
+            data.extend_from_slice(&u32::try_from(raw.len()).unwrap().to_le_bytes());
+            let mut out = Vec::new();
+            decrypt(&mut io::Cursor::new(&data), &mut out).unwrap();
+            assert_eq!(out, plain, "key of {key_len} bytes");
+
+            // `QTag` trailer.
+            let mut data = encrypt(&plain, Some(key.clone()));
+            let mut meta = embed_key(&key);
+            meta.extend_from_slice(b",12345,2");
+            data.extend_from_slice(&meta);
+            data.extend_from_slice(&u32::try_from(meta.len()).unwrap().to_be_bytes());
+            data.extend_from_slice(b"QTag");
+            let mut out = Vec::new();
+            decrypt(&mut io::Cursor::new(&data), &mut out).unwrap();
+            assert_eq!(out, plain, "QTag key of {key_len} bytes");
+        }
+    }
+
+    #[test]
+    fn test_bad_keys() {
+        let mut data = encrypt(&flac(100), None);
+        data.extend_from_slice(b"STag");
+        assert!(matches!(
+            decrypt(&mut io::Cursor::new(&data), &mut Vec::new()),
+            Err(NcmError::Decrypt(_))
+        ));
+
+        let mut data = flac(100);
+        data.extend_from_slice(b"AAAAAAAAAAAAAAAAAAAAAAAA");
+        data.extend_from_slice(&24u32.to_le_bytes());
+        assert!(decrypt(&mut io::Cursor::new(&data), &mut Vec::new()).is_err());
+
+        assert!(is_qmc_file(Path::new("a.mflac")));
+        assert!(is_qmc_file(Path::new("a.QMC0")));
+        assert!(!is_qmc_file(Path::new("a.ncm")));
+    }
+}
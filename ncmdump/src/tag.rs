@@ -1,25 +1,354 @@
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::{Cursor, ErrorKind, Read, Seek, SeekFrom};
 use std::path::Path;
 
-use lofty::config::WriteOptions;
-use lofty::file::TaggedFileExt;
+use lofty::TextEncoding;
+use lofty::config::{ParseOptions, WriteOptions};
+use lofty::file::{AudioFile, FileType, TaggedFile, TaggedFileExt};
+use lofty::id3::v2::{Frame, Id3v2Tag, UniqueFileIdentifierFrame};
+use lofty::mpeg::MpegFile;
 use lofty::picture::{MimeType, Picture, PictureType};
 use lofty::probe::Probe;
-use lofty::tag::{Accessor, TagExt};
+use lofty::tag::items::Timestamp;
+use lofty::tag::{Accessor, ItemKey, ItemValue, Tag, TagExt, TagItem, TagType};
 
+use crate::decoder::{AudioFormat, ImageKind};
 use crate::error::{NcmError, Result};
 use crate::metadata::NcmMetadata;
 
-/// PNG magic bytes for MIME detection.
-const PNG_MAGIC: [u8; 8] = [0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A];
+/// Tags read back from an audio file by [`read_tags`].
+#[derive(Debug, Clone, Default)]
+pub struct AudioTags {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub album_artist: Option<String>,
+    pub track: Option<u32>,
+    pub year: Option<u16>,
+    pub comment: Option<String>,
+    pub lyrics: Option<String>,
+    pub has_cover: bool,
+    pub musicbrainz: MusicBrainzIds,
+    pub replay_gain: ReplayGain,
+    /// Audio duration in milliseconds (from the stream properties, not a tag).
+    pub duration_ms: u64,
+}
+
+/// MusicBrainz identifiers, stored under the keys Picard uses
+/// (`MUSICBRAINZ_TRACKID`, … in Vorbis comments).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MusicBrainzIds {
+    /// Recording MBID (`MUSICBRAINZ_TRACKID`).
+    pub recording: Option<String>,
+    /// Release track MBID (`MUSICBRAINZ_RELEASETRACKID`).
+    pub release_track: Option<String>,
+    /// Release MBID (`MUSICBRAINZ_ALBUMID`).
+    pub release: Option<String>,
+    /// Release group MBID (`MUSICBRAINZ_RELEASEGROUPID`).
+    pub release_group: Option<String>,
+    /// Artist MBIDs of the recording, in credit order.
+    pub artists: Vec<String>,
+    /// Artist MBIDs of the release, in credit order.
+    pub album_artists: Vec<String>,
+}
+
+impl MusicBrainzIds {
+    fn single_keys(&self) -> [(ItemKey, Option<&String>); 4] {
+        [
+            (ItemKey::MusicBrainzRecordingId, self.recording.as_ref()),
+            (ItemKey::MusicBrainzTrackId, self.release_track.as_ref()),
+            (ItemKey::MusicBrainzReleaseId, self.release.as_ref()),
+            (
+                ItemKey::MusicBrainzReleaseGroupId,
+                self.release_group.as_ref(),
+            ),
+        ]
+    }
+
+    fn multi_keys(&self) -> [(ItemKey, &[String]); 2] {
+        [
+            (ItemKey::MusicBrainzArtistId, &self.artists),
+            (ItemKey::MusicBrainzReleaseArtistId, &self.album_artists),
+        ]
+    }
+}
+
+/// ReplayGain 2.0 values (`REPLAYGAIN_TRACK_GAIN`, … tags).
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ReplayGain {
+    /// Track gain in dB.
+    pub track_gain: Option<f64>,
+    /// Track sample peak; 1.0 is full scale.
+    pub track_peak: Option<f64>,
+    /// Album gain in dB.
+    pub album_gain: Option<f64>,
+    /// Album sample peak; 1.0 is full scale.
+    pub album_peak: Option<f64>,
+}
+
+impl ReplayGain {
+    /// Keys, field names and values as written by foobar2000 and rsgain:
+    /// `-6.52 dB`, `0.988525`.
+    fn keys(&self) -> [(ItemKey, &'static str, Option<String>); 4] {
+        let gain = |db: Option<f64>| db.map(|db| format!("{db:.2} dB"));
+        let peak = |peak: Option<f64>| peak.map(|peak| format!("{peak:.6}"));
+        [
+            (
+                ItemKey::ReplayGainTrackGain,
+                "REPLAYGAIN_TRACK_GAIN",
+                gain(self.track_gain),
+            ),
+            (
+                ItemKey::ReplayGainTrackPeak,
+                "REPLAYGAIN_TRACK_PEAK",
+                peak(self.track_peak),
+            ),
+            (
+                ItemKey::ReplayGainAlbumGain,
+                "REPLAYGAIN_ALBUM_GAIN",
+                gain(self.album_gain),
+            ),
+            (
+                ItemKey::ReplayGainAlbumPeak,
+                "REPLAYGAIN_ALBUM_PEAK",
+                peak(self.album_peak),
+            ),
+        ]
+    }
+}
+
+/// A ReplayGain tag value, with or without its ` dB` unit.
+fn parse_replay_gain(value: &str) -> Option<f64> {
+    let value = value.trim();
+    let number = value
+        .strip_suffix("dB")
+        .or_else(|| value.strip_suffix("db"))
+        .unwrap_or(value);
+    number.trim().parse().ok()
+}
+
+impl AudioTags {
+    /// Value for a [`template`](crate::template) placeholder, if present.
+    pub fn template_field(&self, name: &str) -> Option<String> {
+        match name {
+            "artist" => self.artist.clone(),
+            "title" => self.title.clone(),
+            "album" => self.album.clone(),
+            "track" => self.track.map(|n| format!("{n:02}")),
+            "year" => self.year.map(|y| y.to_string()),
+            _ => None,
+        }
+    }
+}
+
+/// ID3 revision written to MP3 files.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Id3Version {
+    /// ID3v2.3, with UTF-16 text (v2.3 has no UTF-8); for older players
+    /// and Windows Explorer.
+    V23,
+    /// ID3v2.4, with UTF-8 text.
+    #[default]
+    V24,
+}
+
+/// Text encoding of ID3 frames.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Id3Encoding {
+    /// UTF-8; ID3v2.3 has none, and gets UTF-16 instead.
+    #[default]
+    Utf8,
+    /// UTF-16 with a byte order mark.
+    Utf16,
+    /// Latin-1, for players that read nothing else; other characters
+    /// become `?`.
+    Latin1,
+}
+
+/// What [`write_tags_with`] does with tags the audio already carries (some
+/// NCM payloads ship complete ID3 data).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TagMode {
+    /// Replace them with the NCM metadata.
+    #[default]
+    Overwrite,
+    /// Keep them, only adding fields (and a cover) they lack.
+    FillMissing,
+    /// Leave files that already carry tags untouched.
+    Skip,
+}
+
+/// How [`write_tags_with`] writes tags.
+#[derive(Debug, Clone)]
+pub struct TagOptions {
+    /// Scale down and re-encode the cover before embedding it.
+    #[cfg(feature = "cover")]
+    pub cover: Option<crate::cover::CoverOptions>,
+    /// Write the NCM's `163 key(Don't modify):...` string into the comment,
+    /// as the official client does, so the file can be matched back to its
+    /// Netease track.
+    pub keep_163_key: bool,
+    /// ID3 revision for MP3 files.
+    pub id3_version: Id3Version,
+    /// Text encoding of the ID3 frames.
+    pub id3_encoding: Id3Encoding,
+    /// How tags already in the file are treated.
+    pub mode: TagMode,
+    /// Add a tag of the format's usual type to files that carry none (bare
+    /// FLAC/MP3 payloads); otherwise such files are an error.
+    pub create_tag: bool,
+}
+
+impl Default for TagOptions {
+    fn default() -> Self {
+        Self {
+            #[cfg(feature = "cover")]
+            cover: None,
+            keep_163_key: false,
+            id3_version: Id3Version::default(),
+            id3_encoding: Id3Encoding::default(),
+            mode: TagMode::default(),
+            create_tag: true,
+        }
+    }
+}
+
+impl TagOptions {
+    fn write_options(&self) -> WriteOptions {
+        WriteOptions::default().use_id3v23(self.id3_version == Id3Version::V23)
+    }
+
+    /// `tag` as an ID3 tag with its text in [`id3_encoding`](Self::id3_encoding),
+    /// if it is one and that is not what lofty writes anyway (UTF-8 in
+    /// ID3v2.4).
+    fn recoded_id3(&self, tag: &Tag) -> Option<Id3v2Tag> {
+        let encoding = match (self.id3_encoding, self.id3_version) {
+            (Id3Encoding::Utf8, Id3Version::V24) => return None,
+            (Id3Encoding::Utf8 | Id3Encoding::Utf16, _) => TextEncoding::UTF16,
+            (Id3Encoding::Latin1, _) => TextEncoding::Latin1,
+        };
+        if tag.tag_type() != TagType::Id3v2 {
+            return None;
+        }
+        let converted = Id3v2Tag::from(tag.clone());
+        let mut id3 = Id3v2Tag::new();
+        id3.set_flags(*converted.flags());
+        for mut frame in converted {
+            match &mut frame {
+                Frame::Text(f) => f.encoding = encoding,
+                Frame::UserText(f) => f.encoding = encoding,
+                Frame::Comment(f) => f.encoding = encoding,
+                Frame::UnsynchronizedText(f) => f.encoding = encoding,
+                Frame::Picture(f) => f.encoding = encoding,
+                Frame::Timestamp(f) => f.encoding = encoding,
+                Frame::UserUrl(f) => f.encoding = encoding,
+                _ => {}
+            }
+            id3.insert(frame);
+        }
+        Some(id3)
+    }
+}
 
 /// Write metadata tags and optional cover art to an audio file.
-#[allow(clippy::missing_panics_doc)]
 pub fn write_tags(path: &Path, metadata: &NcmMetadata, cover: Option<&[u8]>) -> Result<()> {
-    let mut tagged_file = Probe::open(path)
-        .map_err(|e| NcmError::Tag(e.to_string()))?
+    write_tags_with(path, metadata, cover, &TagOptions::default())
+}
+
+/// Like [`write_tags`], as `opts` asks.
+pub fn write_tags_with(
+    path: &Path,
+    metadata: &NcmMetadata,
+    cover: Option<&[u8]>,
+    opts: &TagOptions,
+) -> Result<()> {
+    let mut tagged_file = open_tagged(path)?;
+    if skips(&tagged_file, opts) {
+        return Ok(());
+    }
+
+    let tag = fill_tag(&mut tagged_file, metadata, cover, opts)?;
+    match opts.recoded_id3(tag) {
+        Some(id3) => id3.save_to_path(path, opts.write_options()),
+        None => tag.save_to_path(path, opts.write_options()),
+    }
+    .map_err(|e| NcmError::Tag(e.to_string()))?;
+
+    Ok(())
+}
+
+/// Like [`write_tags`], for audio held in memory (e.g. decrypted from a
+/// buffer with [`NcmFile::dump_audio`](crate::NcmFile::dump_audio)).
+pub fn write_tags_buffer(
+    audio: &mut Vec<u8>,
+    metadata: &NcmMetadata,
+    cover: Option<&[u8]>,
+) -> Result<()> {
+    write_tags_buffer_with(audio, metadata, cover, &TagOptions::default())
+}
+
+/// Like [`write_tags_buffer`], as `opts` asks.
+pub fn write_tags_buffer_with(
+    audio: &mut Vec<u8>,
+    metadata: &NcmMetadata,
+    cover: Option<&[u8]>,
+    opts: &TagOptions,
+) -> Result<()> {
+    let mut cursor = Cursor::new(std::mem::take(audio));
+    let result = write_tags_cursor(&mut cursor, metadata, cover, opts);
+    *audio = cursor.into_inner();
+    result
+}
+
+fn write_tags_cursor(
+    cursor: &mut Cursor<Vec<u8>>,
+    metadata: &NcmMetadata,
+    cover: Option<&[u8]>,
+    opts: &TagOptions,
+) -> Result<()> {
+    let mut tagged_file = Probe::new(&mut *cursor)
+        .guess_file_type()?
         .read()
         .map_err(|e| NcmError::Tag(e.to_string()))?;
+    if skips(&tagged_file, opts) {
+        return Ok(());
+    }
+
+    cursor.rewind()?;
+    let tag = fill_tag(&mut tagged_file, metadata, cover, opts)?;
+    match opts.recoded_id3(tag) {
+        Some(id3) => id3.save_to(cursor, opts.write_options()),
+        None => tag.save_to(cursor, opts.write_options()),
+    }
+    .map_err(|e| NcmError::Tag(e.to_string()))?;
+
+    Ok(())
+}
+
+/// Whether [`TagMode::Skip`] leaves `tagged_file` alone: it carries tags.
+fn skips(tagged_file: &TaggedFile, opts: &TagOptions) -> bool {
+    opts.mode == TagMode::Skip
+        && tagged_file
+            .tags()
+            .iter()
+            .any(|tag| tag.item_count() > 0 || tag.picture_count() > 0)
+}
 
+/// Set title, artist, album and cover on the primary (or first) tag,
+/// adding one first if the file has none and `opts` allows it. Under
+/// [`TagMode::FillMissing`] fields the tag already has are kept.
+#[allow(clippy::missing_panics_doc)]
+fn fill_tag<'a>(
+    tagged_file: &'a mut TaggedFile,
+    metadata: &NcmMetadata,
+    cover: Option<&[u8]>,
+    opts: &TagOptions,
+) -> Result<&'a mut Tag> {
+    if tagged_file.first_tag().is_none() && opts.create_tag {
+        let tag_type = tagged_file.primary_tag_type();
+        tagged_file.insert_tag(Tag::new(tag_type));
+    }
     let has_primary = tagged_file.primary_tag().is_some();
     // primary_tag_mut() is guaranteed Some when primary_tag() was Some
     let tag = if has_primary {
@@ -30,25 +359,540 @@ pub fn write_tags(path: &Path, metadata: &NcmMetadata, cover: Option<&[u8]>) ->
             .ok_or_else(|| NcmError::Tag("no tag found in file".into()))?
     };
 
-    tag.set_title(metadata.music_name.clone());
-    tag.set_artist(metadata.artist_names());
-    tag.set_album(metadata.album.clone());
+    let fill = opts.mode == TagMode::FillMissing;
+    let wanted = |tag: &Tag, key: ItemKey| !fill || tag.get(key).is_none();
 
-    if let Some(img_data) = cover {
-        let mime = if img_data.starts_with(&PNG_MAGIC) {
-            MimeType::Png
-        } else {
-            MimeType::Jpeg
+    if wanted(tag, ItemKey::TrackTitle) {
+        tag.set_title(metadata.music_name.clone());
+    }
+    if wanted(tag, ItemKey::TrackArtist) {
+        tag.set_artist(metadata.artist_names());
+    }
+    if wanted(tag, ItemKey::AlbumTitle) {
+        tag.set_album(metadata.album.clone());
+    }
+    if let Some(album_artist) = metadata
+        .album_artist()
+        .filter(|_| wanted(tag, ItemKey::AlbumArtist))
+    {
+        tag.insert_text(ItemKey::AlbumArtist, album_artist);
+    }
+    if let Some(track) = metadata
+        .track_number()
+        .filter(|_| wanted(tag, ItemKey::TrackNumber))
+    {
+        tag.set_track(track);
+    }
+    if let Some(disc) = metadata.disc().filter(|_| wanted(tag, ItemKey::DiscNumber)) {
+        tag.set_disk(disc);
+    }
+    if let Some(year) = metadata.year().filter(|_| !fill || tag.date().is_none()) {
+        tag.set_date(Timestamp {
+            year,
+            ..Timestamp::default()
+        });
+    }
+    if let Some(genre) = metadata.genre().filter(|_| wanted(tag, ItemKey::Genre)) {
+        tag.set_genre(genre.to_owned());
+    }
+    if opts.keep_163_key && wanted(tag, ItemKey::Comment) {
+        let key = match &metadata.raw_key {
+            Some(key) => key.clone(),
+            None => metadata.to_163_key()?,
+        };
+        tag.set_comment(key);
+    }
+
+    if !fill || tag.picture_count() == 0 {
+        if let Some(img_data) = prepare_cover(cover, opts)? {
+            tag.push_picture(cover_picture(&img_data));
+        }
+    }
+
+    Ok(tag)
+}
+
+/// `cover` as it is to be embedded, processed if `opts` asks for it.
+#[cfg_attr(
+    not(feature = "cover"),
+    allow(unused_variables, clippy::unnecessary_wraps)
+)]
+fn prepare_cover<'a>(cover: Option<&'a [u8]>, opts: &TagOptions) -> Result<Option<Cow<'a, [u8]>>> {
+    #[cfg(feature = "cover")]
+    if let (Some(data), Some(cover_opts)) = (cover, &opts.cover) {
+        return Ok(Some(Cow::Owned(crate::cover::process(data, cover_opts)?)));
+    }
+    Ok(cover.map(Cow::Borrowed))
+}
+
+/// Read the primary (or first) tag of an audio file.
+///
+/// Files without any tag yield an [`AudioTags`] with only `duration_ms` set.
+pub fn read_tags(path: &Path) -> Result<AudioTags> {
+    let tagged_file = open_tagged(path)?;
+
+    let duration_ms = u64::try_from(tagged_file.properties().duration().as_millis()).unwrap_or(0);
+    let Some(tag) = tagged_file
+        .primary_tag()
+        .or_else(|| tagged_file.first_tag())
+    else {
+        return Ok(AudioTags {
+            duration_ms,
+            ..AudioTags::default()
+        });
+    };
+    let get = |key| tag.get_string(key).map(String::from);
+    let get_all = |key| tag.get_strings(key).map(String::from).collect();
+    Ok(AudioTags {
+        title: tag.title().map(String::from),
+        artist: tag.artist().map(String::from),
+        album: tag.album().map(String::from),
+        album_artist: tag.get_string(ItemKey::AlbumArtist).map(String::from),
+        track: tag.track(),
+        year: tag.date().map(|d| d.year),
+        comment: tag.comment().map(String::from),
+        lyrics: tag.get_string(ItemKey::Lyrics).map(String::from),
+        has_cover: !tag.pictures().is_empty(),
+        musicbrainz: MusicBrainzIds {
+            recording: get(ItemKey::MusicBrainzRecordingId),
+            release_track: get(ItemKey::MusicBrainzTrackId),
+            release: get(ItemKey::MusicBrainzReleaseId),
+            release_group: get(ItemKey::MusicBrainzReleaseGroupId),
+            artists: get_all(ItemKey::MusicBrainzArtistId),
+            album_artists: get_all(ItemKey::MusicBrainzReleaseArtistId),
+        },
+        replay_gain: ReplayGain {
+            track_gain: get(ItemKey::ReplayGainTrackGain)
+                .as_deref()
+                .and_then(parse_replay_gain),
+            track_peak: get(ItemKey::ReplayGainTrackPeak)
+                .as_deref()
+                .and_then(parse_replay_gain),
+            album_gain: get(ItemKey::ReplayGainAlbumGain)
+                .as_deref()
+                .and_then(parse_replay_gain),
+            album_peak: get(ItemKey::ReplayGainAlbumPeak)
+                .as_deref()
+                .and_then(parse_replay_gain),
+        },
+        duration_ms,
+    })
+}
+
+/// The front cover (or, failing that, the first picture) embedded in an
+/// audio file.
+pub fn read_cover(path: &Path) -> Result<Option<Vec<u8>>> {
+    let tagged_file = open_tagged(path)?;
+    let pictures = tagged_file.tags().iter().flat_map(Tag::pictures);
+    let cover = pictures
+        .clone()
+        .find(|p| p.pic_type() == PictureType::CoverFront)
+        .or_else(|| pictures.clone().next());
+    Ok(cover.map(|p| p.data().to_vec()))
+}
+
+/// Read the tags and properties of the audio file at `path`.
+///
+/// Its type is told by content rather than extension: an M4A or Ogg payload
+/// may still carry the `.mp3`/`.flac` name an older version gave it, and an
+/// `.ogg` may hold Opus rather than Vorbis.
+fn open_tagged(path: &Path) -> Result<TaggedFile> {
+    Probe::open(path)
+        .map_err(|e| NcmError::Tag(e.to_string()))?
+        .guess_file_type()?
+        .read()
+        .map_err(|e| NcmError::Tag(e.to_string()))
+}
+
+/// The size of an audio file without its tags, i.e. of the MP3 or FLAC
+/// stream that tagging leaves alone. A leading ID3 tag and FLAC metadata
+/// blocks (comments, pictures and padding) are left out, as are trailing
+/// ID3 (v1) and APE tags.
+///
+/// `None` for M4A and Ogg, which keep their tags inside the container
+/// (`udta` boxes, comment header pages), so tagging changes the stream.
+pub fn stream_len(path: &Path) -> Result<Option<u64>> {
+    let mut file = File::open(path)?;
+    let size = file.metadata()?.len();
+    let mut start = 0;
+    let mut header = [0u8; 10];
+    let full = read_at(&mut file, 0, &mut header)?;
+    if matches!(
+        AudioFormat::detect(&header),
+        Some(AudioFormat::M4a | AudioFormat::Ogg)
+    ) {
+        return Ok(None);
+    }
+    if full && header.starts_with(b"ID3") {
+        let len = header[6..]
+            .iter()
+            .fold(0, |len, &b| len << 7 | u64::from(b & 0x7F));
+        let footer = if header[5] & 0x10 == 0 { 0 } else { 10 };
+        start = 10 + len + footer;
+    }
+    let mut block = [0u8; 4];
+    if read_at(&mut file, start, &mut block)? && &block == b"fLaC" {
+        start += 4;
+        while read_at(&mut file, start, &mut block)? {
+            start += 4 + u64::from(u32::from_be_bytes([0, block[1], block[2], block[3]]));
+            if block[0] & 0x80 != 0 {
+                break;
+            }
+        }
+    }
+
+    let mut end = size;
+    let mut trailer = [0u8; 32];
+    if end >= start + 128
+        && read_at(&mut file, end - 128, &mut trailer[..3])?
+        && &trailer[..3] == b"TAG"
+    {
+        end -= 128;
+    }
+    if end >= start + 32
+        && read_at(&mut file, end - 32, &mut trailer)?
+        && trailer.starts_with(b"APETAGEX")
+    {
+        let field = |at: usize| {
+            u64::from(u32::from_le_bytes([
+                trailer[at],
+                trailer[at + 1],
+                trailer[at + 2],
+                trailer[at + 3],
+            ]))
         };
-        let pic = Picture::unchecked(img_data.to_vec())
-            .pic_type(PictureType::CoverFront)
-            .mime_type(mime)
-            .build();
-        tag.push_picture(pic);
+        // The size counts the footer but not the optional header.
+        let header = if field(20) & 0x8000_0000 == 0 { 0 } else { 32 };
+        end = end.saturating_sub(field(12) + header);
+    }
+    Ok(Some(end.saturating_sub(start)))
+}
+
+/// Fill `buf` from `offset` in `file`; `false` if the file ends first.
+fn read_at(file: &mut File, offset: u64, buf: &mut [u8]) -> Result<bool> {
+    file.seek(SeekFrom::Start(offset))?;
+    match file.read_exact(buf) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Overwrite tag fields of an audio file with the values present in `tags`.
+///
+/// `None` (or empty) fields, `has_cover` and `duration_ms` are ignored; existing values for
+/// them are kept. A given `cover` replaces any front cover. A tag is created if the
+/// file has none.
+pub fn update_tags(path: &Path, tags: &AudioTags, cover: Option<&[u8]>) -> Result<()> {
+    let mut tagged_file = open_tagged(path)?;
+
+    if tagged_file.primary_tag().is_none() {
+        tagged_file.insert_tag(Tag::new(tagged_file.primary_tag_type()));
+    }
+    let tag = tagged_file
+        .primary_tag_mut()
+        .ok_or_else(|| NcmError::Tag("no tag found in file".into()))?;
+
+    if let Some(title) = &tags.title {
+        tag.set_title(title.clone());
+    }
+    if let Some(artist) = &tags.artist {
+        tag.set_artist(artist.clone());
+    }
+    if let Some(album) = &tags.album {
+        tag.set_album(album.clone());
+    }
+    if let Some(album_artist) = &tags.album_artist {
+        tag.insert_text(ItemKey::AlbumArtist, album_artist.clone());
+    }
+    for (key, value) in tags.musicbrainz.single_keys() {
+        if let Some(value) = value {
+            tag.insert_text(key, value.clone());
+        }
+    }
+    for (key, values) in tags.musicbrainz.multi_keys() {
+        if values.is_empty() {
+            continue;
+        }
+        tag.remove_key(key);
+        for value in values {
+            tag.push(TagItem::new(key, ItemValue::Text(value.clone())));
+        }
+    }
+    for (key, _, value) in tags.replay_gain.keys() {
+        if let Some(value) = value {
+            tag.insert_text(key, value);
+        }
+    }
+    if let Some(track) = tags.track {
+        tag.set_track(track);
+    }
+    if let Some(year) = tags.year {
+        tag.set_date(Timestamp {
+            year,
+            ..Timestamp::default()
+        });
+    }
+    if let Some(comment) = &tags.comment {
+        tag.set_comment(comment.clone());
+    }
+    if let Some(lyrics) = &tags.lyrics {
+        tag.insert_text(ItemKey::Lyrics, lyrics.clone());
+    }
+    if let Some(img_data) = cover {
+        tag.remove_picture_type(PictureType::CoverFront);
+        tag.push_picture(cover_picture(img_data));
     }
 
     tag.save_to_path(path, WriteOptions::default())
         .map_err(|e| NcmError::Tag(e.to_string()))?;
 
+    if tagged_file.file_type() == FileType::Mpeg {
+        write_id3_frames(path, tags)?;
+    }
     Ok(())
 }
+
+/// ID3 keeps the recording MBID in a `UFID` frame, and the other single
+/// MBIDs and the ReplayGain values in `TXXX` frames, which lofty's generic
+/// tag drops on save; set them on the ID3 tag itself.
+fn write_id3_frames(path: &Path, tags: &AudioTags) -> Result<()> {
+    let ids = &tags.musicbrainz;
+    let mut user_text: Vec<(&str, String)> = [
+        ("MusicBrainz Release Track Id", &ids.release_track),
+        ("MusicBrainz Album Id", &ids.release),
+        ("MusicBrainz Release Group Id", &ids.release_group),
+    ]
+    .into_iter()
+    .filter_map(|(description, value)| Some((description, value.clone()?)))
+    .collect();
+    user_text.extend(
+        tags.replay_gain
+            .keys()
+            .into_iter()
+            .filter_map(|(_, description, value)| Some((description, value?))),
+    );
+    if ids.recording.is_none() && user_text.is_empty() {
+        return Ok(());
+    }
+    let mut file = std::fs::File::open(path)?;
+    let mpeg = MpegFile::read_from(&mut file, ParseOptions::new())
+        .map_err(|e| NcmError::Tag(e.to_string()))?;
+    let Some(mut tag) = mpeg.id3v2().cloned() else {
+        return Ok(());
+    };
+    if let Some(id) = &ids.recording {
+        tag.insert(Frame::UniqueFileIdentifier(UniqueFileIdentifierFrame::new(
+            "http://musicbrainz.org",
+            id.clone().into_bytes(),
+        )));
+    }
+    for (description, value) in user_text {
+        tag.insert_user_text(description.to_owned(), value);
+    }
+    tag.save_to_path(path, WriteOptions::default())
+        .map_err(|e| NcmError::Tag(e.to_string()))
+}
+
+/// Build a front-cover picture, detecting PNG vs JPEG from the magic bytes.
+fn cover_picture(img_data: &[u8]) -> Picture {
+    let mime = match ImageKind::detect(img_data) {
+        ImageKind::Png => MimeType::Png,
+        ImageKind::Jpeg => MimeType::Jpeg,
+    };
+    Picture::unchecked(img_data.to_vec())
+        .pic_type(PictureType::CoverFront)
+        .mime_type(mime)
+        .build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replay_gain_values() {
+        let gain = ReplayGain {
+            track_gain: Some(-6.516),
+            track_peak: Some(0.988_525),
+            ..ReplayGain::default()
+        };
+        let values: Vec<Option<String>> = gain.keys().into_iter().map(|(_, _, v)| v).collect();
+        assert_eq!(
+            values,
+            [Some("-6.52 dB".into()), Some("0.988525".into()), None, None]
+        );
+        assert_eq!(parse_replay_gain(" +1.25 dB"), Some(1.25));
+        assert_eq!(parse_replay_gain("-3.1db"), Some(-3.1));
+        assert_eq!(parse_replay_gain("0.5"), Some(0.5));
+        assert_eq!(parse_replay_gain("loud"), None);
+    }
+
+    #[test]
+    fn test_write_album_fields() {
+        let dir = std::env::temp_dir().join(format!("ncmdump-tag-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let input = concat!(env!("CARGO_MANIFEST_DIR"), "/../testdata/test.ncm");
+        let out = crate::convert(Path::new(input), Some(&dir)).unwrap();
+
+        let json = br#"{"musicName":"X","album":"A","artist":[["B",1]],"bitrate":128000,"duration":1000,"format":"flac","trackNumber":4,"cd":"1","publishTime":946684800000,"genre":"Jazz","albumArtist":"C"}"#;
+        let meta = NcmMetadata::from_decrypted(json).unwrap();
+        let opts = TagOptions {
+            keep_163_key: true,
+            ..TagOptions::default()
+        };
+        write_tags_with(&out, &meta, None, &opts).unwrap();
+        let tags = read_tags(&out).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(tags.title.as_deref(), Some("X"));
+        assert_eq!(tags.album_artist.as_deref(), Some("C"));
+        assert_eq!(tags.track, Some(4));
+        assert_eq!(tags.year, Some(2000));
+        let key = NcmMetadata::from_163_key(tags.comment.as_deref().unwrap()).unwrap();
+        assert_eq!(key.album, "A");
+    }
+
+    #[test]
+    fn test_tag_bare_payload() {
+        let dir = std::env::temp_dir().join(format!("ncmdump-bare-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let input = concat!(env!("CARGO_MANIFEST_DIR"), "/../testdata/test.ncm");
+        let out = crate::convert(Path::new(input), Some(&dir)).unwrap();
+        let meta = NcmMetadata::read_from(Path::new(input)).unwrap().unwrap();
+
+        lofty::tag::TagType::VorbisComments
+            .remove_from_path(&out)
+            .unwrap();
+        let keep_bare = TagOptions {
+            create_tag: false,
+            ..TagOptions::default()
+        };
+        assert!(matches!(
+            write_tags_with(&out, &meta, None, &keep_bare),
+            Err(NcmError::Tag(_))
+        ));
+        write_tags(&out, &meta, None).unwrap();
+        let tags = read_tags(&out).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(tags.title.as_deref(), Some(meta.music_name.as_str()));
+    }
+
+    #[test]
+    fn test_tag_modes() {
+        let dir = std::env::temp_dir().join(format!("ncmdump-modes-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let input = concat!(env!("CARGO_MANIFEST_DIR"), "/../testdata/test.ncm");
+        let filled = crate::convert(Path::new(input), Some(&dir)).unwrap();
+        let skipped = dir.join("skipped.flac");
+        std::fs::copy(&filled, &skipped).unwrap();
+        let original = read_tags(&filled).unwrap();
+        let original_bytes = std::fs::read(&skipped).unwrap();
+
+        // Differs from the file's tags, and has fields they lack.
+        let json = br#"{"musicName":"Other","album":"Other","artist":[["Other",1]],"bitrate":128000,"duration":1000,"format":"flac","trackNumber":9,"publishTime":946684800000}"#;
+        let meta = NcmMetadata::from_decrypted(json).unwrap();
+        let opts = |mode| TagOptions {
+            mode,
+            keep_163_key: true,
+            ..TagOptions::default()
+        };
+        write_tags_with(&filled, &meta, None, &opts(TagMode::FillMissing)).unwrap();
+        let fill = read_tags(&filled).unwrap();
+        write_tags_with(&skipped, &meta, None, &opts(TagMode::Skip)).unwrap();
+        let skip = read_tags(&skipped).unwrap();
+        let skipped_bytes = std::fs::read(&skipped).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(original.title.is_some() && original.track.is_none());
+        assert_eq!(fill.title, original.title);
+        assert_eq!(fill.artist, original.artist);
+        assert_eq!(fill.album, original.album);
+        assert_eq!(fill.track, Some(9));
+        assert_eq!(fill.year, Some(2000));
+
+        assert_eq!(skipped_bytes, original_bytes);
+        assert_eq!(skip.title, original.title);
+        assert_eq!(skip.track, None);
+        assert_eq!(skip.year, None);
+        assert_eq!(skip.comment, original.comment);
+    }
+
+    /// Silent MPEG-1 Layer III frames (128 kbps, 44.1 kHz), untagged.
+    fn mp3() -> Vec<u8> {
+        let mut frame = vec![0u8; 417];
+        frame[..4].copy_from_slice(&[0xFF, 0xFB, 0x90, 0x64]);
+        frame.repeat(20)
+    }
+
+    /// The text encoding byte of the first `id` frame in `file`.
+    fn frame_encoding(file: &[u8], id: &[u8]) -> Option<u8> {
+        let pos = file.windows(4).position(|w| w == id)?;
+        file.get(pos + 10).copied()
+    }
+
+    #[test]
+    fn test_id3_version_and_encoding() {
+        let dir = std::env::temp_dir().join(format!("ncmdump-id3-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let json = r#"{"musicName":"Ünïcode 测试","album":"A","artist":[["B",1]],"bitrate":128000,"duration":1000,"format":"mp3","publishTime":946684800000}"#;
+        let meta = NcmMetadata::from_decrypted(json.as_bytes()).unwrap();
+        let write = |name: &str, id3_version, id3_encoding| {
+            let path = dir.join(name);
+            std::fs::write(&path, mp3()).unwrap();
+            let opts = TagOptions {
+                id3_version,
+                id3_encoding,
+                ..TagOptions::default()
+            };
+            write_tags_with(&path, &meta, None, &opts).unwrap();
+            (std::fs::read(&path).unwrap(), read_tags(&path).unwrap())
+        };
+        let (v23, v23_tags) = write("v23.mp3", Id3Version::V23, Id3Encoding::Utf8);
+        let (v24, v24_tags) = write("v24.mp3", Id3Version::V24, Id3Encoding::Utf8);
+        let (utf16, _) = write("utf16.mp3", Id3Version::V24, Id3Encoding::Utf16);
+        let (latin1, latin1_tags) = write("latin1.mp3", Id3Version::V23, Id3Encoding::Latin1);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        // ID3v2.3: the year in TYER, text in UTF-16.
+        assert_eq!(&v23[..4], b"ID3\x03");
+        assert!(v23.windows(4).any(|w| w == b"TYER"));
+        assert!(!v23.windows(4).any(|w| w == b"TDRC"));
+        assert_eq!(frame_encoding(&v23, b"TIT2"), Some(1));
+        assert_eq!(v23_tags.title.as_deref(), Some("Ünïcode 测试"));
+        assert_eq!(v23_tags.year, Some(2000));
+
+        // ID3v2.4: TDRC, UTF-8 unless asked otherwise.
+        assert_eq!(&v24[..4], b"ID3\x04");
+        assert!(v24.windows(4).any(|w| w == b"TDRC"));
+        assert_eq!(frame_encoding(&v24, b"TIT2"), Some(3));
+        assert_eq!(v24_tags.year, Some(2000));
+        assert_eq!(frame_encoding(&utf16, b"TIT2"), Some(1));
+
+        assert_eq!(frame_encoding(&latin1, b"TIT2"), Some(0));
+        assert_eq!(latin1_tags.title.as_deref(), Some("Ünïcode ??"));
+    }
+
+    #[test]
+    fn test_stream_len() {
+        let dir = std::env::temp_dir().join(format!("ncmdump-stream-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("stream.mp3");
+        let mut audio = mp3();
+        let len = audio.len() as u64;
+        // An ID3v1 tag at the end, as some payloads carry.
+        audio.extend_from_slice(b"TAG");
+        audio.resize(audio.len() + 125, 0);
+        std::fs::write(&path, &audio).unwrap();
+        assert_eq!(stream_len(&path).unwrap(), Some(len));
+
+        let json = r#"{"musicName":"T","album":"A","artist":[["B",1]],"bitrate":128000,"duration":1000,"format":"mp3"}"#;
+        let meta = NcmMetadata::from_decrypted(json.as_bytes()).unwrap();
+        write_tags(&path, &meta, Some(&[0xFF, 0xD8, 0xFF, 0xE0])).unwrap();
+        let tagged = std::fs::metadata(&path).unwrap().len();
+        let stream = stream_len(&path).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(tagged > len);
+        assert_eq!(stream, Some(len));
+    }
+}
@@ -0,0 +1,170 @@
+//! File name templates such as `{artist} - {title}`.
+//!
+//! Placeholders are written in braces; `{{` and `}}` produce literal braces.
+//! Substituted values are passed through [`sanitize`], while literal template
+//! text is kept as-is so a `/` in the template can create sub-directories.
+//! Each resulting path component is then made writable with
+//! [`path::component`](crate::path::component): shortened to fit file
+//! system limits and free of names Windows rejects.
+//!
+//! | Placeholder | Value                                   |
+//! |-------------|-----------------------------------------|
+//! | `{artist}`  | Artist names                            |
+//! | `{title}`   | Track title                             |
+//! | `{album}`   | Album name                              |
+//! | `{track}`   | Track number, zero-padded to two digits |
+//! | `{year}`    | Release year                            |
+
+use crate::error::{NcmError, Result};
+
+/// Template used when none is given: `{artist} - {title}`.
+pub const DEFAULT_TEMPLATE: &str = "{artist} - {title}";
+
+/// Placeholder names understood by [`render`].
+pub const FIELDS: &[&str] = &["artist", "title", "album", "track", "year"];
+
+/// Expand `template`, looking each placeholder up with `field`.
+///
+/// # Errors
+///
+/// Returns [`NcmError::Template`] for unbalanced braces, placeholders not in
+/// [`FIELDS`], or placeholders for which `field` returns `None`.
+pub fn render(template: &str, mut field: impl FnMut(&str) -> Option<String>) -> Result<String> {
+    let mut out = String::with_capacity(template.len());
+    let mut chars = template.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' => {
+                let rest = chars.as_str();
+                if let Some(stripped) = rest.strip_prefix('{') {
+                    out.push('{');
+                    chars = stripped.chars();
+                    continue;
+                }
+                let end = rest
+                    .find('}')
+                    .ok_or_else(|| NcmError::Template(format!("unclosed `{{` in {template:?}")))?;
+                let name = &rest[..end];
+                if !FIELDS.contains(&name) {
+                    return Err(NcmError::Template(format!(
+                        "unknown placeholder {{{name}}} (expected one of: {})",
+                        FIELDS.join(", ")
+                    )));
+                }
+                let value = field(name)
+                    .filter(|v| !v.trim().is_empty())
+                    .ok_or_else(|| NcmError::Template(format!("no value for {{{name}}}")))?;
+                out.push_str(&sanitize(&value));
+                chars = rest[end + 1..].chars();
+            }
+            '}' => {
+                let rest = chars.as_str();
+                let stripped = rest
+                    .strip_prefix('}')
+                    .ok_or_else(|| NcmError::Template(format!("unmatched `}}` in {template:?}")))?;
+                out.push('}');
+                chars = stripped.chars();
+            }
+            c => out.push(c),
+        }
+    }
+    let mut path = String::with_capacity(out.len());
+    let mut rest = out.as_str();
+    while let Some(end) = rest.find(['/', '\\']) {
+        path.push_str(&crate::path::component(&rest[..end]));
+        path.push_str(&rest[end..=end]);
+        rest = &rest[end + 1..];
+    }
+    path.push_str(&crate::path::component(rest));
+    Ok(path)
+}
+
+/// Make `name` safe to use as a single path component.
+///
+/// Replaces path separators, characters reserved on Windows and control
+/// characters with `_`, and trims surrounding whitespace. `.` and `..`,
+/// which would name a directory, become `_`.
+pub fn sanitize(name: &str) -> String {
+    let name = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect::<String>();
+    match name.trim() {
+        "." | ".." => "_".to_owned(),
+        name => name.to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lookup(name: &str) -> Option<String> {
+        match name {
+            "artist" => Some("AC/DC".into()),
+            "title" => Some("Thunderstruck".into()),
+            "track" => Some("01".into()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_render_default() {
+        let name = render(DEFAULT_TEMPLATE, lookup).unwrap();
+        assert_eq!(name, "AC_DC - Thunderstruck");
+    }
+
+    #[test]
+    fn test_render_literal_separator_and_braces() {
+        let name = render("{artist}/{track} {{live}}", lookup).unwrap();
+        assert_eq!(name, "AC_DC/01 {live}");
+    }
+
+    #[test]
+    fn test_render_fits_components() {
+        let long = "晴".repeat(100);
+        let name = render("{album}/{title}.", |f| match f {
+            "album" => Some(long.clone()),
+            _ => Some("Vol. 2".into()),
+        })
+        .unwrap();
+        let (album, title) = name.split_once('/').unwrap();
+        assert_eq!(album.len(), crate::path::MAX_STEM_BYTES);
+        assert_eq!(title, "Vol. 2");
+    }
+
+    #[test]
+    fn test_render_stays_inside() {
+        let name = render("{artist}/{title}", |f| match f {
+            "artist" => Some("..".into()),
+            _ => Some(".".into()),
+        })
+        .unwrap();
+        assert_eq!(name, "_/_");
+        assert!(
+            std::path::Path::new(&name)
+                .components()
+                .all(|c| matches!(c, std::path::Component::Normal(_)))
+        );
+    }
+
+    #[test]
+    fn test_render_errors() {
+        assert!(render("{album}", lookup).is_err());
+        assert!(render("{genre}", lookup).is_err());
+        assert!(render("{title", lookup).is_err());
+        assert!(render("title}", lookup).is_err());
+    }
+
+    #[test]
+    fn test_sanitize() {
+        assert_eq!(sanitize(" .. "), "_");
+        assert_eq!(sanitize("."), "_");
+        assert_eq!(sanitize("..."), "...");
+        assert_eq!(sanitize("a/b:c"), "a_b_c");
+    }
+}
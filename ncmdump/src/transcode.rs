@@ -0,0 +1,318 @@
+//! Audio transcoding with ffmpeg (feature `transcode`).
+//!
+//! Converts decrypted tracks — typically FLAC — to Opus, MP3 or AAC, either
+//! file to file (keeping tags and, where the container can hold it, the
+//! cover), from a stream into a file as it arrives (a download that never
+//! lands on disk in its original format) or as a stream for serving over a
+//! slow link. The `ffmpeg` binary must be on `PATH`; nothing is linked.
+
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::process::{Child, ChildStdout, Command, Stdio};
+
+use crate::error::{NcmError, Result};
+
+/// Target codec.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Codec {
+    /// Opus in Ogg (`.opus`).
+    Opus,
+    /// MP3 (`.mp3`).
+    Mp3,
+    /// AAC, in MP4 (`.m4a`) for files and ADTS for streams.
+    Aac,
+}
+
+impl Codec {
+    /// Extension of a transcoded file.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Opus => "opus",
+            Self::Mp3 => "mp3",
+            Self::Aac => "m4a",
+        }
+    }
+
+    /// MIME type of a transcoded stream.
+    pub fn stream_mime(self) -> &'static str {
+        match self {
+            Self::Opus => "audio/ogg",
+            Self::Mp3 => "audio/mpeg",
+            Self::Aac => "audio/aac",
+        }
+    }
+
+    /// The codec named `name` (`opus`/`ogg`, `mp3`, `aac`/`m4a`), ignoring
+    /// case.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "opus" | "ogg" => Some(Self::Opus),
+            "mp3" => Some(Self::Mp3),
+            "aac" | "m4a" => Some(Self::Aac),
+            _ => None,
+        }
+    }
+}
+
+/// What to transcode to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Target {
+    pub codec: Codec,
+    /// Bitrate in kbit/s; `None` for the codec's default (Opus 160, AAC 256,
+    /// MP3 VBR V0).
+    pub bitrate: Option<u32>,
+}
+
+impl Target {
+    pub fn new(codec: Codec) -> Self {
+        Self {
+            codec,
+            bitrate: None,
+        }
+    }
+
+    /// Output options after `-i`. Files get the cover where the container
+    /// can hold it; streams carry audio only, in a format that needs no
+    /// seeking to write.
+    fn args(self, stream: bool) -> Vec<String> {
+        let mut args: Vec<String> = ["-map_metadata", "0", "-map", "0:a"]
+            .map(String::from)
+            .into();
+        let with_cover = !stream && self.codec != Codec::Opus;
+        if with_cover {
+            args.extend(["-map", "0:v?", "-c:v", "copy"].map(String::from));
+        }
+        let (encoder, default) = match self.codec {
+            Codec::Opus => ("libopus", "160k"),
+            Codec::Mp3 => ("libmp3lame", ""),
+            Codec::Aac => ("aac", "256k"),
+        };
+        args.extend(["-c:a".into(), encoder.into()]);
+        match self.bitrate {
+            Some(kbps) => args.extend(["-b:a".into(), format!("{kbps}k")]),
+            None if self.codec == Codec::Mp3 => args.extend(["-q:a", "0"].map(String::from)),
+            None => args.extend(["-b:a".into(), default.into()]),
+        }
+        let format = match self.codec {
+            Codec::Opus => "opus",
+            Codec::Mp3 => {
+                args.extend(["-id3v2_version", "3"].map(String::from));
+                "mp3"
+            }
+            Codec::Aac if stream => "adts",
+            Codec::Aac => {
+                if with_cover {
+                    args.extend(["-disposition:v", "attached_pic"].map(String::from));
+                }
+                "ipod"
+            }
+        };
+        args.extend(["-f".into(), format.into()]);
+        args
+    }
+}
+
+/// Whether `ffmpeg` can be run.
+pub fn available() -> bool {
+    Command::new("ffmpeg")
+        .arg("-version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|s| s.success())
+}
+
+fn ffmpeg() -> Command {
+    let mut cmd = Command::new("ffmpeg");
+    cmd.args(["-hide_banner", "-loglevel", "error", "-nostdin", "-y"]);
+    cmd
+}
+
+/// Convert the audio file `src` into `dest`, keeping its tags (and the cover
+/// where the container supports it). A partial `dest` is removed on failure.
+///
+/// # Errors
+///
+/// Returns [`NcmError::Io`] if ffmpeg cannot be started and
+/// [`NcmError::Transcode`] with its message if it fails.
+pub fn transcode_file(src: &Path, dest: &Path, target: Target) -> Result<()> {
+    tracing::debug!(src = %src.display(), dest = %dest.display(), args = ?target.args(false), "running ffmpeg");
+    let output = ffmpeg()
+        .arg("-i")
+        .arg(crate::path::extended(src).as_os_str())
+        .args(target.args(false))
+        .arg(crate::path::extended(dest).as_os_str())
+        .output()?;
+    if !output.status.success() {
+        let _ = std::fs::remove_file(dest);
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(NcmError::Transcode(error.trim().to_owned()));
+    }
+    Ok(())
+}
+
+/// Transcode the audio read from `input` into the file `dest` as it arrives,
+/// keeping tags and cover like [`transcode_file`]. The input is never
+/// stored; a partial `dest` is removed on failure.
+///
+/// # Errors
+///
+/// Returns [`NcmError::Io`] if ffmpeg cannot be started or reading `input`
+/// fails, and [`NcmError::Transcode`] with its message if ffmpeg fails.
+pub fn transcode_into<R: Read>(input: &mut R, dest: &Path, target: Target) -> Result<()> {
+    tracing::debug!(dest = %dest.display(), args = ?target.args(false), "running ffmpeg on a stream");
+    let mut child = ffmpeg()
+        .args(["-i", "pipe:0"])
+        .args(target.args(false))
+        .arg(crate::path::extended(dest).as_os_str())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let (Some(mut stdin), Some(mut stderr)) = (child.stdin.take(), child.stderr.take()) else {
+        let _ = child.kill();
+        return Err(NcmError::Transcode("ffmpeg pipes unavailable".into()));
+    };
+    // Drained on its own so a chatty ffmpeg can't stall on a full pipe.
+    let errors = std::thread::spawn(move || {
+        let mut text = String::new();
+        let _ = stderr.read_to_string(&mut text);
+        text
+    });
+    let copied = io::copy(input, &mut stdin).and_then(|_| stdin.flush());
+    drop(stdin);
+    let failed = |child: &mut Child| {
+        let _ = child.kill();
+        let _ = child.wait();
+        let _ = std::fs::remove_file(dest);
+    };
+    match copied {
+        // ffmpeg closed its input early; its status and message say why.
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => {}
+        Err(e) => {
+            failed(&mut child);
+            return Err(e.into());
+        }
+        Ok(()) => {}
+    }
+    let status = child.wait()?;
+    let errors = errors.join().unwrap_or_default();
+    if !status.success() {
+        failed(&mut child);
+        return Err(NcmError::Transcode(errors.trim().to_owned()));
+    }
+    Ok(())
+}
+
+/// Transcode the audio read from `input` as it arrives. The returned reader
+/// yields the encoded stream; dropping it stops ffmpeg.
+///
+/// # Errors
+///
+/// Returns [`NcmError::Io`] if ffmpeg cannot be started. Later failures
+/// surface as read errors.
+pub fn transcode_stream<R: Read + Send + 'static>(input: R, target: Target) -> Result<Stream> {
+    tracing::debug!(args = ?target.args(true), "starting ffmpeg stream");
+    let mut child = ffmpeg()
+        .args(["-i", "pipe:0"])
+        .args(target.args(true))
+        .arg("pipe:1")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()?;
+    let (Some(mut stdin), Some(stdout)) = (child.stdin.take(), child.stdout.take()) else {
+        let _ = child.kill();
+        return Err(NcmError::Transcode("ffmpeg pipes unavailable".into()));
+    };
+    std::thread::spawn(move || {
+        let mut input = input;
+        // Fails with a broken pipe once the reader is dropped.
+        let _ = io::copy(&mut input, &mut stdin).and_then(|_| stdin.flush());
+    });
+    Ok(Stream { child, stdout })
+}
+
+/// The output of [`transcode_stream`].
+pub struct Stream {
+    child: Child,
+    stdout: ChildStdout,
+}
+
+impl Read for Stream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.stdout.read(buf)?;
+        if n == 0 && !buf.is_empty() {
+            let status = self.child.wait()?;
+            if !status.success() {
+                return Err(io::Error::other(format!("ffmpeg failed ({status})")));
+            }
+        }
+        Ok(n)
+    }
+}
+
+impl Drop for Stream {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(target: Target, stream: bool) -> String {
+        target.args(stream).join(" ")
+    }
+
+    #[test]
+    fn test_file_args() {
+        assert_eq!(
+            args(Target::new(Codec::Opus), false),
+            "-map_metadata 0 -map 0:a -c:a libopus -b:a 160k -f opus"
+        );
+        assert_eq!(
+            args(Target::new(Codec::Mp3), false),
+            "-map_metadata 0 -map 0:a -map 0:v? -c:v copy -c:a libmp3lame -q:a 0 \
+             -id3v2_version 3 -f mp3"
+        );
+        assert_eq!(
+            args(
+                Target {
+                    codec: Codec::Aac,
+                    bitrate: Some(128)
+                },
+                false
+            ),
+            "-map_metadata 0 -map 0:a -map 0:v? -c:v copy -c:a aac -b:a 128k \
+             -disposition:v attached_pic -f ipod"
+        );
+    }
+
+    #[test]
+    fn test_stream_args() {
+        assert_eq!(
+            args(
+                Target {
+                    codec: Codec::Mp3,
+                    bitrate: Some(96)
+                },
+                true
+            ),
+            "-map_metadata 0 -map 0:a -c:a libmp3lame -b:a 96k -id3v2_version 3 -f mp3"
+        );
+        assert_eq!(
+            args(Target::new(Codec::Aac), true),
+            "-map_metadata 0 -map 0:a -c:a aac -b:a 256k -f adts"
+        );
+    }
+
+    #[test]
+    fn test_codec_from_name() {
+        assert_eq!(Codec::from_name("OPUS"), Some(Codec::Opus));
+        assert_eq!(Codec::from_name("m4a"), Some(Codec::Aac));
+        assert_eq!(Codec::from_name("flac"), None);
+    }
+}
@@ -0,0 +1,67 @@
+//! Album API.
+//!
+//! Endpoint: `POST /weapi/v1/album/{id}`
+//!
+//! Request: `{}`
+//!
+//! Response:
+//! ```json
+//! {
+//!   "code": 200,
+//!   "album": {
+//!     "id": 32311,
+//!     "name": "专辑名",
+//!     "artists": [{ "id": 6452, "name": "歌手" }],
+//!     "publishTime": 1136044800000,
+//!     "company": "唱片公司",
+//!     "description": "简介...",
+//!     "picUrl": "https://...",
+//!     "size": 10
+//!   },
+//!   "songs": [
+//!     { "id": 1, "name": "歌名", "ar": [...], "al": {...}, "dt": 240000, "no": 1 }
+//!   ]
+//! }
+//! ```
+
+use crate::client::NeteaseClient;
+use crate::error::{NeteaseError, Result};
+use crate::track::parse_track;
+use crate::types::{AlbumDetail, Artist};
+use serde_json::json;
+
+impl NeteaseClient {
+    /// Get album detail including its track listing.
+    ///
+    /// Does not require login for public albums.
+    pub fn album_detail(&self, id: u64) -> Result<AlbumDetail> {
+        let resp = self.request(&format!("/v1/album/{id}"), &json!({}))?;
+        let a = &resp["album"];
+        if a.is_null() {
+            return Err(NeteaseError::Other(format!("album not found: {id}")));
+        }
+        Ok(AlbumDetail {
+            id: a["id"].as_u64().unwrap_or(id),
+            name: a["name"].as_str().unwrap_or("").to_owned(),
+            artists: a["artists"]
+                .as_array()
+                .map(|arr| {
+                    arr.iter()
+                        .map(|x| Artist {
+                            id: x["id"].as_u64().unwrap_or(0),
+                            name: x["name"].as_str().unwrap_or("").to_owned(),
+                        })
+                        .collect()
+                })
+                .unwrap_or_default(),
+            publish_time: a["publishTime"].as_u64().unwrap_or(0),
+            company: a["company"].as_str().map(String::from),
+            description: a["description"].as_str().map(String::from),
+            pic_url: a["picUrl"].as_str().map(String::from),
+            tracks: resp["songs"]
+                .as_array()
+                .map(|arr| arr.iter().map(parse_track).collect())
+                .unwrap_or_default(),
+        })
+    }
+}
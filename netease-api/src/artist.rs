@@ -0,0 +1,128 @@
+//! Artist APIs.
+//!
+//! # Endpoints
+//!
+//! ## `artist_detail` — `POST /weapi/v1/artist/{id}`
+//!
+//! Request: `{}`
+//!
+//! Response:
+//! ```json
+//! {
+//!   "code": 200,
+//!   "artist": {
+//!     "id": 6452,
+//!     "name": "歌手",
+//!     "briefDesc": "简介...",
+//!     "picUrl": "https://...",
+//!     "albumSize": 40,
+//!     "musicSize": 500
+//!   },
+//!   "hotSongs": [ { "id": 1, "name": "歌名", "ar": [...], "al": {...}, "dt": 240000 } ]
+//! }
+//! ```
+//!
+//! ## `artist_albums` — `POST /weapi/artist/albums/{id}`
+//!
+//! Request: `{ "limit": 30, "offset": 0, "total": true }`
+//!
+//! Response:
+//! ```json
+//! {
+//!   "code": 200,
+//!   "more": true,
+//!   "hotAlbums": [{ "id": 32311, "name": "专辑名", "publishTime": 1136044800000, "size": 10 }]
+//! }
+//! ```
+//!
+//! Albums are returned newest first.
+//!
+//! ## `followed_artists` — `POST /weapi/artist/sublist`
+//!
+//! Request: `{ "limit": 25, "offset": 0, "total": true }` (requires login)
+//!
+//! Response:
+//! ```json
+//! {
+//!   "code": 200,
+//!   "count": 42,
+//!   "hasMore": true,
+//!   "data": [{ "id": 6452, "name": "歌手", "albumSize": 40 }]
+//! }
+//! ```
+
+use crate::client::NeteaseClient;
+use crate::error::{NeteaseError, Result};
+use crate::track::parse_track;
+use crate::types::{Artist, ArtistAlbum, ArtistDetail};
+use serde_json::json;
+
+impl NeteaseClient {
+    /// Get artist profile and top songs.
+    pub fn artist_detail(&self, id: u64) -> Result<ArtistDetail> {
+        let resp = self.request(&format!("/v1/artist/{id}"), &json!({}))?;
+        let a = &resp["artist"];
+        if a.is_null() {
+            return Err(NeteaseError::Other(format!("artist not found: {id}")));
+        }
+        Ok(ArtistDetail {
+            id: a["id"].as_u64().unwrap_or(id),
+            name: a["name"].as_str().unwrap_or("").to_owned(),
+            brief_desc: a["briefDesc"]
+                .as_str()
+                .filter(|s| !s.is_empty())
+                .map(String::from),
+            pic_url: a["picUrl"].as_str().map(String::from),
+            album_count: a["albumSize"].as_u64().unwrap_or(0),
+            track_count: a["musicSize"].as_u64().unwrap_or(0),
+            top_tracks: resp["hotSongs"]
+                .as_array()
+                .map(|arr| arr.iter().map(parse_track).collect())
+                .unwrap_or_default(),
+        })
+    }
+
+    /// List an artist's albums, newest first.
+    pub fn artist_albums(&self, id: u64, limit: u64, offset: u64) -> Result<Vec<ArtistAlbum>> {
+        let data = json!({ "limit": limit, "offset": offset, "total": true });
+        let resp = self.request(&format!("/artist/albums/{id}"), &data)?;
+        Ok(resp["hotAlbums"]
+            .as_array()
+            .map(|arr| {
+                arr.iter()
+                    .map(|v| ArtistAlbum {
+                        id: v["id"].as_u64().unwrap_or(0),
+                        name: v["name"].as_str().unwrap_or("").to_owned(),
+                        publish_time: v["publishTime"].as_u64().unwrap_or(0),
+                        track_count: v["size"].as_u64().unwrap_or(0),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    /// List the artists the current user follows, most recently followed
+    /// first.
+    ///
+    /// # Errors
+    ///
+    /// - [`NeteaseError::NotLoggedIn`] — no `MUSIC_U` cookie configured
+    pub fn followed_artists(&self, limit: u64, offset: u64) -> Result<Vec<Artist>> {
+        if !self.session().is_logged_in() {
+            return Err(NeteaseError::NotLoggedIn);
+        }
+        let data = json!({ "limit": limit, "offset": offset, "total": true });
+        let resp = self.request("/artist/sublist", &data)?;
+        Ok(resp["data"]
+            .as_array()
+            .map(|arr| {
+                arr.iter()
+                    .map(|v| Artist {
+                        id: v["id"].as_u64().unwrap_or(0),
+                        name: v["name"].as_str().unwrap_or("").to_owned(),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+}
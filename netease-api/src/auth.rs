@@ -9,11 +9,41 @@
 //! The `MUSIC_U` cookie is the authentication token issued by Netease after
 //! login. It can be obtained from browser developer tools → Application → Cookies
 //! on `music.163.com`. Typical lifetime is several months.
+//!
+//! # Encryption at rest
+//!
+//! On machines shared with other users, where no OS keyring is available,
+//! the cookie can be stored encrypted with
+//! [`Session::save_encrypted`]. The file then holds only a ChaCha20-Poly1305
+//! sealed copy of the session:
+//!
+//! ```json
+//! { "encrypted": { "version": 1, "key": "passphrase", "salt": "…", "nonce": "…", "data": "…" } }
+//! ```
+//!
+//! The key is derived with Argon2id from either a passphrase or the
+//! machine's ID (`/etc/machine-id`, the `MachineGuid` registry value on
+//! Windows, `IOPlatformUUID` on macOS). A machine-bound file is useless once
+//! copied to another computer but can be opened by anyone who can read it on
+//! this one, so it is written readable by its owner only. When loading a
+//! passphrase-protected file, the passphrase is taken from
+//! [`PASSPHRASE_ENV`] or asked for through the callback given to
+//! [`Session::load_with`].
 
 use crate::error::{NeteaseError, Result};
+use base64::{Engine, engine::general_purpose::STANDARD as B64};
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Nonce};
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+/// Environment variable holding the passphrase of an encrypted session, for
+/// unattended use (e.g. the daemon).
+pub const PASSPHRASE_ENV: &str = "NCMDUMP_SESSION_PASSPHRASE";
+
+/// Format version of the encrypted envelope written by this build.
+const SEALED_VERSION: u32 = 1;
 
 /// Persistent login session backed by a JSON file on disk.
 #[derive(Debug, Serialize, Deserialize, Default)]
@@ -23,28 +53,96 @@ pub struct Session {
     pub music_u: Option<String>,
 }
 
+/// What an encrypted session's key is derived from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeySource {
+    /// A passphrase the user enters.
+    Passphrase,
+    /// This machine's ID.
+    Machine,
+}
+
+/// How [`Session::save_encrypted`] protects the file.
+#[derive(Debug, Clone)]
+pub enum SessionKey {
+    Passphrase(String),
+    Machine,
+}
+
+/// The session file: either a plain session or a sealed one.
+#[derive(Deserialize)]
+struct File {
+    encrypted: Option<Sealed>,
+    #[serde(flatten)]
+    session: Session,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Sealed {
+    version: u32,
+    key: KeySource,
+    /// Argon2id salt, base64.
+    salt: String,
+    /// ChaCha20-Poly1305 nonce, base64.
+    nonce: String,
+    /// The session JSON, encrypted and base64-encoded.
+    data: String,
+}
+
 impl Session {
     /// Load session from `~/.config/ncmdump/session.json`.
     ///
-    /// Returns a default (empty) session if the file does not exist.
+    /// Returns a default (empty) session if the file does not exist. A
+    /// passphrase-protected file can only be opened with [`PASSPHRASE_ENV`]
+    /// set; use [`load_with`](Self::load_with) to ask for it instead.
     pub fn load() -> Result<Self> {
+        Self::load_with(|| None)
+    }
+
+    /// Like [`load`](Self::load), calling `passphrase` when the file is
+    /// protected by a passphrase and [`PASSPHRASE_ENV`] is not set.
+    ///
+    /// # Errors
+    ///
+    /// - [`NeteaseError::SessionLocked`] — no passphrase was given
+    /// - [`NeteaseError::SessionDecrypt`] — wrong passphrase, or a
+    ///   machine-bound file from another machine
+    pub fn load_with(passphrase: impl FnOnce() -> Option<String>) -> Result<Self> {
         let path = Self::path()?;
         if !path.exists() {
             return Ok(Self::default());
         }
         let data = fs::read_to_string(&path)?;
-        Ok(serde_json::from_str(&data)?)
+        Self::open(&data, || {
+            std::env::var(PASSPHRASE_ENV)
+                .ok()
+                .filter(|p| !p.is_empty())
+                .or_else(passphrase)
+        })
     }
 
-    /// Save session to disk, creating parent directories if needed.
-    pub fn save(&self) -> Result<()> {
+    /// How the session file on disk is encrypted, if it is.
+    pub fn encryption() -> Result<Option<KeySource>> {
         let path = Self::path()?;
-        if let Some(parent) = path.parent() {
-            fs::create_dir_all(parent)?;
+        if !path.exists() {
+            return Ok(None);
         }
-        let data = serde_json::to_string_pretty(self)?;
-        fs::write(&path, data)?;
-        Ok(())
+        let file: File = serde_json::from_str(&fs::read_to_string(&path)?)?;
+        Ok(file.encrypted.map(|sealed| sealed.key))
+    }
+
+    /// Save session to disk unencrypted, creating parent directories if
+    /// needed.
+    pub fn save(&self) -> Result<()> {
+        write_private(&Self::path()?, &serde_json::to_string_pretty(self)?)
+    }
+
+    /// Save session to disk encrypted with `key`.
+    pub fn save_encrypted(&self, key: &SessionKey) -> Result<()> {
+        let sealed = self.seal(key)?;
+        let data = serde_json::to_string_pretty(&serde_json::json!({ "encrypted": sealed }))?;
+        write_private(&Self::path()?, &data)
     }
 
     /// Delete the session file from disk.
@@ -70,9 +168,128 @@ impl Session {
         self.music_u.as_ref().is_some_and(|u| !u.is_empty())
     }
 
-    fn path() -> Result<PathBuf> {
+    /// Location of the session file (`<config dir>/ncmdump/session.json`).
+    pub fn path() -> Result<PathBuf> {
         let config = dirs::config_dir()
             .ok_or_else(|| NeteaseError::Other("cannot determine config directory".into()))?;
         Ok(config.join("ncmdump").join("session.json"))
     }
+
+    /// Parse the contents of a session file, decrypting it if needed.
+    fn open(data: &str, passphrase: impl FnOnce() -> Option<String>) -> Result<Self> {
+        let file: File = serde_json::from_str(data)?;
+        let Some(sealed) = file.encrypted else {
+            return Ok(file.session);
+        };
+        if sealed.version > SEALED_VERSION {
+            return Err(NeteaseError::Other(format!(
+                "session encryption format {} is newer than this build supports",
+                sealed.version
+            )));
+        }
+        let secret = match sealed.key {
+            KeySource::Passphrase => passphrase().ok_or(NeteaseError::SessionLocked)?,
+            KeySource::Machine => machine_secret()?,
+        };
+        let decode = |field: &str| B64.decode(field).map_err(|_| NeteaseError::SessionDecrypt);
+        let (salt, nonce) = (decode(&sealed.salt)?, decode(&sealed.nonce)?);
+        if nonce.len() != 12 {
+            return Err(NeteaseError::SessionDecrypt);
+        }
+        let plain = cipher(&secret, &salt)?
+            .decrypt(Nonce::from_slice(&nonce), decode(&sealed.data)?.as_slice())
+            .map_err(|_| NeteaseError::SessionDecrypt)?;
+        Ok(serde_json::from_slice(&plain)?)
+    }
+
+    fn seal(&self, key: &SessionKey) -> Result<Sealed> {
+        let (source, secret) = match key {
+            SessionKey::Passphrase(passphrase) => (KeySource::Passphrase, passphrase.clone()),
+            SessionKey::Machine => (KeySource::Machine, machine_secret()?),
+        };
+        let salt: [u8; 16] = rand::random();
+        let nonce: [u8; 12] = rand::random();
+        let data = cipher(&secret, &salt)?
+            .encrypt(
+                Nonce::from_slice(&nonce),
+                serde_json::to_vec(self)?.as_slice(),
+            )
+            .map_err(|_| NeteaseError::Other("session encryption failed".into()))?;
+        Ok(Sealed {
+            version: SEALED_VERSION,
+            key: source,
+            salt: B64.encode(salt),
+            nonce: B64.encode(nonce),
+            data: B64.encode(data),
+        })
+    }
+}
+
+/// ChaCha20-Poly1305 keyed with Argon2id over `secret` and `salt`.
+fn cipher(secret: &str, salt: &[u8]) -> Result<ChaCha20Poly1305> {
+    let mut key = [0u8; 32];
+    argon2::Argon2::default()
+        .hash_password_into(secret.as_bytes(), salt, &mut key)
+        .map_err(|e| NeteaseError::Other(format!("key derivation failed: {e}")))?;
+    Ok(ChaCha20Poly1305::new(&key.into()))
+}
+
+/// The ID of this machine, the secret of a machine-bound session.
+fn machine_secret() -> Result<String> {
+    machine_uid::get()
+        .map(|id| id.trim().to_owned())
+        .ok()
+        .filter(|id| !id.is_empty())
+        .ok_or_else(|| NeteaseError::Other("cannot determine this machine's ID".into()))
+}
+
+/// Write `data` to `path`, readable by the owner only on Unix.
+fn write_private(path: &Path, data: &str) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    std::io::Write::write_all(&mut options.open(path)?, data.as_bytes())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session() -> Session {
+        Session {
+            music_u: Some("00AABBCC".into()),
+        }
+    }
+
+    fn sealed_json(key: &SessionKey) -> String {
+        serde_json::json!({ "encrypted": session().seal(key).unwrap() }).to_string()
+    }
+
+    #[test]
+    fn test_plain_session() {
+        let loaded = Session::open(r#"{"MUSIC_U":"00AABBCC"}"#, || None).unwrap();
+        assert_eq!(loaded.music_u.as_deref(), Some("00AABBCC"));
+    }
+
+    #[test]
+    fn test_passphrase_session() {
+        let data = sealed_json(&SessionKey::Passphrase("correct horse".into()));
+        assert!(!data.contains("00AABBCC"));
+
+        let loaded = Session::open(&data, || Some("correct horse".into())).unwrap();
+        assert_eq!(loaded.music_u.as_deref(), Some("00AABBCC"));
+        assert!(matches!(
+            Session::open(&data, || Some("wrong".into())),
+            Err(NeteaseError::SessionDecrypt)
+        ));
+        assert!(matches!(
+            Session::open(&data, || None),
+            Err(NeteaseError::SessionLocked)
+        ));
+    }
 }
@@ -27,8 +27,8 @@ use crate::crypto::weapi_encrypt;
 use crate::error::{NeteaseError, Result};
 use reqwest::blocking::Client;
 use serde_json::Value;
-use std::fs::File;
-use std::io::Write;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Write};
 use std::path::Path;
 
 const BASE_URL: &str = "https://music.163.com";
@@ -45,26 +45,78 @@ pub struct NeteaseClient {
     session: Session,
 }
 
+/// Builder for [`NeteaseClient`].
+///
+/// Without a [`proxy`](Self::proxy), reqwest's defaults apply: the
+/// `HTTPS_PROXY` / `HTTP_PROXY` / `ALL_PROXY` environment variables are honored.
+#[derive(Default)]
+pub struct NeteaseClientBuilder {
+    session: Option<Session>,
+    proxy: Option<String>,
+    passphrase: Option<Box<dyn FnOnce() -> Option<String>>>,
+}
+
+impl NeteaseClientBuilder {
+    /// Use an explicit session instead of loading it from disk.
+    #[must_use]
+    pub fn session(mut self, session: Session) -> Self {
+        self.session = Some(session);
+        self
+    }
+
+    /// Route all requests through a proxy (`http://`, `https://` or `socks5://` URL).
+    #[must_use]
+    pub fn proxy(mut self, url: impl Into<String>) -> Self {
+        self.proxy = Some(url.into());
+        self
+    }
+
+    /// Ask for the passphrase with `prompt` when the session on disk is
+    /// encrypted with one (see [`Session::load_with`]).
+    #[must_use]
+    pub fn passphrase(mut self, prompt: impl FnOnce() -> Option<String> + 'static) -> Self {
+        self.passphrase = Some(Box::new(prompt));
+        self
+    }
+
+    /// Build the client, loading the session from disk if none was given.
+    pub fn build(self) -> Result<NeteaseClient> {
+        let mut http = Client::builder()
+            .user_agent(USER_AGENT)
+            .timeout(std::time::Duration::from_secs(30));
+        if let Some(url) = &self.proxy {
+            http = http.proxy(reqwest::Proxy::all(url)?);
+        }
+        let session = match self.session {
+            Some(session) => session,
+            None => match self.passphrase {
+                Some(prompt) => Session::load_with(prompt)?,
+                None => Session::load()?,
+            },
+        };
+        Ok(NeteaseClient {
+            http: http.build()?,
+            session,
+        })
+    }
+}
+
 impl NeteaseClient {
     /// Create a new client, loading the session from
     /// `~/.config/ncmdump/session.json`.
     pub fn new() -> Result<Self> {
-        let http = Client::builder()
-            .user_agent(USER_AGENT)
-            .timeout(std::time::Duration::from_secs(30))
-            .build()?;
-        let session = Session::load()?;
-        Ok(Self { http, session })
+        Self::builder().build()
     }
 
     /// Create a client with an explicit [`Session`] (useful for testing
     /// or when the cookie is provided programmatically).
     pub fn with_session(session: Session) -> Result<Self> {
-        let http = Client::builder()
-            .user_agent(USER_AGENT)
-            .timeout(std::time::Duration::from_secs(30))
-            .build()?;
-        Ok(Self { http, session })
+        Self::builder().session(session).build()
+    }
+
+    /// Start building a client with non-default settings (e.g. a proxy).
+    pub fn builder() -> NeteaseClientBuilder {
+        NeteaseClientBuilder::default()
     }
 
     /// Return a reference to the current session.
@@ -72,6 +124,12 @@ impl NeteaseClient {
         &self.session
     }
 
+    /// Underlying HTTP client, for endpoints outside the WEAPI envelope
+    /// (e.g. object-store uploads).
+    pub(crate) fn http(&self) -> &Client {
+        &self.http
+    }
+
     /// Send a WEAPI-encrypted POST request to the given endpoint.
     ///
     /// `endpoint` is the path after `/weapi`, e.g. `/song/detail`.
@@ -99,8 +157,16 @@ impl NeteaseClient {
             payload.enc_sec_key,
         );
 
+        let started = std::time::Instant::now();
         let resp = req.body(body).send()?;
+        let status = resp.status().as_u16();
         let json: Value = resp.json()?;
+        tracing::debug!(
+            endpoint,
+            status,
+            elapsed_ms = started.elapsed().as_millis(),
+            "weapi request"
+        );
 
         if let Some(code) = json.get("code").and_then(Value::as_i64) {
             if code != 200 {
@@ -110,6 +176,7 @@ impl NeteaseClient {
                     .and_then(Value::as_str)
                     .unwrap_or("unknown error")
                     .to_owned();
+                tracing::debug!(endpoint, code, message = msg, "API error");
                 return Err(NeteaseError::Api { code, message: msg });
             }
         }
@@ -117,6 +184,14 @@ impl NeteaseClient {
         Ok(json)
     }
 
+    /// Follow the redirects of a share link (e.g. `https://163cn.tv/xxxxxx`)
+    /// and return the URL it finally points at.
+    pub fn resolve_link(&self, url: &str) -> Result<String> {
+        let resp = self.http.get(url).send()?;
+        tracing::debug!(url, target = %resp.url(), "followed share link");
+        Ok(resp.url().to_string())
+    }
+
     /// Download a file from `url` and write it to `dest`.
     ///
     /// Used internally by [`download_track`](Self::download_track) but can
@@ -124,15 +199,168 @@ impl NeteaseClient {
     ///
     /// Returns the number of bytes written.
     pub fn download(&self, url: &str, dest: &Path) -> Result<u64> {
+        let bytes = self.fetch_bytes(url)?;
+        let mut file = File::create(dest)?;
+        file.write_all(&bytes)?;
+        Ok(bytes.len() as u64)
+    }
+
+    /// Like [`download`](Self::download), but streams the body and calls
+    /// `progress(written, total)` after every chunk, where `total` is the
+    /// `Content-Length` if the server sent one.
+    ///
+    /// Returns the number of bytes written.
+    pub fn download_with_progress(
+        &self,
+        url: &str,
+        dest: &Path,
+        mut progress: impl FnMut(u64, Option<u64>),
+    ) -> Result<u64> {
+        let mut resp = self
+            .http
+            .get(url)
+            .header("Referer", "https://music.163.com/")
+            .send()?
+            .error_for_status()?;
+        let total = resp.content_length();
+        tracing::debug!(dest = %dest.display(), total, "downloading");
+        let mut file = File::create(dest)?;
+        let mut buf = vec![0u8; 0x10000];
+        let mut written = 0u64;
+        loop {
+            let n = resp.read(&mut buf)?;
+            if n == 0 {
+                break;
+            }
+            file.write_all(&buf[..n])?;
+            written += n as u64;
+            progress(written, total);
+        }
+        file.flush()?;
+        Ok(written)
+    }
+
+    /// Start fetching `url` (e.g. a track URL) and return the response to
+    /// read the body from as it arrives. `range` is passed on as the `Range`
+    /// header, e.g. `bytes=1000-`.
+    pub fn open_stream(&self, url: &str, range: Option<&str>) -> Result<Stream> {
+        let mut req = self
+            .http
+            .get(url)
+            .header("Referer", "https://music.163.com/");
+        if let Some(range) = range {
+            req = req.header("Range", range);
+        }
+        let resp = req.send()?.error_for_status()?;
+        tracing::debug!(status = resp.status().as_u16(), range, "opened stream");
+        let header = |name| {
+            resp.headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_owned)
+        };
+        Ok(Stream {
+            status: resp.status().as_u16(),
+            content_length: resp.content_length(),
+            content_range: header(reqwest::header::CONTENT_RANGE),
+            content_type: header(reqwest::header::CONTENT_TYPE),
+            body: resp,
+        })
+    }
+
+    /// Fetch the body of `url` into memory (e.g. an album cover image).
+    pub fn fetch_bytes(&self, url: &str) -> Result<Vec<u8>> {
         let resp = self
             .http
             .get(url)
             .header("Referer", "https://music.163.com/")
             .send()?;
+        Ok(resp.bytes()?.to_vec())
+    }
 
-        let mut file = File::create(dest)?;
-        let bytes = resp.bytes()?;
-        file.write_all(&bytes)?;
-        Ok(bytes.len() as u64)
+    /// Download a file from `url` to `dest`, resuming an interrupted transfer.
+    ///
+    /// Data is streamed into `<dest>.part`; if that file already exists, a
+    /// `Range` request continues from its current length. Servers that ignore
+    /// the range (no `206 Partial Content`) restart the transfer from scratch.
+    /// The partial file is renamed to `dest` once the body has been fully read,
+    /// so an interrupted run can simply be repeated.
+    ///
+    /// Returns the final file size in bytes.
+    pub fn download_resumable(&self, url: &str, dest: &Path) -> Result<u64> {
+        self.download_resumable_with_progress(url, dest, |_, _| {})
+    }
+
+    /// Like [`download_resumable`](Self::download_resumable), but calls
+    /// `progress(written, total)` after every chunk. Both count the bytes
+    /// already in the partial file; `total` is known if the server sent a
+    /// `Content-Length`.
+    pub fn download_resumable_with_progress(
+        &self,
+        url: &str,
+        dest: &Path,
+        mut progress: impl FnMut(u64, Option<u64>),
+    ) -> Result<u64> {
+        let mut part_name = dest.as_os_str().to_owned();
+        part_name.push(".part");
+        let part = Path::new(&part_name);
+
+        let existing = std::fs::metadata(part).map_or(0, |m| m.len());
+        let mut req = self
+            .http
+            .get(url)
+            .header("Referer", "https://music.163.com/");
+        if existing > 0 {
+            req = req.header("Range", format!("bytes={existing}-"));
+        }
+        let resp = req.send()?;
+        // A previous run already fetched the whole body but did not rename it.
+        if existing > 0 && resp.status() == reqwest::StatusCode::RANGE_NOT_SATISFIABLE {
+            std::fs::rename(part, dest)?;
+            return Ok(existing);
+        }
+        let mut resp = resp.error_for_status()?;
+
+        let resumed = resp.status() == reqwest::StatusCode::PARTIAL_CONTENT;
+        tracing::debug!(dest = %dest.display(), resumed, offset = existing, "downloading");
+        let (mut file, mut written) = if resumed {
+            (OpenOptions::new().append(true).open(part)?, existing)
+        } else {
+            (File::create(part)?, 0)
+        };
+        let total = resp.content_length().map(|len| written + len);
+        let mut buf = vec![0u8; 0x10000];
+        loop {
+            let n = resp.read(&mut buf)?;
+            if n == 0 {
+                break;
+            }
+            file.write_all(&buf[..n])?;
+            written += n as u64;
+            progress(written, total);
+        }
+        file.flush()?;
+        drop(file);
+
+        std::fs::rename(part, dest)?;
+        Ok(std::fs::metadata(dest)?.len())
+    }
+}
+
+/// A response being read, from [`NeteaseClient::open_stream`]. Reading it
+/// yields the body.
+pub struct Stream {
+    /// HTTP status: 200, or 206 if a range was served.
+    pub status: u16,
+    pub content_length: Option<u64>,
+    /// `Content-Range` header of a partial response.
+    pub content_range: Option<String>,
+    pub content_type: Option<String>,
+    body: reqwest::blocking::Response,
+}
+
+impl Read for Stream {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.body.read(buf)
     }
 }
@@ -0,0 +1,246 @@
+//! Cloud drive (云盘) APIs.
+//!
+//! # Endpoints
+//!
+//! ## `cloud_list` — `POST /weapi/v1/cloud/get`
+//!
+//! Request: `{ "limit": 30, "offset": 0 }`
+//!
+//! Response:
+//! ```json
+//! {
+//!   "code": 200,
+//!   "count": 42,
+//!   "size": "1234567890",
+//!   "maxSize": "64424509440",
+//!   "data": [{
+//!     "songId": 123,
+//!     "songName": "歌名",
+//!     "artist": "歌手",
+//!     "album": "专辑",
+//!     "fileName": "歌名.flac",
+//!     "fileSize": 31457280,
+//!     "bitrate": 999,
+//!     "addTime": 1700000000000
+//!   }]
+//! }
+//! ```
+//!
+//! ## `cloud_delete` — `POST /weapi/cloud/del`
+//!
+//! Request: `{ "songIds": [123, 456] }`
+//!
+//! ## `cloud_upload` — multi-step
+//!
+//! 1. `POST /weapi/cloud/upload/check` — `{ md5, length, ext, bitrate, songId: "0", version: 1 }`
+//!    returns `songId` and `needUpload` (false when the server already has the file)
+//! 2. `POST /weapi/nos/token/alloc` — returns `result.{bucket, objectKey, token, resourceId}`
+//! 3. `GET https://wanproxy.127.net/lbs?version=1.0&bucketname={bucket}` — upload host list;
+//!    the file body is then posted to `{host}/{bucket}/{objectKey}?offset=0&complete=true&version=1.0`
+//!    with an `x-nos-token` header (skipped when `needUpload` is false)
+//! 4. `POST /weapi/upload/cloud/info/v2` — registers song/album/artist names
+//! 5. `POST /weapi/cloud/pub/v2` — publishes the song into the cloud drive
+//!
+//! All cloud endpoints require login.
+
+use crate::client::NeteaseClient;
+use crate::error::{NeteaseError, Result};
+use crate::types::{CloudList, CloudTrack};
+use md5::{Digest, Md5};
+use serde_json::{Value, json};
+use std::path::Path;
+
+const NOS_LBS_URL: &str = "https://wanproxy.127.net/lbs";
+const UNKNOWN_ALBUM: &str = "未知专辑";
+const UNKNOWN_ARTIST: &str = "未知艺术家";
+/// Bitrate reported to the upload endpoints; the server re-probes the file anyway.
+const UPLOAD_BITRATE: u64 = 999_000;
+
+impl NeteaseClient {
+    /// List tracks in the user's cloud drive.
+    ///
+    /// # Errors
+    ///
+    /// - [`NeteaseError::NotLoggedIn`] — no `MUSIC_U` cookie configured
+    pub fn cloud_list(&self, limit: u64, offset: u64) -> Result<CloudList> {
+        if !self.session().is_logged_in() {
+            return Err(NeteaseError::NotLoggedIn);
+        }
+        let data = json!({ "limit": limit, "offset": offset });
+        let resp = self.request("/v1/cloud/get", &data)?;
+        Ok(CloudList {
+            count: resp["count"].as_u64().unwrap_or(0),
+            size: as_u64_lenient(&resp["size"]),
+            max_size: as_u64_lenient(&resp["maxSize"]),
+            tracks: resp["data"]
+                .as_array()
+                .map(|arr| arr.iter().map(parse_cloud_track).collect())
+                .unwrap_or_default(),
+        })
+    }
+
+    /// Upload a local audio file to the cloud drive.
+    ///
+    /// The song name is derived from the file name; album and artist are left
+    /// for the server to match. Returns the cloud song ID.
+    ///
+    /// # Errors
+    ///
+    /// - [`NeteaseError::NotLoggedIn`] — no `MUSIC_U` cookie configured
+    /// - [`NeteaseError::Io`] — the file cannot be read
+    pub fn cloud_upload(&self, path: &Path) -> Result<u64> {
+        if !self.session().is_logged_in() {
+            return Err(NeteaseError::NotLoggedIn);
+        }
+        let bytes = std::fs::read(path)?;
+        let md5 = format!("{:x}", Md5::digest(&bytes));
+        let ext = path
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        let song_name = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        // The NOS token endpoint rejects whitespace and extra dots in file names.
+        let filename: String = song_name
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .map(|c| if c == '.' { '_' } else { c })
+            .collect();
+
+        let check = self.request(
+            "/cloud/upload/check",
+            &json!({
+                "bitrate": UPLOAD_BITRATE.to_string(),
+                "ext": "",
+                "length": bytes.len(),
+                "md5": md5,
+                "songId": "0",
+                "version": 1,
+            }),
+        )?;
+        let song_id = check["songId"]
+            .as_str()
+            .map(str::to_owned)
+            .or_else(|| check["songId"].as_u64().map(|n| n.to_string()))
+            .ok_or_else(|| NeteaseError::Other("upload check returned no songId".into()))?;
+
+        let token = self.request(
+            "/nos/token/alloc",
+            &json!({
+                "bucket": "",
+                "ext": ext,
+                "filename": filename,
+                "local": false,
+                "nos_product": 3,
+                "type": "audio",
+                "md5": md5,
+            }),
+        )?;
+        let token = &token["result"];
+
+        if check["needUpload"].as_bool().unwrap_or(true) {
+            self.nos_upload(token, &md5, bytes)?;
+        }
+
+        let info = self.request(
+            "/upload/cloud/info/v2",
+            &json!({
+                "md5": md5,
+                "songid": song_id,
+                "filename": filename,
+                "song": song_name,
+                "album": UNKNOWN_ALBUM,
+                "artist": UNKNOWN_ARTIST,
+                "bitrate": UPLOAD_BITRATE.to_string(),
+                "resourceId": token["resourceId"],
+            }),
+        )?;
+        let cloud_id = info["songId"]
+            .as_str()
+            .map(str::to_owned)
+            .or_else(|| info["songId"].as_u64().map(|n| n.to_string()))
+            .ok_or_else(|| NeteaseError::Other("upload info returned no songId".into()))?;
+
+        self.request("/cloud/pub/v2", &json!({ "songid": cloud_id }))?;
+        cloud_id
+            .parse()
+            .map_err(|_| NeteaseError::Other(format!("invalid cloud song id: {cloud_id}")))
+    }
+
+    /// Remove tracks from the cloud drive by cloud song ID.
+    ///
+    /// # Errors
+    ///
+    /// - [`NeteaseError::NotLoggedIn`] — no `MUSIC_U` cookie configured
+    pub fn cloud_delete(&self, ids: &[u64]) -> Result<()> {
+        if !self.session().is_logged_in() {
+            return Err(NeteaseError::NotLoggedIn);
+        }
+        self.request("/cloud/del", &json!({ "songIds": ids }))?;
+        Ok(())
+    }
+
+    /// POST the raw file body to the NOS object store using an allocated token.
+    fn nos_upload(&self, token: &Value, md5: &str, bytes: Vec<u8>) -> Result<()> {
+        let bucket = token["bucket"].as_str().unwrap_or_default();
+        let object_key = token["objectKey"].as_str().unwrap_or_default();
+        let nos_token = token["token"].as_str().unwrap_or_default();
+
+        let lbs: Value = self
+            .http()
+            .get(NOS_LBS_URL)
+            .query(&[("version", "1.0"), ("bucketname", bucket)])
+            .send()?
+            .json()?;
+        let host = lbs["upload"][0]
+            .as_str()
+            .ok_or_else(|| NeteaseError::Other("no NOS upload host available".into()))?;
+
+        let url = format!(
+            "{host}/{bucket}/{}?offset=0&complete=true&version=1.0",
+            urlencoding::encode(object_key)
+        );
+        let resp = self
+            .http()
+            .post(&url)
+            .header("x-nos-token", nos_token)
+            .header("Content-MD5", md5)
+            .header("Content-Type", "audio/mpeg")
+            .body(bytes)
+            .send()?;
+        if !resp.status().is_success() {
+            return Err(NeteaseError::Other(format!(
+                "NOS upload failed: HTTP {}",
+                resp.status()
+            )));
+        }
+        Ok(())
+    }
+}
+
+fn parse_cloud_track(v: &Value) -> CloudTrack {
+    let song = &v["simpleSong"];
+    CloudTrack {
+        id: v["songId"].as_u64().unwrap_or(0),
+        name: v["songName"]
+            .as_str()
+            .or_else(|| song["name"].as_str())
+            .unwrap_or("")
+            .to_owned(),
+        artist: v["artist"].as_str().unwrap_or("").to_owned(),
+        album: v["album"].as_str().unwrap_or("").to_owned(),
+        file_name: v["fileName"].as_str().unwrap_or("").to_owned(),
+        file_size: v["fileSize"].as_u64().unwrap_or(0),
+        bitrate: v["bitrate"].as_u64().unwrap_or(0),
+        add_time: v["addTime"].as_u64().unwrap_or(0),
+    }
+}
+
+/// The cloud endpoints return byte sizes as strings; accept either form.
+fn as_u64_lenient(v: &Value) -> u64 {
+    v.as_u64()
+        .or_else(|| v.as_str().and_then(|s| s.parse().ok()))
+        .unwrap_or(0)
+}
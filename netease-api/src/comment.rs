@@ -0,0 +1,86 @@
+//! Track comment APIs.
+//!
+//! # Endpoints
+//!
+//! ## `track_comments` — `POST /weapi/v1/resource/comments/R_SO_4_{id}`
+//!
+//! ## `track_hot_comments` — `POST /weapi/v1/resource/hotcomments/R_SO_4_{id}`
+//!
+//! Request: `{ "rid": "R_SO_4_123", "limit": 20, "offset": 0, "beforeTime": 0 }`
+//!
+//! Response:
+//! ```json
+//! {
+//!   "code": 200,
+//!   "total": 12345,
+//!   "more": true,
+//!   "hotComments": [ ... ],   // only on the first page of `comments`
+//!   "comments": [{
+//!     "commentId": 1,
+//!     "user": { "userId": 2, "nickname": "用户名" },
+//!     "content": "评论内容",
+//!     "likedCount": 999,
+//!     "time": 1700000000000
+//!   }]
+//! }
+//! ```
+//!
+//! The hot-comments endpoint returns its list under `hotComments`.
+//! Neither endpoint requires login.
+
+use crate::client::NeteaseClient;
+use crate::error::Result;
+use crate::types::{Comment, CommentPage, UserBrief};
+use serde_json::{Value, json};
+
+impl NeteaseClient {
+    /// Get the latest comments for a track.
+    ///
+    /// On the first page (`offset == 0`) the response also carries the
+    /// track's hot comments in [`CommentPage::hot`].
+    pub fn track_comments(&self, id: u64, limit: u64, offset: u64) -> Result<CommentPage> {
+        let rid = format!("R_SO_4_{id}");
+        let data = json!({ "rid": rid, "limit": limit, "offset": offset, "beforeTime": 0 });
+        let resp = self.request(&format!("/v1/resource/comments/{rid}"), &data)?;
+        Ok(CommentPage {
+            total: resp["total"].as_u64().unwrap_or(0),
+            more: resp["more"].as_bool().unwrap_or(false),
+            hot: parse_comments(resp["hotComments"].as_array()),
+            comments: parse_comments(resp["comments"].as_array()),
+        })
+    }
+
+    /// Get hot (most-liked) comments for a track, paginated.
+    ///
+    /// The comments are returned in [`CommentPage::hot`]; `comments` is empty.
+    pub fn track_hot_comments(&self, id: u64, limit: u64, offset: u64) -> Result<CommentPage> {
+        let rid = format!("R_SO_4_{id}");
+        let data = json!({ "rid": rid, "limit": limit, "offset": offset, "beforeTime": 0 });
+        let resp = self.request(&format!("/v1/resource/hotcomments/{rid}"), &data)?;
+        Ok(CommentPage {
+            total: resp["total"].as_u64().unwrap_or(0),
+            more: resp["hasMore"]
+                .as_bool()
+                .or_else(|| resp["more"].as_bool())
+                .unwrap_or(false),
+            hot: parse_comments(resp["hotComments"].as_array()),
+            comments: vec![],
+        })
+    }
+}
+
+fn parse_comments(arr: Option<&Vec<Value>>) -> Vec<Comment> {
+    let Some(arr) = arr else { return vec![] };
+    arr.iter()
+        .map(|v| Comment {
+            id: v["commentId"].as_u64().unwrap_or(0),
+            user: UserBrief {
+                id: v["user"]["userId"].as_u64().unwrap_or(0),
+                name: v["user"]["nickname"].as_str().unwrap_or("").to_owned(),
+            },
+            content: v["content"].as_str().unwrap_or("").to_owned(),
+            liked_count: v["likedCount"].as_u64().unwrap_or(0),
+            time: v["time"].as_u64().unwrap_or(0),
+        })
+        .collect()
+}
@@ -27,6 +27,15 @@ pub enum NeteaseError {
     #[error("not logged in")]
     NotLoggedIn,
 
+    /// The session file is encrypted with a passphrase and none was given.
+    #[error("session is encrypted; a passphrase is required")]
+    SessionLocked,
+
+    /// The encrypted session file could not be opened: wrong passphrase,
+    /// or a machine-bound file from another machine.
+    #[error("cannot decrypt session: wrong passphrase or different machine")]
+    SessionDecrypt,
+
     /// File I/O error (session read/write, download write).
     #[error("I/O error: {0}")]
     Io(#[from] std::io::Error),
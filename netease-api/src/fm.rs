@@ -0,0 +1,88 @@
+//! Personal FM (私人 FM) APIs.
+//!
+//! All endpoints require a login cookie.
+//!
+//! # Endpoints
+//!
+//! ## `personal_fm` — `POST /weapi/v1/radio/get`
+//!
+//! Request: `{}` (the batch is personalised via the login cookie).
+//!
+//! Response:
+//! ```json
+//! {
+//!   "code": 200,
+//!   "popAdjust": false,
+//!   "data": [{ "id": 1, "name": "歌名", "artists": [...], "album": {...}, "duration": 240000 }]
+//! }
+//! ```
+//!
+//! Each call returns a fresh batch of (usually three) tracks.
+//!
+//! ## `fm_trash` — `POST /weapi/radio/trash/add?alg=RT&songId={id}&time=25`
+//!
+//! Request: `{ "songId": 347230 }`
+//!
+//! Response: `{ "code": 200, "count": 1 }`
+//!
+//! A trashed song is no longer recommended by the FM.
+//!
+//! ## `like_track` — `POST /weapi/radio/like`
+//!
+//! Request: `{ "alg": "itembased", "trackId": 347230, "like": true, "time": "3" }`
+//!
+//! Response: `{ "code": 200, "playlistId": 1 }`
+//!
+//! Adds the song to (or removes it from) 我喜欢的音乐.
+
+use crate::client::NeteaseClient;
+use crate::error::{NeteaseError, Result};
+use crate::track::parse_track;
+use crate::types::Track;
+use serde_json::json;
+
+impl NeteaseClient {
+    /// Fetch the next batch of personal-FM tracks.
+    ///
+    /// # Errors
+    ///
+    /// - [`NeteaseError::NotLoggedIn`] — no `MUSIC_U` cookie configured
+    pub fn personal_fm(&self) -> Result<Vec<Track>> {
+        if !self.session().is_logged_in() {
+            return Err(NeteaseError::NotLoggedIn);
+        }
+        let resp = self.request("/v1/radio/get", &json!({}))?;
+        Ok(resp["data"]
+            .as_array()
+            .map(|arr| arr.iter().map(parse_track).collect())
+            .unwrap_or_default())
+    }
+
+    /// Move a song to the FM trash so the FM stops recommending it.
+    ///
+    /// # Errors
+    ///
+    /// - [`NeteaseError::NotLoggedIn`] — no `MUSIC_U` cookie configured
+    pub fn fm_trash(&self, id: u64) -> Result<()> {
+        if !self.session().is_logged_in() {
+            return Err(NeteaseError::NotLoggedIn);
+        }
+        let endpoint = format!("/radio/trash/add?alg=RT&songId={id}&time=25");
+        self.request(&endpoint, &json!({ "songId": id }))?;
+        Ok(())
+    }
+
+    /// Like (or with `like == false`, unlike) a song.
+    ///
+    /// # Errors
+    ///
+    /// - [`NeteaseError::NotLoggedIn`] — no `MUSIC_U` cookie configured
+    pub fn like_track(&self, id: u64, like: bool) -> Result<()> {
+        if !self.session().is_logged_in() {
+            return Err(NeteaseError::NotLoggedIn);
+        }
+        let data = json!({ "alg": "itembased", "trackId": id, "like": like, "time": "3" });
+        self.request("/radio/like", &data)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,77 @@
+//! Hot (trending) search APIs.
+//!
+//! # Endpoints
+//!
+//! ## `hot_searches` — `POST /weapi/search/hot`
+//!
+//! Request: `{ "type": 1111 }`
+//!
+//! Response:
+//! ```json
+//! {
+//!   "code": 200,
+//!   "result": { "hots": [{ "first": "晴天", "second": 1, "iconType": 1 }] }
+//! }
+//! ```
+//!
+//! ## `hot_search_detail` — `POST /weapi/hotsearchlist/get`
+//!
+//! Request: `{}`
+//!
+//! Response:
+//! ```json
+//! {
+//!   "code": 200,
+//!   "data": [{
+//!     "searchWord": "晴天",
+//!     "score": 1234567,
+//!     "content": "周杰伦经典曲目",
+//!     "iconType": 1,
+//!     "iconUrl": "https://p1.music.126.net/..."
+//!   }]
+//! }
+//! ```
+
+use crate::client::NeteaseClient;
+use crate::error::Result;
+use crate::types::HotSearch;
+use serde_json::json;
+
+impl NeteaseClient {
+    /// Get the current hot-search keywords, most popular first.
+    pub fn hot_searches(&self) -> Result<Vec<String>> {
+        let resp = self.request("/search/hot", &json!({ "type": 1111 }))?;
+        Ok(resp["result"]["hots"]
+            .as_array()
+            .map(|arr| {
+                arr.iter()
+                    .filter_map(|h| h["first"].as_str().map(String::from))
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    /// Get the detailed trending list with popularity scores and descriptions.
+    pub fn hot_search_detail(&self) -> Result<Vec<HotSearch>> {
+        let resp = self.request("/hotsearchlist/get", &json!({}))?;
+        Ok(resp["data"]
+            .as_array()
+            .map(|arr| {
+                arr.iter()
+                    .map(|h| HotSearch {
+                        keyword: h["searchWord"].as_str().unwrap_or("").to_owned(),
+                        score: h["score"].as_u64().unwrap_or(0),
+                        content: h["content"]
+                            .as_str()
+                            .filter(|s| !s.is_empty())
+                            .map(String::from),
+                        icon_url: h["iconUrl"]
+                            .as_str()
+                            .filter(|s| !s.is_empty())
+                            .map(String::from),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+}
@@ -25,27 +25,74 @@
 //! | Method                  | WEAPI endpoint                  | Description          |
 //! |-------------------------|---------------------------------|----------------------|
 //! | [`NeteaseClient::search`]         | `/cloudsearch/get/web`  | Search music         |
+//! | [`NeteaseClient::search_pages`]   | (uses `search`)         | Page through results |
+//! | [`NeteaseClient::hot_searches`]   | `/search/hot`           | Hot-search keywords  |
+//! | [`NeteaseClient::hot_search_detail`] | `/hotsearchlist/get` | Trending topics      |
 //! | [`NeteaseClient::track_detail`]   | `/song/detail`          | Track metadata       |
 //! | [`NeteaseClient::track_url`]      | `/song/enhance/player/url` | Playback URL      |
+//! | [`NeteaseClient::track_file`]     | `/song/enhance/player/url` | URL + size/MD5/type |
+//! | [`NeteaseClient::track_qualities`] | `/song/enhance/player/url/v1` | Available tiers |
 //! | [`NeteaseClient::track_lyric`]    | `/song/lyric`           | LRC lyrics           |
+//! | [`NeteaseClient::track_comments`] | `/v1/resource/comments/R_SO_4_{id}` | Latest comments |
+//! | [`NeteaseClient::track_hot_comments`] | `/v1/resource/hotcomments/R_SO_4_{id}` | Hot comments |
 //! | [`NeteaseClient::download_track`] | (uses `track_url`)      | Download audio file  |
+//! | [`NeteaseClient::album_detail`]   | `/v1/album/{id}`        | Album with tracks    |
+//! | [`NeteaseClient::artist_detail`]  | `/v1/artist/{id}`       | Artist + top songs   |
+//! | [`NeteaseClient::artist_albums`]  | `/artist/albums/{id}`   | Artist discography   |
+//! | [`NeteaseClient::followed_artists`] | `/artist/sublist`     | Followed artists     |
+//! | [`NeteaseClient::mv_detail`]      | `/v1/mv/detail`         | MV metadata          |
+//! | [`NeteaseClient::mv_url`]         | `/song/enhance/play/mv/url` | MV video URL     |
+//! | [`NeteaseClient::download_mv`]    | (uses `mv_url`)         | Download MV (resumable) |
 //! | [`NeteaseClient::playlist_detail`]| `/v6/playlist/detail`   | Playlist with tracks |
+//! | [`NeteaseClient::similar_tracks`] | `/v1/discovery/simiSong` | Similar songs       |
+//! | [`NeteaseClient::similar_artists`]| `/discovery/simiArtist` | Similar artists      |
+//! | [`NeteaseClient::toplists`]       | `/toplist`              | Official charts      |
+//! | [`NeteaseClient::radio_detail`]   | `/djradio/v2/get`       | DJ radio (podcast)   |
+//! | [`NeteaseClient::radio_programs`] | `/dj/program/byradio`   | Radio episodes       |
+//! | [`NeteaseClient::subscribed_radios`] | `/djradio/get/subed` | Subscribed radios    |
 //! | [`NeteaseClient::user_info`]      | `/nuser/account/get`    | Current user profile |
+//! | [`NeteaseClient::user_playlists`] | `/user/playlist`        | User's playlists     |
+//! | [`NeteaseClient::liked_track_ids`] | `/song/like/get`       | Liked song IDs       |
+//! | [`NeteaseClient::user_level`]     | `/user/level`           | Listening level      |
+//! | [`NeteaseClient::vip_info`]       | `/music-vip-membership/front/vip/info` | VIP status |
+//! | [`NeteaseClient::play_record`]    | `/v1/play/record`       | Listening history    |
+//! | [`NeteaseClient::personal_fm`]    | `/v1/radio/get`         | Personal FM batch    |
+//! | [`NeteaseClient::fm_trash`]       | `/radio/trash/add`      | Dislike an FM track  |
+//! | [`NeteaseClient::like_track`]     | `/radio/like`           | Like / unlike a song |
+//! | [`NeteaseClient::daily_signin`]   | `/point/dailyTask`      | Daily sign-in points |
+//! | [`NeteaseClient::daily_songs`]    | `/v3/discovery/recommend/songs` | Daily recommendations |
+//! | [`NeteaseClient::cloud_list`]     | `/v1/cloud/get`         | Cloud drive listing  |
+//! | [`NeteaseClient::cloud_upload`]   | `/cloud/upload/check` + NOS | Upload to cloud drive |
+//! | [`NeteaseClient::cloud_delete`]   | `/cloud/del`            | Remove from cloud drive |
 //!
 //! # Encryption
 //!
 //! All requests use the WEAPI encryption scheme (double AES-128-CBC + RSA),
 //! matching the Netease web client. See [`crypto`](crate::crypto) (internal).
 
+mod album;
+mod artist;
 pub mod auth;
 pub mod client;
+mod cloud;
+mod comment;
 mod crypto;
 pub mod error;
+mod fm;
+mod hot;
+pub mod lrc;
+mod mv;
 mod playlist;
+mod radio;
+mod recommend;
 mod search;
+mod signin;
+mod similar;
+mod toplist;
 mod track;
 pub mod types;
 mod user;
 
-pub use client::NeteaseClient;
+pub use client::{NeteaseClient, NeteaseClientBuilder, Stream};
 pub use error::{NeteaseError, Result};
+pub use search::SearchPages;
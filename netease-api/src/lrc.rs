@@ -0,0 +1,146 @@
+//! LRC lyric post-processing.
+//!
+//! Netease lyrics ([`Lyric`](crate::types::Lyric)) are LRC text: each line
+//! carries one or more `[mm:ss.xx]` time tags, optionally preceded by
+//! `[ar:...]`-style metadata lines and JSON credit lines such as
+//! `{"t":0,"c":[{"tx":"作词: "},{"tx":"方文山"}]}`.
+
+use serde_json::Value;
+
+/// Remove time tags and metadata lines, leaving the plain lyric text.
+///
+/// JSON credit lines are flattened to their text; blank lines are dropped.
+pub fn strip_timestamps(lrc: &str) -> String {
+    let mut out = Vec::new();
+    for line in lrc.lines() {
+        let line = line.trim();
+        if line.starts_with('{') {
+            if let Some(text) = credit_text(line) {
+                out.push(text);
+            }
+            continue;
+        }
+        let (tags, text) = split_tags(line);
+        let text = text.trim();
+        // Metadata-only lines like `[ar:周杰伦]` carry no lyric text.
+        if text.is_empty() || (tags.is_empty() && line.starts_with('[')) {
+            continue;
+        }
+        out.push(text.to_owned());
+    }
+    out.join("\n")
+}
+
+/// Shift every time tag by `offset_ms` (negative moves lyrics earlier).
+///
+/// Shifted times are clamped at zero and keep the original precision
+/// (`.xx` or `.xxx`). Lines without time tags are left unchanged.
+pub fn shift(lrc: &str, offset_ms: i64) -> String {
+    lrc.lines()
+        .map(|line| {
+            let (tags, text) = split_tags(line);
+            if tags.is_empty() {
+                return line.to_owned();
+            }
+            let mut shifted: String = tags
+                .iter()
+                .map(|t| {
+                    let ms = i64::try_from(t.ms).unwrap_or(i64::MAX);
+                    let ms = u64::try_from(ms.saturating_add(offset_ms)).unwrap_or(0);
+                    format_tag(ms, t.decimals)
+                })
+                .collect();
+            shifted.push_str(text);
+            shifted
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// A parsed `[mm:ss.xx]` tag.
+struct TimeTag {
+    ms: u64,
+    decimals: usize,
+}
+
+/// Split the leading time tags off a line. Non-time tags (metadata) stop the
+/// scan and stay in the returned text.
+fn split_tags(line: &str) -> (Vec<TimeTag>, &str) {
+    let mut tags = Vec::new();
+    let mut rest = line;
+    while let Some(inner) = rest.strip_prefix('[') {
+        let Some(end) = inner.find(']') else { break };
+        let Some(tag) = parse_time(&inner[..end]) else {
+            break;
+        };
+        tags.push(tag);
+        rest = &inner[end + 1..];
+    }
+    (tags, rest)
+}
+
+fn parse_time(s: &str) -> Option<TimeTag> {
+    let (min, sec) = s.split_once(':')?;
+    let (sec, frac) = sec.split_once('.').unwrap_or((sec, ""));
+    let min: u64 = min.parse().ok()?;
+    let sec: u64 = sec.parse().ok()?;
+    if !frac.chars().all(|c| c.is_ascii_digit()) || frac.len() > 3 {
+        return None;
+    }
+    let frac_ms = match frac.len() {
+        0 => 0,
+        1 => frac.parse::<u64>().ok()? * 100,
+        2 => frac.parse::<u64>().ok()? * 10,
+        _ => frac.parse::<u64>().ok()?,
+    };
+    Some(TimeTag {
+        ms: (min * 60 + sec) * 1000 + frac_ms,
+        decimals: frac.len(),
+    })
+}
+
+fn format_tag(ms: u64, decimals: usize) -> String {
+    let (min, sec, rem) = (ms / 60_000, (ms / 1000) % 60, ms % 1000);
+    match decimals {
+        0 => format!("[{min:02}:{sec:02}]"),
+        1 => format!("[{min:02}:{sec:02}.{}]", rem / 100),
+        2 => format!("[{min:02}:{sec:02}.{:02}]", rem / 10),
+        _ => format!("[{min:02}:{sec:02}.{rem:03}]"),
+    }
+}
+
+/// Text of a JSON credit line (`{"c":[{"tx":"..."}]}`).
+fn credit_text(line: &str) -> Option<String> {
+    let v: Value = serde_json::from_str(line).ok()?;
+    let text: String = v["c"]
+        .as_array()?
+        .iter()
+        .filter_map(|c| c["tx"].as_str())
+        .collect();
+    Some(text).filter(|t| !t.trim().is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LRC: &str = "[ar:周杰伦]\n{\"t\":0,\"c\":[{\"tx\":\"作词: \"},{\"tx\":\"方文山\"}]}\n[00:01.50]故事的小黄花\n[00:05.00][01:05.00]从出生那年就飘着\n[00:09.120]\n";
+
+    #[test]
+    fn test_strip_timestamps() {
+        assert_eq!(
+            strip_timestamps(LRC),
+            "作词: 方文山\n故事的小黄花\n从出生那年就飘着"
+        );
+    }
+
+    #[test]
+    fn test_shift() {
+        let shifted = shift(LRC, -2000);
+        assert!(shifted.contains("[00:00.00]故事的小黄花"));
+        assert!(shifted.contains("[00:03.00][01:03.00]从出生那年就飘着"));
+        assert!(shifted.contains("[00:07.120]"));
+        assert!(shifted.starts_with("[ar:周杰伦]"));
+        assert!(shift("[00:59.99]x", 20).starts_with("[01:00.01]"));
+    }
+}
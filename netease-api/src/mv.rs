@@ -0,0 +1,103 @@
+//! Music video (MV) APIs.
+//!
+//! # Endpoints
+//!
+//! ## `mv_detail` — `POST /weapi/v1/mv/detail`
+//!
+//! Request: `{ "id": 5436712 }`
+//!
+//! Response:
+//! ```json
+//! {
+//!   "code": 200,
+//!   "data": {
+//!     "id": 5436712,
+//!     "name": "MV 名",
+//!     "artists": [{ "id": 1, "name": "歌手" }],
+//!     "duration": 240000,
+//!     "brs": [{ "br": 240 }, { "br": 480 }, { "br": 720 }, { "br": 1080 }]
+//!   }
+//! }
+//! ```
+//!
+//! Older responses return `brs` as an object keyed by resolution (`{"240": "..."}`).
+//!
+//! ## `mv_url` — `POST /weapi/song/enhance/play/mv/url`
+//!
+//! Request: `{ "id": 5436712, "r": 1080 }`
+//!
+//! Response:
+//! ```json
+//! { "code": 200, "data": { "id": 5436712, "url": "https://vodkgeyttp8.vod.126.net/...", "r": 1080, "size": 123456789 } }
+//! ```
+//!
+//! The server falls back to the closest available resolution; `url` is
+//! `null` when the MV is unavailable.
+
+use crate::client::NeteaseClient;
+use crate::error::{NeteaseError, Result};
+use crate::types::{Artist, Mv, MvResolution};
+use serde_json::json;
+use std::path::Path;
+
+impl NeteaseClient {
+    /// Get MV metadata, including the list of available resolutions.
+    pub fn mv_detail(&self, id: u64) -> Result<Mv> {
+        let resp = self.request("/v1/mv/detail", &json!({ "id": id }))?;
+        let d = &resp["data"];
+        if d.is_null() {
+            return Err(NeteaseError::Other(format!("MV not found: {id}")));
+        }
+
+        let mut resolutions: Vec<u64> = if let Some(arr) = d["brs"].as_array() {
+            arr.iter().filter_map(|b| b["br"].as_u64()).collect()
+        } else if let Some(obj) = d["brs"].as_object() {
+            obj.keys().filter_map(|k| k.parse().ok()).collect()
+        } else {
+            vec![]
+        };
+        resolutions.sort_unstable();
+
+        Ok(Mv {
+            id: d["id"].as_u64().unwrap_or(id),
+            name: d["name"].as_str().unwrap_or("").to_owned(),
+            artists: d["artists"]
+                .as_array()
+                .map(|arr| {
+                    arr.iter()
+                        .map(|a| Artist {
+                            id: a["id"].as_u64().unwrap_or(0),
+                            name: a["name"].as_str().unwrap_or("").to_owned(),
+                        })
+                        .collect()
+                })
+                .unwrap_or_default(),
+            duration_ms: d["duration"].as_u64().unwrap_or(0),
+            resolutions,
+        })
+    }
+
+    /// Get a direct video URL for an MV at (up to) the requested resolution.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`NeteaseError::Other`] if the server returns no URL.
+    pub fn mv_url(&self, id: u64, resolution: MvResolution) -> Result<String> {
+        let data = json!({ "id": id, "r": resolution.height() });
+        let resp = self.request("/song/enhance/play/mv/url", &data)?;
+        resp["data"]["url"]
+            .as_str()
+            .filter(|u| !u.is_empty())
+            .map(String::from)
+            .ok_or_else(|| NeteaseError::Other("MV unavailable".into()))
+    }
+
+    /// Download an MV to a local file, resuming a previous partial download.
+    ///
+    /// Combines [`mv_url`](Self::mv_url) + [`download_resumable`](Self::download_resumable).
+    /// Returns the final file size in bytes.
+    pub fn download_mv(&self, id: u64, resolution: MvResolution, dest: &Path) -> Result<u64> {
+        let url = self.mv_url(id, resolution)?;
+        self.download_resumable(&url, dest)
+    }
+}
@@ -0,0 +1,156 @@
+//! DJ radio (podcast, 播客/电台) APIs.
+//!
+//! # Endpoints
+//!
+//! ## `radio_detail` — `POST /weapi/djradio/v2/get`
+//!
+//! Request: `{ "id": 336355127 }`
+//!
+//! Response:
+//! ```json
+//! {
+//!   "code": 200,
+//!   "data": {
+//!     "id": 336355127,
+//!     "name": "电台名",
+//!     "desc": "描述...",
+//!     "picUrl": "https://...",
+//!     "category": "音乐故事",
+//!     "programCount": 120,
+//!     "subCount": 5000,
+//!     "dj": { "userId": 1, "nickname": "主播" }
+//!   }
+//! }
+//! ```
+//!
+//! ## `radio_programs` — `POST /weapi/dj/program/byradio`
+//!
+//! Request: `{ "radioId": 336355127, "limit": 30, "offset": 0, "asc": false }`
+//!
+//! Response:
+//! ```json
+//! {
+//!   "code": 200,
+//!   "count": 120,
+//!   "more": true,
+//!   "programs": [{
+//!     "id": 2500000000,
+//!     "name": "节目名",
+//!     "serialNum": 120,
+//!     "duration": 1800000,
+//!     "createTime": 1700000000000,
+//!     "listenerCount": 10000,
+//!     "mainSong": { "id": 1990000000, "name": "节目名" }
+//!   }]
+//! }
+//! ```
+//!
+//! `mainSong.id` is a regular track ID, so program audio is fetched with
+//! [`NeteaseClient::track_url`] / [`NeteaseClient::download_track`].
+//!
+//! ## `subscribed_radios` — `POST /weapi/djradio/get/subed`
+//!
+//! Request: `{ "limit": 30, "offset": 0, "total": true }` (requires login)
+//!
+//! Response: `{ "code": 200, "count": 3, "hasMore": false, "djRadios": [...] }`,
+//! each entry shaped like `radio_detail`'s `data` (with `rcmdtext` in place
+//! of `desc`).
+
+use crate::client::NeteaseClient;
+use crate::error::{NeteaseError, Result};
+use crate::types::{Program, ProgramPage, Radio, UserBrief};
+use serde_json::{Value, json};
+
+impl NeteaseClient {
+    /// Get DJ radio (podcast) metadata.
+    pub fn radio_detail(&self, id: u64) -> Result<Radio> {
+        let resp = self.request("/djradio/v2/get", &json!({ "id": id }))?;
+        let d = &resp["data"];
+        if d.is_null() {
+            return Err(NeteaseError::Other(format!("radio not found: {id}")));
+        }
+        let mut radio = parse_radio(d);
+        if radio.id == 0 {
+            radio.id = id;
+        }
+        Ok(radio)
+    }
+
+    /// List the DJ radios the current user subscribes to.
+    ///
+    /// # Errors
+    ///
+    /// - [`NeteaseError::NotLoggedIn`] — no `MUSIC_U` cookie configured
+    pub fn subscribed_radios(&self, limit: u64, offset: u64) -> Result<Vec<Radio>> {
+        if !self.session().is_logged_in() {
+            return Err(NeteaseError::NotLoggedIn);
+        }
+        let data = json!({ "limit": limit, "offset": offset, "total": true });
+        let resp = self.request("/djradio/get/subed", &data)?;
+        Ok(resp["djRadios"]
+            .as_array()
+            .map(|arr| arr.iter().map(parse_radio).collect())
+            .unwrap_or_default())
+    }
+
+    /// List programs (episodes) of a DJ radio, newest first unless `asc` is set.
+    pub fn radio_programs(
+        &self,
+        radio_id: u64,
+        limit: u64,
+        offset: u64,
+        asc: bool,
+    ) -> Result<ProgramPage> {
+        let data = json!({
+            "radioId": radio_id,
+            "limit": limit,
+            "offset": offset,
+            "asc": asc,
+        });
+        let resp = self.request("/dj/program/byradio", &data)?;
+        Ok(ProgramPage {
+            count: resp["count"].as_u64().unwrap_or(0),
+            more: resp["more"].as_bool().unwrap_or(false),
+            programs: resp["programs"]
+                .as_array()
+                .map(|arr| arr.iter().map(parse_program).collect())
+                .unwrap_or_default(),
+        })
+    }
+}
+
+fn parse_radio(d: &Value) -> Radio {
+    let dj = &d["dj"];
+    Radio {
+        id: d["id"].as_u64().unwrap_or(0),
+        name: d["name"].as_str().unwrap_or("").to_owned(),
+        description: d["desc"]
+            .as_str()
+            .or_else(|| d["rcmdtext"].as_str())
+            .map(String::from),
+        cover_url: d["picUrl"].as_str().map(String::from),
+        category: d["category"].as_str().map(String::from),
+        program_count: d["programCount"].as_u64().unwrap_or(0),
+        subscriber_count: d["subCount"].as_u64().unwrap_or(0),
+        dj: if dj.is_null() {
+            None
+        } else {
+            Some(UserBrief {
+                id: dj["userId"].as_u64().unwrap_or(0),
+                name: dj["nickname"].as_str().unwrap_or("").to_owned(),
+            })
+        },
+    }
+}
+
+fn parse_program(v: &Value) -> Program {
+    Program {
+        id: v["id"].as_u64().unwrap_or(0),
+        name: v["name"].as_str().unwrap_or("").to_owned(),
+        serial_num: v["serialNum"].as_u64().unwrap_or(0),
+        track_id: v["mainSong"]["id"].as_u64().unwrap_or(0),
+        duration_ms: v["duration"].as_u64().unwrap_or(0),
+        create_time: v["createTime"].as_u64().unwrap_or(0),
+        listener_count: v["listenerCount"].as_u64().unwrap_or(0),
+    }
+}
@@ -0,0 +1,41 @@
+//! Daily recommendations (每日推荐) API.
+//!
+//! Endpoint: `POST /weapi/v3/discovery/recommend/songs`
+//!
+//! Request: `{}` (the list is personalised via the login cookie).
+//!
+//! Response:
+//! ```json
+//! {
+//!   "code": 200,
+//!   "data": {
+//!     "dailySongs": [{ "id": 1, "name": "歌名", "ar": [...], "al": {...}, "dt": 240000 }]
+//!   }
+//! }
+//! ```
+//!
+//! The list is refreshed once a day (at 06:00 China time).
+
+use crate::client::NeteaseClient;
+use crate::error::{NeteaseError, Result};
+use crate::track::parse_track;
+use crate::types::Track;
+use serde_json::json;
+
+impl NeteaseClient {
+    /// Fetch today's recommended songs.
+    ///
+    /// # Errors
+    ///
+    /// - [`NeteaseError::NotLoggedIn`] — no `MUSIC_U` cookie configured
+    pub fn daily_songs(&self) -> Result<Vec<Track>> {
+        if !self.session().is_logged_in() {
+            return Err(NeteaseError::NotLoggedIn);
+        }
+        let resp = self.request("/v3/discovery/recommend/songs", &json!({}))?;
+        Ok(resp["data"]["dailySongs"]
+            .as_array()
+            .map(|arr| arr.iter().map(parse_track).collect())
+            .unwrap_or_default())
+    }
+}
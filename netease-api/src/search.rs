@@ -87,6 +87,61 @@ impl NeteaseClient {
 
         Ok(sr)
     }
+
+    /// Iterate over consecutive pages of a search, `limit` results each,
+    /// starting at `offset`.
+    ///
+    /// The iterator ends after the page that reaches the server's `total`,
+    /// after an empty page, or after the first error.
+    pub fn search_pages(
+        &self,
+        keyword: &str,
+        search_type: SearchType,
+        limit: u64,
+        offset: u64,
+    ) -> SearchPages<'_> {
+        SearchPages {
+            client: self,
+            keyword: keyword.to_owned(),
+            search_type,
+            limit,
+            offset,
+            done: false,
+        }
+    }
+}
+
+/// Iterator over search result pages, returned by
+/// [`NeteaseClient::search_pages`].
+pub struct SearchPages<'a> {
+    client: &'a NeteaseClient,
+    keyword: String,
+    search_type: SearchType,
+    limit: u64,
+    offset: u64,
+    done: bool,
+}
+
+impl Iterator for SearchPages<'_> {
+    type Item = Result<SearchResult>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let page = self
+            .client
+            .search(&self.keyword, self.search_type, self.limit, self.offset);
+        match &page {
+            Ok(result) => {
+                let count = result.len() as u64;
+                self.offset += count;
+                self.done = count == 0 || self.offset >= result.total;
+            }
+            Err(_) => self.done = true,
+        }
+        Some(page)
+    }
 }
 
 fn parse_tracks(arr: Option<&Vec<Value>>) -> Vec<Track> {
@@ -146,7 +201,7 @@ fn parse_artists(arr: Option<&Vec<Value>>) -> Vec<Artist> {
         .collect()
 }
 
-fn parse_playlists(arr: Option<&Vec<Value>>) -> Vec<Playlist> {
+pub(crate) fn parse_playlists(arr: Option<&Vec<Value>>) -> Vec<Playlist> {
     let Some(arr) = arr else { return vec![] };
     arr.iter()
         .map(|v| {
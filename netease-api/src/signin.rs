@@ -0,0 +1,45 @@
+//! Daily sign-in (签到) API.
+//!
+//! Endpoint: `POST /weapi/point/dailyTask`
+//!
+//! Request: `{ "type": 0 }` — `0` signs in on mobile, `1` on web/PC.
+//!
+//! Response:
+//! ```json
+//! { "code": 200, "point": 3 }
+//! ```
+//!
+//! Each platform can be signed in once a day; repeating it returns code
+//! `-2` ("重复签到").
+
+use crate::client::NeteaseClient;
+use crate::error::{NeteaseError, Result};
+use crate::types::SigninPlatform;
+use serde_json::json;
+
+/// API code for a platform that was already signed in today.
+const ALREADY_SIGNED_IN: i64 = -2;
+
+impl NeteaseClient {
+    /// Sign in for today on `platform`.
+    ///
+    /// Returns the points earned, or `None` if the platform was already
+    /// signed in today.
+    ///
+    /// # Errors
+    ///
+    /// - [`NeteaseError::NotLoggedIn`] — no `MUSIC_U` cookie configured
+    pub fn daily_signin(&self, platform: SigninPlatform) -> Result<Option<u64>> {
+        if !self.session().is_logged_in() {
+            return Err(NeteaseError::NotLoggedIn);
+        }
+        match self.request("/point/dailyTask", &json!({ "type": platform as u64 })) {
+            Ok(resp) => Ok(Some(resp["point"].as_u64().unwrap_or(0))),
+            Err(NeteaseError::Api {
+                code: ALREADY_SIGNED_IN,
+                ..
+            }) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
@@ -0,0 +1,60 @@
+//! Recommendation APIs for similar songs and artists.
+//!
+//! # Endpoints
+//!
+//! ## `similar_tracks` — `POST /weapi/v1/discovery/simiSong`
+//!
+//! Request: `{ "songid": 347230, "limit": 50, "offset": 0 }`
+//!
+//! Response:
+//! ```json
+//! {
+//!   "code": 200,
+//!   "songs": [{ "id": 1, "name": "歌名", "artists": [...], "album": {...}, "duration": 240000 }]
+//! }
+//! ```
+//!
+//! ## `similar_artists` — `POST /weapi/discovery/simiArtist`
+//!
+//! Request: `{ "artistid": 6452 }`
+//!
+//! Response:
+//! ```json
+//! { "code": 200, "artists": [{ "id": 2, "name": "歌手" }] }
+//! ```
+//!
+//! Similar-artist lookups require login; the server returns code 301 otherwise.
+
+use crate::client::NeteaseClient;
+use crate::error::Result;
+use crate::track::parse_track;
+use crate::types::{Artist, Track};
+use serde_json::json;
+
+impl NeteaseClient {
+    /// Get songs similar to the seed track.
+    pub fn similar_tracks(&self, id: u64, limit: u64) -> Result<Vec<Track>> {
+        let data = json!({ "songid": id, "limit": limit, "offset": 0 });
+        let resp = self.request("/v1/discovery/simiSong", &data)?;
+        Ok(resp["songs"]
+            .as_array()
+            .map(|arr| arr.iter().map(parse_track).collect())
+            .unwrap_or_default())
+    }
+
+    /// Get artists similar to the given artist.
+    pub fn similar_artists(&self, artist_id: u64) -> Result<Vec<Artist>> {
+        let resp = self.request("/discovery/simiArtist", &json!({ "artistid": artist_id }))?;
+        Ok(resp["artists"]
+            .as_array()
+            .map(|arr| {
+                arr.iter()
+                    .map(|a| Artist {
+                        id: a["id"].as_u64().unwrap_or(0),
+                        name: a["name"].as_str().unwrap_or("").to_owned(),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+}
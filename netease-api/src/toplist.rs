@@ -0,0 +1,52 @@
+//! Official charts (排行榜) API.
+//!
+//! Endpoint: `POST /weapi/toplist`
+//!
+//! Request: `{}`
+//!
+//! Response:
+//! ```json
+//! {
+//!   "code": 200,
+//!   "list": [{
+//!     "id": 19723756,
+//!     "name": "飙升榜",
+//!     "description": "...",
+//!     "coverImgUrl": "https://...",
+//!     "trackCount": 100,
+//!     "updateFrequency": "每天更新",
+//!     "updateTime": 1700000000000
+//!   }]
+//! }
+//! ```
+//!
+//! Each chart is a regular playlist; fetch its current entries with
+//! [`NeteaseClient::playlist_detail`].
+
+use crate::client::NeteaseClient;
+use crate::error::Result;
+use crate::types::Toplist;
+use serde_json::json;
+
+impl NeteaseClient {
+    /// List all official charts.
+    pub fn toplists(&self) -> Result<Vec<Toplist>> {
+        let resp = self.request("/toplist", &json!({}))?;
+        Ok(resp["list"]
+            .as_array()
+            .map(|arr| {
+                arr.iter()
+                    .map(|v| Toplist {
+                        id: v["id"].as_u64().unwrap_or(0),
+                        name: v["name"].as_str().unwrap_or("").to_owned(),
+                        description: v["description"].as_str().map(String::from),
+                        cover_url: v["coverImgUrl"].as_str().map(String::from),
+                        track_count: v["trackCount"].as_u64().unwrap_or(0),
+                        update_frequency: v["updateFrequency"].as_str().map(String::from),
+                        update_time: v["updateTime"].as_u64().unwrap_or(0),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+}
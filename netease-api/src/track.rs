@@ -32,6 +32,7 @@
 //!     "url": "https://m701.music.126.net/...",  // null if unavailable
 //!     "br": 320000,
 //!     "size": 12345678,
+//!     "md5": "0123456789abcdef0123456789abcdef",
 //!     "type": "mp3"
 //!   }]
 //! }
@@ -39,6 +40,18 @@
 //!
 //! `url` is `null` when the track requires VIP/purchase or is region-locked.
 //!
+//! ## `track_qualities` — `POST /weapi/song/enhance/player/url/v1`
+//!
+//! Request: `{ "ids": "[123]", "level": "hires", "encodeType": "flac" }`
+//!
+//! Response: as for `track_url`, plus the tier actually served:
+//! ```json
+//! { "code": 200, "data": [{ "id": 123, "url": "...", "level": "lossless", "br": 985000, "size": 31234567, "type": "flac" }] }
+//! ```
+//!
+//! The server silently falls back to the best tier the account may play, so
+//! a tier is only available when `level` echoes the one requested.
+//!
 //! ## `track_lyric` — `POST /weapi/song/lyric`
 //!
 //! Request: `{ "id": 123, "lv": -1, "tv": -1 }`
@@ -56,7 +69,7 @@
 
 use crate::client::NeteaseClient;
 use crate::error::{NeteaseError, Result};
-use crate::types::{Album, Artist, Lyric, Quality, Track};
+use crate::types::{Album, Artist, Lyric, Quality, QualityTier, Track, TrackFile};
 use serde_json::{Value, json};
 use std::path::Path;
 
@@ -91,18 +104,71 @@ impl NeteaseClient {
     /// Returns [`NeteaseError::Other`] if the track is unavailable (VIP-only,
     /// region-locked, or taken down — the API returns `url: null`).
     pub fn track_url(&self, id: u64, quality: Quality) -> Result<String> {
+        Ok(self.track_file(id, quality)?.url)
+    }
+
+    /// Like [`track_url`](Self::track_url), but also returns the file size,
+    /// MD5 and format reported by the server.
+    ///
+    /// Useful to check whether an existing local copy is already complete.
+    pub fn track_file(&self, id: u64, quality: Quality) -> Result<TrackFile> {
         let data = json!({
             "ids": format!("[{}]", id),
             "br": quality.bitrate(),
         });
         let resp = self.request("/song/enhance/player/url", &data)?;
-        let url = resp["data"][0]["url"]
+        let d = &resp["data"][0];
+        let url = d["url"]
             .as_str()
             .ok_or_else(|| {
                 NeteaseError::Other("track unavailable (no copyright or VIP required)".into())
             })?
             .to_owned();
-        Ok(url)
+        Ok(TrackFile {
+            id: d["id"].as_u64().unwrap_or(id),
+            url,
+            bitrate: d["br"].as_u64().unwrap_or(0),
+            size: d["size"].as_u64().unwrap_or(0),
+            md5: d["md5"]
+                .as_str()
+                .filter(|s| !s.is_empty())
+                .map(str::to_lowercase),
+            format: d["type"]
+                .as_str()
+                .filter(|s| !s.is_empty())
+                .map(str::to_lowercase),
+        })
+    }
+
+    /// Check every [`Quality`] tier of a track, lowest first.
+    ///
+    /// Makes one request per tier. Does not require login, but without it
+    /// usually only the lower tiers are available.
+    pub fn track_qualities(&self, id: u64) -> Result<Vec<QualityTier>> {
+        Quality::ALL
+            .into_iter()
+            .map(|quality| {
+                let data = json!({
+                    "ids": format!("[{}]", id),
+                    "level": quality.level(),
+                    "encodeType": "flac",
+                });
+                let resp = self.request("/song/enhance/player/url/v1", &data)?;
+                let d = &resp["data"][0];
+                let served = d["url"].as_str().and(d["level"].as_str()).map(String::from);
+                Ok(QualityTier {
+                    quality,
+                    available: served.as_deref() == Some(quality.level()),
+                    served,
+                    bitrate: d["br"].as_u64().unwrap_or(0),
+                    size: d["size"].as_u64().unwrap_or(0),
+                    format: d["type"]
+                        .as_str()
+                        .filter(|s| !s.is_empty())
+                        .map(str::to_lowercase),
+                })
+            })
+            .collect()
     }
 
     /// Get lyrics for a track.
@@ -129,7 +195,7 @@ impl NeteaseClient {
     }
 }
 
-fn parse_track(v: &Value) -> Track {
+pub(crate) fn parse_track(v: &Value) -> Track {
     let artists = v["ar"]
         .as_array()
         .or_else(|| v["artists"].as_array())
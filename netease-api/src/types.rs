@@ -19,6 +19,49 @@ pub struct Artist {
     pub name: String,
 }
 
+/// Artist profile with top songs.
+///
+/// Returned by [`NeteaseClient::artist_detail`](crate::NeteaseClient::artist_detail).
+///
+/// API JSON path: `response.artist` (profile) and `response.hotSongs` (top songs).
+/// Fields from API: `id`, `name`, `briefDesc`, `picUrl`, `albumSize`, `musicSize`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArtistDetail {
+    /// Netease artist ID.
+    pub id: u64,
+    /// Display name.
+    pub name: String,
+    /// Short biography.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub brief_desc: Option<String>,
+    /// Artist image URL.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pic_url: Option<String>,
+    /// Number of albums.
+    pub album_count: u64,
+    /// Number of songs.
+    pub track_count: u64,
+    /// Top (hot) songs, most popular first.
+    pub top_tracks: Vec<Track>,
+}
+
+/// An album entry in an artist's discography.
+///
+/// Returned by [`NeteaseClient::artist_albums`](crate::NeteaseClient::artist_albums).
+///
+/// API JSON fields: `id`, `name`, `publishTime` (epoch milliseconds), `size`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArtistAlbum {
+    /// Netease album ID.
+    pub id: u64,
+    /// Album title.
+    pub name: String,
+    /// Release date in milliseconds since the Unix epoch.
+    pub publish_time: u64,
+    /// Number of tracks.
+    pub track_count: u64,
+}
+
 /// An album.
 ///
 /// Returned inside [`Track`] (as `al` or `album`) and in album search results.
@@ -35,6 +78,36 @@ pub struct Album {
     pub pic_url: Option<String>,
 }
 
+/// Full album info with track listing.
+///
+/// Returned by [`NeteaseClient::album_detail`](crate::NeteaseClient::album_detail).
+///
+/// API JSON path: `response.album` (metadata) and `response.songs` (tracks).
+/// Fields from API: `id`, `name`, `artists`, `publishTime` (epoch milliseconds),
+/// `company`, `description`, `picUrl`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlbumDetail {
+    /// Netease album ID.
+    pub id: u64,
+    /// Album title.
+    pub name: String,
+    /// Album artists.
+    pub artists: Vec<Artist>,
+    /// Release date in milliseconds since the Unix epoch.
+    pub publish_time: u64,
+    /// Record label.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub company: Option<String>,
+    /// Album description.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Cover image URL.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pic_url: Option<String>,
+    /// Track listing in album order.
+    pub tracks: Vec<Track>,
+}
+
 /// A music track (song).
 ///
 /// Returned by [`NeteaseClient::track_detail`](crate::NeteaseClient::track_detail)
@@ -56,6 +129,42 @@ pub struct Track {
     pub duration_ms: u64,
 }
 
+/// A resolved audio file for a track.
+///
+/// Returned by [`NeteaseClient::track_file`](crate::NeteaseClient::track_file).
+///
+/// API JSON path: `response.data[0]` with fields `id`, `url`, `br`, `size`,
+/// `md5`, `type`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrackFile {
+    /// Netease track ID.
+    pub id: u64,
+    /// Temporary CDN URL.
+    pub url: String,
+    /// Actual bitrate served (may be lower than requested).
+    pub bitrate: u64,
+    /// File size in bytes (0 if unknown).
+    pub size: u64,
+    /// Lowercase hex MD5 of the file, when reported.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub md5: Option<String>,
+    /// Lowercase file type (e.g. `mp3`, `flac`), when reported.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<String>,
+}
+
+impl TrackFile {
+    /// File extension for saving this file: the reported type, or a guess
+    /// from the URL when the server omits it.
+    pub fn extension(&self) -> &str {
+        match self.format.as_deref() {
+            Some(f) => f,
+            None if self.url.contains(".flac") => "flac",
+            None => "mp3",
+        }
+    }
+}
+
 /// A playlist (song list).
 ///
 /// Returned by [`NeteaseClient::playlist_detail`](crate::NeteaseClient::playlist_detail)
@@ -87,6 +196,53 @@ pub struct Playlist {
     pub tracks: Option<Vec<Track>>,
 }
 
+/// A trending search topic.
+///
+/// Returned by [`NeteaseClient::hot_search_detail`](crate::NeteaseClient::hot_search_detail).
+///
+/// API JSON fields: `searchWord`, `score`, `content`, `iconUrl`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HotSearch {
+    /// Search keyword.
+    pub keyword: String,
+    /// Popularity score.
+    pub score: u64,
+    /// Short description of why it is trending.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    /// Badge icon URL (e.g. "HOT" / "NEW").
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub icon_url: Option<String>,
+}
+
+/// An official chart (排行榜).
+///
+/// Returned by [`NeteaseClient::toplists`](crate::NeteaseClient::toplists).
+/// The `id` is a playlist ID; use `playlist_detail` to get the entries.
+///
+/// API JSON fields: `id`, `name`, `description`, `coverImgUrl`, `trackCount`,
+/// `updateFrequency`, `updateTime` (epoch milliseconds).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Toplist {
+    /// Chart playlist ID.
+    pub id: u64,
+    /// Chart name (e.g. `飙升榜`).
+    pub name: String,
+    /// Chart description.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Cover image URL.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cover_url: Option<String>,
+    /// Number of entries.
+    pub track_count: u64,
+    /// Human-readable update schedule (e.g. `每天更新`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub update_frequency: Option<String>,
+    /// Last update time in milliseconds since the Unix epoch.
+    pub update_time: u64,
+}
+
 /// Abbreviated user info embedded in [`Playlist`].
 ///
 /// API JSON fields: `userId` (number), `nickname` (string).
@@ -98,6 +254,44 @@ pub struct UserBrief {
     pub name: String,
 }
 
+/// A user comment on a track.
+///
+/// Returned inside [`CommentPage`].
+///
+/// API JSON fields: `commentId`, `user` (`{ userId, nickname }`), `content`,
+/// `likedCount`, `time` (epoch milliseconds).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Comment {
+    /// Netease comment ID.
+    pub id: u64,
+    /// Comment author.
+    pub user: UserBrief,
+    /// Comment text.
+    pub content: String,
+    /// Number of likes.
+    pub liked_count: u64,
+    /// Post time in milliseconds since the Unix epoch.
+    pub time: u64,
+}
+
+/// One page of track comments.
+///
+/// Returned by [`NeteaseClient::track_comments`](crate::NeteaseClient::track_comments)
+/// and [`NeteaseClient::track_hot_comments`](crate::NeteaseClient::track_hot_comments).
+///
+/// API JSON fields: `total`, `more`/`hasMore`, `hotComments`, `comments`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommentPage {
+    /// Total number of comments on the track.
+    pub total: u64,
+    /// Whether more pages are available.
+    pub more: bool,
+    /// Hot comments (first page of `track_comments`, or all of `track_hot_comments`).
+    pub hot: Vec<Comment>,
+    /// Latest comments.
+    pub comments: Vec<Comment>,
+}
+
 /// Current user profile.
 ///
 /// Returned by [`NeteaseClient::user_info`](crate::NeteaseClient::user_info).
@@ -114,6 +308,133 @@ pub struct UserProfile {
     pub avatar_url: Option<String>,
 }
 
+/// Listening level (等级) of the current user.
+///
+/// Returned by [`NeteaseClient::user_level`](crate::NeteaseClient::user_level).
+///
+/// API JSON path: `response.data` with fields `level`, `progress`,
+/// `nowPlayCount`, `nextPlayCount`, `nowLoginCount`, `nextLoginCount`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserLevel {
+    /// Current level (1–10).
+    pub level: u64,
+    /// Progress towards the next level, from 0.0 to 1.0.
+    pub progress: f64,
+    /// Songs played so far.
+    pub play_count: u64,
+    /// Songs played needed for the next level.
+    pub next_play_count: u64,
+    /// Days logged in so far.
+    pub login_count: u64,
+    /// Days logged in needed for the next level.
+    pub next_login_count: u64,
+}
+
+/// VIP memberships of the current user.
+///
+/// Returned by [`NeteaseClient::vip_info`](crate::NeteaseClient::vip_info).
+///
+/// API JSON path: `response.data` with fields `redVipLevel`,
+/// `associator.expireTime` and `musicPackage.expireTime`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VipInfo {
+    /// 黑胶 VIP level (0 if never a member).
+    pub level: u64,
+    /// When the 黑胶 VIP membership ends (or ended), Unix milliseconds.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vip_expire_ms: Option<u64>,
+    /// When the music package ends (or ended), Unix milliseconds.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub music_package_expire_ms: Option<u64>,
+}
+
+/// A music video (MV).
+///
+/// Returned by [`NeteaseClient::mv_detail`](crate::NeteaseClient::mv_detail).
+///
+/// API JSON path: `response.data` with fields `id`, `name`, `artists`,
+/// `duration` (milliseconds), `brs` (available resolutions).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Mv {
+    /// Netease MV ID.
+    pub id: u64,
+    /// MV title.
+    pub name: String,
+    /// Performing artists.
+    pub artists: Vec<Artist>,
+    /// Duration in milliseconds.
+    pub duration_ms: u64,
+    /// Available vertical resolutions (e.g. `[240, 480, 720, 1080]`), ascending.
+    pub resolutions: Vec<u64>,
+}
+
+/// A DJ radio (podcast).
+///
+/// Returned by [`NeteaseClient::radio_detail`](crate::NeteaseClient::radio_detail).
+///
+/// API JSON path: `response.data` with fields `id`, `name`, `desc`, `picUrl`,
+/// `category`, `programCount`, `subCount`, `dj` (`{ userId, nickname }`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Radio {
+    /// Netease radio ID.
+    pub id: u64,
+    /// Radio title.
+    pub name: String,
+    /// Radio description.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Cover image URL.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cover_url: Option<String>,
+    /// Category name (e.g. `音乐故事`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    /// Number of published programs.
+    pub program_count: u64,
+    /// Number of subscribers.
+    pub subscriber_count: u64,
+    /// Radio host.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dj: Option<UserBrief>,
+}
+
+/// A single DJ radio program (episode).
+///
+/// Returned inside [`ProgramPage`].
+///
+/// API JSON fields: `id`, `name`, `serialNum`, `mainSong.id`, `duration`
+/// (milliseconds), `createTime` (epoch milliseconds), `listenerCount`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Program {
+    /// Netease program ID.
+    pub id: u64,
+    /// Program title.
+    pub name: String,
+    /// Episode number within the radio.
+    pub serial_num: u64,
+    /// Track ID of the program audio (use with `track_url` / `download_track`).
+    pub track_id: u64,
+    /// Duration in milliseconds.
+    pub duration_ms: u64,
+    /// Publish time in milliseconds since the Unix epoch.
+    pub create_time: u64,
+    /// Listener count.
+    pub listener_count: u64,
+}
+
+/// One page of DJ radio programs.
+///
+/// Returned by [`NeteaseClient::radio_programs`](crate::NeteaseClient::radio_programs).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProgramPage {
+    /// Total number of programs.
+    pub count: u64,
+    /// Whether more pages are available.
+    pub more: bool,
+    /// Programs on this page.
+    pub programs: Vec<Program>,
+}
+
 /// Song lyrics.
 ///
 /// Returned by [`NeteaseClient::track_lyric`](crate::NeteaseClient::track_lyric).
@@ -162,6 +483,60 @@ pub struct SearchResult {
     pub artists: Option<Vec<Artist>>,
 }
 
+impl SearchResult {
+    /// Number of results on this page.
+    pub fn len(&self) -> usize {
+        self.tracks.as_ref().map_or(0, Vec::len)
+            + self.albums.as_ref().map_or(0, Vec::len)
+            + self.playlists.as_ref().map_or(0, Vec::len)
+            + self.artists.as_ref().map_or(0, Vec::len)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// One entry of a user's listening history.
+///
+/// Returned by [`NeteaseClient::play_record`](crate::NeteaseClient::play_record).
+///
+/// API JSON path: `response.weekData[]` / `response.allData[]` with fields
+/// `playCount`, `score` and `song`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlayRecord {
+    /// Times the track was played in the period.
+    pub play_count: u64,
+    /// Relative weight (0–100) the ranking is sorted by.
+    pub score: u64,
+    /// The track played.
+    pub track: Track,
+}
+
+/// Period of a listening history, mapped to the API `type` parameter.
+///
+/// | Variant   | API value | Covers            |
+/// |-----------|-----------|-------------------|
+/// | `AllTime` | 0         | Every play so far |
+/// | `Weekly`  | 1         | The last 7 days   |
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordPeriod {
+    AllTime = 0,
+    Weekly = 1,
+}
+
+/// Platform of a daily sign-in, mapped to the API `type` parameter.
+///
+/// | Variant  | API value | Points |
+/// |----------|-----------|--------|
+/// | `Mobile` | 0         | 3      |
+/// | `Web`    | 1         | 2      |
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SigninPlatform {
+    Mobile = 0,
+    Web = 1,
+}
+
 /// Search target type, mapped to the API `type` parameter.
 ///
 /// | Variant    | API value | Searches for |
@@ -190,9 +565,15 @@ pub enum SearchType {
 /// | `Higher`   | 192 kbps  | MP3            |
 /// | `Exhigh`   | 320 kbps  | MP3            |
 /// | `Lossless` | 999 kbps* | FLAC           |
+/// | `Hires`    | 1999 kbps*| FLAC (24-bit)  |
+/// | `Sky`      | 1999 kbps*| FLAC (surround)|
 ///
-/// *999000 is a sentinel value; actual lossless bitrate varies.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// *Sentinel values; actual lossless bitrates vary. The `br` parameter
+/// cannot tell `Sky` from `Hires` apart; only the `level` parameter of
+/// [`NeteaseClient::track_qualities`](crate::NeteaseClient::track_qualities)
+/// can.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Quality {
     /// 128 kbps MP3.
     Standard,
@@ -202,9 +583,23 @@ pub enum Quality {
     Exhigh,
     /// Lossless (FLAC). Requires VIP.
     Lossless,
+    /// Hi-Res (24-bit FLAC). Requires VIP.
+    Hires,
+    /// Immersive surround sound (沉浸环绕声). Requires 黑胶 SVIP.
+    Sky,
 }
 
 impl Quality {
+    /// Every tier, lowest first.
+    pub const ALL: [Self; 6] = [
+        Self::Standard,
+        Self::Higher,
+        Self::Exhigh,
+        Self::Lossless,
+        Self::Hires,
+        Self::Sky,
+    ];
+
     /// Return the bitrate value sent to the API `br` parameter.
     pub fn bitrate(self) -> u64 {
         match self {
@@ -212,6 +607,113 @@ impl Quality {
             Self::Higher => 192_000,
             Self::Exhigh => 320_000,
             Self::Lossless => 999_000,
+            Self::Hires | Self::Sky => 1_999_000,
+        }
+    }
+
+    /// Return the value sent to the API `level` parameter.
+    pub fn level(self) -> &'static str {
+        match self {
+            Self::Standard => "standard",
+            Self::Higher => "higher",
+            Self::Exhigh => "exhigh",
+            Self::Lossless => "lossless",
+            Self::Hires => "hires",
+            Self::Sky => "sky",
+        }
+    }
+}
+
+/// What one quality tier of a track looks like for the current account.
+///
+/// Returned by [`NeteaseClient::track_qualities`](crate::NeteaseClient::track_qualities).
+///
+/// API JSON path: `response.data[0]` with fields `url`, `level`, `br`,
+/// `size`, `type`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QualityTier {
+    /// The tier requested.
+    pub quality: Quality,
+    /// Whether the server serves this exact tier (not a lower one).
+    pub available: bool,
+    /// The tier the server actually serves, if it serves any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub served: Option<String>,
+    /// Bitrate served, in bits per second (0 if none).
+    pub bitrate: u64,
+    /// File size in bytes (0 if none).
+    pub size: u64,
+    /// Lowercase codec / file type (e.g. `mp3`, `flac`), when reported.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<String>,
+}
+
+/// A track stored in the user's cloud drive (云盘).
+///
+/// Returned inside [`CloudList`].
+///
+/// API JSON fields: `songId`, `songName`, `artist`, `album`, `fileName`,
+/// `fileSize` (bytes), `bitrate` (kbps), `addTime` (epoch milliseconds).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CloudTrack {
+    /// Cloud song ID (used by `cloud_delete`).
+    pub id: u64,
+    /// Song title.
+    pub name: String,
+    /// Artist name as stored in the cloud entry.
+    pub artist: String,
+    /// Album name as stored in the cloud entry.
+    pub album: String,
+    /// Original uploaded file name.
+    pub file_name: String,
+    /// File size in bytes.
+    pub file_size: u64,
+    /// Bitrate in kbps.
+    pub bitrate: u64,
+    /// Upload time in milliseconds since the Unix epoch.
+    pub add_time: u64,
+}
+
+/// One page of the cloud drive listing.
+///
+/// Returned by [`NeteaseClient::cloud_list`](crate::NeteaseClient::cloud_list).
+///
+/// API JSON fields: `count`, `size`/`maxSize` (bytes, as strings), `data` (array).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CloudList {
+    /// Total number of tracks in the cloud drive.
+    pub count: u64,
+    /// Used storage in bytes.
+    pub size: u64,
+    /// Storage quota in bytes.
+    pub max_size: u64,
+    /// Tracks on this page.
+    pub tracks: Vec<CloudTrack>,
+}
+
+/// Video resolution for MV URLs, mapped to the API `r` parameter.
+///
+/// The server returns the closest available resolution not above the requested one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MvResolution {
+    /// 240p.
+    R240,
+    /// 480p.
+    R480,
+    /// 720p.
+    R720,
+    /// 1080p.
+    R1080,
+}
+
+impl MvResolution {
+    /// Return the vertical resolution sent to the API `r` parameter.
+    pub fn height(self) -> u64 {
+        match self {
+            Self::R240 => 240,
+            Self::R480 => 480,
+            Self::R720 => 720,
+            Self::R1080 => 1080,
         }
     }
 }
@@ -1,6 +1,10 @@
-//! User profile API.
+//! User account APIs.
 //!
-//! Endpoint: `POST /weapi/nuser/account/get`
+//! All endpoints require a login cookie.
+//!
+//! # Endpoints
+//!
+//! ## `user_info` — `POST /weapi/nuser/account/get`
 //!
 //! Request: `{}` (empty object, authentication is via cookie).
 //!
@@ -17,11 +21,87 @@
 //! ```
 //!
 //! Returns code 301 if the cookie is invalid or expired.
+//!
+//! ## `user_playlists` — `POST /weapi/user/playlist`
+//!
+//! Request: `{ "uid": 413184081, "limit": 30, "offset": 0, "includeVideo": true }`
+//!
+//! Response:
+//! ```json
+//! {
+//!   "code": 200,
+//!   "more": true,
+//!   "playlist": [{ "id": 1, "name": "我喜欢的音乐", "trackCount": 320, "creator": {...} }]
+//! }
+//! ```
+//!
+//! Created playlists come first (the first is always 我喜欢的音乐), followed
+//! by subscribed ones.
+//!
+//! ## `liked_track_ids` — `POST /weapi/song/like/get`
+//!
+//! Request: `{ "uid": 413184081 }`
+//!
+//! Response: `{ "code": 200, "ids": [347230, 186016] }`
+//!
+//! ## `user_level` — `POST /weapi/user/level`
+//!
+//! Request: `{}`
+//!
+//! Response:
+//! ```json
+//! {
+//!   "code": 200,
+//!   "data": {
+//!     "level": 8, "progress": 0.42,
+//!     "nowPlayCount": 8123, "nextPlayCount": 20000,
+//!     "nowLoginCount": 610, "nextLoginCount": 800
+//!   }
+//! }
+//! ```
+//!
+//! ## `vip_info` — `POST /weapi/music-vip-membership/front/vip/info`
+//!
+//! Request: `{}`
+//!
+//! Response:
+//! ```json
+//! {
+//!   "code": 200,
+//!   "data": {
+//!     "redVipLevel": 3,
+//!     "associator": { "vipCode": 100, "expireTime": 1767196800000 },
+//!     "musicPackage": { "vipCode": 220, "expireTime": 0 }
+//!   }
+//! }
+//! ```
+//!
+//! `associator` is the 黑胶 VIP membership, `musicPackage` the cheaper music
+//! package; an `expireTime` of 0 means never subscribed.
+//!
+//! ## `play_record` — `POST /weapi/v1/play/record`
+//!
+//! Request: `{ "uid": 413184081, "type": 1 }` — `1` for the last week, `0`
+//! for all time.
+//!
+//! Response:
+//! ```json
+//! {
+//!   "code": 200,
+//!   "weekData": [{ "playCount": 12, "score": 100, "song": { "id": 1, "name": "歌名", "ar": [...], "al": {...}, "dt": 240000 } }]
+//! }
+//! ```
+//!
+//! The all-time ranking is in `allData` instead. Both hold at most 100
+//! entries, highest score first. Users who hide their listening history
+//! return code `-2` unless `uid` is the logged-in user.
 
 use crate::client::NeteaseClient;
 use crate::error::{NeteaseError, Result};
-use crate::types::UserProfile;
-use serde_json::json;
+use crate::search::parse_playlists;
+use crate::track::parse_track;
+use crate::types::{PlayRecord, Playlist, RecordPeriod, UserLevel, UserProfile, VipInfo};
+use serde_json::{Value, json};
 
 impl NeteaseClient {
     /// Get the current logged-in user's profile.
@@ -43,4 +123,105 @@ impl NeteaseClient {
             avatar_url: p["avatarUrl"].as_str().map(String::from),
         })
     }
+
+    /// List the playlists user `uid` created or subscribed to, without
+    /// their tracks.
+    ///
+    /// # Errors
+    ///
+    /// - [`NeteaseError::NotLoggedIn`] — no `MUSIC_U` cookie configured
+    pub fn user_playlists(&self, uid: u64, limit: u64, offset: u64) -> Result<Vec<Playlist>> {
+        if !self.session().is_logged_in() {
+            return Err(NeteaseError::NotLoggedIn);
+        }
+        let data = json!({ "uid": uid, "limit": limit, "offset": offset, "includeVideo": true });
+        let resp = self.request("/user/playlist", &data)?;
+        Ok(parse_playlists(resp["playlist"].as_array()))
+    }
+
+    /// IDs of the songs user `uid` has liked (the 我喜欢的音乐 playlist).
+    ///
+    /// # Errors
+    ///
+    /// - [`NeteaseError::NotLoggedIn`] — no `MUSIC_U` cookie configured
+    pub fn liked_track_ids(&self, uid: u64) -> Result<Vec<u64>> {
+        if !self.session().is_logged_in() {
+            return Err(NeteaseError::NotLoggedIn);
+        }
+        let resp = self.request("/song/like/get", &json!({ "uid": uid }))?;
+        Ok(resp["ids"]
+            .as_array()
+            .map(|ids| ids.iter().filter_map(Value::as_u64).collect())
+            .unwrap_or_default())
+    }
+
+    /// The current user's listening level and progress towards the next.
+    ///
+    /// # Errors
+    ///
+    /// - [`NeteaseError::NotLoggedIn`] — no `MUSIC_U` cookie configured
+    pub fn user_level(&self) -> Result<UserLevel> {
+        if !self.session().is_logged_in() {
+            return Err(NeteaseError::NotLoggedIn);
+        }
+        let resp = self.request("/user/level", &json!({}))?;
+        let d = &resp["data"];
+        Ok(UserLevel {
+            level: d["level"].as_u64().unwrap_or(0),
+            progress: d["progress"].as_f64().unwrap_or(0.0),
+            play_count: d["nowPlayCount"].as_u64().unwrap_or(0),
+            next_play_count: d["nextPlayCount"].as_u64().unwrap_or(0),
+            login_count: d["nowLoginCount"].as_u64().unwrap_or(0),
+            next_login_count: d["nextLoginCount"].as_u64().unwrap_or(0),
+        })
+    }
+
+    /// The current user's VIP memberships.
+    ///
+    /// # Errors
+    ///
+    /// - [`NeteaseError::NotLoggedIn`] — no `MUSIC_U` cookie configured
+    pub fn vip_info(&self) -> Result<VipInfo> {
+        if !self.session().is_logged_in() {
+            return Err(NeteaseError::NotLoggedIn);
+        }
+        let resp = self.request("/music-vip-membership/front/vip/info", &json!({}))?;
+        let d = &resp["data"];
+        let expiry = |v: &Value| v["expireTime"].as_u64().filter(|&t| t > 0);
+        Ok(VipInfo {
+            level: d["redVipLevel"].as_u64().unwrap_or(0),
+            vip_expire_ms: expiry(&d["associator"]),
+            music_package_expire_ms: expiry(&d["musicPackage"]),
+        })
+    }
+
+    /// User `uid`'s most played tracks over `period`, highest score first.
+    ///
+    /// # Errors
+    ///
+    /// - [`NeteaseError::NotLoggedIn`] — no `MUSIC_U` cookie configured
+    /// - [`NeteaseError::Api`] with code -2 — `uid` hides their history
+    pub fn play_record(&self, uid: u64, period: RecordPeriod) -> Result<Vec<PlayRecord>> {
+        if !self.session().is_logged_in() {
+            return Err(NeteaseError::NotLoggedIn);
+        }
+        let data = json!({ "uid": uid, "type": period as u64 });
+        let resp = self.request("/v1/play/record", &data)?;
+        let key = match period {
+            RecordPeriod::Weekly => "weekData",
+            RecordPeriod::AllTime => "allData",
+        };
+        Ok(resp[key]
+            .as_array()
+            .map(|arr| {
+                arr.iter()
+                    .map(|r| PlayRecord {
+                        play_count: r["playCount"].as_u64().unwrap_or(0),
+                        score: r["score"].as_u64().unwrap_or(0),
+                        track: parse_track(&r["song"]),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
 }